@@ -3,11 +3,14 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 
 use eframe::egui;
+use letrecovery_common::journal::Journal;
+use letrecovery_common::workflow::{target_formatted, WorkflowEvent};
 
 use crate::core::config::{ConfigFileManager, OperationType};
-use crate::core::dism::DismProgress;
 use crate::ui::progress::{InstallStep, BackupStep, ProgressState, ProgressUI};
+use crate::ui::resume::{ResumeChoice, ResumePrompt, STALE_BOOT_ENTRY_WARNING};
 use crate::utils::reboot_pe;
+use crate::workflow;
 
 /// 工作线程消息
 #[derive(Debug, Clone)]
@@ -35,6 +38,8 @@ pub struct App {
     started: bool,
     /// 操作类型
     operation_type: Option<OperationType>,
+    /// 上次流程未完成时的续装提示
+    resume_prompt: Option<ResumePrompt>,
 }
 
 impl App {
@@ -51,11 +56,14 @@ impl App {
             None => ProgressState::new_install(),
         }));
 
+        let resume_prompt = operation_type.and_then(load_resume_prompt);
+
         Self {
             progress_state,
             message_rx: None,
             started: false,
             operation_type,
+            resume_prompt,
         }
     }
 
@@ -93,7 +101,7 @@ impl App {
     }
 
     /// 启动工作线程
-    fn start_worker(&mut self) {
+    fn start_worker(&mut self, choice: ResumeChoice) {
         if self.started {
             return;
         }
@@ -105,14 +113,17 @@ impl App {
         let operation_type = self.operation_type;
 
         thread::spawn(move || {
-            match operation_type {
-                Some(OperationType::Install) => {
+            match (operation_type, choice) {
+                (Some(operation), ResumeChoice::Rollback) => {
+                    execute_rollback_workflow(tx, operation);
+                }
+                (Some(OperationType::Install), ResumeChoice::Resume) => {
                     execute_install_workflow(tx);
                 }
-                Some(OperationType::Backup) => {
+                (Some(OperationType::Backup), ResumeChoice::Resume) => {
                     execute_backup_workflow(tx);
                 }
-                None => {
+                (None, _) => {
                     let _ = tx.send(WorkerMessage::Failed("未检测到安装或备份配置".to_string()));
                 }
             }
@@ -152,9 +163,31 @@ impl App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // 上次流程未完成时先让用户选择继续或回滚
+        if let Some(ref prompt) = self.resume_prompt {
+            let mut choice = None;
+            egui::CentralPanel::default().show(ctx, |ui| {
+                choice = prompt.show(ui);
+            });
+            if let Some(choice) = choice {
+                log::info!("用户选择: {:?}", choice);
+                // 格式化之后回滚：恢复的引导项仍指向已被清空的目标分区，回滚过程中继续提示
+                if choice == ResumeChoice::Rollback && prompt.target_modified {
+                    log::warn!("目标分区已被格式化，原系统的引导项已失效");
+                    if let Ok(mut state) = self.progress_state.lock() {
+                        state.warning_message = Some(STALE_BOOT_ENTRY_WARNING.to_string());
+                    }
+                }
+                self.resume_prompt = None;
+                self.start_worker(choice);
+            }
+            ctx.request_repaint();
+            return;
+        }
+
         // 启动工作线程
         if !self.started {
-            self.start_worker();
+            self.start_worker(ResumeChoice::Resume);
         }
 
         // 处理消息
@@ -172,450 +205,135 @@ impl eframe::App for App {
     }
 }
 
-/// 执行安装工作流
-fn execute_install_workflow(tx: Sender<WorkerMessage>) {
-    use crate::core::bcdedit::BootManager;
-    use crate::core::dism::Dism;
-    use crate::core::disk::DiskManager;
-    use crate::core::ghost::Ghost;
-    use crate::ui::advanced_options::apply_advanced_options;
-
-    log::info!("========== 开始PE安装流程 ==========");
-
-    // 查找配置文件所在分区
-    let data_partition = match ConfigFileManager::find_data_partition() {
-        Some(p) => p,
-        None => {
-            let _ = tx.send(WorkerMessage::Failed("未找到安装配置文件".to_string()));
-            return;
+/// 读取步骤日志，上次流程未完成时生成续装提示
+fn load_resume_prompt(operation: OperationType) -> Option<ResumePrompt> {
+    let data_partition = ConfigFileManager::find_data_partition()?;
+    let journal_path = ConfigFileManager::journal_path(&data_partition);
+
+    let prompt = match operation {
+        OperationType::Install => {
+            let journal = Journal::<InstallStep>::load(&journal_path).ok()??;
+            let step = journal.interrupted_step().or_else(|| journal.next_step())?;
+            journal.has_progress().then(|| {
+                let mut prompt = ResumePrompt::new(true, step.name(), journal.last_error().map(String::from));
+                prompt.target_modified = target_formatted(&journal);
+                prompt
+            })
         }
-    };
-
-    log::info!("数据分区: {}", data_partition);
-    let _ = tx.send(WorkerMessage::SetStatus(format!("数据分区: {}", data_partition)));
-
-    // 读取安装配置
-    let config = match ConfigFileManager::read_install_config(&data_partition) {
-        Ok(c) => c,
-        Err(e) => {
-            let _ = tx.send(WorkerMessage::Failed(format!("读取配置失败: {}", e)));
-            return;
+        OperationType::Backup => {
+            let journal = Journal::<BackupStep>::load(&journal_path).ok()??;
+            let step = journal.interrupted_step().or_else(|| journal.next_step())?;
+            journal
+                .has_progress()
+                .then(|| ResumePrompt::new(false, step.name(), journal.last_error().map(String::from)))
         }
     };
 
-    log::info!("目标分区: {}", config.target_partition);
-    log::info!("镜像文件: {}", config.image_path);
-
-    // 查找安装标记分区
-    let target_partition = ConfigFileManager::find_install_marker_partition()
-        .unwrap_or_else(|| config.target_partition.clone());
-
-    // 构建完整镜像路径
-    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
-    let image_path = format!("{}\\{}", data_dir, config.image_path);
-
-    if !std::path::Path::new(&image_path).exists() {
-        let _ = tx.send(WorkerMessage::Failed(format!("镜像文件不存在: {}", image_path)));
-        return;
-    }
-
-    log::info!("完整镜像路径: {}", image_path);
-
-    // Step 1: 格式化分区
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::FormatPartition));
-    let _ = tx.send(WorkerMessage::SetStatus("正在格式化目标分区...".to_string()));
-
-    match DiskManager::format_partition(&target_partition) {
-        Ok(_) => {
-            log::info!("分区格式化成功");
-            let _ = tx.send(WorkerMessage::SetProgress(100));
-        }
-        Err(e) => {
-            let _ = tx.send(WorkerMessage::Failed(format!("格式化分区失败: {}", e)));
-            return;
-        }
+    if let Some(ref prompt) = prompt {
+        log::info!("检测到未完成的流程，中断于步骤: {}", prompt.step_name);
     }
+    prompt
+}
 
-    // Step 2: 释放镜像
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ApplyImage));
-    let _ = tx.send(WorkerMessage::SetStatus("正在释放系统镜像...".to_string()));
-
-    let apply_dir = format!("{}\\", target_partition);
-
-    // 创建进度通道
-    let (progress_tx, progress_rx) = channel::<DismProgress>();
-    let tx_clone = tx.clone();
-
-    // 启动进度监控线程
-    let progress_handle = thread::spawn(move || {
-        while let Ok(progress) = progress_rx.recv() {
-            let _ = tx_clone.send(WorkerMessage::SetProgress(progress.percentage));
-        }
-    });
-
-    let apply_result = if config.is_gho {
-        // GHO镜像使用Ghost
-        let ghost = Ghost::new();
-        if !ghost.is_available() {
-            let _ = tx.send(WorkerMessage::Failed("Ghost工具不可用".to_string()));
-            return;
-        }
-
-        let partitions = DiskManager::get_partitions().unwrap_or_default();
-        ghost.restore_image_to_letter(&image_path, &target_partition, &partitions, Some(progress_tx))
-    } else {
-        // WIM/ESD使用DISM
-        let dism = Dism::new();
-        dism.apply_image(&image_path, &apply_dir, config.volume_index, Some(progress_tx))
+/// 把工作流事件转换为界面消息
+fn forward_event<S>(
+    tx: &Sender<WorkerMessage>,
+    event: WorkflowEvent<S>,
+    set_step: fn(S) -> WorkerMessage,
+) {
+    let message = match event {
+        WorkflowEvent::Step(step) => set_step(step),
+        WorkflowEvent::Progress(p) => WorkerMessage::SetProgress(p),
+        WorkflowEvent::Status(status) => WorkerMessage::SetStatus(status),
+        WorkflowEvent::Warning(warning) => WorkerMessage::SetStatus(warning),
+        WorkflowEvent::Completed => WorkerMessage::Completed,
+        WorkflowEvent::Failed(error) => WorkerMessage::Failed(error),
     };
+    let _ = tx.send(message);
+}
 
-    drop(progress_handle);
-
-    if let Err(e) = apply_result {
-        let _ = tx.send(WorkerMessage::Failed(format!("释放镜像失败: {}", e)));
-        return;
-    }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // Step 3: 导入驱动
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ImportDrivers));
-
-    if config.restore_drivers {
-        let _ = tx.send(WorkerMessage::SetStatus("正在导入驱动...".to_string()));
-        let driver_path = format!("{}\\drivers", data_dir);
-        if std::path::Path::new(&driver_path).exists() {
-            let dism = Dism::new();
-            if let Err(e) = dism.add_drivers_offline(&apply_dir, &driver_path) {
-                log::warn!("导入驱动失败: {}", e);
-            }
-        }
-    } else {
-        let _ = tx.send(WorkerMessage::SetStatus("跳过驱动导入".to_string()));
-    }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // Step 4: 修复引导
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::RepairBoot));
-    let _ = tx.send(WorkerMessage::SetStatus("正在修复引导...".to_string()));
-
-    let boot_manager = BootManager::new();
-    let use_uefi = DiskManager::detect_uefi_mode();
+/// 执行安装工作流
+fn execute_install_workflow(tx: Sender<WorkerMessage>) {
+    log::info!("========== 开始PE安装流程 ==========");
 
-    if let Err(e) = boot_manager.repair_boot_advanced(&target_partition, use_uefi) {
-        let _ = tx.send(WorkerMessage::Failed(format!("修复引导失败: {}", e)));
+    let sink = |event: WorkflowEvent<InstallStep>| forward_event(&tx, event, WorkerMessage::SetInstallStep);
+    if workflow::run_install(&sink).is_err() {
         return;
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // Step 5: 应用高级选项
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ApplyAdvancedOptions));
-    let _ = tx.send(WorkerMessage::SetStatus("正在应用高级选项...".to_string()));
-
-    if let Err(e) = apply_advanced_options(&target_partition, &config) {
-        log::warn!("应用高级选项失败: {}", e);
-    }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
 
-    // Step 6: 生成无人值守配置
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::GenerateUnattend));
-
-    if config.unattended {
-        let _ = tx.send(WorkerMessage::SetStatus("正在生成无人值守配置...".to_string()));
-        if let Err(e) = generate_unattend_xml(&target_partition, &config) {
-            log::warn!("生成无人值守配置失败: {}", e);
-        }
-    } else {
-        let _ = tx.send(WorkerMessage::SetStatus("跳过无人值守配置".to_string()));
-    }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
+    log::info!("========== PE安装流程完成 ==========");
 
-    // Step 7: 清理临时文件
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Cleanup));
-    let _ = tx.send(WorkerMessage::SetStatus("正在清理临时文件...".to_string()));
+    // PE环境下安装完成后强制重启
+    log::info!("即将重启...");
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    reboot_pe();
+}
 
-    ConfigFileManager::cleanup_all(&data_partition, &target_partition);
-    let _ = tx.send(WorkerMessage::SetProgress(50));
+/// 执行备份工作流
+fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
+    log::info!("========== 开始PE备份流程 ==========");
 
-    // 清理自动创建的数据分区并扩展目标分区
-    let _ = tx.send(WorkerMessage::SetStatus("正在清理自动创建的分区...".to_string()));
-    match DiskManager::cleanup_auto_created_partition_and_extend(&target_partition) {
-        Ok(_) => {
-            log::info!("自动创建分区清理完成");
-        }
-        Err(e) => {
-            // 不中断安装流程，只记录警告
-            log::warn!("清理自动创建分区失败: {}", e);
-        }
+    let sink = |event: WorkflowEvent<BackupStep>| forward_event(&tx, event, WorkerMessage::SetBackupStep);
+    if workflow::run_backup(&sink).is_err() {
+        return;
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // 完成
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Complete));
-    let _ = tx.send(WorkerMessage::Completed);
 
-    log::info!("========== PE安装流程完成 ==========");
+    log::info!("========== PE备份流程完成 ==========");
 
-    // PE环境下安装完成后强制重启
+    // 自动重启
     log::info!("即将重启...");
     std::thread::sleep(std::time::Duration::from_secs(3));
     reboot_pe();
 }
 
-/// 执行备份工作流
-fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
+/// 回滚引导项并放弃未完成的流程
+///
+/// 恢复修复引导前导出的 BCD，删除本次的PE引导项，再清理标记和临时文件，
+/// 重启后不会再次进入PE。
+fn execute_rollback_workflow(tx: Sender<WorkerMessage>, operation: OperationType) {
     use crate::core::bcdedit::BootManager;
-    use crate::core::dism::Dism;
 
-    log::info!("========== 开始PE备份流程 ==========");
+    log::info!("========== 开始回滚引导项 ==========");
 
-    // 查找配置文件所在分区
     let data_partition = match ConfigFileManager::find_data_partition() {
         Some(p) => p,
         None => {
-            let _ = tx.send(WorkerMessage::Failed("未找到备份配置文件".to_string()));
+            let _ = tx.send(WorkerMessage::Failed("未找到配置文件所在分区".to_string()));
             return;
         }
     };
 
-    log::info!("数据分区: {}", data_partition);
-
-    // Step 1: 读取配置
-    let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::ReadConfig));
-    let _ = tx.send(WorkerMessage::SetStatus("正在读取备份配置...".to_string()));
+    let _ = tx.send(WorkerMessage::SetStatus("正在回滚引导项...".to_string()));
 
-    let config = match ConfigFileManager::read_backup_config(&data_partition) {
-        Ok(c) => c,
-        Err(e) => {
-            let _ = tx.send(WorkerMessage::Failed(format!("读取配置失败: {}", e)));
+    let boot_manager = BootManager::new();
+    let backup_path = ConfigFileManager::bcd_backup_path(&data_partition);
+    let backup_path = std::path::Path::new(&backup_path);
+    if backup_path.exists() {
+        if let Err(e) = boot_manager.import_store(backup_path) {
+            let _ = tx.send(WorkerMessage::Failed(format!("恢复引导配置失败: {}", e)));
             return;
         }
-    };
-
-    log::info!("源分区: {}", config.source_partition);
-    log::info!("保存路径: {}", config.save_path);
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // 查找备份标记分区
-    let source_partition = ConfigFileManager::find_backup_marker_partition()
-        .unwrap_or_else(|| config.source_partition.clone());
-
-    // Step 2: 执行DISM备份
-    let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::CaptureImage));
-    let _ = tx.send(WorkerMessage::SetStatus("正在执行系统备份...".to_string()));
-
-    let dism = Dism::new();
-    let capture_dir = format!("{}\\", source_partition);
-
-    // 创建进度通道
-    let (progress_tx, progress_rx) = channel::<DismProgress>();
-    let tx_clone = tx.clone();
-
-    let progress_handle = thread::spawn(move || {
-        while let Ok(progress) = progress_rx.recv() {
-            let _ = tx_clone.send(WorkerMessage::SetProgress(progress.percentage));
-        }
-    });
-
-    let backup_result = if config.incremental && std::path::Path::new(&config.save_path).exists() {
-        dism.append_image(
-            &config.save_path,
-            &capture_dir,
-            &config.name,
-            &config.description,
-            Some(progress_tx),
-        )
+        log::info!("已恢复引导配置: {}", backup_path.display());
     } else {
-        dism.capture_image(
-            &config.save_path,
-            &capture_dir,
-            &config.name,
-            &config.description,
-            Some(progress_tx),
-        )
-    };
-
-    drop(progress_handle);
-
-    if let Err(e) = backup_result {
-        let _ = tx.send(WorkerMessage::Failed(format!("备份失败: {}", e)));
-        return;
+        log::info!("引导尚未修改，无需恢复引导配置");
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
 
-    // Step 3: 验证备份文件
-    let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::VerifyBackup));
-    let _ = tx.send(WorkerMessage::SetStatus("正在验证备份文件...".to_string()));
-
-    if !std::path::Path::new(&config.save_path).exists() {
-        let _ = tx.send(WorkerMessage::Failed("备份文件验证失败".to_string()));
-        return;
-    }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // Step 4: 恢复引导
-    let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::RepairBoot));
-    let _ = tx.send(WorkerMessage::SetStatus("正在恢复引导...".to_string()));
-
-    let boot_manager = BootManager::new();
-    // 删除当前PE引导项
+    // 恢复的存储中仍包含本次的PE引导项
     let _ = boot_manager.delete_current_boot_entry();
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // Step 5: 清理
-    let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::Cleanup));
-    let _ = tx.send(WorkerMessage::SetStatus("正在清理临时文件...".to_string()));
 
-    ConfigFileManager::cleanup_partition_markers(&source_partition);
+    let marker_partition = match operation {
+        OperationType::Install => ConfigFileManager::find_install_marker_partition(),
+        OperationType::Backup => ConfigFileManager::find_backup_marker_partition(),
+    };
+    if let Some(partition) = marker_partition {
+        ConfigFileManager::cleanup_partition_markers(&partition);
+    }
     ConfigFileManager::cleanup_data_dir(&data_partition);
     ConfigFileManager::cleanup_pe_dir(&data_partition);
-    let _ = tx.send(WorkerMessage::SetProgress(100));
 
-    // 完成
-    let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::Complete));
-    let _ = tx.send(WorkerMessage::Completed);
-
-    log::info!("========== PE备份流程完成 ==========");
+    let _ = tx.send(WorkerMessage::SetStatus("引导项已回滚，即将重启...".to_string()));
+    log::info!("========== 回滚完成 ==========");
 
-    // 自动重启
-    log::info!("即将重启...");
     std::thread::sleep(std::time::Duration::from_secs(3));
     reboot_pe();
 }
-
-/// 生成无人值守XML
-/// 
-/// 包含完整的无人值守配置：
-/// - windowsPE pass: 基本设置
-/// - specialize pass: 部署脚本执行
-/// - oobeSystem pass: OOBE设置、用户账户、首次登录命令
-fn generate_unattend_xml(target_partition: &str, config: &crate::core::config::InstallConfig) -> anyhow::Result<()> {
-    use crate::ui::advanced_options::get_scripts_dir_name;
-    
-    let username = if config.custom_username.is_empty() { 
-        "User".to_string() 
-    } else { 
-        config.custom_username.clone() 
-    };
-
-    let scripts_dir = get_scripts_dir_name();
-
-    // 构建 FirstLogonCommands
-    let mut first_logon_commands = String::new();
-    let mut order = 1;
-
-    // 首次登录脚本（如果存在）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c if exist %SystemDrive%\{}\firstlogon.bat call %SystemDrive%\{}\firstlogon.bat</CommandLine>
-                    <Description>Run first login script</Description>
-                </SynchronousCommand>"#, order, scripts_dir, scripts_dir));
-    order += 1;
-
-    // 如果需要删除UWP应用
-    if config.remove_uwp_apps {
-        first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>powershell -ExecutionPolicy Bypass -File %SystemDrive%\{}\remove_uwp.ps1</CommandLine>
-                    <Description>Remove preinstalled UWP apps</Description>
-                </SynchronousCommand>"#, order, scripts_dir));
-        order += 1;
-    }
-
-    // 清理脚本目录（最后执行）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c rd /s /q %SystemDrive%\{}</CommandLine>
-                    <Description>Cleanup scripts directory</Description>
-                </SynchronousCommand>"#, order, scripts_dir));
-
-    let xml_content = format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\{}\deploy.bat call %SystemDrive%\{}\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOEMRegistrationScreen>true</HideOEMRegistrationScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-                <SkipMachineOOBE>true</SkipMachineOOBE>
-                <SkipUserOOBE>true</SkipUserOOBE>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, 
-        scripts_dir, scripts_dir,
-        username, username, username,
-        first_logon_commands
-    );
-
-    let panther_dir = format!("{}\\Windows\\Panther", target_partition);
-    std::fs::create_dir_all(&panther_dir)?;
-
-    let unattend_path = format!("{}\\unattend.xml", panther_dir);
-    std::fs::write(&unattend_path, &xml_content)?;
-    log::info!("[UNATTEND] 已写入: {}", unattend_path);
-
-    // 同时写入到 Sysprep 目录
-    let sysprep_dir = format!("{}\\Windows\\System32\\Sysprep", target_partition);
-    if std::path::Path::new(&sysprep_dir).exists() {
-        let sysprep_unattend = format!("{}\\unattend.xml", sysprep_dir);
-        let _ = std::fs::write(&sysprep_unattend, &xml_content);
-        log::info!("[UNATTEND] 已写入: {}", sysprep_unattend);
-    }
-
-    Ok(())
-}
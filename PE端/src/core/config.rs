@@ -1,4 +1,10 @@
 use anyhow::{Context, Result};
+use letrecovery_common::backup::compression::{BackupFormat, Compression};
+use letrecovery_common::backup::exclusions::ExclusionConfig;
+use letrecovery_common::backup::retention::RetentionPolicy;
+use letrecovery_common::journal::read_operation;
+use letrecovery_common::unattend::accounts::DEFAULT_GROUP;
+use letrecovery_common::unattend::{AnswerFileOptions, BuiltinAdministrator, EncodedPassword, LocalAccount};
 use std::path::Path;
 
 /// 系统安装配置（用于PE环境内安装）
@@ -38,10 +44,81 @@ pub struct InstallConfig {
     pub disable_uac: bool,
     /// 禁用自动设备加密
     pub disable_device_encryption: bool,
+    /// 写入 LabConfig 绕过 Windows 11 硬件要求检查
+    pub bypass_win11_check: bool,
     /// 删除预装UWP应用
     pub remove_uwp_apps: bool,
     /// 自定义用户名
     pub custom_username: String,
+
+    // 无人值守区域与系统信息（为空时不写入应答文件）
+    /// 界面语言
+    pub ui_language: String,
+    /// 键盘/输入法
+    pub input_locale: String,
+    /// 系统区域
+    pub system_locale: String,
+    /// 时区
+    pub time_zone: String,
+    /// 计算机名（已展开模板）
+    pub computer_name: String,
+    /// 产品密钥
+    pub product_key: String,
+    /// 组织名称
+    pub organization: String,
+
+    // 无人值守账户（密码均为编码后的值，配置文件中不出现明文）
+    /// 本地账户，第一个账户用于自动登录
+    pub accounts: Vec<LocalAccount>,
+    /// 不自动登录
+    pub disable_auto_logon: bool,
+    /// 内置 Administrator 账户
+    pub builtin_administrator: BuiltinAdministrator,
+    /// 内置 Administrator 密码
+    pub administrator_password: EncodedPassword,
+    /// 用户提供的应答文件（相对于数据目录，为空表示不导入）
+    pub unattend_file: String,
+    /// 数据目录中暂存了装机软件，部署后复制到目标系统并在首次登录时安装
+    pub install_software: bool,
+    /// 使用的部署方案名称（为空表示未使用方案）
+    pub profile: String,
+}
+
+impl InstallConfig {
+    /// 用户应答文件的完整路径
+    pub fn unattend_file_path(&self, data_dir: &str) -> Option<String> {
+        if self.unattend_file.is_empty() {
+            None
+        } else {
+            Some(format!("{}\\{}", data_dir, self.unattend_file))
+        }
+    }
+
+    /// 转换为应答文件选项（架构由调用方检测后填写）
+    pub fn answer_file_options(&self) -> AnswerFileOptions {
+        let accounts = if self.accounts.is_empty() && !self.custom_username.is_empty() {
+            vec![LocalAccount::new(&self.custom_username, DEFAULT_GROUP, "")]
+        } else {
+            self.accounts.clone()
+        };
+
+        AnswerFileOptions {
+            accounts,
+            disable_auto_logon: self.disable_auto_logon,
+            builtin_administrator: self.builtin_administrator,
+            administrator_password: self.administrator_password.clone(),
+            remove_uwp_apps: self.remove_uwp_apps,
+            install_software: self.install_software,
+            ui_language: self.ui_language.clone(),
+            input_locale: self.input_locale.clone(),
+            system_locale: self.system_locale.clone(),
+            time_zone: self.time_zone.clone(),
+            computer_name: self.computer_name.clone(),
+            product_key: self.product_key.clone(),
+            organization: self.organization.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 /// 系统备份配置（用于PE环境内备份）
@@ -55,8 +132,20 @@ pub struct BackupConfig {
     pub description: String,
     /// 源分区盘符
     pub source_partition: String,
+    /// 备份格式 (WIM 或 GHO)
+    pub format: BackupFormat,
     /// 是否增量备份
     pub incremental: bool,
+    /// 备份后是否校验镜像
+    pub verify: bool,
+    /// 备份后按策略清理旧的卷
+    pub retention: Option<RetentionPolicy>,
+    /// 捕获时排除和不压缩的文件
+    pub exclusions: ExclusionConfig,
+    /// 新建镜像时的压缩方式
+    pub compression: Compression,
+    /// 备份后导出 ESD 归档副本
+    pub export_esd: bool,
 }
 
 /// 配置文件管理器
@@ -77,6 +166,12 @@ impl ConfigFileManager {
     /// 临时数据目录名
     const DATA_DIR: &'static str = "LetRecovery_Data";
 
+    /// 步骤日志文件名（位于数据目录中，随数据目录一起清理）
+    const JOURNAL: &'static str = "LetRecovery_Journal.ini";
+
+    /// 修复引导前导出的 BCD 备份文件名（用于回滚引导项）
+    const BCD_BACKUP: &'static str = "LetRecovery_BCD.bak";
+
     /// 查找包含安装标记文件的分区
     pub fn find_install_marker_partition() -> Option<String> {
         for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
@@ -121,6 +216,18 @@ impl ConfigFileManager {
 
     /// 检测操作类型 (安装或备份)
     pub fn detect_operation_type() -> Option<OperationType> {
+        // 未完成的流程优先：格式化后目标分区上的标记文件已经不存在
+        if let Some(data_part) = Self::find_data_partition() {
+            if let Some(operation) = read_operation(Self::journal_path(&data_part)) {
+                for op in [OperationType::Install, OperationType::Backup] {
+                    if op.journal_name() == operation {
+                        log::info!("检测到未完成的{}流程", operation);
+                        return Some(op);
+                    }
+                }
+            }
+        }
+
         // 先检查安装标记
         if Self::find_install_marker_partition().is_some() {
             if let Some(data_part) = Self::find_data_partition() {
@@ -183,6 +290,16 @@ impl ConfigFileManager {
         format!("{}\\{}", partition, Self::DATA_DIR)
     }
 
+    /// 获取步骤日志路径
+    pub fn journal_path(data_partition: &str) -> String {
+        format!("{}\\{}", Self::get_data_dir(data_partition), Self::JOURNAL)
+    }
+
+    /// 获取 BCD 备份路径
+    pub fn bcd_backup_path(data_partition: &str) -> String {
+        format!("{}\\{}", Self::get_data_dir(data_partition), Self::BCD_BACKUP)
+    }
+
     /// 获取PE目录路径
     pub fn get_pe_dir(partition: &str) -> String {
         format!("{}\\{}", partition, Self::PE_DIR)
@@ -277,8 +394,31 @@ impl ConfigFileManager {
                     "DisableDeviceEncryption" => {
                         config.disable_device_encryption = value.parse().unwrap_or(false)
                     }
+                    "BypassWin11Check" => config.bypass_win11_check = value.parse().unwrap_or(false),
                     "RemoveUWPApps" => config.remove_uwp_apps = value.parse().unwrap_or(false),
                     "CustomUsername" => config.custom_username = value.to_string(),
+                    "UILanguage" => config.ui_language = value.to_string(),
+                    "InputLocale" => config.input_locale = value.to_string(),
+                    "SystemLocale" => config.system_locale = value.to_string(),
+                    "TimeZone" => config.time_zone = value.to_string(),
+                    "ComputerName" => config.computer_name = value.to_string(),
+                    "ProductKey" => config.product_key = value.to_string(),
+                    "Organization" => config.organization = value.to_string(),
+                    "LocalAccount" => {
+                        if let Some(account) = LocalAccount::from_config_value(value) {
+                            config.accounts.push(account);
+                        }
+                    }
+                    "DisableAutoLogon" => config.disable_auto_logon = value.parse().unwrap_or(false),
+                    "BuiltinAdministrator" => {
+                        config.builtin_administrator = BuiltinAdministrator::parse(value)
+                    }
+                    "AdministratorPassword" => {
+                        config.administrator_password = EncodedPassword::from_encoded(value)
+                    }
+                    "UnattendFile" => config.unattend_file = value.to_string(),
+                    "InstallSoftware" => config.install_software = value.parse().unwrap_or(false),
+                    "Profile" => config.profile = value.to_string(),
                     _ => {}
                 }
             }
@@ -290,6 +430,10 @@ impl ConfigFileManager {
     /// 反序列化备份配置
     fn deserialize_backup_config(content: &str) -> Result<BackupConfig> {
         let mut config = BackupConfig::default();
+        let mut exclusions = Vec::new();
+        let mut compression_exclusions = Vec::new();
+        // 旧版本的配置没有此项，使用内置默认
+        let mut use_defaults = true;
 
         for line in content.lines() {
             let line = line.trim();
@@ -306,12 +450,22 @@ impl ConfigFileManager {
                     "Name" => config.name = value.to_string(),
                     "Description" => config.description = value.to_string(),
                     "SourcePartition" => config.source_partition = value.to_string(),
+                    "Format" => config.format = BackupFormat::parse(value).unwrap_or_default(),
                     "Incremental" => config.incremental = value.parse().unwrap_or(false),
+                    "Verify" => config.verify = value.parse().unwrap_or(false),
+                    "Retention" => config.retention = RetentionPolicy::parse(value),
+                    "Compression" => config.compression = Compression::parse(value).unwrap_or_default(),
+                    "ExportEsd" => config.export_esd = value.parse().unwrap_or(false),
+                    "UseDefaults" => use_defaults = value.parse().unwrap_or(true),
+                    "Exclude" => exclusions.push(value.to_string()),
+                    "NoCompress" => compression_exclusions.push(value.to_string()),
                     _ => {}
                 }
             }
         }
 
+        config.exclusions = ExclusionConfig::new(use_defaults, &exclusions, &compression_exclusions);
+
         Ok(config)
     }
 }
@@ -322,3 +476,13 @@ pub enum OperationType {
     Install,
     Backup,
 }
+
+impl OperationType {
+    /// 写入步骤日志的操作标识
+    pub fn journal_name(&self) -> &'static str {
+        match self {
+            OperationType::Install => "Install",
+            OperationType::Backup => "Backup",
+        }
+    }
+}
@@ -3,28 +3,19 @@ use std::path::Path;
 use windows::core::PCWSTR;
 use windows::Win32::Storage::FileSystem::{GetDiskFreeSpaceExW, GetDriveTypeW, GetVolumeInformationW};
 
-use crate::utils::command::new_command;
 use crate::utils::encoding::gbk_to_utf8;
-use crate::utils::path::get_bin_dir;
+use letrecovery_common::command::{CommandRunner, SystemRunner};
+use letrecovery_common::diskpart::script::{DiskpartScript, RunMode};
+use letrecovery_common::diskpart::{self, DiskEntry};
+use letrecovery_common::partition_table::PartitionTable;
+use letrecovery_common::tools::Tools;
+use std::sync::Arc;
 
 const DRIVE_FIXED: u32 = 3;
 
 /// 自动创建分区的标志文件名
 pub const AUTO_CREATED_PARTITION_MARKER: &str = "LetRecovery_AutoCreated.marker";
 
-/// 获取 diskpart 可执行文件路径
-/// 优先使用内置的 diskpart，如果不存在则使用系统的
-fn get_diskpart_path() -> String {
-    let builtin_diskpart = get_bin_dir().join("diskpart").join("diskpart.exe");
-    if builtin_diskpart.exists() {
-        log::info!("使用内置 diskpart: {}", builtin_diskpart.display());
-        builtin_diskpart.to_string_lossy().to_string()
-    } else {
-        log::info!("使用系统 diskpart");
-        "diskpart.exe".to_string()
-    }
-}
-
 /// 分区表类型
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum PartitionStyle {
@@ -65,16 +56,34 @@ pub struct PartitionDetail {
     pub partition_number: Option<u32>,
 }
 
-pub struct DiskManager;
+/// 磁盘和分区操作，diskpart 通过创建时传入的执行器调用
+pub struct DiskManager {
+    tools: Tools,
+}
+
+impl Default for DiskManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl DiskManager {
+    pub fn new() -> Self {
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { tools: crate::core::tools(runner) }
+    }
+
     /// 获取所有固定磁盘分区列表
-    pub fn get_partitions() -> Result<Vec<Partition>> {
+    pub fn get_partitions(&self) -> Result<Vec<Partition>> {
         let mut partitions = Vec::new();
 
         for letter in b'A'..=b'Z' {
             let drive = format!("{}:", letter as char);
-            if let Ok(info) = Self::get_partition_info(&drive) {
+            if let Ok(info) = self.get_partition_info(&drive) {
                 partitions.push(info);
             }
         }
@@ -82,7 +91,7 @@ impl DiskManager {
         Ok(partitions)
     }
 
-    fn get_partition_info(drive: &str) -> Result<Partition> {
+    fn get_partition_info(&self, drive: &str) -> Result<Partition> {
         let path = format!("{}\\", drive);
         let wide_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
 
@@ -134,7 +143,7 @@ impl DiskManager {
         let is_system_partition = has_windows && !is_current_system;
 
         // 获取分区表类型、磁盘号和分区号
-        let detail = Self::get_partition_style(drive);
+        let detail = self.get_partition_style(drive);
 
         Ok(Partition {
             letter: drive.to_string(),
@@ -150,34 +159,25 @@ impl DiskManager {
     }
 
     /// 获取分区表类型和分区号 (GPT/MBR)
-    fn get_partition_style(drive: &str) -> PartitionDetail {
+    fn get_partition_style(&self, drive: &str) -> PartitionDetail {
         // PE环境下直接使用 diskpart
-        Self::get_partition_style_diskpart(drive)
+        self.get_partition_style_diskpart(drive)
     }
 
     /// 使用 diskpart 获取分区信息（备用方法）
-    fn get_partition_style_diskpart(drive: &str) -> PartitionDetail {
+    fn get_partition_style_diskpart(&self, drive: &str) -> PartitionDetail {
         let letter = drive.chars().next().unwrap_or('C');
-        let script = format!("select volume {}\ndetail volume", letter);
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("dp_style.txt");
-
-        if std::fs::write(&script_path, &script).is_err() {
-            return PartitionDetail {
-                style: PartitionStyle::Unknown,
-                disk_number: None,
-                partition_number: None,
-            };
-        }
-
-        let output = match new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()
-        {
-            Ok(o) => o,
-            Err(_) => {
-                let _ = std::fs::remove_file(&script_path);
+        // 选中卷后其所在磁盘和分区也随之选中，list partition 中以 * 标记
+        let script = DiskpartScript::builder()
+            .select_volume(letter)
+            .detail_volume()
+            .list_partition()
+            .build();
+
+        let stdout = match script.and_then(|script| self.run_diskpart(&script, RunMode::Execute)) {
+            Ok(stdout) => stdout,
+            Err(e) => {
+                log::warn!("获取分区信息失败: {}", e);
                 return PartitionDetail {
                     style: PartitionStyle::Unknown,
                     disk_number: None,
@@ -186,37 +186,14 @@ impl DiskManager {
             }
         };
 
-        let _ = std::fs::remove_file(&script_path);
-        let stdout = gbk_to_utf8(&output.stdout);
-
-        let mut disk_num: Option<u32> = None;
-        let mut part_num: Option<u32> = None;
-
-        for line in stdout.lines() {
-            let line_upper = line.to_uppercase();
-            if (line_upper.contains("磁盘") || line_upper.contains("DISK"))
-                && !line_upper.contains("磁盘 ID")
-                && !line_upper.contains("DISK ID")
-            {
-                if let Some(num) = line
-                    .split_whitespace()
-                    .find(|s| s.parse::<u32>().is_ok())
-                {
-                    disk_num = num.parse().ok();
-                }
-            }
-            if line_upper.contains("分区") || line_upper.contains("PARTITION") {
-                if let Some(num) = line
-                    .split_whitespace()
-                    .find(|s| s.parse::<u32>().is_ok())
-                {
-                    part_num = num.parse().ok();
-                }
-            }
-        }
+        let disk_num = diskpart::parse_detail_volume_disk(&stdout);
+        let part_num = diskpart::parse_list_partition(&stdout)
+            .into_iter()
+            .find(|p| p.selected)
+            .map(|p| p.number);
 
         let style = if let Some(num) = disk_num {
-            Self::get_disk_partition_style(num)
+            self.get_disk_partition_style(num)
         } else {
             PartitionStyle::Unknown
         };
@@ -229,63 +206,86 @@ impl DiskManager {
     }
 
     /// 获取指定磁盘的分区表类型
-    fn get_disk_partition_style(disk_number: u32) -> PartitionStyle {
-        let script = format!("select disk {}\ndetail disk", disk_number);
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("dp_disk_style.txt");
-
-        if std::fs::write(&script_path, &script).is_err() {
+    fn get_disk_partition_style(&self, disk_number: u32) -> PartitionStyle {
+        let Ok(disks) = self.list_disks() else {
             return PartitionStyle::Unknown;
-        }
-
-        let output = match new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()
-        {
-            Ok(o) => o,
-            Err(_) => {
-                let _ = std::fs::remove_file(&script_path);
-                return PartitionStyle::Unknown;
-            }
         };
 
-        let _ = std::fs::remove_file(&script_path);
-        let stdout = gbk_to_utf8(&output.stdout).to_uppercase();
-
-        if stdout.contains("GPT") {
-            PartitionStyle::GPT
-        } else if stdout.contains("MBR") {
-            PartitionStyle::MBR
-        } else {
-            PartitionStyle::Unknown
+        match disks
+            .into_iter()
+            .find(|d| d.number == disk_number)
+        {
+            Some(disk) if disk.is_gpt => PartitionStyle::GPT,
+            Some(_) => PartitionStyle::MBR,
+            None => PartitionStyle::Unknown,
         }
     }
 
-    /// 格式化指定分区
-    pub fn format_partition(partition: &str) -> Result<String> {
-        log::info!("格式化分区: {}", partition);
+    /// 执行 diskpart 脚本，返回输出；`RunMode::DryRun` 时不执行，只返回执行计划
+    ///
+    /// 以 /s 运行脚本时任一命令失败 diskpart 都会返回非 0 退出码，
+    /// 据此判断成败，不匹配随系统语言变化的提示文字。
+    pub fn run_diskpart(&self, script: &DiskpartScript, mode: RunMode) -> Result<String> {
+        self.tools.run_diskpart(script, mode)
+    }
 
-        let bin_dir = get_bin_dir();
-        let format_exe = bin_dir.join("format.com").to_string_lossy().to_string();
+    /// 列出所有磁盘
+    pub fn list_disks(&self) -> Result<Vec<DiskEntry>> {
+        let script = DiskpartScript::builder().list_disk().build()?;
+        let output = self.run_diskpart(&script, RunMode::Execute)?;
+        Ok(diskpart::parse_list_disk(&output))
+    }
 
-        // 优先使用 bin 目录下的 format.com，如果不存在则使用系统的
-        let format_cmd = if Path::new(&format_exe).exists() {
-            format_exe
-        } else {
-            "format.com".to_string()
-        };
+    /// 获取卷所在的磁盘号
+    pub fn get_volume_disk_number(&self, letter: char) -> Result<u32> {
+        let script = DiskpartScript::builder().select_volume(letter).detail_volume().build()?;
+        let output = self.run_diskpart(&script, RunMode::Execute)?;
+        diskpart::parse_detail_volume_disk(&output)
+            .ok_or_else(|| anyhow::anyhow!("无法确定分区 {}: 所在磁盘", letter))
+    }
 
-        let output = new_command(&format_cmd)
-            .args([partition, "/FS:NTFS", "/q", "/y"])
-            .output()?;
+    /// 直接读取物理磁盘的分区表
+    pub fn read_partition_table(disk_number: u32) -> Result<PartitionTable> {
+        let path = format!("\\\\.\\PhysicalDrive{}", disk_number);
+        let mut disk = std::fs::File::open(&path)
+            .map_err(|e| anyhow::anyhow!("打开 {} 失败: {}", path, e))?;
+        PartitionTable::read(&mut disk)
+    }
 
-        let result = gbk_to_utf8(&output.stdout);
-        log::info!("格式化结果: {}", result);
+    /// 按分区类型查找磁盘上的 EFI 系统分区，返回分区号
+    ///
+    /// 优先读取分区表按类型 GUID 判断，读取失败时再用 diskpart 逐个查询分区类型。
+    pub fn find_esp_partition_number(&self, disk_number: u32) -> Result<Option<u32>> {
+        match Self::read_partition_table(disk_number) {
+            Ok(table) => return Ok(table.esp().map(|p| p.number)),
+            Err(e) => log::warn!("读取磁盘 {} 分区表失败，改用 diskpart: {}", disk_number, e),
+        }
 
-        if !output.status.success() {
-            let stderr = gbk_to_utf8(&output.stderr);
-            anyhow::bail!("格式化失败: {}", stderr);
+        let script = DiskpartScript::builder().select_disk(disk_number).list_partition().build()?;
+        let output = self.run_diskpart(&script, RunMode::Execute)?;
+        for partition in diskpart::parse_list_partition(&output) {
+            let script = DiskpartScript::builder()
+                .select_disk(disk_number)
+                .select_partition(partition.number)
+                .detail_partition()
+                .build()?;
+            let detail = self.run_diskpart(&script, RunMode::Execute)?;
+            if diskpart::parse_detail_partition_type(&detail).is_some_and(|t| t.is_esp()) {
+                return Ok(Some(partition.number));
+            }
         }
+        Ok(None)
+    }
+
+    /// 格式化指定分区（NTFS 快速格式化）
+    pub fn format_partition(&self, partition: &str) -> Result<String> {
+        log::info!("格式化分区: {}", partition);
+
+        let result = self
+            .tools
+            .format_volume(partition.chars().next().unwrap_or('C'), RunMode::Execute)
+            .map_err(|e| anyhow::anyhow!("格式化失败: {}", e))?;
+        log::info!("格式化结果: {}", result);
 
         Ok(result)
     }
@@ -302,7 +302,7 @@ impl DiskManager {
     }
 
     /// 检测是否为UEFI模式
-    pub fn detect_uefi_mode() -> bool {
+    pub fn detect_uefi_mode(&self) -> bool {
         // 检查EFI系统分区
         for letter in ['S', 'T', 'U', 'V', 'W', 'Y', 'Z'] {
             let efi_path = format!("{}:\\EFI\\Microsoft\\Boot", letter);
@@ -312,9 +312,7 @@ impl DiskManager {
         }
 
         // 检查固件类型
-        let output = new_command("cmd")
-            .args(["/c", "bcdedit /enum firmware"])
-            .output();
+        let output = self.tools.runner().output("cmd", &["/c", "bcdedit /enum firmware"]);
 
         if let Ok(output) = output {
             let stdout = gbk_to_utf8(&output.stdout);
@@ -328,7 +326,7 @@ impl DiskManager {
 
     /// 查找自动创建的分区（通过标志文件）
     /// 返回 (盘符, 磁盘号Option) 如果找到的话
-    pub fn find_auto_created_partition() -> Option<(char, Option<u32>)> {
+    pub fn find_auto_created_partition(&self) -> Option<(char, Option<u32>)> {
         for letter in b'A'..=b'Z' {
             let c = letter as char;
             // 跳过 X 盘（PE系统盘）
@@ -341,7 +339,7 @@ impl DiskManager {
                 log::info!("找到自动创建的分区: {}:", c);
                 
                 // 获取该分区所在的磁盘号
-                let detail = Self::get_partition_style(&format!("{}:", c));
+                let detail = self.get_partition_style(&format!("{}:", c));
                 return Some((c, detail.disk_number));
             }
         }
@@ -364,14 +362,14 @@ impl DiskManager {
     /// 2. 确认该分区和目标分区在同一个磁盘上
     /// 3. 删除该分区
     /// 4. 扩展目标分区以使用释放的空间
-    pub fn cleanup_auto_created_partition_and_extend(target_partition: &str) -> Result<()> {
+    pub fn cleanup_auto_created_partition_and_extend(&self, target_partition: &str) -> Result<()> {
         let target_letter = target_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
         
         log::info!("[CLEANUP] 检查是否有自动创建的分区需要清理...");
         log::info!("[CLEANUP] 目标安装分区: {}:", target_letter);
 
         // 查找自动创建的分区
-        let (auto_letter, auto_disk_num_opt) = match Self::find_auto_created_partition() {
+        let (auto_letter, auto_disk_num_opt) = match self.find_auto_created_partition() {
             Some(info) => info,
             None => {
                 log::info!("[CLEANUP] 未找到自动创建的分区，无需清理");
@@ -384,7 +382,7 @@ impl DiskManager {
             Some(num) => num,
             None => {
                 log::warn!("[CLEANUP] 无法获取自动创建分区 {} 的磁盘号，只删除不扩展", auto_letter);
-                return Self::delete_partition_by_letter(auto_letter);
+                return self.delete_partition_by_letter(auto_letter);
             }
         };
 
@@ -394,13 +392,13 @@ impl DiskManager {
         );
 
         // 获取目标分区所在的磁盘号
-        let target_detail = Self::get_partition_style(&format!("{}:", target_letter));
+        let target_detail = self.get_partition_style(&format!("{}:", target_letter));
         let target_disk_num = match target_detail.disk_number {
             Some(num) => num,
             None => {
                 log::warn!("[CLEANUP] 无法获取目标分区 {} 的磁盘号，只删除分区不扩展", target_letter);
                 // 无法判断是否同一磁盘，安全起见只删除不扩展
-                return Self::delete_partition_by_letter(auto_letter);
+                return self.delete_partition_by_letter(auto_letter);
             }
         };
 
@@ -413,78 +411,45 @@ impl DiskManager {
                 auto_letter, target_letter
             );
             // 只删除分区，不扩展
-            return Self::delete_partition_by_letter(auto_letter);
+            return self.delete_partition_by_letter(auto_letter);
         }
 
         // 删除自动创建分区并扩展目标分区
         log::info!("[CLEANUP] 开始删除分区 {} 并扩展目标分区 {}...", auto_letter, target_letter);
-        Self::delete_partition_and_extend(auto_letter, target_letter)
+        self.delete_partition_and_extend(auto_letter, target_letter)
     }
 
     /// 删除指定盘符的分区
-    fn delete_partition_by_letter(letter: char) -> Result<()> {
+    fn delete_partition_by_letter(&self, letter: char) -> Result<()> {
         log::info!("[CLEANUP] 删除分区 {}:", letter);
 
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            letter
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_delete_part.txt");
-        std::fs::write(&script_path, &script_content)?;
+        let script = DiskpartScript::builder()
+            .select_volume(letter)
+            .delete_partition_override()
+            .build()?;
 
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        let output_text = gbk_to_utf8(&output.stdout);
+        let output_text = self.run_diskpart(&script, RunMode::Execute)
+            .map_err(|e| anyhow::anyhow!("删除分区失败: {}", e))?;
         log::info!("[CLEANUP] Diskpart 删除输出: {}", output_text);
 
-        // 检查是否有错误
-        let output_lower = output_text.to_lowercase();
-        if output_lower.contains("error") || output_lower.contains("错误")
-            || output_lower.contains("失败") || output_lower.contains("failed") {
-            anyhow::bail!("删除分区失败: {}", output_text);
-        }
-
         log::info!("[CLEANUP] 分区 {} 删除成功", letter);
         Ok(())
     }
 
     /// 删除分区并扩展目标分区
-    fn delete_partition_and_extend(auto_letter: char, target_letter: char) -> Result<()> {
+    fn delete_partition_and_extend(&self, auto_letter: char, target_letter: char) -> Result<()> {
         // Step 1: 删除分区
         log::info!("[CLEANUP] Step 1: 删除分区 {}:", auto_letter);
         
-        let delete_script = format!(
-            "select volume {}\ndelete partition override",
-            auto_letter
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_delete_part.txt");
-        std::fs::write(&script_path, &delete_script)?;
-
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
+        let delete_script = DiskpartScript::builder()
+            .select_volume(auto_letter)
+            .delete_partition_override()
+            .build()?;
 
-        let output_text = gbk_to_utf8(&output.stdout);
+        let output_text = self.run_diskpart(&delete_script, RunMode::Execute)
+            .map_err(|e| anyhow::anyhow!("删除分区失败: {}", e))?;
         log::info!("[CLEANUP] 删除分区输出: {}", output_text);
 
-        // 检查删除是否成功
-        let output_lower = output_text.to_lowercase();
-        if output_lower.contains("error") || output_lower.contains("错误")
-            || output_lower.contains("失败") || output_lower.contains("failed") {
-            // 删除失败，直接返回错误
-            anyhow::bail!("删除分区失败: {}", output_text);
-        }
-
         log::info!("[CLEANUP] 分区 {} 删除成功", auto_letter);
 
         // Step 2: 等待系统识别未分配空间，然后扩展目标分区（带重试）
@@ -500,7 +465,7 @@ impl DiskManager {
             std::thread::sleep(std::time::Duration::from_secs(RETRY_DELAY_SECS));
             
             // 尝试扩展
-            match Self::try_extend_volume(target_letter) {
+            match self.try_extend_volume(target_letter) {
                 Ok(_) => {
                     log::info!("[CLEANUP] 分区 {} 扩展成功！", target_letter);
                     return Ok(());
@@ -520,37 +485,12 @@ impl DiskManager {
     }
 
     /// 尝试扩展指定分区（使用 diskpart）
-    fn try_extend_volume(letter: char) -> Result<()> {
-        let extend_script = format!("select volume {}\nextend", letter);
-        
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_extend.txt");
-        std::fs::write(&script_path, &extend_script)?;
-
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        let output_text = gbk_to_utf8(&output.stdout);
-        let output_lower = output_text.to_lowercase();
+    fn try_extend_volume(&self, letter: char) -> Result<()> {
+        let extend_script = DiskpartScript::builder().select_volume(letter).extend().build()?;
 
+        let output_text = self.run_diskpart(&extend_script, RunMode::Execute)
+            .map_err(|e| anyhow::anyhow!("extend 失败: {}", e))?;
         log::info!("[CLEANUP] diskpart extend 输出: {}", output_text);
-
-        // 检查是否成功
-        if output_lower.contains("成功") || output_lower.contains("successfully") {
-            return Ok(());
-        }
-
-        // 检查是否有错误
-        if output_lower.contains("error") || output_lower.contains("错误")
-            || output_lower.contains("失败") || output_lower.contains("failed")
-            || output_lower.contains("没有可用") || output_lower.contains("no usable") {
-            anyhow::bail!("extend 失败: {}", output_text);
-        }
-
-        // 不确定状态，假设失败
-        anyhow::bail!("extend 状态不确定: {}", output_text)
+        Ok(())
     }
 }
@@ -1,9 +1,7 @@
 use anyhow::Result;
-use std::io::Read;
-use std::process::Stdio;
-use std::sync::mpsc::Sender;
+use letrecovery_common::command::{CommandRunner, SystemRunner};
+use std::sync::Arc;
 
-use crate::utils::command::new_command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
@@ -24,10 +22,16 @@ pub struct ImageInfo {
 
 pub struct Dism {
     dism_path: String,
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl Dism {
     pub fn new() -> Self {
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
         let bin_dir = get_bin_dir();
         Self {
             dism_path: bin_dir
@@ -35,6 +39,7 @@ impl Dism {
                 .join("dism.exe")
                 .to_string_lossy()
                 .to_string(),
+            runner,
         }
     }
 
@@ -43,89 +48,17 @@ impl Dism {
         std::path::Path::new(&self.dism_path).exists()
     }
 
-    /// 应用系统镜像 (WIM/ESD)
-    pub fn apply_image(
-        &self,
-        image_file: &str,
-        apply_dir: &str,
-        index: u32,
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        log::info!(
-            "开始释放镜像: {} -> {} (Index: {})",
-            image_file,
-            apply_dir,
-            index
-        );
-
-        let args = [
-            "/Apply-Image",
-            &format!("/ImageFile:{}", image_file),
-            &format!("/ApplyDir:{}", apply_dir),
-            &format!("/Index:{}", index),
-        ];
-
-        self.run_with_progress(&args, progress_tx)
-    }
-
-    /// 捕获系统镜像 (备份)
-    pub fn capture_image(
-        &self,
-        image_file: &str,
-        capture_dir: &str,
-        name: &str,
-        description: &str,
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        log::info!("开始备份镜像: {} -> {}", capture_dir, image_file);
-
-        let args = [
-            "/Capture-Image",
-            &format!("/ImageFile:{}", image_file),
-            &format!("/CaptureDir:{}", capture_dir),
-            &format!("/Name:{}", name),
-            &format!("/Description:{}", description),
-            "/Compress:max",
-        ];
-
-        self.run_with_progress(&args, progress_tx)
-    }
-
-    /// 增量备份镜像
-    pub fn append_image(
-        &self,
-        image_file: &str,
-        capture_dir: &str,
-        name: &str,
-        description: &str,
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        log::info!("开始增量备份: {} -> {}", capture_dir, image_file);
-
-        let args = [
-            "/Append-Image",
-            &format!("/ImageFile:{}", image_file),
-            &format!("/CaptureDir:{}", capture_dir),
-            &format!("/Name:{}", name),
-            &format!("/Description:{}", description),
-        ];
-
-        self.run_with_progress(&args, progress_tx)
-    }
-
     /// 导入驱动到离线系统
     pub fn add_drivers_offline(&self, image_path: &str, driver_path: &str) -> Result<()> {
         log::info!("导入驱动: {} -> {}", driver_path, image_path);
 
-        let output = new_command(&self.dism_path)
-            .args([
-                &format!("/image:{}", image_path),
-                "/add-driver",
-                "/forceunsigned",
-                &format!("/driver:{}", driver_path),
-                "/recurse",
-            ])
-            .output()?;
+        let output = self.runner.output(&self.dism_path, &[
+            &format!("/image:{}", image_path),
+            "/add-driver",
+            "/forceunsigned",
+            &format!("/driver:{}", driver_path),
+            "/recurse",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -136,9 +69,9 @@ impl Dism {
 
     /// 获取 WIM/ESD 镜像信息（所有分卷）
     pub fn get_image_info(&self, image_file: &str) -> Result<Vec<ImageInfo>> {
-        let output = new_command(&self.dism_path)
-            .args(["/get-imageinfo", &format!("/imagefile:{}", image_file)])
-            .output()?;
+        let output = self
+            .runner
+            .output(&self.dism_path, &["/get-imageinfo", &format!("/imagefile:{}", image_file)])?;
 
         let stdout = gbk_to_utf8(&output.stdout);
         Self::parse_image_info(&stdout)
@@ -192,125 +125,6 @@ impl Dism {
 
         Ok(images)
     }
-
-    /// 执行 DISM 命令并实时获取进度
-    fn run_with_progress(
-        &self,
-        args: &[&str],
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        log::info!("DISM 命令: {} {:?}", &self.dism_path, args);
-
-        let mut child = new_command(&self.dism_path)
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        if let Some(mut stdout) = child.stdout.take() {
-            let mut buffer = [0u8; 1024];
-            let mut line_buffer = Vec::new();
-
-            loop {
-                match stdout.read(&mut buffer) {
-                    Ok(0) => break,
-                    Ok(n) => {
-                        for &byte in &buffer[..n] {
-                            if byte == b'\r' || byte == b'\n' {
-                                if !line_buffer.is_empty() {
-                                    let line = gbk_to_utf8(&line_buffer);
-                                    log::debug!("DISM: {}", line);
-
-                                    if let Some(progress) = Self::parse_progress_line(&line) {
-                                        if let Some(ref tx) = progress_tx {
-                                            let _ = tx.send(progress);
-                                        }
-                                    }
-                                    line_buffer.clear();
-                                }
-                            } else {
-                                line_buffer.push(byte);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        if e.kind() != std::io::ErrorKind::Interrupted {
-                            break;
-                        }
-                    }
-                }
-            }
-
-            // 处理剩余的数据
-            if !line_buffer.is_empty() {
-                let line = gbk_to_utf8(&line_buffer);
-                if let Some(progress) = Self::parse_progress_line(&line) {
-                    if let Some(ref tx) = progress_tx {
-                        let _ = tx.send(progress);
-                    }
-                }
-            }
-        }
-
-        // 读取 stderr
-        if let Some(mut stderr) = child.stderr.take() {
-            let mut err_output = Vec::new();
-            let _ = stderr.read_to_end(&mut err_output);
-            if !err_output.is_empty() {
-                let err_str = gbk_to_utf8(&err_output);
-                log::debug!("DISM STDERR: {}", err_str);
-            }
-        }
-
-        let status = child.wait()?;
-        log::info!("DISM 命令结束，状态: {:?}", status);
-
-        if !status.success() {
-            anyhow::bail!("DISM 命令执行失败");
-        }
-        Ok(())
-    }
-
-    /// 解析 DISM 进度输出
-    fn parse_progress_line(line: &str) -> Option<DismProgress> {
-        let mut percentage: Option<u8> = None;
-
-        // 查找 xx.x% 或 xx% 格式
-        let line_chars: Vec<char> = line.chars().collect();
-        for i in 0..line_chars.len() {
-            if line_chars[i] == '%' && i > 0 {
-                let mut num_str = String::new();
-                let mut j = i as i32 - 1;
-
-                while j >= 0 {
-                    let c = line_chars[j as usize];
-                    if c.is_ascii_digit() || c == '.' {
-                        num_str.insert(0, c);
-                        j -= 1;
-                    } else if c == ' ' && num_str.is_empty() {
-                        j -= 1;
-                    } else {
-                        break;
-                    }
-                }
-
-                if !num_str.is_empty() {
-                    if let Some(dot_pos) = num_str.find('.') {
-                        num_str = num_str[..dot_pos].to_string();
-                    }
-                    if let Ok(p) = num_str.parse::<u8>() {
-                        percentage = Some(p);
-                        break;
-                    }
-                }
-            }
-        }
-
-        percentage.map(|p| DismProgress {
-            percentage: p,
-            status: format!("{}%", p),
-        })
-    }
 }
 
 impl Default for Dism {
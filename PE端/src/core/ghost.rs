@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
-use std::io::{BufRead, BufReader, Read};
+use letrecovery_common::command::{SystemRunner, CommandRunner, StreamEvent};
+use std::io::Read;
 use std::path::Path;
-use std::process::{Child, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
@@ -9,7 +9,6 @@ use std::time::Duration;
 
 use crate::core::dism::DismProgress;
 use crate::core::disk::Partition;
-use crate::utils::command::new_command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
@@ -42,10 +41,16 @@ pub enum GhostError {
 pub struct Ghost {
     ghost_path: String,
     cancel_flag: Arc<AtomicBool>,
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl Ghost {
     pub fn new() -> Self {
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
         let bin_dir = get_bin_dir();
         Self {
             ghost_path: bin_dir
@@ -54,6 +59,7 @@ impl Ghost {
                 .to_string_lossy()
                 .to_string(),
             cancel_flag: Arc::new(AtomicBool::new(false)),
+            runner,
         }
     }
 
@@ -179,19 +185,7 @@ impl Ghost {
             clone_param
         );
 
-        let mut child = new_command(&self.ghost_path)
-            .args([&clone_param, "-sure", "-fx", "-batch"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("无法启动 Ghost 进程")?;
-
-        let result = self.monitor_ghost_process(&mut child, progress_tx, estimated_size);
-
-        let _ = child.kill();
-        let _ = child.wait();
-
-        result
+        self.run_ghost(&[&clone_param, "-sure", "-fx", "-batch"], progress_tx, estimated_size)
     }
 
     /// 使用盘符恢复 GHO 镜像
@@ -202,7 +196,81 @@ impl Ghost {
         partitions: &[Partition],
         progress_tx: Option<Sender<DismProgress>>,
     ) -> Result<()> {
-        let letter = target_letter
+        let (ghost_disk, ghost_partition) = Self::ghost_partition(target_letter, partitions)?;
+        self.restore_image(gho_file, ghost_disk, ghost_partition, progress_tx)
+    }
+
+    /// 创建 GHO 镜像（备份功能）
+    pub fn create_image(
+        &self,
+        disk_number: u32,
+        partition_number: u32,
+        gho_file: &str,
+        compression: u8,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()> {
+        self.reset_cancel();
+
+        if !self.is_available() {
+            return Err(GhostError::ExecutableNotFound(self.ghost_path.clone()).into());
+        }
+
+        if disk_number == 0 || partition_number == 0 {
+            return Err(GhostError::InvalidPartition(format!(
+                "无效的分区参数: 磁盘={}, 分区={}",
+                disk_number, partition_number
+            ))
+            .into());
+        }
+
+        if let Some(parent) = Path::new(gho_file).parent() {
+            std::fs::create_dir_all(parent).context("无法创建输出目录")?;
+        }
+
+        let source_partition = format!("{}:{}", disk_number, partition_number);
+        let compression = compression.clamp(1, 9);
+
+        log::info!("========================================");
+        log::info!("开始创建 GHO 镜像");
+        log::info!(
+            "源分区: {} (磁盘 {} 分区 {})",
+            source_partition,
+            disk_number,
+            partition_number
+        );
+        log::info!("输出文件: {}", gho_file);
+        log::info!("压缩级别: {}", compression);
+        log::info!("========================================");
+
+        let clone_param = format!(
+            "-clone,mode=pdump,src={},dst={}",
+            source_partition, gho_file
+        );
+        let compression_param = format!("-z{}", compression);
+
+        self.run_ghost(
+            &[&clone_param, "-sure", "-fx", "-batch", &compression_param],
+            progress_tx,
+            0,
+        )
+    }
+
+    /// 使用盘符备份分区为 GHO 镜像
+    pub fn create_image_from_letter(
+        &self,
+        source_letter: &str,
+        partitions: &[Partition],
+        gho_file: &str,
+        compression: u8,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()> {
+        let (ghost_disk, ghost_partition) = Self::ghost_partition(source_letter, partitions)?;
+        self.create_image(ghost_disk, ghost_partition, gho_file, compression, progress_tx)
+    }
+
+    /// 把盘符转换为 Ghost 使用的磁盘号和分区号
+    fn ghost_partition(letter: &str, partitions: &[Partition]) -> Result<(u32, u32)> {
+        let letter = letter
             .trim_end_matches(['\\', '/'])
             .to_uppercase();
         let letter = if letter.ends_with(':') {
@@ -211,7 +279,7 @@ impl Ghost {
             format!("{}:", letter)
         };
 
-        log::info!("解析目标盘符: {}", letter);
+        log::info!("解析盘符: {}", letter);
 
         let partition = partitions
             .iter()
@@ -251,159 +319,106 @@ impl Ghost {
         );
         log::info!("  Ghost:   {}:{}", ghost_disk, ghost_partition);
 
-        self.restore_image(gho_file, ghost_disk, ghost_partition, progress_tx)
+        Ok((ghost_disk, ghost_partition))
     }
 
-    /// 监控 Ghost 进程并报告进度
-    fn monitor_ghost_process(
+    /// 运行 Ghost 并报告进度
+    ///
+    /// Ghost 在 -batch 模式下没有可解析的进度输出，按预计耗时估算进度（最多 95%）。
+    fn run_ghost(
         &self,
-        child: &mut Child,
+        args: &[&str],
         progress_tx: Option<Sender<DismProgress>>,
         estimated_size: u64,
     ) -> Result<()> {
         let cancel_flag = Arc::clone(&self.cancel_flag);
-
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
-
-        let stdout_handle = if let Some(stdout) = stdout {
-            let cancel = Arc::clone(&cancel_flag);
-            Some(std::thread::spawn(move || {
-                Self::read_ghost_output(stdout, cancel)
-            }))
-        } else {
-            None
-        };
-
-        let stderr_content = Arc::new(std::sync::Mutex::new(String::new()));
-        let stderr_content_clone = Arc::clone(&stderr_content);
-
-        let stderr_handle = if let Some(stderr) = stderr {
-            Some(std::thread::spawn(move || {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines().map_while(Result::ok) {
-                    let line_utf8 = gbk_to_utf8(line.as_bytes());
-                    log::debug!("GHOST STDERR: {}", line_utf8);
-                    if let Ok(mut content) = stderr_content_clone.lock() {
-                        content.push_str(&line_utf8);
-                        content.push('\n');
-                    }
-                }
-            }))
-        } else {
-            None
-        };
-
         let start_time = std::time::Instant::now();
 
         let estimated_seconds = if estimated_size > 0 {
-            (estimated_size / (100 * 1024 * 1024)).max(60) as u64
+            (estimated_size / (100 * 1024 * 1024)).max(60)
         } else {
             300
         };
         let estimated_duration = Duration::from_secs(estimated_seconds);
 
-        log::info!("预计恢复时间: {} 秒", estimated_seconds);
+        log::info!("预计耗时: {} 秒", estimated_seconds);
 
         let mut last_progress: u8 = 0;
+        let mut line_buffer = Vec::new();
+
+        let output = self
+            .runner
+            .stream(&self.ghost_path, args, &mut |event| {
+                if cancel_flag.load(Ordering::SeqCst) {
+                    log::info!("收到取消请求，终止进程");
+                    return false;
+                }
 
-        loop {
-            if cancel_flag.load(Ordering::SeqCst) {
-                log::info!("收到取消请求，终止进程");
-                let _ = child.kill();
-                return Err(GhostError::Cancelled.into());
-            }
-
-            match child.try_wait() {
-                Ok(Some(status)) => {
-                    log::info!("进程退出，状态码: {:?}", status.code());
-
-                    if let Some(handle) = stdout_handle {
-                        let _ = handle.join();
-                    }
-
-                    if let Some(handle) = stderr_handle {
-                        let _ = handle.join();
-                    }
-
-                    let stderr_output = stderr_content
-                        .lock()
-                        .map(|s| s.clone())
-                        .unwrap_or_default();
-
-                    if let Some(ref tx) = progress_tx {
-                        let _ = tx.send(DismProgress {
-                            percentage: 100,
-                            status: "释放系统镜像".to_string(),
-                        });
-                    }
-
-                    if status.success() || status.code() == Some(0) {
-                        log::info!("========================================");
-                        log::info!("镜像恢复成功!");
-                        log::info!("========================================");
-                        return Ok(());
-                    } else {
-                        let error_msg = if stderr_output.trim().is_empty() {
-                            format!("Ghost 进程异常退出，退出码: {:?}", status.code())
-                        } else {
-                            format!("Ghost 错误: {}", stderr_output.trim())
-                        };
-                        log::error!("恢复失败: {}", error_msg);
-                        return Err(GhostError::ExecutionFailed(error_msg).into());
+                match event {
+                    StreamEvent::Stdout(chunk) => {
+                        for &byte in chunk {
+                            if byte == b'\n' {
+                                log::debug!("GHOST STDOUT: {}", gbk_to_utf8(&line_buffer));
+                                line_buffer.clear();
+                            } else if byte != b'\r' {
+                                line_buffer.push(byte);
+                            }
+                        }
                     }
-                }
-                Ok(None) => {
-                    let elapsed = start_time.elapsed();
-                    let progress =
-                        ((elapsed.as_secs_f64() / estimated_duration.as_secs_f64()) * 95.0)
+                    StreamEvent::Tick => {
+                        let elapsed = start_time.elapsed();
+                        let progress = ((elapsed.as_secs_f64() / estimated_duration.as_secs_f64()) * 95.0)
                             .min(95.0) as u8;
 
-                    if progress > last_progress {
-                        last_progress = progress;
-                        log::debug!(
-                            "进度: {}% (已运行 {:.0} 秒)",
-                            progress,
-                            elapsed.as_secs_f64()
-                        );
-
-                        if let Some(ref tx) = progress_tx {
-                            let _ = tx.send(DismProgress {
-                                percentage: progress,
-                                status: "释放系统镜像".to_string(),
-                            });
+                        if progress > last_progress {
+                            last_progress = progress;
+                            log::debug!("进度: {}% (已运行 {:.0} 秒)", progress, elapsed.as_secs_f64());
+
+                            if let Some(ref tx) = progress_tx {
+                                let _ = tx.send(DismProgress {
+                                    percentage: progress,
+                                    status: "释放系统镜像".to_string(),
+                                });
+                            }
                         }
                     }
                 }
-                Err(e) => {
-                    return Err(
-                        GhostError::ExecutionFailed(format!("检查进程状态失败: {}", e)).into(),
-                    );
-                }
-            }
+                true
+            })
+            .context("无法启动 Ghost 进程")?;
 
-            std::thread::sleep(Duration::from_millis(500));
+        if cancel_flag.load(Ordering::SeqCst) && output.status.code().is_none() {
+            return Err(GhostError::Cancelled.into());
         }
-    }
 
-    /// 读取 Ghost 输出
-    fn read_ghost_output<R: Read>(reader: R, cancel_flag: Arc<AtomicBool>) -> Vec<String> {
-        let reader = BufReader::new(reader);
-        let mut lines = Vec::new();
+        log::info!("进程退出，状态码: {:?}", output.status.code());
 
-        for line in reader.lines() {
-            if cancel_flag.load(Ordering::SeqCst) {
-                break;
-            }
+        let stderr_output = gbk_to_utf8(&output.stderr);
+        if !stderr_output.trim().is_empty() {
+            log::debug!("GHOST STDERR: {}", stderr_output.trim());
+        }
 
-            if let Ok(line) = line {
-                let line_utf8 = gbk_to_utf8(line.as_bytes());
-                log::debug!("GHOST STDOUT: {}", line_utf8);
-                lines.push(line_utf8);
-            }
+        if let Some(ref tx) = progress_tx {
+            let _ = tx.send(DismProgress {
+                percentage: 100,
+                status: "释放系统镜像".to_string(),
+            });
         }
 
-        lines
+        if output.status.success() {
+            log::info!("========================================");
+            log::info!("Ghost 执行成功!");
+            log::info!("========================================");
+            Ok(())
+        } else {
+            let error_msg = if stderr_output.trim().is_empty() {
+                format!("Ghost 进程异常退出，退出码: {:?}", output.status.code())
+            } else {
+                format!("Ghost 错误: {}", stderr_output.trim())
+            };
+            log::error!("执行失败: {}", error_msg);
+            Err(GhostError::ExecutionFailed(error_msg).into())
+        }
     }
 }
 
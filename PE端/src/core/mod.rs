@@ -4,3 +4,16 @@ pub mod ghost;
 pub mod bcdedit;
 pub mod registry;
 pub mod disk;
+
+use std::sync::Arc;
+
+use letrecovery_common::command::CommandRunner;
+use letrecovery_common::tools::{ToolPaths, Tools};
+
+use crate::utils::encoding::gbk_to_utf8;
+use crate::utils::path::get_bin_dir;
+
+/// 使用程序目录下的 DISM、diskpart、bcdboot 和指定的执行器
+pub fn tools(runner: Arc<dyn CommandRunner>) -> Tools {
+    Tools::new(runner, ToolPaths::in_bin_dir(&get_bin_dir()), gbk_to_utf8)
+}
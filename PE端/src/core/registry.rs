@@ -1,19 +1,26 @@
 use anyhow::Result;
 
-use crate::utils::command::new_command;
+use letrecovery_common::command::CommandRunner;
+use std::sync::Arc;
 use crate::utils::encoding::gbk_to_utf8;
 
-pub struct OfflineRegistry;
+/// 离线注册表操作，通过创建时传入的执行器调用 reg.exe
+pub struct OfflineRegistry {
+    runner: Arc<dyn CommandRunner>,
+}
 
 impl OfflineRegistry {
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
     /// 加载离线注册表配置单元
-    pub fn load_hive(hive_name: &str, hive_file: &str) -> Result<()> {
+    pub fn load_hive(&self, hive_name: &str, hive_file: &str) -> Result<()> {
         let key_path = format!("HKLM\\{}", hive_name);
         log::info!("加载注册表配置单元: {} -> {}", hive_file, key_path);
 
-        let output = new_command("reg.exe")
-            .args(["load", &key_path, hive_file])
-            .output()?;
+        let output = self.runner.output("reg.exe", &["load", &key_path, hive_file])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -23,15 +30,13 @@ impl OfflineRegistry {
     }
 
     /// 卸载离线注册表配置单元
-    pub fn unload_hive(hive_name: &str) -> Result<()> {
+    pub fn unload_hive(&self, hive_name: &str) -> Result<()> {
         let key_path = format!("HKLM\\{}", hive_name);
         log::info!("卸载注册表配置单元: {}", key_path);
 
         // 尝试多次卸载，因为有时需要等待
         for attempt in 0..5 {
-            let output = new_command("reg.exe")
-                .args(["unload", &key_path])
-                .output()?;
+            let output = self.runner.output("reg.exe", &["unload", &key_path])?;
 
             if output.status.success() {
                 log::info!("注册表配置单元卸载成功");
@@ -43,9 +48,7 @@ impl OfflineRegistry {
         }
 
         // 最后一次尝试
-        let output = new_command("reg.exe")
-            .args(["unload", &key_path])
-            .output()?;
+        let output = self.runner.output("reg.exe", &["unload", &key_path])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -55,22 +58,20 @@ impl OfflineRegistry {
     }
 
     /// 写入 DWORD 值
-    pub fn set_dword(key_path: &str, value_name: &str, data: u32) -> Result<()> {
+    pub fn set_dword(&self, key_path: &str, value_name: &str, data: u32) -> Result<()> {
         log::debug!("设置注册表DWORD: {}\\{} = {}", key_path, value_name, data);
 
-        let output = new_command("reg.exe")
-            .args([
-                "add",
-                key_path,
-                "/v",
-                value_name,
-                "/t",
-                "REG_DWORD",
-                "/d",
-                &data.to_string(),
-                "/f",
-            ])
-            .output()?;
+        let output = self.runner.output("reg.exe", &[
+            "add",
+            key_path,
+            "/v",
+            value_name,
+            "/t",
+            "REG_DWORD",
+            "/d",
+            &data.to_string(),
+            "/f",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -80,7 +81,7 @@ impl OfflineRegistry {
     }
 
     /// 写入字符串值
-    pub fn set_string(key_path: &str, value_name: &str, data: &str) -> Result<()> {
+    pub fn set_string(&self, key_path: &str, value_name: &str, data: &str) -> Result<()> {
         log::debug!(
             "设置注册表字符串: {}\\{} = {}",
             key_path,
@@ -88,11 +89,9 @@ impl OfflineRegistry {
             data
         );
 
-        let output = new_command("reg.exe")
-            .args([
-                "add", key_path, "/v", value_name, "/t", "REG_SZ", "/d", data, "/f",
-            ])
-            .output()?;
+        let output = self.runner.output("reg.exe", &[
+            "add", key_path, "/v", value_name, "/t", "REG_SZ", "/d", data, "/f",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -102,35 +101,29 @@ impl OfflineRegistry {
     }
 
     /// 删除注册表键
-    pub fn delete_key(key_path: &str) -> Result<()> {
+    pub fn delete_key(&self, key_path: &str) -> Result<()> {
         log::debug!("删除注册表键: {}", key_path);
 
-        let _ = new_command("reg.exe")
-            .args(["delete", key_path, "/f"])
-            .output();
+        let _ = self.runner.output("reg.exe", &["delete", key_path, "/f"]);
 
         // 忽略不存在的情况
         Ok(())
     }
 
     /// 删除注册表值
-    pub fn delete_value(key_path: &str, value_name: &str) -> Result<()> {
+    pub fn delete_value(&self, key_path: &str, value_name: &str) -> Result<()> {
         log::debug!("删除注册表值: {}\\{}", key_path, value_name);
 
-        let _ = new_command("reg.exe")
-            .args(["delete", key_path, "/v", value_name, "/f"])
-            .output();
+        let _ = self.runner.output("reg.exe", &["delete", key_path, "/v", value_name, "/f"]);
 
         Ok(())
     }
 
     /// 创建注册表键（如果不存在）
-    pub fn create_key(key_path: &str) -> Result<()> {
+    pub fn create_key(&self, key_path: &str) -> Result<()> {
         log::debug!("创建注册表键: {}", key_path);
 
-        let output = new_command("reg.exe")
-            .args(["add", key_path, "/f"])
-            .output()?;
+        let output = self.runner.output("reg.exe", &["add", key_path, "/f"])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -140,12 +133,10 @@ impl OfflineRegistry {
     }
 
     /// 导入 .reg 文件
-    pub fn import_reg_file(reg_file: &str) -> Result<()> {
+    pub fn import_reg_file(&self, reg_file: &str) -> Result<()> {
         log::info!("导入注册表文件: {}", reg_file);
 
-        let output = new_command("reg.exe")
-            .args(["import", reg_file])
-            .output()?;
+        let output = self.runner.output("reg.exe", &["import", reg_file])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -1,16 +1,20 @@
 use walkdir::WalkDir;
 
+use letrecovery_common::win11;
+use letrecovery_common::SCRIPTS_DIR;
+
 use crate::core::config::InstallConfig;
 use crate::core::registry::OfflineRegistry;
 
-/// 脚本目录名称（统一路径，与正常系统端保持一致）
-const SCRIPTS_DIR: &str = "LetRecovery_Scripts";
-
 /// 应用高级选项到目标系统
 /// 
 /// 此函数在PE环境中执行，负责将用户选择的高级选项应用到目标系统。
 /// 通过离线修改注册表和生成必要的脚本来实现各项功能。
-pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) -> anyhow::Result<()> {
+pub fn apply_advanced_options(
+    target_partition: &str,
+    config: &InstallConfig,
+    registry: &OfflineRegistry,
+) -> anyhow::Result<()> {
     let windows_path = format!("{}\\Windows", target_partition);
     let software_hive = format!("{}\\System32\\config\\SOFTWARE", windows_path);
     let system_hive = format!("{}\\System32\\config\\SYSTEM", windows_path);
@@ -20,11 +24,11 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
 
     // 加载离线注册表
     log::info!("[ADVANCED] 加载离线注册表...");
-    OfflineRegistry::load_hive("pc-soft", &software_hive)?;
-    OfflineRegistry::load_hive("pc-sys", &system_hive)?;
+    registry.load_hive("pc-soft", &software_hive)?;
+    registry.load_hive("pc-sys", &system_hive)?;
     
     // DEFAULT hive 用于设置默认用户配置（如经典右键菜单）
-    let default_loaded = OfflineRegistry::load_hive("pc-default", &default_hive).is_ok();
+    let default_loaded = registry.load_hive("pc-default", &default_hive).is_ok();
     if default_loaded {
         log::info!("[ADVANCED] DEFAULT hive 加载成功");
     } else {
@@ -41,7 +45,7 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     // 1. 移除快捷方式小箭头
     if config.remove_shortcut_arrow {
         log::info!("[ADVANCED] 移除快捷方式小箭头");
-        let _ = OfflineRegistry::set_string(
+        let _ = registry.set_string(
             "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Shell Icons",
             "29",
             "%systemroot%\\system32\\imageres.dll,197",
@@ -54,21 +58,21 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
         // 在 DEFAULT hive 中设置（影响所有新用户）
         if default_loaded {
             // 创建空的 InprocServer32 键，这会禁用新式右键菜单
-            let _ = OfflineRegistry::create_key(
+            let _ = registry.create_key(
                 "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
             );
             // 设置默认值为空字符串
-            let _ = OfflineRegistry::set_string(
+            let _ = registry.set_string(
                 "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
                 "",
                 "",
             );
         }
         // 同时在 SOFTWARE 中设置（系统级）
-        let _ = OfflineRegistry::create_key(
+        let _ = registry.create_key(
             "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
         );
-        let _ = OfflineRegistry::set_string(
+        let _ = registry.set_string(
             "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
             "",
             "",
@@ -78,7 +82,7 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     // 3. OOBE绕过强制联网
     if config.bypass_nro {
         log::info!("[ADVANCED] 设置OOBE绕过联网");
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\OOBE",
             "BypassNRO",
             1,
@@ -89,19 +93,19 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     if config.disable_windows_update {
         log::info!("[ADVANCED] 禁用Windows更新服务");
         // 禁用 Windows Update 服务 (Start=4 表示禁用)
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-sys\\ControlSet001\\Services\\wuauserv",
             "Start",
             4,
         );
         // 禁用 Update Orchestrator Service
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-sys\\ControlSet001\\Services\\UsoSvc",
             "Start",
             4,
         );
         // 设置策略禁用自动更新
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Policies\\Microsoft\\Windows\\WindowsUpdate\\AU",
             "NoAutoUpdate",
             1,
@@ -112,31 +116,31 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     if config.disable_windows_defender {
         log::info!("[ADVANCED] 禁用Windows Defender");
         // 禁用反间谍软件（Defender主开关）
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender",
             "DisableAntiSpyware",
             1,
         );
         // 禁用实时保护
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender\\Real-Time Protection",
             "DisableRealtimeMonitoring",
             1,
         );
         // 禁用 Windows Defender 服务 (Start=4 表示禁用)
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-sys\\ControlSet001\\Services\\WinDefend",
             "Start",
             4,
         );
         // 禁用 Defender 网络检查服务
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-sys\\ControlSet001\\Services\\WdNisSvc",
             "Start",
             4,
         );
         // 禁用安全健康服务
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-sys\\ControlSet001\\Services\\SecurityHealthService",
             "Start",
             4,
@@ -146,12 +150,12 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     // 6. 禁用系统保留空间
     if config.disable_reserved_storage {
         log::info!("[ADVANCED] 禁用系统保留空间");
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
             "ShippedWithReserves",
             0,
         );
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
             "PassedPolicy",
             0,
@@ -161,12 +165,12 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     // 7. 禁用UAC
     if config.disable_uac {
         log::info!("[ADVANCED] 禁用UAC");
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
             "EnableLUA",
             0,
         );
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
             "ConsentPromptBehaviorAdmin",
             0,
@@ -177,25 +181,39 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     if config.disable_device_encryption {
         log::info!("[ADVANCED] 禁用自动设备加密");
         // 禁用 BitLocker 自动加密
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-sys\\ControlSet001\\Control\\BitLocker",
             "PreventDeviceEncryption",
             1,
         );
         // 禁用 MBAM (Microsoft BitLocker Administration and Monitoring)
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-soft\\Policies\\Microsoft\\FVE",
             "OSRecovery",
             0,
         );
         // 禁用 BitLocker 服务
-        let _ = OfflineRegistry::set_dword(
+        let _ = registry.set_dword(
             "HKLM\\pc-sys\\ControlSet001\\Services\\BDESVC",
             "Start",
             4,
         );
     }
 
+    // Windows 11 硬件要求绕过
+    if config.bypass_win11_check {
+        log::info!("[ADVANCED] 写入 Windows 11 硬件检查绕过键");
+        let labconfig = format!("HKLM\\pc-sys\\{}", win11::LABCONFIG_KEY);
+        for value in win11::LABCONFIG_VALUES {
+            let _ = registry.set_dword(&labconfig, value, 1);
+        }
+        let _ = registry.set_dword(
+            &format!("HKLM\\pc-sys\\{}", win11::MOSETUP_KEY),
+            win11::MOSETUP_VALUE,
+            1,
+        );
+    }
+
     // 9. 删除预装UWP应用 - 生成PowerShell脚本
     if config.remove_uwp_apps {
         log::info!("[ADVANCED] 配置删除预装UWP应用");
@@ -216,10 +234,10 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
     // 卸载注册表（确保正确卸载）
     log::info!("[ADVANCED] 卸载离线注册表...");
     std::thread::sleep(std::time::Duration::from_millis(500));
-    let _ = OfflineRegistry::unload_hive("pc-soft");
-    let _ = OfflineRegistry::unload_hive("pc-sys");
+    let _ = registry.unload_hive("pc-soft");
+    let _ = registry.unload_hive("pc-sys");
     if default_loaded {
-        let _ = OfflineRegistry::unload_hive("pc-default");
+        let _ = registry.unload_hive("pc-default");
     }
 
     log::info!("[ADVANCED] 高级选项应用完成");
@@ -304,7 +322,3 @@ pub fn copy_dir_all(src: &str, dst: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// 获取脚本目录名称
-pub fn get_scripts_dir_name() -> &'static str {
-    SCRIPTS_DIR
-}
@@ -1,2 +1,3 @@
 pub mod progress;
 pub mod advanced_options;
+pub mod resume;
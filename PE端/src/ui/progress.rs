@@ -1,112 +1,6 @@
 use egui::{Color32, RichText};
 
-/// 安装/备份步骤
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum InstallStep {
-    FormatPartition,
-    ApplyImage,
-    ImportDrivers,
-    RepairBoot,
-    ApplyAdvancedOptions,
-    GenerateUnattend,
-    Cleanup,
-    Complete,
-}
-
-impl InstallStep {
-    pub fn name(&self) -> &'static str {
-        match self {
-            InstallStep::FormatPartition => "格式化分区",
-            InstallStep::ApplyImage => "释放系统镜像",
-            InstallStep::ImportDrivers => "导入驱动",
-            InstallStep::RepairBoot => "修复引导",
-            InstallStep::ApplyAdvancedOptions => "应用高级选项",
-            InstallStep::GenerateUnattend => "生成无人值守配置",
-            InstallStep::Cleanup => "清理临时文件",
-            InstallStep::Complete => "完成安装",
-        }
-    }
-
-    pub fn index(&self) -> usize {
-        match self {
-            InstallStep::FormatPartition => 0,
-            InstallStep::ApplyImage => 1,
-            InstallStep::ImportDrivers => 2,
-            InstallStep::RepairBoot => 3,
-            InstallStep::ApplyAdvancedOptions => 4,
-            InstallStep::GenerateUnattend => 5,
-            InstallStep::Cleanup => 6,
-            InstallStep::Complete => 7,
-        }
-    }
-
-    pub fn total() -> usize {
-        8
-    }
-
-    pub fn all() -> Vec<InstallStep> {
-        vec![
-            InstallStep::FormatPartition,
-            InstallStep::ApplyImage,
-            InstallStep::ImportDrivers,
-            InstallStep::RepairBoot,
-            InstallStep::ApplyAdvancedOptions,
-            InstallStep::GenerateUnattend,
-            InstallStep::Cleanup,
-            InstallStep::Complete,
-        ]
-    }
-}
-
-/// 备份步骤
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum BackupStep {
-    ReadConfig,
-    CaptureImage,
-    VerifyBackup,
-    RepairBoot,
-    Cleanup,
-    Complete,
-}
-
-impl BackupStep {
-    pub fn name(&self) -> &'static str {
-        match self {
-            BackupStep::ReadConfig => "读取配置",
-            BackupStep::CaptureImage => "执行DISM备份",
-            BackupStep::VerifyBackup => "验证备份文件",
-            BackupStep::RepairBoot => "恢复引导",
-            BackupStep::Cleanup => "清理临时文件",
-            BackupStep::Complete => "备份完成",
-        }
-    }
-
-    pub fn index(&self) -> usize {
-        match self {
-            BackupStep::ReadConfig => 0,
-            BackupStep::CaptureImage => 1,
-            BackupStep::VerifyBackup => 2,
-            BackupStep::RepairBoot => 3,
-            BackupStep::Cleanup => 4,
-            BackupStep::Complete => 5,
-        }
-    }
-
-    pub fn total() -> usize {
-        6
-    }
-
-    pub fn all() -> Vec<BackupStep> {
-        vec![
-            BackupStep::ReadConfig,
-            BackupStep::CaptureImage,
-            BackupStep::VerifyBackup,
-            BackupStep::RepairBoot,
-            BackupStep::Cleanup,
-            BackupStep::Complete,
-        ]
-    }
-}
+pub use letrecovery_common::workflow::{BackupStep, InstallStep};
 
 /// 步骤状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -132,6 +26,8 @@ pub struct ProgressState {
     pub overall_progress: u8,
     /// 状态消息
     pub status_message: String,
+    /// 一直显示的警告（如回滚后失效的引导项）
+    pub warning_message: Option<String>,
     /// 是否已完成
     pub is_completed: bool,
     /// 是否失败
@@ -149,6 +45,7 @@ impl Default for ProgressState {
             step_progress: 0,
             overall_progress: 0,
             status_message: String::new(),
+            warning_message: None,
             is_completed: false,
             is_failed: false,
             error_message: None,
@@ -193,19 +90,11 @@ impl ProgressState {
 
     /// 更新总体进度
     fn update_overall_progress(&mut self) {
-        if self.is_install_mode {
-            let step_idx = self.current_install_step.index();
-            let total = InstallStep::total();
-            let base = (step_idx * 100) / total;
-            let step_contribution = (self.step_progress as usize) / total;
-            self.overall_progress = (base + step_contribution).min(100) as u8;
+        self.overall_progress = if self.is_install_mode {
+            self.current_install_step.overall_progress(self.step_progress)
         } else {
-            let step_idx = self.current_backup_step.index();
-            let total = BackupStep::total();
-            let base = (step_idx * 100) / total;
-            let step_contribution = (self.step_progress as usize) / total;
-            self.overall_progress = (base + step_contribution).min(100) as u8;
-        }
+            self.current_backup_step.overall_progress(self.step_progress)
+        };
     }
 
     /// 标记完成
@@ -308,6 +197,15 @@ impl ProgressUI {
                 );
             }
 
+            if let Some(ref warning) = state.warning_message {
+                ui.add_space(20.0);
+                ui.label(
+                    RichText::new(warning)
+                        .size(14.0)
+                        .color(Color32::from_rgb(255, 165, 0)),
+                );
+            }
+
             // 错误信息
             if let Some(ref error) = state.error_message {
                 ui.add_space(20.0);
@@ -340,7 +238,8 @@ impl ProgressUI {
     fn show_install_steps(ui: &mut egui::Ui, state: &ProgressState) {
         let current_idx = state.current_install_step.index();
 
-        for step in InstallStep::all() {
+        // PE中没有可导出驱动的系统，不显示该步骤
+        for step in InstallStep::all().iter().filter(|s| **s != InstallStep::ExportDrivers) {
             let idx = step.index();
             let status = if state.is_failed && idx == current_idx {
                 StepStatus::Failed
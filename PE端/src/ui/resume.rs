@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+use egui::{Color32, RichText};
+
+/// 无人操作时自动继续的等待时间
+const AUTO_RESUME_DELAY: Duration = Duration::from_secs(30);
+
+/// 目标分区已被改动时回滚的提示
+pub const STALE_BOOT_ENTRY_WARNING: &str =
+    "⚠ 目标分区已被格式化或覆盖，回滚后指向该分区的原系统引导项已失效，请重新安装系统或在引导菜单中删除这些引导项";
+
+/// 用户对未完成流程的选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeChoice {
+    /// 从中断的步骤继续
+    Resume,
+    /// 恢复修复引导前的引导项并放弃本次操作
+    Rollback,
+}
+
+/// 检测到未完成流程时的提示
+#[derive(Debug, Clone)]
+pub struct ResumePrompt {
+    /// 是否为安装模式（否则为备份模式）
+    pub is_install_mode: bool,
+    /// 中断时所在的步骤名称
+    pub step_name: String,
+    /// 上次失败的错误信息（断电等中断时为空）
+    pub last_error: Option<String>,
+    /// 目标分区已被格式化或覆盖，原系统的引导项回滚后仍指向该分区
+    pub target_modified: bool,
+    /// 自动继续的时间点，上次失败时不自动继续
+    auto_resume_at: Option<Instant>,
+}
+
+impl ResumePrompt {
+    pub fn new(is_install_mode: bool, step_name: &str, last_error: Option<String>) -> Self {
+        let auto_resume_at = if last_error.is_none() {
+            Some(Instant::now() + AUTO_RESUME_DELAY)
+        } else {
+            None
+        };
+        Self {
+            is_install_mode,
+            step_name: step_name.to_string(),
+            last_error,
+            target_modified: false,
+            auto_resume_at,
+        }
+    }
+
+    /// 绘制提示，返回用户的选择
+    pub fn show(&self, ui: &mut egui::Ui) -> Option<ResumeChoice> {
+        let mut choice = None;
+        let operation = if self.is_install_mode { "安装" } else { "备份" };
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            ui.heading(
+                RichText::new(format!("检测到未完成的{}", operation))
+                    .size(22.0)
+                    .strong(),
+            );
+
+            ui.add_space(20.0);
+            ui.label(
+                RichText::new(format!("上次{}在 [{}] 步骤中断", operation, self.step_name))
+                    .size(16.0)
+                    .color(Color32::from_rgb(100, 180, 255)),
+            );
+
+            if let Some(ref error) = self.last_error {
+                ui.add_space(10.0);
+                ui.label(
+                    RichText::new(format!("错误: {}", error))
+                        .size(14.0)
+                        .color(Color32::from_rgb(255, 100, 100)),
+                );
+            }
+
+            ui.add_space(30.0);
+
+            if ui
+                .button(RichText::new(format!("继续{}", operation)).size(16.0))
+                .clicked()
+            {
+                choice = Some(ResumeChoice::Resume);
+            }
+            ui.add_space(10.0);
+            if ui
+                .button(RichText::new("回滚引导项并退出").size(16.0))
+                .clicked()
+            {
+                choice = Some(ResumeChoice::Rollback);
+            }
+
+            ui.add_space(20.0);
+            ui.label(
+                RichText::new("回滚会恢复修复引导之前的引导项，并删除本次的PE引导项")
+                    .size(13.0)
+                    .color(Color32::from_rgb(180, 180, 180)),
+            );
+            if self.target_modified {
+                ui.add_space(6.0);
+                ui.label(
+                    RichText::new(STALE_BOOT_ENTRY_WARNING)
+                        .size(13.0)
+                        .color(Color32::from_rgb(255, 165, 0)),
+                );
+            }
+
+            if let Some(deadline) = self.auto_resume_at {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    choice = choice.or(Some(ResumeChoice::Resume));
+                }
+                ui.add_space(10.0);
+                ui.label(
+                    RichText::new(format!("{} 秒后自动继续", remaining.as_secs()))
+                        .size(13.0)
+                        .color(Color32::from_rgb(180, 180, 180)),
+                );
+            }
+        });
+
+        choice
+    }
+}
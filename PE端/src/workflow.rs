@@ -0,0 +1,401 @@
+//! PE 端的安装与备份流程
+//!
+//! 步骤顺序、跳过条件和断点续装由公共库的工作流负责，这里读取数据分区上的
+//! 配置和步骤日志，并用 PE 中的 DISM、Ghost、bcdedit 完成各步骤。
+//! 图形界面和命令行模式都调用本模块，只是显示事件的方式不同。
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use letrecovery_common::backup::compression::{BackupFormat, Compression};
+use letrecovery_common::command::SystemRunner;
+use letrecovery_common::backup::repository::Repository;
+use letrecovery_common::disk_layout::Firmware;
+use letrecovery_common::journal::Journal;
+use letrecovery_common::preflight::{self, Severity};
+use letrecovery_common::software;
+use letrecovery_common::tools::{CaptureOptions, Tools};
+use letrecovery_common::unattend::MergeConflict;
+use letrecovery_common::workflow::{
+    self, forward_progress, BackupBackend, BackupPlan, BackupStep, EventSink, InstallBackend,
+    InstallPlan, InstallStep, Progress, TargetPreparation, WorkflowEvent,
+};
+
+use crate::core::bcdedit::BootManager;
+use crate::core::config::{BackupConfig, ConfigFileManager, InstallConfig, OperationType};
+use crate::core::disk::DiskManager;
+use crate::core::dism::{Dism, DismProgress};
+use crate::core::ghost::Ghost;
+use crate::core::registry::OfflineRegistry;
+use crate::ui::advanced_options::apply_advanced_options;
+
+/// 日志中记录的已解析目标分区（格式化后目标分区上的标记文件不复存在）
+const TARGET_PARTITION_KEY: &str = "TargetPartition";
+/// 日志中记录的已解析源分区
+const SOURCE_PARTITION_KEY: &str = "SourcePartition";
+
+/// 执行安装，成功时返回安装配置
+///
+/// 每个步骤的开始、完成和失败都记录到数据分区上的步骤日志中，
+/// 中途断电后重新进入PE时从中断的步骤继续。读取配置失败时同样发出失败事件。
+pub fn run_install(sink: &dyn EventSink<InstallStep>) -> Result<InstallConfig> {
+    let (plan, backend, mut journal) = match prepare_install() {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let message = format!("{:#}", e);
+            sink.emit(WorkflowEvent::Failed(message.clone()));
+            anyhow::bail!(message);
+        }
+    };
+
+    workflow::run_install(&plan, &backend, sink, Some(&mut journal))?;
+    Ok(backend.config)
+}
+
+/// 执行备份，成功时返回备份配置
+pub fn run_backup(sink: &dyn EventSink<BackupStep>) -> Result<BackupConfig> {
+    let (plan, backend, mut journal) = match prepare_backup() {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let message = format!("{:#}", e);
+            sink.emit(WorkflowEvent::Failed(message.clone()));
+            anyhow::bail!(message);
+        }
+    };
+
+    workflow::run_backup(&plan, &backend, sink, Some(&mut journal))?;
+    Ok(backend.config)
+}
+
+fn prepare_install() -> Result<(InstallPlan, PeInstallBackend, Journal<InstallStep>)> {
+    let data_partition = ConfigFileManager::find_data_partition().context("未找到安装配置文件")?;
+    log::info!("数据分区: {}", data_partition);
+
+    let config = ConfigFileManager::read_install_config(&data_partition).context("读取配置失败")?;
+    log::info!("目标分区: {}", config.target_partition);
+    log::info!("镜像文件: {}", config.image_path);
+    if !config.profile.is_empty() {
+        log::info!("部署方案: {}", config.profile);
+    }
+
+    let mut journal = Journal::open(
+        ConfigFileManager::journal_path(&data_partition),
+        OperationType::Install.journal_name(),
+    )
+    .context("读取步骤日志失败")?;
+
+    // 查找安装标记分区，续装时使用日志中记录的分区
+    let target_partition = match journal.value(TARGET_PARTITION_KEY) {
+        Some(p) => p.to_string(),
+        None => {
+            let p = ConfigFileManager::find_install_marker_partition()
+                .unwrap_or_else(|| config.target_partition.clone());
+            if let Err(e) = journal.set_value(TARGET_PARTITION_KEY, &p) {
+                log::warn!("写入步骤日志失败: {}", e);
+            }
+            p
+        }
+    };
+
+    // 目标分区会被格式化，数据分区上的镜像和配置不能在其中
+    let location = preflight::check_data_location(
+        data_partition.chars().next(),
+        target_partition.chars().next().unwrap_or('C'),
+        true,
+    );
+    if location.severity == Severity::Blocking {
+        anyhow::bail!(location.message);
+    }
+
+    let tools = crate::core::tools(Arc::new(SystemRunner));
+    let disk = DiskManager::with_runner(tools.runner());
+
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let image_path = format!("{}\\{}", data_dir, config.image_path);
+    log::info!("完整镜像路径: {}", image_path);
+
+    let plan = InstallPlan {
+        target_partition,
+        image_path,
+        volume_index: config.volume_index,
+        is_gho: config.is_gho,
+        preparation: TargetPreparation::Format,
+        driver_dir: config.restore_drivers.then(|| format!("{}\\drivers", data_dir)),
+        export_drivers: false,
+        boot_firmware: Some(if disk.detect_uefi_mode() {
+            Firmware::Uefi
+        } else {
+            Firmware::Bios
+        }),
+        unattended: config.unattended,
+    };
+    let backend = PeInstallBackend {
+        config,
+        data_partition,
+        data_dir,
+        tools,
+        disk,
+    };
+    Ok((plan, backend, journal))
+}
+
+fn prepare_backup() -> Result<(BackupPlan, PeBackupBackend, Journal<BackupStep>)> {
+    let data_partition = ConfigFileManager::find_data_partition().context("未找到备份配置文件")?;
+    log::info!("数据分区: {}", data_partition);
+
+    let config = ConfigFileManager::read_backup_config(&data_partition).context("读取配置失败")?;
+    log::info!("源分区: {}", config.source_partition);
+    log::info!("保存路径: {}", config.save_path);
+
+    let mut journal = Journal::open(
+        ConfigFileManager::journal_path(&data_partition),
+        OperationType::Backup.journal_name(),
+    )
+    .context("读取步骤日志失败")?;
+
+    // 查找备份标记分区，续备时使用日志中记录的分区
+    let source_partition = match journal.value(SOURCE_PARTITION_KEY) {
+        Some(p) => p.to_string(),
+        None => {
+            let p = ConfigFileManager::find_backup_marker_partition()
+                .unwrap_or_else(|| config.source_partition.clone());
+            if let Err(e) = journal.set_value(SOURCE_PARTITION_KEY, &p) {
+                log::warn!("写入步骤日志失败: {}", e);
+            }
+            p
+        }
+    };
+
+    let plan = BackupPlan {
+        source_partition,
+        save_path: config.save_path.clone(),
+        name: config.name.clone(),
+        description: config.description.clone(),
+        format: config.format,
+        incremental: config.incremental,
+        verify: config.verify,
+        retention: config.retention,
+        exclusions: config.exclusions.clone(),
+        compression: config.compression,
+        export_esd: config.export_esd,
+    };
+    let tools = crate::core::tools(Arc::new(SystemRunner));
+    let backend = PeBackupBackend {
+        config,
+        data_partition,
+        disk: DiskManager::with_runner(tools.runner()),
+        tools,
+    };
+    Ok((plan, backend, journal))
+}
+
+struct PeInstallBackend {
+    config: InstallConfig,
+    data_partition: String,
+    data_dir: String,
+    /// 外部工具，所有命令经由其中的执行器运行
+    tools: Tools,
+    disk: DiskManager,
+}
+
+impl InstallBackend for PeInstallBackend {
+    fn prepare_target(&self, plan: &InstallPlan) -> Result<()> {
+        self.disk.format_partition(&plan.target_partition)?;
+        log::info!("分区格式化成功");
+        Ok(())
+    }
+
+    fn export_drivers(&self, _driver_dir: &str) -> Result<()> {
+        anyhow::bail!("PE环境中没有可导出驱动的系统")
+    }
+
+    fn apply_image(&self, plan: &InstallPlan, progress: Progress) -> Result<()> {
+        if plan.is_gho {
+            // GHO镜像使用Ghost
+            let ghost = Ghost::with_runner(self.tools.runner());
+            if !ghost.is_available() {
+                anyhow::bail!("Ghost工具不可用");
+            }
+            let partitions = self.disk.get_partitions().unwrap_or_default();
+            forward_progress(progress, dism_percentage, |tx| {
+                ghost.restore_image_to_letter(&plan.image_path, &plan.target_partition, &partitions, Some(tx))
+            })
+        } else {
+            // WIM/ESD使用DISM
+            let apply_dir = format!("{}\\", plan.target_partition);
+            self.tools.apply_image(&plan.image_path, &apply_dir, plan.volume_index, progress)
+        }
+    }
+
+    fn import_drivers(&self, target_partition: &str, driver_dir: &str) -> Result<()> {
+        Dism::with_runner(self.tools.runner()).add_drivers_offline(&format!("{}\\", target_partition), driver_dir)
+    }
+
+    fn repair_boot(&self, target_partition: &str, firmware: Firmware) -> Result<()> {
+        let boot_manager = BootManager::with_runner(self.tools.runner());
+        backup_boot_store(&boot_manager, &self.data_partition);
+        boot_manager.repair_boot_advanced(target_partition, firmware == Firmware::Uefi)
+    }
+
+    fn apply_advanced_options(&self, target_partition: &str) -> Result<()> {
+        let registry = OfflineRegistry::with_runner(self.tools.runner());
+        apply_advanced_options(target_partition, &self.config, &registry)?;
+
+        // 数据目录中暂存的装机软件
+        if self.config.install_software {
+            let scripts_dir = format!("{}\\{}", target_partition, letrecovery_common::SCRIPTS_DIR);
+            if software::copy_staged(Path::new(&self.data_dir), Path::new(&scripts_dir))? {
+                log::info!("[ADVANCED] 装机软件已复制到: {}", scripts_dir);
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_unattend(&self, target_partition: &str) -> Result<Vec<MergeConflict>> {
+        generate_unattend_xml(target_partition, &self.config, &self.data_dir)
+    }
+
+    fn cleanup(&self, plan: &InstallPlan) -> Result<()> {
+        ConfigFileManager::cleanup_all(&self.data_partition, &plan.target_partition);
+
+        // 清理自动创建的数据分区并扩展目标分区
+        self.disk
+            .cleanup_auto_created_partition_and_extend(&plan.target_partition)
+            .context("清理自动创建的分区失败")?;
+        log::info!("自动创建分区清理完成");
+        Ok(())
+    }
+}
+
+struct PeBackupBackend {
+    config: BackupConfig,
+    data_partition: String,
+    tools: Tools,
+    disk: DiskManager,
+}
+
+impl BackupBackend for PeBackupBackend {
+    fn capture_image(&self, plan: &BackupPlan, append: bool, progress: Progress) -> Result<()> {
+        if let BackupFormat::Gho(level) = plan.format {
+            // GHO使用Ghost，按盘符解析磁盘号和分区号
+            let ghost = Ghost::with_runner(self.tools.runner());
+            if !ghost.is_available() {
+                anyhow::bail!("Ghost工具不可用");
+            }
+            let partitions = self.disk.get_partitions().unwrap_or_default();
+            return forward_progress(progress, dism_percentage, |tx| {
+                ghost.create_image_from_letter(&plan.source_partition, &partitions, &plan.save_path, level, Some(tx))
+            });
+        }
+
+        if plan.format.is_dedup() {
+            // 去重仓库直接读取源分区中的文件，不经过 DISM
+            let repository = Repository::open_or_create(Path::new(&plan.save_path))?;
+            let source = format!("{}\\", plan.source_partition);
+            let summary =
+                repository.backup(Path::new(&source), &plan.name, &plan.description, &plan.exclusions, progress)?;
+            log::info!("{}", summary.describe());
+            for skipped in &summary.skipped {
+                log::warn!("跳过 {}", skipped);
+            }
+            return Ok(());
+        }
+
+        let capture_dir = format!("{}\\", plan.source_partition);
+        // 配置文件在捕获结束后删除
+        let config_file = plan.exclusions.write_temp()?;
+        let options = CaptureOptions {
+            config_file: Some(config_file.path().to_string_lossy().into_owned()),
+            compression: plan.compression,
+        };
+        let (image, name, description) = (&plan.save_path, &plan.name, &plan.description);
+        if append {
+            self.tools.append_image(image, &capture_dir, name, description, &options, progress)
+        } else {
+            self.tools.capture_image(image, &capture_dir, name, description, &options, progress)
+        }
+    }
+
+    fn export_image(&self, source: &Path, index: u32, destination: &Path, compression: Compression) -> Result<()> {
+        // 追加到已有镜像时沿用其压缩方式
+        let compress = (!destination.exists()).then_some(compression.to_config_value());
+        let (source, destination) = (source.to_string_lossy(), destination.to_string_lossy());
+        self.tools.export_image(&source, index, &destination, compress, &|_| {})
+    }
+
+    fn export_esd(&self, source: &Path, index: u32, destination: &Path, progress: Progress) -> Result<()> {
+        // 追加到已有的 ESD 时沿用其压缩方式
+        let compress = (!destination.exists()).then_some("recovery");
+        let (source, destination) = (source.to_string_lossy(), destination.to_string_lossy());
+        self.tools.export_image(&source, index, &destination, compress, progress)
+    }
+
+    fn restore_boot(&self) -> Result<()> {
+        let boot_manager = BootManager::with_runner(self.tools.runner());
+        backup_boot_store(&boot_manager, &self.data_partition);
+        // 删除当前PE引导项
+        boot_manager.delete_current_boot_entry()
+    }
+
+    fn cleanup(&self, plan: &BackupPlan) -> Result<()> {
+        ConfigFileManager::cleanup_partition_markers(&plan.source_partition);
+        ConfigFileManager::cleanup_data_dir(&self.data_partition);
+        ConfigFileManager::cleanup_pe_dir(&self.data_partition);
+        Ok(())
+    }
+}
+
+fn dism_percentage(progress: &DismProgress) -> u8 {
+    progress.percentage
+}
+
+/// 修复引导前导出当前 BCD，供回滚使用
+///
+/// 已有备份时不覆盖：中断后重新执行时，当前存储可能已被改动过。
+fn backup_boot_store(boot_manager: &BootManager, data_partition: &str) {
+    let backup_path = ConfigFileManager::bcd_backup_path(data_partition);
+    let backup_path = Path::new(&backup_path);
+    if backup_path.exists() {
+        return;
+    }
+    match boot_manager.export_store(backup_path) {
+        Ok(_) => log::info!("已备份引导配置: {}", backup_path.display()),
+        Err(e) => log::warn!("备份引导配置失败: {}", e),
+    }
+}
+
+/// 生成无人值守XML
+///
+/// 包含完整的无人值守配置：
+/// - windowsPE pass: 基本设置
+/// - specialize pass: 计算机名、时区、产品密钥、部署脚本执行
+/// - oobeSystem pass: 区域设置、OOBE设置、用户账户、自动登录、首次登录命令
+///
+/// 配置中指定了用户应答文件时，只把部署脚本和首次登录命令合并到该文件中，
+/// 其余设置以该文件为准，返回合并冲突。
+fn generate_unattend_xml(
+    target_partition: &str,
+    config: &InstallConfig,
+    data_dir: &str,
+) -> Result<Vec<MergeConflict>> {
+    use letrecovery_common::unattend::{render_answer_file, write_answer_file, AnswerFileOptions, Architecture};
+
+    let architecture = Architecture::detect_from_system(target_partition).unwrap_or_else(|| {
+        log::warn!("[UNATTEND] 无法识别目标系统架构，使用默认值 amd64");
+        Architecture::default()
+    });
+
+    let options = AnswerFileOptions {
+        architecture,
+        ..config.answer_file_options()
+    };
+
+    let user_file = config.unattend_file_path(data_dir);
+    if let Some(path) = &user_file {
+        log::info!("[UNATTEND] 合并用户应答文件: {}", path);
+    }
+    let rendered = render_answer_file(&options, user_file.as_deref())?;
+    write_answer_file(target_partition, &rendered.xml)?;
+    Ok(rendered.conflicts)
+}
@@ -0,0 +1,154 @@
+//! 备份目录
+//!
+//! 增量备份把每次备份追加为同一个 WIM 中的新卷。目录直接从镜像的 XML 描述
+//! 读取每一卷的索引、名称、时间和大小。WIM 不记录来源计算机，备份时把计算机
+//! 名以 `[来源: 名称]` 的形式附加在卷描述末尾，读取目录时再拆分出来。
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::regf::Hive;
+use crate::wim::{self, WimImage};
+
+/// 卷描述中来源计算机标记的前缀
+const SOURCE_TAG: &str = "[来源: ";
+
+/// 目录中的一次备份
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CatalogEntry {
+    pub index: u32,
+    pub name: String,
+    /// 去掉来源标记后的描述
+    pub description: String,
+    /// 备份时间（FILETIME，UTC）
+    pub created: Option<u64>,
+    pub source_machine: Option<String>,
+    /// 卷中文件的总字节数（未压缩）
+    pub size: u64,
+}
+
+impl From<WimImage> for CatalogEntry {
+    fn from(image: WimImage) -> Self {
+        let (description, source_machine) = split_description(&image.description);
+        Self {
+            index: image.index,
+            name: image.name,
+            description,
+            created: image.creation_time,
+            source_machine,
+            size: image.total_bytes,
+        }
+    }
+}
+
+/// 读取镜像中全部备份，按索引排序
+pub fn read_catalog(image_path: &Path) -> Result<Vec<CatalogEntry>> {
+    let (_, images) = wim::read_images(image_path)?;
+    Ok(images.into_iter().map(CatalogEntry::from).collect())
+}
+
+/// 在卷描述末尾附加来源计算机
+pub fn tag_description(description: &str, machine: &str) -> String {
+    let description = description.trim();
+    if description.is_empty() {
+        format!("{}{}]", SOURCE_TAG, machine)
+    } else {
+        format!("{} {}{}]", description, SOURCE_TAG, machine)
+    }
+}
+
+/// 拆分卷描述和其中的来源计算机
+pub fn split_description(text: &str) -> (String, Option<String>) {
+    let text = text.trim();
+    match text.rfind(SOURCE_TAG) {
+        Some(start) if text.ends_with(']') => {
+            let machine = &text[start + SOURCE_TAG.len()..text.len() - 1];
+            (text[..start].trim_end().to_string(), Some(machine.to_string()))
+        }
+        _ => (text.to_string(), None),
+    }
+}
+
+/// 从 SYSTEM 配置单元读取计算机名
+pub fn computer_name(system_hive: &Path) -> Result<Option<String>> {
+    let hive = Hive::open(system_hive)?;
+    let root = hive.root();
+    let current = root
+        .open("Select")?
+        .and_then(|k| k.value("Current").ok().flatten())
+        .and_then(|v| v.as_u32())
+        .unwrap_or(1);
+    let path = format!("ControlSet{:03}\\Control\\ComputerName\\ComputerName", current);
+    Ok(root
+        .open(&path)?
+        .and_then(|k| k.value("ComputerName").ok().flatten())
+        .and_then(|v| v.as_string())
+        .filter(|name| !name.is_empty()))
+}
+
+/// 分区上 Windows 系统的计算机名，分区上没有系统或读取失败时返回 None
+pub fn source_machine(partition: &str) -> Option<String> {
+    let hive = format!("{}\\Windows\\System32\\config\\SYSTEM", partition);
+    match computer_name(Path::new(&hive)) {
+        Ok(name) => name,
+        Err(e) => {
+            log::debug!("读取 {} 的计算机名失败: {}", partition, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regf::writer::{build, utf16z, TestKey};
+    use crate::regf::{REG_DWORD, REG_SZ};
+    use crate::wim::build_test_wim;
+
+    #[test]
+    fn test_description_tag() {
+        let tagged = tag_description("每周备份", "PC-01");
+        assert_eq!(tagged, "每周备份 [来源: PC-01]");
+        assert_eq!(split_description(&tagged), ("每周备份".to_string(), Some("PC-01".to_string())));
+        assert_eq!(split_description(&tag_description("", "PC-01")), (String::new(), Some("PC-01".to_string())));
+        assert_eq!(split_description("[草稿] 测试"), ("[草稿] 测试".to_string(), None));
+    }
+
+    #[test]
+    fn test_read_catalog_and_computer_name() {
+        let dir = std::env::temp_dir().join(format!("lr_catalog_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let xml = "<WIM><IMAGE INDEX=\"1\"><TOTALBYTES>2048</TOTALBYTES>\
+            <CREATIONTIME><HIGHPART>0x01DA6B2F</HIGHPART><LOWPART>0x00000000</LOWPART></CREATIONTIME>\
+            <NAME>系统备份_1</NAME><DESCRIPTION>首次 [来源: PC-01]</DESCRIPTION></IMAGE></WIM>";
+        let image = dir.join("backup.wim");
+        std::fs::write(&image, build_test_wim(xml, &[0; 16], None)).unwrap();
+        let catalog = read_catalog(&image).unwrap();
+        assert_eq!(
+            catalog,
+            [CatalogEntry {
+                index: 1,
+                name: "系统备份_1".to_string(),
+                description: "首次".to_string(),
+                created: Some(0x01DA6B2F_00000000),
+                source_machine: Some("PC-01".to_string()),
+                size: 2048,
+            }]
+        );
+
+        let system = TestKey::new("ROOT")
+            .key(TestKey::new("Select").value("Current", REG_DWORD, 2u32.to_le_bytes().to_vec()))
+            .key(TestKey::new("ControlSet002").key(TestKey::new("Control").key(
+                TestKey::new("ComputerName").key(
+                    TestKey::new("ComputerName").value("ComputerName", REG_SZ, utf16z("OFFICE-PC")),
+                ),
+            )));
+        let hive = dir.join("SYSTEM");
+        std::fs::write(&hive, build(&system)).unwrap();
+        assert_eq!(computer_name(&hive).unwrap().as_deref(), Some("OFFICE-PC"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+//! 备份格式、压缩方式和预估
+//!
+//! 默认用 DISM 捕获为 WIM，可选不压缩、快速压缩（XPRESS）和最大压缩（LZX）。
+//! 另可在备份后把新卷导出为 ESD（LZMS 固实压缩，即 DISM 的 recovery 压缩）
+//! 作为归档副本，体积最小但导出很慢，且 ESD 不能再追加捕获或挂载修改。
+//!
+//! 也可以用 Ghost 把整个分区备份为 GHO，压缩级别 1-9。GHO 不支持追加、
+//! 排除列表、验证和保留策略。或者备份到去重仓库（见 [`super::repository`]），
+//! 保存位置是一个目录。
+//!
+//! 开始前根据源分区已用空间粗略估计镜像大小和耗时。压缩率和速度按一般
+//! Windows 系统分区估计，大量已压缩文件（视频、压缩包）时镜像会偏大。
+
+use std::path::Path;
+
+/// ESD 副本的扩展名
+pub const ESD_EXTENSION: &str = "esd";
+
+/// 捕获时的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    None,
+    Fast,
+    #[default]
+    Max,
+}
+
+impl Compression {
+    pub fn all() -> &'static [Compression] {
+        &[Compression::None, Compression::Fast, Compression::Max]
+    }
+
+    /// 解析配置中的 "none"、"fast" 或 "max"
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "fast" => Some(Self::Fast),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    /// 转换为配置文件中的形式，也是 DISM `/Compress:` 的参数
+    pub fn to_config_value(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Fast => "fast",
+            Self::Max => "max",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "不压缩",
+            Self::Fast => "快速压缩",
+            Self::Max => "最大压缩",
+        }
+    }
+
+    /// 按 WIM 文件头标志判断镜像的压缩方式，LZMS（ESD）等其他方式返回 None
+    pub fn from_wim_flags(flags: u32) -> Option<Self> {
+        const FLAG_COMPRESSION: u32 = 0x2;
+        const FLAG_XPRESS: u32 = 0x2_0000;
+        const FLAG_LZX: u32 = 0x4_0000;
+        if flags & FLAG_COMPRESSION == 0 {
+            return Some(Self::None);
+        }
+        match flags & (FLAG_XPRESS | FLAG_LZX | 0x8_0000) {
+            FLAG_XPRESS => Some(Self::Fast),
+            FLAG_LZX => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    /// 镜像大小占源数据的百分比
+    fn ratio_percent(&self) -> u64 {
+        match self {
+            Self::None => 100,
+            Self::Fast => 60,
+            Self::Max => 50,
+        }
+    }
+
+    /// 捕获速度（每秒处理的源数据，MB）
+    fn speed_mb(&self) -> u64 {
+        match self {
+            Self::None => 120,
+            Self::Fast => 80,
+            Self::Max => 40,
+        }
+    }
+}
+
+/// 备份格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackupFormat {
+    /// DISM 捕获的 WIM 镜像
+    #[default]
+    Wim,
+    /// Ghost 分区镜像，参数为压缩级别 (1-9)
+    Gho(u8),
+    /// 去重备份仓库（目录）
+    Dedup,
+}
+
+impl BackupFormat {
+    /// Ghost 的默认压缩级别（-z9 很慢，压缩率提高有限）
+    pub const DEFAULT_GHOST_LEVEL: u8 = 5;
+
+    /// 解析配置中的 "wim"、"gho"、"gho:级别" 或 "dedup"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        let (kind, level) = match text.split_once(':') {
+            Some((kind, level)) => (kind.trim(), Some(level.trim())),
+            None => (text.as_str(), None),
+        };
+        match (kind, level) {
+            ("wim", None) => Some(Self::Wim),
+            ("dedup", None) => Some(Self::Dedup),
+            ("gho", None) => Some(Self::Gho(Self::DEFAULT_GHOST_LEVEL)),
+            ("gho", Some(level)) => level.parse().ok().filter(|l| (1..=9).contains(l)).map(Self::Gho),
+            _ => None,
+        }
+    }
+
+    /// 转换为配置文件中的形式
+    pub fn to_config_value(&self) -> String {
+        match self {
+            Self::Wim => "wim".to_string(),
+            Self::Gho(level) => format!("gho:{}", level),
+            Self::Dedup => "dedup".to_string(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Wim => "WIM (DISM)",
+            Self::Gho(_) => "GHO (Ghost)",
+            Self::Dedup => "去重仓库",
+        }
+    }
+
+    /// 镜像文件的扩展名，仓库是目录，没有扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wim => "wim",
+            Self::Gho(_) => "gho",
+            Self::Dedup => "",
+        }
+    }
+
+    pub fn is_gho(&self) -> bool {
+        matches!(self, Self::Gho(_))
+    }
+
+    pub fn is_dedup(&self) -> bool {
+        matches!(self, Self::Dedup)
+    }
+}
+
+/// ESD 大小占源数据的百分比
+const ESD_RATIO_PERCENT: u64 = 35;
+/// 导出 ESD 的速度（每秒处理的源数据，MB）
+const ESD_SPEED_MB: u64 = 8;
+
+/// 镜像对应的 ESD 副本路径，如 "D:\backup.wim" -> "D:\backup.esd"
+pub fn esd_path(image_path: &str) -> String {
+    Path::new(image_path)
+        .with_extension(ESD_EXTENSION)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 备份预估
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupEstimate {
+    /// 新卷在镜像中增加的字节数
+    pub image_bytes: u64,
+    /// ESD 副本的字节数，不导出时为 None
+    pub esd_bytes: Option<u64>,
+    /// 预计耗时（秒）
+    pub seconds: u64,
+}
+
+impl BackupEstimate {
+    /// 按源分区已用字节数估计
+    ///
+    /// 追加到已有镜像时与之前的卷相同的文件只保存一次，实际增加的大小通常
+    /// 远小于估计值，这里不考虑。
+    pub fn new(source_bytes: u64, compression: Compression, export_esd: bool) -> Self {
+        let mb = source_bytes / 1024 / 1024;
+        let mut seconds = mb / compression.speed_mb();
+        let esd_bytes = export_esd.then(|| {
+            seconds += mb / ESD_SPEED_MB;
+            source_bytes / 100 * ESD_RATIO_PERCENT
+        });
+        Self {
+            image_bytes: source_bytes / 100 * compression.ratio_percent(),
+            esd_bytes,
+            seconds,
+        }
+    }
+
+    /// 按源分区已用字节数估计 Ghost 备份
+    pub fn for_ghost(source_bytes: u64, level: u8) -> Self {
+        let level = u64::from(level.clamp(1, 9));
+        Self {
+            image_bytes: source_bytes / 100 * (65 - level * 2),
+            esd_bytes: None,
+            seconds: source_bytes / 1024 / 1024 / (100 - level * 8),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = format!("预计镜像约 {}", format_bytes(self.image_bytes));
+        if let Some(esd_bytes) = self.esd_bytes {
+            text.push_str(&format!("，ESD 约 {}", format_bytes(esd_bytes)));
+        }
+        text.push_str(&format!("，耗时约 {}", format_duration(self.seconds)));
+        text
+    }
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const GB: u64 = 1024 * 1024 * 1024;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else {
+        format!("{} MB", bytes / 1024 / 1024)
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    let minutes = seconds.div_ceil(60).max(1);
+    if minutes < 60 {
+        format!("{} 分钟", minutes)
+    } else {
+        format!("{} 小时 {} 分钟", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let source = 40 * 1024 * 1024 * 1024;
+        let estimate = BackupEstimate::new(source, Compression::Max, false);
+        assert_eq!(estimate.esd_bytes, None);
+        assert_eq!(estimate.seconds, 1024);
+        assert_eq!(estimate.describe(), "预计镜像约 20.0 GB，耗时约 18 分钟");
+
+        let estimate = BackupEstimate::new(source, Compression::Fast, true);
+        assert_eq!(estimate.seconds, 512 + 5120);
+        assert_eq!(estimate.describe(), "预计镜像约 24.0 GB，ESD 约 14.0 GB，耗时约 1 小时 34 分钟");
+
+        assert_eq!(BackupEstimate::new(0, Compression::None, false).describe(), "预计镜像约 0 MB，耗时约 1 分钟");
+
+        let estimate = BackupEstimate::for_ghost(source, 5);
+        assert_eq!(estimate.describe(), "预计镜像约 22.0 GB，耗时约 12 分钟");
+    }
+
+    #[test]
+    fn test_parse_and_paths() {
+        for compression in Compression::all() {
+            assert_eq!(Compression::parse(compression.to_config_value()), Some(*compression));
+        }
+        assert_eq!(Compression::parse(" MAX "), Some(Compression::Max));
+        assert_eq!(Compression::parse("lzms"), None);
+        assert_eq!(Compression::from_wim_flags(0), Some(Compression::None));
+        assert_eq!(Compression::from_wim_flags(0x2_0002), Some(Compression::Fast));
+        assert_eq!(Compression::from_wim_flags(0x4_0002), Some(Compression::Max));
+        assert_eq!(Compression::from_wim_flags(0x8_0002), None);
+        assert_eq!(BackupFormat::parse("gho"), Some(BackupFormat::Gho(BackupFormat::DEFAULT_GHOST_LEVEL)));
+        assert_eq!(BackupFormat::parse("GHO:9"), Some(BackupFormat::Gho(9)));
+        assert_eq!(BackupFormat::parse("gho:0"), None);
+        assert_eq!(BackupFormat::parse("wim:3"), None);
+        assert_eq!(BackupFormat::parse("Dedup"), Some(BackupFormat::Dedup));
+        for format in [BackupFormat::Wim, BackupFormat::Gho(1), BackupFormat::Dedup] {
+            assert_eq!(BackupFormat::parse(&format.to_config_value()), Some(format));
+        }
+        assert_eq!(esd_path("D:\\备份\\system.wim"), "D:\\备份\\system.esd");
+    }
+}
@@ -0,0 +1,278 @@
+//! 备份排除列表
+//!
+//! 捕获时通过 DISM 的 `/ConfigFile` 传入 wimscript.ini：`[ExclusionList]` 中的
+//! 文件和目录不写入镜像，`[CompressionExclusionList]` 中的文件不压缩（已压缩
+//! 的格式再压缩只浪费时间）。以 `\` 开头的路径从分区根目录匹配，否则匹配任意
+//! 位置的同名文件或目录；路径的每一级都可以使用 `*` 和 `?` 通配符。
+//! 备份验证统计源分区时使用同一套规则。
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{Context, Result};
+
+/// 捕获时写入临时目录的配置文件名前缀，后接进程号和序号
+pub const CONFIG_FILE_PREFIX: &str = "LetRecovery_WimScript";
+
+/// 内置排除项：DISM 的默认列表，加上回收站、临时目录和浏览器缓存
+pub const DEFAULT_EXCLUSIONS: &[&str] = &[
+    "\\$ntfs.log",
+    "\\hiberfil.sys",
+    "\\pagefile.sys",
+    "\\swapfile.sys",
+    "\\System Volume Information",
+    "\\RECYCLER",
+    "\\$Recycle.Bin",
+    "\\Windows\\CSC",
+    "\\Windows\\Temp\\*",
+    "\\Windows\\SoftwareDistribution\\Download\\*",
+    "\\Users\\*\\AppData\\Local\\Temp\\*",
+    "\\Users\\*\\AppData\\Local\\Microsoft\\Windows\\INetCache\\*",
+    "\\Users\\*\\AppData\\Local\\Google\\Chrome\\User Data\\*\\Cache",
+    "\\Users\\*\\AppData\\Local\\Microsoft\\Edge\\User Data\\*\\Cache",
+    "\\Users\\*\\AppData\\Local\\Mozilla\\Firefox\\Profiles\\*\\cache2",
+];
+
+/// 内置不压缩项：本身已压缩的文件格式
+pub const DEFAULT_COMPRESSION_EXCLUSIONS: &[&str] = &[
+    "*.7z", "*.cab", "*.gz", "*.jpg", "*.mp3", "*.mp4", "*.rar", "*.zip",
+    "\\Windows\\inf\\*.pnf",
+];
+
+/// 捕获配置（wimscript.ini 的内容）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExclusionConfig {
+    pub exclusions: Vec<String>,
+    pub compression_exclusions: Vec<String>,
+}
+
+impl Default for ExclusionConfig {
+    /// 内置默认列表
+    fn default() -> Self {
+        Self::new(true, &[], &[])
+    }
+}
+
+impl ExclusionConfig {
+    /// 在内置列表（`use_defaults` 为 false 时为空）之后追加自定义项，重复项只保留一个
+    pub fn new(use_defaults: bool, exclusions: &[String], compression_exclusions: &[String]) -> Self {
+        let merge = |defaults: &[&str], extra: &[String]| {
+            let mut list: Vec<String> = Vec::new();
+            let defaults = defaults.iter().filter(|_| use_defaults).map(|s| s.to_string());
+            for item in defaults.chain(extra.iter().map(|s| s.trim().to_string())) {
+                if !item.is_empty() && !list.iter().any(|e| e.eq_ignore_ascii_case(&item)) {
+                    list.push(item);
+                }
+            }
+            list
+        };
+        Self {
+            exclusions: merge(DEFAULT_EXCLUSIONS, exclusions),
+            compression_exclusions: merge(DEFAULT_COMPRESSION_EXCLUSIONS, compression_exclusions),
+        }
+    }
+
+    /// 解析 wimscript.ini，忽略不认识的节
+    pub fn parse(text: &str) -> Self {
+        let mut config = Self {
+            exclusions: Vec::new(),
+            compression_exclusions: Vec::new(),
+        };
+        let mut section = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = Some(line[1..line.len() - 1].to_ascii_lowercase());
+                continue;
+            }
+            let item = line.trim_matches('"').to_string();
+            match section.as_deref() {
+                Some("exclusionlist") => config.exclusions.push(item),
+                Some("compressionexclusionlist") => config.compression_exclusions.push(item),
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// 生成 wimscript.ini 文本
+    pub fn render(&self) -> String {
+        let mut text = String::from("[ExclusionList]\r\n");
+        for item in &self.exclusions {
+            text.push_str(&quote(item));
+            text.push_str("\r\n");
+        }
+        text.push_str("\r\n[CompressionExclusionList]\r\n");
+        for item in &self.compression_exclusions {
+            text.push_str(&quote(item));
+            text.push_str("\r\n");
+        }
+        text
+    }
+
+    /// 写入配置文件（UTF-16LE 带 BOM，路径中可以有中文）
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(self.render().encode_utf16().flat_map(u16::to_le_bytes));
+        std::fs::write(path, bytes).with_context(|| format!("写入捕获配置失败 {}", path.display()))
+    }
+
+    /// 写入临时目录下每次不同名的配置文件，同时进行的多个备份互不覆盖
+    pub fn write_temp(&self) -> Result<TempConfigFile> {
+        static FILE_ID: AtomicU32 = AtomicU32::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "{}_{}_{}.ini",
+            CONFIG_FILE_PREFIX,
+            std::process::id(),
+            FILE_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.write(&path)?;
+        Ok(TempConfigFile(path))
+    }
+
+    /// 相对分区根目录的路径（以 `\` 分隔）是否被排除
+    pub fn is_excluded(&self, relative: &str) -> bool {
+        self.exclusions.iter().any(|pattern| matches(pattern, relative))
+    }
+}
+
+/// 临时目录下的捕获配置文件，离开作用域时删除
+#[derive(Debug)]
+pub struct TempConfigFile(PathBuf);
+
+impl TempConfigFile {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempConfigFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// 含空格的路径加引号
+fn quote(item: &str) -> String {
+    if item.contains(' ') {
+        format!("\"{}\"", item)
+    } else {
+        item.to_string()
+    }
+}
+
+/// 按 wimscript.ini 的规则匹配路径
+fn matches(pattern: &str, relative: &str) -> bool {
+    let pattern = pattern.trim().replace('/', "\\");
+    let path: Vec<&str> = relative.split('\\').filter(|p| !p.is_empty()).collect();
+
+    let (anchored, pattern) = match pattern.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, pattern.as_str()),
+    };
+    let parts: Vec<&str> = pattern.split('\\').filter(|p| !p.is_empty()).collect();
+    if parts.is_empty() || parts.len() > path.len() || (anchored && parts.len() != path.len()) {
+        return false;
+    }
+    // 不以 \ 开头时匹配路径末尾的几级
+    let tail = &path[path.len() - parts.len()..];
+    parts.iter().zip(tail).all(|(p, name)| glob(p, name))
+}
+
+/// 单级名称的通配符匹配，不区分大小写
+fn glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 把多行文本解析为列表项，忽略空行和 # 开头的注释
+pub fn parse_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let config = ExclusionConfig::default();
+        assert!(config.is_excluded("pagefile.sys"));
+        assert!(config.is_excluded("PAGEFILE.SYS"));
+        assert!(!config.is_excluded("Users\\a\\pagefile.sys"));
+        assert!(config.is_excluded("$Recycle.Bin"));
+        assert!(config.is_excluded("Windows\\Temp\\setup.log"));
+        assert!(!config.is_excluded("Windows\\Temp"));
+        assert!(config.is_excluded("Users\\张三\\AppData\\Local\\Temp\\x.tmp"));
+        assert!(config.is_excluded("Users\\a\\AppData\\Local\\Google\\Chrome\\User Data\\Default\\Cache"));
+        assert!(!config.is_excluded("Users\\a\\Documents"));
+
+        assert!(matches("*.iso", "Users\\a\\Downloads\\win.ISO"));
+        assert!(matches("Downloads\\*.is?", "Users\\a\\Downloads\\win.iso"));
+        assert!(!matches("*.iso", "Users\\a\\Downloads\\win.iso.txt"));
+        assert!(glob("a*b*c", "axxbyyc") && !glob("a*b*c", "axxbyy"));
+    }
+
+    #[test]
+    fn test_render_and_parse() {
+        let config = ExclusionConfig::new(
+            false,
+            &parse_lines("# 下载目录\r\n\\Users\\*\\Downloads\r\n\r\n*.iso\r\n*.ISO"),
+            &["*.vhdx".to_string()],
+        );
+        assert_eq!(config.exclusions, ["\\Users\\*\\Downloads", "*.iso"]);
+        assert_eq!(
+            config.render(),
+            "[ExclusionList]\r\n\\Users\\*\\Downloads\r\n*.iso\r\n\r\n[CompressionExclusionList]\r\n*.vhdx\r\n"
+        );
+        assert_eq!(ExclusionConfig::parse(&config.render()), config);
+
+        let defaults = ExclusionConfig::default();
+        assert!(defaults.render().contains("\"\\System Volume Information\"\r\n"));
+        assert_eq!(ExclusionConfig::parse(&defaults.render()), defaults);
+
+        let extended = ExclusionConfig::new(true, &["\\pagefile.sys".to_string(), "*.iso".to_string()], &[]);
+        assert_eq!(extended.exclusions.len(), DEFAULT_EXCLUSIONS.len() + 1);
+    }
+
+    #[test]
+    fn test_write_temp() {
+        let config = ExclusionConfig::default();
+        let first = config.write_temp().unwrap();
+        let second = config.write_temp().unwrap();
+        assert_ne!(first.path(), second.path());
+
+        let bytes = std::fs::read(first.path()).unwrap();
+        assert_eq!(&bytes[..2], [0xFF, 0xFE]);
+
+        let path = first.path().to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.path().exists());
+    }
+}
@@ -0,0 +1,12 @@
+//! 系统备份
+//!
+//! 备份流程本身见 [`crate::workflow::run_backup`]，这里是流程之外、与具体
+//! 备份工具无关的部分。
+
+pub mod catalog;
+pub mod compression;
+pub mod exclusions;
+pub mod repository;
+pub mod retention;
+pub mod schedule;
+pub mod verify;
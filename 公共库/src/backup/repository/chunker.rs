@@ -0,0 +1,170 @@
+//! 按内容分块
+//!
+//! 用 Gear 滚动哈希在数据中找切分点：切分位置只取决于附近的内容，文件中间
+//! 插入或删除数据后，之后的块仍与上次相同，只有改动附近的块需要重新保存。
+
+use std::io::Read;
+
+use anyhow::Result;
+
+/// 分块参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkerParams {
+    /// 块的最小长度，之前不找切分点
+    pub min_size: usize,
+    /// 平均块长约为 `min_size + 2^avg_bits`
+    pub avg_bits: u32,
+    /// 块的最大长度，到达后强制切分
+    pub max_size: usize,
+}
+
+impl Default for ChunkerParams {
+    /// 平均约 1.25 MB 一块。系统分区中大量小文件各占一块，块太小时块文件数过多
+    fn default() -> Self {
+        Self {
+            min_size: 256 * 1024,
+            avg_bits: 20,
+            max_size: 4 * 1024 * 1024,
+        }
+    }
+}
+
+impl ChunkerParams {
+    /// 数据开头第一个切分点（块的长度）
+    ///
+    /// 数据不足 `max_size` 且没有切分点时返回全部长度，调用方须保证此时
+    /// 已经读到数据末尾。
+    pub fn cut_point(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let end = data.len().min(self.max_size);
+        let shift = 64 - self.avg_bits;
+        let mut hash = 0u64;
+        for (i, &byte) in data.iter().enumerate().take(end).skip(self.min_size) {
+            hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+            // 高位取决于最近 64 字节
+            if hash >> shift == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+}
+
+/// Gear 哈希的随机表，用 SplitMix64 生成
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x4c65_7452_6563_6f76;
+    let mut i = 0;
+    while i < 256 {
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// 从数据流中依次读出块
+pub struct Chunker<R> {
+    reader: R,
+    params: ChunkerParams,
+    buffer: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> Chunker<R> {
+    pub fn new(reader: R, params: ChunkerParams) -> Self {
+        Self {
+            reader,
+            params,
+            buffer: Vec::with_capacity(params.max_size),
+            eof: false,
+        }
+    }
+
+    /// 读出下一块，数据结束时返回 None
+    pub fn next_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        // 缓冲区至少有 max_size 字节（或已到末尾）时切分点才确定
+        while !self.eof && self.buffer.len() < self.params.max_size {
+            let start = self.buffer.len();
+            self.buffer.resize(self.params.max_size, 0);
+            match self.reader.read(&mut self.buffer[start..]) {
+                Ok(0) => {
+                    self.buffer.truncate(start);
+                    self.eof = true;
+                }
+                Ok(n) => self.buffer.truncate(start + n),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => self.buffer.truncate(start),
+                Err(e) => {
+                    self.buffer.truncate(start);
+                    return Err(e.into());
+                }
+            }
+        }
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        let cut = self.params.cut_point(&self.buffer);
+        let rest = self.buffer.split_off(cut);
+        Ok(Some(std::mem::replace(&mut self.buffer, rest)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(data: &[u8], params: ChunkerParams) -> Vec<Vec<u8>> {
+        let mut chunker = Chunker::new(data, params);
+        let mut chunks = Vec::new();
+        while let Some(chunk) = chunker.next_chunk().unwrap() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
+    /// 伪随机数据
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        let params = ChunkerParams {
+            min_size: 64,
+            avg_bits: 8,
+            max_size: 1024,
+        };
+        let data = noise(64 * 1024, 1);
+        let original = chunks(&data, params);
+        assert_eq!(original.concat(), data);
+        assert!(original.len() > 20);
+        assert!(original.iter().all(|c| c.len() <= params.max_size));
+        assert!(original[..original.len() - 1].iter().all(|c| c.len() > params.min_size));
+
+        // 开头插入数据后，后面的块大部分不变
+        let mut shifted = noise(100, 2);
+        shifted.extend_from_slice(&data);
+        let shifted = chunks(&shifted, params);
+        assert_eq!(shifted.concat().len(), data.len() + 100);
+        let reused = shifted.iter().filter(|c| original.contains(c)).count();
+        assert!(reused + 3 >= original.len(), "只复用了 {} / {} 块", reused, original.len());
+
+        // 没有切分点的数据按最大长度切分
+        let zeros = chunks(&[0u8; 2500], params);
+        assert_eq!(zeros.iter().map(Vec::len).collect::<Vec<_>>(), vec![1024, 1024, 452]);
+        assert!(chunks(&[], params).is_empty());
+    }
+}
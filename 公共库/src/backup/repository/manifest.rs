@@ -0,0 +1,258 @@
+//! 备份清单
+//!
+//! 每次备份一个文本文件：开头是 `键=值` 形式的备份信息，空行之后每行一个
+//! 目录或文件，字段以制表符分隔（示例中以空格表示），路径放在最后。
+//!
+//! ```text
+//! LetRecovery 备份清单 1
+//! Name=周备份
+//! Description=
+//! Created=133724628000000000
+//! Source=D:
+//!
+//! D Users
+//! F 12 133724620000000000 5f70bf18...,9a0364b9... Users\a.txt
+//! ```
+//!
+//! 文件行依次为大小、修改时间（FILETIME）、按顺序组成文件内容的块（空文件为 `-`）。
+
+use anyhow::{Context, Result};
+
+use super::ChunkId;
+use crate::backup::catalog::CatalogEntry;
+use crate::hash::{to_hex, SHA256_LEN};
+
+/// 清单文件的第一行
+const HEADER: &str = "LetRecovery 备份清单 1";
+
+/// 清单中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    Dir {
+        path: String,
+    },
+    File {
+        path: String,
+        size: u64,
+        /// 修改时间（FILETIME）
+        modified: u64,
+        chunks: Vec<ChunkId>,
+    },
+}
+
+impl Entry {
+    /// 相对源分区根目录的路径，以 `\` 分隔
+    pub fn path(&self) -> &str {
+        match self {
+            Entry::Dir { path } | Entry::File { path, .. } => path,
+        }
+    }
+}
+
+/// 一次备份的清单
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Manifest {
+    /// 备份编号，从 1 开始递增
+    pub id: u32,
+    pub name: String,
+    pub description: String,
+    /// 备份时间（FILETIME）
+    pub created: u64,
+    /// 源分区，如 "D:"
+    pub source: String,
+    pub entries: Vec<Entry>,
+}
+
+impl Manifest {
+    pub fn file_count(&self) -> u64 {
+        self.entries.iter().filter(|e| matches!(e, Entry::File { .. })).count() as u64
+    }
+
+    /// 文件的总字节数
+    pub fn total_bytes(&self) -> u64 {
+        self.entries
+            .iter()
+            .map(|e| match e {
+                Entry::File { size, .. } => *size,
+                Entry::Dir { .. } => 0,
+            })
+            .sum()
+    }
+
+    /// 清单引用的所有块（可能重复）
+    pub fn chunks(&self) -> impl Iterator<Item = &ChunkId> {
+        self.entries.iter().flat_map(|e| match e {
+            Entry::File { chunks, .. } => chunks.as_slice(),
+            Entry::Dir { .. } => &[],
+        })
+    }
+
+    /// 作为备份目录中的一项
+    pub fn to_catalog_entry(&self) -> CatalogEntry {
+        let (description, source_machine) = crate::backup::catalog::split_description(&self.description);
+        CatalogEntry {
+            index: self.id,
+            name: self.name.clone(),
+            description,
+            created: Some(self.created),
+            source_machine,
+            size: self.total_bytes(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let single_line = |text: &str| text.replace(['\r', '\n'], " ");
+        let mut lines = vec![
+            HEADER.to_string(),
+            format!("Name={}", single_line(&self.name)),
+            format!("Description={}", single_line(&self.description)),
+            format!("Created={}", self.created),
+            format!("Source={}", self.source),
+            String::new(),
+        ];
+        for entry in &self.entries {
+            lines.push(match entry {
+                Entry::Dir { path } => format!("D\t{}", path),
+                Entry::File {
+                    path,
+                    size,
+                    modified,
+                    chunks,
+                } => {
+                    let chunks = if chunks.is_empty() {
+                        "-".to_string()
+                    } else {
+                        chunks.iter().map(|c| to_hex(c)).collect::<Vec<_>>().join(",")
+                    };
+                    format!("F\t{}\t{}\t{}\t{}", size, modified, chunks, path)
+                }
+            });
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        text
+    }
+
+    /// 解析清单，`id` 取自文件名
+    pub fn parse(id: u32, text: &str) -> Result<Self> {
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some(HEADER) {
+            anyhow::bail!("不是备份清单");
+        }
+
+        let mut manifest = Manifest {
+            id,
+            ..Default::default()
+        };
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                anyhow::bail!("无效的清单行: {}", line);
+            };
+            match key {
+                "Name" => manifest.name = value.to_string(),
+                "Description" => manifest.description = value.to_string(),
+                "Created" => manifest.created = value.parse().with_context(|| format!("无效的备份时间: {}", value))?,
+                "Source" => manifest.source = value.to_string(),
+                _ => {}
+            }
+        }
+
+        for (number, line) in lines.enumerate() {
+            let entry = parse_entry(line).with_context(|| format!("清单第 {} 项无效", number + 1))?;
+            manifest.entries.push(entry);
+        }
+        Ok(manifest)
+    }
+}
+
+fn parse_entry(line: &str) -> Result<Entry> {
+    match line.split_once('\t') {
+        Some(("D", path)) => Ok(Entry::Dir { path: path.to_string() }),
+        Some(("F", rest)) => {
+            let mut fields = rest.splitn(4, '\t');
+            let (Some(size), Some(modified), Some(chunks), Some(path)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                anyhow::bail!("字段不足");
+            };
+            let chunks = match chunks {
+                "-" => Vec::new(),
+                list => list.split(',').map(parse_chunk_id).collect::<Result<_>>()?,
+            };
+            Ok(Entry::File {
+                path: path.to_string(),
+                size: size.parse().context("无效的文件大小")?,
+                modified: modified.parse().context("无效的修改时间")?,
+                chunks,
+            })
+        }
+        _ => anyhow::bail!("未知的类型"),
+    }
+}
+
+/// 解析块的十六进制摘要
+pub fn parse_chunk_id(hex: &str) -> Result<ChunkId> {
+    if hex.len() != SHA256_LEN * 2 || !hex.is_ascii() {
+        anyhow::bail!("无效的块摘要: {}", hex);
+    }
+    let mut id = [0u8; SHA256_LEN];
+    for (byte, pair) in id.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+        let pair = std::str::from_utf8(pair).unwrap_or_default();
+        *byte = u8::from_str_radix(pair, 16).with_context(|| format!("无效的块摘要: {}", hex))?;
+    }
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Sha256;
+
+    #[test]
+    fn test_render_and_parse() {
+        let manifest = Manifest {
+            id: 3,
+            name: "周备份".to_string(),
+            description: "第一行\n第二行 [来源: PC-01]".to_string(),
+            created: 133_724_628_000_000_000,
+            source: "D:".to_string(),
+            entries: vec![
+                Entry::Dir { path: "Users".to_string() },
+                Entry::File {
+                    path: "Users\\a b.txt".to_string(),
+                    size: 12,
+                    modified: 133_724_620_000_000_000,
+                    chunks: vec![Sha256::digest(b"a"), Sha256::digest(b"b")],
+                },
+                Entry::File {
+                    path: "empty".to_string(),
+                    size: 0,
+                    modified: 0,
+                    chunks: Vec::new(),
+                },
+            ],
+        };
+        let text = manifest.render();
+        assert!(text.contains("\nDescription=第一行 第二行 [来源: PC-01]\n"));
+        assert!(text.contains("\nF\t0\t0\t-\tempty\n"));
+
+        let parsed = Manifest::parse(3, &text).unwrap();
+        assert_eq!(parsed.entries, manifest.entries);
+        assert_eq!(parsed.name, "周备份");
+        assert_eq!(parsed.file_count(), 2);
+        assert_eq!(parsed.total_bytes(), 12);
+        assert_eq!(parsed.chunks().count(), 2);
+
+        let entry = parsed.to_catalog_entry();
+        assert_eq!((entry.index, entry.size), (3, 12));
+        assert_eq!(entry.description, "第一行 第二行");
+        assert_eq!(entry.source_machine.as_deref(), Some("PC-01"));
+
+        assert!(Manifest::parse(1, "Name=x\n").is_err());
+        assert!(Manifest::parse(1, &format!("{}\n\nF\t1\t0\tabc\tx\n", HEADER)).is_err());
+        assert!(parse_chunk_id(&to_hex(&Sha256::digest(b"a"))).is_ok());
+    }
+}
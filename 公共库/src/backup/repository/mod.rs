@@ -0,0 +1,801 @@
+//! 去重备份仓库
+//!
+//! DISM 捕获的 WIM 之外的第二种备份格式。源分区的文件按内容分块，块以
+//! SHA-256 摘要命名保存在仓库中；每次备份写一份清单，记录目录结构和组成各
+//! 文件的块。多次备份之间相同的块只保存一次，大小和修改时间与上一次备份
+//! 相同的文件直接沿用上次的块，不再读取。
+//!
+//! 可以从任意一次备份还原全部或部分文件。删除旧备份的清单后，不再被任何
+//! 清单引用的块由垃圾回收删除。块不压缩；仓库只用普通文件操作，不依赖
+//! Windows 工具。
+//!
+//! 仓库只保存文件内容和修改时间，不保存权限、文件属性、备用数据流、短文件名
+//! 和硬链接，也不备份符号链接和目录联接，因此不能用于 Windows 系统分区。
+//! 备份、保留策略和垃圾回收期间以锁文件独占仓库。
+//!
+//! ```text
+//! 仓库/
+//!   letrecovery-repository      标记文件
+//!   lock                        锁文件（操作进行中）
+//!   chunks/5f/5f70bf18...       块
+//!   snapshots/00000001.txt      清单
+//! ```
+
+pub mod chunker;
+pub mod manifest;
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use self::chunker::{Chunker, ChunkerParams};
+use self::manifest::{parse_chunk_id, Entry, Manifest};
+use super::catalog::CatalogEntry;
+use super::compression::format_bytes;
+use super::exclusions::ExclusionConfig;
+use super::retention::{RetentionOutcome, RetentionPolicy};
+use crate::hash::{to_hex, Sha256, SHA256_LEN};
+use crate::wim;
+use crate::workflow::Progress;
+
+/// 块的标识（内容的 SHA-256）
+pub type ChunkId = [u8; SHA256_LEN];
+
+/// 仓库根目录下的标记文件
+const MARKER_FILE: &str = "letrecovery-repository";
+const MARKER: &str = "LetRecovery 去重备份仓库 1";
+const CHUNKS_DIR: &str = "chunks";
+const SNAPSHOTS_DIR: &str = "snapshots";
+const LOCK_FILE: &str = "lock";
+/// 写入中的块和清单，完成后改名；垃圾回收时删除残留的
+const TEMP_EXTENSION: &str = "tmp";
+
+/// 一次备份的结果
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BackupSummary {
+    pub id: u32,
+    pub files: u64,
+    pub dirs: u64,
+    pub total_bytes: u64,
+    /// 与上一次备份相同、沿用原有块的文件数
+    pub unchanged_files: u64,
+    pub new_chunks: u64,
+    pub new_bytes: u64,
+    /// 跳过的文件（无法读取、符号链接和目录联接等）及原因
+    pub skipped: Vec<String>,
+}
+
+impl BackupSummary {
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "备份 {}: {} 个文件，共 {}，其中 {} 个未变化；新增 {} 个数据块 ({})",
+            self.id,
+            self.files,
+            format_bytes(self.total_bytes),
+            self.unchanged_files,
+            self.new_chunks,
+            format_bytes(self.new_bytes)
+        );
+        if !self.skipped.is_empty() {
+            text.push_str(&format!("，跳过 {} 项", self.skipped.len()));
+        }
+        text
+    }
+}
+
+/// 还原的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RestoreSummary {
+    pub files: u64,
+    pub dirs: u64,
+    pub bytes: u64,
+}
+
+/// 垃圾回收的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GcSummary {
+    pub removed_chunks: u64,
+    pub freed_bytes: u64,
+}
+
+/// 仓库锁，释放时删除锁文件
+struct RepositoryLock {
+    path: PathBuf,
+}
+
+impl Drop for RepositoryLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// 源目录中的一项
+struct SourceEntry {
+    relative: String,
+    path: PathBuf,
+    metadata: std::fs::Metadata,
+}
+
+/// 去重备份仓库
+#[derive(Debug, Clone)]
+pub struct Repository {
+    root: PathBuf,
+    params: ChunkerParams,
+}
+
+impl Repository {
+    /// 目录是否为备份仓库
+    pub fn is_repository(path: &Path) -> bool {
+        path.join(MARKER_FILE).is_file()
+    }
+
+    /// 在空目录（或不存在的目录）中新建仓库
+    pub fn create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let mut entries = std::fs::read_dir(path).with_context(|| format!("无法读取目录 {}", path.display()))?;
+            if entries.next().is_some() {
+                anyhow::bail!("{} 不是空目录，不能新建备份仓库", path.display());
+            }
+        }
+        std::fs::create_dir_all(path.join(CHUNKS_DIR))
+            .and_then(|_| std::fs::create_dir_all(path.join(SNAPSHOTS_DIR)))
+            .with_context(|| format!("无法创建备份仓库 {}", path.display()))?;
+        std::fs::write(path.join(MARKER_FILE), MARKER)?;
+        Ok(Self::at(path))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if !Self::is_repository(path) {
+            anyhow::bail!("{} 不是备份仓库", path.display());
+        }
+        Ok(Self::at(path))
+    }
+
+    pub fn open_or_create(path: &Path) -> Result<Self> {
+        if Self::is_repository(path) {
+            Self::open(path)
+        } else {
+            Self::create(path)
+        }
+    }
+
+    fn at(path: &Path) -> Self {
+        Self {
+            root: path.to_path_buf(),
+            params: ChunkerParams::default(),
+        }
+    }
+
+    /// 使用指定的分块参数（同一仓库换用参数后，未变化的文件仍能沿用原有的块）
+    pub fn with_params(mut self, params: ChunkerParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 所有备份的编号（升序）
+    pub fn snapshot_ids(&self) -> Result<Vec<u32>> {
+        let dir = self.root.join(SNAPSHOTS_DIR);
+        let entries = std::fs::read_dir(&dir).with_context(|| format!("无法读取 {}", dir.display()))?;
+        let mut ids: Vec<u32> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_str()?.strip_suffix(".txt")?.parse().ok()
+            })
+            .collect();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    pub fn read_manifest(&self, id: u32) -> Result<Manifest> {
+        let path = self.manifest_path(id);
+        let text = std::fs::read_to_string(&path).with_context(|| format!("无法读取备份 {} 的清单", id))?;
+        Manifest::parse(id, &text).with_context(|| format!("备份 {} 的清单无效", id))
+    }
+
+    /// 备份目录，与 WIM 镜像的卷列表形式相同，索引为备份编号
+    pub fn catalog(&self) -> Result<Vec<CatalogEntry>> {
+        self.snapshot_ids()?
+            .into_iter()
+            .map(|id| self.read_manifest(id).map(|m| m.to_catalog_entry()))
+            .collect()
+    }
+
+    /// 源目录能否备份到仓库：Windows 系统分区不能
+    pub fn check_source(source_root: &Path) -> Result<()> {
+        if source_root.join("Windows").join("System32").is_dir() {
+            anyhow::bail!(
+                "{} 是 Windows 系统分区。去重仓库不保存权限、文件属性、硬链接和目录联接，还原后无法启动，请使用 WIM 或 GHO 格式备份系统分区",
+                source_root.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// 独占仓库，已被其他备份或清理操作占用时失败
+    fn lock(&self) -> Result<RepositoryLock> {
+        let path = self.root.join(LOCK_FILE);
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let _ = write!(file, "{}", std::process::id());
+                Ok(RepositoryLock { path })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let holder = std::fs::read_to_string(&path).unwrap_or_default();
+                anyhow::bail!(
+                    "备份仓库 {} 正被其他备份或清理操作使用（进程 {}）。确认没有其他操作在进行后，可删除 {} 再重试",
+                    self.root.display(),
+                    holder.trim(),
+                    path.display()
+                )
+            }
+            Err(e) => Err(e).with_context(|| format!("无法创建锁文件 {}", path.display())),
+        }
+    }
+
+    /// 删除一次备份的清单，其中的块由垃圾回收删除（调用方须持有锁）
+    fn remove_snapshot(&self, id: u32) -> Result<()> {
+        std::fs::remove_file(self.manifest_path(id)).with_context(|| format!("删除备份 {} 失败", id))
+    }
+
+    fn manifest_path(&self, id: u32) -> PathBuf {
+        self.root.join(SNAPSHOTS_DIR).join(format!("{:08}.txt", id))
+    }
+
+    fn chunk_path(&self, id: &ChunkId) -> PathBuf {
+        let hex = to_hex(id);
+        self.root.join(CHUNKS_DIR).join(&hex[..2]).join(hex)
+    }
+
+    /// 保存块，返回其标识和是否为新块
+    fn store_chunk(&self, data: &[u8]) -> Result<(ChunkId, bool)> {
+        let id = Sha256::digest(data);
+        let path = self.chunk_path(&id);
+        if path.exists() {
+            return Ok((id, false));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_atomic(&path, data).with_context(|| format!("写入数据块失败 {}", path.display()))?;
+        Ok((id, true))
+    }
+
+    /// 读取块并校验摘要
+    pub fn read_chunk(&self, id: &ChunkId) -> Result<Vec<u8>> {
+        let data = std::fs::read(self.chunk_path(id)).with_context(|| format!("缺少数据块 {}", to_hex(id)))?;
+        if Sha256::digest(&data) != *id {
+            anyhow::bail!("数据块 {} 已损坏", to_hex(id));
+        }
+        Ok(data)
+    }
+
+    /// 备份源目录，生成新的清单
+    ///
+    /// 不进入符号链接和目录联接，跳过排除的文件和目录。跳过的链接和无法读取
+    /// 的文件记录在结果中，不中断备份。源目录是 Windows 系统分区时失败。
+    pub fn backup(
+        &self,
+        source_root: &Path,
+        name: &str,
+        description: &str,
+        exclusions: &ExclusionConfig,
+        progress: Progress,
+    ) -> Result<BackupSummary> {
+        Self::check_source(source_root)?;
+        let _lock = self.lock()?;
+        let mut summary = BackupSummary::default();
+        let sources = scan_source(source_root, exclusions, &mut summary.skipped)?;
+        let total_bytes: u64 = sources.iter().filter(|s| s.metadata.is_file()).map(|s| s.metadata.len()).sum();
+
+        // 上一次备份中的文件：大小和修改时间都相同且块仍在时沿用
+        let previous = self.snapshot_ids()?.last().map(|&id| self.read_manifest(id)).transpose()?;
+        let previous: HashMap<&str, (u64, u64, &[ChunkId])> = previous
+            .iter()
+            .flat_map(|m| &m.entries)
+            .filter_map(|entry| match entry {
+                Entry::File {
+                    path,
+                    size,
+                    modified,
+                    chunks,
+                } => Some((path.as_str(), (*size, *modified, chunks.as_slice()))),
+                Entry::Dir { .. } => None,
+            })
+            .collect();
+
+        let mut entries = Vec::with_capacity(sources.len());
+        let mut done_bytes = 0u64;
+        for source in sources {
+            if source.metadata.is_dir() {
+                summary.dirs += 1;
+                entries.push(Entry::Dir { path: source.relative });
+                continue;
+            }
+
+            let size = source.metadata.len();
+            let modified = source.metadata.modified().map(wim::to_filetime).unwrap_or(0);
+            let unchanged = previous.get(source.relative.as_str()).filter(|(s, m, chunks)| {
+                *s == size && *m == modified && chunks.iter().all(|c| self.chunk_path(c).exists())
+            });
+            let chunks = match unchanged {
+                Some((_, _, chunks)) => {
+                    summary.unchanged_files += 1;
+                    chunks.to_vec()
+                }
+                None => match self.store_file(&source.path, &mut summary) {
+                    Ok(chunks) => chunks,
+                    Err(e) => {
+                        log::warn!("跳过无法读取的文件 {}: {:#}", source.relative, e);
+                        summary.skipped.push(format!("{}: {:#}", source.relative, e));
+                        continue;
+                    }
+                },
+            };
+
+            summary.files += 1;
+            summary.total_bytes += size;
+            entries.push(Entry::File {
+                path: source.relative,
+                size,
+                modified,
+                chunks,
+            });
+            done_bytes += size;
+            progress((done_bytes * 100 / total_bytes.max(1)).min(99) as u8);
+        }
+
+        summary.id = self.snapshot_ids()?.last().map_or(1, |id| id + 1);
+        let manifest = Manifest {
+            id: summary.id,
+            name: name.to_string(),
+            description: description.to_string(),
+            created: wim::filetime_now(),
+            source: source_root.to_string_lossy().trim_end_matches(['\\', '/']).to_string(),
+            entries,
+        };
+        write_atomic(&self.manifest_path(summary.id), manifest.render().as_bytes())
+            .with_context(|| format!("写入备份 {} 的清单失败", summary.id))?;
+        progress(100);
+        Ok(summary)
+    }
+
+    /// 分块保存一个文件，返回组成文件的块
+    fn store_file(&self, path: &Path, summary: &mut BackupSummary) -> Result<Vec<ChunkId>> {
+        let file = std::fs::File::open(path)?;
+        let mut chunker = Chunker::new(std::io::BufReader::new(file), self.params);
+        let mut chunks = Vec::new();
+        while let Some(data) = chunker.next_chunk()? {
+            let (id, new) = self.store_chunk(&data)?;
+            if new {
+                summary.new_chunks += 1;
+                summary.new_bytes += data.len() as u64;
+            }
+            chunks.push(id);
+        }
+        Ok(chunks)
+    }
+
+    /// 从备份还原到目标目录
+    ///
+    /// `prefix` 为 None 时还原全部；否则只还原该路径（文件或目录，以 `\` 分隔）
+    /// 下的内容，在目标目录中保留其相对路径。已存在的文件被覆盖。
+    pub fn restore(
+        &self,
+        id: u32,
+        destination: &Path,
+        prefix: Option<&str>,
+        progress: Progress,
+    ) -> Result<RestoreSummary> {
+        let manifest = self.read_manifest(id)?;
+        let prefix = prefix
+            .map(|p| p.replace('/', "\\").trim_matches('\\').to_string())
+            .filter(|p| !p.is_empty());
+        let selected: Vec<&Entry> = manifest
+            .entries
+            .iter()
+            .filter(|entry| match &prefix {
+                Some(prefix) => {
+                    let path = entry.path();
+                    path == prefix || path.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with('\\'))
+                }
+                None => true,
+            })
+            .collect();
+        if let (Some(prefix), true) = (&prefix, selected.is_empty()) {
+            anyhow::bail!("备份 {} 中没有 {}", id, prefix);
+        }
+
+        std::fs::create_dir_all(destination).with_context(|| format!("无法创建目录 {}", destination.display()))?;
+        let total_bytes: u64 = selected
+            .iter()
+            .map(|e| match e {
+                Entry::File { size, .. } => *size,
+                Entry::Dir { .. } => 0,
+            })
+            .sum();
+
+        let mut summary = RestoreSummary::default();
+        for entry in selected {
+            let target = destination.join(relative_path(entry.path())?);
+            match entry {
+                Entry::Dir { .. } => {
+                    std::fs::create_dir_all(&target).with_context(|| format!("无法创建目录 {}", target.display()))?;
+                    summary.dirs += 1;
+                }
+                Entry::File {
+                    path,
+                    size,
+                    modified,
+                    chunks,
+                } => {
+                    if let Some(parent) = target.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    let mut file = std::fs::File::create(&target)
+                        .with_context(|| format!("无法写入 {}", target.display()))?;
+                    let mut written = 0u64;
+                    for chunk in chunks {
+                        let data = self.read_chunk(chunk).with_context(|| format!("还原 {} 失败", path))?;
+                        file.write_all(&data)?;
+                        written += data.len() as u64;
+                    }
+                    if written != *size {
+                        anyhow::bail!("还原 {} 失败: 长度为 {}，应为 {}", path, written, size);
+                    }
+                    if let Err(e) = file.set_modified(wim::from_filetime(*modified)) {
+                        log::warn!("设置 {} 的修改时间失败: {}", path, e);
+                    }
+                    summary.files += 1;
+                    summary.bytes += written;
+                    progress((summary.bytes * 100 / total_bytes.max(1)).min(99) as u8);
+                }
+            }
+        }
+        progress(100);
+        Ok(summary)
+    }
+
+    /// 校验一次备份：读取其引用的每个块，核对摘要和文件长度
+    ///
+    /// 返回发现的问题，为空表示备份完整。
+    pub fn check(&self, id: u32, progress: Progress) -> Result<Vec<String>> {
+        let manifest = self.read_manifest(id)?;
+        let unique: HashSet<&ChunkId> = manifest.chunks().collect();
+        let total = unique.len().max(1);
+
+        let mut problems = Vec::new();
+        let mut lengths: HashMap<&ChunkId, u64> = HashMap::new();
+        for (i, chunk) in unique.into_iter().enumerate() {
+            match self.read_chunk(chunk) {
+                Ok(data) => {
+                    lengths.insert(chunk, data.len() as u64);
+                }
+                Err(e) => problems.push(e.to_string()),
+            }
+            progress(((i + 1) * 100 / total) as u8);
+        }
+
+        for entry in &manifest.entries {
+            let Entry::File { path, size, chunks, .. } = entry else {
+                continue;
+            };
+            // 缺少块的文件已在上面报告
+            let length: Option<u64> = chunks.iter().map(|c| lengths.get(c).copied()).sum();
+            if length.is_some_and(|length| length != *size) {
+                problems.push(format!("{} 的长度与清单不符", path));
+            }
+        }
+        progress(100);
+        Ok(problems)
+    }
+
+    /// 按策略删除旧的备份并回收空间
+    pub fn apply_retention(&self, policy: RetentionPolicy, now: u64) -> Result<(RetentionOutcome, GcSummary)> {
+        let _lock = self.lock()?;
+        let entries = self.catalog()?;
+        let kept = policy.select(&entries, now);
+        let removed: Vec<u32> = entries
+            .iter()
+            .map(|e| e.index)
+            .filter(|i| !kept.contains(i))
+            .collect();
+        for &id in &removed {
+            log::info!("删除备份 {}", id);
+            self.remove_snapshot(id)?;
+        }
+        let gc = if removed.is_empty() {
+            GcSummary::default()
+        } else {
+            self.collect_garbage()?
+        };
+        Ok((RetentionOutcome { kept, removed }, gc))
+    }
+
+    /// 删除不再被任何清单引用的块和中断时残留的临时文件
+    ///
+    /// 有清单无法读取时不删除任何块，以免误删其中引用的数据。
+    pub fn gc(&self) -> Result<GcSummary> {
+        let _lock = self.lock()?;
+        self.collect_garbage()
+    }
+
+    fn collect_garbage(&self) -> Result<GcSummary> {
+        let mut referenced = HashSet::new();
+        for id in self.snapshot_ids()? {
+            referenced.extend(self.read_manifest(id)?.chunks().copied());
+        }
+
+        let mut summary = GcSummary::default();
+        remove_temp_files(&self.root.join(SNAPSHOTS_DIR));
+        let chunks_dir = self.root.join(CHUNKS_DIR);
+        for group in std::fs::read_dir(&chunks_dir)
+            .with_context(|| format!("无法读取 {}", chunks_dir.display()))?
+            .flatten()
+        {
+            let group = group.path();
+            if !group.is_dir() {
+                continue;
+            }
+            remove_temp_files(&group);
+            for entry in std::fs::read_dir(&group)?.flatten() {
+                let name = entry.file_name();
+                let Ok(id) = parse_chunk_id(&name.to_string_lossy()) else {
+                    continue;
+                };
+                if referenced.contains(&id) {
+                    continue;
+                }
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                std::fs::remove_file(entry.path())
+                    .with_context(|| format!("删除数据块失败 {}", entry.path().display()))?;
+                summary.removed_chunks += 1;
+                summary.freed_bytes += size;
+            }
+            // 组目录空了就删除，不空时删除失败可以忽略
+            let _ = std::fs::remove_dir(&group);
+        }
+        Ok(summary)
+    }
+}
+
+/// 列出源目录中要备份的目录和文件，按路径排序
+fn scan_source(root: &Path, exclusions: &ExclusionConfig, skipped: &mut Vec<String>) -> Result<Vec<SourceEntry>> {
+    std::fs::read_dir(root).with_context(|| format!("无法读取源目录 {}", root.display()))?;
+
+    let mut sources = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, relative)) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                skipped.push(format!("{}: {}", relative, e));
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            // 清单以 `\` 分隔路径、以换行分隔各项，这些名称无法记录
+            let name = entry.file_name();
+            let Some(name) = name.to_str().filter(|n| !n.contains(['\\', '\r', '\n'])) else {
+                skipped.push(format!("{}: 无法记录的文件名", entry.path().display()));
+                continue;
+            };
+            let relative = if relative.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}\\{}", relative, name)
+            };
+            if exclusions.is_excluded(&relative) {
+                continue;
+            }
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            if metadata.file_type().is_symlink() {
+                skipped.push(format!("{}: 符号链接或目录联接，未备份", relative));
+                continue;
+            }
+            if metadata.is_dir() {
+                pending.push((entry.path(), relative.clone()));
+            }
+            sources.push(SourceEntry {
+                relative,
+                path: entry.path(),
+                metadata,
+            });
+        }
+    }
+    sources.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(sources)
+}
+
+/// 清单中的相对路径转换为本地路径，拒绝跳出目标目录的路径
+fn relative_path(path: &str) -> Result<PathBuf> {
+    let mut result = PathBuf::new();
+    for part in path.split('\\') {
+        if part.is_empty() || part == "." || part == ".." || part.contains('/') || (cfg!(windows) && part.contains(':')) {
+            anyhow::bail!("清单中的路径无效: {}", path);
+        }
+        result.push(part);
+    }
+    Ok(result)
+}
+
+/// 先写入临时文件再改名，中断时不会留下不完整的文件
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let temp = path.with_extension(TEMP_EXTENSION);
+    std::fs::write(&temp, data)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+fn remove_temp_files(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == TEMP_EXTENSION) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMS: ChunkerParams = ChunkerParams {
+        min_size: 64,
+        avg_bits: 8,
+        max_size: 1024,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lr_repository_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    fn write(root: &Path, relative: &str, data: &[u8]) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_backup_restore_and_gc() {
+        let source = temp_dir("source");
+        let repo_dir = temp_dir("repo");
+        let restore_dir = temp_dir("restore");
+        let big = noise(20_000, 1);
+        write(&source, "Users/a.txt", b"hello");
+        write(&source, "Users/big.bin", &big);
+        write(&source, "Users/copy.bin", &big);
+        write(&source, "pagefile.sys", b"excluded");
+        write(&source, "empty", b"");
+        std::fs::create_dir_all(source.join("Empty Dir")).unwrap();
+
+        assert!(Repository::open(&repo_dir).is_err());
+        let repository = Repository::open_or_create(&repo_dir).unwrap().with_params(PARAMS);
+        let exclusions = ExclusionConfig::new(false, &["\\pagefile.sys".to_string()], &[]);
+        let first = repository.backup(&source, "第一次", "", &exclusions, &|_| {}).unwrap();
+        assert_eq!((first.id, first.files, first.dirs), (1, 4, 2));
+        assert_eq!(first.total_bytes, 5 + 40_000);
+        assert_eq!(first.unchanged_files, 0);
+        // 两个相同的大文件只保存一份
+        assert!(first.new_bytes < 20_100, "{}", first.new_bytes);
+        assert!(first.skipped.is_empty());
+
+        // 修改大文件中间的几个字节，只新增附近的块；其余文件沿用上次的块
+        let mut changed = big.clone();
+        changed[10_000..10_010].copy_from_slice(b"0123456789");
+        write(&source, "Users/big.bin", &changed);
+        write(&source, "Users/new.txt", b"new file");
+        std::fs::remove_file(source.join("Users/a.txt")).unwrap();
+        let second = repository.backup(&source, "第二次", "", &exclusions, &|_| {}).unwrap();
+        assert_eq!((second.id, second.files), (2, 4));
+        assert_eq!(second.unchanged_files, 2);
+        assert!(second.new_bytes < 5000, "{}", second.new_bytes);
+        assert!(second.describe().starts_with("备份 2: 4 个文件"));
+
+        let catalog = repository.catalog().unwrap();
+        assert_eq!(catalog.iter().map(|e| e.index).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(catalog[0].name, "第一次");
+        assert!(repository.check(1, &|_| {}).unwrap().is_empty());
+
+        // 从第一次备份还原全部，从第二次备份取出单个文件
+        let restored = repository.restore(1, &restore_dir, None, &|_| {}).unwrap();
+        assert_eq!((restored.files, restored.dirs), (4, 2));
+        assert_eq!(std::fs::read(restore_dir.join("Users/big.bin")).unwrap(), big);
+        assert_eq!(std::fs::read(restore_dir.join("empty")).unwrap(), b"");
+        assert!(restore_dir.join("Empty Dir").is_dir());
+        assert!(!restore_dir.join("pagefile.sys").exists());
+        let restored = repository.restore(2, &restore_dir, Some("Users/big.bin"), &|_| {}).unwrap();
+        assert_eq!(restored.files, 1);
+        assert_eq!(std::fs::read(restore_dir.join("Users/big.bin")).unwrap(), changed);
+        assert!(repository.restore(2, &restore_dir, Some("Users\\missing"), &|_| {}).is_err());
+
+        // 只保留最近一次，回收第一次独有的块（已删除的 a.txt）后第二次仍然完整
+        let (outcome, gc) = repository.apply_retention(RetentionPolicy::KeepLast(1), wim::filetime_now()).unwrap();
+        assert_eq!((outcome.kept, outcome.removed), (vec![2], vec![1]));
+        assert_eq!(gc, GcSummary { removed_chunks: 1, freed_bytes: 5 });
+        assert!(repository.check(2, &|_| {}).unwrap().is_empty());
+        assert_eq!(repository.gc().unwrap(), GcSummary::default());
+
+        // 损坏的块在校验和还原时报告
+        let manifest = repository.read_manifest(2).unwrap();
+        let chunk = manifest.chunks().next().unwrap();
+        std::fs::write(repository.chunk_path(chunk), b"corrupt").unwrap();
+        let problems = repository.check(2, &|_| {}).unwrap();
+        assert!(problems.iter().any(|p| p.contains("已损坏")), "{:?}", problems);
+        assert!(repository.restore(2, &temp_dir("corrupt"), None, &|_| {}).is_err());
+
+        // 其他操作持有锁时备份和回收失败，释放后恢复
+        let lock = repository.lock().unwrap();
+        let err = repository.backup(&source, "第三次", "", &exclusions, &|_| {}).unwrap_err();
+        assert!(err.to_string().contains("正被其他备份或清理操作使用"), "{}", err);
+        assert!(repository.gc().is_err());
+        drop(lock);
+        assert!(repository.gc().is_ok());
+
+        for dir in [source, repo_dir, restore_dir, temp_dir("corrupt")] {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn test_rejects_system_partition_and_records_links() {
+        let source = temp_dir("links_source");
+        let repo_dir = temp_dir("links_repo");
+        write(&source, "data/a.txt", b"a");
+        let repository = Repository::create(&repo_dir).unwrap().with_params(PARAMS);
+        let exclusions = ExclusionConfig::new(false, &[], &[]);
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(source.join("data"), source.join("link")).unwrap();
+            let summary = repository.backup(&source, "", "", &exclusions, &|_| {}).unwrap();
+            assert_eq!(summary.files, 1);
+            assert_eq!(summary.skipped, ["link: 符号链接或目录联接，未备份"]);
+        }
+
+        std::fs::create_dir_all(source.join("Windows").join("System32")).unwrap();
+        let err = repository.backup(&source, "", "", &exclusions, &|_| {}).unwrap_err();
+        assert!(err.to_string().contains("Windows 系统分区"), "{}", err);
+
+        for dir in [source, repo_dir] {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn test_create_requires_empty_dir_and_safe_paths() {
+        let dir = temp_dir("not_empty");
+        write(&dir, "file.txt", b"x");
+        assert!(Repository::create(&dir).is_err());
+        assert!(Repository::open_or_create(&dir).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(relative_path("Users\\a.txt").unwrap(), Path::new("Users").join("a.txt"));
+        assert!(relative_path("..\\evil").is_err());
+        assert!(relative_path("a\\\\b").is_err());
+        assert!(relative_path("").is_err());
+    }
+}
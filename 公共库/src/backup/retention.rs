@@ -0,0 +1,243 @@
+//! 备份保留策略
+//!
+//! 增量备份的镜像会无限增长。按策略选出要保留的卷，把它们依次导出到新的
+//! 镜像文件，再替换原文件；DISM 删除卷后不会缩小文件，只有导出才能回收空间。
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::catalog::{read_catalog, CatalogEntry};
+use crate::wim;
+
+/// FILETIME 中一天的长度
+const FILETIME_DAY: u64 = 24 * 3600 * 10_000_000;
+
+/// 保留策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// 只保留最近的 N 次备份
+    KeepLast(u32),
+    /// 最近 N 天内每天保留最后一次备份，更早的全部删除
+    ///
+    /// "天" 按距当前时间的 24 小时区间计算，不受时区影响。
+    KeepDaily(u32),
+}
+
+impl RetentionPolicy {
+    /// 解析配置中的 "last:N" 或 "daily:N"
+    pub fn parse(text: &str) -> Option<Self> {
+        let (kind, count) = text.trim().split_once(':')?;
+        let count: u32 = count.trim().parse().ok().filter(|n| *n > 0)?;
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "last" => Some(Self::KeepLast(count)),
+            "daily" => Some(Self::KeepDaily(count)),
+            _ => None,
+        }
+    }
+
+    /// 转换为配置文件中的形式
+    pub fn to_config_value(&self) -> String {
+        match self {
+            Self::KeepLast(n) => format!("last:{}", n),
+            Self::KeepDaily(n) => format!("daily:{}", n),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::KeepLast(n) => format!("保留最近 {} 次备份", n),
+            Self::KeepDaily(n) => format!("最近 {} 天每天保留一次备份", n),
+        }
+    }
+
+    /// 选出要保留的卷索引（升序）
+    ///
+    /// 最新的一次备份总是保留。按天保留时，没有时间记录的卷无法判断新旧，也保留。
+    pub fn select(&self, entries: &[CatalogEntry], now: u64) -> Vec<u32> {
+        let Some(newest) = entries.iter().map(|e| e.index).max() else {
+            return Vec::new();
+        };
+
+        let mut kept: Vec<u32> = match *self {
+            Self::KeepLast(n) => {
+                let mut indexes: Vec<u32> = entries.iter().map(|e| e.index).collect();
+                indexes.sort_unstable_by(|a, b| b.cmp(a));
+                indexes.truncate(n as usize);
+                indexes
+            }
+            Self::KeepDaily(days) => {
+                // 按距今天数分组，每组保留时间最晚的一卷
+                let mut newest_per_day: Vec<(u64, &CatalogEntry)> = Vec::new();
+                for entry in entries {
+                    let Some(created) = entry.created else { continue };
+                    let day = now.saturating_sub(created) / FILETIME_DAY;
+                    if day >= u64::from(days) {
+                        continue;
+                    }
+                    match newest_per_day.iter_mut().find(|(d, _)| *d == day) {
+                        Some(slot) if slot.1.created < entry.created => slot.1 = entry,
+                        Some(_) => {}
+                        None => newest_per_day.push((day, entry)),
+                    }
+                }
+                newest_per_day
+                    .into_iter()
+                    .map(|(_, e)| e.index)
+                    .chain(entries.iter().filter(|e| e.created.is_none()).map(|e| e.index))
+                    .collect()
+            }
+        };
+        kept.push(newest);
+        kept.sort_unstable();
+        kept.dedup();
+        kept
+    }
+}
+
+/// 应用保留策略的结果
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RetentionOutcome {
+    pub kept: Vec<u32>,
+    pub removed: Vec<u32>,
+}
+
+/// 按策略清理镜像
+///
+/// `export` 把源镜像的一卷导出（追加）到目标镜像。全部导出成功后才替换原文件，
+/// 中途失败时原镜像不受影响。没有需要删除的卷时不做任何修改。
+pub fn apply_retention(
+    image_path: &Path,
+    policy: RetentionPolicy,
+    now: u64,
+    export: &dyn Fn(&Path, u32, &Path) -> Result<()>,
+) -> Result<RetentionOutcome> {
+    let entries = read_catalog(image_path)?;
+    let kept = policy.select(&entries, now);
+    let removed: Vec<u32> = entries
+        .iter()
+        .map(|e| e.index)
+        .filter(|i| !kept.contains(i))
+        .collect();
+    if removed.is_empty() {
+        return Ok(RetentionOutcome { kept, removed });
+    }
+
+    let temp = sibling(image_path, "retention.tmp");
+    if temp.exists() {
+        std::fs::remove_file(&temp).context("删除上次残留的临时镜像失败")?;
+    }
+    for &index in &kept {
+        log::info!("导出第 {} 卷到新镜像", index);
+        if let Err(e) = export(image_path, index, &temp) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e.context(format!("导出第 {} 卷失败", index)));
+        }
+    }
+    let (header, _) = wim::read_images(&temp)?;
+    if header.image_count as usize != kept.len() {
+        let _ = std::fs::remove_file(&temp);
+        anyhow::bail!("新镜像中有 {} 卷，应为 {} 卷", header.image_count, kept.len());
+    }
+
+    // 先把原文件改名，替换失败时再恢复
+    let old = sibling(image_path, "old");
+    std::fs::rename(image_path, &old).context("重命名原镜像失败")?;
+    if let Err(e) = std::fs::rename(&temp, image_path) {
+        let _ = std::fs::rename(&old, image_path);
+        return Err(anyhow::Error::from(e).context("替换镜像失败"));
+    }
+    if let Err(e) = std::fs::remove_file(&old) {
+        log::warn!("删除旧镜像失败: {}", e);
+    }
+    Ok(RetentionOutcome { kept, removed })
+}
+
+/// 镜像旁的临时文件，如 "D:\backup.wim.old"
+fn sibling(image_path: &Path, extension: &str) -> PathBuf {
+    let mut name = image_path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wim::build_test_wim;
+
+    const HOUR: u64 = 3600 * 10_000_000;
+
+    fn entry(index: u32, created: Option<u64>) -> CatalogEntry {
+        CatalogEntry { index, created, ..Default::default() }
+    }
+
+    #[test]
+    fn test_select() {
+        let now = 1000 * FILETIME_DAY;
+        let entries = [
+            entry(1, Some(now - 50 * FILETIME_DAY)),
+            entry(2, Some(now - 30 * HOUR)),
+            entry(3, Some(now - 26 * HOUR)),
+            entry(4, None),
+            entry(5, Some(now - 5 * HOUR)),
+            entry(6, Some(now - HOUR)),
+        ];
+        assert_eq!(RetentionPolicy::KeepLast(2).select(&entries, now), [5, 6]);
+        assert_eq!(RetentionPolicy::KeepLast(10).select(&entries, now), [1, 2, 3, 4, 5, 6]);
+        // 第 1 天（24-48 小时前）保留较晚的 3，当天保留 6，50 天前的超出范围
+        assert_eq!(RetentionPolicy::KeepDaily(7).select(&entries, now), [3, 4, 6]);
+        assert_eq!(RetentionPolicy::KeepDaily(1).select(&entries[..3], now), [3]);
+        assert!(RetentionPolicy::KeepLast(1).select(&[], now).is_empty());
+
+        assert_eq!(RetentionPolicy::parse("daily:14"), Some(RetentionPolicy::KeepDaily(14)));
+        assert_eq!(RetentionPolicy::parse(&RetentionPolicy::KeepLast(3).to_config_value()), Some(RetentionPolicy::KeepLast(3)));
+        assert_eq!(RetentionPolicy::parse("last:0"), None);
+        assert_eq!(RetentionPolicy::parse("weekly:2"), None);
+    }
+
+    #[test]
+    fn test_apply_retention() {
+        let dir = std::env::temp_dir().join(format!("lr_retention_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("backup.wim");
+        let xml: String = (1..=4)
+            .map(|i| format!("<IMAGE INDEX=\"{}\"><NAME>备份{}</NAME></IMAGE>", i, i))
+            .collect();
+        std::fs::write(&image, build_test_wim(&format!("<WIM>{}</WIM>", xml), &[0; 8], None)).unwrap();
+
+        // 模拟 DISM 导出：每次导出后重写目标镜像，卷数加一
+        let exported = std::sync::Mutex::new(Vec::new());
+        let export = |source: &Path, index: u32, dest: &Path| -> Result<()> {
+            assert_eq!(source, image.as_path());
+            let mut exported = exported.lock().unwrap();
+            exported.push(index);
+            let xml: String = exported
+                .iter()
+                .enumerate()
+                .map(|(i, n)| format!("<IMAGE INDEX=\"{}\"><NAME>备份{}</NAME></IMAGE>", i + 1, n))
+                .collect();
+            std::fs::write(dest, build_test_wim(&format!("<WIM>{}</WIM>", xml), &[0; 8], None))?;
+            Ok(())
+        };
+
+        let outcome = apply_retention(&image, RetentionPolicy::KeepLast(2), 0, &export).unwrap();
+        assert_eq!(outcome, RetentionOutcome { kept: vec![3, 4], removed: vec![1, 2] });
+        let names: Vec<String> = read_catalog(&image).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["备份3", "备份4"]);
+        assert!(!sibling(&image, "old").exists());
+
+        // 已满足策略时不重写
+        let outcome = apply_retention(&image, RetentionPolicy::KeepLast(2), 0, &export).unwrap();
+        assert!(outcome.removed.is_empty());
+        assert_eq!(*exported.lock().unwrap(), [3, 4]);
+
+        // 导出失败时原镜像保持不变
+        let failing = |_: &Path, _: u32, _: &Path| -> Result<()> { anyhow::bail!("磁盘已满") };
+        let err = apply_retention(&image, RetentionPolicy::KeepLast(1), 0, &failing).unwrap_err();
+        assert_eq!(format!("{:#}", err), "导出第 2 卷失败: 磁盘已满");
+        assert_eq!(read_catalog(&image).unwrap().len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,403 @@
+//! 计划备份
+//!
+//! 生成 Windows 任务计划程序的任务定义（XML），按每天或每周的固定时间以
+//! SYSTEM 身份在后台运行命令行备份：每次追加到目标文件夹中同一个 WIM，
+//! 再按保留策略清理旧的卷。
+//!
+//! 运行中的系统分区不能直接捕获，此时命令行只把备份排入 PE：写入备份配置
+//! 并添加一次性 PE 启动项，备份要等用户下次重启后在 PE 中完成，计划时间并不会重启。
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::compression::{BackupFormat, Compression};
+use super::retention::RetentionPolicy;
+use crate::unattend::xml::Element;
+
+/// 任务计划程序中的文件夹
+pub const TASK_FOLDER: &str = "LetRecovery";
+
+/// 计划备份的卷名称
+pub const BACKUP_NAME: &str = "计划备份";
+
+/// 单次备份的最长运行时间，超过后由任务计划程序终止
+const EXECUTION_TIME_LIMIT: &str = "PT12H";
+
+/// 星期
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub fn all() -> &'static [Weekday] {
+        &[
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weekday::Monday => "星期一",
+            Weekday::Tuesday => "星期二",
+            Weekday::Wednesday => "星期三",
+            Weekday::Thursday => "星期四",
+            Weekday::Friday => "星期五",
+            Weekday::Saturday => "星期六",
+            Weekday::Sunday => "星期日",
+        }
+    }
+
+    /// 用于 "每周一" 这类说明的简称
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            Weekday::Monday => "一",
+            Weekday::Tuesday => "二",
+            Weekday::Wednesday => "三",
+            Weekday::Thursday => "四",
+            Weekday::Friday => "五",
+            Weekday::Saturday => "六",
+            Weekday::Sunday => "日",
+        }
+    }
+
+    /// 任务定义中 `DaysOfWeek` 的元素名
+    fn element(&self) -> &'static str {
+        match self {
+            Weekday::Monday => "Monday",
+            Weekday::Tuesday => "Tuesday",
+            Weekday::Wednesday => "Wednesday",
+            Weekday::Thursday => "Thursday",
+            Weekday::Friday => "Friday",
+            Weekday::Saturday => "Saturday",
+            Weekday::Sunday => "Sunday",
+        }
+    }
+}
+
+/// 备份频率
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly(Weekday),
+}
+
+/// 计划备份设置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupSchedule {
+    pub frequency: Frequency,
+    pub hour: u8,
+    pub minute: u8,
+    /// 源分区，如 "D:"
+    pub source_partition: String,
+    /// 保存镜像的文件夹
+    pub target_folder: String,
+    pub retention: Option<RetentionPolicy>,
+    pub verify: bool,
+    pub format: BackupFormat,
+    /// 新建镜像时的压缩方式
+    pub compression: Compression,
+    /// 使用内置排除列表
+    pub use_default_exclusions: bool,
+    /// 自定义排除项
+    pub exclusions: Vec<String>,
+    /// 自定义不压缩项
+    pub compression_exclusions: Vec<String>,
+    /// 源分区是运行中的系统分区时使用的 PE 文件，None 时直接捕获
+    pub pe_file: Option<String>,
+}
+
+impl BackupSchedule {
+    /// 任务名称（含文件夹），每个源分区一个任务
+    pub fn task_name(&self) -> String {
+        format!("{}\\备份_{}", TASK_FOLDER, drive_letter(&self.source_partition))
+    }
+
+    /// 镜像路径：目标文件夹下以源分区命名的镜像，去重仓库为同名文件夹
+    pub fn image_path(&self) -> String {
+        let path = format!(
+            "{}\\LetRecovery_{}",
+            self.target_folder.trim_end_matches(['\\', '/']),
+            drive_letter(&self.source_partition)
+        );
+        match self.format.extension() {
+            "" => path,
+            extension => format!("{}.{}", path, extension),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let when = match self.frequency {
+            Frequency::Daily => "每天".to_string(),
+            Frequency::Weekly(day) => format!("每周{}", day.short_name()),
+        };
+        let mut text = format!(
+            "{} {:02}:{:02} 备份 {} 到 {}",
+            when,
+            self.hour,
+            self.minute,
+            self.source_partition,
+            self.image_path()
+        );
+        if let Some(policy) = self.retention {
+            text.push_str(&format!("，{}", policy.describe()));
+        }
+        if self.pe_file.is_some() {
+            text.push_str("，到时只添加 PE 启动项，下次重启后才在 PE 中执行");
+        }
+        text
+    }
+
+    /// 命令行备份的参数
+    pub fn arguments(&self) -> Vec<String> {
+        let mut args = vec![
+            "backup".to_string(),
+            "--source".to_string(),
+            self.source_partition.clone(),
+            "--dest".to_string(),
+            self.image_path(),
+            "--name".to_string(),
+            BACKUP_NAME.to_string(),
+            "--incremental".to_string(),
+            "--format".to_string(),
+            self.format.to_config_value(),
+            "--compression".to_string(),
+            self.compression.to_config_value().to_string(),
+        ];
+        if self.verify {
+            args.push("--verify".to_string());
+        }
+        // 写出创建任务时的排除设置，不引用部署方案，任务不随方案以后的修改而变
+        args.push("--default-exclusions".to_string());
+        args.push(if self.use_default_exclusions { "on" } else { "off" }.to_string());
+        for item in &self.exclusions {
+            args.push("--exclude".to_string());
+            args.push(item.clone());
+        }
+        for item in &self.compression_exclusions {
+            args.push("--no-compress".to_string());
+            args.push(item.clone());
+        }
+        if let Some(policy) = self.retention {
+            args.push("--retention".to_string());
+            args.push(policy.to_config_value());
+        }
+        if let Some(pe_file) = &self.pe_file {
+            args.push("--pe".to_string());
+            args.push(pe_file.clone());
+        }
+        args
+    }
+
+    /// 生成任务定义
+    ///
+    /// `program` 为程序的完整路径，`start_date` 为首次触发的日期 (YYYY-MM-DD)。
+    pub fn render_task_xml(&self, program: &str, start_date: &str) -> String {
+        let schedule = match self.frequency {
+            Frequency::Daily => Element::new("ScheduleByDay").text_child("DaysInterval", "1"),
+            Frequency::Weekly(day) => Element::new("ScheduleByWeek")
+                .child(Element::new("DaysOfWeek").child(Element::new(day.element())))
+                .text_child("WeeksInterval", "1"),
+        };
+        let trigger = Element::new("CalendarTrigger")
+            .text_child(
+                "StartBoundary",
+                &format!("{}T{:02}:{:02}:00", start_date, self.hour, self.minute),
+            )
+            .text_child("Enabled", "true")
+            .child(schedule);
+
+        // SYSTEM 账户，不需要用户登录
+        let principal = Element::new("Principal")
+            .attr("id", "Author")
+            .text_child("UserId", "S-1-5-18")
+            .text_child("RunLevel", "HighestAvailable");
+
+        let settings = Element::new("Settings")
+            .text_child("MultipleInstancesPolicy", "IgnoreNew")
+            .text_child("DisallowStartIfOnBatteries", "false")
+            .text_child("StopIfGoingOnBatteries", "false")
+            // 错过计划时间（关机）时在下次开机后补做
+            .text_child("StartWhenAvailable", "true")
+            .text_child("ExecutionTimeLimit", EXECUTION_TIME_LIMIT)
+            .text_child("Enabled", "true");
+
+        let arguments: Vec<String> = self.arguments().iter().map(|a| quote_argument(a)).collect();
+        // 按 Windows 路径取程序所在目录
+        let working_dir = program.rsplit_once(['\\', '/']).map(|(dir, _)| dir).unwrap_or_default();
+        let exec = Element::new("Exec")
+            .text_child("Command", program)
+            .text_child("Arguments", &arguments.join(" "))
+            .text_child("WorkingDirectory", working_dir);
+
+        let task = Element::new("Task")
+            .attr("version", "1.2")
+            .attr("xmlns", "http://schemas.microsoft.com/windows/2004/02/mit/task")
+            .child(Element::new("RegistrationInfo").text_child("Description", &self.describe()))
+            .child(Element::new("Triggers").child(trigger))
+            .child(Element::new("Principals").child(principal))
+            .child(settings)
+            .child(Element::new("Actions").attr("Context", "Author").child(exec));
+
+        // 声明的编码须与写入文件的 UTF-16 一致
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n");
+        task.write_to(&mut xml, 0);
+        xml.push('\n');
+        xml
+    }
+
+    /// 写入任务定义文件（任务计划程序要求 UTF-16）
+    pub fn write_task_xml(&self, path: &Path, program: &str, start_date: &str) -> Result<()> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(
+            self.render_task_xml(program, start_date)
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        );
+        std::fs::write(path, bytes).with_context(|| format!("写入任务定义失败 {}", path.display()))
+    }
+}
+
+/// 盘符字母，如 "D:\" -> "D"
+fn drive_letter(partition: &str) -> String {
+    partition.trim_end_matches(['\\', '/', ':']).to_uppercase()
+}
+
+/// 按 Windows 命令行规则给参数加引号
+fn quote_argument(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // 引号前的反斜杠和引号本身都要转义
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+                continue;
+            }
+            _ => {}
+        }
+        if c != '\\' {
+            quoted.extend(std::iter::repeat_n('\\', backslashes));
+            backslashes = 0;
+            quoted.push(c);
+        }
+    }
+    // 结尾的反斜杠后面紧跟闭合引号，需要加倍
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule() -> BackupSchedule {
+        BackupSchedule {
+            frequency: Frequency::Weekly(Weekday::Sunday),
+            hour: 3,
+            minute: 5,
+            source_partition: "d:".to_string(),
+            target_folder: "E:\\My Backups\\".to_string(),
+            retention: Some(RetentionPolicy::KeepLast(4)),
+            verify: false,
+            format: BackupFormat::Wim,
+            compression: Compression::Fast,
+            use_default_exclusions: true,
+            exclusions: vec!["*.iso".to_string()],
+            compression_exclusions: Vec::new(),
+            pe_file: None,
+        }
+    }
+
+    #[test]
+    fn test_arguments() {
+        let schedule = schedule();
+        assert_eq!(schedule.task_name(), "LetRecovery\\备份_D");
+        assert_eq!(schedule.image_path(), "E:\\My Backups\\LetRecovery_D.wim");
+        assert_eq!(
+            schedule.arguments().join(" "),
+            "backup --source d: --dest E:\\My Backups\\LetRecovery_D.wim --name 计划备份 --incremental --format wim --compression fast --default-exclusions on --exclude *.iso --retention last:4"
+        );
+        assert_eq!(schedule.describe(), "每周日 03:05 备份 d: 到 E:\\My Backups\\LetRecovery_D.wim，保留最近 4 次备份");
+
+        let dedup = BackupSchedule {
+            frequency: Frequency::Weekly(Weekday::Monday),
+            format: BackupFormat::Dedup,
+            use_default_exclusions: false,
+            exclusions: Vec::new(),
+            compression_exclusions: vec!["*.vhdx".to_string()],
+            ..schedule.clone()
+        };
+        assert_eq!(dedup.image_path(), "E:\\My Backups\\LetRecovery_D");
+        assert!(dedup
+            .arguments()
+            .join(" ")
+            .contains("--format dedup --compression fast --default-exclusions off --no-compress *.vhdx"));
+        assert!(!dedup.arguments().contains(&"--profile".to_string()));
+        assert!(dedup.describe().starts_with("每周一 03:05"));
+
+        assert_eq!(quote_argument("C:"), "C:");
+        assert_eq!(quote_argument(""), "\"\"");
+        assert_eq!(quote_argument("a b\\"), "\"a b\\\\\"");
+        assert_eq!(quote_argument("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_argument("C:\\a b\\c"), "\"C:\\a b\\c\"");
+    }
+
+    #[test]
+    fn test_render_task_xml() {
+        let mut schedule = schedule();
+        let xml = schedule.render_task_xml("C:\\Tools\\LetRecovery.exe", "2026-10-18");
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n<Task version=\"1.2\""));
+        let task = crate::unattend::xml::parse(&xml).unwrap();
+        let text = |path: &[&str]| {
+            let mut element = &task;
+            for name in path {
+                element = element.find(name).unwrap_or_else(|| panic!("缺少 {}", name));
+            }
+            element.text_content()
+        };
+        assert_eq!(text(&["Triggers", "CalendarTrigger", "StartBoundary"]), "2026-10-18T03:05:00");
+        let week = task.find("Triggers").and_then(|t| t.find("CalendarTrigger")).and_then(|t| t.find("ScheduleByWeek"));
+        assert!(week.and_then(|w| w.find("DaysOfWeek")).and_then(|d| d.find("Sunday")).is_some());
+        assert_eq!(text(&["Principals", "Principal", "UserId"]), "S-1-5-18");
+        assert_eq!(text(&["Actions", "Exec", "Command"]), "C:\\Tools\\LetRecovery.exe");
+        assert_eq!(
+            text(&["Actions", "Exec", "Arguments"]),
+            "backup --source d: --dest \"E:\\My Backups\\LetRecovery_D.wim\" --name 计划备份 --incremental --format wim --compression fast --default-exclusions on --exclude *.iso --retention last:4"
+        );
+        assert_eq!(text(&["Actions", "Exec", "WorkingDirectory"]), "C:\\Tools");
+        assert!(xml.contains("&quot;E:\\My Backups\\LetRecovery_D.wim&quot;"));
+
+        // 系统分区排入 PE 执行
+        schedule.frequency = Frequency::Daily;
+        schedule.verify = true;
+        schedule.pe_file = Some("WinPE.wim".to_string());
+        let task = crate::unattend::xml::parse(&schedule.render_task_xml("C:\\LetRecovery.exe", "2026-10-18")).unwrap();
+        let trigger = task.find("Triggers").and_then(|t| t.find("CalendarTrigger")).unwrap();
+        assert_eq!(trigger.find("ScheduleByDay").and_then(|d| d.find("DaysInterval")).unwrap().text_content(), "1");
+        assert!(trigger.find("ScheduleByWeek").is_none());
+        let exec = task.find("Actions").and_then(|a| a.find("Exec")).unwrap();
+        assert!(exec.find("Arguments").unwrap().text_content().ends_with("--verify --default-exclusions on --exclude *.iso --retention last:4 --pe WinPE.wim"));
+        assert!(task.find("RegistrationInfo").unwrap().find("Description").unwrap().text_content().ends_with("下次重启后才在 PE 中执行"));
+    }
+}
@@ -0,0 +1,307 @@
+//! 备份验证
+//!
+//! 捕获或追加完成后重新读取镜像：按完整性表校验数据，确认 XML 描述中有新卷
+//! 的记录，并把卷的文件数和总字节数与源分区比较。结果以文本追加到镜像旁的
+//! 验证报告中，多次增量备份的验证记录保存在同一个文件里。
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::exclusions::ExclusionConfig;
+use crate::wim::{self, IntegrityStatus, WimImage};
+use crate::workflow::Progress;
+
+/// 验证报告文件的扩展名（追加在镜像文件名之后）
+pub const REPORT_EXTENSION: &str = "verify.txt";
+
+/// 文件数或总字节数相差超过该百分比时给出警告
+///
+/// 源分区在备份后仍可能变化（日志、临时文件），也有 DISM 与目录遍历
+/// 计数方式不同的文件，小的差异不视为问题。
+const MISMATCH_WARNING_PERCENT: u64 = 2;
+
+/// 完整性校验占验证进度的比例，其余为扫描源分区
+const INTEGRITY_PROGRESS_SHARE: u64 = 90;
+
+/// 源分区的统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VolumeStats {
+    pub file_count: u64,
+    pub dir_count: u64,
+    pub total_bytes: u64,
+    /// 无法读取的目录数
+    pub unreadable: u64,
+}
+
+/// 遍历分区统计文件数和字节数
+///
+/// 不进入符号链接和目录联接，跳过捕获时排除的文件和目录。
+pub fn scan_volume(root: &Path, exclusions: &ExclusionConfig) -> VolumeStats {
+    let mut stats = VolumeStats::default();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+
+    while let Some((dir, relative)) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => {
+                stats.unreadable += 1;
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = if relative.is_empty() {
+                name
+            } else {
+                format!("{}\\{}", relative, name)
+            };
+            if exclusions.is_excluded(&relative) {
+                continue;
+            }
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            if metadata.file_type().is_symlink() {
+                continue;
+            }
+            if metadata.is_dir() {
+                stats.dir_count += 1;
+                pending.push((entry.path(), relative));
+            } else {
+                stats.file_count += 1;
+                stats.total_bytes += metadata.len();
+            }
+        }
+    }
+    stats
+}
+
+/// 验证结果
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub image_path: PathBuf,
+    pub source_root: PathBuf,
+    /// 被验证的卷（镜像中的最后一卷）
+    pub index: u32,
+    /// XML 描述中的卷信息，缺失时为 None
+    pub image: Option<WimImage>,
+    pub integrity: IntegrityStatus,
+    pub source: VolumeStats,
+    /// 验证时间（FILETIME）
+    pub verified_at: u64,
+    /// 备份不可用的问题
+    pub errors: Vec<String>,
+    /// 需要注意但不影响使用的问题
+    pub warnings: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn passed(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// 生成报告文本
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            "==== 备份验证报告 ====".to_string(),
+            format!("时间: {} (UTC)", wim::format_filetime(self.verified_at)),
+            format!("镜像: {}", self.image_path.display()),
+            format!("源分区: {}", self.source_root.display()),
+        ];
+        match &self.image {
+            Some(image) => {
+                lines.push(format!("卷: {} \"{}\"", image.index, image.name));
+                lines.push(format!(
+                    "文件数: 镜像 {}，源分区 {}",
+                    image.file_count, self.source.file_count
+                ));
+                lines.push(format!(
+                    "总字节: 镜像 {}，源分区 {}",
+                    image.total_bytes, self.source.total_bytes
+                ));
+            }
+            None => lines.push(format!("卷: {} (无记录)", self.index)),
+        }
+        lines.push(format!(
+            "完整性: {}",
+            match &self.integrity {
+                IntegrityStatus::Valid { chunks } => format!("通过（{} 块）", chunks),
+                IntegrityStatus::NotPresent => "镜像没有完整性表，未校验".to_string(),
+                IntegrityStatus::Corrupt(reason) => format!("失败，{}", reason),
+            }
+        ));
+        if self.source.unreadable > 0 {
+            lines.push(format!("无法读取的目录: {}", self.source.unreadable));
+        }
+        lines.push(format!(
+            "结果: {}",
+            match (self.passed(), self.warnings.len()) {
+                (false, _) => "失败".to_string(),
+                (true, 0) => "通过".to_string(),
+                (true, n) => format!("通过（{} 个警告）", n),
+            }
+        ));
+        lines.extend(self.errors.iter().map(|e| format!("错误: {}", e)));
+        lines.extend(self.warnings.iter().map(|w| format!("警告: {}", w)));
+
+        let mut text = lines.join("\r\n");
+        text.push_str("\r\n\r\n");
+        text
+    }
+
+    /// 镜像对应的报告路径，如 "D:\backup.wim.verify.txt"
+    pub fn path_for(image_path: &Path) -> PathBuf {
+        let mut name = image_path.as_os_str().to_os_string();
+        name.push(".");
+        name.push(REPORT_EXTENSION);
+        PathBuf::from(name)
+    }
+
+    /// 把报告追加到镜像旁的报告文件，返回文件路径
+    pub fn append_to_file(&self) -> Result<PathBuf> {
+        use std::io::Write;
+
+        let path = Self::path_for(&self.image_path);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("无法写入验证报告 {}", path.display()))?;
+        file.write_all(self.render().as_bytes())?;
+        Ok(path)
+    }
+}
+
+/// 验证镜像中最后一卷（刚捕获或追加的卷）
+///
+/// 镜像无法打开或不是 WIM 时返回错误，其余问题记录在报告中。
+pub fn verify_backup(
+    image_path: &Path,
+    source_root: &Path,
+    expected_name: &str,
+    exclusions: &ExclusionConfig,
+    progress: Progress,
+) -> Result<VerifyReport> {
+    let mut file =
+        std::fs::File::open(image_path).with_context(|| format!("无法打开镜像 {}", image_path.display()))?;
+    let header = wim::WimHeader::read(&mut file)?;
+
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    let index = header.image_count;
+    let image = match wim::read_xml(&mut file, &header).and_then(|xml| wim::parse_images(&xml)) {
+        Ok(images) => images.into_iter().find(|i| i.index == index),
+        Err(e) => {
+            errors.push(format!("无法读取镜像描述: {:#}", e));
+            None
+        }
+    };
+    if image.is_none() && errors.is_empty() {
+        errors.push(format!("镜像描述中没有第 {} 卷的记录", index));
+    }
+
+    let integrity = match wim::check_integrity(&mut file, &header, &|p| {
+        progress((p as u64 * INTEGRITY_PROGRESS_SHARE / 100) as u8)
+    }) {
+        Ok(status) => status,
+        Err(e) => IntegrityStatus::Corrupt(format!("{:#}", e)),
+    };
+    match &integrity {
+        IntegrityStatus::Corrupt(reason) => errors.push(format!("完整性校验失败: {}", reason)),
+        IntegrityStatus::NotPresent => warnings.push("镜像没有完整性表，未校验数据".to_string()),
+        IntegrityStatus::Valid { .. } => {}
+    }
+
+    let source = scan_volume(source_root, exclusions);
+    progress(100);
+
+    if let Some(image) = &image {
+        if !expected_name.is_empty() && image.name != expected_name {
+            warnings.push(format!("卷名称为 \"{}\"，与备份名称 \"{}\" 不同", image.name, expected_name));
+        }
+        if let Some(warning) = compare("文件数", image.file_count, source.file_count) {
+            warnings.push(warning);
+        }
+        if let Some(warning) = compare("总字节数", image.total_bytes, source.total_bytes) {
+            warnings.push(warning);
+        }
+    }
+    if source.unreadable > 0 {
+        warnings.push(format!("源分区中有 {} 个目录无法读取，统计可能偏小", source.unreadable));
+    }
+
+    Ok(VerifyReport {
+        image_path: image_path.to_path_buf(),
+        source_root: source_root.to_path_buf(),
+        index,
+        image,
+        integrity,
+        source,
+        verified_at: wim::filetime_now(),
+        errors,
+        warnings,
+    })
+}
+
+/// 相差超过 [`MISMATCH_WARNING_PERCENT`] 时返回警告
+fn compare(what: &str, image: u64, source: u64) -> Option<String> {
+    let diff = image.abs_diff(source);
+    if diff * 100 > source.max(1) * MISMATCH_WARNING_PERCENT {
+        Some(format!("镜像{}为 {}，源分区为 {}", what, image, source))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wim::build_test_wim;
+
+    #[test]
+    fn test_verify_backup() {
+        let dir = std::env::temp_dir().join(format!("lr_verify_{}", std::process::id()));
+        let source = dir.join("source");
+        std::fs::create_dir_all(source.join("Users").join("a")).unwrap();
+        std::fs::create_dir_all(source.join("System Volume Information")).unwrap();
+        std::fs::write(source.join("Users").join("a").join("doc.txt"), [0u8; 100]).unwrap();
+        std::fs::write(source.join("boot.ini"), [0u8; 50]).unwrap();
+        std::fs::write(source.join("pagefile.sys"), [0u8; 4000]).unwrap();
+        std::fs::write(source.join("System Volume Information").join("x"), [0u8; 10]).unwrap();
+
+        let stats = scan_volume(&source, &ExclusionConfig::default());
+        assert_eq!(stats, VolumeStats { file_count: 2, dir_count: 2, total_bytes: 150, unreadable: 0 });
+
+        let xml = "<WIM><IMAGE INDEX=\"1\"><FILECOUNT>1</FILECOUNT><NAME>旧</NAME></IMAGE>\
+            <IMAGE INDEX=\"2\"><FILECOUNT>2</FILECOUNT><TOTALBYTES>150</TOTALBYTES><NAME>系统备份</NAME></IMAGE></WIM>";
+        let image = dir.join("backup.wim");
+        std::fs::write(&image, build_test_wim(xml, &[7; 500], Some(64))).unwrap();
+
+        let report = verify_backup(&image, &source, "系统备份", &ExclusionConfig::default(), &|_| {}).unwrap();
+        assert!(report.passed());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(report.index, 2);
+        assert_eq!(report.integrity, IntegrityStatus::Valid { chunks: 9 });
+
+        // 文件数不符只是警告；数据损坏是错误
+        std::fs::write(source.join("new.log"), [0u8; 1]).unwrap();
+        let mut bytes = std::fs::read(&image).unwrap();
+        bytes[300] ^= 1;
+        std::fs::write(&image, bytes).unwrap();
+        let report = verify_backup(&image, &source, "系统备份", &ExclusionConfig::default(), &|_| {}).unwrap();
+        assert_eq!(report.errors, ["完整性校验失败: 第 2 块数据校验失败"]);
+        assert_eq!(report.warnings, ["镜像文件数为 2，源分区为 3"]);
+
+        let path = report.append_to_file().unwrap();
+        report.append_to_file().unwrap();
+        assert_eq!(path, dir.join("backup.wim.verify.txt"));
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.matches("==== 备份验证报告 ====").count(), 2);
+        assert!(text.contains("卷: 2 \"系统备份\"\r\n文件数: 镜像 2，源分区 3\r\n"));
+        assert!(text.contains("结果: 失败\r\n"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
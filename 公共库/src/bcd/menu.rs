@@ -0,0 +1,181 @@
+//! 启动菜单管理
+//!
+//! 在解析后的 BCD 存储上整理启动菜单：列出菜单项、识别失效项和
+//! LetRecovery 遗留的 PE 启动项，并计算删除时需要一并清理的对象。
+//! 实际修改由调用方通过 bcdedit 完成。
+
+use super::{elements, well_known, BcdObject, BcdStore, Device, ElementValue, Guid, ObjectKind, PartitionId};
+
+/// LetRecovery PE 文件所在目录（相对于分区根目录）
+pub const LETRECOVERY_PE_DIR: &str = "\\LetRecovery_PE\\";
+
+/// 启动菜单项
+#[derive(Debug, Clone)]
+pub struct MenuEntry<'a> {
+    pub object: &'a BcdObject,
+    /// 是否在启动菜单中显示
+    pub in_display_order: bool,
+    /// 是否为默认启动项
+    pub is_default: bool,
+    /// 是否为 LetRecovery 创建的 PE 启动项
+    pub is_letrecovery_pe: bool,
+    /// 所在分区已不存在
+    pub is_stale: bool,
+}
+
+impl MenuEntry<'_> {
+    /// 启动项所在设备，系统加载器优先取 osdevice
+    pub fn device(&self) -> Option<&Device> {
+        self.object.os_device().or_else(|| self.object.device())
+    }
+
+    /// 是否建议删除
+    pub fn is_removable(&self) -> bool {
+        self.is_stale || self.is_letrecovery_pe
+    }
+}
+
+/// 可以出现在启动菜单中的对象
+fn is_menu_object(object: &BcdObject) -> bool {
+    matches!(
+        object.kind(),
+        ObjectKind::OsLoader
+            | ObjectKind::Resume
+            | ObjectKind::MemoryDiagnostic
+            | ObjectKind::LegacyLoader
+            | ObjectKind::BootSector
+    )
+}
+
+/// 是否为 LetRecovery 创建的 PE 启动项
+pub fn is_letrecovery_pe(object: &BcdObject) -> bool {
+    object.is_winpe()
+        && matches!(
+            object.os_device().or_else(|| object.device()),
+            Some(Device::File { path, .. })
+                if path.to_ascii_lowercase().starts_with(&LETRECOVERY_PE_DIR.to_ascii_lowercase())
+        )
+}
+
+impl BcdStore {
+    /// 启动菜单项：先按显示顺序，再列出不在菜单中的启动项
+    ///
+    /// `present` 为当前存在的分区，用于识别失效项；为空时不做判断。
+    pub fn menu_entries(&self, present: &[PartitionId]) -> Vec<MenuEntry<'_>> {
+        let order = self.display_order();
+        let default = self.default_entry();
+
+        let in_order = order.iter().filter_map(|id| self.object(id));
+        let others = self
+            .objects
+            .iter()
+            .filter(|o| is_menu_object(o) && !order.contains(&o.id));
+
+        in_order
+            .chain(others)
+            .map(|object| {
+                let partition = object
+                    .os_device()
+                    .or_else(|| object.device())
+                    .and_then(Device::partition);
+                MenuEntry {
+                    object,
+                    in_display_order: order.contains(&object.id),
+                    is_default: Some(object.id) == default,
+                    is_letrecovery_pe: is_letrecovery_pe(object),
+                    is_stale: !present.is_empty() && partition.is_some_and(|p| !present.contains(p)),
+                }
+            })
+            .collect()
+    }
+
+    /// 删除启动项时需要一并删除的对象：启动项本身，
+    /// 以及只被它引用的 ramdisk 选项等设备对象
+    pub fn removal_set(&self, id: &Guid) -> Vec<Guid> {
+        let mut removal = vec![*id];
+        let Some(object) = self.object(id) else {
+            return removal;
+        };
+
+        for options in referenced_options(object) {
+            let shared = self
+                .objects
+                .iter()
+                .any(|o| o.id != *id && referenced_options(o).contains(&options));
+            let is_device = self
+                .object(&options)
+                .is_some_and(|o| o.kind() == ObjectKind::Device);
+            if is_device && !shared && well_known::alias(&options).is_none() && !removal.contains(&options) {
+                removal.push(options);
+            }
+        }
+        removal
+    }
+}
+
+/// 对象的设备元素引用的附加选项对象
+fn referenced_options(object: &BcdObject) -> Vec<Guid> {
+    [elements::DEVICE, elements::OSLOADER_OS_DEVICE]
+        .iter()
+        .filter_map(|e| match object.element(*e) {
+            Some(ElementValue::Device(Device::File {
+                ramdisk_options: Some(options),
+                ..
+            })) => Some(*options),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{gpt, sample_store, PE, RAMDISK, WIN10, WIN7};
+    use super::*;
+
+    fn guid(s: &str) -> Guid {
+        Guid::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_menu_entries() {
+        let store = sample_store();
+        let entries = store.menu_entries(&[gpt(1)]);
+        let ids: Vec<_> = entries.iter().map(|e| e.object.id).collect();
+        assert_eq!(ids, [guid(PE), guid(WIN10), guid(WIN7)]);
+
+        let pe = &entries[0];
+        assert!(pe.is_letrecovery_pe && !pe.is_default && pe.is_removable());
+        let win10 = &entries[1];
+        assert!(win10.is_default && !win10.is_stale && !win10.is_removable());
+        assert_eq!(win10.device(), Some(&Device::Partition(gpt(1))));
+        // Windows 7 所在分区已不存在
+        assert!(entries[2].is_stale);
+
+        // 无法获取分区列表时不判断失效
+        assert!(store.menu_entries(&[]).iter().all(|e| !e.is_stale));
+    }
+
+    #[test]
+    fn test_entries_outside_display_order() {
+        let mut store = sample_store();
+        let bootmgr = store
+            .objects
+            .iter_mut()
+            .find(|o| o.id == well_known::BOOTMGR)
+            .unwrap();
+        bootmgr.elements.insert(
+            elements::BOOTMGR_DISPLAY_ORDER,
+            ElementValue::ObjectList(vec![guid(WIN10)]),
+        );
+        let entries = store.menu_entries(&[]);
+        let listed: Vec<_> = entries.iter().map(|e| (e.object.id, e.in_display_order)).collect();
+        assert_eq!(listed, [(guid(WIN10), true), (guid(WIN7), false), (guid(PE), false)]);
+    }
+
+    #[test]
+    fn test_removal_set() {
+        let store = sample_store();
+        assert_eq!(store.removal_set(&guid(PE)), [guid(PE), guid(RAMDISK)]);
+        assert_eq!(store.removal_set(&guid(WIN10)), [guid(WIN10)]);
+    }
+}
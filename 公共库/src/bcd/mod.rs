@@ -0,0 +1,1002 @@
+//! BCD 存储解析
+//!
+//! 直接读取 BCD 配置单元（`bcdedit /export` 导出的文件或 EFI 分区中的 `BCD`），
+//! 得到类型化的引导对象，不再依赖随系统语言变化的 `bcdedit /enum` 输出。
+//!
+//! 存储结构：`Objects\{GUID}\Description\Type` 为对象类型，
+//! `Objects\{GUID}\Elements\<元素类型>\Element` 为各个元素的值。
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::regf::{Hive, Key};
+
+pub mod menu;
+
+/// GUID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl Guid {
+    pub const NIL: Guid = Guid::from_fields(0, 0, 0, [0; 8]);
+
+    pub const fn from_fields(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        Self {
+            data1,
+            data2,
+            data3,
+            data4,
+        }
+    }
+
+    /// 从 Windows 内存布局（前三段小端）读取
+    pub fn from_bytes_le(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..16)?;
+        Some(Self {
+            data1: u32::from_le_bytes(bytes[0..4].try_into().ok()?),
+            data2: u16::from_le_bytes(bytes[4..6].try_into().ok()?),
+            data3: u16::from_le_bytes(bytes[6..8].try_into().ok()?),
+            data4: bytes[8..16].try_into().ok()?,
+        })
+    }
+
+    /// 转为 Windows 内存布局
+    pub fn to_bytes_le(&self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[0..4].copy_from_slice(&self.data1.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.data2.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.data3.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.data4);
+        bytes
+    }
+
+    /// 解析 `{xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}`，花括号可省略
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let s = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')).unwrap_or(s);
+        let parts: Vec<&str> = s.split('-').collect();
+        let lens = [8, 4, 4, 4, 12];
+        if parts.len() != 5
+            || parts.iter().zip(lens).any(|(p, len)| {
+                p.len() != len || !p.bytes().all(|b| b.is_ascii_hexdigit())
+            })
+        {
+            return None;
+        }
+        let tail = format!("{}{}", parts[3], parts[4]);
+        let mut data4 = [0u8; 8];
+        for (i, byte) in data4.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&tail[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        Some(Self {
+            data1: u32::from_str_radix(parts[0], 16).ok()?,
+            data2: u16::from_str_radix(parts[1], 16).ok()?,
+            data3: u16::from_str_radix(parts[2], 16).ok()?,
+            data4,
+        })
+    }
+
+    pub fn is_nil(&self) -> bool {
+        *self == Self::NIL
+    }
+}
+
+impl std::fmt::Display for Guid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let d = &self.data4;
+        write!(
+            f,
+            "{{{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}}}",
+            self.data1, self.data2, self.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+        )
+    }
+}
+
+/// 系统预定义的对象标识符
+pub mod well_known {
+    use super::Guid;
+
+    /// {fwbootmgr}
+    pub const FW_BOOTMGR: Guid =
+        Guid::from_fields(0xa5a30fa2, 0x3d06, 0x4e9f, [0xb5, 0xf4, 0xa0, 0x1d, 0xf9, 0xd1, 0xfc, 0xba]);
+    /// {bootmgr}
+    pub const BOOTMGR: Guid =
+        Guid::from_fields(0x9dea862c, 0x5cdd, 0x4e70, [0xac, 0xc1, 0xf3, 0x2b, 0x34, 0x4d, 0x47, 0x95]);
+    /// {memdiag}
+    pub const MEMDIAG: Guid =
+        Guid::from_fields(0xb2721d73, 0x1db4, 0x4c62, [0xbf, 0x78, 0xc5, 0x48, 0xa8, 0x80, 0x14, 0x2d]);
+    /// {ntldr}
+    pub const NTLDR: Guid =
+        Guid::from_fields(0x466f5a88, 0x0af2, 0x4f76, [0x90, 0x38, 0x09, 0x5b, 0x17, 0x0d, 0xc2, 0x1c]);
+    /// {ramdiskoptions}
+    pub const RAMDISK_OPTIONS: Guid =
+        Guid::from_fields(0xae5534e0, 0xa924, 0x466c, [0xb8, 0x36, 0x75, 0x85, 0x39, 0xa3, 0xee, 0x3a]);
+    /// {globalsettings}
+    pub const GLOBAL_SETTINGS: Guid =
+        Guid::from_fields(0x7ea2e1ac, 0x2e61, 0x4728, [0xaa, 0xa3, 0x89, 0x6d, 0x9d, 0x0a, 0x9f, 0x0e]);
+    /// {bootloadersettings}
+    pub const BOOTLOADER_SETTINGS: Guid =
+        Guid::from_fields(0x6efb52bf, 0x1766, 0x41db, [0xa6, 0xb3, 0x0e, 0xe5, 0xef, 0xf7, 0x2b, 0xd7]);
+    /// {resumeloadersettings}
+    pub const RESUMELOADER_SETTINGS: Guid =
+        Guid::from_fields(0x1afa9c49, 0x16ab, 0x4a5c, [0x90, 0x1b, 0x21, 0x28, 0x02, 0xda, 0x94, 0x60]);
+    /// {emssettings}
+    pub const EMS_SETTINGS: Guid =
+        Guid::from_fields(0x0ce4991b, 0xe6b3, 0x4b16, [0xb2, 0x3c, 0x5e, 0x0d, 0x92, 0x50, 0xe5, 0xd9]);
+    /// {dbgsettings}
+    pub const DBG_SETTINGS: Guid =
+        Guid::from_fields(0x4636856e, 0x540f, 0x4170, [0xa1, 0x30, 0xa8, 0x47, 0x76, 0xf4, 0xc6, 0x54]);
+    /// {badmemory}
+    pub const BAD_MEMORY: Guid =
+        Guid::from_fields(0x5189b25c, 0x5558, 0x4bf2, [0xbc, 0xa4, 0x28, 0x9b, 0x11, 0xbd, 0x29, 0xe2]);
+    /// {hypervisorsettings}
+    pub const HYPERVISOR_SETTINGS: Guid =
+        Guid::from_fields(0x7ff607e0, 0x4395, 0x11db, [0xb0, 0xde, 0x08, 0x00, 0x20, 0x0c, 0x9a, 0x66]);
+
+    const ALIASES: &[(Guid, &str)] = &[
+        (FW_BOOTMGR, "{fwbootmgr}"),
+        (BOOTMGR, "{bootmgr}"),
+        (MEMDIAG, "{memdiag}"),
+        (NTLDR, "{ntldr}"),
+        (RAMDISK_OPTIONS, "{ramdiskoptions}"),
+        (GLOBAL_SETTINGS, "{globalsettings}"),
+        (BOOTLOADER_SETTINGS, "{bootloadersettings}"),
+        (RESUMELOADER_SETTINGS, "{resumeloadersettings}"),
+        (EMS_SETTINGS, "{emssettings}"),
+        (DBG_SETTINGS, "{dbgsettings}"),
+        (BAD_MEMORY, "{badmemory}"),
+        (HYPERVISOR_SETTINGS, "{hypervisorsettings}"),
+    ];
+
+    /// bcdedit 显示的别名，如 `{bootmgr}`
+    pub fn alias(guid: &Guid) -> Option<&'static str> {
+        ALIASES.iter().find(|(g, _)| g == guid).map(|(_, a)| *a)
+    }
+}
+
+/// 元素类型
+///
+/// 同一数值在不同类型的对象中含义可能不同（如 0x23000003 在启动管理器中是
+/// 默认项，在系统加载器中是休眠恢复对象），读取时需结合对象类型。
+pub mod elements {
+    /// 应用程序所在设备
+    pub const DEVICE: u32 = 0x11000001;
+    /// 应用程序路径
+    pub const PATH: u32 = 0x12000002;
+    /// 描述
+    pub const DESCRIPTION: u32 = 0x12000004;
+    /// 语言
+    pub const LOCALE: u32 = 0x12000005;
+    /// 继承的对象
+    pub const INHERIT: u32 = 0x14000006;
+
+    /// 启动菜单显示顺序
+    pub const BOOTMGR_DISPLAY_ORDER: u32 = 0x24000001;
+    /// 一次性启动顺序
+    pub const BOOTMGR_BOOT_SEQUENCE: u32 = 0x24000002;
+    /// 默认启动项
+    pub const BOOTMGR_DEFAULT: u32 = 0x23000003;
+    /// 菜单等待时间（秒）
+    pub const BOOTMGR_TIMEOUT: u32 = 0x25000004;
+    /// 工具菜单显示顺序
+    pub const BOOTMGR_TOOLS_DISPLAY_ORDER: u32 = 0x24000010;
+
+    /// 系统所在设备
+    pub const OSLOADER_OS_DEVICE: u32 = 0x21000001;
+    /// 系统目录
+    pub const OSLOADER_SYSTEM_ROOT: u32 = 0x22000002;
+    /// 检测 HAL
+    pub const OSLOADER_DETECT_HAL: u32 = 0x26000010;
+    /// WinPE 模式
+    pub const OSLOADER_WINPE: u32 = 0x26000022;
+
+    /// ramdisk SDI 文件所在设备
+    pub const RAMDISK_SDI_DEVICE: u32 = 0x31000003;
+    /// ramdisk SDI 文件路径
+    pub const RAMDISK_SDI_PATH: u32 = 0x32000004;
+}
+
+/// 对象类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    /// 固件启动管理器 {fwbootmgr}
+    FirmwareBootManager,
+    /// Windows 启动管理器 {bootmgr}
+    BootManager,
+    /// Windows 系统加载器（winload）
+    OsLoader,
+    /// 休眠恢复
+    Resume,
+    /// 内存诊断
+    MemoryDiagnostic,
+    /// 旧版系统加载器（ntldr）
+    LegacyLoader,
+    /// 引导扇区
+    BootSector,
+    /// 可继承的设置
+    Inherit,
+    /// 设备选项（如 ramdisk 选项）
+    Device,
+    /// 其它类型
+    Other,
+}
+
+impl ObjectKind {
+    /// 根据 `Description\Type` 判断
+    pub fn from_type(object_type: u32) -> Self {
+        match object_type >> 28 {
+            1 => match object_type & 0x000F_FFFF {
+                1 => ObjectKind::FirmwareBootManager,
+                2 => ObjectKind::BootManager,
+                3 => ObjectKind::OsLoader,
+                4 => ObjectKind::Resume,
+                5 => ObjectKind::MemoryDiagnostic,
+                6 => ObjectKind::LegacyLoader,
+                8 => ObjectKind::BootSector,
+                _ => ObjectKind::Other,
+            },
+            2 => ObjectKind::Inherit,
+            3 => ObjectKind::Device,
+            _ => ObjectKind::Other,
+        }
+    }
+
+    /// 界面显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            ObjectKind::FirmwareBootManager => "固件启动管理器",
+            ObjectKind::BootManager => "启动管理器",
+            ObjectKind::OsLoader => "系统加载器",
+            ObjectKind::Resume => "休眠恢复",
+            ObjectKind::MemoryDiagnostic => "内存诊断",
+            ObjectKind::LegacyLoader => "旧版加载器",
+            ObjectKind::BootSector => "引导扇区",
+            ObjectKind::Inherit => "继承设置",
+            ObjectKind::Device => "设备选项",
+            ObjectKind::Other => "其它",
+        }
+    }
+}
+
+/// 分区标识，与 BCD 中 partition 设备的存储方式一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionId {
+    /// MBR 磁盘：磁盘签名 + 分区起始字节偏移
+    Mbr { disk_signature: u32, offset: u64 },
+    /// GPT 磁盘：磁盘 GUID + 分区 GUID
+    Gpt { disk_id: Guid, partition_id: Guid },
+}
+
+impl PartitionId {
+    /// 从 `IOCTL_DISK_GET_DRIVE_LAYOUT_EX` 返回的 DRIVE_LAYOUT_INFORMATION_EX 中
+    /// 取出指定分区号的标识
+    pub fn from_drive_layout(layout: &[u8], partition_number: u32) -> Option<Self> {
+        /// 分区表头部长度（含 MBR/GPT 联合体）
+        const HEADER: usize = 48;
+        /// PARTITION_INFORMATION_EX 长度
+        const ENTRY: usize = 144;
+
+        let style = read_u32(layout, 0)?;
+        let count = read_u32(layout, 4)? as usize;
+        let entry = (0..count)
+            .map(|i| layout.get(HEADER + i * ENTRY..HEADER + (i + 1) * ENTRY))
+            .find(|e| e.and_then(|e| read_u32(e, 24)) == Some(partition_number))??;
+        let offset = u64::from_le_bytes(entry[8..16].try_into().ok()?);
+
+        match style {
+            0 => Some(PartitionId::Mbr {
+                disk_signature: read_u32(layout, 8)?,
+                offset,
+            }),
+            1 => Some(PartitionId::Gpt {
+                disk_id: Guid::from_bytes_le(&layout[8..])?,
+                partition_id: Guid::from_bytes_le(&entry[48..])?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PartitionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartitionId::Mbr {
+                disk_signature,
+                offset,
+            } => write!(f, "MBR 磁盘 {:08X} 偏移 {} MB", disk_signature, offset / 1024 / 1024),
+            PartitionId::Gpt { partition_id, .. } => write!(f, "GPT 分区 {}", partition_id),
+        }
+    }
+}
+
+/// 设备描述符类型
+const DEVICE_BOOT: u32 = 5;
+const DEVICE_PARTITION: u32 = 6;
+const DEVICE_LOCATE: u32 = 8;
+/// 设备描述符头部长度：类型、标志、长度、保留
+const DESCRIPTOR_HEADER: usize = 0x10;
+/// 分区描述符长度
+const PARTITION_DESCRIPTOR_SIZE: usize = 0x48;
+
+/// 设备
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Device {
+    /// 启动设备（boot）
+    Boot,
+    /// 分区
+    Partition(PartitionId),
+    /// 自动定位（locate）
+    Locate,
+    /// 文件或 ramdisk，如 `ramdisk=[C:]\sources\boot.wim,{ramdiskoptions}`
+    File {
+        /// ramdisk 选项对象，普通文件设备为 None
+        ramdisk_options: Option<Guid>,
+        /// 文件所在设备
+        parent: Option<Box<Device>>,
+        /// 文件路径
+        path: String,
+    },
+    /// 无法识别的设备
+    Unknown { kind: u32, data: Vec<u8> },
+}
+
+impl Device {
+    /// 解析设备元素：16 字节附加选项 GUID + 设备描述符
+    pub fn parse(data: &[u8]) -> Device {
+        let options = Guid::from_bytes_le(data).filter(|g| !g.is_nil());
+        let descriptor = data.get(16..).unwrap_or_default();
+        Self::parse_descriptor(descriptor, options)
+    }
+
+    fn parse_descriptor(descriptor: &[u8], options: Option<Guid>) -> Device {
+        let kind = read_u32(descriptor, 0).unwrap_or(0);
+        match kind {
+            DEVICE_BOOT => Device::Boot,
+            DEVICE_LOCATE => Device::Locate,
+            DEVICE_PARTITION => match parse_partition(descriptor) {
+                Some(id) => Device::Partition(id),
+                None => Device::Unknown {
+                    kind,
+                    data: descriptor.to_vec(),
+                },
+            },
+            _ => Self::parse_file(descriptor, options).unwrap_or_else(|| Device::Unknown {
+                kind,
+                data: descriptor.to_vec(),
+            }),
+        }
+    }
+
+    /// 文件型设备的内部布局未公开，这里按经验定位：
+    /// 头部之后依次为父设备描述符（partition 或 boot）和以 0 结尾的 UTF-16 路径。
+    fn parse_file(descriptor: &[u8], options: Option<Guid>) -> Option<Device> {
+        let (parent_at, parent) = (DESCRIPTOR_HEADER..descriptor.len().saturating_sub(DESCRIPTOR_HEADER))
+            .step_by(4)
+            .find_map(|at| {
+                let nested = &descriptor[at..];
+                let kind = read_u32(nested, 0)?;
+                let size = read_u32(nested, 8)? as usize;
+                let valid = matches!(kind, DEVICE_BOOT | DEVICE_PARTITION)
+                    && size == PARTITION_DESCRIPTOR_SIZE
+                    && nested.len() >= size;
+                valid.then(|| (at, Self::parse_descriptor(&nested[..size], None)))
+            })?;
+
+        let after = &descriptor[parent_at + PARTITION_DESCRIPTOR_SIZE..];
+        let units: Vec<u16> = after
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        let start = units.iter().position(|&u| u == u16::from(b'\\'))?;
+        let end = units[start..].iter().position(|&u| u == 0).map_or(units.len(), |e| start + e);
+        Some(Device::File {
+            ramdisk_options: options,
+            parent: Some(Box::new(parent)),
+            path: String::from_utf16_lossy(&units[start..end]),
+        })
+    }
+
+    /// 所在分区：分区设备本身，或文件设备的父分区
+    pub fn partition(&self) -> Option<&PartitionId> {
+        match self {
+            Device::Partition(id) => Some(id),
+            Device::File {
+                parent: Some(parent),
+                ..
+            } => parent.partition(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Device::Boot => write!(f, "boot"),
+            Device::Partition(id) => write!(f, "partition={}", id),
+            Device::Locate => write!(f, "locate"),
+            Device::File {
+                ramdisk_options,
+                parent,
+                path,
+            } => {
+                let parent = match parent.as_deref() {
+                    Some(Device::Partition(id)) => id.to_string(),
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                };
+                match ramdisk_options {
+                    Some(options) => write!(f, "ramdisk=[{}]{},{}", parent, path, options),
+                    None => write!(f, "file=[{}]{}", parent, path),
+                }
+            }
+            Device::Unknown { kind, .. } => write!(f, "未知设备 (类型 {})", kind),
+        }
+    }
+}
+
+fn parse_partition(descriptor: &[u8]) -> Option<PartitionId> {
+    if descriptor.len() < PARTITION_DESCRIPTOR_SIZE {
+        return None;
+    }
+    let payload = &descriptor[DESCRIPTOR_HEADER..];
+    match read_u32(payload, 0x18)? {
+        0 => Some(PartitionId::Gpt {
+            partition_id: Guid::from_bytes_le(payload)?,
+            disk_id: Guid::from_bytes_le(payload.get(0x1C..)?)?,
+        }),
+        1 => Some(PartitionId::Mbr {
+            offset: u64::from_le_bytes(payload.get(..8)?.try_into().ok()?),
+            disk_signature: read_u32(payload, 0x1C)?,
+        }),
+        _ => None,
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// 元素值，按元素类型的格式位（24-27 位）解码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementValue {
+    Device(Device),
+    String(String),
+    Object(Guid),
+    ObjectList(Vec<Guid>),
+    Integer(u64),
+    Boolean(bool),
+    IntegerList(Vec<u64>),
+    /// 无法按格式解码的原始数据
+    Raw(Vec<u8>),
+}
+
+impl ElementValue {
+    fn decode(element_type: u32, value: &crate::regf::Value) -> Self {
+        let raw = || ElementValue::Raw(value.data.clone());
+        let integer = |data: &[u8]| {
+            let mut bytes = [0u8; 8];
+            let len = data.len().min(8);
+            bytes[..len].copy_from_slice(&data[..len]);
+            u64::from_le_bytes(bytes)
+        };
+        match (element_type >> 24) & 0xF {
+            1 => ElementValue::Device(Device::parse(&value.data)),
+            2 => value.as_string().map(ElementValue::String).unwrap_or_else(raw),
+            3 => value
+                .as_string()
+                .and_then(|s| Guid::parse(&s))
+                .map(ElementValue::Object)
+                .unwrap_or_else(raw),
+            4 => value
+                .as_multi_string()
+                .map(|items| ElementValue::ObjectList(items.iter().filter_map(|s| Guid::parse(s)).collect()))
+                .unwrap_or_else(raw),
+            5 if !value.data.is_empty() => ElementValue::Integer(integer(&value.data)),
+            6 if !value.data.is_empty() => ElementValue::Boolean(value.data.iter().any(|&b| b != 0)),
+            7 if value.data.len().is_multiple_of(8) => {
+                ElementValue::IntegerList(value.data.chunks_exact(8).map(integer).collect())
+            }
+            _ => raw(),
+        }
+    }
+}
+
+/// 引导对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BcdObject {
+    pub id: Guid,
+    /// `Description\Type` 原始值
+    pub object_type: u32,
+    pub elements: BTreeMap<u32, ElementValue>,
+}
+
+impl BcdObject {
+    pub fn kind(&self) -> ObjectKind {
+        ObjectKind::from_type(self.object_type)
+    }
+
+    pub fn element(&self, element_type: u32) -> Option<&ElementValue> {
+        self.elements.get(&element_type)
+    }
+
+    fn string(&self, element_type: u32) -> Option<&str> {
+        match self.element(element_type)? {
+            ElementValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn device_element(&self, element_type: u32) -> Option<&Device> {
+        match self.element(element_type)? {
+            ElementValue::Device(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn object(&self, element_type: u32) -> Option<Guid> {
+        match self.element(element_type)? {
+            ElementValue::Object(g) => Some(*g),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn object_list(&self, element_type: u32) -> Vec<Guid> {
+        match self.element(element_type) {
+            Some(ElementValue::ObjectList(list)) => list.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 描述（启动菜单中显示的名称）
+    pub fn description(&self) -> Option<&str> {
+        self.string(elements::DESCRIPTION)
+    }
+
+    /// 应用程序所在设备
+    pub fn device(&self) -> Option<&Device> {
+        self.device_element(elements::DEVICE)
+    }
+
+    /// 应用程序路径
+    pub fn path(&self) -> Option<&str> {
+        self.string(elements::PATH)
+    }
+
+    /// 系统所在设备（仅系统加载器）
+    pub fn os_device(&self) -> Option<&Device> {
+        self.device_element(elements::OSLOADER_OS_DEVICE)
+    }
+
+    /// 系统目录（仅系统加载器）
+    pub fn system_root(&self) -> Option<&str> {
+        self.string(elements::OSLOADER_SYSTEM_ROOT)
+    }
+
+    /// 是否为 WinPE 引导项
+    pub fn is_winpe(&self) -> bool {
+        matches!(self.element(elements::OSLOADER_WINPE), Some(ElementValue::Boolean(true)))
+    }
+
+    /// bcdedit 中使用的标识符，预定义对象使用别名
+    pub fn identifier(&self) -> String {
+        well_known::alias(&self.id)
+            .map(str::to_string)
+            .unwrap_or_else(|| self.id.to_string())
+    }
+}
+
+/// BCD 存储
+#[derive(Debug, Clone, Default)]
+pub struct BcdStore {
+    /// 按 GUID 排序的所有对象
+    pub objects: Vec<BcdObject>,
+}
+
+impl BcdStore {
+    /// 读取 BCD 文件
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::from_hive(&Hive::open(path)?).with_context(|| format!("解析 BCD {} 失败", path.display()))
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Self::from_hive(&Hive::from_bytes(data)?)
+    }
+
+    pub fn from_hive(hive: &Hive) -> Result<Self> {
+        let objects_key = hive
+            .root()
+            .subkey("Objects")?
+            .context("BCD 中没有 Objects 键")?;
+
+        let mut objects = Vec::new();
+        for key in objects_key.subkeys()? {
+            let Some(id) = Guid::parse(key.name()) else {
+                log::warn!("跳过无法识别的 BCD 对象: {}", key.name());
+                continue;
+            };
+            objects.push(Self::read_object(id, &key)?);
+        }
+        objects.sort_by_key(|o| o.id);
+        Ok(Self { objects })
+    }
+
+    fn read_object(id: Guid, key: &Key<'_>) -> Result<BcdObject> {
+        let object_type = key
+            .open("Description")?
+            .and_then(|d| d.value("Type").ok().flatten())
+            .and_then(|v| v.as_u32())
+            .unwrap_or(0);
+
+        let mut elements = BTreeMap::new();
+        if let Some(elements_key) = key.subkey("Elements")? {
+            for element_key in elements_key.subkeys()? {
+                let Ok(element_type) = u32::from_str_radix(element_key.name(), 16) else {
+                    continue;
+                };
+                if let Some(value) = element_key.value("Element")? {
+                    elements.insert(element_type, ElementValue::decode(element_type, &value));
+                }
+            }
+        }
+
+        Ok(BcdObject {
+            id,
+            object_type,
+            elements,
+        })
+    }
+
+    pub fn object(&self, id: &Guid) -> Option<&BcdObject> {
+        self.objects.iter().find(|o| o.id == *id)
+    }
+
+    /// Windows 启动管理器 {bootmgr}
+    pub fn boot_manager(&self) -> Option<&BcdObject> {
+        self.object(&well_known::BOOTMGR)
+    }
+
+    /// 所有系统加载器
+    pub fn os_loaders(&self) -> impl Iterator<Item = &BcdObject> {
+        self.objects.iter().filter(|o| o.kind() == ObjectKind::OsLoader)
+    }
+
+    /// 默认启动项
+    pub fn default_entry(&self) -> Option<Guid> {
+        self.boot_manager()?.object(elements::BOOTMGR_DEFAULT)
+    }
+
+    /// 启动菜单显示顺序
+    pub fn display_order(&self) -> Vec<Guid> {
+        self.boot_manager()
+            .map(|m| m.object_list(elements::BOOTMGR_DISPLAY_ORDER))
+            .unwrap_or_default()
+    }
+
+    /// 工具菜单显示顺序
+    pub fn tools_display_order(&self) -> Vec<Guid> {
+        self.boot_manager()
+            .map(|m| m.object_list(elements::BOOTMGR_TOOLS_DISPLAY_ORDER))
+            .unwrap_or_default()
+    }
+
+    /// 菜单等待时间（秒）
+    pub fn timeout(&self) -> Option<u64> {
+        match self.boot_manager()?.element(elements::BOOTMGR_TIMEOUT)? {
+            ElementValue::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// 查找启动指定分区上系统的加载器
+    ///
+    /// 有多个匹配时依次优先默认项、显示顺序靠前的项；WinPE 项不参与匹配。
+    pub fn find_os_loader_on(&self, partition: &PartitionId) -> Option<&BcdObject> {
+        let matches = |o: &&BcdObject| {
+            !o.is_winpe() && o.os_device().and_then(Device::partition) == Some(partition)
+        };
+        let candidates: Vec<&BcdObject> = self.os_loaders().filter(matches).collect();
+
+        let default = self.default_entry();
+        let order = self.display_order();
+        candidates
+            .iter()
+            .find(|o| Some(o.id) == default)
+            .or_else(|| order.iter().find_map(|id| candidates.iter().find(|o| o.id == *id)))
+            .or_else(|| candidates.first())
+            .copied()
+    }
+
+    /// 相对另一份存储新增的对象，用于确认 `bcdedit /create` 创建的对象
+    pub fn new_objects<'a>(&'a self, before: &BcdStore) -> Vec<&'a BcdObject> {
+        self.objects
+            .iter()
+            .filter(|o| before.object(&o.id).is_none())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::regf::writer::{build, multi_sz, utf16z, TestKey};
+    use crate::regf::{REG_BINARY, REG_DWORD, REG_MULTI_SZ, REG_SZ};
+
+    pub(crate) const WIN10: &str = "{1c3f6f4e-0a7b-11ef-9c5b-a1b2c3d4e5f6}";
+    pub(crate) const WIN7: &str = "{1c3f6f4f-0a7b-11ef-9c5b-a1b2c3d4e5f6}";
+    pub(crate) const PE: &str = "{2d3f6f4e-0a7b-11ef-9c5b-a1b2c3d4e5f6}";
+    pub(crate) const RAMDISK: &str = "{3e3f6f4e-0a7b-11ef-9c5b-a1b2c3d4e5f6}";
+
+    fn object(id: &str, object_type: u32, elements: Vec<(u32, u32, Vec<u8>)>) -> TestKey {
+        let mut elements_key = TestKey::new("Elements");
+        for (element_type, kind, data) in elements {
+            elements_key = elements_key
+                .key(TestKey::new(&format!("{:08x}", element_type)).value("Element", kind, data));
+        }
+        TestKey::new(id)
+            .key(TestKey::new("Description").value("Type", REG_DWORD, object_type.to_le_bytes().to_vec()))
+            .key(elements_key)
+    }
+
+    fn descriptor(kind: u32, payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&kind.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&((payload.len() + DESCRIPTOR_HEADER) as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn partition_descriptor(id: &PartitionId) -> Vec<u8> {
+        let mut payload = vec![0u8; PARTITION_DESCRIPTOR_SIZE - DESCRIPTOR_HEADER];
+        match id {
+            PartitionId::Mbr {
+                disk_signature,
+                offset,
+            } => {
+                payload[..8].copy_from_slice(&offset.to_le_bytes());
+                payload[0x18..0x1C].copy_from_slice(&1u32.to_le_bytes());
+                payload[0x1C..0x20].copy_from_slice(&disk_signature.to_le_bytes());
+            }
+            PartitionId::Gpt {
+                disk_id,
+                partition_id,
+            } => {
+                payload[..16].copy_from_slice(&partition_id.to_bytes_le());
+                payload[0x1C..0x2C].copy_from_slice(&disk_id.to_bytes_le());
+            }
+        }
+        descriptor(DEVICE_PARTITION, &payload)
+    }
+
+    fn device(options: Guid, descriptor: Vec<u8>) -> Vec<u8> {
+        let mut data = options.to_bytes_le().to_vec();
+        data.extend(descriptor);
+        data
+    }
+
+    pub(crate) fn gpt(n: u32) -> PartitionId {
+        PartitionId::Gpt {
+            disk_id: Guid::from_fields(0x11111111, 0x2222, 0x3333, [4; 8]),
+            partition_id: Guid::from_fields(n, 0x5555, 0x6666, [7; 8]),
+        }
+    }
+
+    fn loader(id: &str, description: &str, partition: &PartitionId) -> TestKey {
+        let dev = device(Guid::NIL, partition_descriptor(partition));
+        object(
+            id,
+            0x10200003,
+            vec![
+                (elements::DESCRIPTION, REG_SZ, utf16z(description)),
+                (elements::DEVICE, REG_BINARY, dev.clone()),
+                (elements::PATH, REG_SZ, utf16z("\\Windows\\system32\\winload.efi")),
+                (elements::OSLOADER_OS_DEVICE, REG_BINARY, dev),
+                (elements::OSLOADER_SYSTEM_ROOT, REG_SZ, utf16z("\\Windows")),
+            ],
+        )
+    }
+
+    fn ramdisk_device() -> Vec<u8> {
+        let mut payload = 5u32.to_le_bytes().to_vec();
+        payload.extend(partition_descriptor(&PartitionId::Mbr {
+            disk_signature: 0xA1B2C3D4,
+            offset: 0x100000,
+        }));
+        payload.extend(utf16z("\\LetRecovery_PE\\boot.wim"));
+        device(Guid::parse(RAMDISK).unwrap(), descriptor(3, &payload))
+    }
+
+    pub(crate) fn sample_store() -> BcdStore {
+        let bootmgr = object(
+            "{9dea862c-5cdd-4e70-acc1-f32b344d4795}",
+            0x10100002,
+            vec![
+                (elements::DESCRIPTION, REG_SZ, utf16z("Windows Boot Manager")),
+                (elements::DEVICE, REG_BINARY, device(Guid::NIL, descriptor(DEVICE_BOOT, &[0; 56]))),
+                (elements::BOOTMGR_DEFAULT, REG_SZ, utf16z(WIN10)),
+                (elements::BOOTMGR_DISPLAY_ORDER, REG_MULTI_SZ, multi_sz(&[PE, WIN10, WIN7])),
+                (elements::BOOTMGR_TIMEOUT, REG_BINARY, 30u64.to_le_bytes().to_vec()),
+            ],
+        );
+        let pe = object(
+            PE,
+            0x10200003,
+            vec![
+                (elements::DESCRIPTION, REG_SZ, utf16z("LetRecovery PE")),
+                (elements::OSLOADER_OS_DEVICE, REG_BINARY, ramdisk_device()),
+                (elements::OSLOADER_WINPE, REG_BINARY, vec![1]),
+            ],
+        );
+        let ramdisk = object(
+            RAMDISK,
+            0x30000000,
+            vec![(elements::RAMDISK_SDI_PATH, REG_SZ, utf16z("\\boot.sdi"))],
+        );
+
+        let root = TestKey::new("NewStoreRoot").key(
+            TestKey::new("Objects")
+                .key(bootmgr)
+                .key(loader(WIN7, "Windows 7", &gpt(2)))
+                .key(loader(WIN10, "Windows 10", &gpt(1)))
+                .key(pe)
+                .key(ramdisk)
+                .key(TestKey::new("not-a-guid")),
+        );
+        BcdStore::from_bytes(build(&root)).unwrap()
+    }
+
+    #[test]
+    fn test_guid_roundtrip() {
+        let guid = Guid::parse("{9DEA862C-5CDD-4E70-ACC1-F32B344D4795}").unwrap();
+        assert_eq!(guid, well_known::BOOTMGR);
+        assert_eq!(guid.to_string(), "{9dea862c-5cdd-4e70-acc1-f32b344d4795}");
+        assert_eq!(Guid::from_bytes_le(&guid.to_bytes_le()), Some(guid));
+        assert_eq!(Guid::parse("9dea862c-5cdd-4e70-acc1-f32b344d4795"), Some(guid));
+        assert_eq!(Guid::parse("{bootmgr}"), None);
+        assert_eq!(Guid::parse("{9dea862c-5cdd-4e70-acc1-f32b344d479}"), None);
+    }
+
+    #[test]
+    fn test_store_objects() {
+        let store = sample_store();
+        assert_eq!(store.objects.len(), 5);
+
+        let bootmgr = store.boot_manager().unwrap();
+        assert_eq!(bootmgr.kind(), ObjectKind::BootManager);
+        assert_eq!(bootmgr.identifier(), "{bootmgr}");
+        assert_eq!(bootmgr.device(), Some(&Device::Boot));
+        assert_eq!(store.timeout(), Some(30));
+        assert_eq!(store.default_entry(), Guid::parse(WIN10));
+        assert_eq!(
+            store.display_order(),
+            [PE, WIN10, WIN7].map(|g| Guid::parse(g).unwrap())
+        );
+
+        let win10 = store.object(&Guid::parse(WIN10).unwrap()).unwrap();
+        assert_eq!(win10.kind(), ObjectKind::OsLoader);
+        assert_eq!(win10.description(), Some("Windows 10"));
+        assert_eq!(win10.system_root(), Some("\\Windows"));
+        assert_eq!(win10.os_device(), Some(&Device::Partition(gpt(1))));
+        assert_eq!(store.os_loaders().count(), 3);
+
+        let ramdisk = store.object(&Guid::parse(RAMDISK).unwrap()).unwrap();
+        assert_eq!(ramdisk.kind(), ObjectKind::Device);
+    }
+
+    #[test]
+    fn test_ramdisk_device() {
+        let store = sample_store();
+        let pe = store.object(&Guid::parse(PE).unwrap()).unwrap();
+        assert!(pe.is_winpe());
+        let mbr = PartitionId::Mbr {
+            disk_signature: 0xA1B2C3D4,
+            offset: 0x100000,
+        };
+        assert_eq!(
+            pe.os_device(),
+            Some(&Device::File {
+                ramdisk_options: Guid::parse(RAMDISK),
+                parent: Some(Box::new(Device::Partition(mbr))),
+                path: "\\LetRecovery_PE\\boot.wim".to_string(),
+            })
+        );
+        assert_eq!(pe.os_device().unwrap().partition(), Some(&mbr));
+        assert_eq!(
+            pe.os_device().unwrap().to_string(),
+            format!("ramdisk=[MBR 磁盘 A1B2C3D4 偏移 1 MB]\\LetRecovery_PE\\boot.wim,{}", RAMDISK)
+        );
+
+        let unknown = Device::parse(&device(Guid::NIL, descriptor(9, &[0; 8])));
+        assert!(matches!(unknown, Device::Unknown { kind: 9, .. }));
+    }
+
+    #[test]
+    fn test_find_os_loader() {
+        let store = sample_store();
+        assert_eq!(store.find_os_loader_on(&gpt(1)).map(|o| o.id), Guid::parse(WIN10));
+        assert_eq!(store.find_os_loader_on(&gpt(2)).map(|o| o.id), Guid::parse(WIN7));
+        assert_eq!(store.find_os_loader_on(&gpt(3)), None);
+        // WinPE 项不算作已安装的系统
+        let mbr = PartitionId::Mbr {
+            disk_signature: 0xA1B2C3D4,
+            offset: 0x100000,
+        };
+        assert_eq!(store.find_os_loader_on(&mbr), None);
+    }
+
+    #[test]
+    fn test_partition_from_drive_layout() {
+        let disk_id = Guid::from_fields(0x11111111, 0x2222, 0x3333, [4; 8]);
+        let mut layout = vec![0u8; 48 + 144 * 2];
+        layout[0..4].copy_from_slice(&1u32.to_le_bytes());
+        layout[4..8].copy_from_slice(&2u32.to_le_bytes());
+        layout[8..24].copy_from_slice(&disk_id.to_bytes_le());
+        for (i, n) in [1u32, 2].iter().enumerate() {
+            let entry = &mut layout[48 + i * 144..48 + (i + 1) * 144];
+            entry[8..16].copy_from_slice(&(u64::from(*n) << 20).to_le_bytes());
+            entry[24..28].copy_from_slice(&n.to_le_bytes());
+            entry[48..64].copy_from_slice(&Guid::from_fields(*n, 0x5555, 0x6666, [7; 8]).to_bytes_le());
+        }
+        assert_eq!(PartitionId::from_drive_layout(&layout, 2), Some(gpt(2)));
+        assert_eq!(PartitionId::from_drive_layout(&layout, 3), None);
+
+        // MBR：签名在头部，分区按起始偏移区分
+        layout[0..4].copy_from_slice(&0u32.to_le_bytes());
+        layout[8..12].copy_from_slice(&0xA1B2C3D4u32.to_le_bytes());
+        assert_eq!(
+            PartitionId::from_drive_layout(&layout, 1),
+            Some(PartitionId::Mbr {
+                disk_signature: 0xA1B2C3D4,
+                offset: 1 << 20,
+            })
+        );
+    }
+
+    #[test]
+    fn test_new_objects() {
+        let store = sample_store();
+        let mut before = store.clone();
+        before.objects.retain(|o| o.id != Guid::parse(PE).unwrap());
+        let added: Vec<_> = store.new_objects(&before).iter().map(|o| o.id).collect();
+        assert_eq!(added, [Guid::parse(PE).unwrap()]);
+    }
+
+    /// 用 `bcdedit /export` 导出的真实存储验证解析结果
+    ///
+    /// 导出文件只能在 Windows 上生成，仓库中尚未提供。在 Windows 上执行
+    /// `bcdedit /export src\bcd\testdata\exported.bcd` 后用
+    /// `cargo test -- --ignored` 运行。
+    #[test]
+    #[ignore = "需要 src/bcd/testdata/exported.bcd（bcdedit /export 导出的存储）"]
+    fn test_exported_store() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/bcd/testdata/exported.bcd");
+        let store = BcdStore::open(&path).unwrap();
+
+        let bootmgr = store.boot_manager().unwrap();
+        assert_eq!(bootmgr.kind(), ObjectKind::BootManager);
+        let order = store.display_order();
+        assert!(!order.is_empty());
+        for id in &order {
+            assert!(store.object(id).is_some(), "显示顺序中的 {} 不存在", id);
+        }
+        assert!(store.default_entry().is_some_and(|id| store.object(&id).is_some()));
+
+        let loaders: Vec<_> = store.os_loaders().collect();
+        assert!(!loaders.is_empty());
+        for loader in loaders {
+            assert!(loader.description().is_some(), "{}", loader.identifier());
+            assert!(loader.device().is_some(), "{}", loader.identifier());
+            assert!(loader.os_device().is_some(), "{}", loader.identifier());
+            assert!(loader.path().is_some_and(|p| p.to_ascii_lowercase().contains("winload")));
+        }
+    }
+}
@@ -0,0 +1,561 @@
+//! 命令行接口
+//!
+//! 正常系统端可由部署脚本或远程管理工具在没有界面的情况下调用，
+//! 用法见 [`USAGE`]。这里只把参数解析为 [`Command`]，子命令由正常系统端执行；
+//! 进度和结果以每行一个 JSON 对象的形式写到标准输出，退出码见 [`ExitStatus`]。
+
+use anyhow::{Context, Result};
+
+use crate::backup::compression::{BackupFormat, Compression};
+use crate::backup::exclusions::ExclusionConfig;
+use crate::backup::retention::RetentionPolicy;
+use crate::disk_layout::Firmware;
+
+/// 命令行帮助
+pub const USAGE: &str = "\
+用法:
+  LetRecovery images list <镜像文件>
+  LetRecovery install --image <镜像文件> --target <盘符> [--index <卷索引>]
+                      [--profile <部署方案名称或文件>] [--advanced-options <高级选项文件>]
+                      [--no-format] [--no-boot-repair] [--firmware uefi|bios]
+                      [--export-drivers] [--reboot]
+    指定部署方案时可省略 --image，命令行参数优先于方案中的设置
+    --advanced-options 为界面导出的高级选项 JSON，指定时同时生成无人值守应答文件；
+    --unattend-profile 是它的别名
+    --export-drivers 安装前导出当前系统的驱动并导入新系统，--reboot 安装完成后重启
+  LetRecovery backup --source <盘符> --dest <镜像文件> --name <名称>
+                     [--description <描述>] [--incremental] [--verify]
+                     [--retention last:<次数>|daily:<天数>] [--profile <部署方案名称或文件>]
+                     [--format wim|gho[:<级别>]|dedup] [--compression none|fast|max] [--esd]
+                     [--default-exclusions on|off] [--exclude <路径>]... [--no-compress <路径>]...
+                     [--pe <PE文件名>]
+    指定部署方案时使用方案中的备份排除列表，指定任一排除选项时改用命令行中的排除列表；
+    --esd 在镜像旁另存一份 ESD 归档副本
+    源分区是当前系统分区时须在 PE 中执行；指定 --pe 则写入备份配置并添加 PE 启动项，
+    下次重启后在 PE 中完成备份（供计划备份使用）
+    GHO 格式用 Ghost 备份整个分区（压缩级别 1-9），不支持增量、排除列表、验证和保留策略
+    dedup 格式备份到去重仓库，--dest 为仓库文件夹；不能用于 Windows 系统分区
+  LetRecovery repo list <仓库>
+  LetRecovery repo restore <仓库> --id <备份编号> --dest <目标文件夹> [--path <文件或文件夹>]
+  LetRecovery repo gc <仓库>
+  LetRecovery boot repair --target <盘符> [--firmware uefi|bios]
+  LetRecovery hw report [--json]
+
+进度和结果以每行一个 JSON 对象输出到标准输出（以 { 开头的行）。
+多余的参数（如拼错名称的选项的值）视为参数错误。
+退出码: 0 成功, 1 执行失败, 2 参数错误, 3 需要管理员权限, 4 安装前检查未通过";
+
+/// 界面模式的启动开关（从 PE 启动项或安装流程传入），其余以 `-` 或 `/` 开头的参数视为错误
+pub const GUI_FLAGS: &[&str] = &["/PEINSTALL", "--pe-install", "/PEBACKUP", "--pe-backup"];
+
+/// 命令行退出码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success,
+    /// 操作执行失败
+    Failed,
+    /// 参数错误
+    Usage,
+    /// 未以管理员身份运行
+    NotAdmin,
+    /// 安装前检查发现必须阻止的问题
+    PreflightFailed,
+}
+
+impl ExitStatus {
+    pub fn code(&self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Failed => 1,
+            ExitStatus::Usage => 2,
+            ExitStatus::NotAdmin => 3,
+            ExitStatus::PreflightFailed => 4,
+        }
+    }
+}
+
+/// 子命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    /// 列出镜像中的卷
+    ImagesList { file: String },
+    Install(InstallArgs),
+    Backup(BackupArgs),
+    /// 修复目标分区上系统的引导；未指定固件类型时按分区表判断
+    BootRepair { target: String, firmware: Option<Firmware> },
+    /// 输出硬件信息
+    HwReport { json: bool },
+    /// 列出去重仓库中的备份
+    RepoList { repository: String },
+    /// 从去重仓库的一次备份还原全部文件，或只还原 `path` 下的文件
+    RepoRestore {
+        repository: String,
+        id: u32,
+        destination: String,
+        path: Option<String>,
+    },
+    /// 回收去重仓库中不再被引用的数据块
+    RepoGc { repository: String },
+}
+
+impl Command {
+    /// 是否需要管理员权限
+    pub fn requires_admin(&self) -> bool {
+        !matches!(self, Command::Help | Command::HwReport { .. } | Command::RepoList { .. })
+    }
+}
+
+/// install 子命令参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallArgs {
+    /// 未指定时使用部署方案中的镜像
+    pub image: Option<String>,
+    /// 卷索引，GHO 镜像忽略；未指定时使用部署方案中的卷索引或 1
+    pub index: Option<u32>,
+    /// 目标分区，如 "D:"
+    pub target: String,
+    /// 部署方案名称或方案文件路径
+    pub profile: Option<String>,
+    /// 高级选项文件（界面导出的 JSON），指定时同时生成无人值守应答文件
+    pub advanced_options: Option<String>,
+    pub no_format: bool,
+    pub no_boot_repair: bool,
+    /// 未指定时按目标分区的分区表判断
+    pub firmware: Option<Firmware>,
+    /// 安装前导出当前系统的驱动并导入新系统
+    pub export_drivers: bool,
+    /// 安装完成后重启
+    pub reboot: bool,
+}
+
+/// backup 子命令参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupArgs {
+    /// 源分区，如 "D:"
+    pub source: String,
+    pub dest: String,
+    pub name: String,
+    pub description: String,
+    /// 备份格式
+    pub format: BackupFormat,
+    /// 目标镜像已存在时追加为新卷
+    pub incremental: bool,
+    /// 备份后校验镜像并与源分区比较
+    pub verify: bool,
+    /// 备份后按策略清理旧的卷
+    pub retention: Option<RetentionPolicy>,
+    /// 部署方案名称或文件，使用其中的排除列表
+    pub profile: Option<String>,
+    /// 命令行指定的排除列表，优先于部署方案中的排除列表
+    pub exclusions: Option<ExclusionConfig>,
+    /// 新建镜像时的压缩方式
+    pub compression: Compression,
+    /// 备份后导出 ESD 归档副本
+    pub esd: bool,
+    /// 源分区是当前系统分区时排入该 PE 执行
+    pub pe: Option<String>,
+}
+
+/// 解析命令行参数（不含程序名）
+///
+/// 没有参数或第一个参数是 [`GUI_FLAGS`] 中的开关时返回 None，由调用方按原有方式启动。
+pub fn parse(args: &[String]) -> Result<Option<Command>> {
+    let Some(first) = args.first() else {
+        return Ok(None);
+    };
+    let rest = &args[1..];
+
+    let command = match first.as_str() {
+        "help" | "--help" | "-h" | "/?" => Command::Help,
+        "images" => {
+            let options = Options::parse(action(rest, "images", "list")?, &[], &[], 1)?;
+            Command::ImagesList {
+                file: options.positional(0, "镜像文件")?,
+            }
+        }
+        "install" => {
+            let options = Options::parse(
+                rest,
+                &["image", "index", "target", "profile", "advanced-options", "unattend-profile", "firmware"],
+                &["no-format", "no-boot-repair", "export-drivers", "reboot"],
+                0,
+            )?;
+            let index = options
+                .value("index")
+                .map(|v| {
+                    v.parse::<u32>()
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .with_context(|| format!("无效的卷索引: {}", v))
+                })
+                .transpose()?;
+            let profile = options.value("profile").map(String::from);
+            let image = match profile {
+                Some(_) => options.value("image").map(String::from),
+                None => Some(options.required("image")?),
+            };
+            Command::Install(InstallArgs {
+                image,
+                index,
+                target: normalize_letter(&options.required("target")?)?,
+                profile,
+                advanced_options: options
+                    .value("advanced-options")
+                    .or(options.value("unattend-profile"))
+                    .map(String::from),
+                no_format: options.flag("no-format"),
+                no_boot_repair: options.flag("no-boot-repair"),
+                firmware: options.value("firmware").map(parse_firmware).transpose()?,
+                export_drivers: options.flag("export-drivers"),
+                reboot: options.flag("reboot"),
+            })
+        }
+        "backup" => {
+            let options = Options::parse(
+                rest,
+                &[
+                    "source", "dest", "name", "description", "format", "retention", "profile", "compression",
+                    "default-exclusions", "exclude", "no-compress", "pe",
+                ],
+                &["incremental", "verify", "esd"],
+                0,
+            )?;
+            let default_exclusions = options
+                .value("default-exclusions")
+                .map(|v| match v.to_ascii_lowercase().as_str() {
+                    "on" => Ok(true),
+                    "off" => Ok(false),
+                    _ => anyhow::bail!("无效的内置排除设置: {}（可选 on、off）", v),
+                })
+                .transpose()?;
+            let (exclude, no_compress) = (options.values("exclude"), options.values("no-compress"));
+            let exclusions = (default_exclusions.is_some() || !exclude.is_empty() || !no_compress.is_empty())
+                .then(|| ExclusionConfig::new(default_exclusions.unwrap_or(true), &exclude, &no_compress));
+            Command::Backup(BackupArgs {
+                source: normalize_letter(&options.required("source")?)?,
+                dest: options.required("dest")?,
+                name: options.required("name")?,
+                description: options.value("description").unwrap_or_default().to_string(),
+                format: options
+                    .value("format")
+                    .map(|v| BackupFormat::parse(v).with_context(|| format!("无效的备份格式: {}", v)))
+                    .transpose()?
+                    .unwrap_or_default(),
+                incremental: options.flag("incremental"),
+                verify: options.flag("verify"),
+                retention: options
+                    .value("retention")
+                    .map(|v| RetentionPolicy::parse(v).with_context(|| format!("无效的保留策略: {}", v)))
+                    .transpose()?,
+                profile: options.value("profile").map(String::from),
+                exclusions,
+                compression: options
+                    .value("compression")
+                    .map(|v| Compression::parse(v).with_context(|| format!("无效的压缩方式: {}", v)))
+                    .transpose()?
+                    .unwrap_or_default(),
+                esd: options.flag("esd"),
+                pe: options.value("pe").map(String::from),
+            })
+        }
+        "boot" => {
+            let options = Options::parse(action(rest, "boot", "repair")?, &["target", "firmware"], &[], 0)?;
+            Command::BootRepair {
+                target: normalize_letter(&options.required("target")?)?,
+                firmware: options.value("firmware").map(parse_firmware).transpose()?,
+            }
+        }
+        "hw" => {
+            let options = Options::parse(action(rest, "hw", "report")?, &[], &["json"], 0)?;
+            Command::HwReport {
+                json: options.flag("json"),
+            }
+        }
+        "repo" => match rest.split_first() {
+            Some((action, rest)) if action == "list" => Command::RepoList {
+                repository: Options::parse(rest, &[], &[], 1)?.positional(0, "仓库")?,
+            },
+            Some((action, rest)) if action == "restore" => {
+                let options = Options::parse(rest, &["id", "dest", "path"], &[], 1)?;
+                let id = options.required("id")?;
+                Command::RepoRestore {
+                    repository: options.positional(0, "仓库")?,
+                    id: id.parse().ok().filter(|i| *i > 0).with_context(|| format!("无效的备份编号: {}", id))?,
+                    destination: options.required("dest")?,
+                    path: options.value("path").map(String::from),
+                }
+            }
+            Some((action, rest)) if action == "gc" => Command::RepoGc {
+                repository: Options::parse(rest, &[], &[], 1)?.positional(0, "仓库")?,
+            },
+            Some((action, _)) => anyhow::bail!("未知操作: repo {}", action),
+            None => anyhow::bail!("缺少操作: repo list|restore|gc"),
+        },
+        other if GUI_FLAGS.contains(&other) => return Ok(None),
+        other if other.starts_with('/') || other.starts_with('-') => anyhow::bail!("未知参数: {}", other),
+        other => anyhow::bail!("未知命令: {}", other),
+    };
+    Ok(Some(command))
+}
+
+/// 检查子命令的动作（如 `images list`），返回其后的参数
+fn action<'a>(args: &'a [String], command: &str, expected: &str) -> Result<&'a [String]> {
+    match args.first() {
+        Some(a) if a == expected => Ok(&args[1..]),
+        Some(a) => anyhow::bail!("未知操作: {} {}", command, a),
+        None => anyhow::bail!("缺少操作: {} {}", command, expected),
+    }
+}
+
+/// 把 "d"、"D:"、"D:\" 统一为 "D:"
+fn normalize_letter(value: &str) -> Result<String> {
+    let trimmed = value.trim_end_matches(['\\', '/']).trim_end_matches(':');
+    let mut chars = trimmed.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() => Ok(format!("{}:", c.to_ascii_uppercase())),
+        _ => anyhow::bail!("无效的盘符: {}", value),
+    }
+}
+
+fn parse_firmware(value: &str) -> Result<Firmware> {
+    match value.to_ascii_lowercase().as_str() {
+        "uefi" => Ok(Firmware::Uefi),
+        "bios" | "legacy" => Ok(Firmware::Bios),
+        _ => anyhow::bail!("无效的固件类型: {}（可选 uefi、bios）", value),
+    }
+}
+
+/// 解析后的 `--name value`、`--name=value` 和开关
+struct Options {
+    values: Vec<(String, String)>,
+    flags: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Options {
+    /// `value_names` 中的参数需要带值，`flag_names` 中的是开关，最多接受 `max_positional` 个位置参数
+    ///
+    /// 多出的位置参数通常是拼错名称的选项的值，直接报错而不是忽略。
+    fn parse(args: &[String], value_names: &[&str], flag_names: &[&str], max_positional: usize) -> Result<Self> {
+        let mut options = Options {
+            values: Vec::new(),
+            flags: Vec::new(),
+            positional: Vec::new(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                if options.positional.len() >= max_positional {
+                    anyhow::bail!("多余的参数: {}", arg);
+                }
+                options.positional.push(arg.clone());
+                continue;
+            };
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+
+            if flag_names.contains(&name) && inline_value.is_none() {
+                options.flags.push(name.to_string());
+            } else if value_names.contains(&name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => iter.next().with_context(|| format!("参数 --{} 缺少值", name))?.clone(),
+                };
+                options.values.push((name.to_string(), value));
+            } else {
+                anyhow::bail!("未知参数: --{}", name);
+            }
+        }
+        Ok(options)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 可重复参数的全部值
+    fn values(&self, name: &str) -> Vec<String> {
+        self.values
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    fn required(&self, name: &str) -> Result<String> {
+        self.value(name)
+            .map(String::from)
+            .with_context(|| format!("缺少参数 --{}", name))
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    fn positional(&self, index: usize, description: &str) -> Result<String> {
+        self.positional
+            .get(index)
+            .cloned()
+            .with_context(|| format!("缺少{}", description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(parse(&args("/PEINSTALL")).unwrap(), None);
+        assert_eq!(parse(&args("--pe-backup")).unwrap(), None);
+        assert_eq!(parse(&args("/?")).unwrap(), Some(Command::Help));
+
+        assert_eq!(
+            parse(&args("images list D:\\sources\\install.wim")).unwrap(),
+            Some(Command::ImagesList { file: "D:\\sources\\install.wim".to_string() })
+        );
+        assert_eq!(
+            parse(&args("install --image E:\\win.esd --index=6 --target d --no-format --firmware UEFI")).unwrap(),
+            Some(Command::Install(InstallArgs {
+                image: Some("E:\\win.esd".to_string()),
+                index: Some(6),
+                target: "D:".to_string(),
+                profile: None,
+                advanced_options: None,
+                no_format: true,
+                no_boot_repair: false,
+                firmware: Some(Firmware::Uefi),
+                export_drivers: false,
+                reboot: false,
+            }))
+        );
+        assert_eq!(
+            parse(&args("install --profile 办公机 --target C: --advanced-options D:\\选项.json --export-drivers --reboot")).unwrap(),
+            Some(Command::Install(InstallArgs {
+                image: None,
+                index: None,
+                target: "C:".to_string(),
+                profile: Some("办公机".to_string()),
+                advanced_options: Some("D:\\选项.json".to_string()),
+                no_format: false,
+                no_boot_repair: false,
+                firmware: None,
+                export_drivers: true,
+                reboot: true,
+            }))
+        );
+        assert_eq!(
+            parse(&args("backup --source C:\\ --dest F:\\bak.wim --name 周备份 --incremental --verify --retention daily:7 --profile 办公机 --compression fast --esd --pe WinPE.wim")).unwrap(),
+            Some(Command::Backup(BackupArgs {
+                source: "C:".to_string(),
+                dest: "F:\\bak.wim".to_string(),
+                name: "周备份".to_string(),
+                description: String::new(),
+                format: BackupFormat::Wim,
+                incremental: true,
+                verify: true,
+                retention: Some(RetentionPolicy::KeepDaily(7)),
+                profile: Some("办公机".to_string()),
+                exclusions: None,
+                compression: Compression::Fast,
+                esd: true,
+                pe: Some("WinPE.wim".to_string()),
+            }))
+        );
+        let Some(Command::Backup(backup)) = parse(&args(
+            "backup --source D: --dest F:\\d.wim --name 数据 --default-exclusions off --exclude *.iso --exclude \\Temp --no-compress *.vhdx",
+        ))
+        .unwrap() else {
+            panic!("应解析为 backup");
+        };
+        let Some(Command::Install(install)) =
+            parse(&args("install --image a.wim --target C: --unattend-profile D:\\选项.json")).unwrap()
+        else {
+            panic!("应解析为 install");
+        };
+        assert_eq!(install.advanced_options.as_deref(), Some("D:\\选项.json"));
+        assert_eq!(
+            backup.exclusions,
+            Some(ExclusionConfig::new(
+                false,
+                &["*.iso".to_string(), "\\Temp".to_string()],
+                &["*.vhdx".to_string()]
+            ))
+        );
+        assert!(parse(&args("backup --source D: --dest F:\\d.wim --name 数据 --default-exclusions maybe")).is_err());
+
+        assert_eq!(
+            parse(&args("boot repair --target C:")).unwrap(),
+            Some(Command::BootRepair { target: "C:".to_string(), firmware: None })
+        );
+        assert_eq!(parse(&args("hw report --json")).unwrap(), Some(Command::HwReport { json: true }));
+        assert_eq!(
+            parse(&args("repo restore E:\\仓库 --id 3 --dest D:\\还原 --path Users\\me")).unwrap(),
+            Some(Command::RepoRestore {
+                repository: "E:\\仓库".to_string(),
+                id: 3,
+                destination: "D:\\还原".to_string(),
+                path: Some("Users\\me".to_string()),
+            })
+        );
+        assert_eq!(
+            parse(&args("repo list E:\\仓库")).unwrap(),
+            Some(Command::RepoList { repository: "E:\\仓库".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |line: &str| parse(&args(line)).unwrap_err().to_string();
+
+        assert_eq!(error("format C:"), "未知命令: format");
+        assert_eq!(error("images show a.wim"), "未知操作: images show");
+        assert_eq!(error("boot"), "缺少操作: boot repair");
+        assert_eq!(error("install --image a.wim"), "缺少参数 --target");
+        assert_eq!(error("install --image a.wim --target"), "参数 --target 缺少值");
+        assert_eq!(error("install --image a.wim --target C: --index 0"), "无效的卷索引: 0");
+        assert_eq!(error("backup --source CD --dest a.wim --name x"), "无效的盘符: CD");
+        assert_eq!(error("backup --source C: --dest a.wim --name x --retention weekly:2"), "无效的保留策略: weekly:2");
+        assert_eq!(error("backup --source C: --dest a.wim --name x --compression lzms"), "无效的压缩方式: lzms");
+        assert_eq!(error("backup --source D: --dest a.gho --name x --format gho:10"), "无效的备份格式: gho:10");
+        assert_eq!(error("hw report --xml"), "未知参数: --xml");
+        assert_eq!(error("--pe-instal"), "未知参数: --pe-instal");
+        assert_eq!(error("/silent"), "未知参数: /silent");
+        assert_eq!(error("repo check E:\\仓库"), "未知操作: repo check");
+        assert_eq!(error("repo restore E:\\仓库 --id 0 --dest D:"), "无效的备份编号: 0");
+        assert_eq!(error("boot repair --target C: --firmware efi"), "无效的固件类型: efi（可选 uefi、bios）");
+        assert_eq!(error("install --target C:"), "缺少参数 --image");
+        // 拼错名称的选项会把它的值留成多余的位置参数
+        assert_eq!(error("install --image a.wim --target C: -advanced-options x.json"), "多余的参数: -advanced-options");
+        assert_eq!(error("hw report json"), "多余的参数: json");
+        assert_eq!(error("images list a.wim b.wim"), "多余的参数: b.wim");
+        assert_eq!(error("repo restore E:\\仓库 D:\\还原 --id 1 --dest D:"), "多余的参数: D:\\还原");
+        assert!(Command::Install(InstallArgs {
+            image: None,
+            index: None,
+            target: "C:".to_string(),
+            profile: None,
+            advanced_options: None,
+            no_format: false,
+            no_boot_repair: false,
+            firmware: None,
+            export_drivers: false,
+            reboot: false,
+        })
+        .requires_admin());
+        assert!(!Command::HwReport { json: false }.requires_admin());
+    }
+}
@@ -0,0 +1,527 @@
+//! 外部命令执行
+//!
+//! DISM、bcdedit、bcdboot、diskpart、reg.exe、Ghost 等外部程序都通过 [`CommandRunner`] 调用。
+//! 实际运行时使用 [`SystemRunner`]；测试时换成 [`FakeRunner`]，按预设回放输出和退出码，
+//! 并记录每次调用，安装、备份流程因此可以脱离 Windows 测试。
+//!
+//! 执行器由调用方在创建各工具模块时传入，安装、备份后端在构造时取得执行器并交给
+//! 它用到的所有工具，不使用全局状态。
+
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+/// 流式执行时两次 [`StreamEvent::Tick`] 的间隔
+pub const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 退出状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandStatus {
+    code: Option<i32>,
+}
+
+impl CommandStatus {
+    pub fn from_code(code: Option<i32>) -> Self {
+        Self { code }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// 退出码；进程被终止（如取消）时为 None
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+}
+
+/// 命令的执行结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub status: CommandStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl CommandOutput {
+    /// 退出码为 0、带指定标准输出的结果
+    pub fn success(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: CommandStatus::from_code(Some(0)),
+            stdout: stdout.into(),
+            stderr: Vec::new(),
+        }
+    }
+
+    /// 以指定退出码失败的结果
+    pub fn failure(code: i32, stderr: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: CommandStatus::from_code(Some(code)),
+            stdout: Vec::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    /// 附带标准输出
+    pub fn with_stdout(mut self, stdout: impl Into<Vec<u8>>) -> Self {
+        self.stdout = stdout.into();
+        self
+    }
+}
+
+/// 流式执行时的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEvent<'a> {
+    /// 收到一段标准输出（不保证按行切分）
+    Stdout(&'a [u8]),
+    /// 进程仍在运行，约每 [`TICK_INTERVAL`] 一次
+    Tick,
+}
+
+/// 外部命令执行器
+pub trait CommandRunner: Send + Sync {
+    /// 执行命令并等待结束
+    fn output(&self, program: &str, args: &[&str]) -> Result<CommandOutput>;
+
+    /// 执行命令，边运行边回调标准输出；回调返回 false 时终止进程，
+    /// 此时结果的退出码为 None
+    fn stream(
+        &self,
+        program: &str,
+        args: &[&str],
+        on_event: &mut dyn FnMut(StreamEvent<'_>) -> bool,
+    ) -> Result<CommandOutput>;
+
+    /// 启动命令后立即返回，不等待结束
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<()>;
+}
+
+/// 实际执行命令，在 Windows 上隐藏控制台窗口
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl SystemRunner {
+    fn command(program: &str, args: &[&str]) -> Command {
+        let mut command = Command::new(program);
+        command.args(args);
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        command
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        let output = Self::command(program, args)
+            .output()
+            .with_context(|| format!("无法启动 {}", program))?;
+        Ok(CommandOutput {
+            status: CommandStatus::from_code(output.status.code()),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn stream(
+        &self,
+        program: &str,
+        args: &[&str],
+        on_event: &mut dyn FnMut(StreamEvent<'_>) -> bool,
+    ) -> Result<CommandOutput> {
+        let mut child = Self::command(program, args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("无法启动 {}", program))?;
+
+        // 标准输出和标准错误各用一个线程读取，避免任一管道写满后进程阻塞
+        let (tx, rx) = mpsc::channel::<Vec<u8>>();
+        let stdout_reader = child.stdout.take().map(|mut stdout| {
+            std::thread::spawn(move || {
+                let mut buffer = [0u8; 4096];
+                loop {
+                    match stdout.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            if tx.send(buffer[..n].to_vec()).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                        Err(_) => break,
+                    }
+                }
+            })
+        });
+        let stderr_reader = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut content = Vec::new();
+                let _ = stderr.read_to_end(&mut content);
+                content
+            })
+        });
+
+        let mut stdout = Vec::new();
+        let mut cancelled = false;
+        loop {
+            let keep_running = match rx.recv_timeout(TICK_INTERVAL) {
+                Ok(chunk) => {
+                    stdout.extend_from_slice(&chunk);
+                    on_event(StreamEvent::Stdout(&chunk))
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => on_event(StreamEvent::Tick),
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            if !keep_running {
+                cancelled = true;
+                break;
+            }
+        }
+
+        // 标准输出关闭后进程可能仍在运行
+        while !cancelled && child.try_wait()?.is_none() {
+            if !on_event(StreamEvent::Tick) {
+                cancelled = true;
+                break;
+            }
+            std::thread::sleep(TICK_INTERVAL);
+        }
+
+        if cancelled {
+            let _ = child.kill();
+        }
+        let status = child.wait()?;
+        if let Some(handle) = stdout_reader {
+            let _ = handle.join();
+        }
+        let stderr = stderr_reader
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+
+        Ok(CommandOutput {
+            status: CommandStatus::from_code(if cancelled { None } else { status.code() }),
+            stdout,
+            stderr,
+        })
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<()> {
+        Self::command(program, args)
+            .spawn()
+            .with_context(|| format!("无法启动 {}", program))?;
+        Ok(())
+    }
+}
+
+/// 一次命令调用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Invocation {
+    /// 程序名：去掉目录和扩展名，转为小写（`X:\bin\Dism.exe` → `dism`）
+    pub fn program_name(&self) -> String {
+        program_name(&self.program)
+    }
+
+    /// 命令行文本，用于断言和日志
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn program_name(program: &str) -> String {
+    let name = program.rsplit(['\\', '/']).next().unwrap_or(program).to_lowercase();
+    match name.rsplit_once('.') {
+        Some((stem, "exe" | "com")) => stem.to_string(),
+        _ => name,
+    }
+}
+
+/// 按调用生成回复，可以在命令执行时读取参数引用的文件
+pub type Responder = Arc<dyn Fn(&Invocation) -> CommandOutput + Send + Sync>;
+
+#[derive(Clone)]
+enum Reply {
+    Output(CommandOutput),
+    With(Responder),
+}
+
+impl std::fmt::Debug for Reply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reply::Output(output) => f.debug_tuple("Output").field(output).finish(),
+            Reply::With(_) => f.write_str("With(..)"),
+        }
+    }
+}
+
+/// 预设的回复
+#[derive(Debug, Clone)]
+struct Rule {
+    program: String,
+    /// 参数中应包含的片段（不区分大小写）
+    fragments: Vec<String>,
+    reply: Reply,
+    /// 是否可以重复使用
+    repeat: bool,
+}
+
+impl Rule {
+    fn matches(&self, invocation: &Invocation) -> bool {
+        let args: Vec<String> = invocation.args.iter().map(|a| a.to_lowercase()).collect();
+        self.program == invocation.program_name()
+            && self.fragments.iter().all(|f| args.iter().any(|a| a.contains(f.as_str())))
+    }
+}
+
+/// 测试用执行器：按预设回放输出，并记录所有调用
+///
+/// 每次调用按添加顺序查找第一条匹配的预设：程序名相同，且参数包含预设的所有片段。
+/// [`reply`](Self::reply) 添加的预设只使用一次，[`reply_always`](Self::reply_always) 的可以重复使用，
+/// [`reply_with`](Self::reply_with) 的按调用生成回复（如读取 diskpart 的脚本文件）。
+/// 没有匹配的预设时返回 [`fallback`](Self::fallback) 设置的结果，未设置则报错。
+#[derive(Debug, Default)]
+pub struct FakeRunner {
+    rules: Mutex<Vec<Rule>>,
+    fallback: Mutex<Option<CommandOutput>>,
+    calls: Mutex<Vec<Invocation>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_rule(&self, program: &str, args: &[&str], reply: Reply, repeat: bool) -> &Self {
+        self.rules.lock().unwrap().push(Rule {
+            program: program_name(program),
+            fragments: args.iter().map(|a| a.to_lowercase()).collect(),
+            reply,
+            repeat,
+        });
+        self
+    }
+
+    /// 下一次匹配的调用返回 `output`
+    pub fn reply(&self, program: &str, args: &[&str], output: CommandOutput) -> &Self {
+        self.add_rule(program, args, Reply::Output(output), false)
+    }
+
+    /// 所有匹配的调用都返回 `output`
+    pub fn reply_always(&self, program: &str, args: &[&str], output: CommandOutput) -> &Self {
+        self.add_rule(program, args, Reply::Output(output), true)
+    }
+
+    /// 所有匹配的调用都由 `responder` 生成回复
+    pub fn reply_with(
+        &self,
+        program: &str,
+        args: &[&str],
+        responder: impl Fn(&Invocation) -> CommandOutput + Send + Sync + 'static,
+    ) -> &Self {
+        self.add_rule(program, args, Reply::With(Arc::new(responder)), true)
+    }
+
+    /// 没有匹配的预设时返回 `output`
+    pub fn fallback(&self, output: CommandOutput) -> &Self {
+        *self.fallback.lock().unwrap() = Some(output);
+        self
+    }
+
+    /// 到目前为止的所有调用
+    pub fn calls(&self) -> Vec<Invocation> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// 对指定程序的调用
+    pub fn calls_to(&self, program: &str) -> Vec<Invocation> {
+        let name = program_name(program);
+        self.calls().into_iter().filter(|c| c.program_name() == name).collect()
+    }
+
+    /// 记录调用并查找预设的回复
+    fn respond(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        let invocation = Invocation {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        };
+        let command_line = invocation.command_line();
+        self.calls.lock().unwrap().push(invocation.clone());
+
+        let mut rules = self.rules.lock().unwrap();
+        if let Some(index) = rules.iter().position(|r| r.matches(&invocation)) {
+            let rule = &rules[index];
+            let reply = rule.reply.clone();
+            if !rule.repeat {
+                rules.remove(index);
+            }
+            drop(rules);
+            return Ok(match reply {
+                Reply::Output(output) => output,
+                Reply::With(responder) => responder(&invocation),
+            });
+        }
+        drop(rules);
+
+        self.fallback
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("未预设的命令: {}", command_line))
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn output(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        self.respond(program, args)
+    }
+
+    /// 按行（`\r` 或 `\n` 结尾）逐段回放标准输出，每段之后插入一次 Tick
+    fn stream(
+        &self,
+        program: &str,
+        args: &[&str],
+        on_event: &mut dyn FnMut(StreamEvent<'_>) -> bool,
+    ) -> Result<CommandOutput> {
+        let output = self.respond(program, args)?;
+        for chunk in output.stdout.split_inclusive(|&b| b == b'\r' || b == b'\n') {
+            if !on_event(StreamEvent::Stdout(chunk)) || !on_event(StreamEvent::Tick) {
+                return Ok(CommandOutput {
+                    status: CommandStatus::from_code(None),
+                    ..output
+                });
+            }
+        }
+        Ok(output)
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<()> {
+        self.respond(program, args).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_replay() {
+        let fake = FakeRunner::new();
+        fake.reply("dism", &["/get-imageinfo"], CommandOutput::success("Index : 1"))
+            .reply("dism", &["/get-imageinfo"], CommandOutput::failure(2, "error"))
+            .reply_always("bcdedit", &[], CommandOutput::success(""));
+
+        let dism = r"X:\LetRecovery\bin\Dism\DISM.exe";
+        let output = fake.output(dism, &["/Get-ImageInfo", "/ImageFile:D:\\install.wim"]).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"Index : 1");
+
+        // 一次性预设按顺序消耗
+        let output = fake.output(dism, &["/Get-ImageInfo"]).unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        assert!(fake.output(dism, &["/Get-ImageInfo"]).is_err());
+
+        // 参数片段不匹配
+        assert!(fake.output("dism.exe", &["/Apply-Image"]).is_err());
+
+        for _ in 0..3 {
+            assert!(fake.output("bcdedit", &["/enum"]).unwrap().status.success());
+        }
+
+        fake.fallback(CommandOutput::success("ok"));
+        assert_eq!(fake.output("reg.exe", &["load"]).unwrap().stdout, b"ok");
+
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 8);
+        assert_eq!(calls[0].command_line(), format!("{} /Get-ImageInfo /ImageFile:D:\\install.wim", dism));
+        assert_eq!(fake.calls_to("BCDEDIT.EXE").len(), 3);
+    }
+
+    #[test]
+    fn test_fake_stream() {
+        let fake = FakeRunner::new();
+        fake.reply_always(
+            "ghost64",
+            &["-clone"],
+            CommandOutput::success("[==   ] 10.0%\r[==== ] 50.0%\r[=====] 100.0%\r\nDone\n"),
+        );
+
+        let mut chunks = Vec::new();
+        let output = fake
+            .stream("ghost64.exe", &["-clone,mode=pload"], &mut |event| {
+                if let StreamEvent::Stdout(chunk) = event {
+                    chunks.push(String::from_utf8_lossy(chunk).into_owned());
+                }
+                true
+            })
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(chunks, ["[==   ] 10.0%\r", "[==== ] 50.0%\r", "[=====] 100.0%\r", "\n", "Done\n"]);
+
+        // 回调返回 false 时视为取消
+        let mut seen = 0;
+        let output = fake
+            .stream("ghost64.exe", &["-clone"], &mut |event| {
+                if matches!(event, StreamEvent::Stdout(_)) {
+                    seen += 1;
+                }
+                seen < 2
+            })
+            .unwrap();
+        assert_eq!(seen, 2);
+        assert_eq!(output.status.code(), None);
+    }
+
+    #[test]
+    fn test_reply_with() {
+        let fake = FakeRunner::new();
+        fake.reply_with("diskpart", &["/s"], |invocation| {
+            CommandOutput::success(format!("script {}", invocation.args[1]))
+        });
+
+        for script in ["a.txt", "b.txt"] {
+            let output = fake.output("diskpart.exe", &["/s", script]).unwrap();
+            assert_eq!(output.stdout, format!("script {}", script).into_bytes());
+        }
+        assert!(fake.output("diskpart.exe", &[]).is_err());
+        assert_eq!(fake.calls_to("diskpart").len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_stream() {
+        let mut stdout = Vec::new();
+        let output = SystemRunner
+            .stream("sh", &["-c", "printf 'a\\rb\\n'; echo err >&2; exit 3"], &mut |event| {
+                if let StreamEvent::Stdout(chunk) = event {
+                    stdout.extend_from_slice(chunk);
+                }
+                true
+            })
+            .unwrap();
+        assert_eq!(stdout, b"a\rb\n");
+        assert_eq!(output.stdout, stdout);
+        assert_eq!(output.stderr, b"err\n");
+        assert_eq!(output.status.code(), Some(3));
+
+        let started = std::time::Instant::now();
+        let output = SystemRunner.stream("sleep", &["10"], &mut |_| false).unwrap();
+        assert_eq!(output.status.code(), None);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
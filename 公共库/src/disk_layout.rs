@@ -0,0 +1,340 @@
+//! 整盘安装的标准分区布局
+//!
+//! 按 Microsoft 推荐的 Windows 分区布局生成 diskpart 脚本：
+//!
+//! - UEFI/GPT：ESP（FAT32）、MSR、Windows、可选的恢复分区；
+//! - BIOS/MBR：系统保留分区（NTFS，活动）、Windows、可选的恢复分区。
+//!
+//! 恢复分区放在磁盘末尾：先让 Windows 分区占满剩余空间，再从中
+//! `shrink` 出恢复分区的大小，这样无需事先知道磁盘容量。
+
+use anyhow::Result;
+
+use crate::diskpart::script::{DiskpartScript, FileSystem, PartitionStyle, PartitionType};
+use crate::partition_table::gpt_types;
+
+/// 引导固件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Firmware {
+    Uefi,
+    Bios,
+}
+
+impl Firmware {
+    /// 对应的分区表类型
+    pub fn partition_style(&self) -> &'static str {
+        match self {
+            Firmware::Uefi => "GPT",
+            Firmware::Bios => "MBR",
+        }
+    }
+}
+
+/// ESP 大小范围（MB）；4K 扇区磁盘上 FAT32 至少需要 260 MB
+pub const ESP_SIZE_RANGE: (u64, u64) = (100, 300);
+/// MSR 大小范围（MB）
+pub const MSR_SIZE_RANGE: (u64, u64) = (16, 128);
+/// 系统保留分区大小范围（MB）
+pub const SYSTEM_RESERVED_SIZE_RANGE: (u64, u64) = (50, 500);
+/// 恢复分区大小范围（MB）
+pub const RECOVERY_SIZE_RANGE: (u64, u64) = (300, 4096);
+/// Windows 分区至少需要的空间（MB）
+pub const MIN_WINDOWS_SIZE_MB: u64 = 32 * 1024;
+
+/// 恢复分区的 GPT 属性：平台必需 + 不分配盘符
+const RECOVERY_GPT_ATTRIBUTES: u64 = 0x8000000000000001;
+/// 恢复分区的 MBR 类型
+const RECOVERY_MBR_TYPE: &str = "27";
+
+/// 分区布局选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutOptions {
+    pub firmware: Firmware,
+    /// ESP 大小（MB，仅 UEFI）
+    pub esp_size_mb: u64,
+    /// MSR 大小（MB，仅 UEFI）
+    pub msr_size_mb: u64,
+    /// 系统保留分区大小（MB，仅 BIOS）
+    pub system_reserved_size_mb: u64,
+    /// 恢复分区大小（MB），None 表示不创建
+    pub recovery_size_mb: Option<u64>,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            firmware: Firmware::Uefi,
+            esp_size_mb: 260,
+            msr_size_mb: 16,
+            system_reserved_size_mb: 100,
+            recovery_size_mb: Some(1024),
+        }
+    }
+}
+
+fn check_range(name: &str, value: u64, (min, max): (u64, u64)) -> Result<()> {
+    if !(min..=max).contains(&value) {
+        anyhow::bail!("{}大小应在 {} - {} MB 之间，当前为 {} MB", name, min, max, value);
+    }
+    Ok(())
+}
+
+impl LayoutOptions {
+    /// 检查各分区大小是否在合理范围内
+    pub fn validate(&self) -> Result<()> {
+        match self.firmware {
+            Firmware::Uefi => {
+                check_range("ESP ", self.esp_size_mb, ESP_SIZE_RANGE)?;
+                check_range("MSR ", self.msr_size_mb, MSR_SIZE_RANGE)?;
+            }
+            Firmware::Bios => {
+                check_range("系统保留分区", self.system_reserved_size_mb, SYSTEM_RESERVED_SIZE_RANGE)?;
+            }
+        }
+        if let Some(size) = self.recovery_size_mb {
+            check_range("恢复分区", size, RECOVERY_SIZE_RANGE)?;
+        }
+        Ok(())
+    }
+}
+
+/// 分区用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutRole {
+    /// EFI 系统分区
+    Esp,
+    /// Microsoft 保留分区
+    Msr,
+    /// 系统保留分区（BIOS 启动文件）
+    SystemReserved,
+    Windows,
+    Recovery,
+}
+
+impl LayoutRole {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutRole::Esp => "EFI 系统分区",
+            LayoutRole::Msr => "MSR 保留分区",
+            LayoutRole::SystemReserved => "系统保留分区",
+            LayoutRole::Windows => "Windows",
+            LayoutRole::Recovery => "恢复分区",
+        }
+    }
+}
+
+/// 布局中的一个分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedPartition {
+    pub role: LayoutRole,
+    /// 大小（MB），None 表示占用剩余空间
+    pub size_mb: Option<u64>,
+    /// 文件系统，None 表示不格式化
+    pub file_system: Option<FileSystem>,
+}
+
+/// 整盘分区布局
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskLayout {
+    pub disk_number: u32,
+    pub options: LayoutOptions,
+    pub partitions: Vec<PlannedPartition>,
+}
+
+impl DiskLayout {
+    pub fn new(disk_number: u32, options: LayoutOptions) -> Result<Self> {
+        options.validate()?;
+
+        let part = |role, size_mb, file_system| PlannedPartition {
+            role,
+            size_mb,
+            file_system,
+        };
+        let mut partitions = match options.firmware {
+            Firmware::Uefi => vec![
+                part(LayoutRole::Esp, Some(options.esp_size_mb), Some(FileSystem::Fat32)),
+                part(LayoutRole::Msr, Some(options.msr_size_mb), None),
+            ],
+            Firmware::Bios => vec![part(
+                LayoutRole::SystemReserved,
+                Some(options.system_reserved_size_mb),
+                Some(FileSystem::Ntfs),
+            )],
+        };
+        partitions.push(part(LayoutRole::Windows, None, Some(FileSystem::Ntfs)));
+        if let Some(size) = options.recovery_size_mb {
+            partitions.push(part(LayoutRole::Recovery, Some(size), Some(FileSystem::Ntfs)));
+        }
+
+        Ok(Self {
+            disk_number,
+            options,
+            partitions,
+        })
+    }
+
+    /// 布局需要的最小磁盘容量（MB）
+    pub fn minimum_disk_size_mb(&self) -> u64 {
+        // 预留 1 MB 对齐和 GPT 备份分区表的空间
+        self.partitions.iter().filter_map(|p| p.size_mb).sum::<u64>() + MIN_WINDOWS_SIZE_MB + 2
+    }
+
+    /// 检查磁盘容量是否足够
+    pub fn check_disk_size(&self, disk_size_bytes: u64) -> Result<()> {
+        let size_mb = disk_size_bytes >> 20;
+        if size_mb < self.minimum_disk_size_mb() {
+            anyhow::bail!(
+                "磁盘 {} 容量不足：{} MB，至少需要 {} MB",
+                self.disk_number,
+                size_mb,
+                self.minimum_disk_size_mb()
+            );
+        }
+        Ok(())
+    }
+
+    /// 生成 diskpart 脚本
+    ///
+    /// `windows_letter` 为 Windows 分区的盘符；`system_letter` 为 ESP / 系统保留分区的盘符，
+    /// None 时不分配（之后由修复引导按分区类型查找）。
+    pub fn diskpart_script(&self, windows_letter: char, system_letter: Option<char>) -> Result<DiskpartScript> {
+        let mut script = DiskpartScript::builder()
+            .select_disk(self.disk_number)
+            .clean()
+            .convert(match self.options.firmware {
+                Firmware::Uefi => PartitionStyle::Gpt,
+                Firmware::Bios => PartitionStyle::Mbr,
+            });
+
+        for partition in &self.partitions {
+            let kind = match partition.role {
+                LayoutRole::Esp => PartitionType::Efi,
+                LayoutRole::Msr => PartitionType::Msr,
+                _ => PartitionType::Primary,
+            };
+            script = script.create_partition(kind, partition.size_mb);
+
+            // Windows 之后还有恢复分区时，先从 Windows 分区末尾腾出空间
+            if partition.role == LayoutRole::Windows {
+                if let Some(size) = self.options.recovery_size_mb {
+                    script = script.shrink_minimum(size);
+                }
+            }
+
+            if let Some(fs) = partition.file_system {
+                let label = match partition.role {
+                    LayoutRole::Esp | LayoutRole::SystemReserved => "System",
+                    LayoutRole::Recovery => "Recovery",
+                    _ => "Windows",
+                };
+                script = script.format(fs, Some(label));
+            }
+
+            match partition.role {
+                LayoutRole::Esp | LayoutRole::SystemReserved => {
+                    if partition.role == LayoutRole::SystemReserved {
+                        script = script.active();
+                    }
+                    if let Some(letter) = system_letter {
+                        script = script.assign_letter(letter);
+                    }
+                }
+                LayoutRole::Windows => script = script.assign_letter(windows_letter),
+                LayoutRole::Recovery => match self.options.firmware {
+                    Firmware::Uefi => {
+                        script = script
+                            .set_id(&gpt_types::WINRE.to_string())
+                            .gpt_attributes(RECOVERY_GPT_ATTRIBUTES);
+                    }
+                    Firmware::Bios => script = script.set_id(RECOVERY_MBR_TYPE),
+                },
+                LayoutRole::Msr => {}
+            }
+        }
+
+        script.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uefi_layout() {
+        let layout = DiskLayout::new(1, LayoutOptions::default()).unwrap();
+        let roles: Vec<_> = layout.partitions.iter().map(|p| p.role).collect();
+        assert_eq!(
+            roles,
+            [LayoutRole::Esp, LayoutRole::Msr, LayoutRole::Windows, LayoutRole::Recovery]
+        );
+        assert_eq!(
+            layout.diskpart_script('W', None).unwrap().render(),
+            "select disk 1\n\
+             clean\n\
+             convert gpt\n\
+             create partition efi size=260\n\
+             format fs=fat32 quick label=\"System\"\n\
+             create partition msr size=16\n\
+             create partition primary\n\
+             shrink minimum=1024\n\
+             format fs=ntfs quick label=\"Windows\"\n\
+             assign letter=W\n\
+             create partition primary size=1024\n\
+             format fs=ntfs quick label=\"Recovery\"\n\
+             set id=de94bba4-06d1-4d40-a16a-bfd50179d6ac override\n\
+             gpt attributes=0x8000000000000001\n"
+        );
+    }
+
+    #[test]
+    fn test_bios_layout() {
+        let options = LayoutOptions {
+            firmware: Firmware::Bios,
+            recovery_size_mb: None,
+            ..Default::default()
+        };
+        let layout = DiskLayout::new(0, options).unwrap();
+        assert_eq!(
+            layout.diskpart_script('W', Some('S')).unwrap().render(),
+            "select disk 0\n\
+             clean\n\
+             convert mbr\n\
+             create partition primary size=100\n\
+             format fs=ntfs quick label=\"System\"\n\
+             active\n\
+             assign letter=S\n\
+             create partition primary\n\
+             format fs=ntfs quick label=\"Windows\"\n\
+             assign letter=W\n"
+        );
+    }
+
+    #[test]
+    fn test_validate_sizes() {
+        let options = |esp_size_mb, recovery_size_mb| LayoutOptions {
+            esp_size_mb,
+            recovery_size_mb,
+            ..Default::default()
+        };
+        assert!(DiskLayout::new(0, options(50, None)).is_err());
+        assert!(DiskLayout::new(0, options(512, None)).is_err());
+        assert!(DiskLayout::new(0, options(100, Some(100))).is_err());
+        assert!(DiskLayout::new(0, options(300, Some(1024))).is_ok());
+
+        // BIOS 布局不检查 ESP 大小
+        let bios = LayoutOptions {
+            firmware: Firmware::Bios,
+            ..options(0, None)
+        };
+        assert!(bios.validate().is_ok());
+    }
+
+    #[test]
+    fn test_disk_size() {
+        let layout = DiskLayout::new(0, LayoutOptions::default()).unwrap();
+        assert_eq!(layout.minimum_disk_size_mb(), 260 + 16 + 1024 + MIN_WINDOWS_SIZE_MB + 2);
+        assert!(layout.check_disk_size(16 << 30).is_err());
+        assert!(layout.check_disk_size(256 << 30).is_ok());
+    }
+}
@@ -0,0 +1,439 @@
+//! diskpart 输出解析
+//!
+//! diskpart 的输出随系统语言变化（"Disk"/"磁盘"/"Datenträger"/"ディスク"），
+//! 这里不匹配任何单词，只依赖与语言无关的结构：
+//!
+//! - 表格的列位置由表头下方的 `---` 分隔线确定，按列取值；
+//! - 当前选中的行以 `*` 开头；
+//! - 编号、大小、GUID、十六进制分区类型等取值本身与语言无关。
+//!
+//! 唯一的例外是 `detail partition` 的分区类型：其中的名称行没有固定顺序，
+//! 只能按各语言的名称（[`PARTITION_TYPE_KEYS`]）查找。
+//!
+//! 命令是否成功应以 diskpart 的退出码为准（脚本中任一命令失败即返回非 0）。
+//! 脚本本身由 [`script`] 构建。
+
+pub mod script;
+
+/// EFI 系统分区的 GPT 类型
+pub const ESP_GPT_TYPE: &str = "c12a7328-f81f-11d2-ba4b-00a0c93ec93b";
+/// EFI 系统分区的 MBR 类型
+pub const ESP_MBR_TYPE: u8 = 0xEF;
+
+/// `detail partition` 输出中分区类型的名称（英、简中、繁中、德、日）
+pub const PARTITION_TYPE_KEYS: &[&str] = &["Type", "类型", "類型", "Typ", "種類"];
+
+/// 表格中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRow {
+    /// 是否为当前选中项（行首为 `*`）
+    pub selected: bool,
+    /// 按列切分并去除空白后的单元格
+    pub cells: Vec<String>,
+}
+
+impl TableRow {
+    fn cell(&self, index: usize) -> &str {
+        self.cells.get(index).map(String::as_str).unwrap_or("")
+    }
+
+    /// 第一列末尾的编号，如 "Disk 0" / "磁盘 0" 中的 0
+    pub fn number(&self) -> Option<u32> {
+        self.cell(0).split_whitespace().last()?.parse().ok()
+    }
+}
+
+/// 表格
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    /// 各列的起止显示位置
+    pub columns: Vec<(usize, usize)>,
+    pub rows: Vec<TableRow>,
+}
+
+/// 字符在控制台中的显示宽度，中日韩等全角字符占两列
+fn char_width(c: char) -> usize {
+    let c = c as u32;
+    let wide = matches!(c,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6);
+    if wide {
+        2
+    } else {
+        1
+    }
+}
+
+/// 分隔线：只由 `-` 和空格组成且至少两组
+fn separator_columns(line: &str) -> Option<Vec<(usize, usize)>> {
+    if line.trim().is_empty() || !line.chars().all(|c| c == '-' || c == ' ') {
+        return None;
+    }
+    let mut columns = Vec::new();
+    let mut start = None;
+    for (i, c) in line.chars().chain(std::iter::once(' ')).enumerate() {
+        match (c, start) {
+            ('-', None) => start = Some(i),
+            (' ', Some(s)) => {
+                columns.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    (columns.len() >= 2).then_some(columns)
+}
+
+/// 按列位置切分一行
+fn split_row(line: &str, columns: &[(usize, usize)]) -> TableRow {
+    let mut cells = vec![String::new(); columns.len()];
+    let mut position = 0;
+    let mut selected = false;
+    for c in line.chars() {
+        if position < columns[0].0 {
+            selected |= c == '*';
+        } else {
+            // 超出列宽的字符归入前一列
+            let index = columns.iter().rposition(|(start, _)| position >= *start).unwrap_or(0);
+            cells[index].push(c);
+        }
+        position += char_width(c);
+    }
+    TableRow {
+        selected,
+        cells: cells.iter().map(|c| c.trim().to_string()).collect(),
+    }
+}
+
+/// 解析输出中的所有表格
+pub fn parse_tables(output: &str) -> Vec<Table> {
+    let lines: Vec<&str> = output.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(columns) = separator_columns(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        let mut rows = Vec::new();
+        i += 1;
+        while i < lines.len() && !lines[i].trim().is_empty() {
+            rows.push(split_row(lines[i], &columns));
+            i += 1;
+        }
+        tables.push(Table { columns, rows });
+    }
+    tables
+}
+
+/// 解析大小，如 "476 GB"、"1024 KB"、"0 B"，返回字节数
+pub fn parse_size(text: &str) -> Option<u64> {
+    let mut parts = text.split_whitespace();
+    let number: u64 = parts.next()?.replace([',', '.', '\u{a0}'], "").parse().ok()?;
+    let unit = parts.next().unwrap_or("B");
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "B" => 0,
+        "KB" => 10,
+        "MB" => 20,
+        "GB" => 30,
+        "TB" => 40,
+        _ => return None,
+    };
+    Some(number << shift)
+}
+
+/// `list disk` 中的磁盘
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskEntry {
+    pub number: u32,
+    pub size_bytes: u64,
+    pub free_bytes: u64,
+    pub is_gpt: bool,
+    pub selected: bool,
+}
+
+/// `list volume` 中的卷
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeEntry {
+    pub number: u32,
+    pub letter: Option<char>,
+    pub label: String,
+    pub file_system: String,
+    pub size_bytes: u64,
+    pub selected: bool,
+}
+
+/// `list partition` 中的分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionEntry {
+    pub number: u32,
+    pub size_bytes: u64,
+    pub offset_bytes: u64,
+    pub selected: bool,
+}
+
+/// 按列数识别表格：list disk 6 列、list volume 8 列、list partition 4 列
+fn tables_with_columns(output: &str, count: usize) -> impl Iterator<Item = Table> {
+    parse_tables(output).into_iter().filter(move |t| t.columns.len() == count)
+}
+
+/// 解析 `list disk`（`detail volume` 中的磁盘表格式相同）
+pub fn parse_list_disk(output: &str) -> Vec<DiskEntry> {
+    tables_with_columns(output, 6)
+        .flat_map(|t| t.rows)
+        .filter_map(|row| {
+            Some(DiskEntry {
+                number: row.number()?,
+                size_bytes: parse_size(row.cell(2)).unwrap_or(0),
+                free_bytes: parse_size(row.cell(3)).unwrap_or(0),
+                is_gpt: row.cell(5) == "*",
+                selected: row.selected,
+            })
+        })
+        .collect()
+}
+
+/// 解析 `list volume`
+pub fn parse_list_volume(output: &str) -> Vec<VolumeEntry> {
+    tables_with_columns(output, 8)
+        .flat_map(|t| t.rows)
+        .filter_map(|row| {
+            let letter = row.cell(1);
+            let letter = match letter.chars().next() {
+                Some(c) if letter.len() == 1 && c.is_ascii_alphabetic() => Some(c.to_ascii_uppercase()),
+                _ => None,
+            };
+            Some(VolumeEntry {
+                number: row.number()?,
+                letter,
+                label: row.cell(2).to_string(),
+                file_system: row.cell(3).to_string(),
+                size_bytes: parse_size(row.cell(5)).unwrap_or(0),
+                selected: row.selected,
+            })
+        })
+        .collect()
+}
+
+/// 解析 `list partition`
+pub fn parse_list_partition(output: &str) -> Vec<PartitionEntry> {
+    tables_with_columns(output, 4)
+        .flat_map(|t| t.rows)
+        .filter_map(|row| {
+            Some(PartitionEntry {
+                number: row.number()?,
+                size_bytes: parse_size(row.cell(2)).unwrap_or(0),
+                offset_bytes: parse_size(row.cell(3)).unwrap_or(0),
+                selected: row.selected,
+            })
+        })
+        .collect()
+}
+
+/// 从 `detail volume` 输出中取卷所在的磁盘号
+///
+/// 跨多个磁盘的动态卷无法确定唯一磁盘，返回 None。
+pub fn parse_detail_volume_disk(output: &str) -> Option<u32> {
+    let disks = parse_list_disk(output);
+    match disks.as_slice() {
+        [disk] => Some(disk.number),
+        _ => disks.iter().find(|d| d.selected).map(|d| d.number),
+    }
+}
+
+/// 分区类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionType {
+    /// GPT 分区类型 GUID（小写，不含花括号）
+    Gpt(String),
+    /// MBR 分区类型
+    Mbr(u8),
+}
+
+impl PartitionType {
+    /// 是否为 EFI 系统分区
+    pub fn is_esp(&self) -> bool {
+        match self {
+            PartitionType::Gpt(guid) => guid == ESP_GPT_TYPE,
+            PartitionType::Mbr(kind) => *kind == ESP_MBR_TYPE,
+        }
+    }
+}
+
+fn is_guid(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('-').collect();
+    parts.len() == 5
+        && parts
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(p, len)| p.len() == len && p.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// 从 `detail partition` 输出中取分区类型
+///
+/// 取名称为 [`PARTITION_TYPE_KEYS`] 之一的 `名称 : 值` 行，其值为 GUID（GPT）
+/// 或两位十六进制数（MBR）。不按取值猜测，避免把计算机名等标题行误当成类型。
+pub fn parse_detail_partition_type(output: &str) -> Option<PartitionType> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| {
+            let key = key.trim();
+            PARTITION_TYPE_KEYS.iter().any(|k| key.eq_ignore_ascii_case(k))
+        })
+        .find_map(|(_, value)| {
+            let value = value.trim();
+            let value = value.trim_start_matches('{').trim_end_matches('}');
+            if is_guid(value) {
+                Some(PartitionType::Gpt(value.to_ascii_lowercase()))
+            } else if value.len() == 2 {
+                u8::from_str_radix(value, 16).ok().map(PartitionType::Mbr)
+            } else {
+                None
+            }
+        })
+}
+
+/// 从 `shrink querymax` 输出中取可压缩的最大空间（MB）
+///
+/// 取输出中最后一个 "数字 + 单位" 的大小，如
+/// "12 GB (12345 MB)" 取更精确的 12345 MB。
+pub fn parse_shrink_querymax(output: &str) -> Option<u64> {
+    let mut last = None;
+    for line in output.lines() {
+        let tokens: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ':' | '：'))
+            .filter(|t| !t.is_empty())
+            .collect();
+        for pair in tokens.windows(2) {
+            if matches!(pair[1].to_ascii_uppercase().as_str(), "KB" | "MB" | "GB" | "TB") {
+                if let Some(bytes) = parse_size(&format!("{} {}", pair[0], pair[1])) {
+                    last = Some(bytes >> 20);
+                }
+            }
+        }
+    }
+    last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试数据按 `=== 命令 ===` 分段
+    fn section<'a>(fixture: &'a str, name: &str) -> &'a str {
+        let marker = format!("=== {} ===", name);
+        let start = fixture.find(&marker).unwrap() + marker.len();
+        let rest = &fixture[start..];
+        &rest[..rest.find("\n=== ").unwrap_or(rest.len())]
+    }
+
+    const FIXTURES: &[(&str, &str)] = &[
+        ("en", include_str!("../testdata/diskpart/en.txt")),
+        ("zh", include_str!("../testdata/diskpart/zh.txt")),
+        ("de", include_str!("../testdata/diskpart/de.txt")),
+        ("ja", include_str!("../testdata/diskpart/ja.txt")),
+    ];
+
+    #[test]
+    fn test_list_disk() {
+        for (lang, fixture) in FIXTURES {
+            let disks = parse_list_disk(section(fixture, "list disk"));
+            assert_eq!(disks.len(), 2, "{}", lang);
+            assert_eq!(disks[0].number, 0, "{}", lang);
+            assert_eq!(disks[0].size_bytes, 476 << 30, "{}", lang);
+            assert!(disks[0].is_gpt && !disks[1].is_gpt, "{}", lang);
+            assert_eq!(disks[1].number, 1, "{}", lang);
+            assert_eq!(disks[1].free_bytes, 1024 << 10, "{}", lang);
+        }
+    }
+
+    #[test]
+    fn test_list_volume() {
+        for (lang, fixture) in FIXTURES {
+            let volumes = parse_list_volume(section(fixture, "list volume"));
+            let summary: Vec<_> = volumes
+                .iter()
+                .map(|v| (v.number, v.letter, v.file_system.as_str(), v.size_bytes >> 20))
+                .collect();
+            assert_eq!(
+                summary,
+                [
+                    (0, Some('D'), "", 0),
+                    (1, Some('C'), "NTFS", 475 << 10),
+                    (2, None, "FAT32", 100),
+                    (3, Some('E'), "NTFS", 1863 << 10),
+                ],
+                "{}",
+                lang
+            );
+            assert!(volumes[1].selected && !volumes[0].selected, "{}", lang);
+            assert_eq!(volumes[3].label, "Data", "{}", lang);
+        }
+    }
+
+    #[test]
+    fn test_list_partition() {
+        for (lang, fixture) in FIXTURES {
+            let partitions = parse_list_partition(section(fixture, "list partition"));
+            let summary: Vec<_> = partitions
+                .iter()
+                .map(|p| (p.number, p.size_bytes >> 20, p.offset_bytes >> 10, p.selected))
+                .collect();
+            assert_eq!(
+                summary,
+                [
+                    (1, 100, 1024, false),
+                    (2, 16, 101 << 10, false),
+                    (3, 475 << 10, 117 << 10, true),
+                ],
+                "{}",
+                lang
+            );
+        }
+    }
+
+    #[test]
+    fn test_detail_volume_and_partition() {
+        for (lang, fixture) in FIXTURES {
+            assert_eq!(parse_detail_volume_disk(section(fixture, "detail volume")), Some(0), "{}", lang);
+
+            let esp = parse_detail_partition_type(section(fixture, "detail partition")).unwrap();
+            assert!(esp.is_esp(), "{}", lang);
+        }
+        assert_eq!(
+            parse_detail_partition_type("Partition 1\nType  : 07\nHidden: No\n"),
+            Some(PartitionType::Mbr(0x07))
+        );
+        // 标题行的值恰好是两位十六进制数时不能当成类型
+        assert_eq!(
+            parse_detail_partition_type("On computer: AB\n\nPartition 1\nType  : 07\n"),
+            Some(PartitionType::Mbr(0x07))
+        );
+        assert_eq!(parse_detail_partition_type("On computer: AB\nHidden: No\n"), None);
+    }
+
+    #[test]
+    fn test_shrink_querymax() {
+        for (lang, fixture) in FIXTURES {
+            assert_eq!(parse_shrink_querymax(section(fixture, "shrink querymax")), Some(12345), "{}", lang);
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("476 GB"), Some(476 << 30));
+        assert_eq!(parse_size("0 B"), Some(0));
+        assert_eq!(parse_size("1,024 KB"), Some(1024 << 10));
+        assert_eq!(parse_size("abc"), None);
+    }
+}
@@ -0,0 +1,734 @@
+//! diskpart 脚本构建
+//!
+//! 用类型化的命令代替手工拼接的脚本文本。构建时检查参数（盘符、大小、卷标、分区类型）
+//! 和命令顺序（操作分区前必须先选中对象），再渲染为脚本文本；
+//! 同一个脚本也能列出执行计划，供界面在执行前向用户确认其中会破坏数据的操作。
+
+use anyhow::Result;
+
+use super::is_guid;
+
+/// 操作对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Disk(u32),
+    Volume(char),
+    Partition(u32),
+}
+
+/// 文件系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSystem {
+    Ntfs,
+    Fat32,
+}
+
+impl FileSystem {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileSystem::Ntfs => "ntfs",
+            FileSystem::Fat32 => "fat32",
+        }
+    }
+
+    /// 卷标最大长度
+    fn max_label_len(&self) -> usize {
+        match self {
+            FileSystem::Ntfs => 32,
+            FileSystem::Fat32 => 11,
+        }
+    }
+}
+
+/// 新建分区的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    Primary,
+    Efi,
+    Msr,
+}
+
+/// 分区表类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionStyle {
+    Gpt,
+    Mbr,
+}
+
+/// diskpart 命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Select(Target),
+    /// 删除磁盘上的所有分区
+    Clean,
+    Convert(PartitionStyle),
+    CreatePartition {
+        kind: PartitionType,
+        /// None 表示占用全部剩余空间
+        size_mb: Option<u64>,
+    },
+    Shrink {
+        desired_mb: Option<u64>,
+        minimum_mb: Option<u64>,
+    },
+    ShrinkQueryMax,
+    Extend,
+    /// 快速格式化
+    Format {
+        file_system: FileSystem,
+        label: Option<String>,
+        /// 必要时强制卸载卷
+        force: bool,
+    },
+    /// 分配盘符，None 表示自动分配
+    Assign(Option<char>),
+    /// 移除盘符，None 表示移除全部
+    Remove(Option<char>),
+    DeletePartition {
+        /// 允许删除受保护的分区（ESP、MSR、恢复分区等）
+        force: bool,
+    },
+    Active,
+    /// 设置分区类型：GPT 类型 GUID 或 MBR 类型（两位十六进制）
+    SetId(String),
+    GptAttributes(u64),
+    ListDisk,
+    ListVolume,
+    ListPartition,
+    DetailVolume,
+    DetailPartition,
+}
+
+impl Command {
+    /// 脚本中的文本
+    pub fn render(&self) -> String {
+        match self {
+            Command::Select(Target::Disk(n)) => format!("select disk {}", n),
+            Command::Select(Target::Volume(c)) => format!("select volume {}", c),
+            Command::Select(Target::Partition(n)) => format!("select partition {}", n),
+            Command::Clean => "clean".to_string(),
+            Command::Convert(PartitionStyle::Gpt) => "convert gpt".to_string(),
+            Command::Convert(PartitionStyle::Mbr) => "convert mbr".to_string(),
+            Command::CreatePartition { kind, size_mb } => {
+                let kind = match kind {
+                    PartitionType::Primary => "primary",
+                    PartitionType::Efi => "efi",
+                    PartitionType::Msr => "msr",
+                };
+                match size_mb {
+                    Some(size) => format!("create partition {} size={}", kind, size),
+                    None => format!("create partition {}", kind),
+                }
+            }
+            Command::Shrink { desired_mb, minimum_mb } => {
+                let mut text = "shrink".to_string();
+                if let Some(size) = desired_mb {
+                    text += &format!(" desired={}", size);
+                }
+                if let Some(size) = minimum_mb {
+                    text += &format!(" minimum={}", size);
+                }
+                text
+            }
+            Command::ShrinkQueryMax => "shrink querymax".to_string(),
+            Command::Extend => "extend".to_string(),
+            Command::Format {
+                file_system,
+                label,
+                force,
+            } => {
+                let mut text = format!("format fs={} quick", file_system.name());
+                if let Some(label) = label {
+                    text += &format!(" label=\"{}\"", label);
+                }
+                if *force {
+                    text += " override";
+                }
+                text
+            }
+            Command::Assign(Some(c)) => format!("assign letter={}", c),
+            Command::Assign(None) => "assign".to_string(),
+            Command::Remove(Some(c)) => format!("remove letter={}", c),
+            Command::Remove(None) => "remove all".to_string(),
+            Command::DeletePartition { force: true } => "delete partition override".to_string(),
+            Command::DeletePartition { force: false } => "delete partition".to_string(),
+            Command::Active => "active".to_string(),
+            Command::SetId(id) => format!("set id={} override", id),
+            Command::GptAttributes(attributes) => format!("gpt attributes={:#018x}", attributes),
+            Command::ListDisk => "list disk".to_string(),
+            Command::ListVolume => "list volume".to_string(),
+            Command::ListPartition => "list partition".to_string(),
+            Command::DetailVolume => "detail volume".to_string(),
+            Command::DetailPartition => "detail partition".to_string(),
+        }
+    }
+
+    /// 是否会破坏已有数据
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            Command::Clean
+                | Command::Convert(_)
+                | Command::Shrink { .. }
+                | Command::Format { .. }
+                | Command::DeletePartition { .. }
+        )
+    }
+
+    /// 面向用户的说明，`focus` 为执行时选中的对象
+    fn describe(&self, focus: &Focus) -> String {
+        let object = focus.describe();
+        match self {
+            Command::Select(_) => format!("选择{}", object),
+            Command::Clean => format!("清空{}上的所有分区和数据", object),
+            Command::Convert(style) => format!(
+                "将{}转换为 {}",
+                object,
+                if *style == PartitionStyle::Gpt { "GPT" } else { "MBR" }
+            ),
+            Command::CreatePartition { kind, size_mb } => {
+                let kind = match kind {
+                    PartitionType::Primary => "主分区",
+                    PartitionType::Efi => "EFI 系统分区",
+                    PartitionType::Msr => "MSR 保留分区",
+                };
+                match size_mb {
+                    Some(size) => format!("在{}上创建 {} MB 的{}", object, size, kind),
+                    None => format!("在{}上用剩余空间创建{}", object, kind),
+                }
+            }
+            Command::Shrink { desired_mb, minimum_mb } => {
+                format!("将{}缩小 {} MB", object, desired_mb.or(*minimum_mb).unwrap_or(0))
+            }
+            Command::ShrinkQueryMax => format!("查询{}可缩小的空间", object),
+            Command::Extend => format!("用相邻的未分配空间扩展{}", object),
+            Command::Format {
+                file_system, label, ..
+            } => match label {
+                Some(label) => format!(
+                    "将{}格式化为 {}（卷标 {}），其中数据将被清除",
+                    object,
+                    file_system.name().to_uppercase(),
+                    label
+                ),
+                None => format!(
+                    "将{}格式化为 {}，其中数据将被清除",
+                    object,
+                    file_system.name().to_uppercase()
+                ),
+            },
+            Command::Assign(Some(c)) => format!("为{}分配盘符 {}:", object, c),
+            Command::Assign(None) => format!("为{}自动分配盘符", object),
+            Command::Remove(Some(c)) => format!("移除{}的盘符 {}:", object, c),
+            Command::Remove(None) => format!("移除{}的所有盘符", object),
+            Command::DeletePartition { .. } => format!("删除{}及其中的所有数据", object),
+            Command::Active => format!("将{}设为活动分区", object),
+            Command::SetId(id) => format!("将{}的类型设为 {}", object, id),
+            Command::GptAttributes(attributes) => format!("将{}的 GPT 属性设为 {:#018x}", object, attributes),
+            Command::ListDisk => "列出磁盘".to_string(),
+            Command::ListVolume => "列出卷".to_string(),
+            Command::ListPartition => format!("列出{}上的分区", object),
+            Command::DetailVolume => format!("查看{}的详细信息", object),
+            Command::DetailPartition => format!("查看{}的详细信息", object),
+        }
+    }
+}
+
+/// 在盘符、编号与紧随其后的汉字之间补一个空格，如“磁盘 1 上”“卷 D: 及”
+fn space_before_cjk(text: &str) -> String {
+    let mut spaced = String::with_capacity(text.len() + 4);
+    let mut previous = None;
+    for c in text.chars() {
+        let after_ascii = previous.is_some_and(|p: char| p.is_ascii_alphanumeric() || p == ':');
+        if after_ascii && ('\u{4e00}'..='\u{9fff}').contains(&c) {
+            spaced.push(' ');
+        }
+        spaced.push(c);
+        previous = Some(c);
+    }
+    spaced
+}
+
+/// 当前选中的对象，用于检查命令顺序和生成说明
+#[derive(Debug, Clone, Default)]
+struct Focus {
+    disk: Option<u32>,
+    /// 选中了卷或分区；卷的盘符、分区号在未知时为 None
+    volume: Option<Option<char>>,
+    partition: Option<Option<u32>>,
+}
+
+impl Focus {
+    fn has_disk(&self) -> bool {
+        // 选中卷时其所在磁盘也随之选中
+        self.disk.is_some() || self.volume.is_some()
+    }
+
+    fn has_partition(&self) -> bool {
+        self.volume.is_some() || self.partition.is_some()
+    }
+
+    fn describe(&self) -> String {
+        match (self.volume, self.partition, self.disk) {
+            (Some(Some(c)), _, _) => format!("卷 {}:", c),
+            (_, Some(Some(n)), Some(disk)) => format!("磁盘 {} 的分区 {}", disk, n),
+            (_, Some(Some(n)), None) => format!("分区 {}", n),
+            (_, Some(None), _) | (Some(None), _, _) => "新建的分区".to_string(),
+            (None, None, Some(disk)) => format!("磁盘 {}", disk),
+            (None, None, None) => "所选对象".to_string(),
+        }
+    }
+
+    /// 检查命令能否在当前选中的对象上执行，并更新选中状态
+    fn apply(&mut self, command: &Command) -> Result<()> {
+        let needs = |ok: bool, what: &str| -> Result<()> {
+            if !ok {
+                anyhow::bail!("命令 \"{}\" 之前需要先选择{}", command.render(), what);
+            }
+            Ok(())
+        };
+
+        match command {
+            Command::Select(Target::Disk(n)) => {
+                *self = Focus {
+                    disk: Some(*n),
+                    ..Default::default()
+                };
+            }
+            Command::Select(Target::Volume(c)) => {
+                *self = Focus {
+                    volume: Some(Some(*c)),
+                    ..Default::default()
+                };
+            }
+            Command::Select(Target::Partition(n)) => {
+                needs(self.has_disk(), "磁盘")?;
+                self.volume = None;
+                self.partition = Some(Some(*n));
+            }
+            Command::Clean | Command::Convert(_) => {
+                needs(self.has_disk(), "磁盘")?;
+                self.volume = None;
+                self.partition = None;
+            }
+            Command::CreatePartition { .. } => {
+                needs(self.has_disk(), "磁盘")?;
+                // 新建的分区自动成为选中对象
+                self.volume = None;
+                self.partition = Some(None);
+            }
+            Command::DeletePartition { .. } => {
+                needs(self.has_partition(), "卷或分区")?;
+                self.volume = None;
+                self.partition = None;
+            }
+            Command::ListPartition => needs(self.has_disk(), "磁盘")?,
+            Command::ListDisk | Command::ListVolume => {}
+            Command::Assign(Some(c)) => {
+                needs(self.has_partition(), "卷或分区")?;
+                if self.partition == Some(None) || self.volume == Some(None) {
+                    self.volume = Some(Some(*c));
+                    self.partition = None;
+                }
+            }
+            _ => needs(self.has_partition(), "卷或分区")?,
+        }
+        Ok(())
+    }
+}
+
+fn check_letter(letter: char) -> Result<()> {
+    if !letter.is_ascii_uppercase() {
+        anyhow::bail!("无效的盘符: {}", letter);
+    }
+    Ok(())
+}
+
+fn check_size(size_mb: Option<u64>) -> Result<()> {
+    if size_mb == Some(0) {
+        anyhow::bail!("分区大小不能为 0");
+    }
+    Ok(())
+}
+
+/// 检查单条命令的参数
+fn check_arguments(command: &Command) -> Result<()> {
+    match command {
+        Command::Select(Target::Volume(c)) | Command::Assign(Some(c)) | Command::Remove(Some(c)) => check_letter(*c),
+        Command::CreatePartition { size_mb, .. } => check_size(*size_mb),
+        Command::Shrink { desired_mb, minimum_mb } => {
+            if desired_mb.is_none() && minimum_mb.is_none() {
+                anyhow::bail!("shrink 需要指定大小");
+            }
+            check_size(*desired_mb)?;
+            check_size(*minimum_mb)
+        }
+        Command::Format {
+            file_system,
+            label: Some(label),
+            ..
+        } => {
+            if label.chars().count() > file_system.max_label_len() {
+                anyhow::bail!("卷标 \"{}\" 过长，{} 最多 {} 个字符", label, file_system.name(), file_system.max_label_len());
+            }
+            if label.chars().any(|c| c == '"' || c.is_control()) {
+                anyhow::bail!("卷标 \"{}\" 含有无效字符", label);
+            }
+            Ok(())
+        }
+        Command::SetId(id) => {
+            let is_mbr_type = id.len() == 2 && id.bytes().all(|b| b.is_ascii_hexdigit());
+            if !is_mbr_type && !is_guid(id) {
+                anyhow::bail!("无效的分区类型: {}", id);
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 执行计划中的一步
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanStep {
+    /// 脚本中的命令
+    pub command: String,
+    /// 面向用户的说明
+    pub description: String,
+    /// 是否会破坏已有数据
+    pub destructive: bool,
+}
+
+/// 执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunMode {
+    #[default]
+    Execute,
+    /// 不执行，只返回执行计划
+    DryRun,
+}
+
+/// 构建中的脚本
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBuilder {
+    commands: Vec<Command>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn command(mut self, command: Command) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    pub fn select_disk(self, number: u32) -> Self {
+        self.command(Command::Select(Target::Disk(number)))
+    }
+
+    pub fn select_volume(self, letter: char) -> Self {
+        self.command(Command::Select(Target::Volume(letter.to_ascii_uppercase())))
+    }
+
+    pub fn select_partition(self, number: u32) -> Self {
+        self.command(Command::Select(Target::Partition(number)))
+    }
+
+    pub fn clean(self) -> Self {
+        self.command(Command::Clean)
+    }
+
+    pub fn convert(self, style: PartitionStyle) -> Self {
+        self.command(Command::Convert(style))
+    }
+
+    pub fn create_partition(self, kind: PartitionType, size_mb: Option<u64>) -> Self {
+        self.command(Command::CreatePartition { kind, size_mb })
+    }
+
+    /// 缩小选中的卷，`desired_mb` 为期望缩小的空间
+    pub fn shrink(self, desired_mb: u64) -> Self {
+        self.command(Command::Shrink {
+            desired_mb: Some(desired_mb),
+            minimum_mb: None,
+        })
+    }
+
+    /// 缩小选中的卷，至少缩小 `minimum_mb`，否则失败
+    pub fn shrink_minimum(self, minimum_mb: u64) -> Self {
+        self.command(Command::Shrink {
+            desired_mb: None,
+            minimum_mb: Some(minimum_mb),
+        })
+    }
+
+    pub fn shrink_querymax(self) -> Self {
+        self.command(Command::ShrinkQueryMax)
+    }
+
+    pub fn extend(self) -> Self {
+        self.command(Command::Extend)
+    }
+
+    /// 快速格式化选中的卷
+    pub fn format(self, file_system: FileSystem, label: Option<&str>) -> Self {
+        self.command(Command::Format {
+            file_system,
+            label: label.map(str::to_string),
+            force: false,
+        })
+    }
+
+    /// 快速格式化选中的卷，必要时强制卸载
+    pub fn format_override(self, file_system: FileSystem, label: Option<&str>) -> Self {
+        self.command(Command::Format {
+            file_system,
+            label: label.map(str::to_string),
+            force: true,
+        })
+    }
+
+    pub fn assign_letter(self, letter: char) -> Self {
+        self.command(Command::Assign(Some(letter.to_ascii_uppercase())))
+    }
+
+    pub fn assign(self) -> Self {
+        self.command(Command::Assign(None))
+    }
+
+    pub fn remove_letter(self, letter: char) -> Self {
+        self.command(Command::Remove(Some(letter.to_ascii_uppercase())))
+    }
+
+    pub fn delete_partition_override(self) -> Self {
+        self.command(Command::DeletePartition { force: true })
+    }
+
+    pub fn active(self) -> Self {
+        self.command(Command::Active)
+    }
+
+    pub fn set_id(self, id: &str) -> Self {
+        self.command(Command::SetId(id.trim_matches(['{', '}']).to_ascii_lowercase()))
+    }
+
+    pub fn gpt_attributes(self, attributes: u64) -> Self {
+        self.command(Command::GptAttributes(attributes))
+    }
+
+    pub fn list_disk(self) -> Self {
+        self.command(Command::ListDisk)
+    }
+
+    pub fn list_volume(self) -> Self {
+        self.command(Command::ListVolume)
+    }
+
+    pub fn list_partition(self) -> Self {
+        self.command(Command::ListPartition)
+    }
+
+    pub fn detail_volume(self) -> Self {
+        self.command(Command::DetailVolume)
+    }
+
+    pub fn detail_partition(self) -> Self {
+        self.command(Command::DetailPartition)
+    }
+
+    /// 检查参数和命令顺序
+    pub fn build(self) -> Result<DiskpartScript> {
+        if self.commands.is_empty() {
+            anyhow::bail!("diskpart 脚本为空");
+        }
+        let mut focus = Focus::default();
+        for command in &self.commands {
+            check_arguments(command)?;
+            focus.apply(command)?;
+        }
+        Ok(DiskpartScript {
+            commands: self.commands,
+        })
+    }
+}
+
+/// 已通过检查的 diskpart 脚本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskpartScript {
+    commands: Vec<Command>,
+}
+
+impl DiskpartScript {
+    pub fn builder() -> ScriptBuilder {
+        ScriptBuilder::new()
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// 脚本文本，供 `diskpart /s` 执行
+    pub fn render(&self) -> String {
+        let mut text = self.commands.iter().map(Command::render).collect::<Vec<_>>().join("\n");
+        text.push('\n');
+        text
+    }
+
+    /// 是否包含会破坏数据的操作
+    pub fn is_destructive(&self) -> bool {
+        self.commands.iter().any(Command::is_destructive)
+    }
+
+    /// 执行计划
+    pub fn plan(&self) -> Vec<PlanStep> {
+        let mut focus = Focus::default();
+        self.commands
+            .iter()
+            .map(|command| {
+                // 选择命令说明选中后的对象，其余命令说明执行前的对象；
+                // build 时已检查过顺序，这里只用于跟踪选中对象
+                let before = focus.clone();
+                let _ = focus.apply(command);
+                let object = if matches!(command, Command::Select(_)) { &focus } else { &before };
+                PlanStep {
+                    command: command.render(),
+                    description: space_before_cjk(&command.describe(object)),
+                    destructive: command.is_destructive(),
+                }
+            })
+            .collect()
+    }
+
+    /// 执行计划的文本形式，破坏性操作前标记 `!`
+    pub fn plan_text(&self) -> String {
+        self.plan()
+            .iter()
+            .map(|step| format!("{} {}  # {}", if step.destructive { "!" } else { " " }, step.command, step.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let script = DiskpartScript::builder()
+            .select_volume('c')
+            .shrink(10240)
+            .create_partition(PartitionType::Primary, None)
+            .format(FileSystem::Ntfs, Some("LetRecovery"))
+            .assign_letter('y')
+            .build()
+            .unwrap();
+        assert_eq!(
+            script.render(),
+            "select volume C\n\
+             shrink desired=10240\n\
+             create partition primary\n\
+             format fs=ntfs quick label=\"LetRecovery\"\n\
+             assign letter=Y\n"
+        );
+        assert!(script.is_destructive());
+
+        let script = DiskpartScript::builder()
+            .select_disk(0)
+            .create_partition(PartitionType::Primary, None)
+            .set_id("{DE94BBA4-06D1-4D40-A16A-BFD50179D6AC}")
+            .gpt_attributes(0x8000000000000001)
+            .build()
+            .unwrap();
+        assert_eq!(
+            script.render(),
+            "select disk 0\n\
+             create partition primary\n\
+             set id=de94bba4-06d1-4d40-a16a-bfd50179d6ac override\n\
+             gpt attributes=0x8000000000000001\n"
+        );
+        assert!(!script.is_destructive());
+    }
+
+    #[test]
+    fn test_validate_arguments() {
+        let build = |builder: ScriptBuilder| builder.build().map(|_| ());
+        assert!(build(ScriptBuilder::new()).is_err());
+        assert!(build(ScriptBuilder::new().select_volume('1')).is_err());
+        assert!(build(ScriptBuilder::new().select_disk(0).create_partition(PartitionType::Efi, Some(0))).is_err());
+        assert!(build(
+            ScriptBuilder::new()
+                .select_volume('C')
+                .format(FileSystem::Fat32, Some("TOO LONG LABEL"))
+        )
+        .is_err());
+        assert!(build(ScriptBuilder::new().select_volume('C').format(FileSystem::Ntfs, Some("a\"b"))).is_err());
+        assert!(build(ScriptBuilder::new().select_disk(0).select_partition(1).set_id("xyz")).is_err());
+        assert!(build(ScriptBuilder::new().select_disk(0).select_partition(1).set_id("27")).is_ok());
+    }
+
+    #[test]
+    fn test_validate_order() {
+        let build = |builder: ScriptBuilder| builder.build().map(|_| ());
+        // 未选择对象
+        assert!(build(ScriptBuilder::new().clean()).is_err());
+        assert!(build(ScriptBuilder::new().format(FileSystem::Ntfs, None)).is_err());
+        assert!(build(ScriptBuilder::new().select_partition(1)).is_err());
+        // 选择磁盘后不能直接格式化
+        assert!(build(ScriptBuilder::new().select_disk(0).format(FileSystem::Ntfs, None)).is_err());
+        // 删除分区后需要重新选择
+        assert!(build(
+            ScriptBuilder::new()
+                .select_volume('D')
+                .delete_partition_override()
+                .format(FileSystem::Ntfs, None)
+        )
+        .is_err());
+        // 新建的分区自动选中
+        assert!(build(
+            ScriptBuilder::new()
+                .select_disk(1)
+                .clean()
+                .create_partition(PartitionType::Primary, None)
+                .format(FileSystem::Ntfs, None)
+                .assign()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_plan() {
+        let script = DiskpartScript::builder()
+            .select_disk(1)
+            .clean()
+            .convert(PartitionStyle::Gpt)
+            .create_partition(PartitionType::Efi, Some(260))
+            .format(FileSystem::Fat32, Some("System"))
+            .create_partition(PartitionType::Primary, None)
+            .format(FileSystem::Ntfs, Some("Windows"))
+            .assign_letter('W')
+            .select_volume('D')
+            .delete_partition_override()
+            .build()
+            .unwrap();
+
+        let destructive: Vec<_> = script
+            .plan()
+            .into_iter()
+            .filter(|s| s.destructive)
+            .map(|s| s.description)
+            .collect();
+        assert_eq!(
+            destructive,
+            [
+                "清空磁盘 1 上的所有分区和数据",
+                "将磁盘 1 转换为 GPT",
+                "将新建的分区格式化为 FAT32（卷标 System），其中数据将被清除",
+                "将新建的分区格式化为 NTFS（卷标 Windows），其中数据将被清除",
+                "删除卷 D: 及其中的所有数据",
+            ]
+        );
+        assert!(script.plan_text().contains("! clean"));
+    }
+}
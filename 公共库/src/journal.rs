@@ -0,0 +1,409 @@
+//! 可恢复的步骤日志
+//!
+//! 安装和备份流程按步骤执行，每个步骤开始、完成或失败时都会立即写入
+//! 日志文件。流程中途断电或崩溃后重新运行时，根据日志从正确的步骤继续，
+//! 而不是从头开始（例如重新格式化一个已经释放好镜像的分区）。
+//!
+//! 日志为 INI 格式，便于在 PE 中直接查看：
+//!
+//! ```ini
+//! [Journal]
+//! Operation=Install
+//! Completed=FormatPartition,ApplyImage
+//! Current=ImportDrivers
+//! LastError=...
+//!
+//! [Values]
+//! TargetPartition=D:
+//! ```
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 可记录到日志中的流程步骤
+pub trait JournalStep: Copy + Eq + Sized + 'static {
+    /// 写入日志的步骤标识，不随界面文字变化
+    fn key(&self) -> &'static str;
+
+    /// 需要记录的全部步骤，按执行顺序排列
+    fn sequence() -> &'static [Self];
+
+    /// 该步骤中途中断后应从哪一步重新开始
+    ///
+    /// 默认重做本步骤；依赖前一步结果的步骤（如释放镜像需要干净的分区）
+    /// 可以返回更早的步骤。
+    fn restart_from(&self) -> Self {
+        *self
+    }
+
+    /// 根据标识查找步骤
+    fn from_key(key: &str) -> Option<Self> {
+        Self::sequence().iter().copied().find(|s| s.key() == key)
+    }
+
+    /// 步骤在执行顺序中的位置
+    fn position(&self) -> usize {
+        Self::sequence()
+            .iter()
+            .position(|s| s == self)
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/// 步骤日志
+#[derive(Debug, Clone)]
+pub struct Journal<S: JournalStep> {
+    path: PathBuf,
+    operation: String,
+    completed: Vec<S>,
+    current: Option<S>,
+    last_error: Option<String>,
+    values: Vec<(String, String)>,
+}
+
+impl<S: JournalStep> Journal<S> {
+    /// 打开日志，不存在时创建新的空日志（首次写入时才落盘）
+    ///
+    /// 已有日志属于其他操作类型时返回错误，避免把备份日志当成安装日志继续。
+    pub fn open(path: impl AsRef<Path>, operation: &str) -> Result<Self> {
+        let path = path.as_ref();
+        match Self::load(path)? {
+            Some(journal) if journal.operation == operation => Ok(journal),
+            Some(journal) => anyhow::bail!(
+                "日志 {} 属于 {} 操作，而不是 {}",
+                path.display(),
+                journal.operation,
+                operation
+            ),
+            None => Ok(Self {
+                path: path.to_path_buf(),
+                operation: operation.to_string(),
+                completed: Vec::new(),
+                current: None,
+                last_error: None,
+                values: Vec::new(),
+            }),
+        }
+    }
+
+    /// 读取已有日志，文件不存在时返回 None
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取日志失败: {}", path.display()))?;
+        Self::parse(path, &content).map(Some)
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Self> {
+        let parse_step = |key: &str| {
+            S::from_key(key).ok_or_else(|| anyhow::anyhow!("日志中有未知步骤: {}", key))
+        };
+
+        let mut journal = Self {
+            path: path.to_path_buf(),
+            operation: String::new(),
+            completed: Vec::new(),
+            current: None,
+            last_error: None,
+            values: Vec::new(),
+        };
+        let mut in_values = false;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_values = line.eq_ignore_ascii_case("[Values]");
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            if in_values {
+                journal.values.push((key.to_string(), value.to_string()));
+                continue;
+            }
+            match key {
+                "Operation" => journal.operation = value.to_string(),
+                "Completed" => {
+                    for step in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        journal.completed.push(parse_step(step)?);
+                    }
+                }
+                "Current" if !value.is_empty() => journal.current = Some(parse_step(value)?),
+                "LastError" if !value.is_empty() => journal.last_error = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        if journal.operation.is_empty() {
+            anyhow::bail!("日志缺少操作类型: {}", path.display());
+        }
+        Ok(journal)
+    }
+
+    fn serialize(&self) -> String {
+        let completed: Vec<_> = self.completed.iter().map(|s| s.key()).collect();
+        let mut text = String::from("[Journal]\r\n");
+        text.push_str(&format!("Operation={}\r\n", self.operation));
+        text.push_str(&format!("Completed={}\r\n", completed.join(",")));
+        text.push_str(&format!(
+            "Current={}\r\n",
+            self.current.map(|s| s.key()).unwrap_or_default()
+        ));
+        if let Some(error) = &self.last_error {
+            text.push_str(&format!("LastError={}\r\n", error));
+        }
+        if !self.values.is_empty() {
+            text.push_str("\r\n[Values]\r\n");
+            for (key, value) in &self.values {
+                text.push_str(&format!("{}={}\r\n", key, value));
+            }
+        }
+        text
+    }
+
+    /// 写入磁盘：先写临时文件并刷盘，再替换正式文件，断电时不会留下半个日志
+    fn flush(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut file = std::fs::File::create(&tmp_path)
+                .with_context(|| format!("创建日志失败: {}", tmp_path.display()))?;
+            file.write_all(self.serialize().as_bytes())?;
+            file.sync_all()?;
+        }
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("写入日志失败: {}", self.path.display()))
+    }
+
+    /// 日志文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 操作类型
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// 是否已有执行记录（即这是一次中断后的重新运行）
+    pub fn has_progress(&self) -> bool {
+        !self.completed.is_empty() || self.current.is_some()
+    }
+
+    /// 中断或失败时正在执行的步骤
+    pub fn interrupted_step(&self) -> Option<S> {
+        self.current
+    }
+
+    /// 上次失败的错误信息
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// 步骤是否已完成
+    pub fn is_completed(&self, step: S) -> bool {
+        self.completed.contains(&step)
+    }
+
+    /// 步骤是否已经开始执行过（已完成，或中断、失败于该步骤）
+    pub fn has_started(&self, step: S) -> bool {
+        self.current == Some(step) || self.is_completed(step)
+    }
+
+    /// 下一个要执行的步骤，全部完成时返回 None
+    pub fn next_step(&self) -> Option<S> {
+        if let Some(current) = self.current {
+            return Some(current.restart_from());
+        }
+        S::sequence()
+            .iter()
+            .copied()
+            .find(|s| !self.completed.contains(s))
+    }
+
+    /// 从下一个步骤开始、尚需执行的全部步骤
+    pub fn remaining_steps(&self) -> Vec<S> {
+        match self.next_step() {
+            Some(next) => S::sequence()[next.position()..].to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// 记录步骤开始
+    ///
+    /// 重新执行某一步时，它之后的步骤都视为未完成。
+    pub fn begin(&mut self, step: S) -> Result<()> {
+        let position = step.position();
+        self.completed.retain(|s| s.position() < position);
+        self.current = Some(step);
+        self.last_error = None;
+        self.flush()
+    }
+
+    /// 记录步骤完成
+    pub fn complete(&mut self, step: S) -> Result<()> {
+        if !self.completed.contains(&step) {
+            self.completed.push(step);
+        }
+        if self.current == Some(step) {
+            self.current = None;
+        }
+        self.flush()
+    }
+
+    /// 记录步骤失败，下次运行时从该步骤的重启点继续
+    pub fn fail(&mut self, step: S, error: &str) -> Result<()> {
+        self.current = Some(step);
+        self.last_error = Some(error.replace(['\r', '\n'], " "));
+        self.flush()
+    }
+
+    /// 读取附加值
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 写入附加值（如解析出的目标分区、引导配置备份路径）
+    pub fn set_value(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = value.replace(['\r', '\n'], " ");
+        match self.values.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.values.push((key.to_string(), value)),
+        }
+        self.flush()
+    }
+
+    /// 删除日志文件（流程完成或放弃时调用）
+    pub fn remove(self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("删除日志失败: {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 只读取日志的操作类型，不解析步骤
+pub fn read_operation(path: impl AsRef<Path>) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    content.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once('=')?;
+        (key.trim() == "Operation").then(|| value.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Step {
+        Format,
+        Apply,
+        Boot,
+    }
+
+    impl JournalStep for Step {
+        fn key(&self) -> &'static str {
+            match self {
+                Step::Format => "Format",
+                Step::Apply => "Apply",
+                Step::Boot => "Boot",
+            }
+        }
+
+        fn sequence() -> &'static [Self] {
+            &[Step::Format, Step::Apply, Step::Boot]
+        }
+
+        fn restart_from(&self) -> Self {
+            match self {
+                Step::Apply => Step::Format,
+                other => *other,
+            }
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lr_journal_{}_{}.ini", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_resume_after_interruption() {
+        let path = temp_path("resume");
+        let mut journal = Journal::<Step>::open(&path, "Install").unwrap();
+        assert!(!journal.has_progress());
+        assert_eq!(journal.remaining_steps(), [Step::Format, Step::Apply, Step::Boot]);
+
+        journal.begin(Step::Format).unwrap();
+        journal.complete(Step::Format).unwrap();
+        journal.set_value("TargetPartition", "D:").unwrap();
+        journal.begin(Step::Apply).unwrap();
+
+        // 模拟断电：重新读取日志
+        let journal = Journal::<Step>::open(&path, "Install").unwrap();
+        assert!(journal.has_progress());
+        assert_eq!(journal.interrupted_step(), Some(Step::Apply));
+        assert_eq!(journal.value("TargetPartition"), Some("D:"));
+        assert_eq!(journal.next_step(), Some(Step::Format));
+        assert!(journal.has_started(Step::Format) && journal.has_started(Step::Apply));
+        assert!(!journal.has_started(Step::Boot));
+
+        journal.remove().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_failure_and_completion() {
+        let path = temp_path("fail");
+        let mut journal = Journal::<Step>::open(&path, "Install").unwrap();
+        for step in [Step::Format, Step::Apply] {
+            journal.begin(step).unwrap();
+            journal.complete(step).unwrap();
+        }
+        journal.begin(Step::Boot).unwrap();
+        journal.fail(Step::Boot, "bcdboot 失败\n退出码 1").unwrap();
+
+        let mut journal = Journal::<Step>::load(&path).unwrap().unwrap();
+        assert_eq!(journal.last_error(), Some("bcdboot 失败 退出码 1"));
+        assert_eq!(journal.remaining_steps(), [Step::Boot]);
+
+        journal.begin(Step::Boot).unwrap();
+        journal.complete(Step::Boot).unwrap();
+        assert_eq!(journal.next_step(), None);
+        assert!(journal.remaining_steps().is_empty());
+        assert_eq!(read_operation(&path).as_deref(), Some("Install"));
+        assert!(Journal::<Step>::open(&path, "Backup").is_err());
+
+        journal.remove().unwrap();
+    }
+
+    #[test]
+    fn test_rerun_invalidates_later_steps() {
+        let path = temp_path("rerun");
+        let mut journal = Journal::<Step>::open(&path, "Install").unwrap();
+        for step in [Step::Format, Step::Apply, Step::Boot] {
+            journal.begin(step).unwrap();
+            journal.complete(step).unwrap();
+        }
+        journal.begin(Step::Apply).unwrap();
+        assert!(journal.is_completed(Step::Format));
+        assert!(!journal.is_completed(Step::Boot));
+
+        assert!(Journal::<Step>::parse(&path, "[Journal]\nOperation=Install\nCompleted=Unknown\n").is_err());
+        journal.remove().unwrap();
+    }
+}
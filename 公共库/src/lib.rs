@@ -0,0 +1,25 @@
+//! LetRecovery 公共库
+//!
+//! 存放正常系统端与PE端共用、且不依赖 Windows API 的核心逻辑，
+//! 可以在任意平台上编译和测试。
+
+pub mod backup;
+pub mod bcd;
+pub mod cli;
+pub mod command;
+pub mod disk_layout;
+pub mod diskpart;
+pub mod hash;
+pub mod journal;
+pub mod partition_table;
+pub mod preflight;
+pub mod regf;
+pub mod software;
+pub mod tools;
+pub mod unattend;
+pub mod wim;
+pub mod win11;
+pub mod workflow;
+
+/// 部署脚本目录名称（位于目标系统分区根目录）
+pub const SCRIPTS_DIR: &str = "LetRecovery_Scripts";
@@ -0,0 +1,823 @@
+//! 分区表读取
+//!
+//! 从任意 `Read + Seek` 数据源（磁盘镜像文件、`\\.\PhysicalDriveN`）读取 MBR 和 GPT 分区表，
+//! 不依赖 diskpart 或 Windows API。GPT 会校验头部和分区项数组的 CRC32，
+//! 主分区表损坏时改用磁盘末尾的备份分区表。
+//!
+//! 读取总是以 4096 字节对齐的整块进行，以满足物理磁盘设备的扇区对齐要求。
+
+use anyhow::{Context, Result};
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::bcd::{Guid, PartitionId};
+
+/// GPT 分区类型
+pub mod gpt_types {
+    use crate::bcd::Guid;
+
+    /// EFI 系统分区
+    pub const ESP: Guid =
+        Guid::from_fields(0xc12a7328, 0xf81f, 0x11d2, [0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9, 0x3b]);
+    /// Microsoft 保留分区（MSR）
+    pub const MSR: Guid =
+        Guid::from_fields(0xe3c9e316, 0x0b5c, 0x4db8, [0x81, 0x7d, 0xf9, 0x2d, 0xf0, 0x02, 0x15, 0xae]);
+    /// 基本数据分区
+    pub const BASIC_DATA: Guid =
+        Guid::from_fields(0xebd0a0a2, 0xb9e5, 0x4433, [0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7]);
+    /// Windows 恢复环境分区（WinRE）
+    pub const WINRE: Guid =
+        Guid::from_fields(0xde94bba4, 0x06d1, 0x4d40, [0xa1, 0x6a, 0xbf, 0xd5, 0x01, 0x79, 0xd6, 0xac]);
+}
+
+/// GPT 分区属性
+pub mod gpt_attributes {
+    /// 平台必需
+    pub const PLATFORM_REQUIRED: u64 = 1 << 0;
+    /// 只读
+    pub const READ_ONLY: u64 = 1 << 60;
+    /// 卷影副本
+    pub const SHADOW_COPY: u64 = 1 << 61;
+    /// 隐藏
+    pub const HIDDEN: u64 = 1 << 62;
+    /// 不自动分配盘符
+    pub const NO_DRIVE_LETTER: u64 = 1 << 63;
+}
+
+/// MBR 分区类型
+pub mod mbr_types {
+    /// NTFS / exFAT
+    pub const NTFS: u8 = 0x07;
+    /// FAT32（CHS）
+    pub const FAT32: u8 = 0x0B;
+    /// FAT32（LBA）
+    pub const FAT32_LBA: u8 = 0x0C;
+    /// 扩展分区（CHS）
+    pub const EXTENDED: u8 = 0x05;
+    /// 扩展分区（LBA）
+    pub const EXTENDED_LBA: u8 = 0x0F;
+    /// Windows 恢复环境（隐藏 NTFS）
+    pub const WINRE: u8 = 0x27;
+    /// GPT 保护分区
+    pub const PROTECTIVE: u8 = 0xEE;
+    /// EFI 系统分区
+    pub const ESP: u8 = 0xEF;
+}
+
+/// 一次读取的对齐块大小，同时也是支持的最大扇区大小
+const BLOCK: u64 = 4096;
+/// 支持的扇区大小
+const SECTOR_SIZES: [u64; 2] = [512, 4096];
+/// GPT 头部签名
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// GPT 分区项数量上限，防止损坏的头部导致读取过量数据
+const MAX_GPT_ENTRIES: u32 = 1024;
+/// 扩展分区链长度上限，防止循环链表
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+/// CRC32（IEEE 802.3），GPT 校验使用
+pub fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+    !data
+        .iter()
+        .fold(!0u32, |crc, &b| TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// 分区用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionRole {
+    /// EFI 系统分区
+    Esp,
+    /// Microsoft 保留分区
+    Msr,
+    /// 基本数据分区（普通 NTFS/FAT 分区）
+    BasicData,
+    /// Windows 恢复环境分区
+    Recovery,
+    /// MBR 扩展分区容器
+    Extended,
+    Other,
+}
+
+/// 分区表特有的信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionKind {
+    Gpt {
+        type_guid: Guid,
+        unique_guid: Guid,
+        attributes: u64,
+        name: String,
+    },
+    Mbr {
+        partition_type: u8,
+        bootable: bool,
+        /// 是否为扩展分区中的逻辑分区
+        logical: bool,
+    },
+}
+
+/// 分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    /// 分区号，从 1 开始，与 diskpart `select partition` 的编号一致；
+    /// MBR 扩展分区本身不占编号，与 diskpart 一样记为 0
+    pub number: u32,
+    /// 起始字节偏移
+    pub offset: u64,
+    /// 字节大小
+    pub size: u64,
+    pub kind: PartitionKind,
+}
+
+impl Partition {
+    pub fn role(&self) -> PartitionRole {
+        match &self.kind {
+            PartitionKind::Gpt { type_guid, .. } => match *type_guid {
+                gpt_types::ESP => PartitionRole::Esp,
+                gpt_types::MSR => PartitionRole::Msr,
+                gpt_types::BASIC_DATA => PartitionRole::BasicData,
+                gpt_types::WINRE => PartitionRole::Recovery,
+                _ => PartitionRole::Other,
+            },
+            PartitionKind::Mbr { partition_type, .. } => match *partition_type {
+                mbr_types::ESP => PartitionRole::Esp,
+                mbr_types::NTFS | mbr_types::FAT32 | mbr_types::FAT32_LBA => PartitionRole::BasicData,
+                mbr_types::WINRE => PartitionRole::Recovery,
+                mbr_types::EXTENDED | mbr_types::EXTENDED_LBA => PartitionRole::Extended,
+                _ => PartitionRole::Other,
+            },
+        }
+    }
+
+    pub fn is_esp(&self) -> bool {
+        self.role() == PartitionRole::Esp
+    }
+
+    /// GPT 分区名称，MBR 分区没有名称
+    pub fn name(&self) -> Option<&str> {
+        match &self.kind {
+            PartitionKind::Gpt { name, .. } => Some(name),
+            PartitionKind::Mbr { .. } => None,
+        }
+    }
+}
+
+/// 分区表类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableStyle {
+    Mbr {
+        disk_signature: u32,
+    },
+    Gpt {
+        disk_guid: Guid,
+        /// 主分区表损坏，读取的是备份分区表
+        used_backup: bool,
+    },
+}
+
+/// 分区表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionTable {
+    pub style: TableStyle,
+    pub sector_size: u64,
+    pub partitions: Vec<Partition>,
+}
+
+impl PartitionTable {
+    /// 从磁盘或磁盘镜像读取分区表
+    pub fn read<R: Read + Seek>(source: &mut R) -> Result<Self> {
+        let head = read_at(source, 0, 2 * BLOCK)?;
+        let mbr_valid = head.len() >= 512 && head[510..512] == [0x55, 0xAA];
+        let protective = mbr_valid && mbr_entries(&head).any(|e| e.partition_type == mbr_types::PROTECTIVE);
+        let has_gpt_header = SECTOR_SIZES
+            .iter()
+            .any(|&ss| head.get(ss as usize..ss as usize + 8) == Some(GPT_SIGNATURE));
+
+        if protective || (!mbr_valid && has_gpt_header) {
+            read_gpt(source, &head)
+        } else if mbr_valid {
+            read_mbr(source, &head)
+        } else {
+            anyhow::bail!("未找到有效的分区表")
+        }
+    }
+
+    pub fn is_gpt(&self) -> bool {
+        matches!(self.style, TableStyle::Gpt { .. })
+    }
+
+    /// 第一个 EFI 系统分区
+    pub fn esp(&self) -> Option<&Partition> {
+        self.partitions.iter().find(|p| p.is_esp())
+    }
+
+    /// 分区号对应的分区
+    pub fn partition(&self, number: u32) -> Option<&Partition> {
+        self.partitions.iter().find(|p| p.number == number)
+    }
+
+    /// 分区标识，可与 BCD 中的 partition 设备比较
+    pub fn partition_id(&self, partition: &Partition) -> PartitionId {
+        match (&self.style, &partition.kind) {
+            (TableStyle::Gpt { disk_guid, .. }, PartitionKind::Gpt { unique_guid, .. }) => PartitionId::Gpt {
+                disk_id: *disk_guid,
+                partition_id: *unique_guid,
+            },
+            (TableStyle::Mbr { disk_signature }, _) => PartitionId::Mbr {
+                disk_signature: *disk_signature,
+                offset: partition.offset,
+            },
+            // GPT 表中只会出现 GPT 分区项
+            (TableStyle::Gpt { .. }, PartitionKind::Mbr { .. }) => PartitionId::Mbr {
+                disk_signature: 0,
+                offset: partition.offset,
+            },
+        }
+    }
+}
+
+/// 读取 FAT32 分区的剩余空间（字节），取自 FSInfo 扇区中记录的空闲簇数
+///
+/// 分区不是 FAT32 时返回错误；FSInfo 中没有记录空闲簇数时返回 None。
+pub fn fat32_free_bytes<R: Read + Seek>(source: &mut R, partition: &Partition) -> Result<Option<u64>> {
+    let boot = read_at(source, partition.offset, 512)?;
+    if boot.len() < 512 || boot[510..512] != [0x55, 0xAA] || &boot[82..90] != b"FAT32   " {
+        anyhow::bail!("分区 {} 不是 FAT32 文件系统", partition.number);
+    }
+
+    let bytes_per_sector = u16::from_le_bytes([boot[11], boot[12]]) as u64;
+    let sectors_per_cluster = boot[13] as u64;
+    let fsinfo_sector = u16::from_le_bytes([boot[48], boot[49]]) as u64;
+    if bytes_per_sector == 0 || sectors_per_cluster == 0 || fsinfo_sector == 0 {
+        anyhow::bail!("分区 {} 的 FAT32 引导扇区无效", partition.number);
+    }
+
+    let fsinfo_offset = fsinfo_sector
+        .checked_mul(bytes_per_sector)
+        .and_then(|o| o.checked_add(partition.offset))
+        .context("FSInfo 扇区位置溢出")?;
+    let fsinfo = read_at(source, fsinfo_offset, 512)?;
+    let le32 = |at: usize| u32::from_le_bytes(fsinfo[at..at + 4].try_into().unwrap());
+    if fsinfo.len() < 512 || le32(0) != 0x4161_5252 || le32(484) != 0x6141_7272 {
+        anyhow::bail!("分区 {} 的 FSInfo 扇区无效", partition.number);
+    }
+
+    let free_clusters = le32(488);
+    if free_clusters == u32::MAX {
+        return Ok(None);
+    }
+    Ok(Some(free_clusters as u64 * sectors_per_cluster * bytes_per_sector))
+}
+
+/// 从 `offset` 开始读取最多 `len` 字节，按 [`BLOCK`] 对齐读取，到达末尾时返回较短的数据
+fn read_at<R: Read + Seek>(source: &mut R, offset: u64, len: u64) -> Result<Vec<u8>> {
+    let start = offset / BLOCK * BLOCK;
+    let end = offset
+        .checked_add(len)
+        .and_then(|end| end.div_ceil(BLOCK).checked_mul(BLOCK))
+        .context("读取位置超出范围")?;
+    source.seek(SeekFrom::Start(start))?;
+
+    let mut data = vec![0u8; (end - start) as usize];
+    let mut filled = 0;
+    while filled < data.len() {
+        match source.read(&mut data[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e).context("读取磁盘失败"),
+        }
+    }
+    data.truncate(filled);
+
+    let skip = (offset - start) as usize;
+    if data.len() <= skip {
+        return Ok(Vec::new());
+    }
+    let mut data = data.split_off(skip);
+    data.truncate(len as usize);
+    Ok(data)
+}
+
+/// MBR 分区项
+struct MbrEntry {
+    bootable: bool,
+    partition_type: u8,
+    start_lba: u32,
+    sector_count: u32,
+}
+
+/// 扇区中的 4 个 MBR 分区项（包括空项）
+fn mbr_entries(sector: &[u8]) -> impl Iterator<Item = MbrEntry> + '_ {
+    (0..4).map(move |i| {
+        let e = &sector[446 + i * 16..446 + (i + 1) * 16];
+        MbrEntry {
+            bootable: e[0] == 0x80,
+            partition_type: e[4],
+            start_lba: u32::from_le_bytes(e[8..12].try_into().unwrap()),
+            sector_count: u32::from_le_bytes(e[12..16].try_into().unwrap()),
+        }
+    })
+}
+
+fn is_extended(partition_type: u8) -> bool {
+    matches!(partition_type, mbr_types::EXTENDED | mbr_types::EXTENDED_LBA)
+}
+
+fn read_mbr<R: Read + Seek>(source: &mut R, head: &[u8]) -> Result<PartitionTable> {
+    // MBR 磁盘的地址总是以 512 字节扇区计
+    const SECTOR: u64 = 512;
+
+    let disk_signature = u32::from_le_bytes(head[440..444].try_into().unwrap());
+    let mut partitions = Vec::new();
+    let mut extended = None;
+    // 扩展分区不占编号，与 diskpart 一致
+    let mut next_number = 1;
+
+    for entry in mbr_entries(head).filter(|e| e.partition_type != 0 && e.sector_count != 0) {
+        let number = if is_extended(entry.partition_type) {
+            extended.get_or_insert(entry.start_lba as u64);
+            0
+        } else {
+            let number = next_number;
+            next_number += 1;
+            number
+        };
+        partitions.push(Partition {
+            number,
+            offset: entry.start_lba as u64 * SECTOR,
+            size: entry.sector_count as u64 * SECTOR,
+            kind: PartitionKind::Mbr {
+                partition_type: entry.partition_type,
+                bootable: entry.bootable,
+                logical: false,
+            },
+        });
+    }
+
+    // 逻辑分区：扩展分区中的 EBR 链表，逻辑分区的起始位置相对于当前 EBR，
+    // 下一个 EBR 的位置相对于扩展分区起始
+    if let Some(extended_start) = extended {
+        let mut ebr = extended_start;
+        for _ in 0..MAX_LOGICAL_PARTITIONS {
+            let sector = read_at(source, ebr * SECTOR, SECTOR)?;
+            if sector.len() < 512 || sector[510..512] != [0x55, 0xAA] {
+                break;
+            }
+            let mut entries = mbr_entries(&sector);
+            let (Some(logical), Some(next)) = (entries.next(), entries.next()) else {
+                break;
+            };
+            if logical.partition_type != 0 && logical.sector_count != 0 {
+                partitions.push(Partition {
+                    number: next_number,
+                    offset: (ebr + logical.start_lba as u64) * SECTOR,
+                    size: logical.sector_count as u64 * SECTOR,
+                    kind: PartitionKind::Mbr {
+                        partition_type: logical.partition_type,
+                        bootable: logical.bootable,
+                        logical: true,
+                    },
+                });
+                next_number += 1;
+            }
+            if !is_extended(next.partition_type) || next.start_lba == 0 {
+                break;
+            }
+            ebr = extended_start + next.start_lba as u64;
+        }
+    }
+
+    Ok(PartitionTable {
+        style: TableStyle::Mbr { disk_signature },
+        sector_size: SECTOR,
+        partitions,
+    })
+}
+
+/// GPT 头部
+struct GptHeader {
+    disk_guid: Guid,
+    alternate_lba: u64,
+    entries_lba: u64,
+    entry_count: u32,
+    entry_size: u32,
+    entries_crc: u32,
+}
+
+/// 解析并校验 GPT 头部
+fn parse_gpt_header(sector: &[u8], lba: u64) -> Result<GptHeader> {
+    if sector.get(..8) != Some(GPT_SIGNATURE) {
+        anyhow::bail!("GPT 头部签名无效");
+    }
+    let u32_at = |o: usize| u32::from_le_bytes(sector[o..o + 4].try_into().unwrap());
+    let u64_at = |o: usize| u64::from_le_bytes(sector[o..o + 8].try_into().unwrap());
+
+    let header_size = u32_at(12) as usize;
+    if !(92..=sector.len()).contains(&header_size) {
+        anyhow::bail!("GPT 头部长度无效: {}", header_size);
+    }
+    let mut header = sector[..header_size].to_vec();
+    header[16..20].fill(0);
+    if crc32(&header) != u32_at(16) {
+        anyhow::bail!("GPT 头部 CRC 校验失败");
+    }
+    if u64_at(24) != lba {
+        anyhow::bail!("GPT 头部位置不符");
+    }
+
+    let entry_count = u32_at(80);
+    let entry_size = u32_at(84);
+    // 分区项不会跨扇区，更大的值只可能来自损坏的头部
+    if entry_size < 128
+        || entry_size as usize > sector.len()
+        || !entry_size.is_multiple_of(8)
+        || entry_count > MAX_GPT_ENTRIES
+    {
+        anyhow::bail!("GPT 分区项格式无效");
+    }
+    Ok(GptHeader {
+        disk_guid: Guid::from_bytes_le(&sector[56..72]).unwrap(),
+        alternate_lba: u64_at(32),
+        entries_lba: u64_at(72),
+        entry_count,
+        entry_size,
+        entries_crc: u32_at(88),
+    })
+}
+
+/// 读取指定位置的 GPT 头部及其分区项数组，均需通过校验
+fn read_gpt_at<R: Read + Seek>(source: &mut R, lba: u64, sector_size: u64) -> Result<(GptHeader, Vec<u8>)> {
+    let offset = lba.checked_mul(sector_size).context("GPT 头部位置超出范围")?;
+    let sector = read_at(source, offset, sector_size)?;
+    let header = parse_gpt_header(&sector, lba)?;
+    let len = header.entry_count as u64 * header.entry_size as u64;
+    let entries_offset = header
+        .entries_lba
+        .checked_mul(sector_size)
+        .context("GPT 分区项位置超出范围")?;
+    let entries = read_at(source, entries_offset, len)?;
+    if entries.len() as u64 != len || crc32(&entries) != header.entries_crc {
+        anyhow::bail!("GPT 分区项 CRC 校验失败");
+    }
+    Ok((header, entries))
+}
+
+fn read_gpt<R: Read + Seek>(source: &mut R, head: &[u8]) -> Result<PartitionTable> {
+    let sector_size = SECTOR_SIZES
+        .into_iter()
+        .find(|&ss| head.get(ss as usize..ss as usize + 8) == Some(GPT_SIGNATURE))
+        .unwrap_or(512);
+
+    let (header, entries, used_backup) = match read_gpt_at(source, 1, sector_size) {
+        Ok((header, entries)) => (header, entries, false),
+        Err(primary_error) => {
+            let backup_lba = backup_header_lba(source, head, sector_size)?;
+            let (header, entries) = read_gpt_at(source, backup_lba, sector_size)
+                .with_context(|| format!("主 GPT 无效（{}），备份 GPT 也无效", primary_error))?;
+            (header, entries, true)
+        }
+    };
+
+    let partitions = entries
+        .chunks_exact(header.entry_size as usize)
+        .filter_map(|e| {
+            let type_guid = Guid::from_bytes_le(&e[0..16])?;
+            if type_guid.is_nil() {
+                return None;
+            }
+            let first_lba = u64::from_le_bytes(e[32..40].try_into().ok()?);
+            let last_lba = u64::from_le_bytes(e[40..48].try_into().ok()?);
+            let name: Vec<u16> = e[56..128]
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0)
+                .collect();
+            Some((first_lba, last_lba, type_guid, e, name))
+        })
+        .enumerate()
+        .map(|(i, (first_lba, last_lba, type_guid, e, name))| {
+            let offset = first_lba.checked_mul(sector_size);
+            let size = last_lba
+                .checked_add(1)
+                .map(|end| end.saturating_sub(first_lba))
+                .and_then(|sectors| sectors.checked_mul(sector_size));
+            let (Some(offset), Some(size)) = (offset, size) else {
+                anyhow::bail!("GPT 分区项 {} 的位置超出范围", i + 1);
+            };
+            Ok(Partition {
+                number: i as u32 + 1,
+                offset,
+                size,
+                kind: PartitionKind::Gpt {
+                    type_guid,
+                    unique_guid: Guid::from_bytes_le(&e[16..32]).unwrap_or_default(),
+                    attributes: u64::from_le_bytes(e[48..56].try_into().unwrap()),
+                    name: String::from_utf16_lossy(&name),
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(PartitionTable {
+        style: TableStyle::Gpt {
+            disk_guid: header.disk_guid,
+            used_backup,
+        },
+        sector_size,
+        partitions,
+    })
+}
+
+/// 备份 GPT 头部位于磁盘最后一个扇区
+///
+/// 优先使用主头部记录的位置（头部完好而分区项损坏时），
+/// 其次使用保护性 MBR 覆盖的范围，最后按数据源长度计算。
+fn backup_header_lba<R: Read + Seek>(source: &mut R, head: &[u8], sector_size: u64) -> Result<u64> {
+    let primary = read_at(source, sector_size, sector_size)?;
+    if let Ok(header) = parse_gpt_header(&primary, 1) {
+        return Ok(header.alternate_lba);
+    }
+    if head.len() >= 512 && head[510..512] == [0x55, 0xAA] {
+        if let Some(entry) = mbr_entries(head)
+            .find(|e| e.partition_type == mbr_types::PROTECTIVE && e.sector_count != u32::MAX)
+        {
+            return Ok(entry.start_lba as u64 + entry.sector_count as u64 - 1);
+        }
+    }
+    let len = source.seek(SeekFrom::End(0))?;
+    if len < 2 * sector_size {
+        anyhow::bail!("无法确定磁盘大小");
+    }
+    Ok(len / sector_size - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const DISK_GUID: Guid = Guid::from_fields(0x11111111, 0x2222, 0x3333, [0x44; 8]);
+
+    /// 测试用 GPT 分区：(类型, 起始 LBA, 结束 LBA, 属性, 名称)
+    type GptSpec<'a> = (Guid, u64, u64, u64, &'a str);
+
+    fn unique(i: usize) -> Guid {
+        Guid::from_fields(0xaaaa0000 + i as u32, 0xbbbb, 0xcccc, [i as u8; 8])
+    }
+
+    /// 构造 GPT 磁盘镜像，共 `sectors` 个扇区，包含保护性 MBR 和主、备份分区表
+    fn gpt_image(sector_size: u64, sectors: u64, specs: &[GptSpec]) -> Vec<u8> {
+        let ss = sector_size as usize;
+        let mut image = vec![0u8; ss * sectors as usize];
+
+        // 保护性 MBR
+        image[446 + 4] = mbr_types::PROTECTIVE;
+        image[446 + 8..446 + 12].copy_from_slice(&1u32.to_le_bytes());
+        image[446 + 12..446 + 16].copy_from_slice(&((sectors - 1) as u32).to_le_bytes());
+        image[510] = 0x55;
+        image[511] = 0xAA;
+
+        let mut entries = vec![0u8; 128 * 128];
+        for (i, (kind, first, last, attributes, name)) in specs.iter().enumerate() {
+            let e = &mut entries[i * 128..(i + 1) * 128];
+            e[0..16].copy_from_slice(&kind.to_bytes_le());
+            e[16..32].copy_from_slice(&unique(i).to_bytes_le());
+            e[32..40].copy_from_slice(&first.to_le_bytes());
+            e[40..48].copy_from_slice(&last.to_le_bytes());
+            e[48..56].copy_from_slice(&attributes.to_le_bytes());
+            for (j, c) in name.encode_utf16().enumerate() {
+                e[56 + j * 2..58 + j * 2].copy_from_slice(&c.to_le_bytes());
+            }
+        }
+        let entry_sectors = (entries.len() / ss) as u64;
+        let last = sectors - 1;
+
+        let mut write_header = |lba: u64, alternate: u64, entries_lba: u64| {
+            let mut h = vec![0u8; 92];
+            h[0..8].copy_from_slice(GPT_SIGNATURE);
+            h[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+            h[12..16].copy_from_slice(&92u32.to_le_bytes());
+            h[24..32].copy_from_slice(&lba.to_le_bytes());
+            h[32..40].copy_from_slice(&alternate.to_le_bytes());
+            h[40..48].copy_from_slice(&(2 + entry_sectors).to_le_bytes());
+            h[48..56].copy_from_slice(&(last - 1 - entry_sectors).to_le_bytes());
+            h[56..72].copy_from_slice(&DISK_GUID.to_bytes_le());
+            h[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+            h[80..84].copy_from_slice(&128u32.to_le_bytes());
+            h[84..88].copy_from_slice(&128u32.to_le_bytes());
+            h[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+            let crc = crc32(&h);
+            h[16..20].copy_from_slice(&crc.to_le_bytes());
+            let at = lba as usize * ss;
+            image[at..at + 92].copy_from_slice(&h);
+            let at = entries_lba as usize * ss;
+            image[at..at + entries.len()].copy_from_slice(&entries);
+        };
+        write_header(1, last, 2);
+        write_header(last, 1, last - entry_sectors);
+        image
+    }
+
+    fn windows_layout() -> Vec<GptSpec<'static>> {
+        vec![
+            (gpt_types::ESP, 40, 239, gpt_attributes::PLATFORM_REQUIRED, "EFI system partition"),
+            (gpt_types::MSR, 240, 271, 0, "Microsoft reserved partition"),
+            (gpt_types::BASIC_DATA, 272, 1799, 0, "Basic data partition"),
+            (gpt_types::WINRE, 1800, 1999, gpt_attributes::NO_DRIVE_LETTER | gpt_attributes::PLATFORM_REQUIRED, ""),
+        ]
+    }
+
+    fn assert_windows_layout(table: &PartitionTable, sector_size: u64) {
+        assert_eq!(table.sector_size, sector_size);
+        let roles: Vec<_> = table.partitions.iter().map(|p| (p.number, p.role())).collect();
+        assert_eq!(
+            roles,
+            [
+                (1, PartitionRole::Esp),
+                (2, PartitionRole::Msr),
+                (3, PartitionRole::BasicData),
+                (4, PartitionRole::Recovery),
+            ]
+        );
+        let esp = table.esp().unwrap();
+        assert_eq!((esp.offset, esp.size), (40 * sector_size, 200 * sector_size));
+        assert_eq!(esp.name(), Some("EFI system partition"));
+        assert_eq!(
+            table.partition_id(table.partition(3).unwrap()),
+            PartitionId::Gpt {
+                disk_id: DISK_GUID,
+                partition_id: unique(2),
+            }
+        );
+        assert!(matches!(
+            table.partition(4).unwrap().kind,
+            PartitionKind::Gpt { attributes, .. } if attributes & gpt_attributes::NO_DRIVE_LETTER != 0
+        ));
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_read_gpt() {
+        let image = gpt_image(512, 2048, &windows_layout());
+        let table = PartitionTable::read(&mut Cursor::new(image)).unwrap();
+        assert_eq!(
+            table.style,
+            TableStyle::Gpt {
+                disk_guid: DISK_GUID,
+                used_backup: false
+            }
+        );
+        assert_windows_layout(&table, 512);
+    }
+
+    #[test]
+    fn test_read_gpt_4k_sectors() {
+        let image = gpt_image(4096, 2048, &windows_layout());
+        let table = PartitionTable::read(&mut Cursor::new(image)).unwrap();
+        assert_windows_layout(&table, 4096);
+    }
+
+    #[test]
+    fn test_gpt_backup() {
+        // 主头部损坏
+        let mut image = gpt_image(512, 2048, &windows_layout());
+        image[512 + 60] ^= 0xFF;
+        let table = PartitionTable::read(&mut Cursor::new(image)).unwrap();
+        assert!(matches!(table.style, TableStyle::Gpt { used_backup: true, .. }));
+        assert_windows_layout(&table, 512);
+
+        // 主分区项损坏
+        let mut image = gpt_image(512, 2048, &windows_layout());
+        image[2 * 512 + 100] ^= 0xFF;
+        let table = PartitionTable::read(&mut Cursor::new(image)).unwrap();
+        assert!(matches!(table.style, TableStyle::Gpt { used_backup: true, .. }));
+
+        // 主、备份均损坏
+        let mut image = gpt_image(512, 2048, &windows_layout());
+        image[512 + 60] ^= 0xFF;
+        image[2047 * 512 + 60] ^= 0xFF;
+        assert!(PartitionTable::read(&mut Cursor::new(image)).is_err());
+    }
+
+    #[test]
+    fn test_read_mbr() {
+        let mut image = vec![0u8; 512 * 4096];
+        image[440..444].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+        let mut entry = |sector: usize, slot: usize, boot: bool, kind: u8, start: u32, count: u32| {
+            let e = sector * 512 + 446 + slot * 16;
+            image[e] = if boot { 0x80 } else { 0 };
+            image[e + 4] = kind;
+            image[e + 8..e + 12].copy_from_slice(&start.to_le_bytes());
+            image[e + 12..e + 16].copy_from_slice(&count.to_le_bytes());
+            image[sector * 512 + 510] = 0x55;
+            image[sector * 512 + 511] = 0xAA;
+        };
+        entry(0, 0, true, mbr_types::NTFS, 2048, 1024);
+        entry(0, 1, false, mbr_types::EXTENDED_LBA, 3072, 1024);
+        // 两个逻辑分区
+        entry(3072, 0, false, mbr_types::NTFS, 64, 256);
+        entry(3072, 1, false, mbr_types::EXTENDED, 512, 512);
+        entry(3584, 0, false, mbr_types::FAT32_LBA, 64, 128);
+
+        let table = PartitionTable::read(&mut Cursor::new(image)).unwrap();
+        assert_eq!(table.style, TableStyle::Mbr { disk_signature: 0x1234_5678 });
+        let summary: Vec<_> = table
+            .partitions
+            .iter()
+            .map(|p| (p.number, p.offset / 512, p.size / 512, p.role()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, 2048, 1024, PartitionRole::BasicData),
+                (0, 3072, 1024, PartitionRole::Extended),
+                (2, 3136, 256, PartitionRole::BasicData),
+                (3, 3648, 128, PartitionRole::BasicData),
+            ]
+        );
+        assert!(matches!(table.partitions[0].kind, PartitionKind::Mbr { bootable: true, logical: false, .. }));
+        assert!(matches!(table.partitions[3].kind, PartitionKind::Mbr { logical: true, .. }));
+        assert_eq!(
+            table.partition_id(&table.partitions[0]),
+            PartitionId::Mbr {
+                disk_signature: 0x1234_5678,
+                offset: 2048 * 512
+            }
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid() {
+        assert!(PartitionTable::read(&mut Cursor::new(vec![0u8; 8192])).is_err());
+        assert!(PartitionTable::read(&mut Cursor::new(Vec::new())).is_err());
+        assert!(read_at(&mut Cursor::new(vec![0u8; 512]), u64::MAX - 10, 512).is_err());
+
+        // 分区项大于一个扇区的头部即使 CRC 正确也不接受
+        let header = |entry_size: u32| {
+            let mut sector = vec![0u8; 512];
+            sector[..8].copy_from_slice(GPT_SIGNATURE);
+            sector[12..16].copy_from_slice(&92u32.to_le_bytes());
+            sector[24..32].copy_from_slice(&1u64.to_le_bytes());
+            sector[80..84].copy_from_slice(&4u32.to_le_bytes());
+            sector[84..88].copy_from_slice(&entry_size.to_le_bytes());
+            let crc = crc32(&sector[..92]);
+            sector[16..20].copy_from_slice(&crc.to_le_bytes());
+            sector
+        };
+        assert!(parse_gpt_header(&header(128), 1).is_ok());
+        assert!(parse_gpt_header(&header(1024), 1).is_err());
+    }
+
+    #[test]
+    fn test_fat32_free_bytes() {
+        let offset = 4096u64;
+        let mut image = vec![0u8; offset as usize + 4096];
+        {
+            let boot = &mut image[offset as usize..];
+            boot[11..13].copy_from_slice(&512u16.to_le_bytes());
+            boot[13] = 8;
+            boot[48..50].copy_from_slice(&1u16.to_le_bytes());
+            boot[82..90].copy_from_slice(b"FAT32   ");
+            boot[510] = 0x55;
+            boot[511] = 0xAA;
+            let fsinfo = &mut boot[512..1024];
+            fsinfo[0..4].copy_from_slice(&0x4161_5252u32.to_le_bytes());
+            fsinfo[484..488].copy_from_slice(&0x6141_7272u32.to_le_bytes());
+            fsinfo[488..492].copy_from_slice(&1000u32.to_le_bytes());
+        }
+        let partition = Partition {
+            number: 1,
+            offset,
+            size: 4096,
+            kind: PartitionKind::Mbr { partition_type: mbr_types::ESP, bootable: false, logical: false },
+        };
+
+        let free = fat32_free_bytes(&mut Cursor::new(&image), &partition).unwrap();
+        assert_eq!(free, Some(1000 * 8 * 512));
+
+        image[offset as usize + 512 + 488..offset as usize + 512 + 492].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(fat32_free_bytes(&mut Cursor::new(&image), &partition).unwrap(), None);
+
+        image[offset as usize + 82] = b'N';
+        assert!(fat32_free_bytes(&mut Cursor::new(&image), &partition).is_err());
+    }
+}
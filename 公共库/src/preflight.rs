@@ -0,0 +1,375 @@
+//! 安装前检查
+//!
+//! 在格式化分区或重启到 PE 之前汇总各项检查结果。每项检查只根据调用方
+//! 收集好的信息给出结论，不访问系统，便于测试：
+//!
+//! - [`Severity::Blocking`]：继续操作必然失败或会破坏数据，必须阻止；
+//! - [`Severity::Warning`]：可能出问题，需要用户确认后才能继续。
+
+use std::io::Read;
+use std::path::Path;
+
+use crate::disk_layout::Firmware;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+const MIB: u64 = 1024 * 1024;
+
+/// 释放镜像后目标分区至少应保留的空间，低于此值时警告
+pub const TARGET_HEADROOM_BYTES: u64 = 10 * GIB;
+/// 未接通电源时低于此电量阻止安装
+pub const MIN_BATTERY_PERCENT: u8 = 20;
+/// ESP 剩余空间低于此值时 bcdboot 无法写入引导文件
+pub const MIN_ESP_FREE_BYTES: u64 = 10 * MIB;
+/// ESP 剩余空间低于此值时警告
+pub const RECOMMENDED_ESP_FREE_BYTES: u64 = 32 * MIB;
+
+/// WIM/ESD 文件头
+const WIM_MAGIC: &[u8; 8] = b"MSWIM\0\0\0";
+
+/// 检查结果级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Pass,
+    Warning,
+    Blocking,
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckItem {
+    /// 检查项名称
+    pub title: &'static str,
+    pub severity: Severity,
+    /// 结论说明
+    pub message: String,
+}
+
+impl CheckItem {
+    pub fn pass(title: &'static str, message: impl Into<String>) -> Self {
+        Self { title, severity: Severity::Pass, message: message.into() }
+    }
+
+    pub fn warning(title: &'static str, message: impl Into<String>) -> Self {
+        Self { title, severity: Severity::Warning, message: message.into() }
+    }
+
+    pub fn blocking(title: &'static str, message: impl Into<String>) -> Self {
+        Self { title, severity: Severity::Blocking, message: message.into() }
+    }
+}
+
+/// 安装前检查报告
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreflightReport {
+    pub items: Vec<CheckItem>,
+}
+
+impl PreflightReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, item: CheckItem) {
+        self.items.push(item);
+    }
+
+    /// 最严重的级别
+    pub fn severity(&self) -> Severity {
+        self.items
+            .iter()
+            .map(|i| i.severity)
+            .max()
+            .unwrap_or(Severity::Pass)
+    }
+
+    /// 是否有必须阻止操作的问题
+    pub fn has_blocking(&self) -> bool {
+        self.severity() == Severity::Blocking
+    }
+
+    /// 是否需要用户确认（有警告但没有阻止项）
+    pub fn needs_confirmation(&self) -> bool {
+        self.severity() == Severity::Warning
+    }
+
+    /// 纯文本形式，用于日志
+    pub fn to_text(&self) -> String {
+        self.items
+            .iter()
+            .map(|i| {
+                let tag = match i.severity {
+                    Severity::Pass => "通过",
+                    Severity::Warning => "警告",
+                    Severity::Blocking => "阻止",
+                };
+                format!("[{}] {}: {}", tag, i.title, i.message)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 目标分区的加密状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionState {
+    NotEncrypted,
+    Encrypted,
+    /// 正在加密或解密
+    Converting,
+    Unknown,
+}
+
+/// 电池状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryLevel {
+    pub percent: u8,
+    pub ac_connected: bool,
+}
+
+/// ESP 状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EspState {
+    /// 目标磁盘上没有 ESP
+    Missing,
+    /// 找到 ESP；剩余空间读取失败时为 None
+    Present { free_bytes: Option<u64> },
+    /// 无法读取分区表
+    Unknown(String),
+    /// 整盘安装会新建 ESP
+    WillCreate,
+}
+
+/// 检查镜像文件头：WIM/ESD 需要有效的文件头，其他格式只检查能否打开
+pub fn read_image_header(path: &Path) -> Result<(), String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("无法打开镜像: {}", e))?;
+    let mut header = [0u8; 8];
+    file.read_exact(&mut header)
+        .map_err(|e| format!("无法读取镜像文件头: {}", e))?;
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    if matches!(extension.as_str(), "wim" | "esd" | "swm") && &header != WIM_MAGIC {
+        return Err("文件头无效，不是 WIM/ESD 镜像".to_string());
+    }
+    Ok(())
+}
+
+/// 镜像可读且所选卷存在
+///
+/// `index` 为 None 表示镜像没有卷的概念（GHO）。
+pub fn check_image(readable: Result<(), String>, index: Option<u32>, indexes: &[u32]) -> CheckItem {
+    const TITLE: &str = "系统镜像";
+    if let Err(e) = readable {
+        return CheckItem::blocking(TITLE, e);
+    }
+    match index {
+        Some(index) if !indexes.contains(&index) => {
+            CheckItem::blocking(TITLE, format!("镜像中不存在卷 {}", index))
+        }
+        Some(index) => CheckItem::pass(TITLE, format!("镜像可读，卷 {} 有效", index)),
+        None => CheckItem::pass(TITLE, "镜像可读"),
+    }
+}
+
+/// 目标分区容量足以容纳镜像展开后的大小（`TOTALBYTES`）
+///
+/// `capacity_bytes` 为格式化后的分区容量；不格式化时应传入剩余空间。
+pub fn check_target_size(capacity_bytes: Option<u64>, image_bytes: Option<u64>) -> CheckItem {
+    const TITLE: &str = "目标分区空间";
+    let (Some(capacity), Some(image)) = (capacity_bytes, image_bytes.filter(|&b| b > 0)) else {
+        return CheckItem::warning(TITLE, "无法确定镜像或分区大小");
+    };
+
+    let to_gb = |b: u64| b as f64 / GIB as f64;
+    if capacity < image {
+        CheckItem::blocking(
+            TITLE,
+            format!("镜像展开后需要 {:.1} GB，目标分区只有 {:.1} GB", to_gb(image), to_gb(capacity)),
+        )
+    } else if capacity - image < TARGET_HEADROOM_BYTES {
+        CheckItem::warning(
+            TITLE,
+            format!(
+                "释放后仅剩 {:.1} GB，系统更新和页面文件可能空间不足",
+                to_gb(capacity - image)
+            ),
+        )
+    } else {
+        CheckItem::pass(TITLE, format!("需要 {:.1} GB，可用 {:.1} GB", to_gb(image), to_gb(capacity)))
+    }
+}
+
+/// 数据分区不能是目标分区，也不能被格式化
+///
+/// 直接安装时数据分区就是镜像所在分区；通过 PE 安装时是重启前
+/// 镜像和配置要复制到的分区，为 None 表示找不到可用的分区。
+pub fn check_data_location(data_letter: Option<char>, target_letter: char, will_format: bool) -> CheckItem {
+    const TITLE: &str = "数据位置";
+    match data_letter {
+        None => CheckItem::blocking(TITLE, "找不到可以存放镜像和配置的数据分区"),
+        Some(letter) if letter.eq_ignore_ascii_case(&target_letter) && will_format => CheckItem::blocking(
+            TITLE,
+            format!("数据分区 {}: 就是要格式化的目标分区", letter.to_ascii_uppercase()),
+        ),
+        Some(letter) if letter.eq_ignore_ascii_case(&target_letter) => CheckItem::blocking(
+            TITLE,
+            format!("数据分区 {}: 就是目标分区，安装会覆盖其中的文件", letter.to_ascii_uppercase()),
+        ),
+        Some(letter) => CheckItem::pass(TITLE, format!("数据分区 {}: 不受安装影响", letter.to_ascii_uppercase())),
+    }
+}
+
+/// 目标分区的 BitLocker 状态
+pub fn check_encryption(state: EncryptionState, via_pe: bool, will_format: bool) -> CheckItem {
+    const TITLE: &str = "BitLocker";
+    match state {
+        EncryptionState::NotEncrypted => CheckItem::pass(TITLE, "目标分区未加密"),
+        EncryptionState::Converting => {
+            CheckItem::blocking(TITLE, "目标分区正在加密或解密，请等待完成后再安装")
+        }
+        EncryptionState::Encrypted if via_pe => {
+            CheckItem::blocking(TITLE, "目标分区已加密，PE 中无法访问，请先关闭 BitLocker")
+        }
+        EncryptionState::Encrypted if will_format => {
+            CheckItem::warning(TITLE, "目标分区已加密，格式化后加密和其中的数据将被清除")
+        }
+        EncryptionState::Encrypted => CheckItem::warning(TITLE, "目标分区已加密"),
+        EncryptionState::Unknown if via_pe => {
+            CheckItem::warning(TITLE, "无法确定加密状态；若已加密，PE 中将无法访问")
+        }
+        EncryptionState::Unknown => CheckItem::pass(TITLE, "无法确定加密状态"),
+    }
+}
+
+/// 引导方式与分区表类型、当前固件是否匹配
+pub fn check_boot_mode(firmware: Firmware, is_gpt: Option<bool>, current_firmware: Firmware) -> CheckItem {
+    const TITLE: &str = "引导方式";
+    let name = |f: Firmware| match f {
+        Firmware::Uefi => "UEFI",
+        Firmware::Bios => "Legacy",
+    };
+
+    match (firmware, is_gpt) {
+        (Firmware::Bios, Some(true)) => {
+            return CheckItem::blocking(TITLE, "Legacy 引导无法从 GPT 磁盘启动");
+        }
+        (Firmware::Uefi, Some(false)) => {
+            return CheckItem::warning(TITLE, "UEFI 引导使用 MBR 磁盘，Windows 官方不支持此组合");
+        }
+        (_, None) => return CheckItem::warning(TITLE, "无法确定目标磁盘的分区表类型"),
+        _ => {}
+    }
+
+    if firmware != current_firmware {
+        CheckItem::warning(
+            TITLE,
+            format!(
+                "当前以 {} 方式启动，需在固件设置中切换为 {} 才能启动新系统",
+                name(current_firmware),
+                name(firmware)
+            ),
+        )
+    } else {
+        CheckItem::pass(TITLE, format!("{} 引导与分区表匹配", name(firmware)))
+    }
+}
+
+/// 笔记本电量
+pub fn check_battery(battery: Option<BatteryLevel>) -> CheckItem {
+    const TITLE: &str = "电源";
+    match battery {
+        None => CheckItem::pass(TITLE, "未检测到电池"),
+        Some(b) if b.ac_connected => CheckItem::pass(TITLE, format!("已接通电源（电量 {}%）", b.percent)),
+        Some(b) if b.percent < MIN_BATTERY_PERCENT => CheckItem::blocking(
+            TITLE,
+            format!("电量仅 {}% 且未接通电源，安装中断电会导致系统无法启动", b.percent),
+        ),
+        Some(b) => CheckItem::warning(TITLE, format!("未接通电源（电量 {}%），建议接通电源", b.percent)),
+    }
+}
+
+/// UEFI 引导时目标磁盘上需要有 ESP 且有足够空间
+pub fn check_esp(firmware: Firmware, esp: &EspState) -> CheckItem {
+    const TITLE: &str = "EFI 系统分区";
+    if firmware == Firmware::Bios {
+        return CheckItem::pass(TITLE, "Legacy 引导不需要 ESP");
+    }
+    match esp {
+        EspState::WillCreate => CheckItem::pass(TITLE, "整盘安装将新建 ESP"),
+        EspState::Missing => CheckItem::blocking(TITLE, "目标磁盘上没有 EFI 系统分区，无法写入引导"),
+        EspState::Unknown(e) => CheckItem::warning(TITLE, format!("无法读取分区表: {}", e)),
+        EspState::Present { free_bytes: None } => CheckItem::warning(TITLE, "无法确定 ESP 剩余空间"),
+        EspState::Present { free_bytes: Some(free) } => {
+            let mb = free / MIB;
+            if *free < MIN_ESP_FREE_BYTES {
+                CheckItem::blocking(TITLE, format!("ESP 仅剩 {} MB，不足以写入引导文件", mb))
+            } else if *free < RECOMMENDED_ESP_FREE_BYTES {
+                CheckItem::warning(TITLE, format!("ESP 仅剩 {} MB", mb))
+            } else {
+                CheckItem::pass(TITLE, format!("ESP 剩余 {} MB", mb))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_severity() {
+        let mut report = PreflightReport::new();
+        assert!(!report.has_blocking() && !report.needs_confirmation());
+
+        report.push(check_battery(Some(BatteryLevel { percent: 60, ac_connected: false })));
+        assert!(report.needs_confirmation());
+
+        report.push(check_image(Ok(()), Some(3), &[1, 2]));
+        assert!(report.has_blocking());
+        assert!(!report.needs_confirmation());
+        assert!(report.to_text().contains("[阻止] 系统镜像: 镜像中不存在卷 3"));
+    }
+
+    #[test]
+    fn test_target_size() {
+        assert_eq!(check_target_size(Some(20 * GIB), Some(30 * GIB)).severity, Severity::Blocking);
+        assert_eq!(check_target_size(Some(35 * GIB), Some(30 * GIB)).severity, Severity::Warning);
+        assert_eq!(check_target_size(Some(60 * GIB), Some(30 * GIB)).severity, Severity::Pass);
+        assert_eq!(check_target_size(Some(60 * GIB), None).severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_location_boot_and_esp() {
+        assert_eq!(check_data_location(Some('c'), 'C', true).severity, Severity::Blocking);
+        assert_eq!(check_data_location(Some('C'), 'C', false).severity, Severity::Blocking);
+        assert_eq!(check_data_location(None, 'C', true).severity, Severity::Blocking);
+        assert_eq!(check_data_location(Some('D'), 'C', true).severity, Severity::Pass);
+
+        assert_eq!(check_encryption(EncryptionState::Encrypted, true, true).severity, Severity::Blocking);
+        assert_eq!(check_encryption(EncryptionState::Encrypted, false, true).severity, Severity::Warning);
+
+        assert_eq!(check_boot_mode(Firmware::Bios, Some(true), Firmware::Bios).severity, Severity::Blocking);
+        assert_eq!(check_boot_mode(Firmware::Uefi, Some(true), Firmware::Bios).severity, Severity::Warning);
+        assert_eq!(check_boot_mode(Firmware::Uefi, Some(true), Firmware::Uefi).severity, Severity::Pass);
+
+        assert_eq!(check_esp(Firmware::Uefi, &EspState::Missing).severity, Severity::Blocking);
+        assert_eq!(check_esp(Firmware::Bios, &EspState::Missing).severity, Severity::Pass);
+        let small = EspState::Present { free_bytes: Some(5 * MIB) };
+        assert_eq!(check_esp(Firmware::Uefi, &small).severity, Severity::Blocking);
+    }
+
+    #[test]
+    fn test_read_image_header() {
+        let dir = std::env::temp_dir();
+        let wim = dir.join(format!("lr_preflight_{}.wim", std::process::id()));
+        std::fs::write(&wim, b"MSWIM\0\0\0rest").unwrap();
+        assert!(read_image_header(&wim).is_ok());
+        std::fs::write(&wim, b"PK\x03\x04rest").unwrap();
+        assert!(read_image_header(&wim).is_err());
+        let _ = std::fs::remove_file(&wim);
+        assert!(read_image_header(&wim).is_err());
+    }
+}
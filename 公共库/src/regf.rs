@@ -0,0 +1,517 @@
+//! 注册表配置单元（regf）只读解析
+//!
+//! BCD 存储本身就是一个注册表配置单元文件。这里只实现读取所需的部分：
+//! 键（nk）、子键列表（lf/lh/li/ri）、值（vk）和大数据块（db），
+//! 不依赖 Windows API，可以直接解析 `bcdedit /export` 导出的文件。
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// 字符串
+pub const REG_SZ: u32 = 1;
+/// 二进制
+pub const REG_BINARY: u32 = 3;
+/// 32 位整数
+pub const REG_DWORD: u32 = 4;
+/// 多字符串
+pub const REG_MULTI_SZ: u32 = 7;
+
+/// 第一个 hbin 的文件偏移，单元偏移都相对于此
+const HBIN_START: usize = 0x1000;
+/// 无效单元偏移
+const NO_CELL: u32 = 0xFFFF_FFFF;
+/// 超过该长度的值数据存放在 db 大数据块中
+const BIG_DATA_SEGMENT: usize = 16344;
+/// ri 列表嵌套深度上限，防止损坏文件导致无限递归
+const MAX_LIST_DEPTH: usize = 8;
+
+/// 配置单元
+pub struct Hive {
+    data: Vec<u8>,
+    root: u32,
+}
+
+impl Hive {
+    /// 读取配置单元文件
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).with_context(|| format!("读取 {} 失败", path.display()))?;
+        Self::from_bytes(data)
+    }
+
+    /// 从内存数据解析
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        if data.len() < HBIN_START || &data[..4] != b"regf" {
+            anyhow::bail!("不是有效的注册表配置单元文件");
+        }
+        let root = u32::from_le_bytes(data[0x24..0x28].try_into().unwrap());
+        let hive = Self { data, root };
+        hive.key(root, String::new()).context("根键无效")?;
+        Ok(hive)
+    }
+
+    /// 根键
+    pub fn root(&self) -> Key<'_> {
+        // from_bytes 已验证根键
+        self.key(self.root, String::new()).unwrap()
+    }
+
+    /// 读取单元内容（不含长度前缀）
+    fn cell(&self, offset: u32) -> Result<&[u8]> {
+        let start = HBIN_START
+            .checked_add(offset as usize)
+            .filter(|s| s + 4 <= self.data.len())
+            .with_context(|| format!("单元偏移 {:#x} 越界", offset))?;
+        let size = i32::from_le_bytes(self.data[start..start + 4].try_into().unwrap());
+        let len = size.unsigned_abs() as usize;
+        if len < 4 || start + len > self.data.len() {
+            anyhow::bail!("单元 {:#x} 长度无效", offset);
+        }
+        Ok(&self.data[start + 4..start + len])
+    }
+
+    fn key(&self, offset: u32, name: String) -> Result<Key<'_>> {
+        let cell = self.cell(offset)?;
+        if cell.len() < 0x4C || &cell[..2] != b"nk" {
+            anyhow::bail!("单元 {:#x} 不是键", offset);
+        }
+        let name = if name.is_empty() {
+            let flags = read_u16(cell, 0x02)?;
+            let len = read_u16(cell, 0x48)? as usize;
+            decode_name(slice(cell, 0x4C, len)?, flags & 0x20 != 0)
+        } else {
+            name
+        };
+        Ok(Key {
+            hive: self,
+            offset,
+            name,
+        })
+    }
+
+    /// 展开子键列表，返回所有 nk 单元偏移
+    fn collect_subkeys(&self, offset: u32, depth: usize, out: &mut Vec<u32>) -> Result<()> {
+        if depth > MAX_LIST_DEPTH {
+            anyhow::bail!("子键列表嵌套过深");
+        }
+        let cell = self.cell(offset)?;
+        let count = read_u16(cell, 0x02)? as usize;
+        match cell.get(..2) {
+            Some(b"lf") | Some(b"lh") => {
+                for i in 0..count {
+                    out.push(read_u32(cell, 4 + i * 8)?);
+                }
+            }
+            Some(b"li") => {
+                for i in 0..count {
+                    out.push(read_u32(cell, 4 + i * 4)?);
+                }
+            }
+            Some(b"ri") => {
+                for i in 0..count {
+                    self.collect_subkeys(read_u32(cell, 4 + i * 4)?, depth + 1, out)?;
+                }
+            }
+            _ => anyhow::bail!("单元 {:#x} 不是子键列表", offset),
+        }
+        Ok(())
+    }
+
+    fn value(&self, offset: u32) -> Result<Value> {
+        let cell = self.cell(offset)?;
+        if cell.len() < 0x14 || &cell[..2] != b"vk" {
+            anyhow::bail!("单元 {:#x} 不是值", offset);
+        }
+        let name_len = read_u16(cell, 0x02)? as usize;
+        let size = read_u32(cell, 0x04)?;
+        let data_offset = read_u32(cell, 0x08)?;
+        let kind = read_u32(cell, 0x0C)?;
+        let flags = read_u16(cell, 0x10)?;
+        let name = decode_name(slice(cell, 0x14, name_len)?, flags & 0x01 != 0);
+
+        let len = (size & 0x7FFF_FFFF) as usize;
+        let data = if size & 0x8000_0000 != 0 {
+            // 不超过 4 字节的数据直接存放在偏移字段中
+            slice(&cell[0x08..0x0C], 0, len)?.to_vec()
+        } else if len == 0 {
+            Vec::new()
+        } else {
+            let data_cell = self.cell(data_offset)?;
+            if len > BIG_DATA_SEGMENT && data_cell.starts_with(b"db") {
+                self.big_data(data_cell, len)?
+            } else {
+                slice(data_cell, 0, len)?.to_vec()
+            }
+        };
+
+        Ok(Value { name, kind, data })
+    }
+
+    fn big_data(&self, db: &[u8], len: usize) -> Result<Vec<u8>> {
+        let count = read_u16(db, 0x02)? as usize;
+        let list = self.cell(read_u32(db, 0x04)?)?;
+        let mut data = Vec::with_capacity(len);
+        for i in 0..count {
+            let segment = self.cell(read_u32(list, i * 4)?)?;
+            let take = (len - data.len()).min(BIG_DATA_SEGMENT).min(segment.len());
+            data.extend_from_slice(&segment[..take]);
+        }
+        if data.len() != len {
+            anyhow::bail!("大数据块长度不符");
+        }
+        Ok(data)
+    }
+}
+
+/// 注册表键
+#[derive(Clone)]
+pub struct Key<'a> {
+    hive: &'a Hive,
+    offset: u32,
+    name: String,
+}
+
+impl<'a> Key<'a> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cell(&self) -> &'a [u8] {
+        // 键在构造时已验证
+        self.hive.cell(self.offset).unwrap()
+    }
+
+    /// 所有子键
+    pub fn subkeys(&self) -> Result<Vec<Key<'a>>> {
+        let cell = self.cell();
+        let count = read_u32(cell, 0x14)?;
+        let list = read_u32(cell, 0x1C)?;
+        if count == 0 || list == NO_CELL {
+            return Ok(Vec::new());
+        }
+        let mut offsets = Vec::new();
+        self.hive.collect_subkeys(list, 0, &mut offsets)?;
+        offsets
+            .into_iter()
+            .map(|offset| self.hive.key(offset, String::new()))
+            .collect()
+    }
+
+    /// 按名称查找子键（不区分大小写）
+    pub fn subkey(&self, name: &str) -> Result<Option<Key<'a>>> {
+        Ok(self
+            .subkeys()?
+            .into_iter()
+            .find(|k| k.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// 按 `A\B\C` 形式的路径查找子键
+    pub fn open(&self, path: &str) -> Result<Option<Key<'a>>> {
+        let mut key = self.clone();
+        for part in path.split('\\').filter(|p| !p.is_empty()) {
+            match key.subkey(part)? {
+                Some(next) => key = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(key))
+    }
+
+    /// 所有值
+    pub fn values(&self) -> Result<Vec<Value>> {
+        let cell = self.cell();
+        let count = read_u32(cell, 0x24)? as usize;
+        let list = read_u32(cell, 0x28)?;
+        if count == 0 || list == NO_CELL {
+            return Ok(Vec::new());
+        }
+        let list = self.hive.cell(list)?;
+        (0..count)
+            .map(|i| self.hive.value(read_u32(list, i * 4)?))
+            .collect()
+    }
+
+    /// 按名称查找值（不区分大小写，空字符串为默认值）
+    pub fn value(&self, name: &str) -> Result<Option<Value>> {
+        Ok(self
+            .values()?
+            .into_iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name)))
+    }
+}
+
+/// 注册表值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value {
+    pub name: String,
+    /// 值类型，如 [`REG_SZ`]
+    pub kind: u32,
+    pub data: Vec<u8>,
+}
+
+impl Value {
+    /// 按 REG_SZ 解码
+    pub fn as_string(&self) -> Option<String> {
+        match self.kind {
+            REG_SZ | 2 => Some(decode_utf16(&self.data).trim_end_matches('\0').to_string()),
+            _ => None,
+        }
+    }
+
+    /// 按 REG_MULTI_SZ 解码
+    pub fn as_multi_string(&self) -> Option<Vec<String>> {
+        match self.kind {
+            REG_MULTI_SZ => Some(
+                decode_utf16(&self.data)
+                    .split('\0')
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// 按 REG_DWORD 解码
+    pub fn as_u32(&self) -> Option<u32> {
+        match self.kind {
+            REG_DWORD => Some(u32::from_le_bytes(self.data.get(..4)?.try_into().ok()?)),
+            _ => None,
+        }
+    }
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    data.get(offset..offset + len)
+        .with_context(|| format!("读取 {:#x}+{} 越界", offset, len))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(slice(data, offset, 2)?.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(slice(data, offset, 4)?.try_into().unwrap()))
+}
+
+fn decode_utf16(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// 名称为压缩（Latin-1）或 UTF-16LE
+fn decode_name(data: &[u8], compressed: bool) -> String {
+    if compressed {
+        data.iter().map(|&b| b as char).collect()
+    } else {
+        decode_utf16(data)
+    }
+}
+
+/// 测试用配置单元生成器，用于构造解析测试的输入
+#[cfg(test)]
+pub(crate) mod writer {
+    use super::*;
+
+    /// 待写入的键
+    #[derive(Default)]
+    pub struct TestKey {
+        pub name: String,
+        pub values: Vec<Value>,
+        pub subkeys: Vec<TestKey>,
+    }
+
+    impl TestKey {
+        pub fn new(name: &str) -> Self {
+            Self {
+                name: name.to_string(),
+                ..Default::default()
+            }
+        }
+
+        pub fn value(mut self, name: &str, kind: u32, data: Vec<u8>) -> Self {
+            self.values.push(Value {
+                name: name.to_string(),
+                kind,
+                data,
+            });
+            self
+        }
+
+        pub fn key(mut self, key: TestKey) -> Self {
+            self.subkeys.push(key);
+            self
+        }
+    }
+
+    pub fn utf16z(s: &str) -> Vec<u8> {
+        s.encode_utf16().chain([0]).flat_map(|u| u.to_le_bytes()).collect()
+    }
+
+    pub fn multi_sz(items: &[&str]) -> Vec<u8> {
+        let mut data: Vec<u8> = items.iter().flat_map(|s| utf16z(s)).collect();
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    #[derive(Default)]
+    struct Cells(Vec<u8>);
+
+    impl Cells {
+        /// 分配单元，返回相对偏移
+        fn alloc(&mut self, content: &[u8]) -> u32 {
+            let offset = 0x20 + self.0.len() as u32;
+            let size = (content.len() + 4).next_multiple_of(8);
+            self.0.extend_from_slice(&(-(size as i32)).to_le_bytes());
+            self.0.extend_from_slice(content);
+            self.0.resize(self.0.len() + size - 4 - content.len(), 0);
+            offset
+        }
+
+        fn write_key(&mut self, key: &TestKey) -> u32 {
+            let children: Vec<u32> = key.subkeys.iter().map(|k| self.write_key(k)).collect();
+            let subkey_list = if children.is_empty() {
+                NO_CELL
+            } else {
+                let mut lf = b"lf".to_vec();
+                lf.extend_from_slice(&(children.len() as u16).to_le_bytes());
+                for child in &children {
+                    lf.extend_from_slice(&child.to_le_bytes());
+                    lf.extend_from_slice(&[0; 4]);
+                }
+                self.alloc(&lf)
+            };
+
+            let values: Vec<u32> = key.values.iter().map(|v| self.write_value(v)).collect();
+            let value_list = if values.is_empty() {
+                NO_CELL
+            } else {
+                let list: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+                self.alloc(&list)
+            };
+
+            let mut nk = vec![0u8; 0x4C];
+            nk[..2].copy_from_slice(b"nk");
+            nk[0x02..0x04].copy_from_slice(&0x20u16.to_le_bytes());
+            nk[0x14..0x18].copy_from_slice(&(children.len() as u32).to_le_bytes());
+            nk[0x1C..0x20].copy_from_slice(&subkey_list.to_le_bytes());
+            nk[0x24..0x28].copy_from_slice(&(values.len() as u32).to_le_bytes());
+            nk[0x28..0x2C].copy_from_slice(&value_list.to_le_bytes());
+            nk[0x48..0x4A].copy_from_slice(&(key.name.len() as u16).to_le_bytes());
+            nk.extend_from_slice(key.name.as_bytes());
+            self.alloc(&nk)
+        }
+
+        fn write_value(&mut self, value: &Value) -> u32 {
+            let len = value.data.len();
+            let (size, data_offset) = if len <= 4 {
+                let mut inline = [0u8; 4];
+                inline[..len].copy_from_slice(&value.data);
+                (len as u32 | 0x8000_0000, u32::from_le_bytes(inline))
+            } else if len > BIG_DATA_SEGMENT {
+                let segments: Vec<u32> = value
+                    .data
+                    .chunks(BIG_DATA_SEGMENT)
+                    .map(|c| self.alloc(c))
+                    .collect();
+                let list: Vec<u8> = segments.iter().flat_map(|s| s.to_le_bytes()).collect();
+                let list = self.alloc(&list);
+                let mut db = b"db".to_vec();
+                db.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+                db.extend_from_slice(&list.to_le_bytes());
+                (len as u32, self.alloc(&db))
+            } else {
+                (len as u32, self.alloc(&value.data))
+            };
+
+            let mut vk = vec![0u8; 0x14];
+            vk[..2].copy_from_slice(b"vk");
+            vk[0x02..0x04].copy_from_slice(&(value.name.len() as u16).to_le_bytes());
+            vk[0x04..0x08].copy_from_slice(&size.to_le_bytes());
+            vk[0x08..0x0C].copy_from_slice(&data_offset.to_le_bytes());
+            vk[0x0C..0x10].copy_from_slice(&value.kind.to_le_bytes());
+            vk[0x10..0x12].copy_from_slice(&1u16.to_le_bytes());
+            vk.extend_from_slice(value.name.as_bytes());
+            self.alloc(&vk)
+        }
+    }
+
+    /// 生成配置单元文件内容
+    pub fn build(root: &TestKey) -> Vec<u8> {
+        let mut cells = Cells::default();
+        let root_offset = cells.write_key(root);
+
+        let hbin_size = (cells.0.len() + 0x20).next_multiple_of(0x1000);
+        let mut data = vec![0u8; HBIN_START + hbin_size];
+        data[..4].copy_from_slice(b"regf");
+        data[0x14..0x18].copy_from_slice(&1u32.to_le_bytes());
+        data[0x18..0x1C].copy_from_slice(&5u32.to_le_bytes());
+        data[0x20..0x24].copy_from_slice(&1u32.to_le_bytes());
+        data[0x24..0x28].copy_from_slice(&root_offset.to_le_bytes());
+        data[0x28..0x2C].copy_from_slice(&(hbin_size as u32).to_le_bytes());
+
+        let hbin = &mut data[HBIN_START..];
+        hbin[..4].copy_from_slice(b"hbin");
+        hbin[0x08..0x0C].copy_from_slice(&(hbin_size as u32).to_le_bytes());
+        hbin[0x20..0x20 + cells.0.len()].copy_from_slice(&cells.0);
+        // 剩余空间标记为一个空闲单元
+        let free = hbin_size - 0x20 - cells.0.len();
+        if free >= 8 {
+            let at = 0x20 + cells.0.len();
+            hbin[at..at + 4].copy_from_slice(&(free as i32).to_le_bytes());
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::writer::*;
+    use super::*;
+
+    #[test]
+    fn test_read_hive() {
+        let big: Vec<u8> = (0..40000u32).map(|i| i as u8).collect();
+        let root = TestKey::new("ROOT")
+            .key(
+                TestKey::new("Objects")
+                    .key(TestKey::new("A").value("Name", REG_SZ, utf16z("测试")))
+                    .key(TestKey::new("B").value("", REG_DWORD, 7u32.to_le_bytes().to_vec())),
+            )
+            .value("List", REG_MULTI_SZ, multi_sz(&["x", "yz"]))
+            .value("Big", REG_BINARY, big.clone())
+            .value("Tiny", REG_BINARY, vec![1, 2]);
+        let hive = Hive::from_bytes(build(&root)).unwrap();
+
+        let root = hive.root();
+        assert_eq!(root.name(), "ROOT");
+        let objects = root.subkey("objects").unwrap().unwrap();
+        let names: Vec<_> = objects.subkeys().unwrap().iter().map(|k| k.name().to_string()).collect();
+        assert_eq!(names, ["A", "B"]);
+
+        let a = root.open("Objects\\A").unwrap().unwrap();
+        assert_eq!(a.value("name").unwrap().unwrap().as_string().as_deref(), Some("测试"));
+        let b = root.open("Objects\\B").unwrap().unwrap();
+        assert_eq!(b.value("").unwrap().unwrap().as_u32(), Some(7));
+        assert!(root.open("Objects\\C").unwrap().is_none());
+
+        assert_eq!(
+            root.value("List").unwrap().unwrap().as_multi_string().unwrap(),
+            ["x", "yz"]
+        );
+        assert_eq!(root.value("Big").unwrap().unwrap().data, big);
+        assert_eq!(root.value("Tiny").unwrap().unwrap().data, [1, 2]);
+    }
+
+    #[test]
+    fn test_reject_invalid() {
+        assert!(Hive::from_bytes(b"not a hive".to_vec()).is_err());
+
+        let mut data = build(&TestKey::new("ROOT"));
+        data[0x24..0x28].copy_from_slice(&0x7FFF_0000u32.to_le_bytes());
+        assert!(Hive::from_bytes(data).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! 装机后自动安装的软件
+//!
+//! 安装包复制到目标系统脚本目录下的 [`SOFTWARE_DIR`]，并生成按顺序静默安装的
+//! [`SOFTWARE_SCRIPT`]，由应答文件在首次登录时调用。通过 PE 安装时先暂存到
+//! 数据目录，PE 端部署完成后再用 [`copy_staged`] 复制到目标系统。
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// 安装包目录名称（位于脚本目录或数据目录下）
+pub const SOFTWARE_DIR: &str = "software";
+/// 首次登录时执行的安装脚本名称
+pub const SOFTWARE_SCRIPT: &str = "software.bat";
+
+/// 一个安装包
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SoftwareItem {
+    /// 安装包路径（.exe 或 .msi）
+    pub installer: String,
+    /// 静默安装参数，如 "/S"
+    pub arguments: String,
+}
+
+impl SoftwareItem {
+    /// 解析 "安装包路径|参数" 形式的一行，空行和 # 开头的注释行返回 None
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (installer, arguments) = line.split_once('|').unwrap_or((line, ""));
+        Some(Self {
+            installer: installer.trim().trim_matches('"').to_string(),
+            arguments: arguments.trim().to_string(),
+        })
+    }
+
+    /// 转换为 "安装包路径|参数" 形式，用于配置文件
+    pub fn to_line(&self) -> String {
+        if self.arguments.is_empty() {
+            self.installer.clone()
+        } else {
+            format!("{}|{}", self.installer, self.arguments)
+        }
+    }
+
+    /// 安装包的文件名
+    fn file_name(&self) -> Option<&str> {
+        self.installer.rsplit(['\\', '/']).next().filter(|n| !n.is_empty())
+    }
+
+    fn is_msi(&self) -> bool {
+        self.installer.to_lowercase().ends_with(".msi")
+    }
+}
+
+/// 解析多行软件列表，每行一个安装包
+pub fn parse_list(text: &str) -> Vec<SoftwareItem> {
+    text.lines().filter_map(SoftwareItem::parse_line).collect()
+}
+
+/// 复制到目标目录后的文件名，加序号避免同名安装包互相覆盖
+fn staged_name(index: usize, item: &SoftwareItem) -> Result<String> {
+    let name = item
+        .file_name()
+        .with_context(|| format!("无效的安装包路径: {}", item.installer))?;
+    Ok(format!("{:02}_{}", index + 1, name))
+}
+
+/// 生成按顺序安装全部软件的批处理脚本
+///
+/// 安装包位于脚本所在目录的 [`SOFTWARE_DIR`] 子目录中，逐个等待安装结束。
+pub fn render_install_script(items: &[SoftwareItem]) -> Result<String> {
+    let mut script = String::from("@echo off\r\nchcp 65001 >nul\r\n");
+    for (index, item) in items.iter().enumerate() {
+        let name = staged_name(index, item)?;
+        let path = format!("%~dp0{}\\{}", SOFTWARE_DIR, name);
+        script.push_str(&format!("echo [{}/{}] {}\r\n", index + 1, items.len(), name));
+        let command = if item.is_msi() {
+            format!("msiexec /i \"{}\" /qn /norestart", path)
+        } else {
+            format!("start \"\" /wait \"{}\"", path)
+        };
+        if item.arguments.is_empty() {
+            script.push_str(&format!("{}\r\n", command));
+        } else {
+            script.push_str(&format!("{} {}\r\n", command, item.arguments));
+        }
+    }
+    Ok(script)
+}
+
+/// 把安装包和安装脚本写入目录（目标系统的脚本目录或 PE 数据目录）
+pub fn stage(items: &[SoftwareItem], dir: &Path) -> Result<()> {
+    let software_dir = dir.join(SOFTWARE_DIR);
+    std::fs::create_dir_all(&software_dir).context("创建软件目录失败")?;
+
+    for (index, item) in items.iter().enumerate() {
+        let target = software_dir.join(staged_name(index, item)?);
+        std::fs::copy(&item.installer, &target)
+            .with_context(|| format!("复制安装包失败: {}", item.installer))?;
+    }
+    std::fs::write(dir.join(SOFTWARE_SCRIPT), render_install_script(items)?)
+        .context("写入软件安装脚本失败")
+}
+
+/// 把暂存目录中的安装包和脚本复制到目标系统的脚本目录
+///
+/// 暂存目录中没有安装脚本时返回 false。
+pub fn copy_staged(staging_dir: &Path, scripts_dir: &Path) -> Result<bool> {
+    let script = staging_dir.join(SOFTWARE_SCRIPT);
+    if !script.exists() {
+        return Ok(false);
+    }
+
+    let target_dir = scripts_dir.join(SOFTWARE_DIR);
+    std::fs::create_dir_all(&target_dir).context("创建软件目录失败")?;
+    for entry in std::fs::read_dir(staging_dir.join(SOFTWARE_DIR)).context("读取暂存的安装包失败")? {
+        let entry = entry?;
+        std::fs::copy(entry.path(), target_dir.join(entry.file_name()))
+            .with_context(|| format!("复制安装包失败: {}", entry.path().display()))?;
+    }
+    std::fs::copy(&script, scripts_dir.join(SOFTWARE_SCRIPT)).context("复制软件安装脚本失败")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render() {
+        let items = parse_list(
+            "# 常用软件\r\n\
+             D:\\Soft\\7z.exe | /S\r\n\
+             \r\n\
+             \"E:\\msi\\agent.msi\"\r\n\
+             D:\\Other\\7z.exe|/S /D=C:\\7z",
+        );
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].to_line(), "D:\\Soft\\7z.exe|/S");
+        assert_eq!(items[1].to_line(), "E:\\msi\\agent.msi");
+        assert_eq!(SoftwareItem::parse_line(&items[2].to_line()).as_ref(), Some(&items[2]));
+
+        assert_eq!(
+            render_install_script(&items).unwrap(),
+            "@echo off\r\nchcp 65001 >nul\r\n\
+             echo [1/3] 01_7z.exe\r\n\
+             start \"\" /wait \"%~dp0software\\01_7z.exe\" /S\r\n\
+             echo [2/3] 02_agent.msi\r\n\
+             msiexec /i \"%~dp0software\\02_agent.msi\" /qn /norestart\r\n\
+             echo [3/3] 03_7z.exe\r\n\
+             start \"\" /wait \"%~dp0software\\03_7z.exe\" /S /D=C:\\7z\r\n"
+        );
+        assert!(render_install_script(&[SoftwareItem {
+            installer: "D:\\Soft\\".to_string(),
+            arguments: String::new(),
+        }])
+        .is_err());
+    }
+}
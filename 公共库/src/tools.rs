@@ -0,0 +1,433 @@
+//! 安装和备份用到的外部工具
+//!
+//! 两端的安装、备份后端通过 [`Tools`] 调用 DISM、diskpart 和 bcdboot，命令行在这里统一生成。
+//! 执行器在创建时传入：实际运行时是 [`SystemRunner`](crate::command::SystemRunner)，
+//! 测试时换成 [`FakeRunner`](crate::command::FakeRunner)，据此断言完整的命令行。
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::backup::compression::Compression;
+use crate::command::{CommandRunner, StreamEvent};
+use crate::disk_layout::Firmware;
+use crate::diskpart::script::{DiskpartScript, FileSystem, RunMode};
+use crate::workflow::Progress;
+
+/// 外部工具的路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolPaths {
+    pub dism: String,
+    pub diskpart: String,
+    pub bcdboot: String,
+    /// 没有 bootsect 时 BIOS 引导不重写分区引导扇区
+    pub bootsect: Option<String>,
+}
+
+impl ToolPaths {
+    /// 程序目录下 bin 中的工具；没有内置 diskpart 时使用系统的 diskpart
+    pub fn in_bin_dir(bin_dir: &Path) -> Self {
+        let path = |path: &Path| path.to_string_lossy().into_owned();
+        let diskpart = bin_dir.join("diskpart").join("diskpart.exe");
+        let bootsect = bin_dir.join("bootsect.exe");
+        Self {
+            dism: path(&bin_dir.join("dism").join("dism.exe")),
+            diskpart: if diskpart.exists() {
+                path(&diskpart)
+            } else {
+                "diskpart.exe".to_string()
+            },
+            bcdboot: path(&bin_dir.join("bcdboot.exe")),
+            bootsect: bootsect.exists().then(|| path(&bootsect)),
+        }
+    }
+}
+
+/// 捕获镜像的选项
+#[derive(Debug, Clone, Default)]
+pub struct CaptureOptions {
+    /// 排除列表和不压缩列表 (wimscript.ini)
+    pub config_file: Option<String>,
+    /// 压缩方式，追加到已有镜像时沿用镜像原有的压缩方式
+    pub compression: Compression,
+}
+
+/// 通过指定执行器调用 DISM、diskpart 和 bcdboot
+#[derive(Clone)]
+pub struct Tools {
+    runner: Arc<dyn CommandRunner>,
+    paths: ToolPaths,
+    /// 把工具输出转为文本（中文系统上为 GBK）
+    decode: fn(&[u8]) -> String,
+}
+
+impl Tools {
+    pub fn new(runner: Arc<dyn CommandRunner>, paths: ToolPaths, decode: fn(&[u8]) -> String) -> Self {
+        Self { runner, paths, decode }
+    }
+
+    /// 创建时传入的执行器，供同一后端中的其他工具使用
+    pub fn runner(&self) -> Arc<dyn CommandRunner> {
+        self.runner.clone()
+    }
+
+    pub fn paths(&self) -> &ToolPaths {
+        &self.paths
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        (self.decode)(bytes)
+    }
+
+    /// 执行 diskpart 脚本，返回输出；`RunMode::DryRun` 时不执行，只返回执行计划
+    ///
+    /// 以 /s 运行脚本时任一命令失败 diskpart 都会返回非 0 退出码，
+    /// 据此判断成败，不匹配随系统语言变化的提示文字。
+    pub fn run_diskpart(&self, script: &DiskpartScript, mode: RunMode) -> Result<String> {
+        if mode == RunMode::DryRun {
+            return Ok(script.plan_text());
+        }
+
+        static SCRIPT_ID: AtomicU32 = AtomicU32::new(0);
+        let script_path = std::env::temp_dir().join(format!(
+            "lr_diskpart_{}_{}.txt",
+            std::process::id(),
+            SCRIPT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&script_path, script.render())?;
+
+        let output = self
+            .runner
+            .output(&self.paths.diskpart, &["/s", &script_path.to_string_lossy()]);
+        let _ = std::fs::remove_file(&script_path);
+
+        let output = output?;
+        let output_text = self.decode(&output.stdout);
+        if !output.status.success() {
+            anyhow::bail!(
+                "diskpart 执行失败（退出码 {}）: {}",
+                output.status.code().unwrap_or(-1),
+                output_text
+            );
+        }
+        Ok(output_text)
+    }
+
+    /// 快速格式化为 NTFS
+    pub fn format_volume(&self, letter: char, mode: RunMode) -> Result<String> {
+        let script = DiskpartScript::builder()
+            .select_volume(letter)
+            .format_override(FileSystem::Ntfs, None)
+            .build()?;
+        self.run_diskpart(&script, mode)
+    }
+
+    /// 应用系统镜像 (WIM/ESD)
+    pub fn apply_image(&self, image_file: &str, apply_dir: &str, index: u32, progress: Progress) -> Result<()> {
+        log::info!("开始释放镜像: {} -> {} (Index: {})", image_file, apply_dir, index);
+        self.run_dism(
+            &[
+                "/Apply-Image".to_string(),
+                format!("/ImageFile:{}", image_file),
+                format!("/ApplyDir:{}", apply_dir),
+                format!("/Index:{}", index),
+            ],
+            progress,
+        )
+    }
+
+    /// 捕获系统镜像 (备份)
+    pub fn capture_image(
+        &self,
+        image_file: &str,
+        capture_dir: &str,
+        name: &str,
+        description: &str,
+        options: &CaptureOptions,
+        progress: Progress,
+    ) -> Result<()> {
+        log::info!("开始备份镜像: {} -> {}", capture_dir, image_file);
+        let mut args = capture_args("/Capture-Image", image_file, capture_dir, name, description);
+        args.insert(5, format!("/Compress:{}", options.compression.to_config_value()));
+        self.run_dism(&with_config_file(args, options), progress)
+    }
+
+    /// 增量备份，追加到已有镜像时沿用镜像原有的压缩方式
+    pub fn append_image(
+        &self,
+        image_file: &str,
+        capture_dir: &str,
+        name: &str,
+        description: &str,
+        options: &CaptureOptions,
+        progress: Progress,
+    ) -> Result<()> {
+        log::info!("开始增量备份: {} -> {}", capture_dir, image_file);
+        let args = capture_args("/Append-Image", image_file, capture_dir, name, description);
+        self.run_dism(&with_config_file(args, options), progress)
+    }
+
+    /// 导出镜像中的一卷，目标文件已存在时追加为新卷
+    ///
+    /// `compress` 为 DISM 的 `/Compress:` 参数，追加时 DISM 沿用目标镜像的压缩方式，应传 None。
+    pub fn export_image(
+        &self,
+        source_file: &str,
+        index: u32,
+        destination_file: &str,
+        compress: Option<&str>,
+        progress: Progress,
+    ) -> Result<()> {
+        log::info!("导出镜像: {} 第 {} 卷 -> {}", source_file, index, destination_file);
+        let mut args = vec![
+            "/Export-Image".to_string(),
+            format!("/SourceImageFile:{}", source_file),
+            format!("/SourceIndex:{}", index),
+            format!("/DestinationImageFile:{}", destination_file),
+            "/CheckIntegrity".to_string(),
+        ];
+        if let Some(compress) = compress {
+            args.push(format!("/Compress:{}", compress));
+        }
+        self.run_dism(&args, progress)
+    }
+
+    /// 执行 DISM 命令，按输出中的百分比报告进度
+    fn run_dism(&self, args: &[String], progress: Progress) -> Result<()> {
+        log::info!("DISM 命令: {} {:?}", self.paths.dism, args);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        // \r 或 \n 都作为行结束符，进度行以 \r 刷新
+        let mut line_buffer = Vec::new();
+        let handle_line = |line_buffer: &mut Vec<u8>| {
+            if line_buffer.is_empty() {
+                return;
+            }
+            let line = self.decode(line_buffer);
+            log::debug!("DISM: {}", line);
+            if let Some(percentage) = parse_dism_progress(&line) {
+                progress(percentage);
+            }
+            line_buffer.clear();
+        };
+
+        let output = self.runner.stream(&self.paths.dism, &args, &mut |event| {
+            if let StreamEvent::Stdout(chunk) = event {
+                for &byte in chunk {
+                    if byte == b'\r' || byte == b'\n' {
+                        handle_line(&mut line_buffer);
+                    } else {
+                        line_buffer.push(byte);
+                    }
+                }
+            }
+            true
+        })?;
+        handle_line(&mut line_buffer);
+
+        if !output.stderr.is_empty() {
+            log::debug!("DISM STDERR: {}", self.decode(&output.stderr));
+        }
+        log::info!("DISM 命令结束，退出码: {:?}", output.status.code());
+        if !output.status.success() {
+            anyhow::bail!("DISM 命令执行失败（退出码 {}）", output.status.code().unwrap_or(-1));
+        }
+        Ok(())
+    }
+
+    /// 用 bcdboot 写入引导文件
+    ///
+    /// UEFI 时 `esp` 为已挂载的 EFI 系统分区（如 "S:"），依次尝试 `/f UEFI`、`/f ALL` 和不指定
+    /// 引导类型；找不到 ESP 时交给 bcdboot 自行查找。BIOS 时先用 bootsect 重写分区引导扇区。
+    pub fn write_boot_files(&self, windows_partition: &str, firmware: Firmware, esp: Option<&str>) -> Result<()> {
+        let windows_path = format!("{}\\Windows", windows_partition);
+
+        match (firmware, esp) {
+            (Firmware::Uefi, Some(esp)) => {
+                if Path::new(&format!("{}\\", esp)).exists() {
+                    let _ = std::fs::create_dir_all(format!("{}\\EFI\\Microsoft", esp));
+                    let _ = std::fs::create_dir_all(format!("{}\\EFI\\Boot", esp));
+                }
+
+                let attempts: [&[&str]; 3] = [&["/f", "UEFI"], &["/f", "ALL"], &[]];
+                self.bcdboot_with_fallback(&windows_path, &["/s", esp], &attempts)
+                    .map_err(|e| anyhow::anyhow!("UEFI 引导修复失败: {}", e))?;
+
+                // 部分固件只认默认路径下的引导程序
+                let bootmgfw = format!("{}\\EFI\\Microsoft\\Boot\\bootmgfw.efi", esp);
+                let bootx64 = format!("{}\\EFI\\Boot\\bootx64.efi", esp);
+                if Path::new(&bootmgfw).exists() && !Path::new(&bootx64).exists() {
+                    let _ = std::fs::copy(&bootmgfw, &bootx64);
+                    log::info!("已复制 bootmgfw.efi -> bootx64.efi");
+                }
+            }
+            (Firmware::Uefi, None) => {
+                log::warn!("未找到 ESP，由 bcdboot 自行查找");
+                self.bcdboot_with_fallback(&windows_path, &[], &[&["/f", "UEFI"]])
+                    .map_err(|e| anyhow::anyhow!("引导修复失败: {}", e))?;
+            }
+            (Firmware::Bios, _) => {
+                if let Some(bootsect) = &self.paths.bootsect {
+                    let output = self.runner.output(bootsect, &["/nt60", windows_partition, "/mbr"])?;
+                    log::debug!("bootsect: {}", self.decode(&output.stdout));
+                }
+                self.bcdboot_with_fallback(&windows_path, &[], &[&["/f", "BIOS"], &[]])
+                    .map_err(|e| anyhow::anyhow!("Legacy 引导修复失败: {}", e))?;
+            }
+        }
+        log::info!("引导文件写入完成");
+        Ok(())
+    }
+
+    /// 依次尝试 `attempts` 中的引导类型参数，直到 bcdboot 成功；全部失败时返回最后一次的错误输出
+    fn bcdboot_with_fallback(&self, windows_path: &str, target: &[&str], attempts: &[&[&str]]) -> Result<()> {
+        let mut last_error = String::new();
+        for firmware_args in attempts {
+            let args: Vec<&str> = std::iter::once(windows_path)
+                .chain(target.iter().copied())
+                .chain(firmware_args.iter().copied())
+                .chain(["/l", "zh-cn"])
+                .collect();
+            log::info!("执行: bcdboot {}", args.join(" "));
+            let output = self.runner.output(&self.paths.bcdboot, &args)?;
+            if output.status.success() {
+                return Ok(());
+            }
+            last_error = self.decode(&output.stderr);
+            log::warn!("bcdboot 失败: {}", last_error.trim());
+        }
+        anyhow::bail!("{}", last_error.trim())
+    }
+}
+
+fn capture_args(verb: &str, image_file: &str, capture_dir: &str, name: &str, description: &str) -> Vec<String> {
+    vec![
+        verb.to_string(),
+        format!("/ImageFile:{}", image_file),
+        format!("/CaptureDir:{}", capture_dir),
+        format!("/Name:{}", name),
+        format!("/Description:{}", description),
+        // 写入完整性表，备份验证时据此校验数据
+        "/CheckIntegrity".to_string(),
+    ]
+}
+
+fn with_config_file(mut args: Vec<String>, options: &CaptureOptions) -> Vec<String> {
+    if let Some(config_file) = &options.config_file {
+        args.push(format!("/ConfigFile:{}", config_file));
+    }
+    args
+}
+
+/// 解析 DISM 进度行中的百分比
+///
+/// ```text
+/// [==                    ]  10.0%
+/// 正在捕获映像 [===========         ]  55.0%
+/// ```
+pub fn parse_dism_progress(line: &str) -> Option<u8> {
+    let (before, _) = line.split_once('%')?;
+    let number = before.trim_end();
+    let start = number
+        .rfind(|c: char| !c.is_ascii_digit() && c != '.')
+        .map_or(0, |i| i + 1);
+    let number = &number[start..];
+    number.split('.').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{CommandOutput, FakeRunner, Invocation};
+
+    fn fake_tools(fake: &Arc<FakeRunner>) -> Tools {
+        let paths = ToolPaths {
+            dism: r"X:\bin\dism\dism.exe".to_string(),
+            diskpart: "diskpart.exe".to_string(),
+            bcdboot: r"X:\bin\bcdboot.exe".to_string(),
+            bootsect: Some(r"X:\bin\bootsect.exe".to_string()),
+        };
+        Tools::new(fake.clone(), paths, |bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn command_lines(fake: &FakeRunner) -> Vec<String> {
+        fake.calls().iter().map(Invocation::command_line).collect()
+    }
+
+    #[test]
+    fn test_parse_dism_progress() {
+        assert_eq!(parse_dism_progress("[==                    ]  10.0%"), Some(10));
+        assert_eq!(parse_dism_progress("[=====                 ] 25%"), Some(25));
+        assert_eq!(parse_dism_progress("正在捕获映像 [=====      ]  55.0%"), Some(55));
+        assert_eq!(parse_dism_progress("[==========================100.0%==========================]"), Some(100));
+        assert_eq!(parse_dism_progress("操作成功完成。"), None);
+        assert_eq!(parse_dism_progress("%"), None);
+    }
+
+    #[test]
+    fn test_dism_progress_and_failure() {
+        let fake = Arc::new(FakeRunner::new());
+        fake.reply(
+            "dism",
+            &["/Apply-Image"],
+            CommandOutput::success("[==    ] 10.0%\r[===== ] 55.0%\r\n操作成功完成。\n"),
+        )
+        .reply("dism", &["/Export-Image"], CommandOutput::failure(5, "拒绝访问"));
+        let tools = fake_tools(&fake);
+
+        let seen = std::sync::Mutex::new(Vec::new());
+        tools
+            .apply_image(r"D:\install.wim", "C:\\", 2, &|p| seen.lock().unwrap().push(p))
+            .unwrap();
+        assert_eq!(*seen.lock().unwrap(), [10, 55]);
+
+        let err = tools.export_image("a.wim", 1, "b.esd", Some("recovery"), &|_| {}).unwrap_err();
+        assert!(err.to_string().contains("退出码 5"));
+        assert_eq!(
+            command_lines(&fake),
+            [
+                r"X:\bin\dism\dism.exe /Apply-Image /ImageFile:D:\install.wim /ApplyDir:C:\ /Index:2",
+                r"X:\bin\dism\dism.exe /Export-Image /SourceImageFile:a.wim /SourceIndex:1 /DestinationImageFile:b.esd /CheckIntegrity /Compress:recovery",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bcdboot_fallback() {
+        let fake = Arc::new(FakeRunner::new());
+        fake.reply("bcdboot", &["UEFI"], CommandOutput::failure(1, "failed"))
+            .reply("bcdboot", &["ALL"], CommandOutput::failure(1, "failed"))
+            .reply("bcdboot", &[], CommandOutput::success(""))
+            .reply("bootsect", &[], CommandOutput::success(""))
+            .reply("bcdboot", &["BIOS"], CommandOutput::failure(1, "BIOS failed"))
+            .reply("bcdboot", &[], CommandOutput::failure(1, "still failed"));
+        let tools = fake_tools(&fake);
+
+        tools.write_boot_files("W:", Firmware::Uefi, Some("S:")).unwrap();
+        let err = tools.write_boot_files("W:", Firmware::Bios, None).unwrap_err();
+        assert_eq!(err.to_string(), "Legacy 引导修复失败: still failed");
+
+        assert_eq!(
+            command_lines(&fake),
+            [
+                r"X:\bin\bcdboot.exe W:\Windows /s S: /f UEFI /l zh-cn",
+                r"X:\bin\bcdboot.exe W:\Windows /s S: /f ALL /l zh-cn",
+                r"X:\bin\bcdboot.exe W:\Windows /s S: /l zh-cn",
+                r"X:\bin\bootsect.exe /nt60 W: /mbr",
+                r"X:\bin\bcdboot.exe W:\Windows /f BIOS /l zh-cn",
+                r"X:\bin\bcdboot.exe W:\Windows /l zh-cn",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diskpart_dry_run() {
+        let fake = Arc::new(FakeRunner::new());
+        let tools = fake_tools(&fake);
+        let plan = tools.format_volume('D', RunMode::DryRun).unwrap();
+        assert!(plan.contains("格式化"));
+        assert!(fake.calls().is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+//! 本地账户与内置管理员设置
+//!
+//! 应答文件中的密码按 Microsoft 约定编码：密码与设置名拼接后转为 UTF-16LE，
+//! 再做 Base64 编码，并设置 `PlainText` 为 false。
+//! 编码后的密码用 [`EncodedPassword`] 表示，明文不会离开界面层。
+
+use anyhow::Result;
+
+/// 已编码的应答文件密码
+///
+/// 空值表示无密码。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodedPassword(String);
+
+impl EncodedPassword {
+    /// 编码本地账户 / 自动登录密码（`Password` 设置）
+    pub fn for_account(plain: &str) -> Self {
+        Self::encode(plain, "Password")
+    }
+
+    /// 编码内置管理员密码（`AdministratorPassword` 设置）
+    pub fn for_administrator(plain: &str) -> Self {
+        Self::encode(plain, "AdministratorPassword")
+    }
+
+    /// 使用已编码的值（如从配置文件读取）
+    pub fn from_encoded(value: &str) -> Self {
+        Self(value.trim().to_string())
+    }
+
+    fn encode(plain: &str, setting: &str) -> Self {
+        if plain.is_empty() {
+            return Self::default();
+        }
+        let bytes: Vec<u8> = format!("{}{}", plain, setting)
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        Self(base64_encode(&bytes))
+    }
+
+    /// 编码后的值
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// 本地账户
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalAccount {
+    /// 用户名
+    pub name: String,
+    /// 所属组，如 "Administrators"、"Users"
+    pub group: String,
+    /// 已编码的密码
+    pub password: EncodedPassword,
+}
+
+impl LocalAccount {
+    /// 创建账户，明文密码在此处编码
+    pub fn new(name: &str, group: &str, plain_password: &str) -> Self {
+        Self {
+            name: name.trim().to_string(),
+            group: group.trim().to_string(),
+            password: EncodedPassword::for_account(plain_password),
+        }
+    }
+
+    /// 序列化为配置文件中的一行：`用户名|组|已编码密码`
+    ///
+    /// 用户名不允许包含 `|`，Base64 编码也不会出现该字符。
+    pub fn to_config_value(&self) -> String {
+        format!("{}|{}|{}", self.name, self.group, self.password.as_str())
+    }
+
+    /// 从配置文件值解析
+    pub fn from_config_value(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, '|');
+        let name = parts.next()?.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let group = parts.next().unwrap_or("").trim();
+        let password = parts.next().unwrap_or("");
+        Some(Self {
+            name: name.to_string(),
+            group: if group.is_empty() { DEFAULT_GROUP } else { group }.to_string(),
+            password: EncodedPassword::from_encoded(password),
+        })
+    }
+}
+
+/// 新建账户的默认组
+pub const DEFAULT_GROUP: &str = "Administrators";
+
+/// 界面中可选的账户组
+pub const ACCOUNT_GROUPS: &[(&str, &str)] = &[("Administrators", "管理员"), ("Users", "标准用户")];
+
+/// 内置 Administrator 账户的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuiltinAdministrator {
+    /// 保持系统默认（禁用）
+    #[default]
+    Unchanged,
+    /// 启用
+    Enabled,
+    /// 显式禁用
+    Disabled,
+}
+
+impl BuiltinAdministrator {
+    /// 配置文件中的取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuiltinAdministrator::Unchanged => "Unchanged",
+            BuiltinAdministrator::Enabled => "Enabled",
+            BuiltinAdministrator::Disabled => "Disabled",
+        }
+    }
+
+    /// 从配置文件解析，无法识别时保持默认
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "Enabled" => BuiltinAdministrator::Enabled,
+            "Disabled" => BuiltinAdministrator::Disabled,
+            _ => BuiltinAdministrator::Unchanged,
+        }
+    }
+}
+
+/// 检查账户列表：用户名非空、不含非法字符、不超过 20 个字符且不重复
+pub fn validate_accounts(accounts: &[LocalAccount]) -> Result<()> {
+    const INVALID_CHARS: &[char] = &[
+        '"', '/', '\\', '[', ']', ':', ';', '|', '=', ',', '+', '*', '?', '<', '>', '@',
+    ];
+
+    for (i, account) in accounts.iter().enumerate() {
+        let name = account.name.as_str();
+        if name.is_empty() {
+            anyhow::bail!("第 {} 个账户的用户名为空", i + 1);
+        }
+        if name.chars().count() > 20 {
+            anyhow::bail!("用户名 {} 超过 20 个字符", name);
+        }
+        if name.contains(INVALID_CHARS) || name.trim_end_matches('.').is_empty() {
+            anyhow::bail!("用户名 {} 包含非法字符", name);
+        }
+        if name.eq_ignore_ascii_case("Administrator") || name.eq_ignore_ascii_case("Guest") {
+            anyhow::bail!("用户名 {} 与内置账户冲突", name);
+        }
+        if accounts[..i].iter().any(|a| a.name.eq_ignore_ascii_case(name)) {
+            anyhow::bail!("用户名 {} 重复", name);
+        }
+    }
+    Ok(())
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(TABLE[(n >> 18) as usize & 63] as char);
+        out.push(TABLE[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { TABLE[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { TABLE[n as usize & 63] as char } else { '=' });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_password() {
+        // "abc" + "Password" 的 UTF-16LE Base64
+        assert_eq!(
+            EncodedPassword::for_account("abc").as_str(),
+            "YQBiAGMAUABhAHMAcwB3AG8AcgBkAA=="
+        );
+        assert_eq!(
+            EncodedPassword::for_administrator("abc").as_str(),
+            "YQBiAGMAQQBkAG0AaQBuAGkAcwB0AHIAYQB0AG8AcgBQAGEAcwBzAHcAbwByAGQA"
+        );
+        assert!(EncodedPassword::for_account("").is_empty());
+    }
+
+    #[test]
+    fn test_account_config_roundtrip() {
+        let account = LocalAccount::new("张三", "Users", "p@ss|word");
+        let value = account.to_config_value();
+        assert!(!value.contains("p@ss"));
+        assert_eq!(LocalAccount::from_config_value(&value), Some(account));
+        assert_eq!(LocalAccount::from_config_value("|Users|"), None);
+    }
+
+    #[test]
+    fn test_validate_accounts() {
+        let ok = [LocalAccount::new("User", "Administrators", ""), LocalAccount::new("Guest1", "Users", "")];
+        assert!(validate_accounts(&ok).is_ok());
+        assert!(validate_accounts(&[LocalAccount::new("a/b", "Users", "")]).is_err());
+        assert!(validate_accounts(&[LocalAccount::new("administrator", "Users", "")]).is_err());
+        let dup = [LocalAccount::new("Tom", "Users", ""), LocalAccount::new("tom", "Users", "")];
+        assert!(validate_accounts(&dup).is_err());
+    }
+}
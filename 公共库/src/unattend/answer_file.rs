@@ -0,0 +1,453 @@
+//! LetRecovery 安装流程使用的标准应答文件
+
+use anyhow::Result;
+
+use super::schema::{DEPLOYMENT, INTERNATIONAL_CORE, SETUP, SHELL_SETUP};
+use super::lint::{has_errors, lint, Diagnostic, Severity};
+use super::merge::{merge_answer_file, MergeConflict};
+use super::accounts::{validate_accounts, BuiltinAdministrator, EncodedPassword, LocalAccount};
+use super::regional::validate_computer_name;
+use super::xml::Element;
+use super::{Architecture, Component, Pass, Unattend};
+use crate::software::SOFTWARE_SCRIPT;
+use crate::SCRIPTS_DIR;
+
+/// 生成应答文件所需的选项
+#[derive(Debug, Clone, Default)]
+pub struct AnswerFileOptions {
+    /// 目标系统架构
+    pub architecture: Architecture,
+    /// 本地账户（为空时创建无密码的管理员账户 "User"），第一个账户用于自动登录
+    pub accounts: Vec<LocalAccount>,
+    /// 不自动登录第一个账户
+    pub disable_auto_logon: bool,
+    /// 内置 Administrator 账户
+    pub builtin_administrator: BuiltinAdministrator,
+    /// 内置 Administrator 密码（仅启用时写入）
+    pub administrator_password: EncodedPassword,
+    /// 首次登录时删除预装UWP应用
+    pub remove_uwp_apps: bool,
+    /// 首次登录时运行软件安装脚本
+    pub install_software: bool,
+
+    /// 界面语言，如 "zh-CN"（为空时不设置，下同）
+    pub ui_language: String,
+    /// 键盘/输入法，如 "0804:00000804"
+    pub input_locale: String,
+    /// 系统区域（同时作为用户区域）
+    pub system_locale: String,
+    /// 时区 ID，如 "China Standard Time"
+    pub time_zone: String,
+    /// 计算机名（已展开模板；为空时由安装程序随机生成）
+    pub computer_name: String,
+    /// 产品密钥
+    pub product_key: String,
+    /// 组织名称
+    pub organization: String,
+}
+
+/// 构建 LetRecovery 标准应答文件
+///
+/// - windowsPE: 接受许可协议
+/// - specialize: 计算机名、时区、组织、产品密钥、部署脚本、内置管理员
+/// - oobeSystem: 区域设置、跳过 OOBE、本地账户、自动登录、首次登录命令
+pub fn build_answer_file(options: &AnswerFileOptions) -> Result<Unattend> {
+    let default_accounts;
+    let accounts = if options.accounts.is_empty() {
+        default_accounts = [LocalAccount::new("User", "Administrators", "")];
+        &default_accounts[..]
+    } else {
+        validate_accounts(&options.accounts)?;
+        &options.accounts[..]
+    };
+
+    let mut unattend = Unattend::new(options.architecture);
+
+    // windowsPE
+    let setup = unattend.component_mut(Pass::WindowsPE, SETUP)?;
+    setup.settings.push(
+        Element::new("UserData")
+            .child(Element::new("ProductKey").text_child("WillShowUI", "OnError"))
+            .text_child("AcceptEula", "true"),
+    );
+
+    // specialize
+    let shell = unattend.component_mut(Pass::Specialize, SHELL_SETUP)?;
+    let computer_name = if options.computer_name.is_empty() {
+        "*"
+    } else {
+        options.computer_name.as_str()
+    };
+    validate_computer_name(computer_name)?;
+    shell.set_text("ComputerName", computer_name);
+    set_text_if_present(shell, "TimeZone", &options.time_zone);
+    set_text_if_present(shell, "RegisteredOrganization", &options.organization);
+    set_text_if_present(shell, "ProductKey", &options.product_key);
+
+    let deployment = unattend.component_mut(Pass::Specialize, DEPLOYMENT)?;
+    add_run_synchronous(
+        deployment,
+        &format!(
+            "cmd /c if exist %SystemDrive%\\{0}\\deploy.bat call %SystemDrive%\\{0}\\deploy.bat",
+            SCRIPTS_DIR
+        ),
+        "Run custom deploy script",
+    );
+    match options.builtin_administrator {
+        BuiltinAdministrator::Unchanged => {}
+        BuiltinAdministrator::Enabled => add_run_synchronous(
+            deployment,
+            &builtin_administrator_command("Enable-LocalUser"),
+            "Enable built-in Administrator",
+        ),
+        BuiltinAdministrator::Disabled => add_run_synchronous(
+            deployment,
+            &builtin_administrator_command("Disable-LocalUser"),
+            "Disable built-in Administrator",
+        ),
+    }
+
+    // oobeSystem
+    if !options.ui_language.is_empty()
+        || !options.input_locale.is_empty()
+        || !options.system_locale.is_empty()
+    {
+        let international = unattend.component_mut(Pass::OobeSystem, INTERNATIONAL_CORE)?;
+        set_text_if_present(international, "InputLocale", &options.input_locale);
+        set_text_if_present(international, "SystemLocale", &options.system_locale);
+        set_text_if_present(international, "UILanguage", &options.ui_language);
+        set_text_if_present(international, "UserLocale", &options.system_locale);
+    }
+
+    let shell = unattend.component_mut(Pass::OobeSystem, SHELL_SETUP)?;
+    shell.settings.push(
+        Element::new("OOBE")
+            .text_child("HideEULAPage", "true")
+            .text_child("HideLocalAccountScreen", "true")
+            .text_child("HideOEMRegistrationScreen", "true")
+            .text_child("HideOnlineAccountScreens", "true")
+            .text_child("HideWirelessSetupInOOBE", "true")
+            .text_child("ProtectYourPC", "3")
+            .text_child("SkipMachineOOBE", "true")
+            .text_child("SkipUserOOBE", "true"),
+    );
+    let mut user_accounts = Element::new("UserAccounts");
+    if options.builtin_administrator == BuiltinAdministrator::Enabled
+        && !options.administrator_password.is_empty()
+    {
+        let mut password = password_element(&options.administrator_password);
+        password.name = "AdministratorPassword".to_string();
+        user_accounts.push(password);
+    }
+    let local_accounts = user_accounts.find_or_insert("LocalAccounts");
+    for account in accounts {
+        local_accounts.push(
+            Element::new("LocalAccount")
+                .attr("wcm:action", "add")
+                .child(password_element(&account.password))
+                .text_child("Description", "Local User")
+                .text_child("DisplayName", &account.name)
+                .text_child("Group", &account.group)
+                .text_child("Name", &account.name),
+        );
+    }
+    shell.settings.push(user_accounts);
+
+    if !options.disable_auto_logon {
+        let account = &accounts[0];
+        shell.settings.push(
+            Element::new("AutoLogon")
+                .child(password_element(&account.password))
+                .text_child("Enabled", "true")
+                .text_child("LogonCount", "1")
+                .text_child("Username", &account.name),
+        );
+    }
+
+    add_first_logon_command(
+        shell,
+        &format!(
+            "cmd /c if exist %SystemDrive%\\{0}\\firstlogon.bat call %SystemDrive%\\{0}\\firstlogon.bat",
+            SCRIPTS_DIR
+        ),
+        "Run first login script",
+    );
+    if options.remove_uwp_apps {
+        add_first_logon_command(
+            shell,
+            &format!(
+                "powershell -ExecutionPolicy Bypass -File %SystemDrive%\\{}\\remove_uwp.ps1",
+                SCRIPTS_DIR
+            ),
+            "Remove preinstalled UWP apps",
+        );
+    }
+    if options.install_software {
+        add_first_logon_command(
+            shell,
+            &format!(
+                "cmd /c call %SystemDrive%\\{}\\{}",
+                SCRIPTS_DIR, SOFTWARE_SCRIPT
+            ),
+            "Install software",
+        );
+    }
+    // 清理脚本目录（最后执行）
+    add_first_logon_command(
+        shell,
+        &format!("cmd /c rd /s /q %SystemDrive%\\{}", SCRIPTS_DIR),
+        "Cleanup scripts directory",
+    );
+
+    Ok(unattend)
+}
+
+/// 最终写入目标系统的应答文件
+#[derive(Debug, Clone)]
+pub struct RenderedAnswerFile {
+    pub xml: String,
+    /// 与用户应答文件合并时的冲突
+    pub conflicts: Vec<MergeConflict>,
+    /// 检查发现的警告
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// 生成最终写入目标系统的应答文件内容
+///
+/// 指定用户应答文件时，先检查用户文件（行号对应该文件），再将生成的设置合并进去并记录合并冲突。
+/// 存在错误时返回错误而不是写出无法使用的应答文件。
+pub fn render_answer_file(options: &AnswerFileOptions, user_file: Option<&str>) -> Result<RenderedAnswerFile> {
+    let unattend = build_answer_file(options)?;
+    let (xml, conflicts, warnings) = match user_file.filter(|p| !p.is_empty()) {
+        None => {
+            let xml = unattend.to_xml();
+            let warnings = check_lint(&xml, "生成的应答文件")?;
+            (xml, Vec::new(), warnings)
+        }
+        Some(user_file) => {
+            let bytes = std::fs::read(user_file)
+                .map_err(|e| anyhow::anyhow!("读取应答文件 {} 失败: {}", user_file, e))?;
+            let content = super::xml::decode(&bytes)?;
+            let warnings = check_lint(&content, &format!("应答文件 {}", user_file))?;
+            let merged = merge_answer_file(&content, &unattend)
+                .map_err(|e| anyhow::anyhow!("解析应答文件 {} 失败: {}", user_file, e))?;
+            for conflict in &merged.conflicts {
+                log::warn!("[UNATTEND] 合并冲突 {}", conflict);
+            }
+            // 合并结果的行号与用户文件对不上，只用于拦截错误
+            check_lint(&merged.xml, "合并后的应答文件")?;
+            (merged.xml, merged.conflicts, warnings)
+        }
+    };
+
+    for warning in &warnings {
+        log::warn!("[UNATTEND] {}", warning);
+    }
+
+    Ok(RenderedAnswerFile { xml, conflicts, warnings })
+}
+
+/// 检查应答文件内容，存在错误时返回错误，否则返回警告
+fn check_lint(content: &str, source: &str) -> Result<Vec<Diagnostic>> {
+    let diagnostics = lint(content);
+    if has_errors(&diagnostics) {
+        let errors: Vec<String> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| d.to_string())
+            .collect();
+        anyhow::bail!("{}检查未通过:\n{}", source, errors.join("\n"));
+    }
+    Ok(diagnostics)
+}
+
+/// 将应答文件写入目标系统
+///
+/// 写入 `Windows\Panther\unattend.xml`，Sysprep 目录存在时同时写入一份。
+pub fn write_answer_file(target_partition: &str, xml: &str) -> Result<()> {
+    let panther_dir = format!("{}\\Windows\\Panther", target_partition);
+    std::fs::create_dir_all(&panther_dir)?;
+
+    let unattend_path = format!("{}\\unattend.xml", panther_dir);
+    std::fs::write(&unattend_path, xml)?;
+    log::info!("[UNATTEND] 已写入: {}", unattend_path);
+
+    let sysprep_dir = format!("{}\\Windows\\System32\\Sysprep", target_partition);
+    if std::path::Path::new(&sysprep_dir).exists() {
+        let sysprep_unattend = format!("{}\\unattend.xml", sysprep_dir);
+        if let Err(e) = std::fs::write(&sysprep_unattend, xml) {
+            log::warn!("[UNATTEND] 写入 {} 失败: {}", sysprep_unattend, e);
+        } else {
+            log::info!("[UNATTEND] 已写入: {}", sysprep_unattend);
+        }
+    }
+
+    Ok(())
+}
+
+/// 对内置 Administrator 执行 PowerShell 命令
+///
+/// 部分语言的系统中该账户名称不同（如法语 "Administrateur"），也可能已被改名，按众所周知的 SID（以 -500 结尾）查找。
+fn builtin_administrator_command(cmdlet: &str) -> String {
+    format!(
+        "powershell -NoProfile -Command \"Get-LocalUser | Where-Object {{ $_.SID.Value -like '*-500' }} | {}\"",
+        cmdlet
+    )
+}
+
+/// 值非空时设置文本设置项
+fn set_text_if_present(component: &mut Component, name: &str, value: &str) {
+    let value = value.trim();
+    if !value.is_empty() {
+        component.set_text(name, value);
+    }
+}
+
+/// 账户密码元素（无密码时保持空明文，否则写入编码后的值）
+fn password_element(password: &EncodedPassword) -> Element {
+    let plain_text = if password.is_empty() { "true" } else { "false" };
+    Element::new("Password")
+        .text_child("Value", password.as_str())
+        .text_child("PlainText", plain_text)
+}
+
+/// 添加 specialize 阶段的同步命令，Order 自动递增
+fn add_run_synchronous(component: &mut Component, path: &str, description: &str) {
+    let list = component.setting_mut("RunSynchronous");
+    let order = list.elements().count() + 1;
+    list.push(
+        Element::new("RunSynchronousCommand")
+            .attr("wcm:action", "add")
+            .text_child("Order", &order.to_string())
+            .text_child("Path", path)
+            .text_child("Description", description),
+    );
+}
+
+/// 添加首次登录命令，Order 自动递增
+fn add_first_logon_command(component: &mut Component, command: &str, description: &str) {
+    let list = component.setting_mut("FirstLogonCommands");
+    let order = list.elements().count() + 1;
+    list.push(
+        Element::new("SynchronousCommand")
+            .attr("wcm:action", "add")
+            .text_child("Order", &order.to_string())
+            .text_child("CommandLine", command)
+            .text_child("Description", description),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_answer_file_golden() {
+        let options = AnswerFileOptions::default();
+        let xml = build_answer_file(&options).unwrap().to_xml();
+        assert_eq!(xml, include_str!("testdata/default_amd64.xml"));
+    }
+
+    #[test]
+    fn test_answer_file_with_uwp_arm64_golden() {
+        let options = AnswerFileOptions {
+            architecture: Architecture::Arm64,
+            accounts: vec![LocalAccount::new("Tom & Jerry", "Administrators", "")],
+            remove_uwp_apps: true,
+            ..Default::default()
+        };
+        let xml = build_answer_file(&options).unwrap().to_xml();
+        assert_eq!(xml, include_str!("testdata/uwp_arm64.xml"));
+    }
+
+    #[test]
+    fn test_answer_file_installs_software_before_cleanup() {
+        let options = AnswerFileOptions {
+            install_software: true,
+            ..Default::default()
+        };
+        let xml = build_answer_file(&options).unwrap().to_xml();
+        let install = xml.find("LetRecovery_Scripts\\software.bat").unwrap();
+        let cleanup = xml.find("rd /s /q").unwrap();
+        assert!(install < cleanup);
+        assert!(xml.contains("<Order>3</Order>"));
+    }
+
+    #[test]
+    fn test_answer_file_regional_golden() {
+        let options = AnswerFileOptions {
+            accounts: vec![LocalAccount::new("Admin", "Administrators", "")],
+            ui_language: "zh-CN".to_string(),
+            input_locale: "0804:00000804".to_string(),
+            system_locale: "zh-CN".to_string(),
+            time_zone: "China Standard Time".to_string(),
+            computer_name: "PC-5CD1234XYZ".to_string(),
+            product_key: "VK7JG-NPHTM-C97JM-9MPGT-3V66T".to_string(),
+            organization: "Contoso".to_string(),
+            ..Default::default()
+        };
+        let xml = build_answer_file(&options).unwrap().to_xml();
+        assert_eq!(xml, include_str!("testdata/regional_zh_cn.xml"));
+
+        // 未展开的计算机名模板不能写入应答文件
+        let options = AnswerFileOptions {
+            computer_name: "PC-{serial}".to_string(),
+            ..options
+        };
+        assert!(build_answer_file(&options).is_err());
+    }
+
+    #[test]
+    fn test_answer_file_accounts_golden() {
+        let options = AnswerFileOptions {
+            accounts: vec![
+                LocalAccount::new("Owner", "Administrators", "abc"),
+                LocalAccount::new("Kid", "Users", ""),
+            ],
+            disable_auto_logon: true,
+            builtin_administrator: BuiltinAdministrator::Enabled,
+            administrator_password: EncodedPassword::for_administrator("abc"),
+            ..Default::default()
+        };
+        let xml = build_answer_file(&options).unwrap().to_xml();
+        assert!(!xml.contains(">abc<"));
+        assert_eq!(xml, include_str!("testdata/accounts.xml"));
+
+        let invalid = AnswerFileOptions {
+            accounts: vec![LocalAccount::new("a:b", "Users", "")],
+            ..Default::default()
+        };
+        assert!(build_answer_file(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_render_lints_user_file() {
+        let path = std::env::temp_dir().join(format!("letrecovery_user_unattend_{}.xml", std::process::id()));
+        let user_file = path.to_string_lossy().to_string();
+        let options = AnswerFileOptions::default();
+
+        // 行号对应用户文件，而不是合并后的输出
+        std::fs::write(&path, include_str!("testdata/lint_bad.xml")).unwrap();
+        let error = render_answer_file(&options, Some(&user_file)).unwrap_err().to_string();
+        assert!(error.contains(&user_file), "{}", error);
+        assert!(error.contains("第 2 行"), "{}", error);
+
+        std::fs::write(
+            &path,
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+                "<unattend xmlns=\"urn:schemas-microsoft-com:unattend\">\n",
+                "    <settings pass=\"oobeSystem\">\n",
+                "        <component name=\"Contoso-Custom\" processorArchitecture=\"amd64\" publicKeyToken=\"31bf3856ad364e35\" language=\"neutral\" versionScope=\"nonSxS\">\n",
+                "        </component>\n",
+                "    </settings>\n",
+                "</unattend>\n",
+            ),
+        )
+        .unwrap();
+        let rendered = render_answer_file(&options, Some(&user_file)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<_> = rendered.warnings.iter().map(|w| (w.line, w.severity)).collect();
+        assert_eq!(lines, [(4, Severity::Warning)]);
+    }
+}
@@ -0,0 +1,313 @@
+//! 应答文件检查
+//!
+//! 在写入目标系统前发现 Windows 安装程序无法处理的问题，
+//! 避免在磁盘已被清空后才陷入 "无法分析或处理应答文件" 的重启循环。
+
+use std::collections::HashMap;
+
+use super::schema::{self, UNATTEND_NAMESPACE};
+use super::xml::{self, Element, ParseError};
+use super::Pass;
+
+/// 合法的 processorArchitecture 取值
+const VALID_ARCHITECTURES: &[&str] = &["x86", "amd64", "arm64", "arm", "wow64"];
+
+/// 组件必须具备的属性
+const REQUIRED_COMPONENT_ATTRS: &[&str] = &[
+    "name",
+    "processorArchitecture",
+    "publicKeyToken",
+    "language",
+    "versionScope",
+];
+
+/// 问题级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// 会导致安装程序报错
+    Error,
+    /// 可能不会生效
+    Warning,
+}
+
+/// 检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 所在行号
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "错误",
+            Severity::Warning => "警告",
+        };
+        write!(f, "第 {} 行: {}: {}", self.line, level, self.message)
+    }
+}
+
+/// 检查应答文件，按行号排序返回所有问题
+pub fn lint(content: &str) -> Vec<Diagnostic> {
+    let root = match xml::parse(content) {
+        Ok(root) => root,
+        Err(e) => {
+            let (line, message) = match e.downcast_ref::<ParseError>() {
+                Some(pe) => (pe.line, format!("XML 格式错误: {}", pe.message)),
+                None => (1, format!("XML 格式错误: {}", e)),
+            };
+            return vec![Diagnostic {
+                line,
+                severity: Severity::Error,
+                message,
+            }];
+        }
+    };
+
+    let mut linter = Linter::default();
+    linter.check_root(&root);
+    linter.diagnostics.sort_by_key(|d| d.line);
+    linter.diagnostics
+}
+
+/// 是否存在错误级别的问题
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+#[derive(Default)]
+struct Linter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn report(&mut self, line: usize, severity: Severity, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity,
+            message,
+        });
+    }
+
+    fn check_root(&mut self, root: &Element) {
+        if root.name != "unattend" {
+            self.report(
+                root.line,
+                Severity::Error,
+                format!("根元素应为 <unattend>，实际为 <{}>", root.name),
+            );
+            return;
+        }
+        if root.get_attr("xmlns") != Some(UNATTEND_NAMESPACE) {
+            self.report(
+                root.line,
+                Severity::Error,
+                format!("<unattend> 缺少命名空间 xmlns=\"{}\"", UNATTEND_NAMESPACE),
+            );
+        }
+
+        self.check_namespaces(root, &[]);
+
+        for settings in root.elements() {
+            if settings.name != "settings" {
+                if settings.name != "servicing" {
+                    self.report(
+                        settings.line,
+                        Severity::Warning,
+                        format!("未知元素 <{}>", settings.name),
+                    );
+                }
+                continue;
+            }
+            let pass = match settings.get_attr("pass") {
+                None => {
+                    self.report(settings.line, Severity::Error, "<settings> 缺少 pass 属性".to_string());
+                    continue;
+                }
+                Some(value) => match Pass::parse(value) {
+                    Some(pass) => pass,
+                    None => {
+                        self.report(settings.line, Severity::Error, format!("未知的配置阶段 \"{}\"", value));
+                        continue;
+                    }
+                },
+            };
+            self.check_settings(settings, pass);
+        }
+    }
+
+    fn check_settings(&mut self, settings: &Element, pass: Pass) {
+        let mut seen: HashMap<(String, String), usize> = HashMap::new();
+
+        for component in settings.elements() {
+            if component.name != "component" {
+                self.report(
+                    component.line,
+                    Severity::Error,
+                    format!("<settings> 下只能包含 <component>，发现 <{}>", component.name),
+                );
+                continue;
+            }
+
+            for attr in REQUIRED_COMPONENT_ATTRS {
+                if component.get_attr(attr).is_none() {
+                    self.report(component.line, Severity::Error, format!("组件缺少 {} 属性", attr));
+                }
+            }
+            let Some(name) = component.get_attr("name") else {
+                continue;
+            };
+
+            let arch = component.get_attr("processorArchitecture").unwrap_or("");
+            if !arch.is_empty() && !VALID_ARCHITECTURES.contains(&arch) {
+                self.report(
+                    component.line,
+                    Severity::Error,
+                    format!("组件 {} 的 processorArchitecture \"{}\" 无效", name, arch),
+                );
+            }
+
+            match schema::allowed_passes(name) {
+                None => self.report(
+                    component.line,
+                    Severity::Warning,
+                    format!("未收录的组件 {}，无法检查其适用阶段", name),
+                ),
+                Some(passes) if !passes.contains(&pass) => self.report(
+                    component.line,
+                    Severity::Error,
+                    format!(
+                        "组件 {} 不能出现在 {} 阶段（适用: {}）",
+                        name,
+                        pass.as_str(),
+                        passes.iter().map(|p| p.as_str()).collect::<Vec<_>>().join(", ")
+                    ),
+                ),
+                Some(_) => {}
+            }
+
+            let key = (name.to_string(), arch.to_string());
+            if let Some(first_line) = seen.get(&key) {
+                self.report(
+                    component.line,
+                    Severity::Error,
+                    format!("组件 {} ({}) 在 {} 阶段重复，首次出现于第 {} 行", name, arch, pass.as_str(), first_line),
+                );
+            } else {
+                seen.insert(key, component.line);
+            }
+
+            self.check_orders(component);
+        }
+    }
+
+    /// 检查带 Order 的命令列表（SynchronousCommand、RunSynchronousCommand 等）
+    fn check_orders(&mut self, element: &Element) {
+        let mut orders: HashMap<u32, usize> = HashMap::new();
+        for child in element.elements() {
+            if let Some(order) = child.find("Order") {
+                let text = order.text_content();
+                match text.trim().parse::<u32>() {
+                    Ok(value) if value > 0 => {
+                        if let Some(first_line) = orders.get(&value) {
+                            self.report(
+                                order.line,
+                                Severity::Error,
+                                format!(
+                                    "<{}> 中 Order {} 重复，首次出现于第 {} 行",
+                                    element.name, value, first_line
+                                ),
+                            );
+                        } else {
+                            orders.insert(value, order.line);
+                        }
+                    }
+                    _ => self.report(
+                        order.line,
+                        Severity::Error,
+                        format!("Order 必须是正整数，实际为 \"{}\"", text.trim()),
+                    ),
+                }
+            }
+            self.check_orders(child);
+        }
+    }
+
+    /// 检查使用的命名空间前缀是否已声明
+    fn check_namespaces(&mut self, element: &Element, inherited: &[String]) {
+        let mut declared = inherited.to_vec();
+        for (name, _) in &element.attributes {
+            if let Some(prefix) = name.strip_prefix("xmlns:") {
+                declared.push(prefix.to_string());
+            }
+        }
+
+        let element_prefix = element.name.split_once(':').map(|(p, _)| p);
+        let attr_prefixes = element
+            .attributes
+            .iter()
+            .filter_map(|(n, _)| n.split_once(':').map(|(p, _)| p))
+            .filter(|p| *p != "xmlns" && *p != "xml");
+        for prefix in element_prefix.into_iter().chain(attr_prefixes) {
+            if !declared.iter().any(|d| d == prefix) {
+                self.report(
+                    element.line,
+                    Severity::Error,
+                    format!("<{}> 使用了未声明的命名空间前缀 {}:", element.name, prefix),
+                );
+            }
+        }
+
+        for child in element.elements() {
+            self.check_namespaces(child, &declared);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unattend::{build_answer_file, AnswerFileOptions};
+
+    #[test]
+    fn test_generated_file_is_clean() {
+        let xml = build_answer_file(&AnswerFileOptions {
+            remove_uwp_apps: true,
+            ..Default::default()
+        })
+        .unwrap()
+        .to_xml();
+        assert_eq!(lint(&xml), []);
+    }
+
+    #[test]
+    fn test_lint_diagnostics() {
+        let diagnostics = lint(include_str!("testdata/lint_bad.xml"));
+        let found: Vec<_> = diagnostics.iter().map(|d| (d.line, d.severity)).collect();
+        assert_eq!(
+            found,
+            [
+                (2, Severity::Error),    // 缺少 unattend 命名空间
+                (3, Severity::Error),    // 未知阶段
+                (7, Severity::Error),    // Shell-Setup 不能出现在 windowsPE
+                (8, Severity::Error),    // 无效架构
+                (13, Severity::Error),   // 未声明 wcm 前缀
+                (17, Severity::Error),   // Order 重复
+                (21, Severity::Warning), // 未收录组件
+            ],
+            "{:#?}",
+            diagnostics
+        );
+        assert!(has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn test_lint_parse_error_line() {
+        let diagnostics = lint("<unattend>\n<settings>\n</unattend>");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 3);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+}
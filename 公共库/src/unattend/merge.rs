@@ -0,0 +1,237 @@
+//! 将 LetRecovery 生成的设置合并到用户提供的应答文件
+//!
+//! 只合并必需部分：部署脚本和首次登录命令（含脚本目录清理）追加到对应
+//! 命令列表末尾并重新编号 Order。其余设置（账户、自动登录、OOBE 等）
+//! 一律以用户文件为准，不写入；用户文件中取值不同的记为冲突。
+
+use anyhow::Result;
+
+use super::schema::{DEPLOYMENT, SHELL_SETUP, WCM_NAMESPACE};
+use super::xml::{self, Element, Node};
+use super::{Architecture, Component, Pass, Unattend};
+
+/// 必须合并的命令列表：(组件, 设置项, 命令所在子元素)
+const MERGED_LISTS: &[(&str, &str, &str)] = &[
+    (DEPLOYMENT, "RunSynchronous", "Path"),
+    (SHELL_SETUP, "FirstLogonCommands", "CommandLine"),
+];
+
+/// 合并冲突
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// 位置，如 `oobeSystem/Microsoft-Windows-Shell-Setup/AutoLogon`
+    pub location: String,
+    /// 说明
+    pub message: String,
+}
+
+impl std::fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// 合并结果
+#[derive(Debug, Clone)]
+pub struct MergedAnswerFile {
+    /// 合并后的应答文件
+    pub xml: String,
+    /// 合并冲突（用户设置已保留，LetRecovery 的对应设置未写入）
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// 将生成的应答文件中的必需命令合并到用户应答文件中
+pub fn merge_answer_file(user_xml: &str, generated: &Unattend) -> Result<MergedAnswerFile> {
+    let mut root = xml::parse(user_xml)?;
+    if root.name != "unattend" {
+        anyhow::bail!("根元素应为 <unattend>，实际为 <{}>", root.name);
+    }
+    if root.get_attr("xmlns:wcm").is_none() {
+        root.set_attr("xmlns:wcm", WCM_NAMESPACE);
+    }
+
+    let arch = generated.architecture;
+    let mut conflicts = Vec::new();
+
+    let has_arch = root
+        .elements()
+        .filter(|e| e.name == "settings")
+        .flat_map(|s| s.elements())
+        .any(|c| c.name == "component" && is_arch(c, arch));
+    if !has_arch && root.elements().any(|e| e.name == "settings") {
+        conflicts.push(MergeConflict {
+            location: "unattend".to_string(),
+            message: format!("应答文件中没有 {} 架构的组件，其中的设置不会对目标系统生效", arch),
+        });
+    }
+
+    for (pass, components) in generated.passes() {
+        for component in components {
+            for setting in &component.settings {
+                let list = MERGED_LISTS
+                    .iter()
+                    .find(|(c, s, _)| *c == component.name && *s == setting.name);
+                match list {
+                    Some((_, _, command_key)) => {
+                        let target = component_element(&mut root, pass, component, arch);
+                        match target.find_mut(&setting.name) {
+                            Some(existing) => append_commands(existing, setting, command_key),
+                            None => target.push(setting.clone()),
+                        }
+                    }
+                    None => {
+                        let differs = find_component(&root, pass, &component.name, arch)
+                            .and_then(|c| c.find(&setting.name))
+                            .is_some_and(|existing| existing != setting);
+                        if differs {
+                            conflicts.push(MergeConflict {
+                                location: format!("{}/{}/{}", pass.as_str(), component.name, setting.name),
+                                message: "应答文件中已有不同的设置，保留应答文件中的值".to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(MergedAnswerFile {
+        xml: xml::to_document(&root),
+        conflicts,
+    })
+}
+
+fn is_arch(component: &Element, arch: Architecture) -> bool {
+    component
+        .get_attr("processorArchitecture")
+        .is_some_and(|a| a.eq_ignore_ascii_case(arch.as_str()))
+}
+
+/// 查找用户文件中对应阶段、同名同架构的组件
+fn find_component<'a>(root: &'a Element, pass: Pass, name: &str, arch: Architecture) -> Option<&'a Element> {
+    root.elements()
+        .filter(|e| e.name == "settings" && e.get_attr("pass") == Some(pass.as_str()))
+        .flat_map(|s| s.elements())
+        .find(|c| c.name == "component" && c.get_attr("name") == Some(name) && is_arch(c, arch))
+}
+
+/// 查找用户文件中对应阶段、同名同架构的组件，不存在时创建
+fn component_element<'a>(
+    root: &'a mut Element,
+    pass: Pass,
+    component: &Component,
+    arch: Architecture,
+) -> &'a mut Element {
+    let settings_pos = root.children.iter().position(
+        |n| matches!(n, Node::Element(e) if e.name == "settings" && e.get_attr("pass") == Some(pass.as_str())),
+    );
+    let settings_pos = match settings_pos {
+        Some(pos) => pos,
+        None => {
+            // 按配置阶段顺序插入
+            let pos = root
+                .children
+                .iter()
+                .position(|n| {
+                    matches!(n, Node::Element(e) if e.name == "settings"
+                        && e.get_attr("pass").and_then(Pass::parse).is_some_and(|p| p > pass))
+                })
+                .unwrap_or(root.children.len());
+            let settings = Element::new("settings").attr("pass", pass.as_str());
+            root.children.insert(pos, Node::Element(settings));
+            pos
+        }
+    };
+    let Node::Element(settings) = &mut root.children[settings_pos] else {
+        unreachable!()
+    };
+
+    let component_pos = settings.children.iter().position(|n| {
+        matches!(n, Node::Element(e) if e.name == "component"
+            && e.get_attr("name") == Some(component.name.as_str())
+            && is_arch(e, arch))
+    });
+    let component_pos = match component_pos {
+        Some(pos) => pos,
+        None => {
+            let empty = Component {
+                name: component.name.clone(),
+                settings: Vec::new(),
+            };
+            settings.push(empty.to_element(arch));
+            settings.children.len() - 1
+        }
+    };
+    match &mut settings.children[component_pos] {
+        Node::Element(e) => e,
+        Node::Text(_) => unreachable!(),
+    }
+}
+
+/// 将命令追加到用户的命令列表末尾，跳过已存在的相同命令
+fn append_commands(existing: &mut Element, generated: &Element, command_key: &str) {
+    let command_of = |e: &Element| e.find(command_key).map(|c| c.text_content());
+
+    let mut next_order = existing
+        .elements()
+        .filter_map(|e| e.find("Order"))
+        .filter_map(|o| o.text_content().trim().parse::<u32>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    for command in generated.elements() {
+        let text = command_of(command);
+        if existing.elements().any(|e| command_of(e) == text) {
+            continue;
+        }
+        let mut command = command.clone();
+        if let Some(order) = command.find_mut("Order") {
+            *order = Element::text("Order", &next_order.to_string());
+        }
+        next_order += 1;
+        existing.push(command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unattend::{build_answer_file, AnswerFileOptions};
+
+    #[test]
+    fn test_merge_golden() {
+        let options = AnswerFileOptions {
+            computer_name: "LR-PC".to_string(),
+            remove_uwp_apps: true,
+            ..Default::default()
+        };
+        let generated = build_answer_file(&options).unwrap();
+        let merged = merge_answer_file(include_str!("testdata/user_input.xml"), &generated).unwrap();
+        assert_eq!(merged.xml, include_str!("testdata/user_merged.xml"));
+        // 只合并命令，不加入账户、自动登录等用户文件中没有的设置
+        for setting in ["<UserAccounts>", "<AutoLogon>", "<UserData>", "<SkipMachineOOBE>"] {
+            assert!(!merged.xml.contains(setting), "{}", setting);
+        }
+
+        let locations: Vec<_> = merged.conflicts.iter().map(|c| c.location.as_str()).collect();
+        assert_eq!(
+            locations,
+            [
+                "specialize/Microsoft-Windows-Shell-Setup/ComputerName",
+                "oobeSystem/Microsoft-Windows-Shell-Setup/OOBE",
+            ]
+        );
+
+        // 重复合并不会重复追加命令
+        let again = merge_answer_file(&merged.xml, &generated).unwrap();
+        assert_eq!(again.xml, merged.xml);
+    }
+
+    #[test]
+    fn test_merge_rejects_non_unattend() {
+        let generated = build_answer_file(&AnswerFileOptions::default()).unwrap();
+        assert!(merge_answer_file("<root/>", &generated).is_err());
+        assert!(merge_answer_file("<unattend>", &generated).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! 无人值守应答文件 (unattend.xml)
+//!
+//! 以“配置阶段 → 组件 → 设置”的层次构建应答文件，输出时统一转义，
+//! 组件的 processorArchitecture 取自实际安装的镜像。
+
+pub mod accounts;
+mod answer_file;
+pub mod lint;
+pub mod merge;
+pub mod regional;
+pub mod schema;
+pub mod xml;
+
+pub use accounts::{BuiltinAdministrator, EncodedPassword, LocalAccount};
+pub use answer_file::{
+    build_answer_file, render_answer_file, write_answer_file, AnswerFileOptions, RenderedAnswerFile,
+};
+pub use merge::{merge_answer_file, MergeConflict};
+
+use anyhow::Result;
+
+use xml::Element;
+
+/// 配置阶段 (configuration pass)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pass {
+    WindowsPE,
+    OfflineServicing,
+    Generalize,
+    Specialize,
+    AuditSystem,
+    AuditUser,
+    OobeSystem,
+}
+
+impl Pass {
+    /// 所有配置阶段，按 Windows 安装程序的执行顺序排列
+    pub const ALL: [Pass; 7] = [
+        Pass::WindowsPE,
+        Pass::OfflineServicing,
+        Pass::Generalize,
+        Pass::Specialize,
+        Pass::AuditSystem,
+        Pass::AuditUser,
+        Pass::OobeSystem,
+    ];
+
+    /// 应答文件中 `pass` 属性的取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pass::WindowsPE => "windowsPE",
+            Pass::OfflineServicing => "offlineServicing",
+            Pass::Generalize => "generalize",
+            Pass::Specialize => "specialize",
+            Pass::AuditSystem => "auditSystem",
+            Pass::AuditUser => "auditUser",
+            Pass::OobeSystem => "oobeSystem",
+        }
+    }
+
+    /// 从 `pass` 属性解析
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == value)
+    }
+}
+
+/// 处理器架构
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Architecture {
+    X86,
+    #[default]
+    Amd64,
+    Arm64,
+}
+
+impl Architecture {
+    /// 应答文件中 `processorArchitecture` 属性的取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Architecture::X86 => "x86",
+            Architecture::Amd64 => "amd64",
+            Architecture::Arm64 => "arm64",
+        }
+    }
+
+    /// 从 PE 文件头的 Machine 字段转换
+    pub fn from_pe_machine(machine: u16) -> Option<Self> {
+        match machine {
+            0x014C => Some(Architecture::X86),
+            0x8664 => Some(Architecture::Amd64),
+            0xAA64 => Some(Architecture::Arm64),
+            _ => None,
+        }
+    }
+
+    /// 从 PE 可执行文件头部数据中识别架构
+    pub fn from_pe_image(data: &[u8]) -> Option<Self> {
+        if data.len() < 0x40 || &data[0..2] != b"MZ" {
+            return None;
+        }
+        let pe_offset = u32::from_le_bytes(data[0x3C..0x40].try_into().ok()?) as usize;
+        let header = data.get(pe_offset..pe_offset + 6)?;
+        if &header[0..4] != b"PE\0\0" {
+            return None;
+        }
+        Self::from_pe_machine(u16::from_le_bytes([header[4], header[5]]))
+    }
+
+    /// 检测已释放到目标分区的系统架构
+    ///
+    /// 读取目标分区中 ntoskrnl.exe 的 PE 文件头，WIM/ESD/GHO 镜像都适用。
+    pub fn detect_from_system(target_partition: &str) -> Option<Self> {
+        use std::io::Read;
+
+        let kernel_path = format!("{}\\Windows\\System32\\ntoskrnl.exe", target_partition);
+        let mut file = std::fs::File::open(&kernel_path).ok()?;
+        let mut header = vec![0u8; 4096];
+        let n = file.read(&mut header).ok()?;
+        header.truncate(n);
+
+        let arch = Self::from_pe_image(&header);
+        log::info!("[UNATTEND] 目标系统架构: {:?} ({})", arch, kernel_path);
+        arch
+    }
+}
+
+impl std::fmt::Display for Architecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// 应答文件中的组件
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub name: String,
+    /// 组件下的设置项
+    pub settings: Vec<Element>,
+}
+
+impl Component {
+    /// 获取设置项（不存在时创建）
+    pub fn setting_mut(&mut self, name: &str) -> &mut Element {
+        let pos = match self.settings.iter().position(|s| s.name == name) {
+            Some(pos) => pos,
+            None => {
+                self.settings.push(Element::new(name));
+                self.settings.len() - 1
+            }
+        };
+        &mut self.settings[pos]
+    }
+
+    /// 设置只包含文本的设置项（已存在则覆盖）
+    pub fn set_text(&mut self, name: &str, value: &str) {
+        *self.setting_mut(name) = Element::text(name, value);
+    }
+
+    fn to_element(&self, arch: Architecture) -> Element {
+        let mut element = Element::new("component")
+            .attr("name", &self.name)
+            .attr("processorArchitecture", arch.as_str())
+            .attr("publicKeyToken", schema::PUBLIC_KEY_TOKEN)
+            .attr("language", "neutral")
+            .attr("versionScope", "nonSxS")
+            .attr("xmlns:wcm", schema::WCM_NAMESPACE)
+            .attr("xmlns:xsi", schema::XSI_NAMESPACE);
+        for setting in &self.settings {
+            element.push(setting.clone());
+        }
+        element
+    }
+}
+
+/// 无人值守应答文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unattend {
+    pub architecture: Architecture,
+    passes: Vec<(Pass, Vec<Component>)>,
+}
+
+impl Unattend {
+    pub fn new(architecture: Architecture) -> Self {
+        Self {
+            architecture,
+            passes: Vec::new(),
+        }
+    }
+
+    /// 获取指定阶段中的组件（不存在时创建）
+    ///
+    /// 组件不在 [`schema`] 收录范围内、或不允许出现在该阶段时返回错误。
+    pub fn component_mut(&mut self, pass: Pass, name: &str) -> Result<&mut Component> {
+        if !schema::is_allowed(name, pass) {
+            anyhow::bail!("组件 {} 不能出现在 {} 阶段", name, pass.as_str());
+        }
+
+        let pass_pos = match self.passes.iter().position(|(p, _)| *p == pass) {
+            Some(pos) => pos,
+            None => {
+                self.passes.push((pass, Vec::new()));
+                self.passes.sort_by_key(|(p, _)| *p);
+                self.passes.iter().position(|(p, _)| *p == pass).unwrap()
+            }
+        };
+
+        let components = &mut self.passes[pass_pos].1;
+        let pos = match components.iter().position(|c| c.name == name) {
+            Some(pos) => pos,
+            None => {
+                components.push(Component {
+                    name: name.to_string(),
+                    settings: Vec::new(),
+                });
+                components.len() - 1
+            }
+        };
+        Ok(&mut components[pos])
+    }
+
+    /// 查找组件
+    pub fn component(&self, pass: Pass, name: &str) -> Option<&Component> {
+        self.passes
+            .iter()
+            .find(|(p, _)| *p == pass)
+            .and_then(|(_, components)| components.iter().find(|c| c.name == name))
+    }
+
+    /// 按执行顺序遍历各配置阶段及其组件
+    pub fn passes(&self) -> impl Iterator<Item = (Pass, &[Component])> {
+        self.passes.iter().map(|(pass, components)| (*pass, components.as_slice()))
+    }
+
+    /// 转换为 XML 元素树
+    pub fn to_element(&self) -> Element {
+        let mut root = Element::new("unattend")
+            .attr("xmlns", schema::UNATTEND_NAMESPACE)
+            .attr("xmlns:wcm", schema::WCM_NAMESPACE);
+        for (pass, components) in &self.passes {
+            let mut settings = Element::new("settings").attr("pass", pass.as_str());
+            for component in components {
+                settings.push(component.to_element(self.architecture));
+            }
+            root.push(settings);
+        }
+        root
+    }
+
+    /// 序列化为完整的 XML 文档
+    pub fn to_xml(&self) -> String {
+        xml::to_document(&self.to_element())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_component_pass_validation() {
+        let mut unattend = Unattend::new(Architecture::Amd64);
+        assert!(unattend.component_mut(Pass::WindowsPE, schema::SETUP).is_ok());
+        assert!(unattend.component_mut(Pass::WindowsPE, schema::SHELL_SETUP).is_err());
+        assert!(unattend.component_mut(Pass::OobeSystem, "Contoso-Unknown").is_err());
+    }
+
+    #[test]
+    fn test_passes_sorted() {
+        let mut unattend = Unattend::new(Architecture::X86);
+        unattend.component_mut(Pass::OobeSystem, schema::SHELL_SETUP).unwrap();
+        unattend.component_mut(Pass::WindowsPE, schema::SETUP).unwrap();
+        let root = unattend.to_element();
+        let passes: Vec<_> = root.elements().filter_map(|e| e.get_attr("pass")).collect();
+        assert_eq!(passes, ["windowsPE", "oobeSystem"]);
+    }
+
+    #[test]
+    fn test_arch_from_pe_image() {
+        let mut image = vec![0u8; 0x100];
+        image[0..2].copy_from_slice(b"MZ");
+        image[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        image[0x84..0x86].copy_from_slice(&0xAA64u16.to_le_bytes());
+        assert_eq!(Architecture::from_pe_image(&image), Some(Architecture::Arm64));
+
+        image[0x84..0x86].copy_from_slice(&0x014Cu16.to_le_bytes());
+        assert_eq!(Architecture::from_pe_image(&image), Some(Architecture::X86));
+
+        assert_eq!(Architecture::from_pe_image(b"not a pe"), None);
+    }
+}
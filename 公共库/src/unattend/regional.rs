@@ -0,0 +1,189 @@
+//! 区域、时区、计算机名与产品密钥相关的预设数据
+
+use anyhow::Result;
+
+/// 区域预设
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalePreset {
+    /// 显示名称
+    pub display_name: &'static str,
+    /// 界面语言 (UILanguage)
+    pub ui_language: &'static str,
+    /// 键盘/输入法 (InputLocale)
+    pub input_locale: &'static str,
+    /// 系统区域 (SystemLocale / UserLocale)
+    pub system_locale: &'static str,
+    /// 默认时区
+    pub time_zone: &'static str,
+}
+
+/// 常用区域预设
+pub const LOCALE_PRESETS: &[LocalePreset] = &[
+    LocalePreset {
+        display_name: "简体中文 (中国)",
+        ui_language: "zh-CN",
+        input_locale: "0804:00000804",
+        system_locale: "zh-CN",
+        time_zone: "China Standard Time",
+    },
+    LocalePreset {
+        display_name: "English (United States)",
+        ui_language: "en-US",
+        input_locale: "0409:00000409",
+        system_locale: "en-US",
+        time_zone: "Pacific Standard Time",
+    },
+    LocalePreset {
+        display_name: "繁體中文 (台灣)",
+        ui_language: "zh-TW",
+        input_locale: "0404:00000404",
+        system_locale: "zh-TW",
+        time_zone: "Taipei Standard Time",
+    },
+    LocalePreset {
+        display_name: "繁體中文 (香港)",
+        ui_language: "zh-HK",
+        input_locale: "0c04:00000404",
+        system_locale: "zh-HK",
+        time_zone: "China Standard Time",
+    },
+    LocalePreset {
+        display_name: "日本語 (日本)",
+        ui_language: "ja-JP",
+        input_locale: "0411:{03B5835F-F03C-411B-9CE2-AA23E1171E36}{A76C93D9-5523-4E90-AAFA-4DB112F9AC76}",
+        system_locale: "ja-JP",
+        time_zone: "Tokyo Standard Time",
+    },
+    LocalePreset {
+        display_name: "English (United Kingdom)",
+        ui_language: "en-GB",
+        input_locale: "0809:00000809",
+        system_locale: "en-GB",
+        time_zone: "GMT Standard Time",
+    },
+];
+
+/// 常用时区（Windows 时区 ID, 显示名称）
+pub const TIME_ZONES: &[(&str, &str)] = &[
+    ("China Standard Time", "(UTC+08:00) 北京，重庆，香港特别行政区，乌鲁木齐"),
+    ("Taipei Standard Time", "(UTC+08:00) 台北"),
+    ("Singapore Standard Time", "(UTC+08:00) 吉隆坡，新加坡"),
+    ("Tokyo Standard Time", "(UTC+09:00) 大阪，札幌，东京"),
+    ("Korea Standard Time", "(UTC+09:00) 首尔"),
+    ("UTC", "(UTC) 协调世界时"),
+    ("GMT Standard Time", "(UTC+00:00) 都柏林，爱丁堡，里斯本，伦敦"),
+    ("W. Europe Standard Time", "(UTC+01:00) 阿姆斯特丹，柏林，罗马，斯德哥尔摩"),
+    ("Russian Standard Time", "(UTC+03:00) 莫斯科，圣彼得堡"),
+    ("Eastern Standard Time", "(UTC-05:00) 东部时间(美国和加拿大)"),
+    ("Central Standard Time", "(UTC-06:00) 中部时间(美国和加拿大)"),
+    ("Mountain Standard Time", "(UTC-07:00) 山地时间(美国和加拿大)"),
+    ("Pacific Standard Time", "(UTC-08:00) 太平洋时间(美国和加拿大)"),
+];
+
+/// 各版本的通用安装密钥（仅用于选择版本、跳过密钥输入，不能激活系统）
+pub const GENERIC_PRODUCT_KEYS: &[(&str, &str)] = &[
+    ("Home", "YTMG3-N6DKC-DKB77-7M9GH-8HVX7"),
+    ("Home N", "4CPRK-NM3K3-X6XXQ-RXX86-WXCHW"),
+    ("Home Single Language", "BT79Q-G7N6G-PGBYW-4YWX6-6F4BT"),
+    ("Pro", "VK7JG-NPHTM-C97JM-9MPGT-3V66T"),
+    ("Pro N", "2B87N-8KFHP-DKV6R-Y2C8J-PKCKT"),
+    ("Pro for Workstations", "DXG7C-N36C4-C4HTG-X4T3X-2YV77"),
+    ("Education", "YNMGQ-8RYV3-4PGQ3-C8XTP-7CFBY"),
+    ("Enterprise", "XGVPP-NMH47-7TTHJ-W3FW7-8HV2C"),
+];
+
+/// 计算机名最大长度（NetBIOS 限制）
+const MAX_COMPUTER_NAME_LEN: usize = 15;
+
+/// 展开计算机名模板
+///
+/// 支持 `{serial}` 占位符（设备序列号）。结果只保留字母、数字和连字符，
+/// 超过 15 个字符时优先截短序列号（保留末尾部分）。
+/// 模板为空或展开结果无效时返回 `*`，由安装程序随机生成。
+pub fn expand_computer_name(pattern: &str, serial: &str) -> String {
+    let pattern = pattern.trim();
+    if pattern.is_empty() || pattern == "*" {
+        return "*".to_string();
+    }
+
+    let (prefix, suffix, has_serial) = match pattern.find("{serial}") {
+        Some(pos) => (&pattern[..pos], &pattern[pos + "{serial}".len()..], true),
+        None => (pattern, "", false),
+    };
+
+    let prefix = sanitize_computer_name(prefix);
+    let suffix = sanitize_computer_name(suffix);
+    let mut name = if has_serial {
+        let serial = sanitize_computer_name(serial);
+        let available = MAX_COMPUTER_NAME_LEN.saturating_sub(prefix.len() + suffix.len());
+        let serial = &serial[serial.len().saturating_sub(available)..];
+        format!("{}{}{}", prefix, serial, suffix)
+    } else {
+        prefix
+    };
+    name.truncate(MAX_COMPUTER_NAME_LEN);
+
+    let name = name.trim_matches('-').to_string();
+    // 计算机名不能为空或全部是数字
+    if name.is_empty() || name.chars().all(|c| c.is_ascii_digit()) {
+        return "*".to_string();
+    }
+    name
+}
+
+/// 检查计算机名是否为有效的 NetBIOS 名称（`*` 表示由安装程序生成）
+///
+/// 未展开的模板（如 `PC-{serial}`）也会被拒绝，安装程序不接受这样的名称。
+pub fn validate_computer_name(name: &str) -> Result<()> {
+    if name == "*" {
+        return Ok(());
+    }
+    if name.is_empty() || name.len() > MAX_COMPUTER_NAME_LEN {
+        anyhow::bail!("计算机名 {} 的长度应为 1 到 {} 个字符", name, MAX_COMPUTER_NAME_LEN);
+    }
+    if let Some(c) = name.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '-') {
+        anyhow::bail!("计算机名 {} 包含非法字符 {:?}", name, c);
+    }
+    if name.starts_with('-') || name.ends_with('-') || name.chars().all(|c| c.is_ascii_digit()) {
+        anyhow::bail!("计算机名 {} 不能以连字符开头或结尾，也不能全部是数字", name);
+    }
+    Ok(())
+}
+
+fn sanitize_computer_name(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_computer_name() {
+        assert_eq!(expand_computer_name("PC-{serial}", "5CD1234XYZ"), "PC-5CD1234XYZ");
+        assert_eq!(expand_computer_name("", "5CD1234XYZ"), "*");
+        assert_eq!(expand_computer_name("OFFICE PC_01", ""), "OFFICEPC01");
+        // 序列号过长时保留末尾部分
+        assert_eq!(
+            expand_computer_name("LAB-{serial}", "ABCDEFGHIJKLMNOP"),
+            "LAB-FGHIJKLMNOP"
+        );
+        // 没有序列号时去掉多余的连字符
+        assert_eq!(expand_computer_name("PC-{serial}", ""), "PC");
+        assert_eq!(expand_computer_name("{serial}", "123456"), "*");
+    }
+
+    #[test]
+    fn test_validate_computer_name() {
+        assert!(validate_computer_name("*").is_ok());
+        assert!(validate_computer_name("PC-5CD1234XYZ").is_ok());
+        assert!(validate_computer_name("PC-{serial}").is_err());
+        assert!(validate_computer_name("PC-0123456789ABC").is_err());
+        assert!(validate_computer_name("OFFICE PC").is_err());
+        assert!(validate_computer_name("-PC").is_err());
+        assert!(validate_computer_name("123456").is_err());
+    }
+}
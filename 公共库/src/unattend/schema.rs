@@ -0,0 +1,77 @@
+//! 应答文件组件与配置阶段的对应关系
+//!
+//! 只收录 LetRecovery 会生成或常见于部署场景的组件，
+//! 数据来源于 Windows 无人值守安装参考中各组件的 "Applicable configuration passes"。
+
+use super::Pass;
+
+/// 组件的 publicKeyToken（所有 Microsoft-Windows-* 组件相同）
+pub const PUBLIC_KEY_TOKEN: &str = "31bf3856ad364e35";
+
+/// 应答文件根元素命名空间
+pub const UNATTEND_NAMESPACE: &str = "urn:schemas-microsoft-com:unattend";
+
+/// wcm 命名空间（`wcm:action` 等属性使用）
+pub const WCM_NAMESPACE: &str = "http://schemas.microsoft.com/WMIConfig/2002/State";
+
+/// xsi 命名空间
+pub const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+pub const SETUP: &str = "Microsoft-Windows-Setup";
+pub const SHELL_SETUP: &str = "Microsoft-Windows-Shell-Setup";
+pub const DEPLOYMENT: &str = "Microsoft-Windows-Deployment";
+pub const INTERNATIONAL_CORE: &str = "Microsoft-Windows-International-Core";
+pub const INTERNATIONAL_CORE_WINPE: &str = "Microsoft-Windows-International-Core-WinPE";
+
+/// 已知组件及其允许出现的配置阶段
+const KNOWN_COMPONENTS: &[(&str, &[Pass])] = &[
+    (SETUP, &[Pass::WindowsPE]),
+    (INTERNATIONAL_CORE_WINPE, &[Pass::WindowsPE]),
+    ("Microsoft-Windows-PnpCustomizationsWinPE", &[Pass::WindowsPE]),
+    (
+        SHELL_SETUP,
+        &[
+            Pass::OfflineServicing,
+            Pass::Specialize,
+            Pass::AuditSystem,
+            Pass::AuditUser,
+            Pass::OobeSystem,
+        ],
+    ),
+    (
+        DEPLOYMENT,
+        &[
+            Pass::Generalize,
+            Pass::Specialize,
+            Pass::AuditSystem,
+            Pass::AuditUser,
+            Pass::OobeSystem,
+        ],
+    ),
+    (INTERNATIONAL_CORE, &[Pass::Specialize, Pass::OobeSystem]),
+    ("Microsoft-Windows-UnattendedJoin", &[Pass::Specialize]),
+    ("Microsoft-Windows-Security-SPP", &[Pass::Generalize, Pass::Specialize]),
+    ("Microsoft-Windows-Security-SPP-UX", &[Pass::Specialize]),
+    (
+        "Microsoft-Windows-PnpCustomizationsNonWinPE",
+        &[Pass::OfflineServicing, Pass::AuditSystem, Pass::Specialize],
+    ),
+    (
+        "Microsoft-Windows-TerminalServices-LocalSessionManager",
+        &[Pass::OfflineServicing, Pass::Generalize, Pass::Specialize],
+    ),
+    ("Microsoft-Windows-LUA-Settings", &[Pass::OfflineServicing]),
+];
+
+/// 查询组件允许出现的配置阶段，未收录的组件返回 None
+pub fn allowed_passes(component: &str) -> Option<&'static [Pass]> {
+    KNOWN_COMPONENTS
+        .iter()
+        .find(|(name, _)| *name == component)
+        .map(|(_, passes)| *passes)
+}
+
+/// 组件是否可以出现在指定配置阶段
+pub fn is_allowed(component: &str, pass: Pass) -> bool {
+    allowed_passes(component).is_some_and(|passes| passes.contains(&pass))
+}
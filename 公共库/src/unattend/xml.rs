@@ -0,0 +1,508 @@
+//! 最小化的 XML 元素树
+//!
+//! 只覆盖应答文件需要的子集：元素、属性和文本，输出时统一转义并按 4 空格缩进。
+//! 解析时忽略注释、处理指令和 DOCTYPE，并记录每个元素所在的行号。
+
+use anyhow::Result;
+
+/// XML 节点
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// XML 元素
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    /// 元素名（可带命名空间前缀，如 `wcm:action` 形式的属性名同理）
+    pub name: String,
+    /// 属性列表，按插入顺序输出
+    pub attributes: Vec<(String, String)>,
+    /// 子节点
+    pub children: Vec<Node>,
+    /// 源文件中的行号（从 1 开始；代码构建的元素为 0）
+    pub line: usize,
+}
+
+/// 比较时忽略行号
+impl PartialEq for Element {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.attributes == other.attributes
+            && self.children == other.children
+    }
+}
+
+impl Element {
+    /// 创建空元素
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 创建只包含文本的元素，如 `<Order>1</Order>`
+    pub fn text(name: &str, value: &str) -> Self {
+        let mut element = Self::new(name);
+        element.children.push(Node::Text(value.to_string()));
+        element
+    }
+
+    /// 添加属性
+    pub fn attr(mut self, name: &str, value: &str) -> Self {
+        self.set_attr(name, value);
+        self
+    }
+
+    /// 添加子元素
+    pub fn child(mut self, child: Element) -> Self {
+        self.children.push(Node::Element(child));
+        self
+    }
+
+    /// 添加只包含文本的子元素
+    pub fn text_child(self, name: &str, value: &str) -> Self {
+        self.child(Element::text(name, value))
+    }
+
+    /// 设置属性（已存在则覆盖）
+    pub fn set_attr(&mut self, name: &str, value: &str) {
+        if let Some(existing) = self.attributes.iter_mut().find(|(n, _)| n == name) {
+            existing.1 = value.to_string();
+        } else {
+            self.attributes.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    /// 读取属性
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 追加子元素
+    pub fn push(&mut self, child: Element) {
+        self.children.push(Node::Element(child));
+    }
+
+    /// 遍历子元素
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Element(e) => Some(e),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// 查找第一个指定名称的子元素
+    pub fn find(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.name == name)
+    }
+
+    /// 查找第一个指定名称的子元素（可变）
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Element> {
+        self.elements_mut().find(|e| e.name == name)
+    }
+
+    /// 遍历子元素（可变）
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+        self.children.iter_mut().filter_map(|n| match n {
+            Node::Element(e) => Some(e),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// 查找第一个指定名称的子元素（可变），不存在时创建
+    pub fn find_or_insert(&mut self, name: &str) -> &mut Element {
+        let pos = self.children.iter().position(|n| matches!(n, Node::Element(e) if e.name == name));
+        let pos = match pos {
+            Some(pos) => pos,
+            None => {
+                self.push(Element::new(name));
+                self.children.len() - 1
+            }
+        };
+        match &mut self.children[pos] {
+            Node::Element(e) => e,
+            Node::Text(_) => unreachable!(),
+        }
+    }
+
+    /// 元素的文本内容（拼接所有直接文本子节点）
+    pub fn text_content(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|n| match n {
+                Node::Text(t) => Some(t.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+
+    /// 序列化为带缩进的 XML 片段
+    pub fn write_to(&self, out: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+        out.push_str(&indent);
+        out.push('<');
+        out.push_str(&self.name);
+        for (name, value) in &self.attributes {
+            out.push(' ');
+            out.push_str(name);
+            out.push_str("=\"");
+            out.push_str(&escape(value));
+            out.push('"');
+        }
+        out.push('>');
+
+        let has_elements = self.children.iter().any(|n| matches!(n, Node::Element(_)));
+        if has_elements {
+            for node in &self.children {
+                match node {
+                    Node::Element(e) => {
+                        out.push('\n');
+                        e.write_to(out, depth + 1);
+                    }
+                    Node::Text(t) if !t.trim().is_empty() => {
+                        out.push('\n');
+                        out.push_str(&"    ".repeat(depth + 1));
+                        out.push_str(&escape(t.trim()));
+                    }
+                    Node::Text(_) => {}
+                }
+            }
+            out.push('\n');
+            out.push_str(&indent);
+        } else {
+            out.push_str(&escape(&self.text_content()));
+        }
+
+        out.push_str("</");
+        out.push_str(&self.name);
+        out.push('>');
+    }
+}
+
+/// 序列化为带 XML 声明的完整文档
+pub fn to_document(root: &Element) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    root.write_to(&mut out, 0);
+    out.push('\n');
+    out
+}
+
+/// 将文件内容解码为字符串，支持 UTF-8（可带 BOM）和带 BOM 的 UTF-16
+pub fn decode(bytes: &[u8]) -> Result<String> {
+    let utf16 = |be: bool| -> Result<String> {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|c| if be { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+            .collect();
+        String::from_utf16(&units).map_err(|_| anyhow::anyhow!("UTF-16 编码无效"))
+    };
+
+    match bytes {
+        [0xFF, 0xFE, ..] => utf16(false),
+        [0xFE, 0xFF, ..] => utf16(true),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => Ok(std::str::from_utf8(rest)?.to_string()),
+        _ => Ok(std::str::from_utf8(bytes)?.to_string()),
+    }
+}
+
+/// 带行号的解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "第 {} 行: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+macro_rules! parse_bail {
+    ($line:expr, $($arg:tt)*) => {
+        return Err(ParseError { line: $line, message: format!($($arg)*) }.into())
+    };
+}
+
+/// 解析 XML 文档，返回根元素
+///
+/// 语法错误时返回 [`ParseError`]。只包含空白的文本节点会被丢弃。
+pub fn parse(input: &str) -> Result<Element> {
+    let mut parser = Parser { input, pos: 0, line: 1 };
+    parser.skip_misc()?;
+    if parser.peek() != Some('<') {
+        parse_bail!(parser.line, "缺少根元素");
+    }
+    let root = parser.parse_element()?;
+    parser.skip_misc()?;
+    if parser.pos < input.len() {
+        parse_bail!(parser.line, "根元素之后存在多余内容");
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// 前进指定字节数，同时统计换行
+    fn advance(&mut self, len: usize) {
+        self.line += self.input[self.pos..self.pos + len].matches('\n').count();
+        self.pos += len;
+    }
+
+    fn skip_whitespace(&mut self) {
+        let len = self.rest().len() - self.rest().trim_start().len();
+        self.advance(len);
+    }
+
+    /// 跳过到指定结束标记之后
+    fn skip_past(&mut self, end: &str) -> Result<()> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.advance(i + end.len());
+                Ok(())
+            }
+            None => parse_bail!(self.line, "缺少 {}", end),
+        }
+    }
+
+    /// 跳过空白、注释、处理指令和 DOCTYPE
+    fn skip_misc(&mut self) -> Result<()> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String> {
+        let len = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '/' | '>'))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            parse_bail!(self.line, "缺少名称");
+        }
+        let name = self.rest()[..len].to_string();
+        self.advance(len);
+        Ok(name)
+    }
+
+    fn parse_element(&mut self) -> Result<Element> {
+        let line = self.line;
+        self.advance(1); // '<'
+        let mut element = Element::new(&self.parse_name()?);
+        element.line = line;
+
+        // 属性
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.advance(2);
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.advance(1);
+                break;
+            }
+            if self.rest().is_empty() {
+                parse_bail!(line, "元素 <{}> 未结束", element.name);
+            }
+            let name = self.parse_name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                parse_bail!(self.line, "属性 {} 缺少值", name);
+            }
+            self.advance(1);
+            self.skip_whitespace();
+            let quote = match self.peek() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => parse_bail!(self.line, "属性 {} 的值缺少引号", name),
+            };
+            self.advance(1);
+            let Some(end) = self.rest().find(quote) else {
+                parse_bail!(self.line, "属性 {} 的值未结束", name);
+            };
+            let value = unescape(&self.rest()[..end], self.line)?;
+            self.advance(end + 1);
+            element.attributes.push((name, value));
+        }
+
+        // 子节点
+        loop {
+            if self.rest().starts_with("</") {
+                let close_line = self.line;
+                self.advance(2);
+                let name = self.parse_name()?;
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') || name != element.name {
+                    parse_bail!(
+                        close_line,
+                        "结束标签 </{}> 与第 {} 行的 <{}> 不匹配",
+                        name,
+                        line,
+                        element.name
+                    );
+                }
+                self.advance(1);
+                return Ok(element);
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.advance("<![CDATA[".len());
+                let Some(end) = self.rest().find("]]>") else {
+                    parse_bail!(self.line, "CDATA 未结束");
+                };
+                let text = self.rest()[..end].to_string();
+                self.advance(end + 3);
+                element.children.push(Node::Text(text));
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with('<') {
+                let child = self.parse_element()?;
+                element.children.push(Node::Element(child));
+            } else if self.rest().is_empty() {
+                parse_bail!(line, "元素 <{}> 缺少结束标签", element.name);
+            } else {
+                let end = self.rest().find('<').unwrap_or(self.rest().len());
+                let text = unescape(&self.rest()[..end], self.line)?;
+                self.advance(end);
+                if !text.trim().is_empty() {
+                    element.children.push(Node::Text(text.trim().to_string()));
+                }
+            }
+        }
+    }
+}
+
+/// 还原实体引用
+fn unescape(value: &str, line: usize) -> Result<String> {
+    if !value.contains('&') {
+        return Ok(value.to_string());
+    }
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find(';') else {
+            parse_bail!(line, "实体引用未结束");
+        };
+        let entity = &rest[start + 1..start + end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match c {
+            Some(c) => out.push(c),
+            None => parse_bail!(line, "无法识别的实体 &{};", entity),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// 转义 XML 特殊字符
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r#"a&b<c>"d'"#), "a&amp;b&lt;c&gt;&quot;d&apos;");
+    }
+
+    #[test]
+    fn test_write_nested() {
+        let element = Element::new("A")
+            .attr("x", "1")
+            .child(Element::text("B", "<v>"))
+            .child(Element::new("C"));
+        let mut out = String::new();
+        element.write_to(&mut out, 0);
+        assert_eq!(out, "<A x=\"1\">\n    <B>&lt;v&gt;</B>\n    <C></C>\n</A>");
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let input = "<?xml version=\"1.0\"?>\n<!-- c -->\n<A x='1 &amp; 2'>\n  <B>&lt;v&gt;&#x41;</B>\n  <C/>\n  <D><![CDATA[<raw>]]></D>\n</A>\n";
+        let root = parse(input).unwrap();
+        assert_eq!(root.get_attr("x"), Some("1 & 2"));
+        assert_eq!(root.find("B").unwrap().text_content(), "<v>A");
+        assert_eq!(root.find("B").unwrap().line, 4);
+        assert_eq!(root.find("D").unwrap().text_content(), "<raw>");
+
+        let expected = Element::new("A")
+            .attr("x", "1 & 2")
+            .child(Element::text("B", "<v>A"))
+            .child(Element::new("C"))
+            .child(Element::text("D", "<raw>"));
+        assert_eq!(root, expected);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse("<A>\n<B></C>\n</A>").unwrap_err().to_string();
+        assert!(err.contains("第 2 行"), "{}", err);
+        assert!(parse("<A>").is_err());
+        assert!(parse("<A>&bogus;</A>").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("<A/>".encode_utf16().flat_map(|u| u.to_le_bytes()));
+        assert_eq!(decode(&bytes).unwrap(), "<A/>");
+        assert_eq!(decode(b"\xEF\xBB\xBF<A/>").unwrap(), "<A/>");
+    }
+}
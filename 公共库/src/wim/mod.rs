@@ -0,0 +1,441 @@
+//! WIM 镜像文件读取
+//!
+//! 直接读取 WIM 头部、XML 描述和完整性表，不调用 DISM，PE 端与正常系统端
+//! 共用，也可以在任意平台上测试。只读取元数据，不解压资源。
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::hash::{Sha1, SHA1_LEN};
+use crate::unattend::xml;
+
+/// 文件头标识
+pub const WIM_SIGNATURE: &[u8; 8] = b"MSWIM\0\0\0";
+/// 文件头长度，完整性表从这里开始计算
+pub const HEADER_SIZE: u64 = 208;
+
+/// XML 描述和完整性表的上限，头部损坏时不按其中的大小分配内存
+const MAX_METADATA_SIZE: u64 = 64 * 1024 * 1024;
+/// 完整性表数据块大小的上限（DISM 使用 10 MB）
+const MAX_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// FILETIME (1601-01-01 起的 100ns) 与 Unix 时间之差
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// 资源头：资源在文件中的位置和大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceHeader {
+    /// 在文件中占用的字节数（压缩后）
+    pub size: u64,
+    pub flags: u8,
+    pub offset: u64,
+    /// 解压后的字节数
+    pub original_size: u64,
+}
+
+impl ResourceHeader {
+    fn parse(bytes: &[u8]) -> Self {
+        let mut size = [0u8; 8];
+        size[..7].copy_from_slice(&bytes[..7]);
+        Self {
+            size: u64::from_le_bytes(size),
+            flags: bytes[7],
+            offset: read_u64(bytes, 8),
+            original_size: read_u64(bytes, 16),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+/// WIM 文件头
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WimHeader {
+    pub version: u32,
+    pub flags: u32,
+    pub chunk_size: u32,
+    pub part_number: u16,
+    pub total_parts: u16,
+    pub image_count: u32,
+    pub lookup_table: ResourceHeader,
+    pub xml_data: ResourceHeader,
+    pub boot_metadata: ResourceHeader,
+    pub boot_index: u32,
+    pub integrity: ResourceHeader,
+}
+
+impl WimHeader {
+    /// 从文件开头读取并检查标识
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let mut bytes = [0u8; HEADER_SIZE as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut bytes).context("文件过短，不是 WIM 镜像")?;
+        Self::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_SIZE as usize || &bytes[..8] != WIM_SIGNATURE {
+            anyhow::bail!("不是 WIM 镜像（文件头标识不符）");
+        }
+        Ok(Self {
+            version: read_u32(bytes, 12),
+            flags: read_u32(bytes, 16),
+            chunk_size: read_u32(bytes, 20),
+            part_number: read_u16(bytes, 40),
+            total_parts: read_u16(bytes, 42),
+            image_count: read_u32(bytes, 44),
+            lookup_table: ResourceHeader::parse(&bytes[48..72]),
+            xml_data: ResourceHeader::parse(&bytes[72..96]),
+            boot_metadata: ResourceHeader::parse(&bytes[96..120]),
+            boot_index: read_u32(bytes, 120),
+            integrity: ResourceHeader::parse(&bytes[124..148]),
+        })
+    }
+}
+
+/// XML 描述中的一个卷
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WimImage {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub dir_count: u64,
+    pub file_count: u64,
+    pub total_bytes: u64,
+    /// 创建时间（FILETIME）
+    pub creation_time: Option<u64>,
+}
+
+/// 读取 XML 描述（UTF-16 文本）
+pub fn read_xml<R: Read + Seek>(reader: &mut R, header: &WimHeader) -> Result<String> {
+    if header.xml_data.is_empty() {
+        anyhow::bail!("镜像中没有 XML 描述");
+    }
+    check_resource(reader, &header.xml_data, "XML 描述")?;
+    let mut bytes = vec![0u8; header.xml_data.size as usize];
+    reader.seek(SeekFrom::Start(header.xml_data.offset))?;
+    reader.read_exact(&mut bytes).context("读取 XML 描述失败")?;
+    // 部分工具写入的 XML 没有 BOM
+    if !bytes.starts_with(&[0xFF, 0xFE]) {
+        bytes.splice(0..0, [0xFF, 0xFE]);
+    }
+    xml::decode(&bytes)
+}
+
+/// 解析 XML 描述中的全部卷，按索引排序
+pub fn parse_images(text: &str) -> Result<Vec<WimImage>> {
+    let root = xml::parse(text).context("XML 描述格式错误")?;
+    let number = |element: &xml::Element, name: &str| -> u64 {
+        element
+            .find(name)
+            .and_then(|e| parse_number(&e.text_content()))
+            .unwrap_or(0)
+    };
+
+    let mut images: Vec<WimImage> = root
+        .elements()
+        .filter(|e| e.name == "IMAGE")
+        .map(|e| WimImage {
+            index: e.get_attr("INDEX").and_then(|i| i.trim().parse().ok()).unwrap_or(0),
+            name: e.find("NAME").map(|n| n.text_content()).unwrap_or_default(),
+            description: e.find("DESCRIPTION").map(|n| n.text_content()).unwrap_or_default(),
+            dir_count: number(e, "DIRCOUNT"),
+            file_count: number(e, "FILECOUNT"),
+            total_bytes: number(e, "TOTALBYTES"),
+            creation_time: e.find("CREATIONTIME").and_then(|t| {
+                let high = parse_number(&t.find("HIGHPART")?.text_content())?;
+                let low = parse_number(&t.find("LOWPART")?.text_content())?;
+                Some(high << 32 | low)
+            }),
+        })
+        .collect();
+    images.sort_by_key(|i| i.index);
+    Ok(images)
+}
+
+/// 读取镜像文件的头部和全部卷信息
+pub fn read_images(path: &Path) -> Result<(WimHeader, Vec<WimImage>)> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("无法打开镜像 {}", path.display()))?;
+    let header = WimHeader::read(&mut file)?;
+    let images = parse_images(&read_xml(&mut file, &header)?)?;
+    Ok((header, images))
+}
+
+/// 完整性检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityStatus {
+    /// 全部数据块与完整性表一致
+    Valid { chunks: u32 },
+    /// 镜像没有完整性表（捕获时未使用 /CheckIntegrity）
+    NotPresent,
+    /// 数据与完整性表不一致
+    Corrupt(String),
+}
+
+/// 按完整性表逐块校验 SHA-1
+///
+/// 完整性表覆盖从文件头之后到查找表末尾的全部数据。
+pub fn check_integrity<R: Read + Seek>(
+    reader: &mut R,
+    header: &WimHeader,
+    progress: &dyn Fn(u8),
+) -> Result<IntegrityStatus> {
+    if header.integrity.is_empty() {
+        return Ok(IntegrityStatus::NotPresent);
+    }
+    if let Err(e) = check_resource(reader, &header.integrity, "完整性表") {
+        return Ok(IntegrityStatus::Corrupt(e.to_string()));
+    }
+
+    let mut table = vec![0u8; header.integrity.size as usize];
+    reader.seek(SeekFrom::Start(header.integrity.offset))?;
+    reader.read_exact(&mut table).context("读取完整性表失败")?;
+    if table.len() < 12 {
+        return Ok(IntegrityStatus::Corrupt("完整性表过短".to_string()));
+    }
+    let entries = read_u32(&table, 4);
+    let chunk_size = read_u32(&table, 8) as u64;
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE || table.len() < 12 + entries as usize * SHA1_LEN {
+        return Ok(IntegrityStatus::Corrupt("完整性表格式错误".to_string()));
+    }
+
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let end = match header.lookup_table.offset.checked_add(header.lookup_table.size) {
+        Some(end) if end <= file_len => end,
+        _ => return Ok(IntegrityStatus::Corrupt("查找表超出文件末尾".to_string())),
+    };
+    let total = end.saturating_sub(HEADER_SIZE);
+    if total.div_ceil(chunk_size) != entries as u64 {
+        return Ok(IntegrityStatus::Corrupt(format!(
+            "完整性表有 {} 项，与数据长度 {} 字节不符",
+            entries, total
+        )));
+    }
+
+    reader.seek(SeekFrom::Start(HEADER_SIZE))?;
+    let mut buffer = vec![0u8; chunk_size as usize];
+    for chunk in 0..entries {
+        let start = chunk as u64 * chunk_size;
+        let len = chunk_size.min(total - start) as usize;
+        reader
+            .read_exact(&mut buffer[..len])
+            .with_context(|| format!("读取第 {} 块数据失败", chunk + 1))?;
+        let expected = &table[12 + chunk as usize * SHA1_LEN..][..SHA1_LEN];
+        if Sha1::digest(&buffer[..len]) != expected {
+            return Ok(IntegrityStatus::Corrupt(format!("第 {} 块数据校验失败", chunk + 1)));
+        }
+        progress(((chunk as u64 + 1) * 100 / entries as u64) as u8);
+    }
+    Ok(IntegrityStatus::Valid { chunks: entries })
+}
+
+/// 资源须完整位于文件内且不超过 [`MAX_METADATA_SIZE`]，再按其大小分配内存
+fn check_resource<R: Seek>(reader: &mut R, resource: &ResourceHeader, what: &str) -> Result<()> {
+    if resource.size > MAX_METADATA_SIZE {
+        anyhow::bail!("{}过大（{} 字节），镜像头部可能已损坏", what, resource.size);
+    }
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    match resource.offset.checked_add(resource.size) {
+        Some(end) if end <= file_len => Ok(()),
+        _ => anyhow::bail!("{}超出文件末尾，镜像可能不完整", what),
+    }
+}
+
+/// FILETIME 转换为 Unix 时间（秒），早于 1970 年的按 0 计
+pub fn filetime_to_unix(filetime: u64) -> u64 {
+    filetime.saturating_sub(FILETIME_UNIX_EPOCH) / 10_000_000
+}
+
+/// 把 FILETIME 格式化为 "YYYY-MM-DD HH:MM:SS"（UTC）
+pub fn format_filetime(filetime: u64) -> String {
+    let secs = filetime_to_unix(filetime);
+    let (days, rest) = (secs / 86_400, secs % 86_400);
+
+    // 按公历从 1970-01-01 推算年月日
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// 当前时间的 FILETIME
+pub fn filetime_now() -> u64 {
+    to_filetime(std::time::SystemTime::now())
+}
+
+/// 时间转换为 FILETIME，早于 1970 年的按 1970 年计
+pub fn to_filetime(time: std::time::SystemTime) -> u64 {
+    let unix = time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    FILETIME_UNIX_EPOCH + unix.as_secs() * 10_000_000 + u64::from(unix.subsec_nanos()) / 100
+}
+
+/// FILETIME 转换为时间
+pub fn from_filetime(filetime: u64) -> std::time::SystemTime {
+    let ticks = filetime.saturating_sub(FILETIME_UNIX_EPOCH);
+    std::time::UNIX_EPOCH
+        + std::time::Duration::new(ticks / 10_000_000, (ticks % 10_000_000 * 100) as u32)
+}
+
+/// XML 中的数字可能是十进制或 0x 开头的十六进制
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// 测试用的最小 WIM：文件头、资源数据、查找表、XML 描述和完整性表
+#[cfg(test)]
+pub(crate) fn build_test_wim(xml_text: &str, payload: &[u8], integrity_chunk: Option<u32>) -> Vec<u8> {
+    fn resource(out: &mut [u8], size: u64, offset: u64) {
+        out[..7].copy_from_slice(&size.to_le_bytes()[..7]);
+        out[8..16].copy_from_slice(&offset.to_le_bytes());
+        out[16..24].copy_from_slice(&size.to_le_bytes());
+    }
+
+    let mut wim = vec![0u8; HEADER_SIZE as usize];
+    wim[..8].copy_from_slice(WIM_SIGNATURE);
+    wim[8..12].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+    wim[12..16].copy_from_slice(&0x10d00u32.to_le_bytes());
+    wim[20..24].copy_from_slice(&32768u32.to_le_bytes());
+    wim[40..42].copy_from_slice(&1u16.to_le_bytes());
+    wim[42..44].copy_from_slice(&1u16.to_le_bytes());
+    let image_count = xml_text.matches("<IMAGE ").count() as u32;
+    wim[44..48].copy_from_slice(&image_count.to_le_bytes());
+
+    wim.extend_from_slice(payload);
+    let lookup_offset = wim.len() as u64;
+    wim.extend_from_slice(&[0x5A; 50]);
+    resource(&mut wim[48..72], 50, lookup_offset);
+
+    let mut xml_bytes = vec![0xFF, 0xFE];
+    xml_bytes.extend(xml_text.encode_utf16().flat_map(u16::to_le_bytes));
+    let xml_offset = wim.len() as u64;
+    resource(&mut wim[72..96], xml_bytes.len() as u64, xml_offset);
+    wim.extend_from_slice(&xml_bytes);
+
+    if let Some(chunk_size) = integrity_chunk {
+        let covered = wim[HEADER_SIZE as usize..(lookup_offset + 50) as usize].to_vec();
+        let hashes: Vec<[u8; SHA1_LEN]> = covered.chunks(chunk_size as usize).map(Sha1::digest).collect();
+        let mut table = Vec::new();
+        table.extend_from_slice(&((12 + hashes.len() * SHA1_LEN) as u32).to_le_bytes());
+        table.extend_from_slice(&(hashes.len() as u32).to_le_bytes());
+        table.extend_from_slice(&chunk_size.to_le_bytes());
+        hashes.iter().for_each(|h| table.extend_from_slice(h));
+        let integrity_offset = wim.len() as u64;
+        resource(&mut wim[124..148], table.len() as u64, integrity_offset);
+        wim.extend_from_slice(&table);
+    }
+    wim
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const XML: &str = "<WIM><TOTALBYTES>1000</TOTALBYTES>\
+        <IMAGE INDEX=\"2\"><DIRCOUNT>4</DIRCOUNT><FILECOUNT>12</FILECOUNT><TOTALBYTES>4096</TOTALBYTES>\
+        <CREATIONTIME><HIGHPART>0x01DB0A4F</HIGHPART><LOWPART>0x3C8E1200</LOWPART></CREATIONTIME>\
+        <NAME>每日备份</NAME><DESCRIPTION>PC-01</DESCRIPTION></IMAGE>\
+        <IMAGE INDEX=\"1\"><FILECOUNT>10</FILECOUNT><NAME>初始</NAME></IMAGE></WIM>";
+
+    #[test]
+    fn test_read_header_and_images() {
+        let wim = build_test_wim(XML, &[1; 300], None);
+        let mut reader = Cursor::new(&wim);
+        let header = WimHeader::read(&mut reader).unwrap();
+        assert_eq!(header.image_count, 2);
+        assert_eq!(header.lookup_table.offset, HEADER_SIZE + 300);
+
+        let images = parse_images(&read_xml(&mut reader, &header).unwrap()).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].name, "初始");
+        assert_eq!(images[0].creation_time, None);
+        assert_eq!(images[1].index, 2);
+        assert_eq!(images[1].description, "PC-01");
+        assert_eq!((images[1].dir_count, images[1].file_count, images[1].total_bytes), (4, 12, 4096));
+        assert_eq!(images[1].creation_time, Some(0x01DB0A4F_3C8E1200));
+
+        assert!(WimHeader::read(&mut Cursor::new(vec![0u8; 300])).is_err());
+
+        // 头部中的大小损坏时报错，不按其分配内存
+        let mut damaged = header.clone();
+        damaged.xml_data.size = 1 << 50;
+        assert!(read_xml(&mut reader, &damaged).unwrap_err().to_string().contains("过大"));
+        damaged.xml_data.size = wim.len() as u64;
+        assert!(read_xml(&mut reader, &damaged).unwrap_err().to_string().contains("超出文件末尾"));
+        assert_eq!(check_integrity(&mut reader, &header, &|_| {}).unwrap(), IntegrityStatus::NotPresent);
+    }
+
+    #[test]
+    fn test_check_integrity() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 241) as u8).collect();
+        let mut wim = build_test_wim(XML, &payload, Some(128));
+        let header = WimHeader::parse(&wim).unwrap();
+
+        let progress = std::sync::Mutex::new(Vec::new());
+        let status = check_integrity(&mut Cursor::new(&wim), &header, &|p| progress.lock().unwrap().push(p));
+        // 1000 字节数据 + 50 字节查找表，每块 128 字节
+        assert_eq!(status.unwrap(), IntegrityStatus::Valid { chunks: 9 });
+        assert_eq!(progress.lock().unwrap().last(), Some(&100));
+
+        wim[HEADER_SIZE as usize + 700] ^= 0xFF;
+        assert_eq!(
+            check_integrity(&mut Cursor::new(&wim), &header, &|_| {}).unwrap(),
+            IntegrityStatus::Corrupt("第 6 块数据校验失败".to_string())
+        );
+
+        let mut damaged = header.clone();
+        damaged.integrity.size = 1 << 40;
+        assert!(matches!(
+            check_integrity(&mut Cursor::new(&wim), &damaged, &|_| {}).unwrap(),
+            IntegrityStatus::Corrupt(message) if message.contains("过大")
+        ));
+        let chunk_size_offset = header.integrity.offset as usize + 8;
+        wim[chunk_size_offset..chunk_size_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            check_integrity(&mut Cursor::new(&wim), &header, &|_| {}).unwrap(),
+            IntegrityStatus::Corrupt("完整性表格式错误".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_filetime() {
+        assert_eq!(format_filetime(FILETIME_UNIX_EPOCH), "1970-01-01 00:00:00");
+        // 2024-02-29 12:34:56 UTC
+        assert_eq!(format_filetime(FILETIME_UNIX_EPOCH + 1_709_210_096 * 10_000_000), "2024-02-29 12:34:56");
+    }
+}
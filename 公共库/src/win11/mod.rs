@@ -0,0 +1,345 @@
+//! Windows 11 硬件要求检查
+//!
+//! 根据调用方收集好的硬件信息逐项对照 Windows 11 的最低要求，不访问系统。
+//! 不满足要求但仍要安装时，可以在目标系统的 SYSTEM 配置单元中写入
+//! LabConfig 绕过键，让安装程序和系统升级跳过这些检查。
+
+use crate::preflight::CheckItem;
+
+/// 内存下限
+pub const MIN_MEMORY_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+/// 存储下限（按磁盘厂商的十进制 GB 计算）
+pub const MIN_STORAGE_BYTES: u64 = 64 * 1000 * 1000 * 1000;
+/// 处理器最少核心数
+pub const MIN_CPU_CORES: u32 = 2;
+/// 处理器最低主频
+pub const MIN_CPU_MHZ: u32 = 1000;
+/// 最低 TPM 主版本
+pub const MIN_TPM_VERSION: u32 = 2;
+
+/// LabConfig 键（相对 SYSTEM 配置单元）
+pub const LABCONFIG_KEY: &str = "Setup\\LabConfig";
+/// LabConfig 下的绕过值，全部写为 1
+pub const LABCONFIG_VALUES: [&str; 5] = [
+    "BypassTPMCheck",
+    "BypassSecureBootCheck",
+    "BypassRAMCheck",
+    "BypassStorageCheck",
+    "BypassCPUCheck",
+];
+/// 允许在不受支持的硬件上升级的键（相对 SYSTEM 配置单元）
+pub const MOSETUP_KEY: &str = "Setup\\MoSetup";
+/// MoSetup 下的值名
+pub const MOSETUP_VALUE: &str = "AllowUpgradesWithUnsupportedTPMOrCPU";
+
+/// 随程序发布的受支持处理器列表，格式见文件头
+const SUPPORTED_CPUS: &str = include_str!("supported_cpus.txt");
+
+/// 检查所需的硬件信息
+#[derive(Debug, Clone, Default)]
+pub struct Win11Hardware {
+    /// 处理器名称，如 "Intel(R) Core(TM) i7-8700K CPU @ 3.70GHz"
+    pub cpu_name: String,
+    pub cpu_cores: u32,
+    pub cpu_mhz: u32,
+    pub is_64bit: bool,
+    /// TPM 主版本，未检测到 TPM 时为 None
+    pub tpm_version: Option<u32>,
+    /// 当前是否已启用安全启动
+    pub secure_boot: bool,
+    /// 是否以 UEFI 方式启动
+    pub uefi: bool,
+    pub memory_bytes: u64,
+    /// 安装目标所在磁盘的容量
+    pub storage_bytes: u64,
+}
+
+/// 单项要求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    Cpu,
+    Tpm,
+    SecureBoot,
+    Uefi,
+    Memory,
+    Storage,
+}
+
+impl Requirement {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Requirement::Cpu => "处理器",
+            Requirement::Tpm => "TPM",
+            Requirement::SecureBoot => "安全启动",
+            Requirement::Uefi => "UEFI 启动",
+            Requirement::Memory => "内存",
+            Requirement::Storage => "存储",
+        }
+    }
+
+    /// 跳过此项检查的 LabConfig 值（UEFI 与安全启动共用一个）
+    pub fn bypass_value(&self) -> &'static str {
+        match self {
+            Requirement::Cpu => "BypassCPUCheck",
+            Requirement::Tpm => "BypassTPMCheck",
+            Requirement::SecureBoot | Requirement::Uefi => "BypassSecureBootCheck",
+            Requirement::Memory => "BypassRAMCheck",
+            Requirement::Storage => "BypassStorageCheck",
+        }
+    }
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequirementResult {
+    pub requirement: Requirement,
+    pub passed: bool,
+    /// 检测到的值或不满足的原因
+    pub detail: String,
+}
+
+/// 兼容性报告
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Win11Report {
+    pub results: Vec<RequirementResult>,
+}
+
+impl Win11Report {
+    /// 是否满足全部要求
+    pub fn is_compatible(&self) -> bool {
+        self.results.iter().all(|r| r.passed)
+    }
+
+    /// 不满足的要求
+    pub fn failures(&self) -> impl Iterator<Item = &RequirementResult> {
+        self.results.iter().filter(|r| !r.passed)
+    }
+
+    /// 每项要求对应一个检查项，不满足的为警告，可以确认后继续
+    pub fn check_items(&self) -> Vec<CheckItem> {
+        self.results
+            .iter()
+            .map(|r| {
+                let title = r.requirement.name();
+                if r.passed {
+                    CheckItem::pass(title, r.detail.clone())
+                } else {
+                    CheckItem::warning(title, format!("不满足 Windows 11 要求：{}", r.detail))
+                }
+            })
+            .collect()
+    }
+
+    /// 生成文本报告
+    pub fn to_text(&self) -> String {
+        self.results
+            .iter()
+            .map(|r| {
+                format!(
+                    "[{}] {}: {}",
+                    if r.passed { "通过" } else { "不满足" },
+                    r.requirement.name(),
+                    r.detail
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 对照 Windows 11 要求逐项检查
+pub fn check(hardware: &Win11Hardware) -> Win11Report {
+    let mut results = Vec::new();
+    let mut push = |requirement, passed, detail: String| {
+        results.push(RequirementResult { requirement, passed, detail });
+    };
+
+    let cpu_name = hardware.cpu_name.trim();
+    let cpu_detail = if !hardware.is_64bit {
+        Some("需要 64 位处理器".to_string())
+    } else if hardware.cpu_cores < MIN_CPU_CORES {
+        Some(format!("{} 核，至少需要 {} 核", hardware.cpu_cores, MIN_CPU_CORES))
+    } else if hardware.cpu_mhz > 0 && hardware.cpu_mhz < MIN_CPU_MHZ {
+        Some(format!("{} MHz，至少需要 {} MHz", hardware.cpu_mhz, MIN_CPU_MHZ))
+    } else if !is_supported_cpu(cpu_name) {
+        Some(format!("{} 不在受支持的处理器列表中", display_name(cpu_name)))
+    } else {
+        None
+    };
+    match cpu_detail {
+        Some(detail) => push(Requirement::Cpu, false, detail),
+        None => push(Requirement::Cpu, true, display_name(cpu_name).to_string()),
+    }
+
+    match hardware.tpm_version {
+        Some(v) if v >= MIN_TPM_VERSION => push(Requirement::Tpm, true, format!("TPM {}.0", v)),
+        Some(v) => push(Requirement::Tpm, false, format!("TPM {}.x，需要 TPM 2.0", v)),
+        None => push(Requirement::Tpm, false, "未检测到 TPM".to_string()),
+    }
+
+    if hardware.secure_boot {
+        push(Requirement::SecureBoot, true, "已启用".to_string());
+    } else if hardware.uefi {
+        push(Requirement::SecureBoot, false, "未启用，可在固件设置中开启".to_string());
+    } else {
+        push(Requirement::SecureBoot, false, "Legacy 启动不支持安全启动".to_string());
+    }
+
+    if hardware.uefi {
+        push(Requirement::Uefi, true, "UEFI".to_string());
+    } else {
+        push(Requirement::Uefi, false, "当前为 Legacy 启动".to_string());
+    }
+
+    let memory = format!("{:.1} GB", hardware.memory_bytes as f64 / (1u64 << 30) as f64);
+    if hardware.memory_bytes >= MIN_MEMORY_BYTES {
+        push(Requirement::Memory, true, memory);
+    } else {
+        push(Requirement::Memory, false, format!("{}，至少需要 4 GB", memory));
+    }
+
+    let storage = format!("{} GB", hardware.storage_bytes / 1_000_000_000);
+    if hardware.storage_bytes >= MIN_STORAGE_BYTES {
+        push(Requirement::Storage, true, storage);
+    } else {
+        push(Requirement::Storage, false, format!("{}，至少需要 64 GB", storage));
+    }
+
+    Win11Report { results }
+}
+
+/// 根据镜像卷名判断是否为 Windows 11
+pub fn is_win11_image(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("windows 11") || name.contains("win11")
+}
+
+/// 从 TPM 规范版本字符串（如 "2.0, 0, 1.59"）中取主版本
+pub fn parse_tpm_version(spec_version: &str) -> Option<u32> {
+    spec_version
+        .split([',', '.'])
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// 处理器是否在受支持列表中
+pub fn is_supported_cpu(name: &str) -> bool {
+    let name = normalize_cpu_name(name);
+    SUPPORTED_CPUS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(';'))
+        .any(|pattern| contains_pattern(&name, pattern))
+}
+
+fn display_name(name: &str) -> &str {
+    if name.is_empty() {
+        "未知处理器"
+    } else {
+        name
+    }
+}
+
+/// 小写，去掉商标符号和主频，合并空白
+fn normalize_cpu_name(name: &str) -> String {
+    let mut name = name.to_lowercase();
+    for mark in ["(r)", "(tm)", "®", "™"] {
+        name = name.replace(mark, "");
+    }
+    if let Some(pos) = name.find('@') {
+        name.truncate(pos);
+    }
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 模式是否从名称中某个单词的开头匹配，"#" 匹配一个数字
+fn contains_pattern(name: &str, pattern: &str) -> bool {
+    let name = name.as_bytes();
+    let pattern = pattern.as_bytes();
+    (0..name.len())
+        .filter(|&i| i == 0 || name[i - 1] == b' ')
+        .any(|i| {
+            name.len() - i >= pattern.len()
+                && pattern.iter().zip(&name[i..]).all(|(&p, &c)| {
+                    if p == b'#' {
+                        c.is_ascii_digit()
+                    } else {
+                        p == c
+                    }
+                })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_cpu_list() {
+        for name in [
+            "Intel(R) Core(TM) i7-8700K CPU @ 3.70GHz",
+            "11th Gen Intel(R) Core(TM) i5-1135G7 @ 2.40GHz",
+            "13th Gen Intel(R) Core(TM) i9-13900K",
+            "Intel(R) Core(TM) Ultra 7 155H",
+            "AMD Ryzen 7 3700X 8-Core Processor",
+            "AMD Ryzen 5 2600 Six-Core Processor",
+            "AMD Ryzen 7 PRO 4750U with Radeon Graphics",
+            "Intel(R) N100",
+            "Snapdragon(R) X Elite - X1E78100 - Qualcomm(R) Oryon(TM) CPU",
+        ] {
+            assert!(is_supported_cpu(name), "{}", name);
+        }
+        for name in [
+            "Intel(R) Core(TM) i7-7700K CPU @ 4.20GHz",
+            "Intel(R) Core(TM) i7 CPU 980X @ 3.33GHz",
+            "Intel(R) Core(TM)2 Duo CPU E8400 @ 3.00GHz",
+            "AMD Ryzen 7 1700X Eight-Core Processor",
+            "AMD FX(tm)-8350 Eight-Core Processor",
+            "",
+        ] {
+            assert!(!is_supported_cpu(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_check_report() {
+        let mut hardware = Win11Hardware {
+            cpu_name: "Intel(R) Core(TM) i5-8400 CPU @ 2.80GHz".to_string(),
+            cpu_cores: 6,
+            cpu_mhz: 2808,
+            is_64bit: true,
+            tpm_version: Some(2),
+            secure_boot: true,
+            uefi: true,
+            memory_bytes: 8 << 30,
+            storage_bytes: 256_000_000_000,
+        };
+        let report = check(&hardware);
+        assert!(report.is_compatible());
+        assert_eq!(report.results.len(), 6);
+
+        hardware.cpu_name = "Intel(R) Core(TM) i5-4590 CPU @ 3.30GHz".to_string();
+        hardware.tpm_version = Some(1);
+        hardware.secure_boot = false;
+        hardware.uefi = false;
+        let report = check(&hardware);
+        let failed: Vec<_> = report.failures().map(|r| r.requirement).collect();
+        assert_eq!(
+            failed,
+            [Requirement::Cpu, Requirement::Tpm, Requirement::SecureBoot, Requirement::Uefi]
+        );
+        assert!(report.to_text().contains("[不满足] TPM: TPM 1.x"));
+        assert!(report.check_items().iter().all(|i| i.severity != crate::preflight::Severity::Blocking));
+    }
+
+    #[test]
+    fn test_parse_tpm_version_and_image() {
+        assert_eq!(parse_tpm_version("2.0, 0, 1.59"), Some(2));
+        assert_eq!(parse_tpm_version("1.2"), Some(1));
+        assert_eq!(parse_tpm_version(""), None);
+        assert!(is_win11_image("Windows 11 专业版"));
+        assert!(!is_win11_image("Windows 10 Pro"));
+    }
+}
@@ -0,0 +1,1140 @@
+//! 安装与备份工作流
+//!
+//! PE 图形界面、PE 命令行和正常系统端的直接安装使用同一套步骤顺序、
+//! 跳过条件、出错处理和断点续装逻辑。各端只需：
+//!
+//! - 实现 [`InstallBackend`] / [`BackupBackend`]，调用本端的 DISM、Ghost、
+//!   bcdedit 等工具完成单个操作；
+//! - 提供一个 [`EventSink`]，把 [`WorkflowEvent`] 显示到界面或输出到控制台。
+//!
+//! 格式化、释放镜像和修复引导失败时流程终止；驱动、高级选项、无人值守和
+//! 清理失败只产生警告，不影响新系统启动。
+
+pub mod steps;
+
+use std::path::Path;
+use std::sync::mpsc::{channel, Sender};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::backup::catalog::{self, tag_description};
+use crate::backup::compression::{esd_path, BackupFormat, Compression};
+use crate::backup::retention::{apply_retention, RetentionPolicy};
+use crate::backup::exclusions::ExclusionConfig;
+use crate::backup::repository::Repository;
+use crate::backup::verify::{verify_backup, VerifyReport};
+use crate::disk_layout::Firmware;
+use crate::journal::{Journal, JournalStep};
+use crate::unattend::MergeConflict;
+use crate::wim;
+
+pub use steps::{BackupStep, InstallStep};
+
+/// 日志中记录的备份方式（追加到已有镜像或新建镜像）
+const APPEND_IMAGE_KEY: &str = "AppendImage";
+/// 日志中记录的目标分区已开始格式化或重新分区
+const TARGET_FORMATTED_KEY: &str = "TargetFormatted";
+
+/// 工作流事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkflowEvent<S> {
+    /// 开始执行步骤
+    Step(S),
+    /// 当前步骤进度 (0-100)
+    Progress(u8),
+    /// 状态说明
+    Status(String),
+    /// 不影响继续执行的错误
+    Warning(String),
+    /// 全部步骤完成
+    Completed,
+    /// 步骤失败，流程终止
+    Failed(String),
+}
+
+/// 接收工作流事件
+///
+/// 进度可能从执行外部工具的转发线程中发出，因此要求 `Sync`。
+pub trait EventSink<S>: Sync {
+    fn emit(&self, event: WorkflowEvent<S>);
+}
+
+impl<S, F: Fn(WorkflowEvent<S>) + Sync> EventSink<S> for F {
+    fn emit(&self, event: WorkflowEvent<S>) {
+        self(event)
+    }
+}
+
+impl<S: Send> EventSink<S> for Sender<WorkflowEvent<S>> {
+    fn emit(&self, event: WorkflowEvent<S>) {
+        let _ = self.send(event);
+    }
+}
+
+/// 步骤进度回调
+pub type Progress<'a> = &'a (dyn Fn(u8) + Sync);
+
+/// 在工作线程中执行通过通道报告进度的操作，并把进度转发给 `progress`
+///
+/// 外部工具可能把发送端交给自己的读取线程，通道不一定随操作结束而关闭，
+/// 因此以工作线程结束为准，而不是等待通道关闭。
+pub fn forward_progress<P: Send, T: Send>(
+    progress: Progress,
+    percentage: fn(&P) -> u8,
+    run: impl FnOnce(Sender<P>) -> Result<T> + Send,
+) -> Result<T> {
+    let (tx, rx) = channel();
+    std::thread::scope(|s| {
+        let worker = s.spawn(move || run(tx));
+        while !worker.is_finished() {
+            if let Ok(p) = rx.recv_timeout(Duration::from_millis(100)) {
+                progress(percentage(&p));
+            }
+        }
+        while let Ok(p) = rx.try_recv() {
+            progress(percentage(&p));
+        }
+        worker
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("执行线程异常退出")))
+    })
+}
+
+/// 释放镜像前如何处理目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetPreparation {
+    /// 直接释放到现有分区
+    Keep,
+    /// 格式化目标分区
+    Format,
+    /// 清空整块磁盘并按布局重新分区
+    Partition,
+}
+
+/// 安装参数
+#[derive(Debug, Clone)]
+pub struct InstallPlan {
+    /// 目标分区，如 "C:"
+    pub target_partition: String,
+    /// 镜像完整路径
+    pub image_path: String,
+    pub volume_index: u32,
+    pub is_gho: bool,
+    pub preparation: TargetPreparation,
+    /// 驱动目录：导出驱动时写入，导入驱动时读取；None 时跳过这两步
+    pub driver_dir: Option<String>,
+    /// 释放镜像前从当前系统导出驱动到 `driver_dir`
+    pub export_drivers: bool,
+    /// 修复引导使用的固件类型，None 时不修复
+    pub boot_firmware: Option<Firmware>,
+    pub unattended: bool,
+}
+
+/// 安装时各端需要实现的操作
+pub trait InstallBackend {
+    /// 按 `plan.preparation` 格式化分区或重新分区
+    fn prepare_target(&self, plan: &InstallPlan) -> Result<()>;
+    fn export_drivers(&self, driver_dir: &str) -> Result<()>;
+    fn apply_image(&self, plan: &InstallPlan, progress: Progress) -> Result<()>;
+    fn import_drivers(&self, target_partition: &str, driver_dir: &str) -> Result<()>;
+    fn repair_boot(&self, target_partition: &str, firmware: Firmware) -> Result<()>;
+    fn apply_advanced_options(&self, target_partition: &str) -> Result<()>;
+    /// 生成无人值守配置，返回与用户应答文件合并时的冲突
+    fn generate_unattend(&self, target_partition: &str) -> Result<Vec<MergeConflict>>;
+    /// 删除临时文件等收尾工作
+    fn cleanup(&self, plan: &InstallPlan) -> Result<()>;
+}
+
+/// 执行安装
+///
+/// 提供步骤日志时跳过日志中已完成的步骤，并记录每一步的开始、完成和失败。
+pub fn run_install(
+    plan: &InstallPlan,
+    backend: &dyn InstallBackend,
+    sink: &dyn EventSink<InstallStep>,
+    mut journal: Option<&mut Journal<InstallStep>>,
+) -> Result<()> {
+    let steps = match journal.as_deref() {
+        Some(journal) => journal.remaining_steps(),
+        None => InstallStep::sequence().to_vec(),
+    };
+    if let Some(step) = steps.first().filter(|_| journal.as_deref().is_some_and(|j| j.has_progress())) {
+        log::info!("从步骤 [{}] 继续安装", step.name());
+    }
+
+    for step in steps {
+        sink.emit(WorkflowEvent::Step(step));
+        if let Some(journal) = journal.as_deref_mut() {
+            warn_journal_error(journal.begin(step));
+            if step == InstallStep::FormatPartition && plan.preparation != TargetPreparation::Keep {
+                warn_journal_error(journal.set_value(TARGET_FORMATTED_KEY, "true"));
+            }
+        }
+
+        let result = run_install_step(step, plan, backend, sink);
+        if let Err(e) = result {
+            let message = format!("{}失败: {}", step.name(), e);
+            if is_fatal_install_step(step) {
+                log::error!("{}", message);
+                if let Some(journal) = journal.as_deref_mut() {
+                    warn_journal_error(journal.fail(step, &message));
+                }
+                sink.emit(WorkflowEvent::Failed(message.clone()));
+                anyhow::bail!(message);
+            }
+            log::warn!("{}", message);
+            sink.emit(WorkflowEvent::Warning(message));
+        }
+        sink.emit(WorkflowEvent::Progress(100));
+
+        // 清理步骤可能连同数据目录一起删除日志
+        if step != InstallStep::Cleanup {
+            if let Some(journal) = journal.as_deref_mut() {
+                warn_journal_error(journal.complete(step));
+            }
+        }
+    }
+
+    sink.emit(WorkflowEvent::Step(InstallStep::Complete));
+    sink.emit(WorkflowEvent::Completed);
+    Ok(())
+}
+
+/// 日志记录的安装是否已格式化或重新分区目标分区
+///
+/// 未启用格式化时格式化步骤只是跳过，不算在内，原系统的引导项仍然有效。
+pub fn target_formatted(journal: &Journal<InstallStep>) -> bool {
+    journal.value(TARGET_FORMATTED_KEY) == Some("true")
+}
+
+/// 失败后新系统无法启动的步骤
+fn is_fatal_install_step(step: InstallStep) -> bool {
+    matches!(
+        step,
+        InstallStep::FormatPartition | InstallStep::ApplyImage | InstallStep::RepairBoot
+    )
+}
+
+fn run_install_step(
+    step: InstallStep,
+    plan: &InstallPlan,
+    backend: &dyn InstallBackend,
+    sink: &dyn EventSink<InstallStep>,
+) -> Result<()> {
+    let status = |message: &str| sink.emit(WorkflowEvent::Status(message.to_string()));
+    let target = plan.target_partition.as_str();
+
+    match step {
+        InstallStep::FormatPartition => match plan.preparation {
+            TargetPreparation::Keep => status("跳过格式化"),
+            TargetPreparation::Format => {
+                status("正在格式化目标分区...");
+                backend.prepare_target(plan)?;
+            }
+            TargetPreparation::Partition => {
+                status("正在清空磁盘并重新分区...");
+                backend.prepare_target(plan)?;
+            }
+        },
+        InstallStep::ExportDrivers => match &plan.driver_dir {
+            Some(dir) if plan.export_drivers => {
+                status("正在导出驱动...");
+                backend.export_drivers(dir)?;
+            }
+            _ => status("跳过导出驱动"),
+        },
+        InstallStep::ApplyImage => {
+            status("正在释放系统镜像...");
+            if !Path::new(&plan.image_path).exists() {
+                anyhow::bail!("镜像文件不存在: {}", plan.image_path);
+            }
+            let progress = |p: u8| sink.emit(WorkflowEvent::Progress(p));
+            backend.apply_image(plan, &progress)?;
+        }
+        InstallStep::ImportDrivers => match &plan.driver_dir {
+            Some(dir) if Path::new(dir).exists() => {
+                status("正在导入驱动...");
+                backend.import_drivers(target, dir)?;
+            }
+            _ => status("跳过驱动导入"),
+        },
+        InstallStep::RepairBoot => match plan.boot_firmware {
+            Some(firmware) => {
+                status("正在修复引导...");
+                backend.repair_boot(target, firmware)?;
+            }
+            None => status("跳过修复引导"),
+        },
+        InstallStep::ApplyAdvancedOptions => {
+            status("正在应用高级选项...");
+            backend.apply_advanced_options(target)?;
+        }
+        InstallStep::GenerateUnattend => {
+            if plan.unattended {
+                status("正在生成无人值守配置...");
+                for conflict in backend.generate_unattend(target)? {
+                    sink.emit(WorkflowEvent::Warning(format!("应答文件合并冲突 {}", conflict)));
+                }
+            } else {
+                status("跳过无人值守配置");
+            }
+        }
+        InstallStep::Cleanup => {
+            status("正在清理临时文件...");
+            backend.cleanup(plan)?;
+        }
+        InstallStep::Complete => {}
+    }
+    Ok(())
+}
+
+/// 备份参数
+#[derive(Debug, Clone)]
+pub struct BackupPlan {
+    /// 源分区，如 "C:"
+    pub source_partition: String,
+    /// 镜像保存路径
+    pub save_path: String,
+    pub name: String,
+    pub description: String,
+    /// WIM 或 GHO；GHO 时忽略增量、排除列表、验证、ESD 导出和保留策略
+    pub format: BackupFormat,
+    /// 镜像已存在时追加为新卷
+    pub incremental: bool,
+    /// 备份后重新读取镜像并与源分区比较，报告写在镜像旁
+    pub verify: bool,
+    /// 备份完成后按策略删除旧的卷，None 时保留全部
+    pub retention: Option<RetentionPolicy>,
+    /// 捕获时排除和不压缩的文件
+    pub exclusions: ExclusionConfig,
+    /// 新建镜像时的压缩方式，追加时沿用镜像原有的压缩方式
+    pub compression: Compression,
+    /// 备份后把新卷导出（追加）到镜像旁的 ESD 归档副本
+    pub export_esd: bool,
+}
+
+/// 备份时各端需要实现的操作
+pub trait BackupBackend {
+    /// 按 `plan.format` 备份源分区，按 `plan.exclusions` 排除文件；`append` 为 true 时追加到已有 WIM
+    fn capture_image(&self, plan: &BackupPlan, append: bool, progress: Progress) -> Result<()>;
+    /// 验证镜像中刚备份的卷，默认直接读取镜像文件并遍历源分区
+    fn verify_image(&self, plan: &BackupPlan, progress: Progress) -> Result<VerifyReport> {
+        verify_backup(
+            Path::new(&plan.save_path),
+            Path::new(&format!("{}\\", plan.source_partition)),
+            &plan.name,
+            &plan.exclusions,
+            progress,
+        )
+    }
+    /// 把镜像中的一卷导出（追加）到另一个镜像，用于按保留策略重建镜像
+    ///
+    /// 目标镜像不存在时以 `compression` 新建，已存在时 DISM 沿用其压缩方式。
+    fn export_image(&self, source: &Path, index: u32, destination: &Path, compression: Compression) -> Result<()>;
+    /// 把镜像中的一卷以 recovery 压缩导出（追加）到 ESD 文件
+    fn export_esd(&self, source: &Path, index: u32, destination: &Path, progress: Progress) -> Result<()>;
+    /// 源分区所属计算机的名称，记录在卷描述中；默认读取分区上系统的注册表
+    fn source_machine(&self, plan: &BackupPlan) -> Option<String> {
+        catalog::source_machine(&plan.source_partition)
+    }
+    /// 删除本次进入 PE 的引导项
+    fn restore_boot(&self) -> Result<()>;
+    fn cleanup(&self, plan: &BackupPlan) -> Result<()>;
+}
+
+/// 执行备份
+///
+/// 追加还是新建镜像在第一次运行时确定并记入日志：中断后镜像文件可能
+/// 已被创建了一半，不能再据此判断。新建镜像中途中断时先删除残留文件。
+pub fn run_backup(
+    plan: &BackupPlan,
+    backend: &dyn BackupBackend,
+    sink: &dyn EventSink<BackupStep>,
+    mut journal: Option<&mut Journal<BackupStep>>,
+) -> Result<()> {
+    let recorded = journal.as_deref().and_then(|j| j.value(APPEND_IMAGE_KEY)).map(|v| v == "true");
+    let append = match recorded {
+        Some(append) => append,
+        None => {
+            // Ghost 不能追加，已有的 GHO 文件被覆盖；仓库每次都是新的一次备份
+            let append = plan.incremental && plan.format == BackupFormat::Wim && Path::new(&plan.save_path).exists();
+            if let Some(journal) = journal.as_deref_mut() {
+                warn_journal_error(journal.set_value(APPEND_IMAGE_KEY, &append.to_string()));
+            }
+            append
+        }
+    };
+    // 仓库中断时只残留未被清单引用的块，由垃圾回收删除
+    let discard_partial_image = !append
+        && !plan.format.is_dedup()
+        && journal.as_deref().and_then(|j| j.interrupted_step()) == Some(BackupStep::CaptureImage);
+
+    let steps = match journal.as_deref() {
+        Some(journal) => journal.remaining_steps(),
+        None => BackupStep::sequence().to_vec(),
+    };
+    if let Some(step) = steps.first().filter(|_| journal.as_deref().is_some_and(|j| j.has_progress())) {
+        log::info!("从步骤 [{}] 继续备份", step.name());
+    }
+
+    for step in steps {
+        sink.emit(WorkflowEvent::Step(step));
+        if let Some(journal) = journal.as_deref_mut() {
+            warn_journal_error(journal.begin(step));
+        }
+
+        let result = run_backup_step(step, plan, backend, sink, append, discard_partial_image);
+        if let Err(e) = result {
+            let message = format!("{}失败: {}", step.name(), e);
+            if is_fatal_backup_step(step) {
+                log::error!("{}", message);
+                if let Some(journal) = journal.as_deref_mut() {
+                    warn_journal_error(journal.fail(step, &message));
+                }
+                sink.emit(WorkflowEvent::Failed(message.clone()));
+                anyhow::bail!(message);
+            }
+            log::warn!("{}", message);
+            sink.emit(WorkflowEvent::Warning(message));
+        }
+        sink.emit(WorkflowEvent::Progress(100));
+
+        // 清理步骤可能连同数据目录一起删除日志
+        if step != BackupStep::Cleanup {
+            if let Some(journal) = journal.as_deref_mut() {
+                warn_journal_error(journal.complete(step));
+            }
+        }
+    }
+
+    sink.emit(WorkflowEvent::Step(BackupStep::Complete));
+    sink.emit(WorkflowEvent::Completed);
+    Ok(())
+}
+
+/// 失败后备份不可用的步骤
+fn is_fatal_backup_step(step: BackupStep) -> bool {
+    matches!(
+        step,
+        BackupStep::ReadConfig | BackupStep::CaptureImage | BackupStep::VerifyBackup
+    )
+}
+
+fn run_backup_step(
+    step: BackupStep,
+    plan: &BackupPlan,
+    backend: &dyn BackupBackend,
+    sink: &dyn EventSink<BackupStep>,
+    append: bool,
+    discard_partial_image: bool,
+) -> Result<()> {
+    let status = |message: &str| sink.emit(WorkflowEvent::Status(message.to_string()));
+
+    match step {
+        BackupStep::ReadConfig => {
+            status("正在读取备份配置...");
+            if plan.save_path.is_empty() {
+                anyhow::bail!("备份配置缺少保存路径");
+            }
+        }
+        BackupStep::CaptureImage => {
+            status("正在执行系统备份...");
+            if discard_partial_image && Path::new(&plan.save_path).exists() {
+                log::info!("删除上次中断时残留的镜像: {}", plan.save_path);
+                std::fs::remove_file(&plan.save_path)?;
+            }
+            let progress = |p: u8| sink.emit(WorkflowEvent::Progress(p));
+            match backend.source_machine(plan) {
+                Some(machine) => {
+                    let plan = BackupPlan {
+                        description: tag_description(&plan.description, &machine),
+                        ..plan.clone()
+                    };
+                    backend.capture_image(&plan, append, &progress)?;
+                }
+                None => backend.capture_image(plan, append, &progress)?,
+            }
+        }
+        BackupStep::VerifyBackup => {
+            status("正在验证备份文件...");
+            if !Path::new(&plan.save_path).exists() {
+                anyhow::bail!("备份文件不存在: {}", plan.save_path);
+            }
+            if plan.verify && plan.format.is_gho() {
+                sink.emit(WorkflowEvent::Warning("GHO 镜像不支持备份验证，已跳过".to_string()));
+            } else if plan.verify && plan.format.is_dedup() {
+                status("正在校验备份仓库中的数据块...");
+                let repository = Repository::open(Path::new(&plan.save_path))?;
+                let id = repository.snapshot_ids()?.last().copied().context("备份仓库中没有备份")?;
+                let progress = |p: u8| sink.emit(WorkflowEvent::Progress(p));
+                let problems = repository.check(id, &progress)?;
+                if !problems.is_empty() {
+                    anyhow::bail!("备份 {} 有 {} 个问题: {}", id, problems.len(), problems[..problems.len().min(5)].join("; "));
+                }
+            } else if plan.verify {
+                status("正在校验镜像并与源分区比较...");
+                let progress = |p: u8| sink.emit(WorkflowEvent::Progress(p));
+                let report = backend.verify_image(plan, &progress)?;
+                match report.append_to_file() {
+                    Ok(path) => status(&format!("验证报告: {}", path.display())),
+                    Err(e) => sink.emit(WorkflowEvent::Warning(format!("写入验证报告失败: {}", e))),
+                }
+                for warning in &report.warnings {
+                    sink.emit(WorkflowEvent::Warning(format!("备份验证: {}", warning)));
+                }
+                if !report.passed() {
+                    anyhow::bail!("{}", report.errors.join("; "));
+                }
+            }
+        }
+        BackupStep::ExportEsd => {
+            if !plan.export_esd || plan.format != BackupFormat::Wim {
+                return Ok(());
+            }
+            let destination = esd_path(&plan.save_path);
+            status(&format!("正在导出ESD镜像: {}", destination));
+            // 刚捕获或追加的卷是镜像中的最后一卷
+            let mut file = std::fs::File::open(&plan.save_path)?;
+            let index = wim::WimHeader::read(&mut file)?.image_count;
+            drop(file);
+            let progress = |p: u8| sink.emit(WorkflowEvent::Progress(p));
+            backend.export_esd(Path::new(&plan.save_path), index, Path::new(&destination), &progress)?;
+        }
+        BackupStep::ApplyRetention => {
+            let Some(policy) = plan.retention.filter(|_| !plan.format.is_gho()) else {
+                return Ok(());
+            };
+            status(&format!("正在清理旧备份（{}）...", policy.describe()));
+            if plan.format.is_dedup() {
+                let repository = Repository::open(Path::new(&plan.save_path))?;
+                let (outcome, gc) = repository.apply_retention(policy, wim::filetime_now())?;
+                if !outcome.removed.is_empty() {
+                    status(&format!(
+                        "已删除 {} 个旧备份，保留 {} 个，回收 {} 个数据块",
+                        outcome.removed.len(),
+                        outcome.kept.len(),
+                        gc.removed_chunks
+                    ));
+                }
+                return Ok(());
+            }
+            let export = |source: &Path, index: u32, destination: &Path| {
+                backend.export_image(source, index, destination, plan.compression)
+            };
+            let outcome = apply_retention(Path::new(&plan.save_path), policy, wim::filetime_now(), &export)?;
+            if !outcome.removed.is_empty() {
+                status(&format!("已删除 {} 个旧备份，保留 {} 个", outcome.removed.len(), outcome.kept.len()));
+            }
+
+            // ESD 副本按同一策略重新导出保留的卷，否则其中的卷只增不减
+            let esd = esd_path(&plan.save_path);
+            if Path::new(&esd).exists() {
+                let export_esd = |source: &Path, index: u32, destination: &Path| {
+                    backend.export_esd(source, index, destination, &|_| {})
+                };
+                match apply_retention(Path::new(&esd), policy, wim::filetime_now(), &export_esd) {
+                    Ok(outcome) if !outcome.removed.is_empty() => {
+                        status(&format!("已从ESD镜像删除 {} 个旧备份", outcome.removed.len()))
+                    }
+                    Ok(_) => {}
+                    Err(e) => sink.emit(WorkflowEvent::Warning(format!("清理ESD镜像失败: {:#}", e))),
+                }
+            }
+        }
+        BackupStep::RepairBoot => {
+            status("正在恢复引导...");
+            backend.restore_boot()?;
+        }
+        BackupStep::Cleanup => {
+            status("正在清理临时文件...");
+            backend.cleanup(plan)?;
+        }
+        BackupStep::Complete => {}
+    }
+    Ok(())
+}
+
+/// 日志写入失败不中断流程，只是失去断点续装的能力
+fn warn_journal_error(result: Result<()>) {
+    if let Err(e) = result {
+        log::warn!("写入步骤日志失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{CommandOutput, FakeRunner, Invocation};
+    use crate::diskpart::script::RunMode;
+    use crate::tools::{CaptureOptions, ToolPaths, Tools};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    /// 记录调用顺序的后端，可指定失败的操作
+    #[derive(Default)]
+    struct FakeBackend {
+        calls: Mutex<Vec<String>>,
+        fail: Option<&'static str>,
+    }
+
+    impl FakeBackend {
+        fn call(&self, name: &'static str) -> Result<()> {
+            self.calls.lock().unwrap().push(name.to_string());
+            if self.fail == Some(name) {
+                anyhow::bail!("{} 出错", name);
+            }
+            Ok(())
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl InstallBackend for FakeBackend {
+        fn prepare_target(&self, _plan: &InstallPlan) -> Result<()> {
+            self.call("prepare")
+        }
+        fn export_drivers(&self, _driver_dir: &str) -> Result<()> {
+            self.call("export")
+        }
+        fn apply_image(&self, _plan: &InstallPlan, progress: Progress) -> Result<()> {
+            progress(50);
+            self.call("apply")
+        }
+        fn import_drivers(&self, _target: &str, _driver_dir: &str) -> Result<()> {
+            self.call("import")
+        }
+        fn repair_boot(&self, _target: &str, _firmware: Firmware) -> Result<()> {
+            self.call("boot")
+        }
+        fn apply_advanced_options(&self, _target: &str) -> Result<()> {
+            self.call("advanced")
+        }
+        fn generate_unattend(&self, _target: &str) -> Result<Vec<MergeConflict>> {
+            self.call("unattend").map(|_| Vec::new())
+        }
+        fn cleanup(&self, _plan: &InstallPlan) -> Result<()> {
+            self.call("cleanup")
+        }
+    }
+
+    impl BackupBackend for FakeBackend {
+        fn capture_image(&self, plan: &BackupPlan, append: bool, progress: Progress) -> Result<()> {
+            self.call(if append { "append" } else { "capture" })?;
+            if plan.format.is_dedup() {
+                // 与各端后端相同地备份到仓库，源分区在测试中是一个目录
+                let repository = Repository::open_or_create(Path::new(&plan.save_path))?;
+                let source = Path::new(&plan.source_partition);
+                repository.backup(source, &plan.name, &plan.description, &plan.exclusions, progress)?;
+                return Ok(());
+            }
+            std::fs::write(&plan.save_path, b"image")?;
+            Ok(())
+        }
+        fn export_image(&self, _source: &Path, _index: u32, _destination: &Path, _compression: Compression) -> Result<()> {
+            self.call("export")
+        }
+        fn export_esd(&self, _source: &Path, _index: u32, _destination: &Path, _progress: Progress) -> Result<()> {
+            self.call("esd")
+        }
+        fn source_machine(&self, _plan: &BackupPlan) -> Option<String> {
+            None
+        }
+        fn restore_boot(&self) -> Result<()> {
+            self.call("boot")
+        }
+        fn cleanup(&self, _plan: &BackupPlan) -> Result<()> {
+            self.call("cleanup")
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lr_workflow_{}_{}", name, std::process::id()))
+    }
+
+    fn install_plan(image: &Path) -> InstallPlan {
+        InstallPlan {
+            target_partition: "D:".to_string(),
+            image_path: image.to_string_lossy().into_owned(),
+            volume_index: 1,
+            is_gho: false,
+            preparation: TargetPreparation::Format,
+            driver_dir: None,
+            export_drivers: false,
+            boot_firmware: Some(Firmware::Uefi),
+            unattended: false,
+        }
+    }
+
+    #[test]
+    fn test_install_skips_and_warnings() {
+        let image = temp_path("install.wim");
+        std::fs::write(&image, b"MSWIM\0\0\0").unwrap();
+        let plan = install_plan(&image);
+        let backend = FakeBackend { fail: Some("advanced"), ..Default::default() };
+        let events = Mutex::new(Vec::new());
+        let sink = |e: WorkflowEvent<InstallStep>| events.lock().unwrap().push(e);
+
+        run_install(&plan, &backend, &sink, None).unwrap();
+        std::fs::remove_file(&image).unwrap();
+
+        // 没有驱动目录、未启用无人值守时跳过对应操作；高级选项失败不终止流程
+        assert_eq!(backend.calls(), ["prepare", "apply", "boot", "advanced", "cleanup"]);
+        let events = events.into_inner().unwrap();
+        assert!(events.contains(&WorkflowEvent::Progress(50)));
+        assert!(events.contains(&WorkflowEvent::Warning("应用高级选项失败: advanced 出错".to_string())));
+        assert_eq!(events.last(), Some(&WorkflowEvent::Completed));
+    }
+
+    #[test]
+    fn test_install_failure_and_resume() {
+        let image = temp_path("resume.wim");
+        std::fs::write(&image, b"MSWIM\0\0\0").unwrap();
+        let journal_path = temp_path("install_journal.ini");
+        let plan = install_plan(&image);
+
+        let backend = FakeBackend { fail: Some("boot"), ..Default::default() };
+        let mut journal = Journal::<InstallStep>::open(&journal_path, "Install").unwrap();
+        let sink = |_: WorkflowEvent<InstallStep>| {};
+        let err = run_install(&plan, &backend, &sink, Some(&mut journal)).unwrap_err();
+        assert_eq!(err.to_string(), "修复引导失败: boot 出错");
+        assert_eq!(backend.calls(), ["prepare", "apply", "boot"]);
+        assert!(target_formatted(&journal));
+
+        // 重新运行时从失败的步骤继续，不再格式化和释放镜像
+        let backend = FakeBackend::default();
+        let mut journal = Journal::<InstallStep>::load(&journal_path).unwrap().unwrap();
+        assert_eq!(journal.last_error(), Some("修复引导失败: boot 出错"));
+        run_install(&plan, &backend, &sink, Some(&mut journal)).unwrap();
+        assert_eq!(backend.calls(), ["boot", "advanced", "cleanup"]);
+
+        std::fs::remove_file(&image).unwrap();
+        journal.remove().unwrap();
+    }
+
+    #[test]
+    fn test_keep_target_is_not_formatted() {
+        let image = temp_path("keep.wim");
+        std::fs::write(&image, b"MSWIM\0\0\0").unwrap();
+        let journal_path = temp_path("keep_journal.ini");
+        let plan = InstallPlan {
+            preparation: TargetPreparation::Keep,
+            ..install_plan(&image)
+        };
+
+        let backend = FakeBackend { fail: Some("boot"), ..Default::default() };
+        let mut journal = Journal::<InstallStep>::open(&journal_path, "Install").unwrap();
+        let sink = |_: WorkflowEvent<InstallStep>| {};
+        run_install(&plan, &backend, &sink, Some(&mut journal)).unwrap_err();
+        std::fs::remove_file(&image).unwrap();
+
+        // 跳过的格式化步骤也记为已开始，但目标分区没有被格式化
+        let journal = Journal::<InstallStep>::load(&journal_path).unwrap().unwrap();
+        assert!(journal.has_started(InstallStep::FormatPartition));
+        assert!(!target_formatted(&journal));
+        journal.remove().unwrap();
+    }
+
+    #[test]
+    fn test_backup_decides_append_once() {
+        let save_path = temp_path("backup.wim");
+        let journal_path = temp_path("backup_journal.ini");
+        let plan = BackupPlan {
+            source_partition: "C:".to_string(),
+            save_path: save_path.to_string_lossy().into_owned(),
+            name: "备份".to_string(),
+            description: String::new(),
+            format: BackupFormat::Wim,
+            incremental: true,
+            verify: false,
+            retention: None,
+            exclusions: ExclusionConfig::default(),
+            compression: Compression::Max,
+            export_esd: false,
+        };
+
+        // 镜像不存在时新建，捕获中途失败
+        let mut journal = Journal::<BackupStep>::open(&journal_path, "Backup").unwrap();
+        let backend = FakeBackend { fail: Some("capture"), ..Default::default() };
+        let sink = |_: WorkflowEvent<BackupStep>| {};
+        assert!(run_backup(&plan, &backend, &sink, Some(&mut journal)).is_err());
+
+        // 续备时镜像文件已存在一半，仍按日志新建，并先删除残留文件
+        std::fs::write(&save_path, b"partial").unwrap();
+        let mut journal = Journal::<BackupStep>::load(&journal_path).unwrap().unwrap();
+        let backend = FakeBackend::default();
+        run_backup(&plan, &backend, &sink, Some(&mut journal)).unwrap();
+        assert_eq!(backend.calls(), ["capture", "boot", "cleanup"]);
+        assert_eq!(std::fs::read(&save_path).unwrap(), b"image");
+
+        std::fs::remove_file(&save_path).unwrap();
+        journal.remove().unwrap();
+    }
+
+    #[test]
+    fn test_backup_verify_failure_is_fatal() {
+        let save_path = temp_path("verify.wim");
+        let plan = BackupPlan {
+            source_partition: "C:".to_string(),
+            save_path: save_path.to_string_lossy().into_owned(),
+            name: "备份".to_string(),
+            description: String::new(),
+            format: BackupFormat::Wim,
+            incremental: false,
+            verify: true,
+            retention: None,
+            exclusions: ExclusionConfig::default(),
+            compression: Compression::Max,
+            export_esd: false,
+        };
+
+        // 假后端写入的不是 WIM 镜像，验证失败后不再恢复引导
+        let backend = FakeBackend::default();
+        let sink = |_: WorkflowEvent<BackupStep>| {};
+        let err = run_backup(&plan, &backend, &sink, None).unwrap_err();
+        assert_eq!(err.to_string(), "验证备份文件失败: 文件过短，不是 WIM 镜像");
+        assert_eq!(backend.calls(), ["capture"]);
+
+        std::fs::remove_file(&save_path).unwrap();
+    }
+
+    #[test]
+    fn test_backup_dedup_repository() {
+        let source = temp_path("dedup_source");
+        let repository_dir = temp_path("dedup_repository");
+        let _ = std::fs::remove_dir_all(&repository_dir);
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.txt"), b"first").unwrap();
+        let plan = BackupPlan {
+            source_partition: source.to_string_lossy().into_owned(),
+            save_path: repository_dir.to_string_lossy().into_owned(),
+            name: "备份".to_string(),
+            description: String::new(),
+            format: BackupFormat::Dedup,
+            incremental: true,
+            verify: true,
+            retention: Some(RetentionPolicy::KeepLast(1)),
+            exclusions: ExclusionConfig::default(),
+            compression: Compression::Max,
+            export_esd: true,
+        };
+
+        // 每次都是仓库中新的一次备份，校验通过；不导出 ESD，保留策略删除旧备份
+        let backend = FakeBackend::default();
+        let sink = |_: WorkflowEvent<BackupStep>| {};
+        run_backup(&plan, &backend, &sink, None).unwrap();
+        std::fs::write(source.join("a.txt"), b"second").unwrap();
+        let events = Mutex::new(Vec::new());
+        let sink = |event: WorkflowEvent<BackupStep>| events.lock().unwrap().push(event);
+        run_backup(&plan, &backend, &sink, None).unwrap();
+        assert_eq!(backend.calls(), ["capture", "boot", "cleanup", "capture", "boot", "cleanup"]);
+        let events = events.into_inner().unwrap();
+        assert!(events.contains(&WorkflowEvent::Status("已删除 1 个旧备份，保留 1 个，回收 1 个数据块".to_string())));
+        assert!(!events.iter().any(|e| matches!(e, WorkflowEvent::Warning(_))));
+
+        let repository = Repository::open(&repository_dir).unwrap();
+        assert_eq!(repository.snapshot_ids().unwrap(), [2]);
+
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&repository_dir);
+    }
+
+    #[test]
+    fn test_backup_esd_failure_is_warning() {
+        let save_path = temp_path("esd.wim");
+        let plan = BackupPlan {
+            source_partition: "C:".to_string(),
+            save_path: save_path.to_string_lossy().into_owned(),
+            name: "备份".to_string(),
+            description: String::new(),
+            format: BackupFormat::Wim,
+            incremental: false,
+            verify: false,
+            retention: None,
+            exclusions: ExclusionConfig::default(),
+            compression: Compression::Fast,
+            export_esd: true,
+        };
+
+        // 读不出卷数时不导出，WIM 备份本身仍然可用
+        let backend = FakeBackend::default();
+        let events = Mutex::new(Vec::new());
+        let sink = |event: WorkflowEvent<BackupStep>| events.lock().unwrap().push(event);
+        run_backup(&plan, &backend, &sink, None).unwrap();
+        assert_eq!(backend.calls(), ["capture", "boot", "cleanup"]);
+        let events = events.into_inner().unwrap();
+        assert!(events.contains(&WorkflowEvent::Warning("导出ESD镜像失败: 文件过短，不是 WIM 镜像".to_string())));
+        assert_eq!(events.last(), Some(&WorkflowEvent::Completed));
+
+        std::fs::remove_file(&save_path).unwrap();
+    }
+
+    #[test]
+    fn test_backup_gho_skips_wim_steps() {
+        let save_path = temp_path("backup.gho");
+        std::fs::write(&save_path, b"old").unwrap();
+        let plan = BackupPlan {
+            source_partition: "D:".to_string(),
+            save_path: save_path.to_string_lossy().into_owned(),
+            name: "备份".to_string(),
+            description: String::new(),
+            format: BackupFormat::Gho(9),
+            incremental: true,
+            verify: true,
+            retention: Some(RetentionPolicy::KeepLast(1)),
+            exclusions: ExclusionConfig::default(),
+            compression: Compression::Max,
+            export_esd: true,
+        };
+
+        // 已有的 GHO 文件不追加；不读取 WIM 结构，也不导出和清理
+        let backend = FakeBackend::default();
+        let events = Mutex::new(Vec::new());
+        let sink = |event: WorkflowEvent<BackupStep>| events.lock().unwrap().push(event);
+        run_backup(&plan, &backend, &sink, None).unwrap();
+        assert_eq!(backend.calls(), ["capture", "boot", "cleanup"]);
+        let warnings: Vec<_> = events
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .filter(|e| matches!(e, WorkflowEvent::Warning(_)))
+            .collect();
+        assert_eq!(warnings, [WorkflowEvent::Warning("GHO 镜像不支持备份验证，已跳过".to_string())]);
+
+        std::fs::remove_file(&save_path).unwrap();
+    }
+
+    /// 与各端后端一样通过 [`Tools`] 调用外部工具的后端
+    struct ToolBackend {
+        tools: Tools,
+    }
+
+    impl InstallBackend for ToolBackend {
+        fn prepare_target(&self, plan: &InstallPlan) -> Result<()> {
+            let letter = plan.target_partition.chars().next().unwrap_or('C');
+            self.tools.format_volume(letter, RunMode::Execute).map(|_| ())
+        }
+        fn export_drivers(&self, _driver_dir: &str) -> Result<()> {
+            Ok(())
+        }
+        fn apply_image(&self, plan: &InstallPlan, progress: Progress) -> Result<()> {
+            let apply_dir = format!("{}\\", plan.target_partition);
+            self.tools.apply_image(&plan.image_path, &apply_dir, plan.volume_index, progress)
+        }
+        fn import_drivers(&self, _target: &str, _driver_dir: &str) -> Result<()> {
+            Ok(())
+        }
+        fn repair_boot(&self, target: &str, firmware: Firmware) -> Result<()> {
+            self.tools.write_boot_files(target, firmware, Some("S:"))
+        }
+        fn apply_advanced_options(&self, _target: &str) -> Result<()> {
+            Ok(())
+        }
+        fn generate_unattend(&self, _target: &str) -> Result<Vec<MergeConflict>> {
+            Ok(Vec::new())
+        }
+        fn cleanup(&self, _plan: &InstallPlan) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl BackupBackend for ToolBackend {
+        fn capture_image(&self, plan: &BackupPlan, append: bool, progress: Progress) -> Result<()> {
+            // 配置文件在捕获结束后删除
+            let config_file = plan.exclusions.write_temp()?;
+            let options = CaptureOptions {
+                config_file: Some(config_file.path().to_string_lossy().into_owned()),
+                compression: plan.compression,
+            };
+            let capture_dir = format!("{}\\", plan.source_partition);
+            let (image, name, description) = (&plan.save_path, &plan.name, &plan.description);
+            if append {
+                self.tools.append_image(image, &capture_dir, name, description, &options, progress)
+            } else {
+                self.tools.capture_image(image, &capture_dir, name, description, &options, progress)
+            }
+        }
+        fn export_image(&self, source: &Path, index: u32, destination: &Path, compression: Compression) -> Result<()> {
+            let compress = (!destination.exists()).then_some(compression.to_config_value());
+            let (source, destination) = (source.to_string_lossy(), destination.to_string_lossy());
+            self.tools.export_image(&source, index, &destination, compress, &|_| {})
+        }
+        fn export_esd(&self, source: &Path, index: u32, destination: &Path, progress: Progress) -> Result<()> {
+            // 追加到已有的 ESD 时沿用其压缩方式
+            let compress = (!destination.exists()).then_some("recovery");
+            let (source, destination) = (source.to_string_lossy(), destination.to_string_lossy());
+            self.tools.export_image(&source, index, &destination, compress, progress)
+        }
+        fn source_machine(&self, _plan: &BackupPlan) -> Option<String> {
+            None
+        }
+        fn restore_boot(&self) -> Result<()> {
+            Ok(())
+        }
+        fn cleanup(&self, _plan: &BackupPlan) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn tool_backend(fake: &Arc<FakeRunner>) -> ToolBackend {
+        let paths = ToolPaths {
+            dism: r"X:\bin\dism\dism.exe".to_string(),
+            diskpart: "diskpart.exe".to_string(),
+            bcdboot: r"X:\bin\bcdboot.exe".to_string(),
+            bootsect: None,
+        };
+        ToolBackend {
+            tools: Tools::new(fake.clone(), paths, |bytes| String::from_utf8_lossy(bytes).into_owned()),
+        }
+    }
+
+    #[test]
+    fn test_install_command_lines() {
+        let image = temp_path("tools_install.wim");
+        std::fs::write(&image, b"MSWIM\0\0\0").unwrap();
+        let plan = InstallPlan {
+            volume_index: 3,
+            ..install_plan(&image)
+        };
+
+        // diskpart 的脚本文件在执行后即被删除，执行时读出内容
+        let fake = Arc::new(FakeRunner::new());
+        let scripts = Arc::new(Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        fake.reply_with("diskpart", &["/s"], move |invocation| {
+            recorded.lock().unwrap().push(std::fs::read_to_string(&invocation.args[1]).unwrap());
+            CommandOutput::success("DiskPart 成功地格式化该卷。")
+        })
+        .reply("dism", &["/Apply-Image"], CommandOutput::success("[=====   ] 50.0%\r\n"))
+        .reply("bcdboot", &["UEFI"], CommandOutput::failure(1, "failed"))
+        .reply("bcdboot", &["ALL"], CommandOutput::success(""));
+
+        let events = Mutex::new(Vec::new());
+        let sink = |e: WorkflowEvent<InstallStep>| events.lock().unwrap().push(e);
+        run_install(&plan, &tool_backend(&fake), &sink, None).unwrap();
+        std::fs::remove_file(&image).unwrap();
+
+        assert_eq!(*scripts.lock().unwrap(), ["select volume D\nformat fs=ntfs quick override\n"]);
+        let lines: Vec<_> = fake.calls().iter().map(Invocation::command_line).collect();
+        assert!(lines[0].starts_with("diskpart.exe /s "));
+        assert_eq!(
+            lines[1..],
+            [
+                format!(r"X:\bin\dism\dism.exe /Apply-Image /ImageFile:{} /ApplyDir:D:\ /Index:3", image.display()),
+                r"X:\bin\bcdboot.exe D:\Windows /s S: /f UEFI /l zh-cn".to_string(),
+                r"X:\bin\bcdboot.exe D:\Windows /s S: /f ALL /l zh-cn".to_string(),
+            ]
+        );
+        assert!(events.into_inner().unwrap().contains(&WorkflowEvent::Progress(50)));
+    }
+
+    #[test]
+    fn test_backup_command_lines() {
+        let save_path = temp_path("tools_backup.wim");
+        let image_xml = |count: u32| {
+            let images: String = (1..=count)
+                .map(|i| format!("<IMAGE INDEX=\"{}\"><NAME>备份{}</NAME></IMAGE>", i, i))
+                .collect();
+            format!("<WIM>{}</WIM>", images)
+        };
+        std::fs::write(&save_path, wim::build_test_wim(&image_xml(1), &[0; 8], None)).unwrap();
+        let esd = esd_path(&save_path.to_string_lossy());
+        std::fs::write(&esd, wim::build_test_wim(&image_xml(1), &[0; 8], None)).unwrap();
+        let plan = BackupPlan {
+            source_partition: "C:".to_string(),
+            save_path: save_path.to_string_lossy().into_owned(),
+            name: "周备份".to_string(),
+            description: "说明".to_string(),
+            format: BackupFormat::Wim,
+            incremental: true,
+            verify: false,
+            retention: Some(RetentionPolicy::KeepLast(1)),
+            exclusions: ExclusionConfig::default(),
+            compression: Compression::Fast,
+            export_esd: true,
+        };
+
+        // 追加后镜像中多出一卷，ESD 副本导出这一卷；保留策略把 WIM 和 ESD 的最后一卷各自导出为新镜像
+        let fake = Arc::new(FakeRunner::new());
+        let appended = wim::build_test_wim(&image_xml(2), &[0; 8], None);
+        fake.reply_with("dism", &["/Append-Image"], move |invocation| {
+            let image = invocation.args[1].trim_start_matches("/ImageFile:");
+            std::fs::write(image, &appended).unwrap();
+            CommandOutput::success("")
+        });
+        fake.reply_with("dism", &["/Export-Image"], move |invocation| {
+            let destination = invocation.args[3].trim_start_matches("/DestinationImageFile:");
+            let count = wim::read_images(Path::new(destination)).map_or(0, |(header, _)| header.image_count);
+            std::fs::write(destination, wim::build_test_wim(&image_xml(count + 1), &[0; 8], None)).unwrap();
+            CommandOutput::success("")
+        });
+
+        let sink = |_: WorkflowEvent<BackupStep>| {};
+        run_backup(&plan, &tool_backend(&fake), &sink, None).unwrap();
+        std::fs::remove_file(&save_path).unwrap();
+        assert_eq!(wim::read_images(Path::new(&esd)).unwrap().0.image_count, 1);
+        std::fs::remove_file(&esd).unwrap();
+
+        let calls = fake.calls_to("dism");
+        assert_eq!(calls.len(), 4);
+        let (config_file, append_args) = calls[0].args.split_last().unwrap();
+        assert!(config_file.starts_with("/ConfigFile:"));
+        assert_eq!(
+            append_args,
+            [
+                "/Append-Image".to_string(),
+                format!("/ImageFile:{}", save_path.display()),
+                r"/CaptureDir:C:\".to_string(),
+                "/Name:周备份".to_string(),
+                "/Description:说明".to_string(),
+                "/CheckIntegrity".to_string(),
+            ]
+        );
+        // 追加到已有的 ESD 时不指定压缩方式
+        assert_eq!(
+            calls[1].command_line(),
+            format!(
+                r"X:\bin\dism\dism.exe /Export-Image /SourceImageFile:{} /SourceIndex:2 /DestinationImageFile:{} /CheckIntegrity",
+                save_path.display(),
+                esd_path(&plan.save_path)
+            )
+        );
+        // 重建的镜像沿用备份设置的压缩方式
+        assert_eq!(calls[2].args[2], "/SourceIndex:2");
+        assert_eq!(calls[2].args.last().unwrap(), "/Compress:fast");
+        assert_eq!(calls[3].args[1], format!("/SourceImageFile:{}", esd));
+        assert_eq!(calls[3].args[2], "/SourceIndex:2");
+        assert_eq!(calls[3].args.last().unwrap(), "/Compress:recovery");
+    }
+
+    #[test]
+    fn test_overall_progress() {
+        assert_eq!(InstallStep::FormatPartition.overall_progress(0), 0);
+        assert_eq!(InstallStep::ApplyImage.overall_progress(0), 8);
+        assert_eq!(InstallStep::ApplyImage.overall_progress(100), 83);
+        assert_eq!(InstallStep::Complete.overall_progress(0), 100);
+        assert_eq!(BackupStep::CaptureImage.overall_progress(50), 39);
+        assert_eq!(InstallStep::sequence().len(), InstallStep::total() - 1);
+    }
+
+    #[test]
+    fn test_forward_progress_with_leaked_sender() {
+        let received = Mutex::new(Vec::new());
+        let progress = |p: u8| received.lock().unwrap().push(p);
+        let result = forward_progress(&progress, |p: &u8| *p, |tx| {
+            // 模拟外部工具的读取线程在操作结束后仍持有发送端
+            let leaked = tx.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_secs(5));
+                drop(leaked);
+            });
+            tx.send(30)?;
+            tx.send(60)?;
+            Ok("done")
+        });
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(*received.lock().unwrap(), vec![30, 60]);
+    }
+}
@@ -0,0 +1,200 @@
+//! 安装和备份流程的步骤
+
+use crate::journal::JournalStep;
+
+/// 安装步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallStep {
+    FormatPartition,
+    ExportDrivers,
+    ApplyImage,
+    ImportDrivers,
+    RepairBoot,
+    ApplyAdvancedOptions,
+    GenerateUnattend,
+    Cleanup,
+    Complete,
+}
+
+impl InstallStep {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InstallStep::FormatPartition => "格式化分区",
+            InstallStep::ExportDrivers => "导出驱动",
+            InstallStep::ApplyImage => "释放系统镜像",
+            InstallStep::ImportDrivers => "导入驱动",
+            InstallStep::RepairBoot => "修复引导",
+            InstallStep::ApplyAdvancedOptions => "应用高级选项",
+            InstallStep::GenerateUnattend => "生成无人值守配置",
+            InstallStep::Cleanup => "清理临时文件",
+            InstallStep::Complete => "完成安装",
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Self::all().iter().position(|s| s == self).unwrap_or(0)
+    }
+
+    pub fn total() -> usize {
+        Self::all().len()
+    }
+
+    /// 全部步骤（含完成），按执行顺序排列
+    pub fn all() -> &'static [InstallStep] {
+        &[
+            InstallStep::FormatPartition,
+            InstallStep::ExportDrivers,
+            InstallStep::ApplyImage,
+            InstallStep::ImportDrivers,
+            InstallStep::RepairBoot,
+            InstallStep::ApplyAdvancedOptions,
+            InstallStep::GenerateUnattend,
+            InstallStep::Cleanup,
+            InstallStep::Complete,
+        ]
+    }
+
+    /// 步骤在总体进度中所占的份额，合计为 100
+    fn weight(&self) -> u32 {
+        match self {
+            InstallStep::FormatPartition => 3,
+            InstallStep::ExportDrivers => 5,
+            InstallStep::ApplyImage => 75,
+            InstallStep::ImportDrivers => 5,
+            InstallStep::RepairBoot => 4,
+            InstallStep::ApplyAdvancedOptions => 3,
+            InstallStep::GenerateUnattend => 2,
+            InstallStep::Cleanup => 3,
+            InstallStep::Complete => 0,
+        }
+    }
+
+    /// 根据当前步骤及其进度计算总体进度
+    pub fn overall_progress(&self, step_progress: u8) -> u8 {
+        overall_progress(Self::all(), Self::weight, *self, step_progress)
+    }
+}
+
+impl JournalStep for InstallStep {
+    fn key(&self) -> &'static str {
+        match self {
+            InstallStep::FormatPartition => "FormatPartition",
+            InstallStep::ExportDrivers => "ExportDrivers",
+            InstallStep::ApplyImage => "ApplyImage",
+            InstallStep::ImportDrivers => "ImportDrivers",
+            InstallStep::RepairBoot => "RepairBoot",
+            InstallStep::ApplyAdvancedOptions => "ApplyAdvancedOptions",
+            InstallStep::GenerateUnattend => "GenerateUnattend",
+            InstallStep::Cleanup => "Cleanup",
+            InstallStep::Complete => "Complete",
+        }
+    }
+
+    fn sequence() -> &'static [Self] {
+        let all = Self::all();
+        &all[..all.len() - 1]
+    }
+
+    fn restart_from(&self) -> Self {
+        match self {
+            // 释放到一半的分区必须重新格式化后再释放
+            InstallStep::ApplyImage => InstallStep::FormatPartition,
+            other => *other,
+        }
+    }
+}
+
+/// 备份步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupStep {
+    ReadConfig,
+    CaptureImage,
+    VerifyBackup,
+    ExportEsd,
+    ApplyRetention,
+    RepairBoot,
+    Cleanup,
+    Complete,
+}
+
+impl BackupStep {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackupStep::ReadConfig => "读取配置",
+            BackupStep::CaptureImage => "执行DISM备份",
+            BackupStep::VerifyBackup => "验证备份文件",
+            BackupStep::ExportEsd => "导出ESD镜像",
+            BackupStep::ApplyRetention => "清理旧备份",
+            BackupStep::RepairBoot => "恢复引导",
+            BackupStep::Cleanup => "清理临时文件",
+            BackupStep::Complete => "备份完成",
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Self::all().iter().position(|s| s == self).unwrap_or(0)
+    }
+
+    pub fn total() -> usize {
+        Self::all().len()
+    }
+
+    /// 全部步骤（含完成），按执行顺序排列
+    pub fn all() -> &'static [BackupStep] {
+        &[
+            BackupStep::ReadConfig,
+            BackupStep::CaptureImage,
+            BackupStep::VerifyBackup,
+            BackupStep::ExportEsd,
+            BackupStep::ApplyRetention,
+            BackupStep::RepairBoot,
+            BackupStep::Cleanup,
+            BackupStep::Complete,
+        ]
+    }
+
+    /// 步骤在总体进度中所占的份额，合计为 100
+    fn weight(&self) -> u32 {
+        match self {
+            BackupStep::ReadConfig => 2,
+            BackupStep::CaptureImage => 75,
+            BackupStep::VerifyBackup => 5,
+            BackupStep::ExportEsd => 10,
+            BackupStep::ApplyRetention => 3,
+            BackupStep::RepairBoot => 3,
+            BackupStep::Cleanup => 2,
+            BackupStep::Complete => 0,
+        }
+    }
+
+    /// 根据当前步骤及其进度计算总体进度
+    pub fn overall_progress(&self, step_progress: u8) -> u8 {
+        overall_progress(Self::all(), Self::weight, *self, step_progress)
+    }
+}
+
+impl JournalStep for BackupStep {
+    fn key(&self) -> &'static str {
+        match self {
+            BackupStep::ReadConfig => "ReadConfig",
+            BackupStep::CaptureImage => "CaptureImage",
+            BackupStep::VerifyBackup => "VerifyBackup",
+            BackupStep::ExportEsd => "ExportEsd",
+            BackupStep::ApplyRetention => "ApplyRetention",
+            BackupStep::RepairBoot => "RepairBoot",
+            BackupStep::Cleanup => "Cleanup",
+            BackupStep::Complete => "Complete",
+        }
+    }
+
+    fn sequence() -> &'static [Self] {
+        let all = Self::all();
+        &all[..all.len() - 1]
+    }
+}
+
+fn overall_progress<S: Copy + PartialEq>(all: &[S], weight: fn(&S) -> u32, current: S, step_progress: u8) -> u8 {
+    let done: u32 = all.iter().take_while(|&&s| s != current).map(weight).sum();
+    let current = weight(&current) * u32::from(step_progress.min(100)) / 100;
+    (done + current).min(100) as u8
+}
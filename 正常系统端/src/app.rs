@@ -1,4 +1,5 @@
 use eframe::egui;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
@@ -11,6 +12,13 @@ use crate::download::aria2::DownloadProgress;
 use crate::download::config::ConfigManager;
 use crate::download::manager::DownloadManager;
 use crate::ui::advanced_options::AdvancedOptions;
+use crate::ui::backup_exclusions::BackupExclusions;
+use letrecovery_common::backup::catalog::CatalogEntry;
+use letrecovery_common::backup::compression::{BackupFormat, Compression};
+use letrecovery_common::backup::retention::{RetentionOutcome, RetentionPolicy};
+use letrecovery_common::disk_layout::{DiskLayout, LayoutOptions};
+use letrecovery_common::diskpart::DiskEntry;
+use letrecovery_common::preflight::PreflightReport;
 
 /// 应用面板
 #[derive(Debug, Clone, PartialEq)]
@@ -35,7 +43,7 @@ pub struct InstallProgress {
 }
 
 /// 引导模式选择
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum BootModeSelection {
     #[default]
     Auto,
@@ -79,6 +87,8 @@ pub struct InstallOptions {
     pub auto_reboot: bool,
     pub boot_mode: BootModeSelection,
     pub advanced_options: AdvancedOptions,
+    /// 整盘安装：先清空磁盘并按标准布局分区
+    pub clean_install: Option<DiskLayout>,
 }
 
 /// 主应用结构
@@ -126,6 +136,17 @@ pub struct App {
     pub advanced_options: AdvancedOptions,
     pub show_advanced_options: bool,
 
+    // 部署方案
+    pub profile_names: Vec<String>,
+    /// 当前应用的方案名称，也是保存时使用的名称
+    pub profile_name: String,
+    pub default_profile: Option<String>,
+    pub profile_message: Option<String>,
+    /// 应用的方案中有账户需要重新输入密码
+    pub profile_missing_passwords: bool,
+    /// 方案指定的卷索引，镜像信息加载完成后选中
+    pub pending_volume_index: Option<u32>,
+
     // 安装相关
     pub install_options: InstallOptions,
     pub install_target_partition: String,
@@ -135,6 +156,27 @@ pub struct App {
     pub install_step: usize,
     pub install_mode: InstallMode,
 
+    // 整盘安装
+    pub clean_install: bool,
+    pub clean_install_disk: Option<u32>,
+    pub clean_install_layout: LayoutOptions,
+    pub disks: Vec<DiskEntry>,
+    pub show_clean_install_confirm: bool,
+    /// 确认对话框中展示的磁盘操作计划（diskpart 试运行结果）
+    pub disk_plan: String,
+    /// 确认对话框中预览的 Windows 分区盘符，确认后按此盘符分区
+    pub clean_install_letter: Option<char>,
+    /// 等待确认磁盘操作的分区安装：目标分区和是否为当前系统分区
+    pub pending_partition_install: Option<(String, bool)>,
+
+    // 安装前检查
+    /// 检查结果，有警告或阻止项时显示
+    pub preflight_report: Option<PreflightReport>,
+    /// 用户已确认检查警告，下一次开始安装时跳过检查
+    pub preflight_confirmed: bool,
+    /// 目标镜像为 Windows 11 且本机不满足硬件要求，检查窗口中提供绕过选项
+    pub preflight_win11_unsupported: bool,
+
     // 下载管理
     pub current_download: Option<String>,
     pub current_download_filename: Option<String>,
@@ -152,14 +194,28 @@ pub struct App {
     pub backup_save_path: String,
     pub backup_name: String,
     pub backup_description: String,
+    pub backup_format: BackupFormat,
     pub backup_incremental: bool,
+    pub backup_verify: bool,
+    pub backup_retention: Option<RetentionPolicy>,
+    pub backup_exclusions: BackupExclusions,
+    pub backup_compression: Compression,
+    pub backup_export_esd: bool,
+    /// 保存位置镜像中已有的备份
+    pub backup_catalog: Vec<CatalogEntry>,
+    /// 备份目录对应的保存位置，保存位置改变时重新读取
+    pub backup_catalog_path: String,
+    pub backup_catalog_message: Option<String>,
+    pub backup_retention_rx: Option<Receiver<Result<RetentionOutcome, String>>>,
     pub is_backing_up: bool,
     pub backup_progress: u8,
     pub backup_mode: BackupMode,
+    pub backup_schedule: crate::ui::backup_schedule::BackupScheduleState,
 
     // 工具箱
     pub tool_message: String,
     pub tool_target_partition: Option<String>,
+    pub boot_menu: crate::ui::boot_menu::BootMenuState,
 
     // tokio 运行时
     pub runtime: tokio::runtime::Runtime,
@@ -173,6 +229,10 @@ pub struct App {
     // 备份进度通道
     pub backup_progress_rx: Option<Receiver<DismProgress>>,
     pub backup_error: Option<String>,
+    /// 备份过程中不影响结果的问题（如验证时发现的差异）
+    pub backup_warnings: Vec<String>,
+    /// 本次备份的验证报告路径
+    pub backup_report: Option<String>,
 
     // 安装进度通道
     pub install_progress_rx: Option<Receiver<DismProgress>>,
@@ -284,6 +344,12 @@ impl Default for App {
             selected_boot_mode: BootModeSelection::Auto,
             advanced_options: AdvancedOptions::default(),
             show_advanced_options: false,
+            profile_names: Vec::new(),
+            profile_name: String::new(),
+            default_profile: None,
+            profile_message: None,
+            profile_missing_passwords: false,
+            pending_volume_index: None,
             install_options: InstallOptions::default(),
             install_target_partition: String::new(),
             install_image_path: String::new(),
@@ -291,6 +357,17 @@ impl Default for App {
             install_is_system_partition: false,
             install_step: 0,
             install_mode: InstallMode::Direct,
+            clean_install: false,
+            clean_install_disk: None,
+            clean_install_layout: LayoutOptions::default(),
+            disks: Vec::new(),
+            show_clean_install_confirm: false,
+            disk_plan: String::new(),
+            clean_install_letter: None,
+            pending_partition_install: None,
+            preflight_report: None,
+            preflight_confirmed: false,
+            preflight_win11_unsupported: false,
             current_download: None,
             current_download_filename: None,
             download_progress: None,
@@ -303,12 +380,24 @@ impl Default for App {
             backup_save_path: String::new(),
             backup_name: String::new(),
             backup_description: String::new(),
+            backup_format: BackupFormat::Wim,
             backup_incremental: false,
+            backup_verify: true,
+            backup_retention: None,
+            backup_exclusions: BackupExclusions::default(),
+            backup_compression: Compression::Max,
+            backup_export_esd: false,
+            backup_catalog: Vec::new(),
+            backup_catalog_path: String::new(),
+            backup_catalog_message: None,
+            backup_retention_rx: None,
             is_backing_up: false,
             backup_progress: 0,
             backup_mode: BackupMode::Direct,
+            backup_schedule: Default::default(),
             tool_message: String::new(),
             tool_target_partition: None,
+            boot_menu: Default::default(),
             runtime,
             download_manager: Arc::new(Mutex::new(None)),
             download_gid: None,
@@ -316,6 +405,8 @@ impl Default for App {
             download_init_error: None,
             backup_progress_rx: None,
             backup_error: None,
+            backup_warnings: Vec::new(),
+            backup_report: None,
             install_progress_rx: None,
             install_error: None,
             iso_mounting: false,
@@ -491,6 +582,9 @@ impl App {
         // 设置默认备份名称
         self.backup_name = format!("系统备份_{}", chrono::Local::now().format("%Y%m%d_%H%M%S"));
         self.backup_description = "使用 LetRecovery 创建的系统备份".to_string();
+
+        // 应用默认部署方案
+        self.load_default_profile();
     }
 
     /// 使用预加载的配置初始化数据
@@ -570,6 +664,9 @@ impl App {
         // 设置默认备份名称
         self.backup_name = format!("系统备份_{}", chrono::Local::now().format("%Y%m%d_%H%M%S"));
         self.backup_description = "使用 LetRecovery 创建的系统备份".to_string();
+
+        // 应用默认部署方案
+        self.load_default_profile();
     }
     
     /// 开始异步加载远程配置
@@ -0,0 +1,559 @@
+//! 命令行模式
+//!
+//! 参数解析和退出码见公共库的 `cli` 模块。事件和结果以每行一个 JSON 对象
+//! 写到标准输出；各工具自身以 `[` 开头的诊断输出改写到标准错误。
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::{Context, Result};
+use letrecovery_common::backup::exclusions::ExclusionConfig;
+use letrecovery_common::backup::repository::Repository;
+use letrecovery_common::cli::{BackupArgs, Command, ExitStatus, InstallArgs, USAGE};
+use letrecovery_common::command::SystemRunner;
+use letrecovery_common::disk_layout::Firmware;
+use letrecovery_common::journal::JournalStep;
+use letrecovery_common::preflight::Severity;
+use letrecovery_common::wim;
+use letrecovery_common::workflow::{
+    run_backup, run_install, BackupPlan, BackupStep, InstallPlan, InstallStep, TargetPreparation,
+    WorkflowEvent,
+};
+use serde_json::{json, Value};
+
+use crate::app::BootModeSelection;
+use crate::core::bcdedit::BootManager;
+use crate::core::disk::{DiskManager, Partition, PartitionStyle};
+use crate::core::dism::Dism;
+use crate::core::ghost::Ghost;
+use crate::core::hardware_info::HardwareInfo;
+use crate::core::install_config::BackupConfig;
+use crate::core::pe::PeManager;
+use crate::core::preflight::{InstallPreflight, PreflightTarget};
+use crate::core::profile::DeploymentProfile;
+use crate::core::system_info::SystemInfo;
+use crate::ui::advanced_options::AdvancedOptions;
+use crate::workflow::{queue_pe_backup, DesktopBackupBackend, DesktopInstallBackend};
+
+/// 执行子命令，返回退出码
+pub fn run(command: Command) -> ExitStatus {
+    attach_parent_console();
+    redirect_diagnostics();
+
+    if command.requires_admin() && !crate::utils::privilege::is_admin() {
+        emit(json!({ "event": "failed", "error": "需要以管理员身份运行" }));
+        return ExitStatus::NotAdmin;
+    }
+
+    let result = match command {
+        Command::Help => {
+            write_output(USAGE);
+            Ok(ExitStatus::Success)
+        }
+        Command::ImagesList { file } => images_list(&file),
+        Command::Install(args) => install(&args),
+        Command::Backup(args) => backup(&args),
+        Command::BootRepair { target, firmware } => boot_repair(&target, firmware),
+        Command::HwReport { json } => hw_report(json),
+        Command::RepoList { repository } => repo_list(&repository),
+        Command::RepoRestore {
+            repository,
+            id,
+            destination,
+            path,
+        } => repo_restore(&repository, id, &destination, path.as_deref()),
+        Command::RepoGc { repository } => repo_gc(&repository),
+    };
+
+    result.unwrap_or_else(|e| {
+        emit(json!({ "event": "failed", "error": format!("{:#}", e) }));
+        ExitStatus::Failed
+    })
+}
+
+/// 参数错误时输出原因和用法
+pub fn usage_error(error: &anyhow::Error) -> ExitStatus {
+    attach_parent_console();
+    emit(json!({ "event": "failed", "error": error.to_string() }));
+    eprintln!("{}", USAGE);
+    ExitStatus::Usage
+}
+
+fn images_list(file: &str) -> Result<ExitStatus> {
+    let images = if is_gho(file) {
+        let info = Ghost::new().get_image_info(file)?;
+        vec![json!({
+            "index": 1,
+            "name": info.description,
+            "size_bytes": info.original_size,
+            "format": "gho",
+        })]
+    } else {
+        Dism::new()
+            .get_image_info(file)?
+            .into_iter()
+            .map(|image| {
+                json!({
+                    "index": image.index,
+                    "name": image.name,
+                    "size_bytes": image.size_bytes,
+                    "installation_type": image.installation_type,
+                    "format": "wim",
+                })
+            })
+            .collect()
+    };
+
+    emit(json!({ "event": "result", "images": images }));
+    Ok(ExitStatus::Success)
+}
+
+fn install(args: &InstallArgs) -> Result<ExitStatus> {
+    let profile = args.profile.as_deref().map(DeploymentProfile::resolve).transpose()?;
+    let image = args
+        .image
+        .clone()
+        .or_else(|| profile.as_ref().map(|p| p.image_path.clone()))
+        .filter(|image| !image.is_empty())
+        .context("部署方案中没有镜像文件，请用 --image 指定")?;
+    let index = args.index.or(profile.as_ref().map(|p| p.volume_index)).unwrap_or(1);
+    let no_format = args.no_format || profile.as_ref().is_some_and(|p| !p.format_partition);
+    let no_boot_repair = args.no_boot_repair || profile.as_ref().is_some_and(|p| !p.repair_boot);
+    let unattended =
+        args.advanced_options.is_some() || profile.as_ref().is_some_and(|p| p.unattended_install);
+    let export_drivers = args.export_drivers || profile.as_ref().is_some_and(|p| p.export_drivers);
+    let auto_reboot = args.reboot || profile.as_ref().is_some_and(|p| p.auto_reboot);
+
+    let is_gho = is_gho(&image);
+    let partitions = DiskManager::get_partitions()?;
+    let partition = find_partition(&partitions, &args.target)?;
+    if partition.is_system_partition && !SystemInfo::check_pe_environment() {
+        anyhow::bail!("{} 是当前系统分区，请在 PE 中执行安装", args.target);
+    }
+
+    let advanced_options = match (&args.advanced_options, &profile) {
+        (Some(path), _) => load_advanced_options(path)?,
+        (None, Some(profile)) => profile.advanced_options.clone(),
+        (None, None) => AdvancedOptions::default(),
+    };
+    if !unattended && !advanced_options.software_items().is_empty() {
+        anyhow::bail!("装机软件需要无人值守安装才能执行，请在部署方案中启用无人值守安装或用 --advanced-options 指定选项文件");
+    }
+    if unattended {
+        // 选项文件和部署方案都不保存密码，命令行无法输入，不能创建没有密码的账户
+        let missing = advanced_options.accounts_missing_password();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "选项文件和部署方案不保存密码，以下账户没有密码: {}；请在界面中设置密码后安装",
+                missing.join("、")
+            );
+        }
+        for message in advanced_options.check_unattend()? {
+            emit(json!({ "event": "warning", "message": message }));
+        }
+    }
+
+    let profile_firmware = profile.as_ref().and_then(|p| match p.boot_mode {
+        BootModeSelection::UEFI => Some(Firmware::Uefi),
+        BootModeSelection::Legacy => Some(Firmware::Bios),
+        BootModeSelection::Auto => None,
+    });
+    let firmware = args
+        .firmware
+        .or(profile_firmware)
+        .unwrap_or_else(|| partition_firmware(partition));
+
+    let volumes = if is_gho {
+        Vec::new()
+    } else {
+        Dism::new().get_image_info(&image).unwrap_or_default()
+    };
+    let system_info = SystemInfo::collect().ok();
+    let outcome = InstallPreflight {
+        image_path: &image,
+        volume_index: (!is_gho).then_some(index),
+        volumes: &volumes,
+        target: PreflightTarget::Partition {
+            partition,
+            will_format: !no_format,
+            via_pe: false,
+            firmware,
+        },
+        system_info: system_info.as_ref(),
+    }
+    .run();
+
+    for item in outcome.report.items.iter().filter(|i| i.severity != Severity::Pass) {
+        emit(json!({
+            "event": "check",
+            "severity": if item.severity == Severity::Blocking { "blocking" } else { "warning" },
+            "title": item.title,
+            "message": item.message,
+        }));
+    }
+    if outcome.report.has_blocking() {
+        emit(json!({ "event": "failed", "error": "安装前检查未通过" }));
+        return Ok(ExitStatus::PreflightFailed);
+    }
+
+    let driver_dir = export_drivers.then(|| {
+        std::env::temp_dir()
+            .join("LetRecovery_DriverBackup")
+            .to_string_lossy()
+            .to_string()
+    });
+    let plan = InstallPlan {
+        target_partition: args.target.clone(),
+        image_path: image,
+        volume_index: index,
+        is_gho,
+        preparation: if no_format {
+            TargetPreparation::Keep
+        } else {
+            TargetPreparation::Format
+        },
+        driver_dir: driver_dir.clone(),
+        export_drivers,
+        boot_firmware: (!no_boot_repair).then_some(firmware),
+        unattended,
+    };
+    let backend = DesktopInstallBackend {
+        answer_options: advanced_options.answer_file_options(),
+        user_unattend_file: advanced_options.user_unattend_file().map(String::from),
+        advanced_options,
+        disk_layout: None,
+        temp_dirs: driver_dir.into_iter().collect(),
+        tools: crate::core::tools(Arc::new(SystemRunner)),
+    };
+
+    let sink = JsonSink::<InstallStep>::new();
+    if run_install(&plan, &backend, &sink, None).is_err() {
+        return Ok(ExitStatus::Failed);
+    }
+    if auto_reboot {
+        emit(json!({ "event": "status", "message": "安装完成，10 秒后重启" }));
+        crate::utils::cmd::create_command("shutdown")
+            .args(["/r", "/t", "10", "/c", "LetRecovery 系统安装完成，即将重启..."])
+            .spawn()
+            .context("无法重启")?;
+    }
+    Ok(ExitStatus::Success)
+}
+
+fn backup(args: &BackupArgs) -> Result<ExitStatus> {
+    let partitions = DiskManager::get_partitions()?;
+    let partition = find_partition(&partitions, &args.source)?;
+    if args.format.is_dedup() {
+        Repository::check_source(Path::new(&format!("{}\\", args.source)))?;
+    }
+
+    // 命令行中的排除选项优先于部署方案
+    let exclusions = match (&args.exclusions, &args.profile) {
+        (Some(exclusions), _) => exclusions.clone(),
+        (None, Some(profile)) => DeploymentProfile::resolve(profile)?.backup_exclusions.to_config(),
+        (None, None) => ExclusionConfig::default(),
+    };
+
+    if partition.is_system_partition && !SystemInfo::check_pe_environment() {
+        let Some(pe_file) = &args.pe else {
+            anyhow::bail!("{} 是当前系统分区，请在 PE 中执行备份或指定 --pe", args.source);
+        };
+        let config = BackupConfig {
+            save_path: args.dest.clone(),
+            name: args.name.clone(),
+            description: args.description.clone(),
+            source_partition: args.source.clone(),
+            format: args.format,
+            incremental: args.incremental,
+            verify: args.verify,
+            retention: args.retention,
+            exclusions,
+            compression: args.compression,
+            export_esd: args.esd,
+        };
+        queue_pe_backup(pe_file, "LetRecovery 备份", &config, None)?;
+        emit(json!({ "event": "queued", "pe": pe_file }));
+        return Ok(ExitStatus::Success);
+    }
+
+    let plan = BackupPlan {
+        source_partition: args.source.clone(),
+        save_path: args.dest.clone(),
+        name: args.name.clone(),
+        description: args.description.clone(),
+        format: args.format,
+        incremental: args.incremental,
+        verify: args.verify,
+        retention: args.retention,
+        exclusions,
+        compression: args.compression,
+        export_esd: args.esd,
+    };
+
+    let sink = JsonSink::<BackupStep>::new();
+    let backend = DesktopBackupBackend::with_runner(Arc::new(SystemRunner));
+    Ok(match run_backup(&plan, &backend, &sink, None) {
+        Ok(()) => ExitStatus::Success,
+        Err(_) => ExitStatus::Failed,
+    })
+}
+
+fn boot_repair(target: &str, firmware: Option<Firmware>) -> Result<ExitStatus> {
+    let firmware = match firmware {
+        Some(firmware) => firmware,
+        None => {
+            let partitions = DiskManager::get_partitions()?;
+            partition_firmware(find_partition(&partitions, target)?)
+        }
+    };
+    let use_uefi = firmware == Firmware::Uefi;
+
+    emit(json!({
+        "event": "status",
+        "message": format!("正在修复 {} 的引导 ({})", target, if use_uefi { "UEFI" } else { "Legacy" }),
+    }));
+    BootManager::new().repair_boot_advanced(target, use_uefi)?;
+    emit(json!({ "event": "completed" }));
+    Ok(ExitStatus::Success)
+}
+
+fn repo_list(repository: &str) -> Result<ExitStatus> {
+    let backups: Vec<Value> = Repository::open(Path::new(repository))?
+        .catalog()?
+        .into_iter()
+        .map(|entry| {
+            json!({
+                "id": entry.index,
+                "name": entry.name,
+                "description": entry.description,
+                "created": entry.created.map(wim::format_filetime),
+                "source_machine": entry.source_machine,
+                "size_bytes": entry.size,
+            })
+        })
+        .collect();
+    emit(json!({ "event": "result", "backups": backups }));
+    Ok(ExitStatus::Success)
+}
+
+fn repo_restore(repository: &str, id: u32, destination: &str, path: Option<&str>) -> Result<ExitStatus> {
+    let repository = Repository::open(Path::new(repository))?;
+    emit(json!({
+        "event": "status",
+        "message": format!("正在从备份 {} 还原{}到 {}", id, path.map(|p| format!(" {} ", p)).unwrap_or_default(), destination),
+    }));
+    let progress = |p: u8| emit(json!({ "event": "progress", "percent": p }));
+    let summary = repository.restore(id, Path::new(destination), path, &progress)?;
+    emit(json!({
+        "event": "completed",
+        "files": summary.files,
+        "dirs": summary.dirs,
+        "bytes": summary.bytes,
+    }));
+    Ok(ExitStatus::Success)
+}
+
+fn repo_gc(repository: &str) -> Result<ExitStatus> {
+    let summary = Repository::open(Path::new(repository))?.gc()?;
+    emit(json!({
+        "event": "completed",
+        "removed_chunks": summary.removed_chunks,
+        "freed_bytes": summary.freed_bytes,
+    }));
+    Ok(ExitStatus::Success)
+}
+
+fn hw_report(as_json: bool) -> Result<ExitStatus> {
+    let hardware = HardwareInfo::collect().map_err(|e| anyhow::anyhow!("获取硬件信息失败: {}", e))?;
+    let system_info = SystemInfo::collect().ok();
+
+    if as_json {
+        emit(json!({
+            "event": "result",
+            "hardware": serde_json::to_value(&hardware)?,
+            "system": serde_json::to_value(&system_info)?,
+        }));
+    } else {
+        write_output(&hardware.to_formatted_text(system_info.as_ref()));
+    }
+    Ok(ExitStatus::Success)
+}
+
+/// 把工作流事件逐行输出为 JSON
+struct JsonSink<S> {
+    current: Mutex<Option<S>>,
+}
+
+impl<S> JsonSink<S> {
+    fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+}
+
+/// JSON 事件中需要的步骤信息
+trait StepInfo: JournalStep + Send {
+    fn name(&self) -> &'static str;
+    fn index(&self) -> usize;
+    fn total() -> usize;
+    fn overall_progress(&self, step_progress: u8) -> u8;
+}
+
+impl StepInfo for InstallStep {
+    fn name(&self) -> &'static str {
+        InstallStep::name(self)
+    }
+
+    fn index(&self) -> usize {
+        InstallStep::index(self)
+    }
+
+    fn total() -> usize {
+        InstallStep::total()
+    }
+
+    fn overall_progress(&self, step_progress: u8) -> u8 {
+        InstallStep::overall_progress(self, step_progress)
+    }
+}
+
+impl StepInfo for BackupStep {
+    fn name(&self) -> &'static str {
+        BackupStep::name(self)
+    }
+
+    fn index(&self) -> usize {
+        BackupStep::index(self)
+    }
+
+    fn total() -> usize {
+        BackupStep::total()
+    }
+
+    fn overall_progress(&self, step_progress: u8) -> u8 {
+        BackupStep::overall_progress(self, step_progress)
+    }
+}
+
+impl<S: StepInfo> letrecovery_common::workflow::EventSink<S> for JsonSink<S> {
+    fn emit(&self, event: WorkflowEvent<S>) {
+        let mut current = self.current.lock().unwrap();
+        let value = match event {
+            WorkflowEvent::Step(step) => {
+                *current = Some(step);
+                json!({
+                    "event": "step",
+                    "step": step.key(),
+                    "name": step.name(),
+                    "index": step.index() + 1,
+                    "total": S::total(),
+                    "overall": step.overall_progress(0),
+                })
+            }
+            WorkflowEvent::Progress(p) => json!({
+                "event": "progress",
+                "step": current.map(|s| s.key()),
+                "percent": p,
+                "overall": current.map_or(0, |s| s.overall_progress(p)),
+            }),
+            WorkflowEvent::Status(message) => json!({ "event": "status", "message": message }),
+            WorkflowEvent::Warning(message) => json!({ "event": "warning", "message": message }),
+            WorkflowEvent::Completed => json!({ "event": "completed" }),
+            WorkflowEvent::Failed(error) => json!({ "event": "failed", "error": error }),
+        };
+        emit(value);
+    }
+}
+
+/// JSON 事件和帮助写入的原标准输出，诊断输出改到标准错误后使用
+static OUTPUT: OnceLock<Mutex<std::fs::File>> = OnceLock::new();
+
+/// 输出一行 JSON
+fn emit(value: Value) {
+    write_output(&value.to_string());
+}
+
+/// 向原标准输出写一行
+fn write_output(line: &str) {
+    if let Some(output) = OUTPUT.get() {
+        let mut output = output.lock().unwrap();
+        let _ = writeln!(output, "{}", line);
+        let _ = output.flush();
+        return;
+    }
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+fn is_gho(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.ends_with(".gho") || lower.ends_with(".ghs")
+}
+
+fn find_partition<'a>(partitions: &'a [Partition], letter: &str) -> Result<&'a Partition> {
+    partitions
+        .iter()
+        .find(|p| p.letter.eq_ignore_ascii_case(letter))
+        .with_context(|| format!("找不到分区 {}", letter))
+}
+
+/// 按分区表判断引导方式，无法判断时与当前系统相同
+fn partition_firmware(partition: &Partition) -> Firmware {
+    match partition.partition_style {
+        PartitionStyle::GPT => Firmware::Uefi,
+        PartitionStyle::MBR => Firmware::Bios,
+        PartitionStyle::Unknown if PeManager::new().is_uefi_boot() => Firmware::Uefi,
+        PartitionStyle::Unknown => Firmware::Bios,
+    }
+}
+
+/// 读取高级选项文件（界面导出的 JSON）
+fn load_advanced_options(path: &str) -> Result<AdvancedOptions> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("无法读取选项文件 {}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("选项文件格式错误 {}", path))
+}
+
+/// 程序以窗口子系统编译，从控制台启动时需附加到父进程的控制台才能输出
+/// 各工具用 println! 输出以 `[` 开头的诊断信息，命令行模式下把进程的标准输出
+/// 改指向标准错误，JSON 事件仍写到原来的标准输出，脚本读到的每行都是 JSON
+fn redirect_diagnostics() {
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::{FromRawHandle, RawHandle};
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetStdHandle(std_handle: u32) -> RawHandle;
+            fn SetStdHandle(std_handle: u32, handle: RawHandle) -> i32;
+        }
+        const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+        const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+        const INVALID_HANDLE_VALUE: RawHandle = -1isize as RawHandle;
+        unsafe {
+            let stdout = GetStdHandle(STD_OUTPUT_HANDLE);
+            let stderr = GetStdHandle(STD_ERROR_HANDLE);
+            let valid = |h: RawHandle| !h.is_null() && h != INVALID_HANDLE_VALUE;
+            if valid(stdout) && valid(stderr) {
+                let _ = OUTPUT.set(Mutex::new(std::fs::File::from_raw_handle(stdout)));
+                SetStdHandle(STD_OUTPUT_HANDLE, stderr);
+            }
+        }
+    }
+}
+
+fn attach_parent_console() {
+    #[cfg(windows)]
+    {
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn AttachConsole(process_id: u32) -> i32;
+        }
+        const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+        unsafe {
+            AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+    }
+}
@@ -1,153 +1,140 @@
 use anyhow::Result;
-use std::path::Path;
+use letrecovery_common::command::{SystemRunner, CommandRunner};
+use letrecovery_common::bcd::BcdStore;
+use letrecovery_common::disk_layout::Firmware;
+use letrecovery_common::diskpart::script::{DiskpartScript, RunMode};
+use letrecovery_common::tools::Tools;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use crate::utils::cmd::create_command;
+use crate::core::disk::DiskManager;
 use crate::utils::encoding::gbk_to_utf8;
-use crate::utils::path::get_bin_dir;
+use crate::utils::path::{get_bin_dir, get_exe_dir};
 
 pub struct BootManager {
     bcdedit_path: String,
-    bcdboot_path: String,
+    runner: Arc<dyn CommandRunner>,
+    tools: Tools,
+    disk: DiskManager,
 }
 
 impl BootManager {
     pub fn new() -> Self {
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
         let bin_dir = get_bin_dir();
         Self {
             bcdedit_path: bin_dir.join("bcdedit.exe").to_string_lossy().to_string(),
-            bcdboot_path: bin_dir.join("bcdboot.exe").to_string_lossy().to_string(),
+            tools: crate::core::tools(runner.clone()),
+            disk: DiskManager::with_runner(runner.clone()),
+            runner,
         }
     }
 
+    /// 导出并解析当前 BCD 存储
+    pub fn read_store(&self) -> Result<BcdStore> {
+        let export_path = std::env::temp_dir().join(format!("LetRecovery_BCD_{}", std::process::id()));
+        self.export_store(&export_path)?;
+
+        let store = BcdStore::open(&export_path);
+        let _ = std::fs::remove_file(&export_path);
+        // bcdedit /export 会同时生成事务日志文件
+        for ext in ["LOG", "LOG1", "LOG2"] {
+            let _ = std::fs::remove_file(export_path.with_extension(ext));
+        }
+        store
+    }
+
+    /// 导出 BCD 存储到文件
+    pub fn export_store(&self, path: &Path) -> Result<()> {
+        let _ = std::fs::remove_file(path);
+        let output = self
+            .runner
+            .output(&self.bcdedit_path, &["/export", &path.to_string_lossy()])?;
+        if !output.status.success() {
+            anyhow::bail!("导出 BCD 失败: {}", gbk_to_utf8(&output.stdout).trim());
+        }
+        Ok(())
+    }
+
+    /// 从文件导入 BCD 存储（覆盖当前存储）
+    pub fn import_store(&self, path: &Path) -> Result<()> {
+        // 先确认是有效的 BCD 文件，避免导入无关文件
+        BcdStore::open(path)?;
+
+        let output = self
+            .runner
+            .output(&self.bcdedit_path, &["/import", &path.to_string_lossy()])?;
+        if !output.status.success() {
+            anyhow::bail!("导入 BCD 失败: {}", gbk_to_utf8(&output.stdout).trim());
+        }
+        Ok(())
+    }
+
+    /// 备份 BCD 存储到程序目录下的 bcd_backup，返回备份文件路径
+    pub fn backup_store(&self) -> Result<PathBuf> {
+        let backup_dir = get_exe_dir().join("bcd_backup");
+        std::fs::create_dir_all(&backup_dir)?;
+        let path = backup_dir.join(format!("BCD_{}", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+        self.export_store(&path)?;
+        Ok(path)
+    }
+
     /// 获取当前系统引导 GUID
+    ///
+    /// 在 BCD 中查找 osdevice 指向当前系统分区的加载器。
     pub fn get_current_boot_guid(&self) -> Result<String> {
-        let output = create_command(&self.bcdedit_path).args(["/enum"]).output()?;
-
-        let stdout = gbk_to_utf8(&output.stdout);
         let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+        let letter = system_drive.chars().next().unwrap_or('C');
+        let partition = DiskManager::get_partition_id(letter)
+            .ok_or_else(|| anyhow::anyhow!("无法获取 {} 的分区信息", system_drive))?;
 
-        let mut current_guid = String::new();
-        for line in stdout.lines() {
-            if line.starts_with("identifier") || line.contains("标识符") {
-                if let Some(guid) = line.split_whitespace().last() {
-                    current_guid = guid.to_string();
-                }
-            }
-            if line.contains("device") && line.contains(&system_drive) {
-                return Ok(current_guid);
-            }
+        let store = self.read_store()?;
+        match store.find_os_loader_on(&partition) {
+            Some(loader) => Ok(loader.id.to_string()),
+            None => anyhow::bail!("BCD 中没有引导 {} 的启动项", system_drive),
         }
-
-        anyhow::bail!("Could not find current boot GUID")
     }
 
     /// 查找目标 Windows 分区所在磁盘的 ESP 分区
     pub fn find_esp_on_same_disk(&self, windows_partition: &str) -> Result<String> {
-        use std::io::Write;
-        
         println!("[BOOT] 查找 {} 所在磁盘的 ESP 分区...", windows_partition);
         
         // 提取盘符（去掉冒号）
-        let drive_letter = windows_partition.trim_end_matches(':').trim_end_matches('\\');
-        
-        // Step 1: 使用 diskpart 获取该分区所在的磁盘号
-        let script1 = format!(r#"select volume {}
-detail volume
-"#, drive_letter);
+        let drive_letter = windows_partition
+            .chars()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("无效的分区: {}", windows_partition))?;
         
-        let script1_path = std::env::temp_dir().join("find_disk.txt");
-        std::fs::write(&script1_path, &script1)?;
-        
-        let output = create_command("diskpart")
-            .args(["/s", &script1_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
-        println!("[BOOT] 查找磁盘号:\n{}", stdout);
-        
-        // 解析磁盘号
-        let mut disk_num: Option<usize> = None;
-        for line in stdout.lines() {
-            let line_lower = line.to_lowercase();
-            // 查找 "Disk 0" 或 "磁盘 0"
-            if line_lower.contains("disk") || line_lower.contains("磁盘") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if part.to_lowercase().contains("disk") || *part == "磁盘" {
-                        if let Some(num_str) = parts.get(i + 1) {
-                            if let Ok(num) = num_str.parse::<usize>() {
-                                disk_num = Some(num);
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
-        let disk_num = disk_num.ok_or_else(|| anyhow::anyhow!("无法确定分区所在磁盘"))?;
+        // Step 1: 获取该分区所在的磁盘号
+        let disk_num = self.disk.get_volume_disk_number(drive_letter)?;
         println!("[BOOT] 目标分区在磁盘 {}", disk_num);
         
-        // Step 2: 查找该磁盘上的 ESP 分区（使用 GPT 类型）
-        let script2 = format!(r#"select disk {}
-list partition
-"#, disk_num);
-        
-        let script2_path = std::env::temp_dir().join("list_part.txt");
-        std::fs::write(&script2_path, &script2)?;
-        
-        let output = create_command("diskpart")
-            .args(["/s", &script2_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
-        println!("[BOOT] 分区列表:\n{}", stdout);
-        
-        // 查找 System/系统 类型的分区（ESP）
-        let mut esp_partition: Option<usize> = None;
-        for line in stdout.lines() {
-            let line_lower = line.to_lowercase();
-            // 查找 "System" 或 "系统" 类型的分区
-            if line_lower.contains("system") || line_lower.contains("系统") {
-                // 提取分区号
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if part.to_lowercase().contains("partition") || *part == "分区" {
-                        if let Some(num_str) = parts.get(i + 1) {
-                            if let Ok(num) = num_str.parse::<usize>() {
-                                esp_partition = Some(num);
-                                println!("[BOOT] 找到 ESP: 分区 {}", num);
-                                break;
-                            }
-                        }
-                    }
-                }
-                if esp_partition.is_some() {
-                    break;
-                }
-            }
-        }
-        
-        let esp_partition = esp_partition.ok_or_else(|| anyhow::anyhow!("未找到 ESP 分区"))?;
+        // Step 2: 按分区类型查找该磁盘上的 ESP 分区
+        let esp_partition = self.disk.find_esp_partition_number(disk_num)?
+            .ok_or_else(|| anyhow::anyhow!("未找到 ESP 分区"))?;
+        println!("[BOOT] 找到 ESP: 分区 {}", esp_partition);
         
         // Step 3: 为 ESP 分配盘符
+        self.assign_esp_letter(disk_num, esp_partition)
+    }
+
+    /// 为 ESP 分配盘符 S:
+    fn assign_esp_letter(&self, disk_num: u32, esp_partition: u32) -> Result<String> {
         // 先尝试移除可能存在的旧盘符
-        let _ = create_command("mountvol").args(["S:", "/d"]).output();
+        let _ = self.runner.output("mountvol", &["S:", "/d"]);
         std::thread::sleep(std::time::Duration::from_millis(200));
         
-        let script3 = format!(r#"select disk {}
-select partition {}
-assign letter=S
-"#, disk_num, esp_partition);
-        
-        let script3_path = std::env::temp_dir().join("assign_esp.txt");
-        std::fs::write(&script3_path, &script3)?;
+        let script = DiskpartScript::builder()
+            .select_disk(disk_num)
+            .select_partition(esp_partition)
+            .assign_letter('S')
+            .build()?;
         
-        let output = create_command("diskpart")
-            .args(["/s", &script3_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
+        let stdout = self.disk.run_diskpart(&script, RunMode::Execute)?;
         println!("[BOOT] 分配 ESP 盘符:\n{}", stdout);
         
         // 等待盘符生效
@@ -174,7 +161,7 @@ assign letter=S
         
         // 方法2: 使用 mountvol /s 挂载 ESP 到 S:
         println!("[BOOT] 尝试使用 mountvol /s 挂载 ESP");
-        let output = create_command("mountvol").args(["S:", "/s"]).output();
+        let output = self.runner.output("mountvol", &["S:", "/s"]);
         if output.is_ok() {
             std::thread::sleep(std::time::Duration::from_millis(500));
             if Path::new("S:\\").exists() {
@@ -189,59 +176,15 @@ assign letter=S
 
     /// 使用 diskpart 查找任意磁盘上的 ESP
     fn find_esp_with_diskpart(&self) -> Result<String> {
-        use std::io::Write;
-        
         println!("[BOOT] 使用 diskpart 查找 ESP");
         
-        // 遍历磁盘0-3
-        for disk in 0..4 {
-            let script = format!(r#"select disk {}
-list partition
-"#, disk);
-            
-            let script_path = std::env::temp_dir().join("check_disk.txt");
-            std::fs::write(&script_path, &script)?;
-            
-            let output = create_command("diskpart")
-                .args(["/s", &script_path.to_string_lossy()])
-                .output()?;
-            
-            let stdout = gbk_to_utf8(&output.stdout);
-            
-            // 查找 System 类型分区
-            for line in stdout.lines() {
-                let line_lower = line.to_lowercase();
-                if line_lower.contains("system") || line_lower.contains("系统") {
-                    // 提取分区号
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    for (i, part) in parts.iter().enumerate() {
-                        if part.to_lowercase().contains("partition") || *part == "分区" {
-                            if let Some(num_str) = parts.get(i + 1) {
-                                if let Ok(part_num) = num_str.parse::<usize>() {
-                                    // 找到了，分配盘符
-                                    let assign_script = format!(r#"select disk {}
-select partition {}
-assign letter=S
-"#, disk, part_num);
-                                    
-                                    let assign_path = std::env::temp_dir().join("assign_esp2.txt");
-                                    std::fs::write(&assign_path, &assign_script)?;
-                                    
-                                    let _ = create_command("diskpart")
-                                        .args(["/s", &assign_path.to_string_lossy()])
-                                        .output();
-                                    
-                                    std::thread::sleep(std::time::Duration::from_millis(500));
-                                    
-                                    if Path::new("S:\\").exists() {
-                                        println!("[BOOT] 找到 ESP: 磁盘 {} 分区 {}", disk, part_num);
-                                        return Ok("S:".to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        for disk in self.disk.list_disks()? {
+            let Ok(Some(part_num)) = self.disk.find_esp_partition_number(disk.number) else {
+                continue;
+            };
+            if self.assign_esp_letter(disk.number, part_num).is_ok() {
+                println!("[BOOT] 找到 ESP: 磁盘 {} 分区 {}", disk.number, part_num);
+                return Ok("S:".to_string());
             }
         }
         
@@ -250,9 +193,7 @@ assign letter=S
 
     /// 设置默认引导项
     pub fn set_default_boot(&self, guid: &str) -> Result<()> {
-        let output = create_command(&self.bcdedit_path)
-            .args(["/default", guid])
-            .output()?;
+        let output = self.runner.output(&self.bcdedit_path, &["/default", guid])?;
 
         if !output.status.success() {
             anyhow::bail!("Failed to set default boot entry");
@@ -262,9 +203,7 @@ assign letter=S
 
     /// 设置引导超时
     pub fn set_timeout(&self, seconds: u32) -> Result<()> {
-        let output = create_command(&self.bcdedit_path)
-            .args(["/timeout", &seconds.to_string()])
-            .output()?;
+        let output = self.runner.output(&self.bcdedit_path, &["/timeout", &seconds.to_string()])?;
 
         if !output.status.success() {
             anyhow::bail!("Failed to set boot timeout");
@@ -274,9 +213,7 @@ assign letter=S
 
     /// 删除引导项
     pub fn delete_boot_entry(&self, guid: &str) -> Result<()> {
-        let output = create_command(&self.bcdedit_path)
-            .args(["/delete", guid, "/f"])
-            .output()?;
+        let output = self.runner.output(&self.bcdedit_path, &["/delete", guid, "/f"])?;
 
         if !output.status.success() {
             anyhow::bail!("Failed to delete boot entry");
@@ -284,6 +221,37 @@ assign letter=S
         Ok(())
     }
 
+    /// 设置启动菜单显示顺序
+    pub fn set_display_order(&self, guids: &[String]) -> Result<()> {
+        if guids.is_empty() {
+            anyhow::bail!("启动菜单不能为空");
+        }
+        let args: Vec<&str> = std::iter::once("/displayorder")
+            .chain(guids.iter().map(String::as_str))
+            .collect();
+        let output = self.runner.output(&self.bcdedit_path, &args)?;
+
+        if !output.status.success() {
+            anyhow::bail!("设置启动菜单顺序失败: {}", gbk_to_utf8(&output.stdout).trim());
+        }
+        Ok(())
+    }
+
+    /// 修改引导项描述
+    pub fn rename_boot_entry(&self, guid: &str, description: &str) -> Result<()> {
+        let output = self.runner.output(&self.bcdedit_path, &[
+            "/set",
+            guid,
+            "description",
+            description,
+        ])?;
+
+        if !output.status.success() {
+            anyhow::bail!("重命名引导项失败: {}", gbk_to_utf8(&output.stdout).trim());
+        }
+        Ok(())
+    }
+
     /// 修复指定分区的引导（简单版本）
     pub fn repair_boot(&self, windows_partition: &str) -> Result<()> {
         self.repair_boot_advanced(windows_partition, true)
@@ -302,167 +270,28 @@ assign letter=S
             anyhow::bail!("Windows 目录不存在: {}", windows_path);
         }
 
-        if use_uefi {
+        let (firmware, esp) = if use_uefi {
             // UEFI 模式：需要找到并挂载 ESP 分区
             println!("[BOOT] UEFI 模式：查找 ESP 分区");
-            
+
             // 首先尝试在同一磁盘上查找 ESP
-            let esp_result = self.find_esp_on_same_disk(windows_partition)
-                .or_else(|_| self.find_and_mount_esp());
-            
-            match esp_result {
+            match self.find_esp_on_same_disk(windows_partition).or_else(|_| self.find_and_mount_esp()) {
                 Ok(esp_letter) => {
                     println!("[BOOT] ESP 分区: {}", esp_letter);
-                    
-                    // 确保 EFI 目录存在
-                    let efi_dir = format!("{}\\EFI", esp_letter);
-                    let efi_ms_dir = format!("{}\\EFI\\Microsoft", esp_letter);
-                    let efi_boot_dir = format!("{}\\EFI\\Boot", esp_letter);
-                    
-                    // 创建必要的目录
-                    let _ = std::fs::create_dir_all(&efi_ms_dir);
-                    let _ = std::fs::create_dir_all(&efi_boot_dir);
-                    
-                    // 使用 bcdboot 写入 UEFI 引导文件
-                    // bcdboot C:\Windows /s S: /f UEFI /l zh-cn
-                    println!("[BOOT] 执行: bcdboot {} /s {} /f UEFI /l zh-cn", windows_path, esp_letter);
-                    let output = create_command(&self.bcdboot_path)
-                        .args([
-                            &windows_path,
-                            "/s", &esp_letter,
-                            "/f", "UEFI",
-                            "/l", "zh-cn"
-                        ])
-                        .output()?;
-                    
-                    let stdout = gbk_to_utf8(&output.stdout);
-                    let stderr = gbk_to_utf8(&output.stderr);
-                    
-                    println!("[BOOT] bcdboot stdout: {}", stdout);
-                    println!("[BOOT] bcdboot stderr: {}", stderr);
-                    
-                    if !output.status.success() {
-                        // 尝试使用 ALL 参数（同时创建 UEFI 和 BIOS 引导）
-                        println!("[BOOT] 重试：使用 ALL 模式");
-                        let output = create_command(&self.bcdboot_path)
-                            .args([
-                                &windows_path,
-                                "/s", &esp_letter,
-                                "/f", "ALL",
-                                "/l", "zh-cn"
-                            ])
-                            .output()?;
-                        
-                        let stdout = gbk_to_utf8(&output.stdout);
-                        let stderr = gbk_to_utf8(&output.stderr);
-                        println!("[BOOT] bcdboot (ALL) stdout: {}", stdout);
-                        println!("[BOOT] bcdboot (ALL) stderr: {}", stderr);
-                        
-                        if !output.status.success() {
-                            // 最后尝试不指定 /f 参数
-                            println!("[BOOT] 重试：不指定引导类型");
-                            let output = create_command(&self.bcdboot_path)
-                                .args([
-                                    &windows_path,
-                                    "/s", &esp_letter,
-                                    "/l", "zh-cn"
-                                ])
-                                .output()?;
-                            
-                            let stderr = gbk_to_utf8(&output.stderr);
-                            if !output.status.success() {
-                                anyhow::bail!("UEFI 引导修复失败: {}", stderr);
-                            }
-                        }
-                    }
-                    
-                    // 验证引导文件是否创建成功
-                    let bootmgfw = format!("{}\\EFI\\Microsoft\\Boot\\bootmgfw.efi", esp_letter);
-                    let bootx64 = format!("{}\\EFI\\Boot\\bootx64.efi", esp_letter);
-                    
-                    if Path::new(&bootmgfw).exists() {
-                        println!("[BOOT] 引导文件已创建: {}", bootmgfw);
-                    } else {
-                        println!("[BOOT] 警告: 未找到 bootmgfw.efi");
-                    }
-                    
-                    if Path::new(&bootx64).exists() {
-                        println!("[BOOT] 引导文件已创建: {}", bootx64);
-                    } else {
-                        // 复制 bootmgfw.efi 到 bootx64.efi
-                        if Path::new(&bootmgfw).exists() {
-                            let _ = std::fs::copy(&bootmgfw, &bootx64);
-                            println!("[BOOT] 已复制 bootmgfw.efi -> bootx64.efi");
-                        }
-                    }
-                    
-                    println!("[BOOT] UEFI 引导修复成功");
+                    (Firmware::Uefi, Some(esp_letter))
                 }
                 Err(e) => {
+                    // 让 bcdboot 自动处理
                     println!("[BOOT] 查找 ESP 失败: {}，尝试默认方式", e);
-                    
-                    // 尝试默认方式（让 bcdboot 自动处理）
-                    let output = create_command(&self.bcdboot_path)
-                        .args([&windows_path, "/f", "UEFI", "/l", "zh-cn"])
-                        .output()?;
-                    
-                    let stdout = gbk_to_utf8(&output.stdout);
-                    let stderr = gbk_to_utf8(&output.stderr);
-                    println!("[BOOT] bcdboot (auto) stdout: {}", stdout);
-                    println!("[BOOT] bcdboot (auto) stderr: {}", stderr);
-                    
-                    if !output.status.success() {
-                        anyhow::bail!("引导修复失败: {}", stderr);
-                    }
+                    (Firmware::Uefi, None)
                 }
             }
         } else {
-            // Legacy/BIOS 模式
             println!("[BOOT] Legacy 模式：写入 MBR 引导");
-            
-            // 使用 bootsect 写入引导扇区
-            let bootsect_path = get_bin_dir().join("bootsect.exe");
-            if bootsect_path.exists() {
-                println!("[BOOT] 使用 bootsect 写入引导扇区");
-                let output = create_command(&bootsect_path)
-                    .args(["/nt60", windows_partition, "/mbr"])
-                    .output()?;
-                
-                let stdout = gbk_to_utf8(&output.stdout);
-                let stderr = gbk_to_utf8(&output.stderr);
-                println!("[BOOT] bootsect stdout: {}", stdout);
-                println!("[BOOT] bootsect stderr: {}", stderr);
-            }
-            
-            // bcdboot C:\Windows /f BIOS /l zh-cn
-            let output = create_command(&self.bcdboot_path)
-                .args([
-                    &windows_path,
-                    "/f", "BIOS",
-                    "/l", "zh-cn"
-                ])
-                .output()?;
-            
-            let stdout = gbk_to_utf8(&output.stdout);
-            let stderr = gbk_to_utf8(&output.stderr);
-            
-            println!("[BOOT] bcdboot stdout: {}", stdout);
-            println!("[BOOT] bcdboot stderr: {}", stderr);
-            
-            if !output.status.success() {
-                // 尝试不指定 /f 参数
-                let output = create_command(&self.bcdboot_path)
-                    .args([&windows_path, "/l", "zh-cn"])
-                    .output()?;
-                
-                let stderr = gbk_to_utf8(&output.stderr);
-                if !output.status.success() {
-                    anyhow::bail!("Legacy 引导修复失败: {}", stderr);
-                }
-            }
-            
-            println!("[BOOT] Legacy 引导修复成功");
-        }
+            (Firmware::Bios, None)
+        };
+
+        self.tools.write_boot_files(windows_partition, firmware, esp.as_deref())?;
 
         println!("[BOOT] ========== 引导修复完成 ==========");
         Ok(())
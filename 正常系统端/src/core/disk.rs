@@ -1,8 +1,13 @@
 use anyhow::Result;
 use std::path::Path;
-use crate::utils::cmd::create_command;
-use crate::utils::encoding::gbk_to_utf8;
-use crate::utils::path::get_bin_dir;
+use std::sync::Arc;
+use letrecovery_common::bcd::PartitionId;
+use letrecovery_common::disk_layout::DiskLayout;
+use letrecovery_common::command::{CommandRunner, SystemRunner};
+use letrecovery_common::diskpart::script::{DiskpartScript, FileSystem, PartitionType, RunMode};
+use letrecovery_common::diskpart::{self, DiskEntry};
+use letrecovery_common::partition_table::PartitionTable;
+use letrecovery_common::tools::Tools;
 
 #[cfg(windows)]
 use windows::{
@@ -29,19 +34,6 @@ const DRIVE_CDROM: u32 = 5;
 #[allow(dead_code)]
 const DRIVE_RAMDISK: u32 = 6;
 
-/// 获取 diskpart 可执行文件路径
-/// 优先使用内置的 diskpart，如果不存在则使用系统的
-fn get_diskpart_path() -> String {
-    let builtin_diskpart = get_bin_dir().join("diskpart").join("diskpart.exe");
-    if builtin_diskpart.exists() {
-        log::info!("使用内置 diskpart: {}", builtin_diskpart.display());
-        builtin_diskpart.to_string_lossy().to_string()
-    } else {
-        log::debug!("使用系统 diskpart");
-        "diskpart.exe".to_string()
-    }
-}
-
 /// 自动创建分区的标志文件名
 pub const AUTO_CREATED_PARTITION_MARKER: &str = "LetRecovery_AutoCreated.marker";
 
@@ -95,19 +87,27 @@ struct StorageDeviceNumber {
     partition_number: u32,
 }
 
-/// DRIVE_LAYOUT_INFORMATION_EX 结构（简化版，只需要头部信息）
-#[cfg(windows)]
-#[repr(C)]
-#[derive(Default)]
-struct DriveLayoutInformationEx {
-    partition_style: u32,
-    partition_count: u32,
-    // union 部分我们不需要完整读取
+/// 磁盘和分区操作，diskpart 通过创建时传入的执行器调用
+pub struct DiskManager {
+    tools: Tools,
 }
 
-pub struct DiskManager;
+impl Default for DiskManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl DiskManager {
+    pub fn new() -> Self {
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { tools: crate::core::tools(runner) }
+    }
+
     /// 获取所有固定磁盘分区列表
     pub fn get_partitions() -> Result<Vec<Partition>> {
         let mut partitions = Vec::new();
@@ -285,6 +285,21 @@ impl DiskManager {
     /// 使用 IOCTL_DISK_GET_DRIVE_LAYOUT_EX 获取磁盘分区表类型
     #[cfg(windows)]
     fn get_disk_partition_style_api(disk_number: u32) -> PartitionStyle {
+        let Some(buffer) = Self::read_drive_layout(disk_number) else {
+            return PartitionStyle::Unknown;
+        };
+
+        // 头部前 4 字节为 partition_style
+        match u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) {
+            x if x == PARTITION_STYLE_MBR.0 as u32 => PartitionStyle::MBR,
+            x if x == PARTITION_STYLE_GPT.0 as u32 => PartitionStyle::GPT,
+            _ => PartitionStyle::Unknown,
+        }
+    }
+
+    /// 读取物理磁盘的 DRIVE_LAYOUT_INFORMATION_EX 原始数据
+    #[cfg(windows)]
+    fn read_drive_layout(disk_number: u32) -> Option<Vec<u8>> {
         unsafe {
             // 打开物理磁盘
             let disk_path = format!("\\\\.\\PhysicalDrive{}", disk_number);
@@ -298,21 +313,16 @@ impl DiskManager {
                 OPEN_EXISTING,
                 Default::default(),
                 None,
-            );
-
-            let handle = match handle {
-                Ok(h) => h,
-                Err(_) => return PartitionStyle::Unknown,
-            };
+            )
+            .ok()?;
 
             if handle == INVALID_HANDLE_VALUE {
-                return PartitionStyle::Unknown;
+                return None;
             }
 
-            // 分配足够大的缓冲区来存储分区布局信息
-            // DRIVE_LAYOUT_INFORMATION_EX 的大小取决于分区数量
-            // 我们只需要头部的 partition_style 字段
-            let mut buffer = vec![0u8; 4096];
+            // DRIVE_LAYOUT_INFORMATION_EX 的大小取决于分区数量：
+            // 头部 48 字节，每个分区 144 字节，按 GPT 最多 128 个分区分配
+            let mut buffer = vec![0u8; 48 + 144 * 128];
             let mut bytes_returned: u32 = 0;
 
             let result = DeviceIoControl(
@@ -329,81 +339,144 @@ impl DiskManager {
             let _ = CloseHandle(handle);
 
             if result.is_ok() && bytes_returned >= 8 {
-                // 读取头部的 partition_style 字段（前4字节）
-                let layout = &*(buffer.as_ptr() as *const DriveLayoutInformationEx);
-                
-                match layout.partition_style {
-                    x if x == PARTITION_STYLE_MBR.0 as u32 => PartitionStyle::MBR,
-                    x if x == PARTITION_STYLE_GPT.0 as u32 => PartitionStyle::GPT,
-                    _ => PartitionStyle::Unknown,
-                }
+                buffer.truncate(bytes_returned as usize);
+                Some(buffer)
             } else {
-                PartitionStyle::Unknown
+                None
             }
         }
     }
 
-    /// 格式化指定分区
-    pub fn format_partition(partition: &str) -> Result<String> {
-        let bin_dir = get_bin_dir();
-        let format_exe = if Self::is_pe_environment() {
-            bin_dir.join("format.com").to_string_lossy().to_string()
-        } else {
-            "format.com".to_string()
-        };
+    /// 获取分区在 BCD 中的标识（MBR: 磁盘签名 + 起始偏移；GPT: 磁盘 GUID + 分区 GUID）
+    #[cfg(windows)]
+    pub fn get_partition_id(letter: char) -> Option<PartitionId> {
+        let (disk_number, partition_number) = Self::get_device_number(letter);
+        let layout = Self::read_drive_layout(disk_number?)?;
+        PartitionId::from_drive_layout(&layout, partition_number?)
+    }
 
-        let output = create_command(&format_exe)
-            .args([partition, "/FS:NTFS", "/q", "/y"])
-            .output()?;
+    #[cfg(not(windows))]
+    pub fn get_partition_id(_letter: char) -> Option<PartitionId> {
+        None
+    }
 
-        Ok(gbk_to_utf8(&output.stdout))
+    /// 格式化指定分区（NTFS 快速格式化）；`RunMode::DryRun` 时只返回执行计划
+    pub fn format_partition(&self, partition: &str, mode: RunMode) -> Result<String> {
+        self.tools.format_volume(partition.chars().next().unwrap_or('C'), mode)
     }
 
     /// 从指定分区缩小并创建新分区
     pub fn shrink_and_create_partition(
+        &self,
         source_partition: &str,
         new_letter: &str,
         size_mb: u64,
     ) -> Result<String> {
-        let script_content = format!(
-            "select volume {}\nshrink desired={}\ncreate partition primary size={}\nformat fs=ntfs quick\nassign letter={}",
-            source_partition.chars().next().unwrap_or('C'),
-            size_mb,
-            size_mb,
-            new_letter.chars().next().unwrap_or('Y').to_ascii_lowercase()
-        );
+        let script = DiskpartScript::builder()
+            .select_volume(source_partition.chars().next().unwrap_or('C'))
+            .shrink(size_mb)
+            .create_partition(PartitionType::Primary, Some(size_mb))
+            .format(FileSystem::Ntfs, None)
+            .assign_letter(new_letter.chars().next().unwrap_or('Y'))
+            .build()?;
 
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("dp_script.txt");
-        std::fs::write(&script_path, &script_content)?;
+        self.run_diskpart(&script, RunMode::Execute)
+    }
+
+    /// 删除指定分区
+    pub fn delete_partition(&self, partition_letter: &str) -> Result<String> {
+        let script = DiskpartScript::builder()
+            .select_volume(partition_letter.chars().next().unwrap_or('Y'))
+            .delete_partition_override()
+            .build()?;
 
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
+        self.run_diskpart(&script, RunMode::Execute)
+    }
 
-        let _ = std::fs::remove_file(&script_path);
+    /// 执行 diskpart 脚本，返回输出；`RunMode::DryRun` 时不执行，只返回执行计划
+    ///
+    /// 以 /s 运行脚本时任一命令失败 diskpart 都会返回非 0 退出码，
+    /// 据此判断成败，不匹配随系统语言变化的提示文字。
+    pub fn run_diskpart(&self, script: &DiskpartScript, mode: RunMode) -> Result<String> {
+        self.tools.run_diskpart(script, mode)
+    }
 
-        Ok(gbk_to_utf8(&output.stdout))
+    /// 列出所有磁盘
+    pub fn list_disks(&self) -> Result<Vec<DiskEntry>> {
+        let script = DiskpartScript::builder().list_disk().build()?;
+        let output = self.run_diskpart(&script, RunMode::Execute)?;
+        Ok(diskpart::parse_list_disk(&output))
     }
 
-    /// 删除指定分区
-    pub fn delete_partition(partition_letter: &str) -> Result<String> {
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            partition_letter.chars().next().unwrap_or('Y')
-        );
+    /// 获取卷所在的磁盘号
+    pub fn get_volume_disk_number(&self, letter: char) -> Result<u32> {
+        #[cfg(windows)]
+        if let (Some(disk_number), _) = Self::get_device_number(letter) {
+            return Ok(disk_number);
+        }
 
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("dp_delete.txt");
-        std::fs::write(&script_path, &script_content)?;
+        let script = DiskpartScript::builder().select_volume(letter).detail_volume().build()?;
+        let output = self.run_diskpart(&script, RunMode::Execute)?;
+        diskpart::parse_detail_volume_disk(&output)
+            .ok_or_else(|| anyhow::anyhow!("无法确定分区 {}: 所在磁盘", letter))
+    }
 
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
+    /// 清空磁盘并按标准布局重新分区
+    ///
+    /// 完成后 Windows 分区挂载到 `windows_letter`，ESP / 系统保留分区不分配盘符，
+    /// 由修复引导时按分区类型查找。`RunMode::DryRun` 时只返回执行计划。
+    pub fn apply_disk_layout(&self, layout: &DiskLayout, windows_letter: char, mode: RunMode) -> Result<String> {
+        let script = layout.diskpart_script(windows_letter, None)?;
+        if mode == RunMode::DryRun {
+            return self.run_diskpart(&script, mode);
+        }
+        println!("[DISK] 整盘分区计划:\n{}", script.plan_text());
 
-        let _ = std::fs::remove_file(&script_path);
+        let output = self.run_diskpart(&script, mode)?;
+        println!("[DISK] Diskpart 输出: {}", output);
 
-        Ok(gbk_to_utf8(&output.stdout))
+        // 等待系统识别新分区
+        let windows_path = format!("{}:\\", windows_letter);
+        for _ in 0..10 {
+            if Path::new(&windows_path).exists() {
+                return Ok(output);
+            }
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+        anyhow::bail!("分区完成但 Windows 分区 {}: 不可访问", windows_letter)
+    }
+
+    /// 直接读取物理磁盘的分区表
+    pub fn read_partition_table(disk_number: u32) -> Result<PartitionTable> {
+        let path = format!("\\\\.\\PhysicalDrive{}", disk_number);
+        let mut disk = std::fs::File::open(&path)
+            .map_err(|e| anyhow::anyhow!("打开 {} 失败: {}", path, e))?;
+        PartitionTable::read(&mut disk)
+    }
+
+    /// 按分区类型查找磁盘上的 EFI 系统分区，返回分区号
+    ///
+    /// 优先读取分区表按类型 GUID 判断，读取失败时再用 diskpart 逐个查询分区类型。
+    pub fn find_esp_partition_number(&self, disk_number: u32) -> Result<Option<u32>> {
+        match Self::read_partition_table(disk_number) {
+            Ok(table) => return Ok(table.esp().map(|p| p.number)),
+            Err(e) => println!("[DISK] 读取磁盘 {} 分区表失败，改用 diskpart: {}", disk_number, e),
+        }
+
+        let script = DiskpartScript::builder().select_disk(disk_number).list_partition().build()?;
+        let output = self.run_diskpart(&script, RunMode::Execute)?;
+        for partition in diskpart::parse_list_partition(&output) {
+            let script = DiskpartScript::builder()
+                .select_disk(disk_number)
+                .select_partition(partition.number)
+                .detail_partition()
+                .build()?;
+            let detail = self.run_diskpart(&script, RunMode::Execute)?;
+            if diskpart::parse_detail_partition_type(&detail).is_some_and(|t| t.is_esp()) {
+                return Ok(Some(partition.number));
+            }
+        }
+        Ok(None)
     }
 
     /// 检查指定分区是否包含有效的 Windows 系统
@@ -590,168 +663,40 @@ impl DiskManager {
     }
 
     /// 查询指定分区可缩小的最大空间（MB）
-    pub fn query_shrink_max(letter: char) -> Result<u64> {
-        let script_content = format!(
-            "select volume {}\nshrink querymax",
-            letter
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_query_shrink.txt");
-        std::fs::write(&script_path, &script_content)?;
-
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
+    pub fn query_shrink_max(&self, letter: char) -> Result<u64> {
+        let script = DiskpartScript::builder().select_volume(letter).shrink_querymax().build()?;
 
-        let _ = std::fs::remove_file(&script_path);
-
-        let output_text = gbk_to_utf8(&output.stdout);
+        let output_text = self.run_diskpart(&script, RunMode::Execute)?;
         println!("[DISK] Shrink querymax 输出: {}", output_text);
 
-        // 解析输出，查找可回收的最大空间
-        // 英文: "The maximum number of reclaimable bytes is: XXX MB"
-        // 中文: "可回收的最大字节数为:  XXX MB" 或 "最多可从此卷收回 XXX MB"
-        
-        // 尝试多种模式匹配
-        let max_mb = Self::parse_shrink_max_output(&output_text)
-            .or_else(|| Self::parse_shrink_max_output_cn(&output_text))
-            .or_else(|| Self::parse_shrink_max_generic(&output_text))
-            .unwrap_or(0);
+        let max_mb = diskpart::parse_shrink_querymax(&output_text).unwrap_or(0);
 
         println!("[DISK] 分区 {}: 可缩小的最大空间: {} MB", letter, max_mb);
         Ok(max_mb)
     }
 
-    /// 解析 shrink querymax 输出（英文）
-    fn parse_shrink_max_output(output: &str) -> Option<u64> {
-        // 匹配 "XXX MB" 或 "XXX GB" 格式
-        for line in output.lines() {
-            let line_lower = line.to_lowercase();
-            if line_lower.contains("reclaimable") || line_lower.contains("maximum") {
-                // 提取数字
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if let Ok(num) = part.replace(",", "").parse::<u64>() {
-                        // 检查单位
-                        if i + 1 < parts.len() {
-                            let unit = parts[i + 1].to_lowercase();
-                            if unit.starts_with("gb") {
-                                return Some(num * 1024);
-                            } else if unit.starts_with("mb") {
-                                return Some(num);
-                            }
-                        }
-                        return Some(num); // 默认 MB
-                    }
-                }
-            }
-        }
-        None
-    }
-
-    /// 解析 shrink querymax 输出（中文）
-    fn parse_shrink_max_output_cn(output: &str) -> Option<u64> {
-        for line in output.lines() {
-            // 中文输出格式: "可回收的最大字节数为:  XXX MB" 或包含 "收回" 的行
-            if line.contains("回收") || line.contains("收回") || line.contains("可用") 
-                || line.contains("压缩") || line.contains("缩小") {
-                return Self::extract_size_from_line(line);
-            }
-        }
-        None
-    }
-
-    /// 通用解析：查找任何包含数字+MB/GB的行
-    fn parse_shrink_max_generic(output: &str) -> Option<u64> {
-        for line in output.lines() {
-            // 跳过明显的非结果行
-            let line_lower = line.to_lowercase();
-            if line_lower.contains("diskpart") || line_lower.contains("microsoft") 
-                || line_lower.contains("version") || line_lower.contains("volume")
-                || line_lower.contains("select") || line.trim().is_empty() {
-                continue;
-            }
-            
-            if let Some(size) = Self::extract_size_from_line(line) {
-                return Some(size);
-            }
-        }
-        None
-    }
-
-    /// 从一行文本中提取大小（MB）
-    fn extract_size_from_line(line: &str) -> Option<u64> {
-        let mut num_str = String::new();
-        let mut found_num = false;
-        let chars: Vec<char> = line.chars().collect();
-        
-        for (i, c) in chars.iter().enumerate() {
-            if c.is_ascii_digit() {
-                num_str.push(*c);
-                found_num = true;
-            } else if found_num && *c == ',' {
-                // 跳过千位分隔符
-                continue;
-            } else if found_num && !c.is_ascii_digit() {
-                // 数字结束，检查单位
-                if let Ok(num) = num_str.replace(",", "").parse::<u64>() {
-                    if num == 0 {
-                        num_str.clear();
-                        found_num = false;
-                        continue;
-                    }
-                    // 查找后面的单位
-                    let rest: String = chars[i..].iter().collect();
-                    let rest_lower = rest.to_lowercase();
-                    if rest_lower.starts_with(" gb") || rest_lower.starts_with("gb") {
-                        return Some(num * 1024);
-                    } else if rest_lower.starts_with(" mb") || rest_lower.starts_with("mb") {
-                        return Some(num);
-                    } else if rest_lower.starts_with(" kb") || rest_lower.starts_with("kb") {
-                        return Some(num / 1024);
-                    }
-                    // 如果数字较大（>100），假设是 MB
-                    if num > 100 {
-                        return Some(num);
-                    }
-                }
-                num_str.clear();
-                found_num = false;
-            }
-        }
-        
-        // 如果循环结束还有数字
-        if !num_str.is_empty() {
-            if let Ok(num) = num_str.parse::<u64>() {
-                if num > 100 {
-                    return Some(num);
-                }
-            }
-        }
-        
-        None
-    }
-
     /// 从指定分区缩小并创建新分区（增强版，带标志文件）
     /// 
     /// # Arguments
     /// * `source_letter` - 源分区盘符
     /// * `desired_size_mb` - 期望的新分区大小（MB）
     /// * `pre_queried_max_mb` - 预先查询的最大可缩小空间（MB），如果为 None 则内部查询
+    /// * `mode` - `RunMode::DryRun` 时不缩小分区，只返回执行计划
     /// 
     /// # Returns
-    /// * `Ok(char)` - 新分区的盘符
+    /// * `Ok((char, String))` - 新分区的盘符和 diskpart 输出（试运行时为执行计划）
     /// * `Err` - 错误信息
     pub fn shrink_and_create_partition_with_marker(
+        &self,
         source_letter: char,
         desired_size_mb: u64,
         pre_queried_max_mb: Option<u64>,
-    ) -> Result<char> {
+        mode: RunMode,
+    ) -> Result<(char, String)> {
         // 使用预查询的值或者重新查询
         let max_shrink_mb = match pre_queried_max_mb {
             Some(mb) => mb,
-            None => Self::query_shrink_max(source_letter)?,
+            None => self.query_shrink_max(source_letter)?,
         };
         
         if max_shrink_mb == 0 {
@@ -793,46 +738,21 @@ impl DiskManager {
 
         // 使用 diskpart 执行操作
         // 注意：shrink 之后的未分配空间会紧跟在当前卷之后
-        let script_content = format!(
-            "select volume {}\n\
-            shrink desired={}\n\
-            create partition primary\n\
-            format fs=ntfs quick label=\"LetRecovery\"\n\
-            assign letter={}",
-            source_letter,
-            actual_size_mb,
-            new_letter
-        );
+        let script = DiskpartScript::builder()
+            .select_volume(source_letter)
+            .shrink(actual_size_mb)
+            .create_partition(PartitionType::Primary, None)
+            .format(FileSystem::Ntfs, Some("LetRecovery"))
+            .assign_letter(new_letter)
+            .build()?;
 
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_shrink_script.txt");
-        std::fs::write(&script_path, &script_content)?;
-
-        println!("[DISK] Diskpart 脚本内容:\n{}", script_content);
-
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        let output_text = gbk_to_utf8(&output.stdout);
-        let error_text = gbk_to_utf8(&output.stderr);
-
-        println!("[DISK] Diskpart 输出: {}", output_text);
-        if !error_text.is_empty() {
-            println!("[DISK] Diskpart 错误: {}", error_text);
+        if mode == RunMode::DryRun {
+            return Ok((new_letter, self.run_diskpart(&script, mode)?));
         }
+        println!("[DISK] Diskpart 执行计划:\n{}", script.plan_text());
 
-        // 检查输出是否包含错误信息
-        let output_lower = output_text.to_lowercase();
-        if output_lower.contains("error") || output_lower.contains("错误") 
-            || output_lower.contains("失败") || output_lower.contains("failed")
-            || output_lower.contains("无效") || output_lower.contains("invalid")
-            || output_lower.contains("不支持") || output_lower.contains("无法")
-            || output_lower.contains("拒绝") || output_lower.contains("denied") {
-            anyhow::bail!("Diskpart 执行失败: {}", output_text);
-        }
+        let output_text = self.run_diskpart(&script, mode)?;
+        println!("[DISK] Diskpart 输出: {}", output_text);
 
         // 等待系统识别新分区
         std::thread::sleep(std::time::Duration::from_secs(2));
@@ -875,7 +795,7 @@ impl DiskManager {
             new_letter, actual_size_mb
         );
 
-        Ok(new_letter)
+        Ok((new_letter, output_text))
     }
 
     /// 检查分区是否是自动创建的（通过检查标志文件）
@@ -885,29 +805,19 @@ impl DiskManager {
     }
 
     /// 删除自动创建的分区
-    pub fn delete_auto_created_partition(letter: char) -> Result<()> {
+    pub fn delete_auto_created_partition(&self, letter: char) -> Result<()> {
         if !Self::is_auto_created_partition(letter) {
             anyhow::bail!("分区 {} 不是自动创建的分区", letter);
         }
 
         println!("[DISK] 准备删除自动创建的分区 {}:", letter);
 
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            letter
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_delete_script.txt");
-        std::fs::write(&script_path, &script_content)?;
-
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
+        let script = DiskpartScript::builder()
+            .select_volume(letter)
+            .delete_partition_override()
+            .build()?;
 
-        let output_text = gbk_to_utf8(&output.stdout);
+        let output_text = self.run_diskpart(&script, RunMode::Execute)?;
         println!("[DISK] Diskpart 删除输出: {}", output_text);
 
         Ok(())
@@ -918,15 +828,19 @@ impl DiskManager {
     /// # Arguments
     /// * `exclude_partition` - 要排除的分区（通常是目标安装分区）
     /// * `required_size_bytes` - 需要的最小空间（字节）
+    /// * `mode` - `RunMode::DryRun` 时需要新建分区也不缩小，只返回执行计划
     /// 
     /// # Returns
-    /// * `Ok(Some((partition, is_auto_created)))` - 找到可用分区，返回分区盘符和是否是自动创建的
+    /// * `Ok(Some((partition, created)))` - 找到可用分区，返回分区盘符；新建的分区附带 diskpart
+    ///   输出（试运行时为执行计划）
     /// * `Ok(None)` - 没有找到可用分区，且无法自动创建
     /// * `Err` - 发生错误
     pub fn find_suitable_data_partition(
+        &self,
         exclude_partition: &str,
         required_size_bytes: u64,
-    ) -> Result<Option<(String, bool)>> {
+        mode: RunMode,
+    ) -> Result<Option<(String, Option<String>)>> {
         let exclude_letter = exclude_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
         
         println!("[DISK] 查找数据分区，排除: {}, 需要空间: {} bytes ({:.2} GB)", 
@@ -994,7 +908,7 @@ impl DiskManager {
 
             let selected = candidates[0].0;
             println!("[DISK] 选择数据分区: {}:", selected);
-            return Ok(Some((format!("{}:", selected), false)));
+            return Ok(Some((format!("{}:", selected), None)));
         }
 
         // 没有找到满足条件的现有分区，尝试从目标安装分区创建新分区
@@ -1002,7 +916,7 @@ impl DiskManager {
         println!("[DISK] 没有找到满足条件的现有分区，尝试从 {} 盘创建新分区", exclude_letter);
 
         // 使用 shrink querymax 查询目标分区实际可缩小的空间
-        let max_shrink_mb = match Self::query_shrink_max(exclude_letter) {
+        let max_shrink_mb = match self.query_shrink_max(exclude_letter) {
             Ok(mb) => mb,
             Err(e) => {
                 println!("[DISK] 查询 {} 盘可缩小空间失败: {}", exclude_letter, e);
@@ -1083,8 +997,9 @@ impl DiskManager {
         }
 
         // 创建新分区（传入预查询的 max_shrink_mb，避免重复查询）
-        let new_letter = Self::shrink_and_create_partition_with_marker(exclude_letter, actual_size_mb, Some(max_shrink_mb))?;
+        let (new_letter, output) =
+            self.shrink_and_create_partition_with_marker(exclude_letter, actual_size_mb, Some(max_shrink_mb), mode)?;
         
-        Ok(Some((format!("{}:", new_letter), true)))
+        Ok(Some((format!("{}:", new_letter), Some(output))))
     }
 }
@@ -1,8 +1,7 @@
 use anyhow::Result;
-use std::process::Stdio;
-use std::sync::mpsc::Sender;
+use letrecovery_common::command::{CommandRunner, SystemRunner};
+use std::sync::Arc;
 
-use crate::utils::cmd::create_command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
@@ -27,10 +26,16 @@ pub struct ImageInfo {
 pub struct Dism {
     dism_path: String,
     is_pe: bool,
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl Dism {
     pub fn new() -> Self {
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
         let bin_dir = get_bin_dir();
         Self {
             dism_path: bin_dir
@@ -39,6 +44,7 @@ impl Dism {
                 .to_string_lossy()
                 .to_string(),
             is_pe: crate::core::system_info::SystemInfo::check_pe_environment(),
+            runner,
         }
     }
 
@@ -47,65 +53,6 @@ impl Dism {
         self.is_pe
     }
 
-    /// 应用系统镜像 (WIM/ESD)
-    pub fn apply_image(
-        &self,
-        image_file: &str,
-        apply_dir: &str,
-        index: u32,
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        let args = [
-            "/Apply-Image",
-            &format!("/ImageFile:{}", image_file),
-            &format!("/ApplyDir:{}", apply_dir),
-            &format!("/Index:{}", index),
-        ];
-
-        self.run_with_progress(&args, progress_tx)
-    }
-
-    /// 捕获系统镜像 (备份)
-    pub fn capture_image(
-        &self,
-        image_file: &str,
-        capture_dir: &str,
-        name: &str,
-        description: &str,
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        let args = [
-            "/Capture-Image",
-            &format!("/ImageFile:{}", image_file),
-            &format!("/CaptureDir:{}", capture_dir),
-            &format!("/Name:{}", name),
-            &format!("/Description:{}", description),
-            "/Compress:max",
-        ];
-
-        self.run_with_progress(&args, progress_tx)
-    }
-
-    /// 增量备份镜像
-    pub fn append_image(
-        &self,
-        image_file: &str,
-        capture_dir: &str,
-        name: &str,
-        description: &str,
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        let args = [
-            "/Append-Image",
-            &format!("/ImageFile:{}", image_file),
-            &format!("/CaptureDir:{}", capture_dir),
-            &format!("/Name:{}", name),
-            &format!("/Description:{}", description),
-        ];
-
-        self.run_with_progress(&args, progress_tx)
-    }
-
     /// 导出驱动 - 自动检测环境
     /// 在PE环境下，需要指定源系统路径 (如 "C:")
     /// 在正常环境下，使用 /online
@@ -116,13 +63,11 @@ impl Dism {
             anyhow::bail!("PE环境下无法导出当前系统驱动，请使用 export_drivers_from_system 并指定目标系统分区");
         }
 
-        let output = create_command(&self.dism_path)
-            .args([
-                "/online",
-                "/export-driver",
-                &format!("/destination:{}", destination),
-            ])
-            .output()?;
+        let output = self.runner.output(&self.dism_path, &[
+            "/online",
+            "/export-driver",
+            &format!("/destination:{}", destination),
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -138,13 +83,11 @@ impl Dism {
         // 使用离线方式导出驱动
         let image_path = format!("{}\\", system_partition);
         
-        let output = create_command(&self.dism_path)
-            .args([
-                &format!("/image:{}", image_path),
-                "/export-driver",
-                &format!("/destination:{}", destination),
-            ])
-            .output()?;
+        let output = self.runner.output(&self.dism_path, &[
+            &format!("/image:{}", image_path),
+            "/export-driver",
+            &format!("/destination:{}", destination),
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -172,15 +115,13 @@ impl Dism {
             anyhow::bail!("PE环境下无法使用在线方式添加驱动，请使用 add_drivers_offline");
         }
 
-        let output = create_command(&self.dism_path)
-            .args([
-                "/online",
-                "/add-driver",
-                "/forceunsigned",
-                &format!("/driver:{}", driver_path),
-                "/recurse",
-            ])
-            .output()?;
+        let output = self.runner.output(&self.dism_path, &[
+            "/online",
+            "/add-driver",
+            "/forceunsigned",
+            &format!("/driver:{}", driver_path),
+            "/recurse",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -191,15 +132,13 @@ impl Dism {
 
     /// 导入驱动到离线系统 (PE和正常环境都可用)
     pub fn add_drivers_offline(&self, image_path: &str, driver_path: &str) -> Result<()> {
-        let output = create_command(&self.dism_path)
-            .args([
-                &format!("/image:{}", image_path),
-                "/add-driver",
-                "/forceunsigned",
-                &format!("/driver:{}", driver_path),
-                "/recurse",
-            ])
-            .output()?;
+        let output = self.runner.output(&self.dism_path, &[
+            &format!("/image:{}", image_path),
+            "/add-driver",
+            "/forceunsigned",
+            &format!("/driver:{}", driver_path),
+            "/recurse",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -227,23 +166,21 @@ impl Dism {
     /// 使用 DISM 命令获取镜像信息
     fn get_image_info_via_dism(&self, image_file: &str) -> Result<Vec<ImageInfo>> {
         // 首先获取所有索引的基本信息
-        let output = create_command(&self.dism_path)
-            .args(["/get-imageinfo", &format!("/imagefile:{}", image_file)])
-            .output()?;
+        let output = self
+            .runner
+            .output(&self.dism_path, &["/get-imageinfo", &format!("/imagefile:{}", image_file)])?;
 
         let stdout = gbk_to_utf8(&output.stdout);
         let mut images = Self::parse_basic_image_info(&stdout)?;
 
         // 对每个索引获取详细信息（包括 Installation Type）
         for image in &mut images {
-            if let Ok(detail_output) = create_command(&self.dism_path)
-                .args([
-                    "/get-imageinfo",
-                    &format!("/imagefile:{}", image_file),
-                    &format!("/index:{}", image.index),
-                ])
-                .output()
-            {
+            let detail = self.runner.output(&self.dism_path, &[
+                "/get-imageinfo",
+                &format!("/imagefile:{}", image_file),
+                &format!("/index:{}", image.index),
+            ]);
+            if let Ok(detail_output) = detail {
                 let detail_stdout = gbk_to_utf8(&detail_output.stdout);
                 // 解析详细信息中的 Installation Type
                 for line in detail_stdout.lines() {
@@ -477,166 +414,12 @@ impl Dism {
         Ok(images)
     }
 
-    /// 执行 DISM 命令并实时获取进度
-    fn run_with_progress(
-        &self,
-        args: &[&str],
-        progress_tx: Option<Sender<DismProgress>>,
-    ) -> Result<()> {
-        use std::io::Read;
-        
-        println!("[DISM] 启动命令: {} {:?}", &self.dism_path, args);
-        
-        let mut child = create_command(&self.dism_path)
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        if let Some(mut stdout) = child.stdout.take() {
-            let mut buffer = [0u8; 1024];
-            let mut line_buffer = Vec::new();
-            
-            loop {
-                match stdout.read(&mut buffer) {
-                    Ok(0) => break, // EOF
-                    Ok(n) => {
-                        // 打印原始字节用于调试
-                        println!("[DISM RAW] 读取 {} 字节", n);
-                        
-                        for &byte in &buffer[..n] {
-                            // \r 或 \n 都作为行结束符
-                            if byte == b'\r' || byte == b'\n' {
-                                if !line_buffer.is_empty() {
-                                    let line = gbk_to_utf8(&line_buffer);
-                                    println!("[DISM LINE] {}", line);
-                                    
-                                    if let Some(progress) = Self::parse_progress_line(&line) {
-                                        println!("[DISM PROGRESS] 解析到进度: {}%", progress.percentage);
-                                        if let Some(ref tx) = progress_tx {
-                                            let _ = tx.send(progress);
-                                        }
-                                    }
-                                    line_buffer.clear();
-                                }
-                            } else {
-                                line_buffer.push(byte);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        println!("[DISM ERROR] 读取错误: {:?}", e);
-                        if e.kind() != std::io::ErrorKind::Interrupted {
-                            break;
-                        }
-                    }
-                }
-            }
-            
-            // 处理剩余的数据
-            if !line_buffer.is_empty() {
-                let line = gbk_to_utf8(&line_buffer);
-                println!("[DISM FINAL] {}", line);
-                if let Some(progress) = Self::parse_progress_line(&line) {
-                    println!("[DISM PROGRESS] 最终进度: {}%", progress.percentage);
-                    if let Some(ref tx) = progress_tx {
-                        let _ = tx.send(progress);
-                    }
-                }
-            }
-        }
-
-        // 同时读取 stderr
-        if let Some(mut stderr) = child.stderr.take() {
-            let mut err_output = Vec::new();
-            let _ = stderr.read_to_end(&mut err_output);
-            if !err_output.is_empty() {
-                let err_str = gbk_to_utf8(&err_output);
-                println!("[DISM STDERR] {}", err_str);
-            }
-        }
-
-        let status = child.wait()?;
-        println!("[DISM] 命令结束，状态: {:?}", status);
-        if !status.success() {
-            anyhow::bail!("DISM command failed");
-        }
-        Ok(())
-    }
-
-    /// 解析 DISM 进度输出
-    /// DISM 输出格式示例:
-    /// [==                    ]  10.0%
-    /// [=====                 ] 25%
-    /// 正在捕获映像 [===========         ]  55.0%
-    fn parse_progress_line(line: &str) -> Option<DismProgress> {
-        // 方法1: 直接查找百分比数字
-        // 匹配格式: 数字 + 可选小数 + %
-        let mut percentage: Option<u8> = None;
-
-        // 查找 xx.x% 或 xx% 格式
-        let line_chars: Vec<char> = line.chars().collect();
-        for i in 0..line_chars.len() {
-            if line_chars[i] == '%' && i > 0 {
-                // 向前查找数字
-                let mut num_str = String::new();
-                let mut j = i as i32 - 1;
-                
-                // 跳过可能的小数部分
-                while j >= 0 {
-                    let c = line_chars[j as usize];
-                    if c.is_ascii_digit() || c == '.' {
-                        num_str.insert(0, c);
-                        j -= 1;
-                    } else if c == ' ' && num_str.is_empty() {
-                        j -= 1;
-                    } else {
-                        break;
-                    }
-                }
-
-                if !num_str.is_empty() {
-                    // 解析数字，取整数部分
-                    if let Some(dot_pos) = num_str.find('.') {
-                        num_str = num_str[..dot_pos].to_string();
-                    }
-                    if let Ok(p) = num_str.parse::<u8>() {
-                        percentage = Some(p);
-                        break;
-                    }
-                }
-            }
-        }
-
-        // 方法2: 如果上面没找到，尝试旧的解析方式
-        if percentage.is_none() && line.contains('[') && line.contains(']') {
-            if let Some(after_bracket) = line.split(']').nth(1) {
-                let cleaned = after_bracket
-                    .trim()
-                    .replace("=", "")
-                    .replace(" ", "")
-                    .replace(".0", "")
-                    .replace("%", "");
-                if let Ok(p) = cleaned.parse::<u8>() {
-                    percentage = Some(p);
-                }
-            }
-        }
-
-        percentage.map(|p| DismProgress {
-            percentage: p,
-            status: format!("{}%", p),
-        })
-    }
-
     /// 获取系统信息 (离线)
     pub fn get_offline_system_info(&self, image_path: &str) -> Result<String> {
-        let output = create_command(&self.dism_path)
-            .args([
-                &format!("/image:{}", image_path),
-                "/get-currentedition",
-            ])
-            .output()?;
+        let output = self.runner.output(&self.dism_path, &[
+            &format!("/image:{}", image_path),
+            "/get-currentedition",
+        ])?;
 
         Ok(gbk_to_utf8(&output.stdout))
     }
@@ -659,9 +442,7 @@ impl Dism {
 
         let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
         
-        let output = create_command(&self.dism_path)
-            .args(&args_ref)
-            .output()?;
+        let output = self.runner.output(&self.dism_path, &args_ref)?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
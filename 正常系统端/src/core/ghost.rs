@@ -6,19 +6,19 @@
 //! - 验证 GHO 文件有效性
 //! - 获取 GHO 镜像信息
 //! - 恢复 GHO 镜像到指定分区（支持进度回调）
+//! - 备份分区为 GHO 镜像
 //! - 支持取消正在进行的操作
 
 use anyhow::{Context, Result};
-use std::io::{BufRead, BufReader, Read};
+use letrecovery_common::command::{SystemRunner, CommandRunner, StreamEvent};
+use std::io::Read;
 use std::path::Path;
-use std::process::{Child, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::core::dism::DismProgress;
-use crate::utils::cmd::create_command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
@@ -104,19 +104,27 @@ pub struct Ghost {
     ghost_path: String,
     /// 取消标志
     cancel_flag: Arc<AtomicBool>,
+    /// 命令执行器
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl Ghost {
     /// 创建新的 Ghost 实例
     pub fn new() -> Self {
-        let bin_dir = get_bin_dir();
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器创建 Ghost 实例
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
+        let ghost_path = get_bin_dir()
+            .join("ghost")
+            .join("ghost64.exe")
+            .to_string_lossy()
+            .to_string();
         Self {
-            ghost_path: bin_dir
-                .join("ghost")
-                .join("ghost64.exe")
-                .to_string_lossy()
-                .to_string(),
+            ghost_path,
             cancel_flag: Arc::new(AtomicBool::new(false)),
+            runner,
         }
     }
 
@@ -125,6 +133,7 @@ impl Ghost {
         Self {
             ghost_path: ghost_path.to_string(),
             cancel_flag: Arc::new(AtomicBool::new(false)),
+            runner: Arc::new(SystemRunner),
         }
     }
 
@@ -284,19 +293,7 @@ impl Ghost {
 
         println!("[GHOST] 执行命令: {} {} -sure -fx -batch", self.ghost_path, clone_param);
 
-        let mut child = create_command(&self.ghost_path)
-            .args([&clone_param, "-sure", "-fx", "-batch"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("无法启动 Ghost 进程")?;
-
-        let result = self.monitor_ghost_process(&mut child, progress_tx, estimated_size);
-
-        let _ = child.kill();
-        let _ = child.wait();
-
-        result
+        self.run_ghost(&[&clone_param, "-sure", "-fx", "-batch"], progress_tx, estimated_size)
     }
 
     /// 使用盘符恢复 GHO 镜像
@@ -307,14 +304,33 @@ impl Ghost {
         partitions: &[crate::core::disk::Partition],
         progress_tx: Option<Sender<DismProgress>>,
     ) -> Result<()> {
-        let letter = target_letter.trim_end_matches(['\\', '/']).to_uppercase();
+        let (ghost_disk, ghost_partition) = Self::ghost_partition(target_letter, partitions)?;
+        self.restore_image(gho_file, ghost_disk, ghost_partition, progress_tx)
+    }
+
+    /// 使用盘符备份分区为 GHO 镜像
+    pub fn create_image_from_letter(
+        &self,
+        source_letter: &str,
+        partitions: &[crate::core::disk::Partition],
+        gho_file: &str,
+        compression: u8,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()> {
+        let (ghost_disk, ghost_partition) = Self::ghost_partition(source_letter, partitions)?;
+        self.create_image(ghost_disk, ghost_partition, gho_file, compression, progress_tx)
+    }
+
+    /// 把盘符转换为 Ghost 使用的磁盘号和分区号
+    fn ghost_partition(letter: &str, partitions: &[crate::core::disk::Partition]) -> Result<(u32, u32)> {
+        let letter = letter.trim_end_matches(['\\', '/']).to_uppercase();
         let letter = if letter.ends_with(':') {
             letter
         } else {
             format!("{}:", letter)
         };
 
-        println!("[GHOST] 解析目标盘符: {}", letter);
+        println!("[GHOST] 解析盘符: {}", letter);
 
         let partition = partitions
             .iter()
@@ -341,153 +357,106 @@ impl Ghost {
         println!("[GHOST]   Windows: Disk {} Partition {}", disk_number, partition_number);
         println!("[GHOST]   Ghost:   {}:{}", ghost_disk, ghost_partition);
 
-        self.restore_image(gho_file, ghost_disk, ghost_partition, progress_tx)
+        Ok((ghost_disk, ghost_partition))
     }
 
-    /// 监控 Ghost 进程并报告进度
-    fn monitor_ghost_process(
+    /// 运行 Ghost 并报告进度
+    ///
+    /// Ghost 在 -batch 模式下没有可解析的进度输出，按预计耗时估算进度（最多 95%）。
+    fn run_ghost(
         &self,
-        child: &mut Child,
+        args: &[&str],
         progress_tx: Option<Sender<DismProgress>>,
         estimated_size: u64,
     ) -> Result<()> {
         let cancel_flag = Arc::clone(&self.cancel_flag);
-        
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
-
-        let stdout_handle = if let Some(stdout) = stdout {
-            let cancel = Arc::clone(&cancel_flag);
-            Some(std::thread::spawn(move || {
-                Self::read_ghost_output(stdout, cancel)
-            }))
-        } else {
-            None
-        };
-
-        let stderr_content = Arc::new(std::sync::Mutex::new(String::new()));
-        let stderr_content_clone = Arc::clone(&stderr_content);
-        
-        let stderr_handle = if let Some(stderr) = stderr {
-            Some(std::thread::spawn(move || {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines().map_while(Result::ok) {
-                    let line_utf8 = gbk_to_utf8(line.as_bytes());
-                    println!("[GHOST STDERR] {}", line_utf8);
-                    if let Ok(mut content) = stderr_content_clone.lock() {
-                        content.push_str(&line_utf8);
-                        content.push('\n');
-                    }
-                }
-            }))
-        } else {
-            None
-        };
-
         let start_time = std::time::Instant::now();
-        
+
         let estimated_seconds = if estimated_size > 0 {
-            (estimated_size / (100 * 1024 * 1024)).max(60) as u64
+            (estimated_size / (100 * 1024 * 1024)).max(60)
         } else {
             300
         };
         let estimated_duration = Duration::from_secs(estimated_seconds);
-        
-        println!("[GHOST] 预计恢复时间: {} 秒", estimated_seconds);
-        
-        let mut last_progress: u8 = 0;
 
-        loop {
-            if cancel_flag.load(Ordering::SeqCst) {
-                println!("[GHOST] 收到取消请求，终止进程");
-                let _ = child.kill();
-                return Err(GhostError::Cancelled.into());
-            }
+        println!("[GHOST] 预计耗时: {} 秒", estimated_seconds);
 
-            match child.try_wait() {
-                Ok(Some(status)) => {
-                    println!("[GHOST] 进程退出，状态码: {:?}", status.code());
-                    
-                    if let Some(handle) = stdout_handle {
-                        let _ = handle.join();
-                    }
-                    
-                    if let Some(handle) = stderr_handle {
-                        let _ = handle.join();
-                    }
-                    
-                    let stderr_output = stderr_content.lock()
-                        .map(|s| s.clone())
-                        .unwrap_or_default();
-
-                    if let Some(ref tx) = progress_tx {
-                        let _ = tx.send(DismProgress {
-                            percentage: 100,
-                            status: "STEP:3:释放系统镜像".to_string(),
-                        });
-                    }
+        let mut last_progress: u8 = 0;
+        let mut line_buffer = Vec::new();
+
+        let output = self
+            .runner
+            .stream(&self.ghost_path, args, &mut |event| {
+                if cancel_flag.load(Ordering::SeqCst) {
+                    println!("[GHOST] 收到取消请求，终止进程");
+                    return false;
+                }
 
-                    if status.success() || status.code() == Some(0) {
-                        println!("[GHOST] ========================================");
-                        println!("[GHOST] 镜像恢复成功!");
-                        println!("[GHOST] ========================================");
-                        return Ok(());
-                    } else {
-                        let error_msg = if stderr_output.trim().is_empty() {
-                            format!("Ghost 进程异常退出，退出码: {:?}", status.code())
-                        } else {
-                            format!("Ghost 错误: {}", stderr_output.trim())
-                        };
-                        println!("[GHOST] 恢复失败: {}", error_msg);
-                        return Err(GhostError::ExecutionFailed(error_msg).into());
+                match event {
+                    StreamEvent::Stdout(chunk) => {
+                        for &byte in chunk {
+                            if byte == b'\n' {
+                                println!("[GHOST STDOUT] {}", gbk_to_utf8(&line_buffer));
+                                line_buffer.clear();
+                            } else if byte != b'\r' {
+                                line_buffer.push(byte);
+                            }
+                        }
                     }
-                }
-                Ok(None) => {
-                    let elapsed = start_time.elapsed();
-                    let progress = ((elapsed.as_secs_f64() / estimated_duration.as_secs_f64()) * 95.0)
-                        .min(95.0) as u8;
-                    
-                    if progress > last_progress {
-                        last_progress = progress;
-                        println!("[GHOST] 进度: {}% (已运行 {:.0} 秒)", progress, elapsed.as_secs_f64());
-                        
-                        if let Some(ref tx) = progress_tx {
-                            let _ = tx.send(DismProgress {
-                                percentage: progress,
-                                status: "STEP:3:释放系统镜像".to_string(),
-                            });
+                    StreamEvent::Tick => {
+                        let elapsed = start_time.elapsed();
+                        let progress = ((elapsed.as_secs_f64() / estimated_duration.as_secs_f64()) * 95.0)
+                            .min(95.0) as u8;
+
+                        if progress > last_progress {
+                            last_progress = progress;
+                            println!("[GHOST] 进度: {}% (已运行 {:.0} 秒)", progress, elapsed.as_secs_f64());
+
+                            if let Some(ref tx) = progress_tx {
+                                let _ = tx.send(DismProgress {
+                                    percentage: progress,
+                                    status: "STEP:3:释放系统镜像".to_string(),
+                                });
+                            }
                         }
                     }
                 }
-                Err(e) => {
-                    return Err(GhostError::ExecutionFailed(
-                        format!("检查进程状态失败: {}", e)
-                    ).into());
-                }
-            }
+                true
+            })
+            .context("无法启动 Ghost 进程")?;
 
-            std::thread::sleep(Duration::from_millis(500));
+        if cancel_flag.load(Ordering::SeqCst) && output.status.code().is_none() {
+            return Err(GhostError::Cancelled.into());
         }
-    }
 
-    /// 读取 Ghost 输出
-    fn read_ghost_output<R: Read>(reader: R, cancel_flag: Arc<AtomicBool>) -> Vec<String> {
-        let reader = BufReader::new(reader);
-        let mut lines = Vec::new();
-        
-        for line in reader.lines() {
-            if cancel_flag.load(Ordering::SeqCst) {
-                break;
-            }
+        println!("[GHOST] 进程退出，状态码: {:?}", output.status.code());
 
-            if let Ok(line) = line {
-                let line_utf8 = gbk_to_utf8(line.as_bytes());
-                println!("[GHOST STDOUT] {}", line_utf8);
-                lines.push(line_utf8);
-            }
+        let stderr_output = gbk_to_utf8(&output.stderr);
+        if !stderr_output.trim().is_empty() {
+            println!("[GHOST STDERR] {}", stderr_output.trim());
+        }
+
+        if let Some(ref tx) = progress_tx {
+            let _ = tx.send(DismProgress {
+                percentage: 100,
+                status: "STEP:3:释放系统镜像".to_string(),
+            });
+        }
+
+        if output.status.success() {
+            println!("[GHOST] ========================================");
+            println!("[GHOST] Ghost 执行成功!");
+            println!("[GHOST] ========================================");
+            Ok(())
+        } else {
+            let error_msg = if stderr_output.trim().is_empty() {
+                format!("Ghost 进程异常退出，退出码: {:?}", output.status.code())
+            } else {
+                format!("Ghost 错误: {}", stderr_output.trim())
+            };
+            println!("[GHOST] 执行失败: {}", error_msg);
+            Err(GhostError::ExecutionFailed(error_msg).into())
         }
-        
-        lines
     }
 
     /// 创建 GHO 镜像（备份功能）
@@ -539,19 +508,12 @@ impl Ghost {
             source_partition, gho_file
         );
 
-        let mut child = create_command(&self.ghost_path)
-            .args([&clone_param, "-sure", "-fx", "-batch", &format!("-z{}", compression)])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("无法启动 Ghost 进程")?;
-
-        let result = self.monitor_ghost_process(&mut child, progress_tx, 0);
-
-        let _ = child.kill();
-        let _ = child.wait();
-
-        result
+        let compression_param = format!("-z{}", compression);
+        self.run_ghost(
+            &[&clone_param, "-sure", "-fx", "-batch", &compression_param],
+            progress_tx,
+            0,
+        )
     }
 }
 
@@ -588,4 +550,32 @@ mod tests {
         ghost.reset_cancel();
         assert!(!ghost.cancel_flag.load(Ordering::SeqCst));
     }
+    #[test]
+    fn test_create_image_with_fake_runner() {
+        use letrecovery_common::command::{CommandOutput, FakeRunner};
+
+        let dir = std::env::temp_dir().join(format!("lr_ghost_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("ghost64.exe");
+        std::fs::write(&exe, b"").unwrap();
+        let gho = dir.join("backup.gho");
+
+        let fake = Arc::new(FakeRunner::new());
+        fake.reply("ghost64", &["mode=pdump", "src=1:2"], CommandOutput::success(""));
+
+        let ghost = Ghost {
+            ghost_path: exe.to_string_lossy().to_string(),
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+            runner: fake.clone(),
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        ghost.create_image(1, 2, &gho.to_string_lossy(), 9, Some(tx)).unwrap();
+
+        let calls = fake.calls_to("ghost64");
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].args.contains(&"-z9".to_string()));
+        assert_eq!(rx.try_iter().last().map(|p| p.percentage), Some(100));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -1,6 +1,7 @@
 //! 硬件信息模块
 //! 使用纯 WinAPI 获取硬件信息
 
+use serde::Serialize;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::mem::{size_of, zeroed};
@@ -45,7 +46,7 @@ use windows::Win32::System::Variant::{
 use windows::Win32::System::Ole::SafeArrayGetElement;
 
 /// 设备类型枚举
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
 pub enum DeviceType {
     #[default]
     Unknown,
@@ -70,7 +71,7 @@ impl std::fmt::Display for DeviceType {
 }
 
 /// 电池信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct BatteryInfo {
     pub charge_percent: u8,
     pub is_charging: bool,
@@ -83,7 +84,7 @@ pub struct BatteryInfo {
 }
 
 /// CPU 信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CpuInfo {
     pub name: String,
     pub manufacturer: String,
@@ -98,7 +99,7 @@ pub struct CpuInfo {
 }
 
 /// 内存条信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct MemoryStickInfo {
     pub capacity: u64,
     pub speed: u32,
@@ -110,7 +111,7 @@ pub struct MemoryStickInfo {
 }
 
 /// 内存信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct MemoryInfo {
     pub total_physical: u64,
     pub available_physical: u64,
@@ -122,7 +123,7 @@ pub struct MemoryInfo {
 }
 
 /// 主板信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct MotherboardInfo {
     pub manufacturer: String,
     pub product: String,
@@ -131,7 +132,7 @@ pub struct MotherboardInfo {
 }
 
 /// BIOS 信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct BiosInfo {
     pub manufacturer: String,
     pub version: String,
@@ -140,7 +141,7 @@ pub struct BiosInfo {
 }
 
 /// 硬盘信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct DiskInfo {
     pub model: String,
     pub interface_type: String,
@@ -156,7 +157,7 @@ pub struct DiskInfo {
 }
 
 /// BitLocker 加密状态
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
 pub enum BitLockerStatus {
     #[default]
     Unknown,
@@ -167,7 +168,7 @@ pub enum BitLockerStatus {
 }
 
 /// 显卡信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct GpuInfo {
     pub name: String,
     pub adapter_compatibility: String,
@@ -180,7 +181,7 @@ pub struct GpuInfo {
 }
 
 /// 操作系统信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct OsInfo {
     pub name: String,
     pub version: String,
@@ -192,7 +193,7 @@ pub struct OsInfo {
 }
 
 /// 网络适配器信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct NetworkAdapterInfo {
     pub name: String,
     pub description: String,
@@ -204,7 +205,7 @@ pub struct NetworkAdapterInfo {
 }
 
 /// 完整硬件信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct HardwareInfo {
     pub cpu: CpuInfo,
     pub memory: MemoryInfo,
@@ -859,6 +860,11 @@ fn get_disk_sizes_wmi() -> HashMap<u32, u64> {
     sizes
 }
 
+/// 获取指定分区的 BitLocker 加密状态
+pub fn get_volume_bitlocker_status(drive_letter: &str) -> BitLockerStatus {
+    get_bitlocker_status_wmi(drive_letter)
+}
+
 /// 使用 WMI 获取 BitLocker 加密状态
 /// 通过 Win32_EncryptableVolume 类查询（需要管理员权限）
 fn get_bitlocker_status_wmi(drive_letter: &str) -> BitLockerStatus {
@@ -978,6 +984,17 @@ impl HardwareInfo {
         Ok(info)
     }
 
+    /// 设备序列号（优先使用整机序列号，其次主板序列号，均无效时返回空）
+    pub fn device_serial(&self) -> &str {
+        if !is_placeholder(&self.system_serial_number) {
+            &self.system_serial_number
+        } else if !is_placeholder(&self.motherboard.serial_number) {
+            &self.motherboard.serial_number
+        } else {
+            ""
+        }
+    }
+
     pub fn to_formatted_text(&self, sys_info: Option<&crate::core::system_info::SystemInfo>) -> String {
         let mut lines = Vec::new();
         let arch_str = match self.os.architecture.as_str() {
@@ -1073,7 +1090,7 @@ impl HardwareInfo {
         os_info
     }
 
-    fn get_cpu_info() -> CpuInfo {
+    pub fn get_cpu_info() -> CpuInfo {
         let mut cpu_info = CpuInfo::default();
         unsafe { let mut sys_info: SYSTEM_INFO = zeroed(); GetNativeSystemInfo(&mut sys_info); cpu_info.logical_processors = sys_info.dwNumberOfProcessors; cpu_info.architecture = match sys_info.Anonymous.Anonymous.wProcessorArchitecture.0 { 0 => "x86".to_string(), 9 => "x64".to_string(), 12 => "ARM64".to_string(), _ => "未知".to_string(), }; }
         let cpu_path = r"HARDWARE\DESCRIPTION\System\CentralProcessor\0";
@@ -1084,7 +1101,7 @@ impl HardwareInfo {
         cpu_info
     }
 
-    fn get_memory_info() -> MemoryInfo {
+    pub fn get_memory_info() -> MemoryInfo {
         let mut mem_info = MemoryInfo::default();
 
         // 使用 GlobalMemoryStatusEx 获取内存总量
@@ -1263,7 +1280,8 @@ impl HardwareInfo {
         DeviceType::Unknown
     }
 
-    fn get_battery_info() -> Option<BatteryInfo> {
+    /// 读取当前电池状态，没有电池时返回 None
+    pub fn get_battery_info() -> Option<BatteryInfo> {
         #[link(name = "kernel32")] extern "system" { fn GetSystemPowerStatus(lpSystemPowerStatus: *mut SYSTEM_POWER_STATUS) -> i32; }
         unsafe {
             let mut power_status: SYSTEM_POWER_STATUS = zeroed();
@@ -1,4 +1,9 @@
 use anyhow::{Context, Result};
+use letrecovery_common::backup::compression::{BackupFormat, Compression};
+use letrecovery_common::backup::exclusions::ExclusionConfig;
+use letrecovery_common::backup::retention::RetentionPolicy;
+use letrecovery_common::unattend::accounts::DEFAULT_GROUP;
+use letrecovery_common::unattend::{AnswerFileOptions, BuiltinAdministrator, EncodedPassword, LocalAccount};
 use std::path::Path;
 
 /// 系统安装配置（用于PE环境内安装）
@@ -38,10 +43,81 @@ pub struct InstallConfig {
     pub disable_uac: bool,
     /// 禁用自动设备加密
     pub disable_device_encryption: bool,
+    /// 写入 LabConfig 绕过 Windows 11 硬件要求检查
+    pub bypass_win11_check: bool,
     /// 删除预装UWP应用
     pub remove_uwp_apps: bool,
     /// 自定义用户名
     pub custom_username: String,
+
+    // 无人值守区域与系统信息（为空时不写入应答文件）
+    /// 界面语言
+    pub ui_language: String,
+    /// 键盘/输入法
+    pub input_locale: String,
+    /// 系统区域
+    pub system_locale: String,
+    /// 时区
+    pub time_zone: String,
+    /// 计算机名（已展开模板）
+    pub computer_name: String,
+    /// 产品密钥
+    pub product_key: String,
+    /// 组织名称
+    pub organization: String,
+
+    // 无人值守账户（密码均为编码后的值，配置文件中不出现明文）
+    /// 本地账户，第一个账户用于自动登录
+    pub accounts: Vec<LocalAccount>,
+    /// 不自动登录
+    pub disable_auto_logon: bool,
+    /// 内置 Administrator 账户
+    pub builtin_administrator: BuiltinAdministrator,
+    /// 内置 Administrator 密码
+    pub administrator_password: EncodedPassword,
+    /// 用户提供的应答文件（相对于数据目录，为空表示不导入）
+    pub unattend_file: String,
+    /// 数据目录中暂存了装机软件，部署后复制到目标系统并在首次登录时安装
+    pub install_software: bool,
+    /// 使用的部署方案名称（为空表示未使用方案）
+    pub profile: String,
+}
+
+impl InstallConfig {
+    /// 用户应答文件的完整路径
+    pub fn unattend_file_path(&self, data_dir: &str) -> Option<String> {
+        if self.unattend_file.is_empty() {
+            None
+        } else {
+            Some(format!("{}\\{}", data_dir, self.unattend_file))
+        }
+    }
+
+    /// 转换为应答文件选项（架构由调用方检测后填写）
+    pub fn answer_file_options(&self) -> AnswerFileOptions {
+        let accounts = if self.accounts.is_empty() && !self.custom_username.is_empty() {
+            vec![LocalAccount::new(&self.custom_username, DEFAULT_GROUP, "")]
+        } else {
+            self.accounts.clone()
+        };
+
+        AnswerFileOptions {
+            accounts,
+            disable_auto_logon: self.disable_auto_logon,
+            builtin_administrator: self.builtin_administrator,
+            administrator_password: self.administrator_password.clone(),
+            remove_uwp_apps: self.remove_uwp_apps,
+            install_software: self.install_software,
+            ui_language: self.ui_language.clone(),
+            input_locale: self.input_locale.clone(),
+            system_locale: self.system_locale.clone(),
+            time_zone: self.time_zone.clone(),
+            computer_name: self.computer_name.clone(),
+            product_key: self.product_key.clone(),
+            organization: self.organization.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 /// 系统备份配置（用于PE环境内备份）
@@ -55,8 +131,20 @@ pub struct BackupConfig {
     pub description: String,
     /// 源分区盘符
     pub source_partition: String,
+    /// 备份格式 (WIM 或 GHO)
+    pub format: BackupFormat,
     /// 是否增量备份
     pub incremental: bool,
+    /// 备份后是否校验镜像
+    pub verify: bool,
+    /// 备份后按策略清理旧的卷
+    pub retention: Option<RetentionPolicy>,
+    /// 捕获时排除和不压缩的文件
+    pub exclusions: ExclusionConfig,
+    /// 新建镜像时的压缩方式
+    pub compression: Compression,
+    /// 备份后导出 ESD 归档副本
+    pub export_esd: bool,
 }
 
 /// 配置文件管理器
@@ -71,6 +159,9 @@ impl ConfigFileManager {
     const INSTALL_CONFIG: &'static str = "LetRecovery_Install.ini";
     const BACKUP_CONFIG: &'static str = "LetRecovery_Backup.ini";
     
+    /// 复制到数据目录的用户应答文件名
+    pub const USER_UNATTEND: &'static str = "LetRecovery_Unattend.xml";
+
     /// PE文件目录名
     const PE_DIR: &'static str = "LetRecovery_PE";
     
@@ -218,7 +309,7 @@ impl ConfigFileManager {
                 println!("[CONFIG] 发现自动创建的分区: {}:", c);
                 
                 // 尝试删除分区
-                if let Ok(_) = crate::core::disk::DiskManager::delete_auto_created_partition(c) {
+                if let Ok(_) = crate::core::disk::DiskManager::new().delete_auto_created_partition(c) {
                     cleaned.push(c);
                     println!("[CONFIG] 已清理自动创建的分区: {}:", c);
                 } else {
@@ -249,7 +340,7 @@ impl ConfigFileManager {
 
     /// 序列化安装配置为INI格式
     fn serialize_install_config(config: &InstallConfig) -> String {
-        format!(
+        let mut content = format!(
             r#"[Install]
 Unattended={}
 RestoreDrivers={}
@@ -259,6 +350,7 @@ VolumeIndex={}
 TargetPartition={}
 ImagePath={}
 IsGho={}
+Profile={}
 
 [Advanced]
 RemoveShortcutArrow={}
@@ -269,8 +361,23 @@ DisableWindowsDefender={}
 DisableReservedStorage={}
 DisableUAC={}
 DisableDeviceEncryption={}
+BypassWin11Check={}
 RemoveUWPApps={}
 CustomUsername={}
+InstallSoftware={}
+
+[Unattend]
+UILanguage={}
+InputLocale={}
+SystemLocale={}
+TimeZone={}
+ComputerName={}
+ProductKey={}
+Organization={}
+DisableAutoLogon={}
+BuiltinAdministrator={}
+AdministratorPassword={}
+UnattendFile={}
 "#,
             config.unattended,
             config.restore_drivers,
@@ -280,6 +387,7 @@ CustomUsername={}
             config.target_partition,
             config.image_path,
             config.is_gho,
+            config.profile,
             config.remove_shortcut_arrow,
             config.restore_classic_context_menu,
             config.bypass_nro,
@@ -288,27 +396,65 @@ CustomUsername={}
             config.disable_reserved_storage,
             config.disable_uac,
             config.disable_device_encryption,
+            config.bypass_win11_check,
             config.remove_uwp_apps,
             config.custom_username,
-        )
+            config.install_software,
+            config.ui_language,
+            config.input_locale,
+            config.system_locale,
+            config.time_zone,
+            config.computer_name,
+            config.product_key,
+            config.organization,
+            config.disable_auto_logon,
+            config.builtin_administrator.as_str(),
+            config.administrator_password.as_str(),
+            config.unattend_file,
+        );
+        // 每个账户一行，密码为编码后的值
+        for account in &config.accounts {
+            content.push_str(&format!("LocalAccount={}\n", account.to_config_value()));
+        }
+        content
     }
 
     /// 序列化备份配置为INI格式
     fn serialize_backup_config(config: &BackupConfig) -> String {
-        format!(
+        let mut content = format!(
             r#"[Backup]
 SavePath={}
 Name={}
 Description={}
 SourcePartition={}
+Format={}
 Incremental={}
+Verify={}
+Retention={}
+Compression={}
+ExportEsd={}
 "#,
             config.save_path,
             config.name,
             config.description,
             config.source_partition,
+            config.format.to_config_value(),
             config.incremental,
-        )
+            config.verify,
+            config.retention.map(|r| r.to_config_value()).unwrap_or_default(),
+            config.compression.to_config_value(),
+            config.export_esd,
+        );
+        // 列表已包含选用的内置项，读取时按原样使用，关闭内置项且没有自定义项时为空
+        content.push_str("UseDefaults=false\n");
+        // 每项一行
+        for item in &config.exclusions.exclusions {
+            content.push_str(&format!("Exclude={}\n", item));
+        }
+        for item in &config.exclusions.compression_exclusions {
+            content.push_str(&format!("NoCompress={}\n", item));
+        }
+        content
     }
 
     /// 反序列化安装配置
@@ -342,8 +488,31 @@ Incremental={}
                     "DisableReservedStorage" => config.disable_reserved_storage = value.parse().unwrap_or(false),
                     "DisableUAC" => config.disable_uac = value.parse().unwrap_or(false),
                     "DisableDeviceEncryption" => config.disable_device_encryption = value.parse().unwrap_or(false),
+                    "BypassWin11Check" => config.bypass_win11_check = value.parse().unwrap_or(false),
                     "RemoveUWPApps" => config.remove_uwp_apps = value.parse().unwrap_or(false),
                     "CustomUsername" => config.custom_username = value.to_string(),
+                    "UILanguage" => config.ui_language = value.to_string(),
+                    "InputLocale" => config.input_locale = value.to_string(),
+                    "SystemLocale" => config.system_locale = value.to_string(),
+                    "TimeZone" => config.time_zone = value.to_string(),
+                    "ComputerName" => config.computer_name = value.to_string(),
+                    "ProductKey" => config.product_key = value.to_string(),
+                    "Organization" => config.organization = value.to_string(),
+                    "LocalAccount" => {
+                        if let Some(account) = LocalAccount::from_config_value(value) {
+                            config.accounts.push(account);
+                        }
+                    }
+                    "DisableAutoLogon" => config.disable_auto_logon = value.parse().unwrap_or(false),
+                    "BuiltinAdministrator" => {
+                        config.builtin_administrator = BuiltinAdministrator::parse(value)
+                    }
+                    "AdministratorPassword" => {
+                        config.administrator_password = EncodedPassword::from_encoded(value)
+                    }
+                    "UnattendFile" => config.unattend_file = value.to_string(),
+                    "InstallSoftware" => config.install_software = value.parse().unwrap_or(false),
+                    "Profile" => config.profile = value.to_string(),
                     _ => {}
                 }
             }
@@ -355,6 +524,10 @@ Incremental={}
     /// 反序列化备份配置
     fn deserialize_backup_config(content: &str) -> Result<BackupConfig> {
         let mut config = BackupConfig::default();
+        let mut exclusions = Vec::new();
+        let mut compression_exclusions = Vec::new();
+        // 旧版本的配置没有此项，使用内置默认
+        let mut use_defaults = true;
         
         for line in content.lines() {
             let line = line.trim();
@@ -371,12 +544,22 @@ Incremental={}
                     "Name" => config.name = value.to_string(),
                     "Description" => config.description = value.to_string(),
                     "SourcePartition" => config.source_partition = value.to_string(),
+                    "Format" => config.format = BackupFormat::parse(value).unwrap_or_default(),
                     "Incremental" => config.incremental = value.parse().unwrap_or(false),
+                    "Verify" => config.verify = value.parse().unwrap_or(false),
+                    "Retention" => config.retention = RetentionPolicy::parse(value),
+                    "Compression" => config.compression = Compression::parse(value).unwrap_or_default(),
+                    "ExportEsd" => config.export_esd = value.parse().unwrap_or(false),
+                    "UseDefaults" => use_defaults = value.parse().unwrap_or(true),
+                    "Exclude" => exclusions.push(value.to_string()),
+                    "NoCompress" => compression_exclusions.push(value.to_string()),
                     _ => {}
                 }
             }
         }
         
+        config.exclusions = ExclusionConfig::new(use_defaults, &exclusions, &compression_exclusions);
+
         Ok(config)
     }
 }
@@ -1,6 +1,9 @@
 use anyhow::Result;
+use letrecovery_common::bcd::{BcdStore, ObjectKind};
+use letrecovery_common::command::{SystemRunner, CommandRunner};
 use std::path::Path;
-use crate::utils::cmd::create_command;
+use std::sync::Arc;
+use crate::core::bcdedit::BootManager;
 
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::{get_bin_dir, get_exe_dir};
@@ -9,14 +12,21 @@ use crate::utils::path::{get_bin_dir, get_exe_dir};
 pub struct PeManager {
     bcdedit_path: String,
     bcdboot_path: String,
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl PeManager {
     pub fn new() -> Self {
+        Self::with_runner(Arc::new(SystemRunner))
+    }
+
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
         let bin_dir = get_bin_dir();
         Self {
             bcdedit_path: bin_dir.join("bcdedit.exe").to_string_lossy().to_string(),
             bcdboot_path: bin_dir.join("bcdboot.exe").to_string_lossy().to_string(),
+            runner,
         }
     }
 
@@ -41,7 +51,7 @@ impl PeManager {
     }
 
     /// 检查是否为UEFI启动
-    pub fn is_uefi_boot() -> bool {
+    pub fn is_uefi_boot(&self) -> bool {
         // 检查 EFI 系统分区是否存在
         Path::new("C:\\Windows\\Boot\\EFI").exists()
             || std::env::var("firmware_type")
@@ -49,9 +59,7 @@ impl PeManager {
                 .unwrap_or(false)
             || {
                 // 通过 bcdedit 检查
-                let output = create_command("bcdedit")
-                    .args(["/enum", "{current}"])
-                    .output();
+                let output = self.runner.output(&self.bcdedit_path, &["/enum", "{current}"]);
                 if let Ok(out) = output {
                     let stdout = gbk_to_utf8(&out.stdout);
                     stdout.contains("winload.efi")
@@ -224,7 +232,7 @@ impl PeManager {
     fn create_pe_boot_entry(&self, display_name: &str, wim_path: &str, sdi_path: &str) -> Result<()> {
         println!("[PE] 创建BCD引导项");
         
-        let is_uefi = Self::is_uefi_boot();
+        let is_uefi = self.is_uefi_boot();
         println!("[PE] 引导模式: {}", if is_uefi { "UEFI" } else { "Legacy" });
 
         // 清理旧的PE引导项
@@ -234,15 +242,23 @@ impl PeManager {
         let wim_bcd_path = wim_path.replace("C:", "").replace("/", "\\");
         let sdi_bcd_path = sdi_path.replace("C:", "").replace("/", "\\");
 
+        // 创建前后对比 BCD 存储，确定新建对象的 GUID
+        let boot_manager = BootManager::with_runner(self.runner.clone());
+        let store = boot_manager.read_store()?;
+
         // 1. 创建ramdisk设备
         println!("[PE] 创建 ramdisk 设备");
-        let output = create_command(&self.bcdedit_path)
-            .args(["/create", "/d", &format!("{} RAM", display_name), "/device"])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
-        println!("[PE] bcdedit output: {}", stdout);
-        let ramdisk_guid = Self::extract_guid(&stdout)?;
+        let ramdisk_name = format!("{} RAM", display_name);
+        let output = self.runner.output(&self.bcdedit_path, &[
+            "/create",
+            "/d",
+            &ramdisk_name,
+            "/device",
+        ])?;
+        println!("[PE] bcdedit output: {}", gbk_to_utf8(&output.stdout));
+
+        let (ramdisk_guid, store) =
+            Self::find_created_object(&boot_manager, &store, ObjectKind::Device, &ramdisk_name)?;
         println!("[PE] Ramdisk GUID: {}", ramdisk_guid);
 
         // 配置ramdisk
@@ -252,19 +268,24 @@ impl PeManager {
         ];
 
         for cmd in &cmds {
-            let output = create_command(&self.bcdedit_path).args(cmd).output()?;
+            let output = self.runner.output(&self.bcdedit_path, cmd)?;
             println!("[PE] bcdedit {:?}: {}", cmd, gbk_to_utf8(&output.stdout));
         }
 
         // 2. 创建osloader
         println!("[PE] 创建 osloader");
-        let output = create_command(&self.bcdedit_path)
-            .args(["/create", "/d", display_name, "/application", "osloader"])
-            .output()?;
-
-        let stdout = gbk_to_utf8(&output.stdout);
-        println!("[PE] bcdedit output: {}", stdout);
-        let loader_guid = Self::extract_guid(&stdout)?;
+        let output = self.runner.output(&self.bcdedit_path, &[
+            "/create",
+            "/d",
+            display_name,
+            "/application",
+            "osloader",
+        ])?;
+
+        println!("[PE] bcdedit output: {}", gbk_to_utf8(&output.stdout));
+
+        let (loader_guid, _) =
+            Self::find_created_object(&boot_manager, &store, ObjectKind::OsLoader, display_name)?;
         println!("[PE] Loader GUID: {}", loader_guid);
 
         // 配置osloader
@@ -287,7 +308,7 @@ impl PeManager {
         ];
 
         for cmd in &cmds {
-            let output = create_command(&self.bcdedit_path).args(cmd).output()?;
+            let output = self.runner.output(&self.bcdedit_path, cmd)?;
             let out_str = gbk_to_utf8(&output.stdout);
             let err_str = gbk_to_utf8(&output.stderr);
             println!("[PE] bcdedit {:?}: {} {}", cmd, out_str, err_str);
@@ -295,15 +316,15 @@ impl PeManager {
 
         // 3. 添加到启动菜单
         println!("[PE] 添加到启动菜单");
-        let output = create_command(&self.bcdedit_path)
-            .args(["/displayorder", &loader_guid, "/addfirst"])
-            .output()?;
+        let output = self.runner.output(&self.bcdedit_path, &[
+            "/displayorder",
+            &loader_guid,
+            "/addfirst",
+        ])?;
         println!("[PE] displayorder: {}", gbk_to_utf8(&output.stdout));
 
         // 4. 设置超时
-        let output = create_command(&self.bcdedit_path)
-            .args(["/timeout", "5"])
-            .output()?;
+        let output = self.runner.output(&self.bcdedit_path, &["/timeout", "5"])?;
         println!("[PE] timeout: {}", gbk_to_utf8(&output.stdout));
 
         // 5. 保存GUID用于清理
@@ -323,9 +344,10 @@ impl PeManager {
                 let loader_guid = lines[1];
                 println!("[PE] 设置下次启动: {}", loader_guid);
                 
-                let output = create_command(&self.bcdedit_path)
-                    .args(["/bootsequence", loader_guid])
-                    .output()?;
+                let output = self.runner.output(&self.bcdedit_path, &[
+                    "/bootsequence",
+                    loader_guid,
+                ])?;
                 println!("[PE] bootsequence: {}", gbk_to_utf8(&output.stdout));
             }
         }
@@ -339,9 +361,7 @@ impl PeManager {
             for guid in content.lines() {
                 if !guid.is_empty() {
                     println!("[PE] 清理旧引导项: {}", guid);
-                    let _ = create_command(&self.bcdedit_path)
-                        .args(["/delete", guid, "/f"])
-                        .output();
+                    let _ = self.runner.output(&self.bcdedit_path, &["/delete", guid, "/f"]);
                 }
             }
         }
@@ -365,43 +385,39 @@ impl PeManager {
     }
 
     /// 重启系统
-    pub fn reboot() {
+    pub fn reboot(&self) {
         println!("[PE] 执行重启");
-        let _ = create_command("shutdown")
-            .args(["/r", "/t", "3", "/c", "LetRecovery 正在重启到 PE 环境..."])
-            .spawn();
+        let _ = self.runner.spawn("shutdown", &[
+            "/r",
+            "/t",
+            "3",
+            "/c",
+            "LetRecovery 正在重启到 PE 环境...",
+        ]);
     }
 
-    /// 从bcdedit输出中提取GUID
-    fn extract_guid(output: &str) -> Result<String> {
-        for word in output.split_whitespace() {
-            if word.starts_with('{') && word.ends_with('}') {
-                return Ok(word.to_string());
-            }
-            if word.starts_with('{') {
-                let cleaned: String = word
-                    .chars()
-                    .filter(|c| !c.is_ascii_punctuation() || *c == '-' || *c == '{' || *c == '}')
-                    .collect();
-                if cleaned.ends_with('}') && cleaned.len() > 10 {
-                    return Ok(cleaned);
-                }
-            }
-        }
-        
-        // 尝试用正则匹配
-        for line in output.lines() {
-            if let Some(start) = line.find('{') {
-                if let Some(end) = line[start..].find('}') {
-                    let guid = &line[start..start + end + 1];
-                    if guid.len() > 10 {
-                        return Ok(guid.to_string());
-                    }
-                }
-            }
+    /// 查找 `bcdedit /create` 新建的对象，返回其 GUID 和创建后的存储
+    ///
+    /// 通过解析 BCD 存储对比创建前后的对象，不依赖 bcdedit 随系统语言变化的输出。
+    fn find_created_object(
+        boot_manager: &BootManager,
+        before: &BcdStore,
+        kind: ObjectKind,
+        description: &str,
+    ) -> Result<(String, BcdStore)> {
+        let after = boot_manager.read_store()?;
+        let created: Vec<_> = after
+            .new_objects(before)
+            .into_iter()
+            .filter(|o| o.kind() == kind && o.description() == Some(description))
+            .map(|o| o.id.to_string())
+            .collect();
+
+        match created.as_slice() {
+            [guid] => Ok((guid.clone(), after)),
+            [] => anyhow::bail!("BCD 中没有找到新建的引导对象: {}", description),
+            _ => anyhow::bail!("BCD 中找到多个新建的引导对象: {}", description),
         }
-        
-        anyhow::bail!("无法从bcdedit输出中提取GUID: {}", output)
     }
 }
 
@@ -0,0 +1,251 @@
+//! 安装前检查：收集本机信息，交给公共库逐项判断
+
+use letrecovery_common::disk_layout::{DiskLayout, Firmware};
+use letrecovery_common::diskpart::script::RunMode;
+use letrecovery_common::partition_table;
+use letrecovery_common::preflight::{
+    self, BatteryLevel, EncryptionState, EspState, PreflightReport,
+};
+use letrecovery_common::win11::{self, Win11Hardware, Win11Report};
+
+use crate::core::disk::{DiskManager, Partition, PartitionStyle};
+use crate::core::dism::ImageInfo;
+use crate::core::ghost::Ghost;
+use crate::core::hardware_info::{get_volume_bitlocker_status, BitLockerStatus, HardwareInfo};
+use crate::core::pe::PeManager;
+use crate::core::system_info::SystemInfo;
+
+const MIB: u64 = 1024 * 1024;
+
+/// 安装目标
+pub enum PreflightTarget<'a> {
+    /// 安装到已有分区
+    Partition {
+        partition: &'a Partition,
+        will_format: bool,
+        via_pe: bool,
+        firmware: Firmware,
+    },
+    /// 整盘安装
+    Disk {
+        size_bytes: u64,
+        layout: &'a DiskLayout,
+    },
+}
+
+/// 检查结果
+pub struct PreflightOutcome {
+    pub report: PreflightReport,
+    /// 镜像为 Windows 11 时的硬件要求检查结果，其中每项也已加入 report
+    pub win11: Option<Win11Report>,
+}
+
+/// 安装前检查参数
+pub struct InstallPreflight<'a> {
+    pub image_path: &'a str,
+    /// 所选卷的索引，GHO 镜像为 None
+    pub volume_index: Option<u32>,
+    /// 镜像中的全部卷
+    pub volumes: &'a [ImageInfo],
+    pub target: PreflightTarget<'a>,
+    /// 启动时收集的系统信息，用于 Windows 11 的 TPM 和安全启动检查
+    pub system_info: Option<&'a SystemInfo>,
+}
+
+impl InstallPreflight<'_> {
+    /// 执行全部检查
+    pub fn run(&self) -> PreflightOutcome {
+        let mut report = PreflightReport::new();
+
+        let indexes: Vec<u32> = self.volumes.iter().map(|v| v.index).collect();
+        report.push(preflight::check_image(self.image_readable(), self.volume_index, &indexes));
+
+        let image_bytes = self.image_bytes();
+        let current_firmware = if PeManager::new().is_uefi_boot() {
+            Firmware::Uefi
+        } else {
+            Firmware::Bios
+        };
+
+        let (firmware, storage_bytes) = match self.target {
+            PreflightTarget::Partition { partition, will_format, via_pe, firmware } => {
+                let target_letter = partition.letter.chars().next().unwrap_or('C');
+                let capacity_mb = if will_format {
+                    partition.total_size_mb
+                } else {
+                    partition.free_size_mb
+                };
+                report.push(preflight::check_target_size(Some(capacity_mb * MIB), image_bytes));
+                // 直接安装时数据就在镜像所在分区，通过 PE 安装时按实际安装的规则选出数据分区
+                let data_letter = if via_pe {
+                    self.pe_data_partition(&partition.letter)
+                } else {
+                    self.image_path.chars().next()
+                };
+                report.push(preflight::check_data_location(data_letter, target_letter, will_format));
+                report.push(preflight::check_encryption(
+                    encryption_state(&get_volume_bitlocker_status(&partition.letter)),
+                    via_pe,
+                    will_format,
+                ));
+                let is_gpt = match partition.partition_style {
+                    PartitionStyle::GPT => Some(true),
+                    PartitionStyle::MBR => Some(false),
+                    PartitionStyle::Unknown => None,
+                };
+                report.push(preflight::check_boot_mode(firmware, is_gpt, current_firmware));
+                report.push(preflight::check_esp(firmware, &esp_state(partition.disk_number)));
+                (firmware, disk_bytes(partition))
+            }
+            PreflightTarget::Disk { size_bytes, layout } => {
+                // Windows 分区占用其他分区以外的全部空间
+                let reserved_mb: u64 = layout.partitions.iter().filter_map(|p| p.size_mb).sum();
+                let capacity = size_bytes.saturating_sub(reserved_mb * MIB);
+                report.push(preflight::check_target_size(Some(capacity), image_bytes));
+
+                let firmware = layout.options.firmware;
+                report.push(preflight::check_boot_mode(
+                    firmware,
+                    Some(firmware == Firmware::Uefi),
+                    current_firmware,
+                ));
+                report.push(preflight::check_esp(firmware, &EspState::WillCreate));
+                (firmware, size_bytes)
+            }
+        };
+
+        let win11 = self.is_win11_image().then(|| {
+            let win11_report = win11::check(&win11_hardware(self.system_info, firmware, storage_bytes));
+            println!("[PREFLIGHT] Windows 11 硬件要求:\n{}", win11_report.to_text());
+            for item in win11_report.check_items() {
+                report.push(item);
+            }
+            win11_report
+        });
+
+        let battery = HardwareInfo::get_battery_info().map(|b| BatteryLevel {
+            percent: b.charge_percent,
+            ac_connected: b.is_ac_connected,
+        });
+        report.push(preflight::check_battery(battery));
+
+        println!("[PREFLIGHT] 安装前检查结果:\n{}", report.to_text());
+        PreflightOutcome { report, win11 }
+    }
+
+    /// 通过 PE 安装时重启前镜像和配置要复制到的分区，只试运行不改动磁盘
+    fn pe_data_partition(&self, target_partition: &str) -> Option<char> {
+        let image_size = std::fs::metadata(self.image_path).ok()?.len();
+        match DiskManager::new().find_suitable_data_partition(target_partition, image_size, RunMode::DryRun) {
+            Ok(found) => found.and_then(|(letter, _)| letter.chars().next()),
+            Err(e) => {
+                println!("[PREFLIGHT] 查找数据分区失败: {}", e);
+                None
+            }
+        }
+    }
+
+    fn is_gho(&self) -> bool {
+        let lower = self.image_path.to_lowercase();
+        lower.ends_with(".gho") || lower.ends_with(".ghs")
+    }
+
+    fn is_win11_image(&self) -> bool {
+        let Some(index) = self.volume_index else {
+            return false;
+        };
+        self.volumes
+            .iter()
+            .any(|v| v.index == index && win11::is_win11_image(&v.name))
+    }
+
+    fn image_readable(&self) -> Result<(), String> {
+        if self.is_gho() {
+            Ghost::new().validate_image(self.image_path).map_err(|e| e.to_string())
+        } else {
+            preflight::read_image_header(std::path::Path::new(self.image_path))
+        }
+    }
+
+    /// 镜像展开后的大小；GHO 没有记录，以文件大小作为下限
+    fn image_bytes(&self) -> Option<u64> {
+        if self.is_gho() {
+            return std::fs::metadata(self.image_path).ok().map(|m| m.len());
+        }
+        let index = self.volume_index?;
+        self.volumes
+            .iter()
+            .find(|v| v.index == index)
+            .map(|v| v.size_bytes)
+    }
+}
+
+fn encryption_state(status: &BitLockerStatus) -> EncryptionState {
+    match status {
+        BitLockerStatus::NotEncrypted => EncryptionState::NotEncrypted,
+        BitLockerStatus::Encrypted => EncryptionState::Encrypted,
+        BitLockerStatus::EncryptionInProgress | BitLockerStatus::DecryptionInProgress => {
+            EncryptionState::Converting
+        }
+        BitLockerStatus::Unknown => EncryptionState::Unknown,
+    }
+}
+
+/// 读取目标磁盘上的 ESP 及其剩余空间
+fn esp_state(disk_number: Option<u32>) -> EspState {
+    let Some(disk_number) = disk_number else {
+        return EspState::Unknown("无法确定目标分区所在磁盘".to_string());
+    };
+    let table = match DiskManager::read_partition_table(disk_number) {
+        Ok(table) => table,
+        Err(e) => return EspState::Unknown(e.to_string()),
+    };
+    let Some(esp) = table.esp() else {
+        return EspState::Missing;
+    };
+
+    let free_bytes = std::fs::File::open(format!("\\\\.\\PhysicalDrive{}", disk_number))
+        .map_err(anyhow::Error::from)
+        .and_then(|mut disk| partition_table::fat32_free_bytes(&mut disk, esp))
+        .unwrap_or_else(|e| {
+            println!("[PREFLIGHT] 读取 ESP 剩余空间失败: {}", e);
+            None
+        });
+    EspState::Present { free_bytes }
+}
+
+/// 目标分区所在磁盘的容量，无法确定磁盘时使用分区大小
+fn disk_bytes(partition: &Partition) -> u64 {
+    partition
+        .disk_number
+        .and_then(|n| {
+            DiskManager::new().list_disks()
+                .ok()?
+                .into_iter()
+                .find(|d| d.number == n)
+        })
+        .map(|d| d.size_bytes)
+        .unwrap_or(partition.total_size_mb * MIB)
+}
+
+/// 收集 Windows 11 检查所需的硬件信息
+fn win11_hardware(system_info: Option<&SystemInfo>, firmware: Firmware, storage_bytes: u64) -> Win11Hardware {
+    let cpu = HardwareInfo::get_cpu_info();
+    let memory = HardwareInfo::get_memory_info();
+    // 可用物理内存扣除了硬件保留部分，优先按内存条容量计算
+    let installed: u64 = memory.sticks.iter().map(|s| s.capacity).sum();
+
+    Win11Hardware {
+        cpu_name: cpu.name,
+        cpu_cores: cpu.cores,
+        cpu_mhz: cpu.max_clock_speed,
+        is_64bit: cpu.architecture == "x64" || cpu.architecture == "ARM64",
+        tpm_version: system_info
+            .filter(|s| s.tpm_enabled)
+            .and_then(|s| win11::parse_tpm_version(&s.tpm_version)),
+        secure_boot: system_info.is_some_and(|s| s.secure_boot),
+        uefi: firmware == Firmware::Uefi,
+        memory_bytes: if installed > 0 { installed } else { memory.total_physical },
+        storage_bytes,
+    }
+}
@@ -0,0 +1,261 @@
+//! 部署方案
+//!
+//! 把镜像、引导模式、格式化和驱动选项以及高级选项（系统优化、无人值守、
+//! 装机软件）和系统备份的排除列表保存为命名方案，多台机器重复使用同一套设置。方案以 JSON 保存在
+//! 程序目录的 profiles 目录中，可导入导出；设为默认的方案在启动时自动应用。
+//! 账户密码不写入方案文件。
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::BootModeSelection;
+use crate::ui::advanced_options::AdvancedOptions;
+use crate::ui::backup_exclusions::BackupExclusions;
+use crate::utils::path::get_profiles_dir;
+
+/// 记录默认方案名称的文件
+const DEFAULT_MARKER: &str = "default.txt";
+
+/// 部署方案
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeploymentProfile {
+    pub name: String,
+    /// 镜像文件路径（为空时保留界面中当前选择的镜像）
+    pub image_path: String,
+    /// 卷索引，GHO 镜像忽略
+    pub volume_index: u32,
+    pub boot_mode: BootModeSelection,
+    pub format_partition: bool,
+    pub repair_boot: bool,
+    pub unattended_install: bool,
+    pub export_drivers: bool,
+    pub auto_reboot: bool,
+    pub advanced_options: AdvancedOptions,
+    /// 系统备份的排除列表
+    pub backup_exclusions: BackupExclusions,
+}
+
+impl Default for DeploymentProfile {
+    /// 与界面的初始选项相同
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            image_path: String::new(),
+            volume_index: 1,
+            boot_mode: BootModeSelection::Auto,
+            format_partition: true,
+            repair_boot: true,
+            unattended_install: true,
+            export_drivers: true,
+            auto_reboot: false,
+            advanced_options: AdvancedOptions::default(),
+            backup_exclusions: BackupExclusions::default(),
+        }
+    }
+}
+
+impl DeploymentProfile {
+    /// 列出已保存的方案名称
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(get_profiles_dir())
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+                    .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// 读取已保存的方案
+    pub fn load(name: &str) -> Result<Self> {
+        let mut profile = Self::read_file(&Self::path_for(name)?)?;
+        profile.name = name.to_string();
+        Ok(profile)
+    }
+
+    /// 按名称读取已保存的方案，名称不存在时作为文件路径读取（命令行使用）
+    pub fn resolve(name_or_path: &str) -> Result<Self> {
+        match Self::path_for(name_or_path) {
+            Ok(path) if path.exists() => Self::load(name_or_path),
+            _ => Self::read_file(Path::new(name_or_path)),
+        }
+    }
+
+    /// 保存到方案目录，同名方案被覆盖
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path_for(&self.name)?;
+        std::fs::create_dir_all(get_profiles_dir()).context("创建方案目录失败")?;
+        self.write_file(&path)?;
+        println!("[PROFILE] 方案已保存: {}", path.display());
+        Ok(())
+    }
+
+    /// 删除已保存的方案，是默认方案时同时取消默认
+    pub fn delete(name: &str) -> Result<()> {
+        std::fs::remove_file(Self::path_for(name)?).with_context(|| format!("删除方案失败: {}", name))?;
+        if Self::default_name().as_deref() == Some(name) {
+            Self::set_default(None)?;
+        }
+        Ok(())
+    }
+
+    /// 从文件导入并保存到方案目录，方案中没有名称时使用文件名
+    pub fn import(path: &Path) -> Result<Self> {
+        let mut profile = Self::read_file(path)?;
+        if profile.name.trim().is_empty() {
+            profile.name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+        }
+        profile.save()?;
+        Ok(profile)
+    }
+
+    /// 导出到指定文件
+    pub fn export(&self, path: &Path) -> Result<()> {
+        self.write_file(path)
+    }
+
+    /// 启动时自动应用的方案名称
+    pub fn default_name() -> Option<String> {
+        std::fs::read_to_string(get_profiles_dir().join(DEFAULT_MARKER))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// 设置或取消默认方案
+    pub fn set_default(name: Option<&str>) -> Result<()> {
+        let marker = get_profiles_dir().join(DEFAULT_MARKER);
+        match name {
+            Some(name) => {
+                Self::path_for(name)?;
+                std::fs::create_dir_all(get_profiles_dir()).context("创建方案目录失败")?;
+                std::fs::write(&marker, name).context("保存默认方案失败")
+            }
+            None if marker.exists() => std::fs::remove_file(&marker).context("取消默认方案失败"),
+            None => Ok(()),
+        }
+    }
+
+    /// 读取默认方案，没有或读取失败时返回 None
+    pub fn load_default() -> Option<Self> {
+        let name = Self::default_name()?;
+        match Self::load(&name) {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                println!("[PROFILE] 读取默认方案 {} 失败: {:#}", name, e);
+                None
+            }
+        }
+    }
+
+    fn read_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取方案文件 {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("方案文件格式错误 {}", path.display()))
+    }
+
+    /// 检查互相依赖的选项：装机软件由无人值守应答文件在首次登录时安装
+    pub fn validate(&self) -> Result<()> {
+        if !self.unattended_install && !self.advanced_options.software_items().is_empty() {
+            anyhow::bail!("装机软件需要无人值守安装才能执行，请启用无人值守安装或关闭装机软件");
+        }
+        Ok(())
+    }
+
+    fn write_file(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content).with_context(|| format!("写入方案文件失败 {}", path.display()))
+    }
+
+    /// 方案名称对应的文件路径，名称不能作为文件名时返回错误
+    fn path_for(name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        Ok(get_profiles_dir().join(format!("{}.json", name)))
+    }
+}
+
+/// 检查方案名称能否作为文件名
+fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        anyhow::bail!("方案名称不能为空");
+    }
+    if name != name.trim() || name.ends_with('.') {
+        anyhow::bail!("方案名称不能以空格或点结尾: {}", name);
+    }
+    if let Some(c) = name.chars().find(|c| r#"\/:*?"<>|"#.contains(*c) || c.is_control()) {
+        anyhow::bail!("方案名称不能包含字符 {:?}", c);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::advanced_options::AccountEntry;
+    use letrecovery_common::unattend::accounts::DEFAULT_GROUP;
+    use letrecovery_common::unattend::BuiltinAdministrator;
+
+    #[test]
+    fn test_profile_json() {
+        // 缺少的字段使用界面的初始值，旧版本导出的方案也能读取
+        let profile: DeploymentProfile = serde_json::from_str(
+            r#"{ "name": "办公机", "image_path": "D:\\win11.wim", "volume_index": 6, "boot_mode": "UEFI" }"#,
+        )
+        .unwrap();
+        assert_eq!(profile.volume_index, 6);
+        assert_eq!(profile.boot_mode, BootModeSelection::UEFI);
+        assert!(profile.format_partition && profile.unattended_install);
+
+        // 高级选项只写了部分字段时其余使用默认值
+        let partial: DeploymentProfile = serde_json::from_str(
+            r#"{ "name": "部分", "advanced_options": { "bypass_nro": true, "computer_name": "PC-{serial}", "extra_accounts": [{ "name": "维护" }] } }"#,
+        )
+        .unwrap();
+        assert!(partial.advanced_options.bypass_nro);
+        assert_eq!(partial.advanced_options.computer_name, "PC-{serial}");
+        assert!(!partial.advanced_options.install_software);
+        assert_eq!(partial.advanced_options.extra_accounts[0].group, DEFAULT_GROUP);
+
+        // 密码不写入方案文件
+        let mut profile = profile;
+        profile.advanced_options.custom_username = true;
+        profile.advanced_options.username = "张三".to_string();
+        profile.advanced_options.password = "s3cret-user".to_string();
+        profile.advanced_options.builtin_administrator = BuiltinAdministrator::Enabled;
+        profile.advanced_options.admin_password = "s3cret-admin".to_string();
+        profile.advanced_options.extra_accounts.push(AccountEntry {
+            name: "维护".to_string(),
+            password: "s3cret-extra".to_string(),
+            ..Default::default()
+        });
+        let json = serde_json::to_string(&profile).unwrap();
+        assert!(!json.contains("s3cret"));
+        assert!(!json.contains("password"));
+        let loaded: DeploymentProfile = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.advanced_options.accounts_missing_password(), vec!["张三", "维护", "Administrator"]);
+
+        // 装机软件依赖无人值守安装
+        profile.advanced_options.install_software = true;
+        profile.advanced_options.software_list = "D:\\Soft\\7z.exe|/S".to_string();
+        assert!(profile.validate().is_ok());
+        profile.unattended_install = false;
+        assert!(profile.validate().is_err());
+
+        assert!(validate_name("办公机 v2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("a/b").is_err());
+        assert!(validate_name("name.").is_err());
+    }
+}
@@ -1,17 +1,24 @@
 use anyhow::Result;
-use crate::utils::cmd::create_command;
+use letrecovery_common::command::CommandRunner;
+use std::sync::Arc;
 
 use crate::utils::encoding::gbk_to_utf8;
 
-pub struct OfflineRegistry;
+/// 离线注册表操作，通过创建时传入的执行器调用 reg.exe
+pub struct OfflineRegistry {
+    runner: Arc<dyn CommandRunner>,
+}
 
 impl OfflineRegistry {
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
     /// 加载离线注册表配置单元
-    pub fn load_hive(hive_name: &str, hive_file: &str) -> Result<()> {
+    pub fn load_hive(&self, hive_name: &str, hive_file: &str) -> Result<()> {
         let key_path = format!("HKLM\\{}", hive_name);
-        let output = create_command("reg.exe")
-            .args(["load", &key_path, hive_file])
-            .output()?;
+        let output = self.runner.output("reg.exe", &["load", &key_path, hive_file])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -21,14 +28,12 @@ impl OfflineRegistry {
     }
 
     /// 卸载离线注册表配置单元
-    pub fn unload_hive(hive_name: &str) -> Result<()> {
+    pub fn unload_hive(&self, hive_name: &str) -> Result<()> {
         let key_path = format!("HKLM\\{}", hive_name);
 
         // 尝试多次卸载，因为有时需要等待
         for _ in 0..3 {
-            let output = create_command("reg.exe")
-                .args(["unload", &key_path])
-                .output()?;
+            let output = self.runner.output("reg.exe", &["unload", &key_path])?;
 
             if output.status.success() {
                 return Ok(());
@@ -37,9 +42,7 @@ impl OfflineRegistry {
         }
 
         // 最后一次尝试
-        let output = create_command("reg.exe")
-            .args(["unload", &key_path])
-            .output()?;
+        let output = self.runner.output("reg.exe", &["unload", &key_path])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -49,20 +52,18 @@ impl OfflineRegistry {
     }
 
     /// 写入 DWORD 值
-    pub fn set_dword(key_path: &str, value_name: &str, data: u32) -> Result<()> {
-        let output = create_command("reg.exe")
-            .args([
-                "add",
-                key_path,
-                "/v",
-                value_name,
-                "/t",
-                "REG_DWORD",
-                "/d",
-                &data.to_string(),
-                "/f",
-            ])
-            .output()?;
+    pub fn set_dword(&self, key_path: &str, value_name: &str, data: u32) -> Result<()> {
+        let output = self.runner.output("reg.exe", &[
+            "add",
+            key_path,
+            "/v",
+            value_name,
+            "/t",
+            "REG_DWORD",
+            "/d",
+            &data.to_string(),
+            "/f",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -72,20 +73,18 @@ impl OfflineRegistry {
     }
 
     /// 写入字符串值
-    pub fn set_string(key_path: &str, value_name: &str, data: &str) -> Result<()> {
-        let output = create_command("reg.exe")
-            .args([
-                "add",
-                key_path,
-                "/v",
-                value_name,
-                "/t",
-                "REG_SZ",
-                "/d",
-                data,
-                "/f",
-            ])
-            .output()?;
+    pub fn set_string(&self, key_path: &str, value_name: &str, data: &str) -> Result<()> {
+        let output = self.runner.output("reg.exe", &[
+            "add",
+            key_path,
+            "/v",
+            value_name,
+            "/t",
+            "REG_SZ",
+            "/d",
+            data,
+            "/f",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -95,20 +94,16 @@ impl OfflineRegistry {
     }
 
     /// 删除注册表键
-    pub fn delete_key(key_path: &str) -> Result<()> {
-        let _ = create_command("reg.exe")
-            .args(["delete", key_path, "/f"])
-            .output();
+    pub fn delete_key(&self, key_path: &str) -> Result<()> {
+        let _ = self.runner.output("reg.exe", &["delete", key_path, "/f"]);
 
         // 忽略不存在的情况
         Ok(())
     }
 
     /// 创建注册表键（如果不存在）
-    pub fn create_key(key_path: &str) -> Result<()> {
-        let output = create_command("reg.exe")
-            .args(["add", key_path, "/f"])
-            .output()?;
+    pub fn create_key(&self, key_path: &str) -> Result<()> {
+        let output = self.runner.output("reg.exe", &["add", key_path, "/f"])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -118,19 +113,15 @@ impl OfflineRegistry {
     }
 
     /// 删除注册表值
-    pub fn delete_value(key_path: &str, value_name: &str) -> Result<()> {
-        let _ = create_command("reg.exe")
-            .args(["delete", key_path, "/v", value_name, "/f"])
-            .output();
+    pub fn delete_value(&self, key_path: &str, value_name: &str) -> Result<()> {
+        let _ = self.runner.output("reg.exe", &["delete", key_path, "/v", value_name, "/f"]);
 
         Ok(())
     }
 
     /// 导入 .reg 文件
-    pub fn import_reg_file(reg_file: &str) -> Result<()> {
-        let output = create_command("reg.exe")
-            .args(["import", reg_file])
-            .output()?;
+    pub fn import_reg_file(&self, reg_file: &str) -> Result<()> {
+        let output = self.runner.output("reg.exe", &["import", reg_file])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use serde::Serialize;
 
 #[cfg(windows)]
 use windows::{
@@ -8,7 +9,7 @@ use windows::{
     },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SystemInfo {
     pub boot_mode: BootMode,
     pub tpm_enabled: bool,
@@ -19,7 +20,7 @@ pub struct SystemInfo {
     pub is_online: bool,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum BootMode {
     UEFI,
     Legacy,
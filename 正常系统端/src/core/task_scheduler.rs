@@ -0,0 +1,58 @@
+//! Windows 任务计划程序
+//!
+//! 通过 schtasks 注册、删除和查询计划备份任务，任务定义由公共库的
+//! `backup::schedule` 生成。
+
+use anyhow::Result;
+use letrecovery_common::backup::schedule::BackupSchedule;
+use letrecovery_common::command::CommandRunner;
+use std::sync::Arc;
+
+use crate::utils::encoding::gbk_to_utf8;
+
+pub struct TaskScheduler {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl TaskScheduler {
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// 注册计划备份任务，同名任务已存在时覆盖
+    pub fn create_backup_task(&self, schedule: &BackupSchedule, program: &str, start_date: &str) -> Result<()> {
+        let xml_path = std::env::temp_dir().join(format!("LetRecovery_Task_{}.xml", std::process::id()));
+        schedule.write_task_xml(&xml_path, program, start_date)?;
+
+        let task_name = schedule.task_name();
+        println!("[TASK] 注册计划任务: {} ({})", task_name, schedule.describe());
+        let result = self.schtasks(&["/Create", "/TN", &task_name, "/XML", &xml_path.to_string_lossy(), "/F"]);
+
+        let _ = std::fs::remove_file(&xml_path);
+        result.map(|_| ())
+    }
+
+    /// 删除计划备份任务
+    pub fn delete_backup_task(&self, task_name: &str) -> Result<()> {
+        println!("[TASK] 删除计划任务: {}", task_name);
+        self.schtasks(&["/Delete", "/TN", task_name, "/F"]).map(|_| ())
+    }
+
+    /// 任务是否已注册
+    pub fn task_exists(&self, task_name: &str) -> bool {
+        self.schtasks(&["/Query", "/TN", task_name]).is_ok()
+    }
+
+    fn schtasks(&self, args: &[&str]) -> Result<String> {
+        let output = self.runner.output("schtasks", args)?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "schtasks 执行失败（退出码 {}）: {}",
+                output.status.code().unwrap_or(-1),
+                gbk_to_utf8(&output.stderr).trim()
+            );
+        }
+        Ok(gbk_to_utf8(&output.stdout))
+    }
+}
@@ -1,12 +1,19 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app;
+mod cli;
 mod core;
 mod download;
 mod ui;
 mod utils;
+mod workflow;
 
 use eframe::egui;
+use letrecovery_common::command::SystemRunner;
+use letrecovery_common::disk_layout::Firmware;
+use letrecovery_common::workflow::{
+    run_backup, run_install, BackupPlan, BackupStep, InstallPlan, InstallStep, TargetPreparation, WorkflowEvent,
+};
 use std::sync::Arc;
 
 /// 预加载的配置数据
@@ -28,6 +35,13 @@ fn main() -> eframe::Result<()> {
     // 检查命令行参数，处理PE环境下的自动安装/备份
     let args: Vec<String> = std::env::args().collect();
     
+    // 命令行子命令（无界面），供部署脚本调用
+    match letrecovery_common::cli::parse(args.get(1..).unwrap_or_default()) {
+        Ok(Some(command)) => std::process::exit(cli::run(command).code()),
+        Ok(None) => {}
+        Err(e) => std::process::exit(cli::usage_error(&e).code()),
+    }
+    
     if args.contains(&"/PEINSTALL".to_string()) || args.contains(&"--pe-install".to_string()) {
         log::info!("检测到PE安装模式，执行自动安装...");
         return run_pe_install();
@@ -259,16 +273,31 @@ fn run_pe_install() -> eframe::Result<()> {
     let data_dir = ConfigFileManager::get_data_dir(&data_partition);
     let image_path = format!("{}\\{}", data_dir, config.image_path);
     
-    if !std::path::Path::new(&image_path).exists() {
-        eprintln!("[PE INSTALL] 错误: 镜像文件不存在: {}", image_path);
-        show_error_message(&format!("镜像文件不存在: {}", image_path));
-        return Ok(());
-    }
-    
     println!("[PE INSTALL] 完整镜像路径: {}", image_path);
     
+    let plan = InstallPlan {
+        target_partition: target_partition.clone(),
+        image_path,
+        volume_index: config.volume_index,
+        is_gho: config.is_gho,
+        preparation: TargetPreparation::Format,
+        driver_dir: config.restore_drivers.then(|| format!("{}\\drivers", data_dir)),
+        export_drivers: false,
+        boot_firmware: Some(if detect_uefi_mode() { Firmware::Uefi } else { Firmware::Bios }),
+        unattended: config.unattended,
+    };
+    let backend = workflow::DesktopInstallBackend {
+        advanced_options: advanced_options_from_config(&config),
+        answer_options: config.answer_file_options(),
+        user_unattend_file: config.unattend_file_path(&data_dir),
+        disk_layout: None,
+        temp_dirs: vec![data_dir],
+        tools: core::tools(Arc::new(SystemRunner)),
+    };
+    
     // 执行安装
-    let result = execute_pe_install(&target_partition, &image_path, &config, &data_dir);
+    let sink = |event: WorkflowEvent<InstallStep>| print_event("[PE INSTALL]", event, InstallStep::name);
+    let result = run_install(&plan, &backend, &sink, None);
     
     // 清理标记文件
     ConfigFileManager::cleanup_partition_markers(&target_partition);
@@ -331,8 +360,24 @@ fn run_pe_backup() -> eframe::Result<()> {
         None => config.source_partition.clone(),
     };
     
+    let plan = BackupPlan {
+        source_partition: source_partition.clone(),
+        save_path: config.save_path.clone(),
+        name: config.name.clone(),
+        description: config.description.clone(),
+        format: config.format,
+        incremental: config.incremental,
+        verify: config.verify,
+        retention: config.retention,
+        exclusions: config.exclusions.clone(),
+        compression: config.compression,
+        export_esd: config.export_esd,
+    };
+    
     // 执行备份
-    let result = execute_pe_backup(&source_partition, &config);
+    let sink = |event: WorkflowEvent<BackupStep>| print_event("[PE BACKUP]", event, BackupStep::name);
+    let backend = workflow::DesktopBackupBackend::with_runner(Arc::new(SystemRunner));
+    let result = run_backup(&plan, &backend, &sink, None);
     
     // 清理标记文件
     ConfigFileManager::cleanup_partition_markers(&source_partition);
@@ -351,64 +396,8 @@ fn run_pe_backup() -> eframe::Result<()> {
     Ok(())
 }
 
-/// 执行PE安装
-fn execute_pe_install(
-    target_partition: &str,
-    image_path: &str,
-    config: &core::install_config::InstallConfig,
-    data_dir: &str,
-) -> anyhow::Result<()> {
-    use anyhow::Context;
-    
-    println!("[PE INSTALL] Step 1: 格式化分区");
-    // 格式化目标分区
-    let output = utils::cmd::create_command("cmd")
-        .args(["/c", &format!("format {} /FS:NTFS /Q /Y", target_partition)])
-        .output()
-        .context("执行格式化命令失败")?;
-    
-    if !output.status.success() {
-        let stderr = utils::encoding::gbk_to_utf8(&output.stderr);
-        anyhow::bail!("格式化分区失败: {}", stderr);
-    }
-    
-    println!("[PE INSTALL] Step 2: 释放镜像");
-    // 释放镜像
-    let apply_dir = format!("{}\\", target_partition);
-    
-    if config.is_gho {
-        // GHO镜像使用Ghost
-        let ghost = core::ghost::Ghost::new();
-        if !ghost.is_available() {
-            anyhow::bail!("Ghost工具不可用");
-        }
-        
-        let partitions = core::disk::DiskManager::get_partitions().unwrap_or_default();
-        ghost.restore_image_to_letter(image_path, target_partition, &partitions, None)?;
-    } else {
-        // WIM/ESD使用DISM
-        let dism = core::dism::Dism::new();
-        dism.apply_image(image_path, &apply_dir, config.volume_index, None)?;
-    }
-    
-    println!("[PE INSTALL] Step 3: 导入驱动");
-    // 导入驱动
-    if config.restore_drivers {
-        let driver_path = format!("{}\\drivers", data_dir);
-        if std::path::Path::new(&driver_path).exists() {
-            let dism = core::dism::Dism::new();
-            let _ = dism.add_drivers_offline(&apply_dir, &driver_path);
-        }
-    }
-    
-    println!("[PE INSTALL] Step 4: 修复引导");
-    // 修复引导
-    let boot_manager = core::bcdedit::BootManager::new();
-    let use_uefi = detect_uefi_mode();
-    boot_manager.repair_boot_advanced(target_partition, use_uefi)?;
-    
-    println!("[PE INSTALL] Step 5: 应用高级选项");
-    // 应用高级选项
+/// 把安装配置中的高级选项还原为界面使用的选项
+fn advanced_options_from_config(config: &core::install_config::InstallConfig) -> ui::advanced_options::AdvancedOptions {
     let mut advanced_options = ui::advanced_options::AdvancedOptions::default();
     advanced_options.remove_shortcut_arrow = config.remove_shortcut_arrow;
     advanced_options.restore_classic_context_menu = config.restore_classic_context_menu;
@@ -418,48 +407,22 @@ fn execute_pe_install(
     advanced_options.disable_reserved_storage = config.disable_reserved_storage;
     advanced_options.disable_uac = config.disable_uac;
     advanced_options.disable_device_encryption = config.disable_device_encryption;
+    advanced_options.bypass_win11_check = config.bypass_win11_check;
     advanced_options.remove_uwp_apps = config.remove_uwp_apps;
     advanced_options.custom_username = !config.custom_username.is_empty();
     advanced_options.username = config.custom_username.clone();
-    
-    let _ = advanced_options.apply_to_system(target_partition);
-    
-    // 生成无人值守配置
-    if config.unattended {
-        let _ = generate_unattend_xml_pe(target_partition, &config.custom_username);
-    }
-    
-    println!("[PE INSTALL] Step 6: 清理临时文件");
-    // 清理数据目录
-    let _ = std::fs::remove_dir_all(data_dir);
-    
-    Ok(())
+    advanced_options
 }
 
-/// 执行PE备份
-fn execute_pe_backup(
-    source_partition: &str,
-    config: &core::install_config::BackupConfig,
-) -> anyhow::Result<()> {
-    let dism = core::dism::Dism::new();
-    let capture_dir = format!("{}\\", source_partition);
-    
-    if config.incremental && std::path::Path::new(&config.save_path).exists() {
-        dism.append_image(
-            &config.save_path,
-            &capture_dir,
-            &config.name,
-            &config.description,
-            None,
-        )
-    } else {
-        dism.capture_image(
-            &config.save_path,
-            &capture_dir,
-            &config.name,
-            &config.description,
-            None,
-        )
+/// 命令行模式下把工作流事件输出到控制台
+fn print_event<S>(tag: &str, event: WorkflowEvent<S>, step_name: fn(&S) -> &'static str) {
+    match event {
+        WorkflowEvent::Step(step) => println!("{} 步骤: {}", tag, step_name(&step)),
+        WorkflowEvent::Progress(_) => {}
+        WorkflowEvent::Status(status) => println!("{} {}", tag, status),
+        WorkflowEvent::Warning(warning) => eprintln!("{} 警告: {}", tag, warning),
+        WorkflowEvent::Completed => {}
+        WorkflowEvent::Failed(error) => eprintln!("{} 错误: {}", tag, error),
     }
 }
 
@@ -519,69 +482,6 @@ fn detect_uefi_mode() -> bool {
     false
 }
 
-/// 生成无人值守XML (PE版本)
-fn generate_unattend_xml_pe(target_partition: &str, username: &str) -> anyhow::Result<()> {
-    let username = if username.is_empty() { "User" } else { username };
-    
-    let xml_content = format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOEMRegistrationScreen>true</HideOEMRegistrationScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-                <SkipMachineOOBE>true</SkipMachineOOBE>
-                <SkipUserOOBE>true</SkipUserOOBE>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <Username>{}</Username>
-            </AutoLogon>
-        </component>
-    </settings>
-</unattend>"#, username, username, username);
-
-    let panther_dir = format!("{}\\Windows\\Panther", target_partition);
-    std::fs::create_dir_all(&panther_dir)?;
-    
-    let unattend_path = format!("{}\\unattend.xml", panther_dir);
-    std::fs::write(&unattend_path, &xml_content)?;
-    
-    Ok(())
-}
-
 /// 显示错误消息框
 fn show_error_message(message: &str) {
     #[cfg(windows)]
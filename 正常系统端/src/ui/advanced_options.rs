@@ -1,10 +1,20 @@
 use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
 
+use letrecovery_common::unattend::accounts::{ACCOUNT_GROUPS, DEFAULT_GROUP};
+use letrecovery_common::unattend::regional::{self, GENERIC_PRODUCT_KEYS, LOCALE_PRESETS, TIME_ZONES};
+use letrecovery_common::software::{self, SoftwareItem};
+use letrecovery_common::unattend::{render_answer_file, AnswerFileOptions, BuiltinAdministrator, EncodedPassword, LocalAccount};
+use letrecovery_common::win11;
+
+use crate::core::hardware_info::HardwareInfo;
 use crate::core::registry::OfflineRegistry;
 
 /// 系统安装高级选项
+///
+/// 选项文件和部署方案中缺少的字段使用默认值，旧版本保存的文件也能读取。
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AdvancedOptions {
     // 系统优化选项
     pub remove_shortcut_arrow: bool,
@@ -15,6 +25,8 @@ pub struct AdvancedOptions {
     pub disable_reserved_storage: bool,
     pub disable_uac: bool,
     pub disable_device_encryption: bool,
+    /// 写入 LabConfig 绕过 Windows 11 硬件要求检查
+    pub bypass_win11_check: bool,
     pub remove_uwp_apps: bool,
 
     // 自定义脚本
@@ -30,18 +42,82 @@ pub struct AdvancedOptions {
     pub registry_file_path: String,
     pub import_custom_files: bool,
     pub custom_files_path: String,
-
-    // 用户设置
+    pub import_unattend_file: bool,
+    pub unattend_file_path: String,
+    /// 应答文件合并检查结果
+    #[serde(skip)]
+    pub unattend_file_messages: Vec<String>,
+
+    // 装机软件（首次登录时依次安装）
+    #[serde(default)]
+    pub install_software: bool,
+    /// 每行一个安装包路径，可在 `|` 后附加静默安装参数
+    #[serde(default)]
+    pub software_list: String,
+
+    // 用户设置（密码只保存在内存中，写入配置前编码）
     pub custom_username: bool,
     pub username: String,
+    #[serde(skip)]
+    pub password: String,
+    pub extra_accounts: Vec<AccountEntry>,
+    pub disable_auto_logon: bool,
+    #[serde(default, with = "builtin_administrator_serde")]
+    pub builtin_administrator: BuiltinAdministrator,
+    #[serde(skip)]
+    pub admin_password: String,
+
+    // 区域和系统信息（为空时不写入应答文件）
+    pub ui_language: String,
+    pub input_locale: String,
+    pub system_locale: String,
+    pub time_zone: String,
+    /// 计算机名模板，支持 {serial} 占位符
+    pub computer_name: String,
+    pub product_key: String,
+    pub organization: String,
+}
+
+/// 界面中的附加账户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountEntry {
+    pub name: String,
+    #[serde(skip)]
+    pub password: String,
+    pub group: String,
+}
+
+impl Default for AccountEntry {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            password: String::new(),
+            group: DEFAULT_GROUP.to_string(),
+        }
+    }
+}
+
+/// 内置 Administrator 的处理方式按配置文件中的取值保存
+mod builtin_administrator_serde {
+    use letrecovery_common::unattend::BuiltinAdministrator;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &BuiltinAdministrator, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(value.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BuiltinAdministrator, D::Error> {
+        Ok(BuiltinAdministrator::parse(&String::deserialize(deserializer)?))
+    }
 }
 
 impl AdvancedOptions {
     /// 脚本目录名称（统一路径）
-    const SCRIPTS_DIR: &'static str = "LetRecovery_Scripts";
+    const SCRIPTS_DIR: &'static str = letrecovery_common::SCRIPTS_DIR;
 
     /// 应用选项到目标系统
-    pub fn apply_to_system(&self, target_partition: &str) -> anyhow::Result<()> {
+    pub fn apply_to_system(&self, target_partition: &str, registry: &OfflineRegistry) -> anyhow::Result<()> {
         println!("[ADVANCED] 开始应用高级选项到: {}", target_partition);
         
         let windows_path = format!("{}\\Windows", target_partition);
@@ -51,10 +127,10 @@ impl AdvancedOptions {
 
         // 加载离线注册表
         println!("[ADVANCED] 加载离线注册表...");
-        OfflineRegistry::load_hive("pc-soft", &software_hive)?;
-        OfflineRegistry::load_hive("pc-sys", &system_hive)?;
+        registry.load_hive("pc-soft", &software_hive)?;
+        registry.load_hive("pc-sys", &system_hive)?;
         // DEFAULT 用于设置默认用户配置（如经典右键菜单）
-        let default_loaded = OfflineRegistry::load_hive("pc-default", &default_hive).is_ok();
+        let default_loaded = registry.load_hive("pc-default", &default_hive).is_ok();
 
         // 创建脚本目录（用于存放自定义脚本）
         let scripts_dir = format!("{}\\{}", target_partition, Self::SCRIPTS_DIR);
@@ -66,7 +142,7 @@ impl AdvancedOptions {
         // 1. 移除快捷方式小箭头
         if self.remove_shortcut_arrow {
             println!("[ADVANCED] 移除快捷方式小箭头");
-            let _ = OfflineRegistry::set_string(
+            let _ = registry.set_string(
                 "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Shell Icons",
                 "29",
                 "%systemroot%\\system32\\imageres.dll,197",
@@ -79,21 +155,21 @@ impl AdvancedOptions {
             // 在 DEFAULT hive 中设置（影响所有新用户）
             if default_loaded {
                 // 创建空的 InprocServer32 键，这会禁用新式右键菜单
-                let _ = OfflineRegistry::create_key(
+                let _ = registry.create_key(
                     "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
                 );
                 // 设置默认值为空字符串
-                let _ = OfflineRegistry::set_string(
+                let _ = registry.set_string(
                     "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
                     "",
                     "",
                 );
             }
             // 同时在 SOFTWARE 中设置（系统级）
-            let _ = OfflineRegistry::create_key(
+            let _ = registry.create_key(
                 "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
             );
-            let _ = OfflineRegistry::set_string(
+            let _ = registry.set_string(
                 "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
                 "",
                 "",
@@ -103,7 +179,7 @@ impl AdvancedOptions {
         // 3. OOBE绕过强制联网
         if self.bypass_nro {
             println!("[ADVANCED] 设置OOBE绕过联网");
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\OOBE",
                 "BypassNRO",
                 1,
@@ -114,19 +190,19 @@ impl AdvancedOptions {
         if self.disable_windows_update {
             println!("[ADVANCED] 禁用Windows更新服务");
             // 禁用 Windows Update 服务
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-sys\\ControlSet001\\Services\\wuauserv",
                 "Start",
                 4, // 4 = Disabled
             );
             // 禁用 Update Orchestrator Service
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-sys\\ControlSet001\\Services\\UsoSvc",
                 "Start",
                 4,
             );
             // 设置策略禁用自动更新
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Policies\\Microsoft\\Windows\\WindowsUpdate\\AU",
                 "NoAutoUpdate",
                 1,
@@ -137,28 +213,28 @@ impl AdvancedOptions {
         if self.disable_windows_defender {
             println!("[ADVANCED] 禁用Windows Defender");
             // 禁用实时保护
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender",
                 "DisableAntiSpyware",
                 1,
             );
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender\\Real-Time Protection",
                 "DisableRealtimeMonitoring",
                 1,
             );
             // 禁用服务
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-sys\\ControlSet001\\Services\\WinDefend",
                 "Start",
                 4, // Disabled
             );
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-sys\\ControlSet001\\Services\\WdNisSvc",
                 "Start",
                 4,
             );
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-sys\\ControlSet001\\Services\\SecurityHealthService",
                 "Start",
                 4,
@@ -168,12 +244,12 @@ impl AdvancedOptions {
         // 6. 禁用系统保留空间
         if self.disable_reserved_storage {
             println!("[ADVANCED] 禁用系统保留空间");
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
                 "ShippedWithReserves",
                 0,
             );
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
                 "PassedPolicy",
                 0,
@@ -183,12 +259,12 @@ impl AdvancedOptions {
         // 7. 禁用UAC
         if self.disable_uac {
             println!("[ADVANCED] 禁用UAC");
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
                 "EnableLUA",
                 0,
             );
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
                 "ConsentPromptBehaviorAdmin",
                 0,
@@ -199,25 +275,39 @@ impl AdvancedOptions {
         if self.disable_device_encryption {
             println!("[ADVANCED] 禁用自动设备加密");
             // 禁用 BitLocker 自动加密
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-sys\\ControlSet001\\Control\\BitLocker",
                 "PreventDeviceEncryption",
                 1,
             );
             // 禁用 MBAM (Microsoft BitLocker Administration and Monitoring)
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-soft\\Policies\\Microsoft\\FVE",
                 "OSRecovery",
                 0,
             );
             // 禁用设备加密
-            let _ = OfflineRegistry::set_dword(
+            let _ = registry.set_dword(
                 "HKLM\\pc-sys\\ControlSet001\\Services\\BDESVC",
                 "Start",
                 4, // Disabled
             );
         }
 
+        // Windows 11 硬件要求绕过
+        if self.bypass_win11_check {
+            println!("[ADVANCED] 写入 Windows 11 硬件检查绕过键");
+            let labconfig = format!("HKLM\\pc-sys\\{}", win11::LABCONFIG_KEY);
+            for value in win11::LABCONFIG_VALUES {
+                let _ = registry.set_dword(&labconfig, value, 1);
+            }
+            let _ = registry.set_dword(
+                &format!("HKLM\\pc-sys\\{}", win11::MOSETUP_KEY),
+                win11::MOSETUP_VALUE,
+                1,
+            );
+        }
+
         // 9. 删除预装UWP应用 - 通过删除 AppxProvisioned 配置
         if self.remove_uwp_apps {
             println!("[ADVANCED] 配置删除预装UWP应用");
@@ -253,10 +343,10 @@ impl AdvancedOptions {
             println!("[ADVANCED] 导入自定义驱动: {}", self.custom_drivers_path);
             
             // 先卸载注册表，因为 DISM 可能需要独占访问
-            let _ = OfflineRegistry::unload_hive("pc-soft");
-            let _ = OfflineRegistry::unload_hive("pc-sys");
+            let _ = registry.unload_hive("pc-soft");
+            let _ = registry.unload_hive("pc-sys");
             if default_loaded {
-                let _ = OfflineRegistry::unload_hive("pc-default");
+                let _ = registry.unload_hive("pc-default");
             }
             
             // 使用 DISM 添加驱动
@@ -268,8 +358,8 @@ impl AdvancedOptions {
             }
             
             // 重新加载注册表
-            let _ = OfflineRegistry::load_hive("pc-soft", &software_hive);
-            let _ = OfflineRegistry::load_hive("pc-sys", &system_hive);
+            let _ = registry.load_hive("pc-soft", &software_hive);
+            let _ = registry.load_hive("pc-sys", &system_hive);
         }
 
         // 13. 导入注册表文件 - 实际导入到离线注册表
@@ -287,7 +377,7 @@ impl AdvancedOptions {
                 std::fs::write(&temp_reg, &converted)?;
                 
                 // 导入注册表
-                match OfflineRegistry::import_reg_file(&temp_reg) {
+                match registry.import_reg_file(&temp_reg) {
                     Ok(_) => println!("[ADVANCED] 注册表文件导入成功"),
                     Err(e) => println!("[ADVANCED] 注册表文件导入失败: {} (继续执行)", e),
                 }
@@ -315,10 +405,17 @@ impl AdvancedOptions {
 
         // 卸载注册表
         println!("[ADVANCED] 卸载离线注册表...");
-        let _ = OfflineRegistry::unload_hive("pc-soft");
-        let _ = OfflineRegistry::unload_hive("pc-sys");
+        let _ = registry.unload_hive("pc-soft");
+        let _ = registry.unload_hive("pc-sys");
         if default_loaded {
-            let _ = OfflineRegistry::unload_hive("pc-default");
+            let _ = registry.unload_hive("pc-default");
+        }
+
+        // 16. 装机软件 - 复制安装包并生成首次登录安装脚本
+        let software_items = self.software_items();
+        if !software_items.is_empty() {
+            println!("[ADVANCED] 复制装机软件: {} 个", software_items.len());
+            software::stage(&software_items, std::path::Path::new(&scripts_dir))?;
         }
 
         println!("[ADVANCED] 高级选项应用完成");
@@ -412,6 +509,122 @@ Write-Host "UWP应用清理完成"
         Ok(())
     }
 
+    /// 要创建的本地账户（主账户在前，密码已编码）
+    pub fn local_accounts(&self) -> Vec<LocalAccount> {
+        let primary_name = if self.custom_username && !self.username.trim().is_empty() {
+            self.username.as_str()
+        } else {
+            "User"
+        };
+        let mut accounts = vec![LocalAccount::new(primary_name, DEFAULT_GROUP, &self.password)];
+        accounts.extend(
+            self.extra_accounts
+                .iter()
+                .filter(|a| !a.name.trim().is_empty())
+                .map(|a| LocalAccount::new(&a.name, &a.group, &a.password)),
+        );
+        accounts
+    }
+
+    /// 设置了但没有密码的账户。方案和选项文件不保存密码，读取后这些账户都需要重新输入
+    pub fn accounts_missing_password(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if self.custom_username && !self.username.trim().is_empty() && self.password.is_empty() {
+            names.push(self.username.trim().to_string());
+        }
+        names.extend(
+            self.extra_accounts
+                .iter()
+                .filter(|a| !a.name.trim().is_empty() && a.password.is_empty())
+                .map(|a| a.name.trim().to_string()),
+        );
+        if self.builtin_administrator == BuiltinAdministrator::Enabled && self.admin_password.is_empty() {
+            names.push("Administrator".to_string());
+        }
+        names
+    }
+
+    /// 已编码的内置管理员密码（仅启用时有效）
+    pub fn administrator_password(&self) -> EncodedPassword {
+        if self.builtin_administrator == BuiltinAdministrator::Enabled {
+            EncodedPassword::for_administrator(&self.admin_password)
+        } else {
+            EncodedPassword::default()
+        }
+    }
+
+    /// 转换为应答文件选项（架构由生成时检测）
+    pub fn answer_file_options(&self) -> AnswerFileOptions {
+        AnswerFileOptions {
+            accounts: self.local_accounts(),
+            disable_auto_logon: self.disable_auto_logon,
+            builtin_administrator: self.builtin_administrator,
+            administrator_password: self.administrator_password(),
+            remove_uwp_apps: self.remove_uwp_apps,
+            install_software: !self.software_items().is_empty(),
+            ui_language: self.ui_language.clone(),
+            input_locale: self.input_locale.clone(),
+            system_locale: self.system_locale.clone(),
+            time_zone: self.time_zone.clone(),
+            computer_name: self.expanded_computer_name(),
+            product_key: self.product_key.clone(),
+            organization: self.organization.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// 展开后的计算机名（`{serial}` 取自本机序列号），未设置时为空
+    ///
+    /// 界面和命令行安装都经由这里生成应答文件中的计算机名。
+    pub fn expanded_computer_name(&self) -> String {
+        if self.computer_name.trim().is_empty() {
+            return String::new();
+        }
+        let serial = if self.computer_name.contains("{serial}") {
+            device_serial()
+        } else {
+            ""
+        };
+        regional::expand_computer_name(&self.computer_name, serial)
+    }
+
+    /// 首次登录时安装的软件（未启用时为空）
+    pub fn software_items(&self) -> Vec<SoftwareItem> {
+        if self.install_software {
+            software::parse_list(&self.software_list)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// 要合并的用户应答文件
+    pub fn user_unattend_file(&self) -> Option<&str> {
+        if self.import_unattend_file && !self.unattend_file_path.is_empty() {
+            Some(self.unattend_file_path.as_str())
+        } else {
+            None
+        }
+    }
+
+    /// 预先生成一次应答文件（含合并），检查格式并列出冲突
+    pub fn check_unattend(&self) -> anyhow::Result<Vec<String>> {
+        let rendered = render_answer_file(&self.answer_file_options(), self.user_unattend_file())?;
+        Ok(rendered
+            .conflicts
+            .iter()
+            .map(|c| format!("冲突: {}", c))
+            .chain(rendered.warnings.iter().map(|w| w.to_string()))
+            .collect())
+    }
+
+    fn check_unattend_file(&mut self) {
+        self.unattend_file_messages = match self.check_unattend() {
+            Ok(messages) if messages.is_empty() => vec!["应答文件检查通过，无冲突".to_string()],
+            Ok(messages) => messages,
+            Err(e) => vec![format!("错误: {}", e)],
+        };
+    }
+
     /// 显示高级选项界面
     pub fn show_ui(&mut self, ui: &mut egui::Ui) {
         egui::ScrollArea::vertical().show(ui, |ui| {
@@ -426,6 +639,7 @@ Write-Host "UWP应用清理完成"
             ui.checkbox(&mut self.disable_reserved_storage, "禁用系统保留空间");
             ui.checkbox(&mut self.disable_uac, "禁用用户账户控制(UAC)");
             ui.checkbox(&mut self.disable_device_encryption, "禁用自动设备加密");
+            ui.checkbox(&mut self.bypass_win11_check, "绕过Win11硬件要求检查");
             ui.checkbox(&mut self.remove_uwp_apps, "删除预装UWP应用");
 
             ui.add_space(15.0);
@@ -505,6 +719,60 @@ Write-Host "UWP应用清理完成"
                 }
             });
 
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.import_unattend_file, "导入应答文件");
+                if self.import_unattend_file {
+                    ui.text_edit_singleline(&mut self.unattend_file_path);
+                    if ui.button("浏览...").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("应答文件", &["xml"])
+                            .pick_file()
+                        {
+                            self.unattend_file_path = path.to_string_lossy().to_string();
+                            self.check_unattend_file();
+                        }
+                    }
+                    if ui.button("检查").clicked() {
+                        self.check_unattend_file();
+                    }
+                }
+            });
+            if self.import_unattend_file {
+                ui.label(
+                    egui::RichText::new("只有 LetRecovery 的脚本与清理命令会合并进应答文件，账户等其余设置以应答文件为准")
+                        .small()
+                        .weak(),
+                );
+                for message in &self.unattend_file_messages {
+                    ui.colored_label(egui::Color32::from_rgb(255, 165, 0), message);
+                }
+            }
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.install_software, "首次登录安装软件");
+                if self.install_software && ui.button("添加安装包...").clicked() {
+                    if let Some(paths) = rfd::FileDialog::new()
+                        .add_filter("安装包", &["exe", "msi"])
+                        .pick_files()
+                    {
+                        for path in paths {
+                            if !self.software_list.is_empty() && !self.software_list.ends_with('\n') {
+                                self.software_list.push('\n');
+                            }
+                            self.software_list.push_str(&path.to_string_lossy());
+                        }
+                    }
+                }
+            });
+            if self.install_software {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.software_list)
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("每行一个安装包，静默参数写在 | 之后，如 D:\\Soft\\7z.exe|/S"),
+                );
+            }
+
             ui.add_space(15.0);
             ui.heading("用户设置");
             ui.separator();
@@ -515,8 +783,203 @@ Write-Host "UWP应用清理完成"
                     ui.text_edit_singleline(&mut self.username);
                 }
             });
+
+            ui.horizontal(|ui| {
+                ui.label("账户密码:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.password)
+                        .password(true)
+                        .hint_text("留空表示无密码"),
+                );
+            });
+
+            ui.checkbox(&mut self.disable_auto_logon, "禁用首次自动登录");
+
+            ui.add_space(5.0);
+            ui.label("附加账户:");
+            self.show_extra_accounts_ui(ui);
+
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label("内置 Administrator:");
+                egui::ComboBox::from_id_salt("builtin_administrator")
+                    .selected_text(match self.builtin_administrator {
+                        BuiltinAdministrator::Unchanged => "保持默认",
+                        BuiltinAdministrator::Enabled => "启用",
+                        BuiltinAdministrator::Disabled => "禁用",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.builtin_administrator, BuiltinAdministrator::Unchanged, "保持默认");
+                        ui.selectable_value(&mut self.builtin_administrator, BuiltinAdministrator::Enabled, "启用");
+                        ui.selectable_value(&mut self.builtin_administrator, BuiltinAdministrator::Disabled, "禁用");
+                    });
+                if self.builtin_administrator == BuiltinAdministrator::Enabled {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.admin_password)
+                            .password(true)
+                            .hint_text("Administrator 密码"),
+                    );
+                }
+            });
+
+            ui.add_space(15.0);
+            ui.heading("区域和系统信息");
+            ui.separator();
+            self.show_regional_ui(ui);
         });
     }
+
+    /// 附加账户列表
+    fn show_extra_accounts_ui(&mut self, ui: &mut egui::Ui) {
+        let mut remove_index = None;
+        for (i, account) in self.extra_accounts.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut account.name)
+                        .hint_text("用户名")
+                        .desired_width(120.0),
+                );
+                ui.add(
+                    egui::TextEdit::singleline(&mut account.password)
+                        .password(true)
+                        .hint_text("密码")
+                        .desired_width(120.0),
+                );
+                let group_name = ACCOUNT_GROUPS
+                    .iter()
+                    .find(|(id, _)| *id == account.group)
+                    .map(|(_, name)| *name)
+                    .unwrap_or("管理员");
+                egui::ComboBox::from_id_salt(("extra_account_group", i))
+                    .selected_text(group_name)
+                    .show_ui(ui, |ui| {
+                        for (id, name) in ACCOUNT_GROUPS {
+                            ui.selectable_value(&mut account.group, id.to_string(), *name);
+                        }
+                    });
+                if ui.button("删除").clicked() {
+                    remove_index = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove_index {
+            self.extra_accounts.remove(i);
+        }
+        if ui.button("添加账户").clicked() {
+            self.extra_accounts.push(AccountEntry::default());
+        }
+    }
+
+    /// 区域、时区、计算机名、产品密钥设置
+    fn show_regional_ui(&mut self, ui: &mut egui::Ui) {
+        let current_locale = LOCALE_PRESETS
+            .iter()
+            .find(|p| p.ui_language == self.ui_language && p.input_locale == self.input_locale)
+            .map(|p| p.display_name)
+            .unwrap_or(if self.ui_language.is_empty() { "保持镜像默认" } else { "自定义" });
+
+        egui::Grid::new("unattend_regional_grid")
+            .num_columns(2)
+            .spacing([10.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("语言和键盘:");
+                egui::ComboBox::from_id_salt("unattend_locale")
+                    .selected_text(current_locale)
+                    .width(220.0)
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(self.ui_language.is_empty(), "保持镜像默认").clicked() {
+                            self.ui_language.clear();
+                            self.input_locale.clear();
+                            self.system_locale.clear();
+                        }
+                        for preset in LOCALE_PRESETS {
+                            let selected = self.ui_language == preset.ui_language
+                                && self.input_locale == preset.input_locale;
+                            if ui.selectable_label(selected, preset.display_name).clicked() {
+                                self.ui_language = preset.ui_language.to_string();
+                                self.input_locale = preset.input_locale.to_string();
+                                self.system_locale = preset.system_locale.to_string();
+                                if self.time_zone.is_empty() {
+                                    self.time_zone = preset.time_zone.to_string();
+                                }
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("时区:");
+                let current_tz = TIME_ZONES
+                    .iter()
+                    .find(|(id, _)| *id == self.time_zone)
+                    .map(|(_, name)| *name)
+                    .unwrap_or(if self.time_zone.is_empty() { "保持镜像默认" } else { self.time_zone.as_str() });
+                egui::ComboBox::from_id_salt("unattend_time_zone")
+                    .selected_text(current_tz)
+                    .width(220.0)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.time_zone, String::new(), "保持镜像默认");
+                        for (id, name) in TIME_ZONES {
+                            ui.selectable_value(&mut self.time_zone, id.to_string(), *name);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("计算机名:");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.computer_name)
+                            .hint_text("留空随机生成，如 PC-{serial}")
+                            .desired_width(160.0),
+                    );
+                    ui.label(egui::RichText::new("{serial} = 设备序列号").weak());
+                });
+                ui.end_row();
+
+                ui.label("系统版本密钥:");
+                ui.horizontal(|ui| {
+                    let current_edition = GENERIC_PRODUCT_KEYS
+                        .iter()
+                        .find(|(_, key)| *key == self.product_key)
+                        .map(|(edition, _)| *edition)
+                        .unwrap_or(if self.product_key.is_empty() { "不指定" } else { "自定义" });
+                    egui::ComboBox::from_id_salt("unattend_edition")
+                        .selected_text(current_edition)
+                        .width(140.0)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.product_key, String::new(), "不指定");
+                            for (edition, key) in GENERIC_PRODUCT_KEYS {
+                                ui.selectable_value(&mut self.product_key, key.to_string(), *edition);
+                            }
+                        });
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.product_key)
+                            .hint_text("XXXXX-XXXXX-XXXXX-XXXXX-XXXXX")
+                            .desired_width(230.0),
+                    );
+                });
+                ui.end_row();
+
+                ui.label("组织名称:");
+                ui.text_edit_singleline(&mut self.organization);
+                ui.end_row();
+            });
+
+        ui.label(
+            egui::RichText::new("通用密钥仅用于选择版本，不能激活系统；以上设置仅在无人值守安装时生效")
+                .small()
+                .weak(),
+        );
+    }
 }
 
 use egui;
+
+/// 本机序列号，首次用到时读取
+fn device_serial() -> &'static str {
+    static SERIAL: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    SERIAL.get_or_init(|| {
+        HardwareInfo::collect()
+            .map(|hw| hw.device_serial().to_string())
+            .unwrap_or_default()
+    })
+}
@@ -0,0 +1,211 @@
+use egui;
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+
+use letrecovery_common::backup::catalog::read_catalog;
+use letrecovery_common::backup::compression::Compression;
+use letrecovery_common::backup::repository::Repository;
+use letrecovery_common::backup::retention::{apply_retention, RetentionOutcome, RetentionPolicy};
+use letrecovery_common::command::SystemRunner;
+use letrecovery_common::wim;
+
+use crate::app::App;
+
+impl App {
+    /// 保留策略选项：备份完成后自动清理，也可以立即对当前镜像执行
+    pub fn show_backup_retention(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("保留策略:");
+            let selected_text = match self.backup_retention {
+                None => "保留全部".to_string(),
+                Some(RetentionPolicy::KeepLast(_)) => "保留最近几次".to_string(),
+                Some(RetentionPolicy::KeepDaily(_)) => "按天保留".to_string(),
+            };
+            egui::ComboBox::from_id_salt("backup_retention")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.backup_retention, None, "保留全部");
+                    ui.selectable_value(
+                        &mut self.backup_retention,
+                        Some(RetentionPolicy::KeepLast(5)),
+                        "保留最近几次",
+                    );
+                    ui.selectable_value(
+                        &mut self.backup_retention,
+                        Some(RetentionPolicy::KeepDaily(7)),
+                        "按天保留",
+                    );
+                });
+
+            match &mut self.backup_retention {
+                Some(RetentionPolicy::KeepLast(count)) => {
+                    ui.add(egui::DragValue::new(count).range(1..=100).suffix(" 次"));
+                }
+                Some(RetentionPolicy::KeepDaily(days)) => {
+                    ui.add(egui::DragValue::new(days).range(1..=365).suffix(" 天"));
+                }
+                None => {}
+            }
+        });
+        if let Some(policy) = self.backup_retention {
+            ui.label(
+                egui::RichText::new(format!("备份完成后{}，其余的卷从镜像中删除", policy.describe()))
+                    .small()
+                    .weak(),
+            );
+        }
+    }
+
+    /// 保存位置已有镜像时列出其中的备份
+    pub fn show_backup_catalog(&mut self, ui: &mut egui::Ui) {
+        self.update_retention_result();
+        if self.backup_catalog_path != self.backup_save_path {
+            self.load_backup_catalog();
+        }
+        if self.backup_catalog.is_empty() && self.backup_catalog_message.is_none() {
+            return;
+        }
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label(format!("镜像中已有 {} 个备份:", self.backup_catalog.len()));
+            if ui.button("刷新").clicked() {
+                self.load_backup_catalog();
+            }
+            let can_apply = self.backup_retention.is_some()
+                && self.backup_catalog.len() > 1
+                && self.backup_retention_rx.is_none()
+                && !self.is_backing_up;
+            if ui
+                .add_enabled(can_apply, egui::Button::new("按策略清理"))
+                .on_hover_text("WIM 导出要保留的备份到新镜像并替换原文件；仓库删除旧备份并回收数据块")
+                .clicked()
+            {
+                self.start_retention();
+            }
+            if self.backup_retention_rx.is_some() {
+                ui.spinner();
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .id_salt("backup_catalog_scroll")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                egui::Grid::new("backup_catalog_grid")
+                    .striped(true)
+                    .min_col_width(60.0)
+                    .show(ui, |ui| {
+                        ui.label("索引");
+                        ui.label("名称");
+                        ui.label("时间");
+                        ui.label("来源计算机");
+                        ui.label("大小");
+                        ui.end_row();
+
+                        for entry in &self.backup_catalog {
+                            ui.label(entry.index.to_string());
+                            ui.label(&entry.name).on_hover_text(&entry.description);
+                            ui.label(entry.created.map(format_local_time).unwrap_or_default());
+                            ui.label(entry.source_machine.as_deref().unwrap_or("-"));
+                            ui.label(Self::format_size(entry.size / 1024 / 1024));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(message) = &self.backup_catalog_message {
+            ui.label(egui::RichText::new(message).small().weak());
+        }
+    }
+
+    /// 重新读取保存位置的镜像目录
+    pub fn load_backup_catalog(&mut self) {
+        self.backup_catalog_path = self.backup_save_path.clone();
+        self.backup_catalog.clear();
+        self.backup_catalog_message = None;
+
+        let path = Path::new(&self.backup_save_path);
+        if Repository::is_repository(path) {
+            match Repository::open(path).and_then(|r| r.catalog()) {
+                Ok(catalog) => self.backup_catalog = catalog,
+                Err(e) => self.backup_catalog_message = Some(format!("无法读取仓库中的备份: {:#}", e)),
+            }
+            return;
+        }
+        let is_wim = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wim"));
+        if !is_wim || !path.is_file() {
+            return;
+        }
+        match read_catalog(path) {
+            Ok(catalog) => self.backup_catalog = catalog,
+            Err(e) => self.backup_catalog_message = Some(format!("无法读取镜像中的备份: {:#}", e)),
+        }
+    }
+
+    fn start_retention(&mut self) {
+        let Some(policy) = self.backup_retention else {
+            return;
+        };
+        let image_path = self.backup_save_path.clone();
+        let (tx, rx) = mpsc::channel();
+        self.backup_retention_rx = Some(rx);
+        self.backup_catalog_message = Some(format!("正在清理旧备份（{}）...", policy.describe()));
+
+        std::thread::spawn(move || {
+            println!("[BACKUP] 应用保留策略: {} -> {}", policy.describe(), image_path);
+            if Repository::is_repository(Path::new(&image_path)) {
+                let result = Repository::open(Path::new(&image_path))
+                    .and_then(|r| r.apply_retention(policy, wim::filetime_now()))
+                    .map(|(outcome, gc)| {
+                        println!("[BACKUP] 回收 {} 个数据块，{} 字节", gc.removed_chunks, gc.freed_bytes);
+                        outcome
+                    })
+                    .map_err(|e| format!("{:#}", e));
+                let _ = tx.send(result);
+                return;
+            }
+            let tools = crate::core::tools(Arc::new(SystemRunner));
+            // 重建的镜像沿用原镜像的压缩方式
+            let compression = wim::read_images(Path::new(&image_path))
+                .ok()
+                .and_then(|(header, _)| Compression::from_wim_flags(header.flags))
+                .unwrap_or_default();
+            let export = |source: &Path, index: u32, destination: &Path| {
+                let compress = (!destination.exists()).then_some(compression.to_config_value());
+                let (source, destination) = (source.to_string_lossy(), destination.to_string_lossy());
+                tools.export_image(&source, index, &destination, compress, &|_| {})
+            };
+            let result = apply_retention(Path::new(&image_path), policy, wim::filetime_now(), &export)
+                .map_err(|e| format!("{:#}", e));
+            let _ = tx.send(result);
+        });
+    }
+
+    fn update_retention_result(&mut self) {
+        let Some(rx) = &self.backup_retention_rx else {
+            return;
+        };
+        let Ok(result) = rx.try_recv() else {
+            return;
+        };
+        self.backup_retention_rx = None;
+        self.load_backup_catalog();
+        self.backup_catalog_message = Some(match result {
+            Ok(RetentionOutcome { removed, .. }) if removed.is_empty() => "没有需要清理的备份".to_string(),
+            Ok(RetentionOutcome { kept, removed }) => {
+                format!("已删除 {} 个旧备份，保留 {} 个", removed.len(), kept.len())
+            }
+            Err(e) => format!("清理失败: {}", e),
+        });
+    }
+}
+
+/// 以本地时间显示 FILETIME
+fn format_local_time(filetime: u64) -> String {
+    chrono::DateTime::from_timestamp(wim::filetime_to_unix(filetime) as i64, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
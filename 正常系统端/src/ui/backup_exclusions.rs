@@ -0,0 +1,87 @@
+use egui;
+use serde::{Deserialize, Serialize};
+
+use letrecovery_common::backup::exclusions::{parse_lines, ExclusionConfig};
+
+use crate::app::App;
+
+/// 备份排除设置（界面中编辑，随部署方案保存）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupExclusions {
+    /// 使用内置的排除列表（分页文件、回收站、临时目录、浏览器缓存等）
+    pub use_defaults: bool,
+    /// 自定义排除项，每行一个
+    pub exclusions: String,
+    /// 自定义不压缩项，每行一个
+    pub compression_exclusions: String,
+}
+
+impl Default for BackupExclusions {
+    fn default() -> Self {
+        Self {
+            use_defaults: true,
+            exclusions: String::new(),
+            compression_exclusions: String::new(),
+        }
+    }
+}
+
+impl BackupExclusions {
+    /// 生成捕获时使用的配置
+    pub fn to_config(&self) -> ExclusionConfig {
+        ExclusionConfig::new(
+            self.use_defaults,
+            &parse_lines(&self.exclusions),
+            &parse_lines(&self.compression_exclusions),
+        )
+    }
+}
+
+impl App {
+    /// 排除列表选项，折叠显示
+    pub fn show_backup_exclusions(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("排除文件")
+            .id_salt("backup_exclusions")
+            .show(ui, |ui| {
+                let settings = &mut self.backup_exclusions;
+                ui.checkbox(
+                    &mut settings.use_defaults,
+                    "排除分页文件、休眠文件、回收站、临时目录和浏览器缓存",
+                );
+                ui.label(
+                    egui::RichText::new(
+                        "每行一项，以 \\ 开头从分区根目录匹配，否则匹配任意位置；可使用 * 和 ? 通配符",
+                    )
+                    .small()
+                    .weak(),
+                );
+
+                ui.label("额外排除:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut settings.exclusions)
+                        .hint_text("\\Users\\*\\Downloads\n*.iso")
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.label("不压缩:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut settings.compression_exclusions)
+                        .hint_text("*.vhdx")
+                        .desired_rows(2)
+                        .desired_width(f32::INFINITY),
+                );
+
+                let config = settings.to_config();
+                ui.label(
+                    egui::RichText::new(format!(
+                        "共 {} 个排除项，{} 个不压缩项",
+                        config.exclusions.len(),
+                        config.compression_exclusions.len()
+                    ))
+                    .small()
+                    .weak(),
+                );
+            });
+    }
+}
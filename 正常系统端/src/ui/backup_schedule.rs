@@ -0,0 +1,256 @@
+use egui;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+use letrecovery_common::backup::exclusions::parse_lines;
+use letrecovery_common::backup::retention::RetentionPolicy;
+use letrecovery_common::backup::schedule::{BackupSchedule, Frequency, Weekday};
+use letrecovery_common::command::SystemRunner;
+
+use crate::app::App;
+use crate::core::task_scheduler::TaskScheduler;
+
+/// 计划备份设置
+pub struct BackupScheduleState {
+    pub frequency: Frequency,
+    pub hour: u8,
+    pub minute: u8,
+    pub target_folder: String,
+    /// 保留最近几次备份
+    pub keep_last: u32,
+    pub verify: bool,
+    /// 已查询过的任务名称，源分区改变时重新查询
+    pub checked_task: String,
+    pub task_registered: bool,
+    pub message: Option<String>,
+    /// 后台执行中的 schtasks 操作
+    pub task_rx: Option<Receiver<TaskEvent>>,
+}
+
+/// 后台 schtasks 操作的结果
+pub enum TaskEvent {
+    Checked { task_name: String, registered: bool },
+    Created { task_name: String, result: Result<(), String> },
+    Deleted { task_name: String, result: Result<(), String> },
+}
+
+impl BackupScheduleState {
+    /// schtasks 可能要等待数秒，在后台线程中执行，避免界面卡住
+    fn spawn_task(&mut self, job: impl FnOnce(&TaskScheduler) -> TaskEvent + Send + 'static) {
+        let (tx, rx) = mpsc::channel();
+        self.task_rx = Some(rx);
+        std::thread::spawn(move || {
+            let scheduler = TaskScheduler::with_runner(Arc::new(SystemRunner));
+            let _ = tx.send(job(&scheduler));
+        });
+    }
+
+    fn update_task_result(&mut self) {
+        let Some(Ok(event)) = self.task_rx.as_ref().map(|rx| rx.try_recv()) else {
+            return;
+        };
+        self.task_rx = None;
+        match event {
+            TaskEvent::Checked { task_name, registered } => {
+                // 查询期间源分区已改变时丢弃结果，稍后重新查询
+                if task_name == self.checked_task {
+                    self.task_registered = registered;
+                }
+            }
+            TaskEvent::Created { task_name, result } => {
+                self.message = Some(match result {
+                    Ok(()) => {
+                        self.task_registered = true;
+                        format!("已创建计划任务 {}", task_name)
+                    }
+                    Err(e) => format!("创建计划任务失败: {}", e),
+                });
+            }
+            TaskEvent::Deleted { task_name, result } => {
+                self.message = Some(match result {
+                    Ok(()) => {
+                        self.task_registered = false;
+                        format!("已删除计划任务 {}", task_name)
+                    }
+                    Err(e) => format!("删除计划任务失败: {}", e),
+                });
+            }
+        }
+    }
+}
+
+impl Default for BackupScheduleState {
+    fn default() -> Self {
+        Self {
+            frequency: Frequency::Weekly(Weekday::Sunday),
+            hour: 3,
+            minute: 0,
+            target_folder: String::new(),
+            keep_last: 4,
+            verify: false,
+            checked_task: String::new(),
+            task_registered: false,
+            message: None,
+            task_rx: None,
+        }
+    }
+}
+
+impl App {
+    /// 计划备份：为选中的分区注册定期执行的后台备份任务
+    pub fn show_backup_schedule(&mut self, ui: &mut egui::Ui) {
+        if self.is_pe_environment() {
+            return;
+        }
+        let Some(partition) = self
+            .backup_source_partition
+            .and_then(|idx| self.partitions.get(idx))
+            .cloned()
+        else {
+            return;
+        };
+        // 系统分区不能在运行中捕获，计划任务只把备份排入 PE
+        let pe_file = if partition.is_system_partition {
+            self.selected_pe_for_backup
+                .and_then(|idx| self.config.as_ref().and_then(|c| c.pe_list.get(idx)))
+                .map(|pe| pe.filename.clone())
+        } else {
+            None
+        };
+        let exclusions = self.backup_exclusions.clone();
+        let (format, compression) = (self.backup_format, self.backup_compression);
+
+        egui::CollapsingHeader::new("计划备份")
+            .id_salt("backup_schedule")
+            .show(ui, |ui| {
+                let state = &mut self.backup_schedule;
+                state.update_task_result();
+
+                ui.horizontal(|ui| {
+                    ui.label("频率:");
+                    let selected_text = match state.frequency {
+                        Frequency::Daily => "每天".to_string(),
+                        Frequency::Weekly(day) => format!("每周 {}", day.name()),
+                    };
+                    egui::ComboBox::from_id_salt("backup_schedule_frequency")
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut state.frequency, Frequency::Daily, "每天");
+                            for day in Weekday::all() {
+                                ui.selectable_value(
+                                    &mut state.frequency,
+                                    Frequency::Weekly(*day),
+                                    format!("每周 {}", day.name()),
+                                );
+                            }
+                        });
+
+                    ui.label("时间:");
+                    ui.add(egui::DragValue::new(&mut state.hour).range(0..=23));
+                    ui.label(":");
+                    ui.add(egui::DragValue::new(&mut state.minute).range(0..=59));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("目标文件夹:");
+                    ui.add(egui::TextEdit::singleline(&mut state.target_folder).desired_width(300.0));
+                    if ui.button("浏览...").clicked() {
+                        if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                            state.target_folder = folder.to_string_lossy().to_string();
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("保留最近:");
+                    ui.add(egui::DragValue::new(&mut state.keep_last).range(1..=100).suffix(" 次"));
+                    ui.checkbox(&mut state.verify, "备份后验证");
+                });
+
+                let schedule = BackupSchedule {
+                    frequency: state.frequency,
+                    hour: state.hour,
+                    minute: state.minute,
+                    source_partition: partition.letter.clone(),
+                    target_folder: state.target_folder.trim().to_string(),
+                    retention: Some(RetentionPolicy::KeepLast(state.keep_last)),
+                    verify: state.verify,
+                    format,
+                    compression,
+                    use_default_exclusions: exclusions.use_defaults,
+                    exclusions: parse_lines(&exclusions.exclusions),
+                    compression_exclusions: parse_lines(&exclusions.compression_exclusions),
+                    pe_file: pe_file.clone(),
+                };
+
+                let task_name = schedule.task_name();
+                let busy = state.task_rx.is_some();
+                if state.checked_task != task_name && !busy {
+                    state.checked_task = task_name.clone();
+                    state.task_registered = false;
+                    let task_name = task_name.clone();
+                    state.spawn_task(move |scheduler| TaskEvent::Checked {
+                        registered: scheduler.task_exists(&task_name),
+                        task_name,
+                    });
+                }
+
+                let blocked_reason = if schedule.target_folder.is_empty() {
+                    Some("请选择目标文件夹")
+                } else if partition.is_system_partition && pe_file.is_none() {
+                    Some("备份当前系统分区需要先选择 PE")
+                } else if partition.is_system_partition && format.is_dedup() {
+                    Some("去重仓库不能用于当前系统分区")
+                } else {
+                    None
+                };
+                match blocked_reason {
+                    Some(reason) => {
+                        ui.label(egui::RichText::new(reason).small().weak());
+                    }
+                    None => {
+                        ui.label(egui::RichText::new(schedule.describe()).small().weak());
+                    }
+                }
+                if partition.is_system_partition && blocked_reason.is_none() {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 165, 0),
+                        "⚠ 系统分区无法在运行中备份：到计划时间只会添加 PE 启动项，不会自动重启，\
+                         备份要等下次重启进入 PE 后才执行",
+                    );
+                }
+
+                ui.horizontal(|ui| {
+                    let label = if state.task_registered { "更新计划" } else { "创建计划" };
+                    if ui.add_enabled(blocked_reason.is_none() && !busy, egui::Button::new(label)).clicked() {
+                        let start_date = chrono::Local::now().format("%Y-%m-%d").to_string();
+                        state.spawn_task(move |scheduler| {
+                            let result = std::env::current_exe()
+                                .map_err(anyhow::Error::from)
+                                .and_then(|exe| {
+                                    scheduler.create_backup_task(&schedule, &exe.to_string_lossy(), &start_date)
+                                });
+                            TaskEvent::Created {
+                                task_name: schedule.task_name(),
+                                result: result.map_err(|e| e.to_string()),
+                            }
+                        });
+                    }
+                    if state.task_registered && ui.add_enabled(!busy, egui::Button::new("删除计划")).clicked() {
+                        let task_name = task_name.clone();
+                        state.spawn_task(move |scheduler| TaskEvent::Deleted {
+                            result: scheduler.delete_backup_task(&task_name).map_err(|e| e.to_string()),
+                            task_name,
+                        });
+                    }
+                    if busy {
+                        ui.spinner();
+                    }
+                });
+
+                if let Some(message) = &state.message {
+                    ui.label(message);
+                }
+            });
+    }
+}
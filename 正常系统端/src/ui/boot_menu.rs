@@ -0,0 +1,536 @@
+use egui;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+
+use letrecovery_common::bcd::{BcdStore, Device, Guid, PartitionId};
+
+use crate::app::App;
+use crate::core::bcdedit::BootManager;
+use crate::core::disk::DiskManager;
+
+/// 启动菜单管理窗口状态
+#[derive(Default)]
+pub struct BootMenuState {
+    pub open: bool,
+    store: Option<BcdStore>,
+    /// 当前存在的分区：(盘符, BCD 分区标识)
+    partitions: Vec<(String, PartitionId)>,
+    /// 编辑中的显示顺序
+    order: Vec<Guid>,
+    /// 编辑中的名称
+    names: HashMap<Guid, String>,
+    timeout: u32,
+    /// 本次打开窗口后修改前创建的 BCD 备份
+    backup_path: Option<PathBuf>,
+    /// 等待确认删除的对象
+    pending_delete: Option<Vec<Guid>>,
+    message: String,
+    /// 后台执行的读取或修改，bcdedit 和磁盘查询较慢，不在界面线程中执行
+    task: Option<Receiver<TaskResult>>,
+}
+
+/// 在后台执行的 BCD 修改
+type Change = Box<dyn FnOnce(&BootManager) -> anyhow::Result<()> + Send>;
+
+/// 后台任务的结果
+struct TaskResult {
+    /// 修改操作的结果，仅刷新时为空
+    message: Option<String>,
+    backup_path: Option<PathBuf>,
+    /// 重新读取的分区和 BCD；修改失败时为 None，保留界面上的编辑
+    loaded: Option<(Vec<(String, PartitionId)>, anyhow::Result<BcdStore>)>,
+}
+
+/// 菜单中的一行
+struct Row {
+    id: Guid,
+    identifier: String,
+    device: String,
+    in_menu: bool,
+    is_default: bool,
+    is_letrecovery_pe: bool,
+    is_stale: bool,
+}
+
+/// 界面操作
+enum Action {
+    Reload,
+    MoveUp(usize),
+    MoveDown(usize),
+    ToggleMenu(Guid),
+    SaveOrder,
+    SetDefault(Guid),
+    Rename(Guid),
+    Delete(Guid),
+    DeleteRemovable,
+    ConfirmDelete,
+    CancelDelete,
+    SetTimeout,
+    Export,
+    Import,
+}
+
+impl BootMenuState {
+    /// 打开窗口并读取 BCD
+    pub fn open(&mut self) {
+        self.open = true;
+        self.backup_path = None;
+        self.pending_delete = None;
+        self.message.clear();
+        self.reload();
+    }
+
+    fn reload(&mut self) {
+        self.spawn_task(None, None, true);
+    }
+
+    /// 启动后台任务：执行操作后重新读取分区和 BCD
+    ///
+    /// `modifies` 为 true 时操作会修改 BCD，执行前先备份；为 false 时（如导出）
+    /// 不备份也不重新读取，保留界面上的编辑。
+    fn spawn_task(&mut self, done: Option<String>, change: Option<Change>, modifies: bool) {
+        if self.task.is_some() {
+            return;
+        }
+        let mut backup_path = self.backup_path.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let boot_manager = BootManager::new();
+            let mut message = None;
+            let mut reload = modifies;
+            if let Some(change) = change {
+                let result = if modifies {
+                    ensure_backup(&boot_manager, &mut backup_path).and_then(|_| change(&boot_manager))
+                } else {
+                    change(&boot_manager)
+                };
+                match result {
+                    Ok(()) => message = done,
+                    Err(e) => {
+                        message = Some(format!("操作失败: {}", e));
+                        reload = false;
+                    }
+                }
+            }
+            let loaded = reload.then(|| (read_partitions(), boot_manager.read_store()));
+            let _ = tx.send(TaskResult { message, backup_path, loaded });
+        });
+        self.task = Some(rx);
+    }
+
+    /// 是否有正在执行的后台任务
+    fn is_busy(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// 检查后台任务是否完成，完成时更新界面状态
+    fn poll_task(&mut self) {
+        let Some(rx) = &self.task else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.task = None;
+                self.message = "读取 BCD 的后台任务意外结束".to_string();
+                return;
+            }
+        };
+        self.task = None;
+        self.backup_path = result.backup_path;
+        if let Some(message) = result.message {
+            self.message = message;
+        }
+        let Some((partitions, store)) = result.loaded else {
+            return;
+        };
+        self.partitions = partitions;
+
+        match store {
+            Ok(store) => {
+                let present: Vec<PartitionId> = self.partitions.iter().map(|(_, id)| *id).collect();
+                self.order = store
+                    .menu_entries(&present)
+                    .iter()
+                    .filter(|e| e.in_display_order)
+                    .map(|e| e.object.id)
+                    .collect();
+                self.names = store
+                    .menu_entries(&present)
+                    .iter()
+                    .map(|e| (e.object.id, e.object.description().unwrap_or_default().to_string()))
+                    .collect();
+                self.timeout = store.timeout().unwrap_or(0) as u32;
+                self.store = Some(store);
+            }
+            Err(e) => {
+                self.store = None;
+                self.message = format!("读取 BCD 失败: {}", e);
+            }
+        }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let Some(store) = &self.store else {
+            return Vec::new();
+        };
+        let present: Vec<PartitionId> = self.partitions.iter().map(|(_, id)| *id).collect();
+        let entries = store.menu_entries(&present);
+
+        // 按编辑中的顺序排列，菜单外的项排在后面
+        let position = |id: &Guid| self.order.iter().position(|o| o == id).unwrap_or(usize::MAX);
+        let mut entries: Vec<_> = entries.iter().collect();
+        entries.sort_by_key(|e| position(&e.object.id));
+
+        entries
+            .into_iter()
+            .map(|e| Row {
+                id: e.object.id,
+                identifier: e.object.identifier(),
+                device: self.describe_device(e.device()),
+                in_menu: self.order.contains(&e.object.id),
+                is_default: e.is_default,
+                is_letrecovery_pe: e.is_letrecovery_pe,
+                is_stale: e.is_stale,
+            })
+            .collect()
+    }
+
+    /// 设备描述，分区显示为盘符
+    fn describe_device(&self, device: Option<&Device>) -> String {
+        let letter_of = |id: &PartitionId| {
+            self.partitions
+                .iter()
+                .find(|(_, p)| p == id)
+                .map(|(letter, _)| letter.clone())
+                .unwrap_or_else(|| id.to_string())
+        };
+        match device {
+            None => String::new(),
+            Some(Device::Partition(id)) => format!("partition={}", letter_of(id)),
+            Some(Device::File {
+                ramdisk_options,
+                parent,
+                path,
+            }) => {
+                let parent = match parent.as_deref() {
+                    Some(Device::Partition(id)) => letter_of(id),
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                };
+                if ramdisk_options.is_some() {
+                    format!("ramdisk=[{}]{}", parent, path)
+                } else {
+                    format!("file=[{}]{}", parent, path)
+                }
+            }
+            Some(other) => other.to_string(),
+        }
+    }
+
+    /// 在后台执行修改：先备份，修改后重新读取
+    fn modify(&mut self, done: &str, change: impl FnOnce(&BootManager) -> anyhow::Result<()> + Send + 'static) {
+        self.spawn_task(Some(done.to_string()), Some(Box::new(change)), true);
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::Reload => {
+                self.message.clear();
+                self.reload();
+            }
+            Action::MoveUp(i) => {
+                if i > 0 && i < self.order.len() {
+                    self.order.swap(i - 1, i);
+                }
+            }
+            Action::MoveDown(i) => {
+                if i + 1 < self.order.len() {
+                    self.order.swap(i, i + 1);
+                }
+            }
+            Action::ToggleMenu(id) => {
+                if let Some(pos) = self.order.iter().position(|o| *o == id) {
+                    self.order.remove(pos);
+                } else {
+                    self.order.push(id);
+                }
+            }
+            Action::SaveOrder => {
+                let order: Vec<String> = self.order.iter().map(|g| g.to_string()).collect();
+                self.modify("启动菜单顺序已保存", move |bm| bm.set_display_order(&order));
+            }
+            Action::SetDefault(id) => {
+                self.modify("默认启动项已设置", move |bm| bm.set_default_boot(&id.to_string()));
+            }
+            Action::Rename(id) => {
+                let name = self.names.get(&id).cloned().unwrap_or_default();
+                if name.trim().is_empty() {
+                    self.message = "名称不能为空".to_string();
+                    return;
+                }
+                self.modify("引导项已重命名", move |bm| bm.rename_boot_entry(&id.to_string(), name.trim()));
+            }
+            Action::Delete(id) => {
+                if let Some(store) = &self.store {
+                    self.pending_delete = Some(store.removal_set(&id));
+                }
+            }
+            Action::DeleteRemovable => {
+                if let Some(store) = &self.store {
+                    let present: Vec<PartitionId> = self.partitions.iter().map(|(_, id)| *id).collect();
+                    let mut ids: Vec<Guid> = Vec::new();
+                    for entry in store.menu_entries(&present).iter().filter(|e| e.is_removable()) {
+                        for id in store.removal_set(&entry.object.id) {
+                            if !ids.contains(&id) {
+                                ids.push(id);
+                            }
+                        }
+                    }
+                    if ids.is_empty() {
+                        self.message = "没有失效或遗留的 PE 引导项".to_string();
+                    } else {
+                        self.pending_delete = Some(ids);
+                    }
+                }
+            }
+            Action::ConfirmDelete => {
+                if let Some(ids) = self.pending_delete.take() {
+                    self.modify(&format!("已删除 {} 个对象", ids.len()), move |bm| {
+                        for id in &ids {
+                            bm.delete_boot_entry(&id.to_string())?;
+                        }
+                        Ok(())
+                    });
+                }
+            }
+            Action::CancelDelete => self.pending_delete = None,
+            Action::SetTimeout => {
+                let timeout = self.timeout;
+                self.modify("等待时间已设置", move |bm| bm.set_timeout(timeout));
+            }
+            Action::Export => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name(format!("BCD_{}", chrono::Local::now().format("%Y%m%d_%H%M%S")))
+                    .save_file()
+                {
+                    let done = format!("BCD 已导出到 {}", path.display());
+                    self.spawn_task(Some(done), Some(Box::new(move |bm| bm.export_store(&path))), false);
+                }
+            }
+            Action::Import => {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.modify(&format!("已从 {} 导入 BCD", path.display()), move |bm| bm.import_store(&path));
+                }
+            }
+        }
+    }
+}
+
+/// 修改前确保已备份 BCD（每次打开窗口只备份一次）
+fn ensure_backup(boot_manager: &BootManager, backup_path: &mut Option<PathBuf>) -> anyhow::Result<()> {
+    if backup_path.is_none() {
+        let path = boot_manager.backup_store()?;
+        println!("[BOOT] 已备份 BCD: {}", path.display());
+        *backup_path = Some(path);
+    }
+    Ok(())
+}
+
+/// 当前存在的分区及其 BCD 分区标识
+fn read_partitions() -> Vec<(String, PartitionId)> {
+    DiskManager::get_partitions()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|p| {
+            let letter = p.letter.chars().next()?;
+            Some((p.letter.clone(), DiskManager::get_partition_id(letter)?))
+        })
+        .collect()
+}
+
+impl App {
+    /// 启动菜单管理窗口
+    pub fn show_boot_menu_window(&mut self, ctx: &egui::Context) {
+        if !self.boot_menu.open {
+            return;
+        }
+
+        self.boot_menu.poll_task();
+
+        let mut open = true;
+        let mut actions = Vec::new();
+        let state = &mut self.boot_menu;
+        let rows = state.rows();
+        let busy = state.is_busy();
+
+        egui::Window::new("启动菜单管理")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(760.0)
+            .default_height(460.0)
+            .show(ctx, |ui| {
+                if busy {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("正在处理 BCD...");
+                    });
+                    ui.disable();
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("刷新").clicked() {
+                        actions.push(Action::Reload);
+                    }
+                    if ui.button("导出 BCD 备份...").clicked() {
+                        actions.push(Action::Export);
+                    }
+                    if ui.button("导入 BCD 备份...").clicked() {
+                        actions.push(Action::Import);
+                    }
+                });
+                match &state.backup_path {
+                    Some(path) => ui.label(
+                        egui::RichText::new(format!("修改前的备份: {}", path.display())).small().weak(),
+                    ),
+                    None => ui.label(egui::RichText::new("首次修改前会自动备份当前 BCD").small().weak()),
+                };
+
+                ui.separator();
+
+                if state.store.is_none() {
+                    if !busy {
+                        ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "⚠ 无法读取 BCD");
+                    }
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.label("菜单等待时间:");
+                        ui.add(egui::DragValue::new(&mut state.timeout).range(0..=999).suffix(" 秒"));
+                        if ui.button("应用").clicked() {
+                            actions.push(Action::SetTimeout);
+                        }
+                    });
+                    ui.add_space(5.0);
+
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        egui::Grid::new("boot_menu_grid")
+                            .num_columns(5)
+                            .spacing([10.0, 6.0])
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("顺序");
+                                ui.strong("名称");
+                                ui.strong("设备");
+                                ui.strong("状态");
+                                ui.strong("操作");
+                                ui.end_row();
+
+                                for row in &rows {
+                                    let index = state.order.iter().position(|o| *o == row.id);
+                                    ui.horizontal(|ui| match index {
+                                        Some(i) => {
+                                            ui.label(format!("{}", i + 1));
+                                            if ui.small_button("↑").clicked() {
+                                                actions.push(Action::MoveUp(i));
+                                            }
+                                            if ui.small_button("↓").clicked() {
+                                                actions.push(Action::MoveDown(i));
+                                            }
+                                        }
+                                        None => {
+                                            ui.label("-");
+                                        }
+                                    });
+
+                                    let name = state.names.entry(row.id).or_default();
+                                    ui.add(egui::TextEdit::singleline(name).desired_width(180.0))
+                                        .on_hover_text(&row.identifier);
+
+                                    ui.label(&row.device);
+
+                                    ui.horizontal(|ui| {
+                                        if row.is_default {
+                                            ui.colored_label(egui::Color32::from_rgb(0, 180, 0), "默认");
+                                        }
+                                        if row.is_letrecovery_pe {
+                                            ui.label("LetRecovery PE");
+                                        }
+                                        if row.is_stale {
+                                            ui.colored_label(egui::Color32::from_rgb(255, 100, 100), "分区不存在");
+                                        }
+                                        if !row.in_menu {
+                                            ui.label(egui::RichText::new("不在菜单中").weak());
+                                        }
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        if ui.small_button("重命名").clicked() {
+                                            actions.push(Action::Rename(row.id));
+                                        }
+                                        if !row.is_default && ui.small_button("设为默认").clicked() {
+                                            actions.push(Action::SetDefault(row.id));
+                                        }
+                                        let toggle = if row.in_menu { "移出菜单" } else { "加入菜单" };
+                                        if ui.small_button(toggle).clicked() {
+                                            actions.push(Action::ToggleMenu(row.id));
+                                        }
+                                        if ui.small_button("删除").clicked() {
+                                            actions.push(Action::Delete(row.id));
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("保存菜单顺序").clicked() {
+                            actions.push(Action::SaveOrder);
+                        }
+                        if ui.button("清理失效项和遗留 PE").clicked() {
+                            actions.push(Action::DeleteRemovable);
+                        }
+                    });
+                }
+
+                if let Some(ids) = &state.pending_delete {
+                    ui.separator();
+                    let names: Vec<String> = ids
+                        .iter()
+                        .map(|id| match state.names.get(id).filter(|n| !n.is_empty()) {
+                            Some(name) => format!("{} {}", name, id),
+                            None => id.to_string(),
+                        })
+                        .collect();
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 165, 0),
+                        format!("确定删除以下对象？\n{}", names.join("\n")),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("确认删除").clicked() {
+                            actions.push(Action::ConfirmDelete);
+                        }
+                        if ui.button("取消").clicked() {
+                            actions.push(Action::CancelDelete);
+                        }
+                    });
+                }
+
+                if !state.message.is_empty() {
+                    ui.separator();
+                    ui.label(&state.message);
+                }
+            });
+
+        if !busy {
+            for action in actions {
+                self.boot_menu.apply(action);
+            }
+        }
+        if !open {
+            self.boot_menu.open = false;
+        }
+    }
+}
@@ -1,13 +1,19 @@
 use egui;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::path::Path;
 
-use crate::app::{App, BootModeSelection, InstallMode};
+use crate::app::{App, BootModeSelection, InstallMode, InstallOptions};
 use crate::core::dism::DismProgress;
-use crate::core::disk::{Partition, PartitionStyle};
-use crate::core::ghost::Ghost;
+use crate::core::disk::PartitionStyle;
 use crate::core::install_config::{ConfigFileManager, InstallConfig};
-use crate::ui::advanced_options::AdvancedOptions;
+use crate::workflow::{export_drivers, DesktopInstallBackend};
+use letrecovery_common::command::SystemRunner;
+use letrecovery_common::disk_layout::Firmware;
+use letrecovery_common::software;
+use letrecovery_common::unattend::{
+    render_answer_file, write_answer_file, AnswerFileOptions, Architecture, MergeConflict,
+};
+use letrecovery_common::workflow::{run_install, InstallPlan, InstallStep, TargetPreparation, WorkflowEvent};
 
 impl App {
     pub fn show_install_progress(&mut self, ui: &mut egui::Ui) {
@@ -60,15 +66,10 @@ impl App {
             .max_height(200.0)
             .show(ui, |ui| {
                 let steps = match self.install_mode {
-                    InstallMode::Direct => vec![
-                        "格式化分区",
-                        "导出驱动",
-                        "释放系统镜像",
-                        "导入驱动",
-                        "修复引导",
-                        "应用高级选项",
-                        "完成安装",
-                    ],
+                    InstallMode::Direct => InstallStep::all()
+                        .iter()
+                        .map(|step| direct_step_name(*step, first_step_name(&self.install_options)))
+                        .collect(),
                     InstallMode::ViaPE => vec![
                         "检查PE环境",
                         "安装PE引导",
@@ -162,6 +163,10 @@ impl App {
     fn update_install_progress(&mut self) {
         if let Some(ref rx) = self.install_progress_rx {
             while let Ok(progress) = rx.try_recv() {
+                if let Some(error) = progress.status.strip_prefix("ERROR:") {
+                    self.install_error = Some(error.to_string());
+                    continue;
+                }
                 if let Some((step, name)) = parse_step_from_status(&progress.status) {
                     self.install_progress.step_progress = progress.percentage;
                     
@@ -172,21 +177,10 @@ impl App {
                     }
                     
                     // 计算总进度
-                    let (base_progress, step_weight) = match self.install_mode {
-                        InstallMode::Direct => {
-                            let base = match step {
-                                1 => 0,
-                                2 => 5,
-                                3 => 10,
-                                4 => 90,
-                                5 => 93,
-                                6 => 96,
-                                7 => 100,
-                                _ => 0,
-                            };
-                            let weight = if step == 3 { 80 } else { 3 };
-                            (base, weight)
-                        }
+                    self.install_progress.total_progress = match self.install_mode {
+                        InstallMode::Direct => InstallStep::all()
+                            .get(step.saturating_sub(1))
+                            .map_or(0, |s| s.overall_progress(progress.percentage)),
                         InstallMode::ViaPE => {
                             let base = match step {
                                 1 => 0,
@@ -201,12 +195,9 @@ impl App {
                                 4 => 40,
                                 _ => 10,
                             };
-                            (base, weight)
+                            (base + (progress.percentage as usize * weight / 100)).min(100) as u8
                         }
                     };
-                    
-                    self.install_progress.total_progress = 
-                        (base_progress + (progress.percentage as usize * step_weight / 100)).min(100) as u8;
                 }
             }
         }
@@ -223,204 +214,100 @@ impl App {
         self.install_progress_rx = Some(progress_rx);
 
         let target_partition = self.install_target_partition.clone();
-        let image_path = self.install_image_path.clone();
-        let volume_index = self.install_volume_index;
         let options = self.install_options.clone();
-        let advanced_options = self.advanced_options.clone();
-        let partitions: Vec<Partition> = self.partitions.clone();
-        
-        let partition_style = self.partitions
-            .iter()
-            .find(|p| p.letter == target_partition)
-            .map(|p| p.partition_style)
-            .unwrap_or(PartitionStyle::Unknown);
+
+        let partition_style = match &options.clean_install {
+            Some(layout) => match layout.options.firmware {
+                Firmware::Uefi => PartitionStyle::GPT,
+                Firmware::Bios => PartitionStyle::MBR,
+            },
+            None => self.partitions
+                .iter()
+                .find(|p| p.letter == target_partition)
+                .map(|p| p.partition_style)
+                .unwrap_or(PartitionStyle::Unknown),
+        };
+        let boot_firmware = options.repair_boot.then(|| {
+            let use_uefi = match options.boot_mode {
+                BootModeSelection::UEFI => true,
+                BootModeSelection::Legacy => false,
+                BootModeSelection::Auto => matches!(partition_style, PartitionStyle::GPT),
+            };
+            if use_uefi {
+                Firmware::Uefi
+            } else {
+                Firmware::Bios
+            }
+        });
+        let preparation = if options.clean_install.is_some() {
+            TargetPreparation::Partition
+        } else if options.format_partition {
+            TargetPreparation::Format
+        } else {
+            TargetPreparation::Keep
+        };
+        let driver_dir = options.export_drivers.then(|| {
+            std::env::temp_dir()
+                .join("LetRecovery_DriverBackup")
+                .to_string_lossy()
+                .to_string()
+        });
+        let image_lower = self.install_image_path.to_lowercase();
+
+        let plan = InstallPlan {
+            target_partition,
+            image_path: self.install_image_path.clone(),
+            volume_index: self.install_volume_index,
+            is_gho: image_lower.ends_with(".gho") || image_lower.ends_with(".ghs"),
+            preparation,
+            driver_dir: driver_dir.clone(),
+            export_drivers: options.export_drivers,
+            boot_firmware,
+            unattended: options.unattended_install,
+        };
+        let advanced_options = options.advanced_options.clone();
+        let backend = DesktopInstallBackend {
+            answer_options: advanced_options.answer_file_options(),
+            user_unattend_file: advanced_options.user_unattend_file().map(String::from),
+            advanced_options,
+            disk_layout: options.clean_install.clone(),
+            temp_dirs: driver_dir.into_iter().collect(),
+            tools: crate::core::tools(Arc::new(SystemRunner)),
+        };
+        let first_step = first_step_name(&options);
 
         self.install_step = 1;
-        self.install_progress.current_step = "格式化分区".to_string();
+        self.install_progress.current_step = first_step.to_string();
 
         std::thread::spawn(move || {
             println!("[INSTALL THREAD] 安装线程启动");
-            
-            let temp_dir = std::env::temp_dir();
-            let driver_backup_path = temp_dir.join("LetRecovery_DriverBackup");
-            let driver_backup_str = driver_backup_path.to_string_lossy().to_string();
-
-            // Step 1: 格式化分区
-            send_step(&progress_tx, 1, "格式化分区", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            if options.format_partition {
-                println!("[INSTALL STEP 1] 开始格式化分区: {}", target_partition);
-                send_step(&progress_tx, 1, "格式化分区", 30);
-                match format_partition(&target_partition) {
-                    Ok(_) => println!("[INSTALL STEP 1] 格式化完成"),
-                    Err(e) => println!("[INSTALL STEP 1] 格式化失败: {}", e),
-                }
-                send_step(&progress_tx, 1, "格式化分区", 100);
-            } else {
-                println!("[INSTALL STEP 1] 跳过格式化");
-                send_step(&progress_tx, 1, "格式化分区", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
 
-            // Step 2: 导出驱动
-            send_step(&progress_tx, 2, "导出驱动", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            if options.export_drivers {
-                println!("[INSTALL STEP 2] 开始导出驱动到: {}", driver_backup_str);
-                send_step(&progress_tx, 2, "导出驱动", 20);
-                
-                match export_drivers(&driver_backup_str) {
-                    Ok(_) => {
-                        println!("[INSTALL STEP 2] 驱动导出成功");
-                        send_step(&progress_tx, 2, "导出驱动", 100);
+            let current_step = Mutex::new(InstallStep::FormatPartition);
+            let sink = |event: WorkflowEvent<InstallStep>| {
+                let mut current_step = current_step.lock().unwrap();
+                match event {
+                    WorkflowEvent::Step(step) => {
+                        *current_step = step;
+                        send_step(&progress_tx, step.index() + 1, direct_step_name(step, first_step), 0);
                     }
-                    Err(e) => {
-                        println!("[INSTALL STEP 2] 驱动导出失败: {} (继续安装)", e);
-                        send_step(&progress_tx, 2, "导出驱动", 100);
+                    WorkflowEvent::Progress(p) => {
+                        let step = *current_step;
+                        send_step(&progress_tx, step.index() + 1, direct_step_name(step, first_step), p);
                     }
-                }
-            } else {
-                println!("[INSTALL STEP 2] 跳过导出驱动");
-                send_step(&progress_tx, 2, "导出驱动", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 3: 释放系统镜像
-            send_step(&progress_tx, 3, "释放系统镜像", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            println!("[INSTALL STEP 3] 开始释放系统镜像");
-
-            let image_lower = image_path.to_lowercase();
-            let is_gho = image_lower.ends_with(".gho") || image_lower.ends_with(".ghs");
-
-            if is_gho {
-                println!("[INSTALL STEP 3] 检测到 GHO 镜像，使用 Ghost 恢复");
-                
-                let ghost = Ghost::new();
-                
-                if !ghost.is_available() {
-                    println!("[INSTALL STEP 3] 错误: Ghost 可执行文件不存在");
-                    send_step(&progress_tx, 3, "释放系统镜像", 100);
-                } else {
-                    let ghost_tx = progress_tx.clone();
-                    let (inner_tx, inner_rx) = mpsc::channel::<DismProgress>();
-                    
-                    std::thread::spawn(move || {
-                        while let Ok(p) = inner_rx.recv() {
-                            let _ = ghost_tx.send(p);
-                        }
-                    });
-                    
-                    match ghost.restore_image_to_letter(&image_path, &target_partition, &partitions, Some(inner_tx)) {
-                        Ok(_) => println!("[INSTALL STEP 3] Ghost 镜像恢复成功"),
-                        Err(e) => println!("[INSTALL STEP 3] Ghost 镜像恢复失败: {}", e),
-                    }
-                }
-                
-                send_step(&progress_tx, 3, "释放系统镜像", 100);
-            } else {
-                println!("[INSTALL STEP 3] 使用 DISM 应用 WIM/ESD 镜像");
-                let dism = crate::core::dism::Dism::new();
-                let apply_dir = format!("{}\\", target_partition);
-                
-                let step_tx = progress_tx.clone();
-                let (inner_tx, inner_rx) = mpsc::channel::<DismProgress>();
-                
-                std::thread::spawn(move || {
-                    while let Ok(p) = inner_rx.recv() {
-                        let _ = step_tx.send(DismProgress {
-                            percentage: p.percentage,
-                            status: "STEP:3:释放系统镜像".to_string(),
+                    WorkflowEvent::Status(status) => println!("[INSTALL] {}", status),
+                    WorkflowEvent::Warning(warning) => println!("[INSTALL] {} (继续安装)", warning),
+                    WorkflowEvent::Completed => println!("[INSTALL] 安装完成!"),
+                    WorkflowEvent::Failed(error) => {
+                        println!("[INSTALL] {}", error);
+                        let _ = progress_tx.send(DismProgress {
+                            percentage: 0,
+                            status: format!("ERROR:{}", error),
                         });
                     }
-                });
-                
-                match dism.apply_image(&image_path, &apply_dir, volume_index, Some(inner_tx)) {
-                    Ok(_) => println!("[INSTALL STEP 3] DISM 镜像释放成功"),
-                    Err(e) => println!("[INSTALL STEP 3] DISM 镜像释放失败: {}", e),
-                }
-                send_step(&progress_tx, 3, "释放系统镜像", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 4: 导入驱动
-            send_step(&progress_tx, 4, "导入驱动", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            if options.export_drivers && driver_backup_path.exists() {
-                println!("[INSTALL STEP 4] 开始导入驱动");
-                send_step(&progress_tx, 4, "导入驱动", 30);
-                
-                match import_drivers(&target_partition, &driver_backup_str) {
-                    Ok(_) => {
-                        println!("[INSTALL STEP 4] 驱动导入成功");
-                        let _ = std::fs::remove_dir_all(&driver_backup_path);
-                        send_step(&progress_tx, 4, "导入驱动", 100);
-                    }
-                    Err(e) => {
-                        println!("[INSTALL STEP 4] 驱动导入失败: {}", e);
-                        let _ = std::fs::remove_dir_all(&driver_backup_path);
-                        send_step(&progress_tx, 4, "导入驱动", 100);
-                    }
                 }
-            } else {
-                println!("[INSTALL STEP 4] 跳过导入驱动");
-                send_step(&progress_tx, 4, "导入驱动", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 5: 修复引导
-            send_step(&progress_tx, 5, "修复引导", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            if options.repair_boot {
-                println!("[INSTALL STEP 5] 开始修复引导");
-                send_step(&progress_tx, 5, "修复引导", 20);
-                
-                let use_uefi = match options.boot_mode {
-                    BootModeSelection::UEFI => true,
-                    BootModeSelection::Legacy => false,
-                    BootModeSelection::Auto => matches!(partition_style, PartitionStyle::GPT),
-                };
-                
-                println!("[INSTALL STEP 5] 引导模式: {}", if use_uefi { "UEFI" } else { "Legacy" });
-                send_step(&progress_tx, 5, "修复引导", 50);
-                
-                let boot_manager = crate::core::bcdedit::BootManager::new();
-                match boot_manager.repair_boot_advanced(&target_partition, use_uefi) {
-                    Ok(_) => println!("[INSTALL STEP 5] 引导修复成功"),
-                    Err(e) => println!("[INSTALL STEP 5] 引导修复失败: {}", e),
-                }
-                send_step(&progress_tx, 5, "修复引导", 100);
-            } else {
-                println!("[INSTALL STEP 5] 跳过修复引导");
-                send_step(&progress_tx, 5, "修复引导", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 6: 应用高级选项
-            send_step(&progress_tx, 6, "应用高级选项", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            println!("[INSTALL STEP 6] 应用高级选项");
-            send_step(&progress_tx, 6, "应用高级选项", 20);
-            
-            match advanced_options.apply_to_system(&target_partition) {
-                Ok(_) => println!("[INSTALL STEP 6] 高级选项应用成功"),
-                Err(e) => println!("[INSTALL STEP 6] 高级选项应用失败: {}", e),
-            }
-            send_step(&progress_tx, 6, "应用高级选项", 50);
-            
-            if options.unattended_install {
-                println!("[INSTALL STEP 6] 生成无人值守配置");
-                match generate_unattend_xml(&target_partition, &advanced_options) {
-                    Ok(_) => println!("[INSTALL STEP 6] 无人值守配置生成成功"),
-                    Err(e) => println!("[INSTALL STEP 6] 无人值守配置生成失败: {}", e),
-                }
-            }
-            send_step(&progress_tx, 6, "应用高级选项", 100);
-            std::thread::sleep(std::time::Duration::from_millis(100));
+            };
 
-            // Step 7: 完成
-            send_step(&progress_tx, 7, "完成安装", 100);
-            println!("[INSTALL STEP 7] 安装完成!");
+            let _ = run_install(&plan, &backend, &sink, None);
             println!("[INSTALL] ========== 安装结束 ==========");
         });
     }
@@ -438,7 +325,12 @@ impl App {
         let image_path = self.install_image_path.clone();
         let volume_index = self.install_volume_index;
         let options = self.install_options.clone();
-        let advanced_options = self.advanced_options.clone();
+        let advanced_options = self.install_options.advanced_options.clone();
+        let profile = if self.profile_names.contains(&self.profile_name) {
+            self.profile_name.clone()
+        } else {
+            String::new()
+        };
         
         // 获取选中的PE信息
         let pe_info = self.selected_pe_for_install.and_then(|idx| {
@@ -570,6 +462,26 @@ impl App {
             let is_gho = image_path.to_lowercase().ends_with(".gho") 
                 || image_path.to_lowercase().ends_with(".ghs");
             
+            // 用户应答文件复制到数据目录，供PE端合并
+            let mut unattend_file = String::new();
+            if let Some(user_file) = advanced_options.user_unattend_file() {
+                let target = format!("{}\\{}", data_dir, ConfigFileManager::USER_UNATTEND);
+                match std::fs::copy(user_file, &target) {
+                    Ok(_) => unattend_file = ConfigFileManager::USER_UNATTEND.to_string(),
+                    Err(e) => println!("[INSTALL PE STEP 5] 复制应答文件失败: {}", e),
+                }
+            }
+
+            // 装机软件暂存到数据目录，PE端部署后复制到目标系统
+            let software_items = advanced_options.software_items();
+            let mut install_software = false;
+            if !software_items.is_empty() {
+                match software::stage(&software_items, Path::new(&data_dir)) {
+                    Ok(_) => install_software = true,
+                    Err(e) => println!("[INSTALL PE STEP 5] 复制装机软件失败: {}", e),
+                }
+            }
+
             let install_config = InstallConfig {
                 unattended: options.unattended_install,
                 restore_drivers: options.export_drivers,
@@ -587,12 +499,27 @@ impl App {
                 disable_reserved_storage: advanced_options.disable_reserved_storage,
                 disable_uac: advanced_options.disable_uac,
                 disable_device_encryption: advanced_options.disable_device_encryption,
+                bypass_win11_check: advanced_options.bypass_win11_check,
                 remove_uwp_apps: advanced_options.remove_uwp_apps,
                 custom_username: if advanced_options.custom_username {
                     advanced_options.username.clone()
                 } else {
                     String::new()
                 },
+                ui_language: advanced_options.ui_language.clone(),
+                input_locale: advanced_options.input_locale.clone(),
+                system_locale: advanced_options.system_locale.clone(),
+                time_zone: advanced_options.time_zone.clone(),
+                computer_name: advanced_options.computer_name.clone(),
+                product_key: advanced_options.product_key.clone(),
+                organization: advanced_options.organization.clone(),
+                accounts: advanced_options.local_accounts(),
+                disable_auto_logon: advanced_options.disable_auto_logon,
+                builtin_administrator: advanced_options.builtin_administrator,
+                administrator_password: advanced_options.administrator_password(),
+                unattend_file,
+                install_software,
+                profile,
             };
             
             match ConfigFileManager::write_install_config(&target_partition, &data_partition, &install_config) {
@@ -618,6 +545,24 @@ impl App {
     }
 }
 
+/// 直接安装第一步的名称
+fn first_step_name(options: &InstallOptions) -> &'static str {
+    if options.clean_install.is_some() {
+        "分区磁盘"
+    } else {
+        "格式化分区"
+    }
+}
+
+/// 直接安装步骤在界面上的名称，整盘安装时第一步为分区磁盘
+fn direct_step_name(step: InstallStep, first_step: &'static str) -> &'static str {
+    if step == InstallStep::FormatPartition {
+        first_step
+    } else {
+        step.name()
+    }
+}
+
 /// 发送步骤消息
 fn send_step(tx: &mpsc::Sender<DismProgress>, step: usize, name: &str, percentage: u8) {
     let _ = tx.send(DismProgress {
@@ -639,208 +584,43 @@ fn parse_step_from_status(status: &str) -> Option<(usize, String)> {
     None
 }
 
-/// 格式化分区
-fn format_partition(partition: &str) -> anyhow::Result<()> {
-    use crate::utils::cmd::create_command;
-    
-    println!("[FORMAT] 格式化分区: {}", partition);
-    
-    let output = create_command("cmd")
-        .args(["/c", &format!("format {} /FS:NTFS /Q /Y", partition)])
-        .output()?;
-    
-    let stdout = crate::utils::encoding::gbk_to_utf8(&output.stdout);
-    let stderr = crate::utils::encoding::gbk_to_utf8(&output.stderr);
-    
-    println!("[FORMAT] stdout: {}", stdout);
-    println!("[FORMAT] stderr: {}", stderr);
-    
-    if !output.status.success() {
-        anyhow::bail!("格式化失败: {}", stderr);
-    }
-    
-    Ok(())
-}
-
-/// 导出驱动
-fn export_drivers(destination: &str) -> anyhow::Result<()> {
-    println!("[DRIVER EXPORT] 目标路径: {}", destination);
-    
-    if Path::new(destination).exists() {
-        let _ = std::fs::remove_dir_all(destination);
-    }
-    
-    std::fs::create_dir_all(destination)?;
-    
-    let dism = crate::core::dism::Dism::new();
-    
-    if dism.is_pe_environment() {
-        println!("[DRIVER EXPORT] PE 环境，查找现有 Windows 系统...");
-        
-        for letter in ['C', 'D', 'E', 'F', 'G'] {
-            let windows_path = format!("{}:\\Windows\\System32\\drivers", letter);
-            if Path::new(&windows_path).exists() {
-                println!("[DRIVER EXPORT] 尝试从 {}: 导出驱动", letter);
-                let source = format!("{}:\\", letter);
-                match dism.export_drivers_from_system(&source, destination) {
-                    Ok(_) => {
-                        println!("[DRIVER EXPORT] 成功从 {}: 导出驱动", letter);
-                        return Ok(());
-                    }
-                    Err(e) => {
-                        println!("[DRIVER EXPORT] 从 {}: 导出失败: {}", letter, e);
-                    }
-                }
-            }
-        }
-        
-        anyhow::bail!("PE 环境下未找到可用的 Windows 系统来导出驱动")
-    } else {
-        println!("[DRIVER EXPORT] 桌面环境，使用在线模式导出");
-        dism.export_drivers(destination)
-    }
-}
-
-/// 导入驱动到目标系统
-fn import_drivers(target_partition: &str, driver_path: &str) -> anyhow::Result<()> {
-    println!("[DRIVER IMPORT] 目标分区: {}, 驱动路径: {}", target_partition, driver_path);
-    
-    let dism = crate::core::dism::Dism::new();
-    let image_path = format!("{}\\", target_partition);
-    
-    dism.add_drivers_offline(&image_path, driver_path)
-}
-
 /// 生成无人值守 XML 文件
-fn generate_unattend_xml(target_partition: &str, options: &AdvancedOptions) -> anyhow::Result<()> {
+///
+/// `answer_options` 中的架构会按目标分区中的系统重新检测。
+/// 指定 `user_file` 时只把必需的命令合并到用户提供的应答文件中，其余设置
+/// 保持不变，返回合并冲突。
+pub fn generate_unattend_xml(
+    target_partition: &str,
+    mut answer_options: AnswerFileOptions,
+    user_file: Option<&str>,
+) -> anyhow::Result<Vec<MergeConflict>> {
     println!("[UNATTEND] 生成无人值守配置文件");
-    
-    let username = if options.custom_username && !options.username.is_empty() {
-        options.username.clone()
-    } else {
-        "User".to_string()
-    };
 
-    // 构建 FirstLogonCommands
-    let mut first_logon_commands = String::new();
-    let mut order = 1;
-
-    // 首次登录脚本
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c if exist %SystemDrive%\LetRecovery_Scripts\firstlogon.bat call %SystemDrive%\LetRecovery_Scripts\firstlogon.bat</CommandLine>
-                    <Description>Run first login script</Description>
-                </SynchronousCommand>"#, order));
-    order += 1;
-
-    // 如果需要删除UWP应用
-    if options.remove_uwp_apps {
-        first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>powershell -ExecutionPolicy Bypass -File %SystemDrive%\LetRecovery_Scripts\remove_uwp.ps1</CommandLine>
-                    <Description>Remove preinstalled UWP apps</Description>
-                </SynchronousCommand>"#, order));
-        order += 1;
-    }
+    answer_options.architecture = Architecture::detect_from_system(target_partition).unwrap_or_else(|| {
+        println!("[UNATTEND] 无法识别目标系统架构，使用默认值 amd64");
+        Architecture::default()
+    });
+    println!("[UNATTEND] 目标系统架构: {}", answer_options.architecture);
 
-    // 清理脚本目录（最后执行）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c rd /s /q %SystemDrive%\LetRecovery_Scripts</CommandLine>
-                    <Description>Cleanup scripts directory</Description>
-                </SynchronousCommand>"#, order));
-    
-    let xml_content = format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\LetRecovery_Scripts\deploy.bat call %SystemDrive%\LetRecovery_Scripts\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOEMRegistrationScreen>true</HideOEMRegistrationScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-                <SkipMachineOOBE>true</SkipMachineOOBE>
-                <SkipUserOOBE>true</SkipUserOOBE>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, username = username, first_logon_commands = first_logon_commands);
-
-    let panther_dir = format!("{}\\Windows\\Panther", target_partition);
-    std::fs::create_dir_all(&panther_dir)?;
-    
-    let unattend_path = format!("{}\\unattend.xml", panther_dir);
-    std::fs::write(&unattend_path, &xml_content)?;
-    println!("[UNATTEND] 已写入: {}", unattend_path);
-    
-    let sysprep_dir = format!("{}\\Windows\\System32\\Sysprep", target_partition);
-    if Path::new(&sysprep_dir).exists() {
-        let sysprep_unattend = format!("{}\\unattend.xml", sysprep_dir);
-        let _ = std::fs::write(&sysprep_unattend, &xml_content);
-        println!("[UNATTEND] 已写入: {}", sysprep_unattend);
+    if let Some(path) = user_file {
+        println!("[UNATTEND] 合并用户应答文件: {}", path);
     }
-    
-    Ok(())
+    let rendered = render_answer_file(&answer_options, user_file)?;
+    for warning in &rendered.warnings {
+        println!("[UNATTEND] {}", warning);
+    }
+    write_answer_file(target_partition, &rendered.xml)?;
+    println!("[UNATTEND] 无人值守配置已写入: {}", target_partition);
+
+    Ok(rendered.conflicts)
 }
 
 /// 查找可用的数据分区（非系统分区）
 /// 返回 (分区盘符, 是否自动创建)
 fn find_data_partition(exclude_partition: &str, image_path: &str) -> Result<(String, bool), String> {
     use crate::core::disk::DiskManager;
+    use letrecovery_common::diskpart::script::RunMode;
+    use letrecovery_common::preflight::{self, Severity};
     
     // 获取镜像文件大小
     let image_size = match std::fs::metadata(image_path) {
@@ -856,9 +636,19 @@ fn find_data_partition(exclude_partition: &str, image_path: &str) -> Result<(Str
     );
 
     // 调用 DiskManager 的新函数
-    match DiskManager::find_suitable_data_partition(exclude_partition, image_size) {
-        Ok(Some((partition, is_auto_created))) => {
+    match DiskManager::new().find_suitable_data_partition(exclude_partition, image_size, RunMode::Execute) {
+        Ok(Some((partition, created))) => {
+            let is_auto_created = created.is_some();
             println!("[DATA PARTITION] 选择分区: {}, 自动创建: {}", partition, is_auto_created);
+            // PE 中会格式化目标分区，数据分区不能与之相同
+            let location = preflight::check_data_location(
+                partition.chars().next(),
+                exclude_partition.chars().next().unwrap_or('C'),
+                true,
+            );
+            if location.severity == Severity::Blocking {
+                return Err(location.message);
+            }
             Ok((partition, is_auto_created))
         }
         Ok(None) => {
@@ -1,9 +1,14 @@
 pub mod about;
 pub mod advanced_options;
+pub mod backup_catalog;
+pub mod backup_exclusions;
+pub mod backup_schedule;
+pub mod boot_menu;
 pub mod download_progress;
 pub mod hardware_info;
 pub mod install_progress;
 pub mod online_download;
+pub mod profile;
 pub mod system_backup;
 pub mod system_install;
 pub mod tools;
@@ -0,0 +1,220 @@
+use egui;
+
+use crate::app::App;
+use crate::core::profile::DeploymentProfile;
+
+impl App {
+    /// 部署方案栏：选择、保存、删除、设为默认、导入导出
+    pub fn show_profile_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("部署方案:");
+
+            let mut selected = None;
+            egui::ComboBox::from_id_salt("profile_select")
+                .selected_text("选择方案")
+                .show_ui(ui, |ui| {
+                    if self.profile_names.is_empty() {
+                        ui.label("还没有保存的方案");
+                    }
+                    for name in &self.profile_names {
+                        let label = if self.default_profile.as_ref() == Some(name) {
+                            format!("{} (默认)", name)
+                        } else {
+                            name.clone()
+                        };
+                        if ui.selectable_label(*name == self.profile_name, label).clicked() {
+                            selected = Some(name.clone());
+                        }
+                    }
+                });
+            if let Some(name) = selected {
+                self.load_profile(&name);
+            }
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.profile_name)
+                    .desired_width(120.0)
+                    .hint_text("方案名称"),
+            );
+
+            let has_name = !self.profile_name.trim().is_empty();
+            if ui.add_enabled(has_name, egui::Button::new("保存")).clicked() {
+                let profile = self.current_profile();
+                let result = profile.save();
+                self.report_profile_result(result, format!("已保存方案: {}", profile.name));
+            }
+
+            let is_saved = self.profile_names.contains(&self.profile_name);
+            let is_default = self.default_profile.as_ref() == Some(&self.profile_name);
+            if ui
+                .add_enabled(is_saved, egui::Button::new(if is_default { "取消默认" } else { "设为默认" }))
+                .clicked()
+            {
+                let name = self.profile_name.clone();
+                let (result, message) = if is_default {
+                    (DeploymentProfile::set_default(None), "已取消默认方案".to_string())
+                } else {
+                    (
+                        DeploymentProfile::set_default(Some(&name)),
+                        format!("启动时将自动应用方案: {}", name),
+                    )
+                };
+                self.report_profile_result(result, message);
+            }
+
+            if ui.add_enabled(is_saved, egui::Button::new("删除")).clicked() {
+                let name = self.profile_name.clone();
+                let result = DeploymentProfile::delete(&name);
+                self.report_profile_result(result, format!("已删除方案: {}", name));
+            }
+
+            if ui.button("导入...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("部署方案", &["json"])
+                    .pick_file()
+                {
+                    match DeploymentProfile::import(&path) {
+                        Ok(profile) => {
+                            let name = profile.name.clone();
+                            self.apply_profile(profile);
+                            self.report_profile_result(Ok(()), format!("已导入方案: {}", name));
+                        }
+                        Err(e) => self.report_profile_result(Err(e), String::new()),
+                    }
+                }
+            }
+
+            if ui.button("导出...").clicked() {
+                let profile = self.current_profile();
+                let file_name = if has_name {
+                    format!("{}.json", profile.name)
+                } else {
+                    "profile.json".to_string()
+                };
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("部署方案", &["json"])
+                    .set_file_name(&file_name)
+                    .save_file()
+                {
+                    let result = profile.export(&path);
+                    self.report_profile_result(result, format!("已导出到: {}", path.display()));
+                }
+            }
+        });
+
+        if let Some(message) = &self.profile_message {
+            ui.label(egui::RichText::new(message).small().weak());
+        }
+
+        if self.profile_missing_passwords {
+            let missing = self.advanced_options.accounts_missing_password();
+            if missing.is_empty() {
+                self.profile_missing_passwords = false;
+            } else {
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 165, 0),
+                    format!(
+                        "⚠ 方案不保存密码，请在高级选项中为以下账户重新输入密码: {}",
+                        missing.join("、")
+                    ),
+                );
+            }
+        }
+    }
+
+    /// 刷新方案列表并应用默认方案（启动时调用）
+    pub fn load_default_profile(&mut self) {
+        self.refresh_profiles();
+        if let Some(profile) = DeploymentProfile::load_default() {
+            println!("[PROFILE] 应用默认方案: {}", profile.name);
+            self.profile_message = Some(format!("已应用默认方案: {}", profile.name));
+            self.apply_profile(profile);
+        }
+    }
+
+    fn load_profile(&mut self, name: &str) {
+        match DeploymentProfile::load(name) {
+            Ok(profile) => {
+                self.profile_message = Some(format!("已应用方案: {}", name));
+                self.apply_profile(profile);
+            }
+            Err(e) => self.profile_message = Some(format!("读取方案失败: {:#}", e)),
+        }
+    }
+
+    /// 用方案中的设置替换界面上的安装选项
+    ///
+    /// 方案不含账户密码，应用后需要时重新填写。
+    pub fn apply_profile(&mut self, profile: DeploymentProfile) {
+        self.profile_name = profile.name;
+        self.format_partition = profile.format_partition;
+        self.repair_boot = profile.repair_boot;
+        self.unattended_install = profile.unattended_install;
+        self.export_drivers = profile.export_drivers;
+        self.auto_reboot = profile.auto_reboot;
+        self.selected_boot_mode = profile.boot_mode;
+        self.advanced_options = profile.advanced_options;
+        self.backup_exclusions = profile.backup_exclusions;
+        self.profile_missing_passwords = !self.advanced_options.accounts_missing_password().is_empty();
+
+        if profile.image_path.is_empty() {
+            return;
+        }
+        if profile.image_path == self.local_image_path && !self.image_volumes.is_empty() {
+            self.select_volume_by_index(profile.volume_index);
+        } else if !self.iso_mounting && !self.image_info_loading {
+            self.local_image_path = profile.image_path;
+            self.iso_mount_error = None;
+            self.pending_volume_index = Some(profile.volume_index);
+            self.load_image_volumes();
+        }
+    }
+
+    /// 用界面上的当前设置生成方案
+    pub fn current_profile(&self) -> DeploymentProfile {
+        let volume_index = self
+            .selected_volume
+            .and_then(|i| self.image_volumes.get(i).map(|v| v.index))
+            .unwrap_or(1);
+
+        DeploymentProfile {
+            name: self.profile_name.trim().to_string(),
+            image_path: self.local_image_path.clone(),
+            volume_index,
+            boot_mode: self.selected_boot_mode,
+            format_partition: self.format_partition,
+            repair_boot: self.repair_boot,
+            unattended_install: self.unattended_install,
+            export_drivers: self.export_drivers,
+            auto_reboot: self.auto_reboot,
+            advanced_options: self.advanced_options.clone(),
+            backup_exclusions: self.backup_exclusions.clone(),
+        }
+    }
+
+    /// 镜像信息加载完成后选中方案指定的卷
+    pub fn apply_pending_volume_index(&mut self) {
+        if let Some(index) = self.pending_volume_index.take() {
+            self.select_volume_by_index(index);
+        }
+    }
+
+    fn select_volume_by_index(&mut self, index: u32) {
+        if let Some(i) = self.image_volumes.iter().position(|v| v.index == index) {
+            self.selected_volume = Some(i);
+        }
+    }
+
+    fn refresh_profiles(&mut self) {
+        self.profile_names = DeploymentProfile::list();
+        self.default_profile = DeploymentProfile::default_name();
+    }
+
+    fn report_profile_result(&mut self, result: anyhow::Result<()>, message: String) {
+        self.profile_message = Some(match result {
+            Ok(()) => message,
+            Err(e) => format!("操作失败: {:#}", e),
+        });
+        self.refresh_profiles();
+    }
+}
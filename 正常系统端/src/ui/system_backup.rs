@@ -1,10 +1,20 @@
 use egui;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::path::Path;
 
+use letrecovery_common::backup::compression::{BackupEstimate, BackupFormat, Compression};
+use letrecovery_common::command::SystemRunner;
+use letrecovery_common::workflow::{run_backup, BackupPlan, BackupStep, WorkflowEvent};
+
 use crate::app::{App, BackupMode, Panel};
-use crate::core::dism::{Dism, DismProgress};
-use crate::core::install_config::{BackupConfig, ConfigFileManager};
+use crate::core::dism::DismProgress;
+use crate::core::install_config::BackupConfig;
+use crate::workflow::{queue_pe_backup, DesktopBackupBackend};
+
+/// 通过进度通道传递警告时状态文本的前缀
+const WARNING_PREFIX: &str = "警告: ";
+/// 通过进度通道传递验证报告路径时状态文本的前缀
+const REPORT_PREFIX: &str = "验证报告: ";
 
 impl App {
     pub fn show_system_backup(&mut self, ui: &mut egui::Ui) {
@@ -24,6 +34,8 @@ impl App {
         
         // 备份按钮是否可用
         let backup_blocked = show_pe_selector && !pe_available;
+        // 去重仓库不保存权限、硬链接等系统所需的信息
+        let dedup_blocked = self.backup_format.is_dedup() && self.backup_source_has_windows();
 
         // 选择要备份的分区
         ui.label("选择要备份的分区:");
@@ -94,14 +106,22 @@ impl App {
                 egui::TextEdit::singleline(&mut self.backup_save_path).desired_width(400.0),
             );
             if ui.button("浏览...").clicked() {
-                if let Some(path) = rfd::FileDialog::new()
-                    .add_filter("WIM镜像", &["wim"])
-                    .set_file_name("backup.wim")
-                    .save_file()
-                {
-                    self.backup_save_path = path.to_string_lossy().to_string();
-                    // 如果保存位置的文件存在，自动勾选增量备份；否则取消勾选
-                    self.backup_incremental = Path::new(&self.backup_save_path).exists();
+                if self.backup_format.is_dedup() {
+                    // 仓库保存在文件夹中
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        self.backup_save_path = folder.to_string_lossy().to_string();
+                    }
+                } else {
+                    let extension = self.backup_format.extension();
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter(format!("{}镜像", extension.to_uppercase()), &[extension])
+                        .set_file_name(format!("backup.{}", extension))
+                        .save_file()
+                    {
+                        self.backup_save_path = path.to_string_lossy().to_string();
+                        // 如果保存位置的文件存在，自动勾选增量备份；否则取消勾选
+                        self.backup_incremental = Path::new(&self.backup_save_path).exists();
+                    }
                 }
             }
         });
@@ -125,7 +145,25 @@ impl App {
         ui.add_space(15.0);
 
         // 备份选项
-        ui.checkbox(&mut self.backup_incremental, "增量备份 (追加到现有镜像)");
+        self.show_backup_format(ui);
+        if self.backup_format.is_gho() {
+            self.show_backup_estimate(ui);
+        } else if self.backup_format.is_dedup() {
+            ui.checkbox(&mut self.backup_verify, "备份后验证 (校验本次备份引用的全部数据块)");
+            self.show_backup_estimate(ui);
+            self.show_backup_retention(ui);
+            self.show_backup_exclusions(ui);
+            self.show_backup_catalog(ui);
+        } else {
+            ui.checkbox(&mut self.backup_incremental, "增量备份 (追加到现有镜像)");
+            ui.checkbox(&mut self.backup_verify, "备份后验证 (校验镜像并与源分区比较，报告保存在镜像旁)");
+            self.show_backup_compression(ui);
+            self.show_backup_estimate(ui);
+            self.show_backup_retention(ui);
+            self.show_backup_exclusions(ui);
+            self.show_backup_catalog(ui);
+        }
+        self.show_backup_schedule(ui);
 
         // PE选择（仅在需要通过PE备份时显示）
         if show_pe_selector {
@@ -177,6 +215,14 @@ impl App {
             );
         }
 
+        if dedup_blocked {
+            ui.add_space(5.0);
+            ui.colored_label(
+                egui::Color32::RED,
+                "❌ 去重仓库不能备份 Windows 系统分区（不保存权限和硬链接，还原后无法启动），请使用 WIM 或 GHO 格式",
+            );
+        }
+
         // PE配置缺失警告
         if backup_blocked {
             ui.add_space(5.0);
@@ -193,6 +239,7 @@ impl App {
             && !self.backup_save_path.is_empty()
             && !self.backup_name.is_empty()
             && !backup_blocked
+            && !dedup_blocked
             && (!show_pe_selector || self.selected_pe_for_backup.is_some());
 
         ui.horizontal(|ui| {
@@ -234,6 +281,7 @@ impl App {
         if self.backup_progress >= 100 && !self.is_backing_up {
             ui.add_space(10.0);
             ui.colored_label(egui::Color32::GREEN, "✓ 备份完成！");
+            self.show_backup_verify_result(ui);
         }
 
         // 显示备份错误
@@ -268,6 +316,13 @@ impl App {
         }
     }
 
+    /// 所选源分区是否有 Windows 系统
+    fn backup_source_has_windows(&self) -> bool {
+        self.backup_source_partition
+            .and_then(|idx| self.partitions.get(idx))
+            .is_some_and(|p| p.has_windows)
+    }
+
     /// 检查是否需要通过PE备份
     fn check_if_needs_pe_for_backup(&self) -> bool {
         // 如果已经在PE环境中，不需要再进PE
@@ -358,6 +413,8 @@ impl App {
         self.is_backing_up = true;
         self.backup_progress = 0;
         self.backup_error = None;
+        self.backup_warnings.clear();
+        self.backup_report = None;
 
         match self.backup_mode {
             BackupMode::Direct => self.start_direct_backup(source_partition),
@@ -369,36 +426,164 @@ impl App {
         let (progress_tx, progress_rx) = mpsc::channel::<DismProgress>();
         self.backup_progress_rx = Some(progress_rx);
 
-        let capture_dir = format!("{}\\", source_partition.letter);
-        let image_file = self.backup_save_path.clone();
-        let name = self.backup_name.clone();
-        let description = self.backup_description.clone();
-        let is_incremental = self.backup_incremental;
+        let plan = BackupPlan {
+            source_partition: source_partition.letter.clone(),
+            save_path: self.backup_save_path.clone(),
+            name: self.backup_name.clone(),
+            description: self.backup_description.clone(),
+            format: self.backup_format,
+            incremental: self.backup_incremental,
+            verify: self.backup_verify,
+            retention: self.backup_retention,
+            exclusions: self.backup_exclusions.to_config(),
+            compression: self.backup_compression,
+            export_esd: self.backup_export_esd,
+        };
 
         std::thread::spawn(move || {
-            let dism = Dism::new();
-            
-            let result = if is_incremental && Path::new(&image_file).exists() {
-                dism.append_image(&image_file, &capture_dir, &name, &description, Some(progress_tx.clone()))
-            } else {
-                dism.capture_image(&image_file, &capture_dir, &name, &description, Some(progress_tx.clone()))
+            // 把工作流事件转换为界面使用的进度消息，完成前进度最多显示 99%
+            let current = Mutex::new((BackupStep::ReadConfig, 0u8));
+            let send = |percentage: u8, status: String| {
+                let _ = progress_tx.send(DismProgress { percentage, status });
             };
-
-            match result {
-                Ok(_) => {
-                    let _ = progress_tx.send(DismProgress {
-                        percentage: 100,
-                        status: "备份完成".to_string(),
-                    });
+            let sink = |event: WorkflowEvent<BackupStep>| {
+                let mut current = current.lock().unwrap();
+                match event {
+                    WorkflowEvent::Step(step) => {
+                        println!("[BACKUP] {}", step.name());
+                        current.0 = step;
+                    }
+                    WorkflowEvent::Progress(p) => {
+                        current.1 = current.0.overall_progress(p).min(99);
+                        send(current.1, current.0.name().to_string());
+                    }
+                    WorkflowEvent::Status(message) => {
+                        println!("[BACKUP] {}", message);
+                        if message.starts_with(REPORT_PREFIX) {
+                            send(current.1, message);
+                        }
+                    }
+                    WorkflowEvent::Warning(message) => {
+                        println!("[BACKUP] 警告: {}", message);
+                        send(current.1, format!("{}{}", WARNING_PREFIX, message));
+                    }
+                    WorkflowEvent::Completed => send(100, "备份完成".to_string()),
+                    WorkflowEvent::Failed(message) => send(0, format!("备份失败: {}", message)),
                 }
-                Err(e) => {
-                    let _ = progress_tx.send(DismProgress {
-                        percentage: 0,
-                        status: format!("备份失败: {}", e),
+            };
+            let backend = DesktopBackupBackend::with_runner(Arc::new(SystemRunner));
+            let _ = run_backup(&plan, &backend, &sink, None);
+        });
+    }
+
+    /// 备份格式，GHO 时显示 Ghost 压缩级别
+    fn show_backup_format(&mut self, ui: &mut egui::Ui) {
+        let previous = self.backup_format;
+        let source_has_windows = self.backup_source_has_windows();
+        ui.horizontal(|ui| {
+            ui.label("备份格式:");
+            egui::ComboBox::from_id_salt("backup_format")
+                .selected_text(self.backup_format.name())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.backup_format, BackupFormat::Wim, BackupFormat::Wim.name());
+                    let gho = match previous {
+                        BackupFormat::Gho(_) => previous,
+                        BackupFormat::Wim | BackupFormat::Dedup => BackupFormat::Gho(BackupFormat::DEFAULT_GHOST_LEVEL),
+                    };
+                    ui.selectable_value(&mut self.backup_format, gho, gho.name());
+                    let dedup = ui
+                        .add_enabled(
+                            !source_has_windows,
+                            egui::SelectableLabel::new(self.backup_format.is_dedup(), BackupFormat::Dedup.name()),
+                        )
+                        .on_disabled_hover_text("去重仓库不能备份 Windows 系统分区");
+                    if dedup.clicked() {
+                        self.backup_format = BackupFormat::Dedup;
+                    }
+                });
+            if let BackupFormat::Gho(level) = &mut self.backup_format {
+                ui.label("压缩级别:");
+                ui.add(egui::DragValue::new(level).range(1..=9))
+                    .on_hover_text("1 最快，9 压缩率最高");
+            }
+        });
+        if self.backup_format.is_gho() {
+            ui.label(
+                egui::RichText::new("Ghost 备份整个分区，不支持增量、排除文件、验证和保留策略")
+                    .small()
+                    .weak(),
+            );
+        } else if self.backup_format.is_dedup() {
+            ui.label(
+                egui::RichText::new("保存位置为文件夹，每次备份只保存变化的数据块，可从任意一次备份还原文件")
+                    .small()
+                    .weak(),
+            );
+        }
+
+        // 切换格式时同步保存位置的扩展名（仓库是没有扩展名的文件夹）
+        if previous.extension() != self.backup_format.extension() && !self.backup_save_path.is_empty() {
+            let path = Path::new(&self.backup_save_path);
+            let matches_previous = match path.extension() {
+                Some(ext) => ext.eq_ignore_ascii_case(previous.extension()),
+                None => previous.extension().is_empty(),
+            };
+            if matches_previous {
+                self.backup_save_path = path
+                    .with_extension(self.backup_format.extension())
+                    .to_string_lossy()
+                    .to_string();
+            }
+        }
+    }
+
+    /// 压缩方式和 ESD 导出
+    fn show_backup_compression(&mut self, ui: &mut egui::Ui) {
+        let appending = self.backup_incremental && Path::new(&self.backup_save_path).exists();
+        ui.horizontal(|ui| {
+            ui.label("压缩方式:");
+            ui.add_enabled_ui(!appending, |ui| {
+                egui::ComboBox::from_id_salt("backup_compression")
+                    .selected_text(self.backup_compression.name())
+                    .show_ui(ui, |ui| {
+                        for compression in Compression::all() {
+                            ui.selectable_value(&mut self.backup_compression, *compression, compression.name());
+                        }
                     });
-                }
+            });
+            if appending {
+                ui.label(egui::RichText::new("追加时沿用镜像原有的压缩方式").small().weak());
             }
         });
+        ui.checkbox(
+            &mut self.backup_export_esd,
+            "另存为 ESD 归档副本 (体积更小，导出较慢，保存在镜像旁)",
+        );
+    }
+
+    /// 按所选分区的已用空间预估镜像大小和耗时
+    fn show_backup_estimate(&self, ui: &mut egui::Ui) {
+        let Some(partition) = self.backup_source_partition.and_then(|i| self.partitions.get(i)) else {
+            return;
+        };
+        let used_bytes = (partition.total_size_mb - partition.free_size_mb) * 1024 * 1024;
+        let estimate = match self.backup_format {
+            BackupFormat::Wim => BackupEstimate::new(used_bytes, self.backup_compression, self.backup_export_esd),
+            BackupFormat::Gho(level) => BackupEstimate::for_ghost(used_bytes, level),
+            // 首次备份保存全部数据（不压缩），之后只保存变化的部分
+            BackupFormat::Dedup => BackupEstimate::new(used_bytes, Compression::None, false),
+        };
+        ui.label(egui::RichText::new(estimate.describe()).small().weak());
+    }
+
+    /// 显示验证报告路径和验证中发现的问题
+    fn show_backup_verify_result(&self, ui: &mut egui::Ui) {
+        if let Some(ref report) = self.backup_report {
+            ui.label(format!("验证报告: {}", report));
+        }
+        for warning in &self.backup_warnings {
+            ui.colored_label(egui::Color32::from_rgb(255, 165, 0), format!("⚠ {}", warning));
+        }
     }
 
     fn start_pe_backup(&mut self, source_partition: crate::core::disk::Partition) {
@@ -412,18 +597,18 @@ impl App {
         let name = self.backup_name.clone();
         let description = self.backup_description.clone();
         let is_incremental = self.backup_incremental;
+        let is_verify = self.backup_verify;
+        let retention = self.backup_retention;
+        let exclusions = self.backup_exclusions.to_config();
+        let compression = self.backup_compression;
+        let format = self.backup_format;
+        let export_esd = self.backup_export_esd;
         
         let pe_info = self.selected_pe_for_backup.and_then(|idx| {
             self.config.as_ref().and_then(|c| c.pe_list.get(idx).cloned())
         });
 
         std::thread::spawn(move || {
-            // Step 1: 检查PE
-            let _ = progress_tx.send(DismProgress {
-                percentage: 10,
-                status: "检查PE环境".to_string(),
-            });
-            
             let pe_info = match pe_info {
                 Some(pe) => pe,
                 None => {
@@ -434,60 +619,36 @@ impl App {
                     return;
                 }
             };
-            
-            let (pe_exists, pe_path) = crate::core::pe::PeManager::check_pe_exists(&pe_info.filename);
-            if !pe_exists {
-                let _ = progress_tx.send(DismProgress {
-                    percentage: 0,
-                    status: format!("备份失败: PE文件不存在 {}", pe_info.filename),
-                });
-                return;
-            }
 
-            // Step 2: 安装PE引导
-            let _ = progress_tx.send(DismProgress {
-                percentage: 30,
-                status: "安装PE引导".to_string(),
-            });
-            
-            let pe_manager = crate::core::pe::PeManager::new();
-            if let Err(e) = pe_manager.boot_to_pe(&pe_path, &pe_info.display_name) {
-                let _ = progress_tx.send(DismProgress {
-                    percentage: 0,
-                    status: format!("备份失败: PE引导安装失败 {}", e),
-                });
-                return;
-            }
-
-            // Step 3: 写入配置文件
-            let _ = progress_tx.send(DismProgress {
-                percentage: 60,
-                status: "写入配置文件".to_string(),
-            });
-            
-            // 找数据分区
-            let data_partition = find_backup_data_partition(&source_letter);
-            
             let backup_config = BackupConfig {
-                save_path: save_path.clone(),
-                name: name.clone(),
-                description: description.clone(),
-                source_partition: source_letter.clone(),
+                save_path,
+                name,
+                description,
+                source_partition: source_letter,
+                format,
                 incremental: is_incremental,
+                verify: is_verify,
+                retention,
+                exclusions,
+                compression,
+                export_esd,
             };
-            
-            if let Err(e) = ConfigFileManager::write_backup_config(&source_letter, &data_partition, &backup_config) {
-                let _ = progress_tx.send(DismProgress {
-                    percentage: 0,
-                    status: format!("备份失败: 配置文件写入失败 {}", e),
-                });
-                return;
-            }
 
-            // Step 4: 完成
-            let _ = progress_tx.send(DismProgress {
-                percentage: 100,
-                status: "PE备份准备完成".to_string(),
+            let result = queue_pe_backup(
+                &pe_info.filename,
+                &pe_info.display_name,
+                &backup_config,
+                Some(progress_tx.clone()),
+            );
+            let _ = progress_tx.send(match result {
+                Ok(()) => DismProgress {
+                    percentage: 100,
+                    status: "PE备份准备完成".to_string(),
+                },
+                Err(e) => DismProgress {
+                    percentage: 0,
+                    status: format!("备份失败: {}", e),
+                },
             });
             
             println!("[BACKUP PE] ========== PE备份准备结束 ==========");
@@ -505,6 +666,14 @@ impl App {
 
         if let Some(ref rx) = self.backup_progress_rx {
             while let Ok(progress) = rx.try_recv() {
+                if let Some(warning) = progress.status.strip_prefix(WARNING_PREFIX) {
+                    self.backup_warnings.push(warning.to_string());
+                    continue;
+                }
+                if let Some(report) = progress.status.strip_prefix(REPORT_PREFIX) {
+                    self.backup_report = Some(report.to_string());
+                    continue;
+                }
                 latest_progress = Some(progress.percentage);
                 
                 if progress.percentage >= 100 {
@@ -527,6 +696,8 @@ impl App {
         if should_finish {
             self.is_backing_up = false;
             self.backup_progress_rx = None;
+            // 直接备份后镜像中的卷已变化，下次显示时重新读取
+            self.backup_catalog_path.clear();
         }
     }
 
@@ -572,6 +743,7 @@ impl App {
             match self.backup_mode {
                 BackupMode::Direct => {
                     ui.colored_label(egui::Color32::GREEN, "备份完成！");
+                    self.show_backup_verify_result(ui);
                     ui.add_space(10.0);
                     if ui.button("返回").clicked() {
                         self.current_panel = Panel::SystemBackup;
@@ -602,50 +774,3 @@ impl App {
         }
     }
 }
-
-/// 查找可用的备份数据分区
-fn find_backup_data_partition(exclude_partition: &str) -> String {
-    use crate::core::disk::DiskManager;
-    
-    let exclude_letter = exclude_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
-    
-    // 遍历 A-Z 查找可用的固定磁盘分区
-    for letter in b'A'..=b'Z' {
-        let c = letter as char;
-        
-        // 跳过排除的分区
-        if c == exclude_letter {
-            continue;
-        }
-        
-        // 跳过 X 盘（PE 系统盘）
-        if c == 'X' {
-            continue;
-        }
-        
-        let partition_path = format!("{}:\\", c);
-        if !Path::new(&partition_path).exists() {
-            continue;
-        }
-        
-        // 检查是否为光驱
-        if DiskManager::is_cdrom(c) {
-            continue;
-        }
-        
-        // 检查是否为固定磁盘
-        if !DiskManager::is_fixed_drive(c) {
-            continue;
-        }
-        
-        // 检查是否有足够空间（至少 100MB 用于配置文件）
-        if let Some(free_space) = DiskManager::get_free_space_bytes(&format!("{}:", c)) {
-            if free_space >= 100 * 1024 * 1024 {
-                return format!("{}:", c);
-            }
-        }
-    }
-    
-    // 如果没找到合适的，使用 C 盘
-    "C:".to_string()
-}
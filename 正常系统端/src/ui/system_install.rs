@@ -2,8 +2,16 @@ use egui;
 use std::sync::mpsc;
 
 use crate::app::{App, BootModeSelection, InstallMode};
-use crate::core::disk::{Partition, PartitionStyle};
+use crate::core::disk::{DiskManager, Partition, PartitionStyle};
 use crate::core::dism::ImageInfo;
+use crate::core::preflight::{InstallPreflight, PreflightTarget};
+use letrecovery_common::disk_layout::{
+    DiskLayout, Firmware, ESP_SIZE_RANGE, MSR_SIZE_RANGE, RECOVERY_SIZE_RANGE,
+    SYSTEM_RESERVED_SIZE_RANGE,
+};
+use letrecovery_common::diskpart::script::RunMode;
+use letrecovery_common::diskpart::DiskEntry;
+use letrecovery_common::preflight::Severity;
 
 /// ISO 挂载结果
 pub enum IsoMountResult {
@@ -22,6 +30,9 @@ impl App {
         ui.heading("系统安装");
         ui.separator();
 
+        self.show_profile_bar(ui);
+        ui.add_space(5.0);
+
         let is_pe = self.is_pe_environment();
         
         // 判断是否需要通过PE安装
@@ -54,6 +65,7 @@ impl App {
                 {
                     self.local_image_path = path.to_string_lossy().to_string();
                     self.iso_mount_error = None;
+                    self.pending_volume_index = None;
                     self.load_image_volumes();
                 }
             }
@@ -133,72 +145,19 @@ impl App {
         ui.add_space(10.0);
         ui.separator();
 
-        // 分区选择表格
-        ui.label("选择安装分区:");
-
-        let partitions_clone: Vec<Partition> = self.partitions.clone();
-        let mut partition_clicked: Option<usize> = None;
-
-        egui::ScrollArea::vertical()
-            .max_height(200.0)
-            .show(ui, |ui| {
-                egui::Grid::new("partition_grid")
-                    .striped(true)
-                    .min_col_width(60.0)
-                    .show(ui, |ui| {
-                        ui.label("分区卷");
-                        ui.label("总空间");
-                        ui.label("可用空间");
-                        ui.label("卷标");
-                        ui.label("分区表");
-                        ui.label("状态");
-                        ui.end_row();
-
-                        for (i, partition) in partitions_clone.iter().enumerate() {
-                            let label = if is_pe {
-                                if partition.has_windows {
-                                    format!("{} (有系统)", partition.letter)
-                                } else {
-                                    partition.letter.clone()
-                                }
-                            } else {
-                                if partition.is_system_partition {
-                                    format!("{} (当前系统)", partition.letter)
-                                } else if partition.has_windows {
-                                    format!("{} (有系统)", partition.letter)
-                                } else {
-                                    partition.letter.clone()
-                                }
-                            };
-
-                            if ui
-                                .selectable_label(self.selected_partition == Some(i), &label)
-                                .clicked()
-                            {
-                                partition_clicked = Some(i);
-                            }
-
-                            ui.label(Self::format_size(partition.total_size_mb));
-                            ui.label(Self::format_size(partition.free_size_mb));
-                            ui.label(&partition.label);
-                            ui.label(format!("{}", partition.partition_style));
-                            
-                            let status = if partition.has_windows {
-                                "已有系统"
-                            } else {
-                                "空闲"
-                            };
-                            ui.label(status);
-                            
-                            ui.end_row();
-                        }
-                    });
-            });
+        // 整盘安装
+        if ui
+            .checkbox(&mut self.clean_install, "整盘安装（清空整个磁盘并按标准布局重新分区）")
+            .changed()
+            && self.clean_install
+        {
+            self.refresh_disks();
+        }
 
-        // 处理分区选择
-        if let Some(i) = partition_clicked {
-            self.selected_partition = Some(i);
-            self.update_install_options_for_partition();
+        if self.clean_install {
+            self.show_clean_install_options(ui);
+        } else {
+            self.show_partition_table(ui, is_pe);
         }
 
         ui.add_space(10.0);
@@ -206,8 +165,8 @@ impl App {
 
         // 安装选项
         ui.horizontal(|ui| {
-            ui.checkbox(&mut self.format_partition, "格式化分区");
-            ui.checkbox(&mut self.repair_boot, "添加引导");
+            ui.add_enabled(!self.clean_install, egui::Checkbox::new(&mut self.format_partition, "格式化分区"));
+            ui.add_enabled(!self.clean_install, egui::Checkbox::new(&mut self.repair_boot, "添加引导"));
             ui.checkbox(&mut self.unattended_install, "无人值守");
             ui.checkbox(&mut self.export_drivers, "保留驱动");
             ui.checkbox(&mut self.auto_reboot, "立即重启");
@@ -236,7 +195,10 @@ impl App {
                     );
                 });
 
-            if let Some(idx) = self.selected_partition {
+            if self.clean_install {
+                let firmware = self.clean_install_firmware();
+                ui.label(format!("( 将使用: {} )", Self::firmware_label(firmware)));
+            } else if let Some(idx) = self.selected_partition {
                 if let Some(partition) = self.partitions.get(idx) {
                     let actual_mode = Self::get_actual_boot_mode(self.selected_boot_mode, partition.partition_style);
                     ui.label(format!("( 将使用: {} )", actual_mode));
@@ -315,7 +277,12 @@ impl App {
         ui.add_space(20.0);
 
         // 开始安装按钮
-        let can_install = self.selected_partition.is_some()
+        let target_selected = if self.clean_install {
+            self.clean_install_disk.is_some() && self.clean_install_layout.validate().is_ok()
+        } else {
+            self.selected_partition.is_some()
+        };
+        let can_install = target_selected
             && !self.local_image_path.is_empty()
             && (self.local_image_path.ends_with(".gho") || self.selected_volume.is_some())
             && !install_blocked
@@ -329,12 +296,18 @@ impl App {
                 )
                 .clicked()
             {
-                self.start_installation();
+                if self.clean_install {
+                    self.open_clean_install_confirm();
+                } else {
+                    self.start_installation();
+                }
             }
 
             // 显示安装模式提示
             if can_install {
-                if needs_pe && !is_pe {
+                if self.clean_install {
+                    ui.label("(整盘安装)");
+                } else if needs_pe && !is_pe {
                     ui.label("(将通过PE环境安装)");
                 } else {
                     ui.label("(直接安装)");
@@ -342,7 +315,14 @@ impl App {
             }
         });
 
+        self.show_clean_install_confirm_window(ui.ctx());
+        self.show_partition_install_confirm_window(ui.ctx());
+        self.show_preflight_window(ui.ctx());
+
         // 警告：安装到有系统的分区
+        if self.clean_install {
+            return;
+        }
         if let Some(idx) = self.selected_partition {
             if let Some(partition) = self.partitions.get(idx) {
                 if partition.has_windows && !self.format_partition {
@@ -363,6 +343,11 @@ impl App {
             return false;
         }
         
+        // 整盘安装不允许清空当前系统所在磁盘，总是直接安装
+        if self.clean_install {
+            return false;
+        }
+
         // 检查目标分区是否是当前系统分区
         if let Some(idx) = self.selected_partition {
             if let Some(partition) = self.partitions.get(idx) {
@@ -373,16 +358,460 @@ impl App {
         false
     }
 
+    /// 分区选择表格
+    fn show_partition_table(&mut self, ui: &mut egui::Ui, is_pe: bool) {
+        // 分区选择表格
+        ui.label("选择安装分区:");
+
+        let partitions_clone: Vec<Partition> = self.partitions.clone();
+        let mut partition_clicked: Option<usize> = None;
+
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("partition_grid")
+                    .striped(true)
+                    .min_col_width(60.0)
+                    .show(ui, |ui| {
+                        ui.label("分区卷");
+                        ui.label("总空间");
+                        ui.label("可用空间");
+                        ui.label("卷标");
+                        ui.label("分区表");
+                        ui.label("状态");
+                        ui.end_row();
+
+                        for (i, partition) in partitions_clone.iter().enumerate() {
+                            let label = if is_pe {
+                                if partition.has_windows {
+                                    format!("{} (有系统)", partition.letter)
+                                } else {
+                                    partition.letter.clone()
+                                }
+                            } else {
+                                if partition.is_system_partition {
+                                    format!("{} (当前系统)", partition.letter)
+                                } else if partition.has_windows {
+                                    format!("{} (有系统)", partition.letter)
+                                } else {
+                                    partition.letter.clone()
+                                }
+                            };
+
+                            if ui
+                                .selectable_label(self.selected_partition == Some(i), &label)
+                                .clicked()
+                            {
+                                partition_clicked = Some(i);
+                            }
+
+                            ui.label(Self::format_size(partition.total_size_mb));
+                            ui.label(Self::format_size(partition.free_size_mb));
+                            ui.label(&partition.label);
+                            ui.label(format!("{}", partition.partition_style));
+                            
+                            let status = if partition.has_windows {
+                                "已有系统"
+                            } else {
+                                "空闲"
+                            };
+                            ui.label(status);
+                            
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        // 处理分区选择
+        if let Some(i) = partition_clicked {
+            self.selected_partition = Some(i);
+            self.update_install_options_for_partition();
+        }
+    }
+
+    /// 整盘安装选项：目标磁盘、各分区大小和布局预览
+    fn show_clean_install_options(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("目标磁盘:");
+            egui::ComboBox::from_id_salt("clean_install_disk")
+                .selected_text(
+                    self.clean_install_disk
+                        .and_then(|n| self.disks.iter().find(|d| d.number == n))
+                        .map(Self::describe_disk)
+                        .unwrap_or_else(|| "请选择磁盘".to_string()),
+                )
+                .show_ui(ui, |ui| {
+                    for disk in &self.disks {
+                        ui.selectable_value(
+                            &mut self.clean_install_disk,
+                            Some(disk.number),
+                            Self::describe_disk(disk),
+                        );
+                    }
+                });
+            if ui.button("刷新磁盘").clicked() {
+                self.refresh_disks();
+            }
+        });
+
+        let firmware = self.clean_install_firmware();
+        let layout = &mut self.clean_install_layout;
+        ui.horizontal(|ui| {
+            match firmware {
+                Firmware::Uefi => {
+                    ui.label("ESP:");
+                    ui.add(egui::DragValue::new(&mut layout.esp_size_mb)
+                        .range(ESP_SIZE_RANGE.0..=ESP_SIZE_RANGE.1)
+                        .suffix(" MB"));
+                    ui.label("MSR:");
+                    ui.add(egui::DragValue::new(&mut layout.msr_size_mb)
+                        .range(MSR_SIZE_RANGE.0..=MSR_SIZE_RANGE.1)
+                        .suffix(" MB"));
+                }
+                Firmware::Bios => {
+                    ui.label("系统保留分区:");
+                    ui.add(egui::DragValue::new(&mut layout.system_reserved_size_mb)
+                        .range(SYSTEM_RESERVED_SIZE_RANGE.0..=SYSTEM_RESERVED_SIZE_RANGE.1)
+                        .suffix(" MB"));
+                }
+            }
+
+            let mut with_recovery = layout.recovery_size_mb.is_some();
+            if ui.checkbox(&mut with_recovery, "恢复分区").changed() {
+                layout.recovery_size_mb = with_recovery.then_some(1024);
+            }
+            if let Some(size) = layout.recovery_size_mb.as_mut() {
+                ui.add(egui::DragValue::new(size)
+                    .range(RECOVERY_SIZE_RANGE.0..=RECOVERY_SIZE_RANGE.1)
+                    .suffix(" MB"));
+            }
+        });
+
+        let mut options = self.clean_install_layout.clone();
+        options.firmware = firmware;
+        match DiskLayout::new(self.clean_install_disk.unwrap_or(0), options) {
+            Ok(layout) => {
+                egui::Grid::new("clean_install_layout")
+                    .striped(true)
+                    .min_col_width(80.0)
+                    .show(ui, |ui| {
+                        for partition in &layout.partitions {
+                            ui.label(partition.role.label());
+                            ui.label(
+                                partition.size_mb
+                                    .map(Self::format_size)
+                                    .unwrap_or_else(|| "剩余空间".to_string()),
+                            );
+                            ui.label(partition.file_system.map_or("-".to_string(), |fs| fs.name().to_uppercase()));
+                            ui.end_row();
+                        }
+                    });
+                ui.label(
+                    egui::RichText::new(format!(
+                        "分区表: {}，磁盘至少需要 {}",
+                        firmware.partition_style(),
+                        Self::format_size(layout.minimum_disk_size_mb())
+                    ))
+                    .small()
+                    .weak(),
+                );
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e.to_string());
+            }
+        }
+
+        ui.colored_label(
+            egui::Color32::from_rgb(255, 165, 0),
+            "⚠ 目标磁盘上的所有分区和数据都将被删除",
+        );
+    }
+
+    /// 整盘安装确认对话框
+    fn show_clean_install_confirm_window(&mut self, ctx: &egui::Context) {
+        if !self.show_clean_install_confirm {
+            return;
+        }
+
+        let disk = self
+            .clean_install_disk
+            .and_then(|n| self.disks.iter().find(|d| d.number == n))
+            .map(Self::describe_disk)
+            .unwrap_or_default();
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("确认清空磁盘")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.colored_label(egui::Color32::RED, format!("将清空 {}", disk));
+                ui.label("该磁盘上的所有分区和数据都将被永久删除，无法恢复。");
+                ui.add_space(10.0);
+                Self::show_disk_plan(ui, "clean_install_plan", &self.disk_plan);
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("确认清空并安装").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        self.show_clean_install_confirm = false;
+                    }
+                });
+            });
+
+        if !open {
+            self.show_clean_install_confirm = false;
+        }
+        if confirmed {
+            self.show_clean_install_confirm = false;
+            self.start_installation();
+        }
+    }
+
+    /// 分区安装确认对话框：格式化或缩小分区之前展示执行计划
+    fn show_partition_install_confirm_window(&mut self, ctx: &egui::Context) {
+        let Some((target_partition, is_system_partition)) = self.pending_partition_install.clone() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new("确认磁盘操作")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("将格式化 {}，其中的数据将被永久删除，无法恢复。", target_partition),
+                );
+                if self.install_mode == InstallMode::ViaPE {
+                    ui.label("数据分区在重启前创建，目标分区在 PE 中格式化，新建的数据分区在安装完成后删除。");
+                }
+                ui.add_space(10.0);
+                Self::show_disk_plan(ui, "partition_install_plan", &self.disk_plan);
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("确认并安装").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+
+        if !open || cancelled || confirmed {
+            self.pending_partition_install = None;
+        }
+        if confirmed {
+            self.begin_installation(target_partition, is_system_partition);
+        }
+    }
+
+    /// 显示 diskpart 试运行返回的执行计划，破坏性操作标红
+    fn show_disk_plan(ui: &mut egui::Ui, id: &str, plan: &str) {
+        ui.label("将执行以下磁盘操作：");
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            egui::Grid::new(id).striped(true).show(ui, |ui| {
+                for line in plan.lines() {
+                    // 每行为 “标记 命令  # 说明”，破坏性操作的标记为 `!`
+                    let (marker, rest) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+                    let (command, description) = rest.trim().split_once("  # ").unwrap_or((rest.trim(), ""));
+                    if marker == "!" {
+                        ui.colored_label(egui::Color32::RED, "⚠");
+                        ui.colored_label(egui::Color32::RED, description);
+                    } else {
+                        ui.label("");
+                        ui.label(description);
+                    }
+                    ui.label(egui::RichText::new(command).monospace().weak());
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// 安装前检查结果窗口：有阻止项时只能关闭，只有警告时可确认后继续
+    fn show_preflight_window(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.preflight_report else {
+            return;
+        };
+
+        let blocking = report.has_blocking();
+        let win11_unsupported = self.preflight_win11_unsupported;
+        let bypass_win11 = &mut self.advanced_options.bypass_win11_check;
+        let mut open = true;
+        let mut proceed = false;
+        let mut close = false;
+        egui::Window::new("安装前检查")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if blocking {
+                    ui.colored_label(egui::Color32::RED, "存在必须先解决的问题，无法开始安装");
+                } else {
+                    ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "存在以下风险，请确认后再继续");
+                }
+                ui.add_space(10.0);
+                egui::Grid::new("preflight_items").striped(true).show(ui, |ui| {
+                    for item in &report.items {
+                        let (icon, color) = match item.severity {
+                            Severity::Pass => ("✓", egui::Color32::GREEN),
+                            Severity::Warning => ("⚠", egui::Color32::from_rgb(255, 165, 0)),
+                            Severity::Blocking => ("❌", egui::Color32::RED),
+                        };
+                        ui.colored_label(color, icon);
+                        ui.label(item.title);
+                        ui.label(&item.message);
+                        ui.end_row();
+                    }
+                });
+                if win11_unsupported && !blocking {
+                    ui.add_space(10.0);
+                    ui.checkbox(bypass_win11, "写入 LabConfig 绕过键，跳过 Windows 11 硬件检查");
+                }
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if !blocking && ui.button("仍然继续").clicked() {
+                        proceed = true;
+                    }
+                    if ui.button(if blocking { "关闭" } else { "取消" }).clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if !open || close || proceed {
+            self.preflight_report = None;
+        }
+        if proceed {
+            self.preflight_confirmed = true;
+            self.start_installation();
+        }
+    }
+
+    /// 执行安装前检查；有警告或阻止项时打开检查结果窗口并返回 false
+    fn passes_preflight(&mut self, target: PreflightTarget) -> bool {
+        if std::mem::take(&mut self.preflight_confirmed) {
+            return true;
+        }
+
+        let lower = self.local_image_path.to_lowercase();
+        let volume_index = if lower.ends_with(".gho") || lower.ends_with(".ghs") {
+            None
+        } else {
+            self.selected_volume
+                .and_then(|i| self.image_volumes.get(i))
+                .map(|v| v.index)
+        };
+        let outcome = InstallPreflight {
+            image_path: &self.local_image_path,
+            volume_index,
+            volumes: &self.image_volumes,
+            target,
+            system_info: self.system_info.as_ref(),
+        }
+        .run();
+
+        if outcome.report.severity() == Severity::Pass {
+            return true;
+        }
+        self.preflight_win11_unsupported = outcome.win11.is_some_and(|r| !r.is_compatible());
+        self.preflight_report = Some(outcome.report);
+        // 下载 PE 后继续安装时可能不在安装页面
+        self.current_panel = crate::app::Panel::SystemInstall;
+        false
+    }
+
+    /// 按当前选项生成整盘安装的分区布局
+    fn build_clean_install_layout(&self, disk_number: u32) -> anyhow::Result<DiskLayout> {
+        let mut options = self.clean_install_layout.clone();
+        options.firmware = self.clean_install_firmware();
+        DiskLayout::new(disk_number, options)
+    }
+
+    /// 生成分区脚本的执行计划并打开确认对话框，确认前不执行任何磁盘操作
+    fn open_clean_install_confirm(&mut self) {
+        let Some(disk_number) = self.clean_install_disk else {
+            return;
+        };
+        let windows_letter = DiskManager::find_available_drive_letter().unwrap_or('W');
+        let plan = self
+            .build_clean_install_layout(disk_number)
+            .and_then(|layout| DiskManager::new().apply_disk_layout(&layout, windows_letter, RunMode::DryRun));
+        match plan {
+            Ok(plan) => {
+                self.disk_plan = plan;
+                self.clean_install_letter = Some(windows_letter);
+                self.show_clean_install_confirm = true;
+            }
+            Err(e) => self.show_error(&format!("分区布局无效: {}", e)),
+        }
+    }
+
+    fn describe_disk(disk: &DiskEntry) -> String {
+        format!(
+            "磁盘 {} - {} ({})",
+            disk.number,
+            Self::format_size(disk.size_bytes >> 20),
+            if disk.is_gpt { "GPT" } else { "MBR" }
+        )
+    }
+
+    fn firmware_label(firmware: Firmware) -> &'static str {
+        match firmware {
+            Firmware::Uefi => "UEFI",
+            Firmware::Bios => "Legacy",
+        }
+    }
+
+    /// 整盘安装使用的引导方式；自动时沿用当前系统的启动方式
+    fn clean_install_firmware(&self) -> Firmware {
+        match self.selected_boot_mode {
+            BootModeSelection::UEFI => Firmware::Uefi,
+            BootModeSelection::Legacy => Firmware::Bios,
+            // refresh_disks 时按当前启动方式设置
+            BootModeSelection::Auto => self.clean_install_layout.firmware,
+        }
+    }
+
+    pub fn refresh_disks(&mut self) {
+        match DiskManager::new().list_disks() {
+            Ok(disks) => self.disks = disks,
+            Err(e) => println!("[INSTALL] 获取磁盘列表失败: {}", e),
+        }
+        if self
+            .clean_install_disk
+            .is_some_and(|n| !self.disks.iter().any(|d| d.number == n))
+        {
+            self.clean_install_disk = None;
+        }
+        self.clean_install_layout.firmware = if crate::core::pe::PeManager::new().is_uefi_boot() {
+            Firmware::Uefi
+        } else {
+            Firmware::Bios
+        };
+    }
+
     /// 根据选择和分区表类型获取实际的引导模式
     fn get_actual_boot_mode(selection: BootModeSelection, partition_style: PartitionStyle) -> &'static str {
+        Self::firmware_label(Self::actual_firmware(selection, partition_style))
+    }
+
+    /// 根据选择和分区表类型获取实际的引导固件
+    fn actual_firmware(selection: BootModeSelection, partition_style: PartitionStyle) -> Firmware {
         match selection {
-            BootModeSelection::UEFI => "UEFI",
-            BootModeSelection::Legacy => "Legacy",
+            BootModeSelection::UEFI => Firmware::Uefi,
+            BootModeSelection::Legacy => Firmware::Bios,
             BootModeSelection::Auto => {
                 match partition_style {
-                    PartitionStyle::GPT => "UEFI",
-                    PartitionStyle::MBR => "Legacy",
-                    PartitionStyle::Unknown => "UEFI",
+                    PartitionStyle::GPT => Firmware::Uefi,
+                    PartitionStyle::MBR => Firmware::Bios,
+                    PartitionStyle::Unknown => Firmware::Uefi,
                 }
             }
         }
@@ -526,11 +955,13 @@ impl App {
                                     // 如果没有可用的系统版本，仍然设为 None
                                     log::warn!("镜像中没有可安装的系统版本（全部为 PE 环境或安装媒体）");
                                 }
+                                self.apply_pending_volume_index();
                             }
                             ImageInfoResult::Error(error) => {
                                 println!("[IMAGE INFO] 加载失败: {}", error);
                                 self.image_volumes.clear();
                                 self.selected_volume = None;
+                                self.pending_volume_index = None;
                             }
                         }
                     }
@@ -610,7 +1041,7 @@ impl App {
     }
 
     pub fn refresh_partitions(&mut self) {
-        if let Ok(partitions) = crate::core::disk::DiskManager::get_partitions() {
+        if let Ok(partitions) = DiskManager::get_partitions() {
             self.partitions = partitions;
             
             // 判断是否为PE环境
@@ -643,6 +1074,11 @@ impl App {
     }
 
     pub fn start_installation(&mut self) {
+        if self.clean_install {
+            self.start_clean_installation();
+            return;
+        }
+
         let partition = self
             .partitions
             .get(self.selected_partition.unwrap())
@@ -652,11 +1088,10 @@ impl App {
         }
         let partition = partition.unwrap();
 
-        let image_path = self.local_image_path.clone();
-        let volume_index = self
-            .selected_volume
-            .and_then(|i| self.image_volumes.get(i).map(|v| v.index))
-            .unwrap_or(1);
+        // 无人值守应答文件有错误时，在格式化分区之前就停止
+        if !self.check_unattend_options() {
+            return;
+        }
 
         let is_system_partition = partition.is_system_partition;
         let is_pe = self.is_pe_environment();
@@ -668,6 +1103,19 @@ impl App {
             InstallMode::ViaPE
         };
 
+        // 安装前检查：阻止项不允许继续，更不会重启到PE
+        let via_pe = self.install_mode == InstallMode::ViaPE;
+        let target = PreflightTarget::Partition {
+            partition: &partition,
+            // PE 中总是先格式化目标分区
+            will_format: self.format_partition || via_pe,
+            via_pe,
+            firmware: Self::actual_firmware(self.selected_boot_mode, partition.partition_style),
+        };
+        if !self.passes_preflight(target) {
+            return;
+        }
+
         // 如果需要通过PE安装，先检查PE是否存在
         if self.install_mode == InstallMode::ViaPE {
             let pe_info = self.selected_pe_for_install.and_then(|idx| {
@@ -701,13 +1149,158 @@ impl App {
             auto_reboot: self.auto_reboot,
             boot_mode: self.selected_boot_mode,
             advanced_options: self.advanced_options.clone(),
+            clean_install: None,
         };
 
+        // 格式化或缩小分区之前先展示 diskpart 试运行得到的执行计划，确认后才开始
+        match self.preview_partition_install(&partition.letter) {
+            Ok(plan) if plan.is_empty() => self.begin_installation(partition.letter.clone(), is_system_partition),
+            Ok(plan) => {
+                self.disk_plan = plan;
+                self.pending_partition_install = Some((partition.letter.clone(), is_system_partition));
+            }
+            Err(e) => self.show_error(&format!("无法生成磁盘操作计划: {}", e)),
+        }
+    }
+
+    /// 试运行本次安装要执行的破坏性磁盘操作，返回执行计划；没有这类操作时为空
+    fn preview_partition_install(&self, target_partition: &str) -> anyhow::Result<String> {
+        let disk = DiskManager::new();
+        let mut plans = Vec::new();
+        let via_pe = self.install_mode == InstallMode::ViaPE;
+        if via_pe {
+            // 镜像复制到数据分区，没有空间足够的分区时从目标分区缩小出一个
+            let image_size = std::fs::metadata(&self.local_image_path)?.len();
+            if let Some((_, Some(plan))) =
+                disk.find_suitable_data_partition(target_partition, image_size, RunMode::DryRun)?
+            {
+                plans.push(plan);
+            }
+        }
+        // PE 中总是先格式化目标分区
+        if self.format_partition || via_pe {
+            plans.push(disk.format_partition(target_partition, RunMode::DryRun)?);
+        }
+        Ok(plans.join("\n"))
+    }
+
+    /// 整盘安装：检查目标磁盘，分区在安装线程的第一步进行
+    /// 检查无人值守相关选项，有错误时提示并返回 false
+    fn check_unattend_options(&mut self) -> bool {
+        if !self.unattended_install {
+            // 装机软件由应答文件在首次登录时启动
+            if !self.advanced_options.software_items().is_empty() {
+                self.show_error("装机软件需要无人值守安装才能执行，请勾选无人值守或关闭装机软件");
+                return false;
+            }
+            return true;
+        }
+        if let Err(e) = self.advanced_options.check_unattend() {
+            self.show_error(&format!("无人值守应答文件有误，已取消安装:\n{}", e));
+            return false;
+        }
+        true
+    }
+
+    fn start_clean_installation(&mut self) {
+        let Some(disk_number) = self.clean_install_disk else {
+            return;
+        };
+
+        let layout = match self.build_clean_install_layout(disk_number) {
+            Ok(layout) => layout,
+            Err(e) => {
+                self.show_error(&format!("分区布局无效: {}", e));
+                return;
+            }
+        };
+        if let Some(disk) = self.disks.iter().find(|d| d.number == disk_number) {
+            if let Err(e) = layout.check_disk_size(disk.size_bytes) {
+                self.show_error(&e.to_string());
+                return;
+            }
+        }
+
+        // 不能清空正在运行的系统或安装镜像所在的磁盘
+        let mut in_use: Vec<(char, &str)> = self
+            .partitions
+            .iter()
+            .filter(|p| p.is_system_partition)
+            .filter_map(|p| p.letter.chars().next())
+            .map(|letter| (letter, "当前系统"))
+            .collect();
+        if let Some(letter) = self.local_image_path.chars().next() {
+            in_use.push((letter, "系统镜像"));
+        }
+        let disk = DiskManager::new();
+        for (letter, what) in in_use {
+            if disk.get_volume_disk_number(letter).ok() == Some(disk_number) {
+                self.show_error(&format!("磁盘 {} 上有{}（{}:），不能清空", disk_number, what, letter));
+                return;
+            }
+        }
+
+        if !self.check_unattend_options() {
+            return;
+        }
+
+        let size_bytes = self
+            .disks
+            .iter()
+            .find(|d| d.number == disk_number)
+            .map(|d| d.size_bytes)
+            .unwrap_or(0);
+        if !self.passes_preflight(PreflightTarget::Disk { size_bytes, layout: &layout }) {
+            return;
+        }
+
+        // 使用确认对话框中预览的盘符，预览后被占用时不再另选，以免实际操作与预览不一致
+        let Some(windows_letter) = self.clean_install_letter.take() else {
+            return;
+        };
+        if DiskManager::get_used_drive_letters().contains(&windows_letter) {
+            self.show_error(&format!("盘符 {}: 在预览后已被占用，请重新开始安装", windows_letter));
+            return;
+        }
+
+        self.install_mode = InstallMode::Direct;
+        self.install_options = crate::app::InstallOptions {
+            format_partition: false,
+            repair_boot: true,
+            unattended_install: self.unattended_install,
+            export_drivers: self.export_drivers,
+            auto_reboot: self.auto_reboot,
+            boot_mode: match layout.options.firmware {
+                Firmware::Uefi => BootModeSelection::UEFI,
+                Firmware::Bios => BootModeSelection::Legacy,
+            },
+            advanced_options: self.advanced_options.clone(),
+            clean_install: Some(layout),
+        };
+
+        self.begin_installation(format!("{}:", windows_letter), false);
+    }
+
+    /// 记录安装参数并切换到安装进度页面
+    fn begin_installation(&mut self, target_partition: String, is_system_partition: bool) {
+        let image_path = self.local_image_path.clone();
+        let volume_index = self
+            .selected_volume
+            .and_then(|i| self.image_volumes.get(i).map(|v| v.index))
+            .unwrap_or(1);
+
+        // 展开计算机名模板，安装流程（含 PE 端）使用展开后的名称
+        if !self.advanced_options.computer_name.trim().is_empty() {
+            let computer_name = self.advanced_options.expanded_computer_name();
+            println!("[INSTALL] 计算机名: {}", computer_name);
+            self.install_options.advanced_options.computer_name = computer_name;
+        }
+
         self.is_installing = true;
         self.current_panel = crate::app::Panel::InstallProgress;
         self.install_progress = crate::app::InstallProgress::default();
 
-        self.install_target_partition = partition.letter.clone();
+        self.install_target_partition = target_partition;
         self.install_image_path = image_path;
         self.install_volume_index = volume_index;
         self.install_is_system_partition = is_system_partition;
@@ -190,6 +190,10 @@ impl App {
                 self.repair_boot_action(is_pe);
             }
 
+            if ui.button("启动菜单管理").clicked() {
+                self.boot_menu.open();
+            }
+
             if ui.button("导出系统驱动").clicked() {
                 self.export_drivers_action(is_pe);
             }
@@ -292,6 +296,10 @@ impl App {
                 });
         }
 
+        // 启动菜单管理窗口
+        let ctx = ui.ctx().clone();
+        self.show_boot_menu_window(&ctx);
+
         // 显示工具状态
         if !self.tool_message.is_empty() {
             ui.add_space(15.0);
@@ -23,6 +23,11 @@ pub fn get_tools_dir() -> PathBuf {
     get_exe_dir().join("tools")
 }
 
+/// 获取部署方案目录
+pub fn get_profiles_dir() -> PathBuf {
+    get_exe_dir().join("profiles")
+}
+
 /// 获取临时目录
 pub fn get_temp_dir() -> PathBuf {
     get_exe_dir().join("temp")
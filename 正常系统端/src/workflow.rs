@@ -0,0 +1,337 @@
+//! 正常系统端的安装与备份流程
+//!
+//! 步骤顺序、跳过条件和出错处理由公共库的工作流负责，这里用本端的 DISM、
+//! Ghost、bcdedit 和磁盘工具完成各步骤。直接安装和 /PEINSTALL、/PEBACKUP
+//! 命令行模式共用这些后端。备份运行中的系统分区时改为排入 PE 执行。
+
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use anyhow::Result;
+use letrecovery_common::backup::catalog;
+use letrecovery_common::backup::compression::{BackupFormat, Compression};
+use letrecovery_common::backup::repository::Repository;
+use letrecovery_common::command::CommandRunner;
+use letrecovery_common::disk_layout::{DiskLayout, Firmware};
+use letrecovery_common::diskpart::script::RunMode;
+use letrecovery_common::tools::{CaptureOptions, Tools};
+use letrecovery_common::unattend::{AnswerFileOptions, MergeConflict};
+use letrecovery_common::workflow::{
+    forward_progress, BackupBackend, BackupPlan, InstallBackend, InstallPlan, Progress,
+    TargetPreparation,
+};
+
+use crate::core::bcdedit::BootManager;
+use crate::core::disk::DiskManager;
+use crate::core::dism::{Dism, DismProgress};
+use crate::core::ghost::Ghost;
+use crate::core::install_config::{BackupConfig, ConfigFileManager};
+use crate::core::pe::PeManager;
+use crate::core::registry::OfflineRegistry;
+use crate::core::system_info::SystemInfo;
+use crate::ui::advanced_options::AdvancedOptions;
+use crate::ui::install_progress::generate_unattend_xml;
+
+/// 安装后端
+pub struct DesktopInstallBackend {
+    pub advanced_options: AdvancedOptions,
+    pub answer_options: AnswerFileOptions,
+    /// 用户提供的应答文件
+    pub user_unattend_file: Option<String>,
+    /// 整盘安装时的分区布局
+    pub disk_layout: Option<DiskLayout>,
+    /// 安装结束后删除的临时目录
+    pub temp_dirs: Vec<String>,
+    /// 外部工具，所有命令经由其中的执行器运行
+    pub tools: Tools,
+}
+
+impl DesktopInstallBackend {
+    fn disk(&self) -> DiskManager {
+        DiskManager::with_runner(self.tools.runner())
+    }
+}
+
+impl InstallBackend for DesktopInstallBackend {
+    fn prepare_target(&self, plan: &InstallPlan) -> Result<()> {
+        match (plan.preparation, &self.disk_layout) {
+            (TargetPreparation::Partition, Some(layout)) => {
+                println!("[INSTALL] 清空磁盘 {} 并重新分区", layout.disk_number);
+                let windows_letter = plan.target_partition.chars().next().unwrap_or('W');
+                self.disk().apply_disk_layout(layout, windows_letter, RunMode::Execute)?;
+                println!("[INSTALL] 分区完成，Windows 分区: {}", plan.target_partition);
+            }
+            (TargetPreparation::Partition, None) => anyhow::bail!("缺少整盘安装的分区布局"),
+            _ => {
+                println!("[FORMAT] 格式化分区: {}", plan.target_partition);
+                let output = self.disk().format_partition(&plan.target_partition, RunMode::Execute)?;
+                println!("[FORMAT] Diskpart 输出: {}", output);
+            }
+        }
+        Ok(())
+    }
+
+    fn export_drivers(&self, driver_dir: &str) -> Result<()> {
+        export_drivers(driver_dir)
+    }
+
+    fn apply_image(&self, plan: &InstallPlan, progress: Progress) -> Result<()> {
+        if plan.is_gho {
+            println!("[INSTALL] 检测到 GHO 镜像，使用 Ghost 恢复");
+            let ghost = Ghost::with_runner(self.tools.runner());
+            if !ghost.is_available() {
+                anyhow::bail!("Ghost工具不可用");
+            }
+            // 整盘安装后分区已变化，按当前分区列表定位目标分区
+            let partitions = DiskManager::get_partitions().unwrap_or_default();
+            forward_progress(progress, dism_percentage, |tx| {
+                ghost.restore_image_to_letter(&plan.image_path, &plan.target_partition, &partitions, Some(tx))
+            })
+        } else {
+            println!("[INSTALL] 使用 DISM 应用 WIM/ESD 镜像");
+            let apply_dir = format!("{}\\", plan.target_partition);
+            self.tools.apply_image(&plan.image_path, &apply_dir, plan.volume_index, progress)
+        }
+    }
+
+    fn import_drivers(&self, target_partition: &str, driver_dir: &str) -> Result<()> {
+        println!("[DRIVER IMPORT] 目标分区: {}, 驱动路径: {}", target_partition, driver_dir);
+        Dism::with_runner(self.tools.runner()).add_drivers_offline(&format!("{}\\", target_partition), driver_dir)
+    }
+
+    fn repair_boot(&self, target_partition: &str, firmware: Firmware) -> Result<()> {
+        let use_uefi = firmware == Firmware::Uefi;
+        println!("[INSTALL] 引导模式: {}", if use_uefi { "UEFI" } else { "Legacy" });
+        BootManager::with_runner(self.tools.runner()).repair_boot_advanced(target_partition, use_uefi)
+    }
+
+    fn apply_advanced_options(&self, target_partition: &str) -> Result<()> {
+        let registry = OfflineRegistry::with_runner(self.tools.runner());
+        self.advanced_options.apply_to_system(target_partition, &registry)
+    }
+
+    fn generate_unattend(&self, target_partition: &str) -> Result<Vec<MergeConflict>> {
+        generate_unattend_xml(
+            target_partition,
+            self.answer_options.clone(),
+            self.user_unattend_file.as_deref(),
+        )
+    }
+
+    fn cleanup(&self, _plan: &InstallPlan) -> Result<()> {
+        for dir in &self.temp_dirs {
+            if Path::new(dir).exists() {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 备份后端
+pub struct DesktopBackupBackend {
+    tools: Tools,
+}
+
+impl DesktopBackupBackend {
+    /// 使用指定的命令执行器
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            tools: crate::core::tools(runner),
+        }
+    }
+}
+
+impl BackupBackend for DesktopBackupBackend {
+    fn capture_image(&self, plan: &BackupPlan, append: bool, progress: Progress) -> Result<()> {
+        if let BackupFormat::Gho(level) = plan.format {
+            // GHO使用Ghost，按盘符解析磁盘号和分区号
+            let ghost = Ghost::with_runner(self.tools.runner());
+            if !ghost.is_available() {
+                anyhow::bail!("Ghost工具不可用");
+            }
+            let partitions = DiskManager::get_partitions().unwrap_or_default();
+            return forward_progress(progress, dism_percentage, |tx| {
+                ghost.create_image_from_letter(&plan.source_partition, &partitions, &plan.save_path, level, Some(tx))
+            });
+        }
+
+        if plan.format.is_dedup() {
+            // 去重仓库直接读取源分区中的文件，不经过 DISM
+            let repository = Repository::open_or_create(Path::new(&plan.save_path))?;
+            let source = format!("{}\\", plan.source_partition);
+            let summary =
+                repository.backup(Path::new(&source), &plan.name, &plan.description, &plan.exclusions, progress)?;
+            println!("[BACKUP] {}", summary.describe());
+            for skipped in &summary.skipped {
+                println!("[BACKUP] 跳过 {}", skipped);
+            }
+            return Ok(());
+        }
+
+        let capture_dir = format!("{}\\", plan.source_partition);
+        // 配置文件在捕获结束后删除
+        let config_file = plan.exclusions.write_temp()?;
+        let options = CaptureOptions {
+            config_file: Some(config_file.path().to_string_lossy().into_owned()),
+            compression: plan.compression,
+        };
+        let (image, name, description) = (&plan.save_path, &plan.name, &plan.description);
+        if append {
+            self.tools.append_image(image, &capture_dir, name, description, &options, progress)
+        } else {
+            self.tools.capture_image(image, &capture_dir, name, description, &options, progress)
+        }
+    }
+
+    fn export_image(&self, source: &Path, index: u32, destination: &Path, compression: Compression) -> Result<()> {
+        // 追加到已有镜像时沿用其压缩方式
+        let compress = (!destination.exists()).then_some(compression.to_config_value());
+        let (source, destination) = (source.to_string_lossy(), destination.to_string_lossy());
+        self.tools.export_image(&source, index, &destination, compress, &|_| {})
+    }
+
+    fn export_esd(&self, source: &Path, index: u32, destination: &Path, progress: Progress) -> Result<()> {
+        // 追加到已有的 ESD 时沿用其压缩方式
+        let compress = (!destination.exists()).then_some("recovery");
+        let (source, destination) = (source.to_string_lossy(), destination.to_string_lossy());
+        self.tools.export_image(&source, index, &destination, compress, progress)
+    }
+
+    fn source_machine(&self, plan: &BackupPlan) -> Option<String> {
+        // 分区上没有系统（数据分区）时记录本机名称；PE 中的本机名称没有意义
+        catalog::source_machine(&plan.source_partition).or_else(|| {
+            if SystemInfo::check_pe_environment() {
+                None
+            } else {
+                std::env::var("COMPUTERNAME").ok()
+            }
+        })
+    }
+
+    fn restore_boot(&self) -> Result<()> {
+        // 本端写入的 PE 引导项只在一次性启动顺序 (bootsequence) 中，重启后不会再次进入
+        Ok(())
+    }
+
+    fn cleanup(&self, _plan: &BackupPlan) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 把备份排入 PE
+///
+/// 添加一次性 PE 启动项并在数据分区写入备份配置，下次重启后由 PE 端执行。
+/// 界面备份系统分区和计划备份共用。
+pub fn queue_pe_backup(
+    pe_filename: &str,
+    display_name: &str,
+    config: &BackupConfig,
+    progress_tx: Option<Sender<DismProgress>>,
+) -> Result<()> {
+    let report = |percentage: u8, status: &str| {
+        if let Some(tx) = &progress_tx {
+            let _ = tx.send(DismProgress {
+                percentage,
+                status: status.to_string(),
+            });
+        }
+    };
+
+    if config.format.is_dedup() {
+        Repository::check_source(Path::new(&format!("{}\\", config.source_partition)))?;
+    }
+
+    report(10, "检查PE环境");
+    let (pe_exists, pe_path) = PeManager::check_pe_exists(pe_filename);
+    if !pe_exists {
+        anyhow::bail!("PE文件不存在 {}", pe_filename);
+    }
+
+    report(30, "安装PE引导");
+    PeManager::new()
+        .boot_to_pe(&pe_path, display_name)
+        .map_err(|e| anyhow::anyhow!("PE引导安装失败 {}", e))?;
+
+    report(60, "写入配置文件");
+    let data_partition = find_backup_data_partition(&config.source_partition);
+    println!("[BACKUP PE] 数据分区: {}", data_partition);
+    ConfigFileManager::write_backup_config(&config.source_partition, &data_partition, config)
+        .map_err(|e| anyhow::anyhow!("配置文件写入失败 {}", e))?;
+    Ok(())
+}
+
+/// 查找可用的备份数据分区
+fn find_backup_data_partition(exclude_partition: &str) -> String {
+    let exclude_letter = exclude_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
+
+    // 遍历 A-Z 查找可用的固定磁盘分区
+    for letter in b'A'..=b'Z' {
+        let c = letter as char;
+
+        // 跳过排除的分区和 X 盘（PE 系统盘）
+        if c == exclude_letter || c == 'X' {
+            continue;
+        }
+
+        let partition_path = format!("{}:\\", c);
+        if !Path::new(&partition_path).exists() {
+            continue;
+        }
+
+        // 跳过光驱和非固定磁盘
+        if DiskManager::is_cdrom(c) || !DiskManager::is_fixed_drive(c) {
+            continue;
+        }
+
+        // 检查是否有足够空间（至少 100MB 用于配置文件）
+        if let Some(free_space) = DiskManager::get_free_space_bytes(&format!("{}:", c)) {
+            if free_space >= 100 * 1024 * 1024 {
+                return format!("{}:", c);
+            }
+        }
+    }
+
+    // 如果没找到合适的，使用 C 盘
+    "C:".to_string()
+}
+
+/// 导出当前系统的驱动
+///
+/// 在 PE 中运行时从找到的第一个 Windows 系统离线导出。
+pub fn export_drivers(destination: &str) -> Result<()> {
+    println!("[DRIVER EXPORT] 目标路径: {}", destination);
+
+    if Path::new(destination).exists() {
+        let _ = std::fs::remove_dir_all(destination);
+    }
+    std::fs::create_dir_all(destination)?;
+
+    let dism = Dism::new();
+    if !dism.is_pe_environment() {
+        println!("[DRIVER EXPORT] 桌面环境，使用在线模式导出");
+        return dism.export_drivers(destination);
+    }
+
+    println!("[DRIVER EXPORT] PE 环境，查找现有 Windows 系统...");
+    for letter in ['C', 'D', 'E', 'F', 'G'] {
+        let windows_path = format!("{}:\\Windows\\System32\\drivers", letter);
+        if Path::new(&windows_path).exists() {
+            println!("[DRIVER EXPORT] 尝试从 {}: 导出驱动", letter);
+            let source = format!("{}:\\", letter);
+            match dism.export_drivers_from_system(&source, destination) {
+                Ok(_) => {
+                    println!("[DRIVER EXPORT] 成功从 {}: 导出驱动", letter);
+                    return Ok(());
+                }
+                Err(e) => println!("[DRIVER EXPORT] 从 {}: 导出失败: {}", letter, e),
+            }
+        }
+    }
+    anyhow::bail!("PE 环境下未找到可用的 Windows 系统来导出驱动")
+}
+
+fn dism_percentage(progress: &DismProgress) -> u8 {
+    progress.percentage
+}